@@ -2,19 +2,21 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::sampler::{NativeStack, Sampler};
+use crate::sampler::{self, NativeStack, Sampler};
 use crossbeam_channel::{after, unbounded, Receiver, Sender};
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use ipc_channel::router::ROUTER;
 use msg::constellation_msg::MonitoredComponentId;
+use msg::constellation_msg::SampledStack;
 use msg::constellation_msg::{
     BackgroundHangMonitor, BackgroundHangMonitorClone, BackgroundHangMonitorRegister,
 };
 use msg::constellation_msg::{HangAlert, HangAnnotation, HangMonitorAlert, SamplerControlMsg};
+use msg::constellation_msg::{HangProfile, HangProfileSymbol};
 use std::cell::Cell;
 use std::collections::{HashMap, VecDeque};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[derive(Clone)]
 pub struct HangMonitorRegister {
@@ -166,6 +168,50 @@ pub struct BackgroundHangMonitorWorker {
     creation: Instant,
     sampling_baseline: Instant,
     samples: VecDeque<Sample>,
+    /// Where to send samples as they are taken, to be symbolicated and streamed.
+    sample_stream: Option<Sender<StreamedSample>>,
+}
+
+/// A sample taken for a stream, whose frames are yet to be symbolicated.
+struct StreamedSample {
+    component: MonitoredComponentId,
+    thread_name: Option<String>,
+    time: f64,
+    frame_addresses: Vec<usize>,
+}
+
+/// Starts a thread that resolves the symbols of streamed samples and sends them on the
+/// stream, so that symbolication does not delay sampling and hang checks. It stops when
+/// the returned sender is dropped, or when the stream is disconnected.
+fn start_symbolicating(stream: IpcSender<SampledStack>) -> Sender<StreamedSample> {
+    let (sender, receiver) = unbounded::<StreamedSample>();
+    let _ = thread::Builder::new()
+        .name("BHMSymbolicator".to_owned())
+        .spawn(move || {
+            // The same frames recur across samples, so their symbols are only resolved once.
+            let mut symbols: HashMap<usize, Vec<HangProfileSymbol>> = HashMap::new();
+            for sample in receiver {
+                let mut backtrace = vec![];
+                for address in sample.frame_addresses {
+                    let frame = symbols.entry(address).or_insert_with(|| {
+                        let mut frame = vec![];
+                        sampler::resolve_frame(address, &mut frame);
+                        frame
+                    });
+                    backtrace.extend(frame.iter().cloned());
+                }
+                let sample = SampledStack {
+                    component: sample.component,
+                    thread_name: sample.thread_name,
+                    time: sample.time,
+                    profile: HangProfile { backtrace },
+                };
+                if stream.send(sample).is_err() {
+                    break;
+                }
+            }
+        });
+    sender
 }
 
 impl BackgroundHangMonitorWorker {
@@ -187,6 +233,7 @@ impl BackgroundHangMonitorWorker {
             sampling_baseline: Instant::now(),
             creation: Instant::now(),
             samples: Default::default(),
+            sample_stream: None,
         }
     }
 
@@ -258,8 +305,21 @@ impl BackgroundHangMonitorWorker {
                     }
                     Ok(SamplerControlMsg::Disable) => {
                         println!("Disabling profiler.");
-                        self.finish_sampled_profile();
-                        self.sampling_duration = None;
+                        if self.sampling_max_duration.take().is_some() {
+                            self.finish_sampled_profile();
+                        }
+                        if self.sample_stream.is_none() {
+                            self.sampling_duration = None;
+                        }
+                        None
+                    }
+                    Ok(SamplerControlMsg::StartStreaming(rate, stream)) => {
+                        self.sampling_duration = Some(rate);
+                        self.sample_stream = Some(start_symbolicating(stream));
+                        None
+                    }
+                    Ok(SamplerControlMsg::StopStreaming) => {
+                        self.stop_streaming();
                         None
                     }
                     Err(_) => return false,
@@ -388,20 +448,42 @@ impl BackgroundHangMonitorWorker {
         }
     }
 
+    fn stop_streaming(&mut self) {
+        self.sample_stream = None;
+        if self.sampling_max_duration.is_none() {
+            self.sampling_duration = None;
+        }
+    }
+
     fn sample(&mut self) {
+        let mut stream_disconnected = false;
         for (component_id, monitored) in self.monitored_components.iter_mut() {
             let instant = Instant::now();
             if let Ok(stack) = monitored.sampler.suspend_and_sample_thread() {
-                if self.sampling_baseline.elapsed() >
-                    self.sampling_max_duration
-                        .expect("Max duration has been set")
-                {
-                    // Buffer is full, start discarding older samples.
-                    self.samples.pop_front();
+                if let Some(ref stream) = self.sample_stream {
+                    let time = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default();
+                    let sample = StreamedSample {
+                        component: component_id.clone(),
+                        thread_name: self.component_names.get(component_id).cloned(),
+                        time: time.as_secs_f64() * 1000.,
+                        frame_addresses: stack.frame_addresses(),
+                    };
+                    stream_disconnected |= stream.send(sample).is_err();
+                }
+                if let Some(max_duration) = self.sampling_max_duration {
+                    if self.sampling_baseline.elapsed() > max_duration {
+                        // Buffer is full, start discarding older samples.
+                        self.samples.pop_front();
+                    }
+                    self.samples
+                        .push_back(Sample(component_id.clone(), instant, stack));
                 }
-                self.samples
-                    .push_back(Sample(component_id.clone(), instant, stack));
             }
         }
+        if stream_disconnected {
+            self.stop_streaming();
+        }
     }
 }
@@ -76,24 +76,38 @@ impl NativeStack {
         let mut profile = HangProfile {
             backtrace: Vec::new(),
         };
-        for ip in self.instruction_ptrs.iter().rev() {
-            if ip.is_null() {
-                continue;
-            }
-            backtrace::resolve(*ip, |symbol| {
-                // TODO: use the demangled or C++ demangled symbols if available.
-                let name = symbol
-                    .name()
-                    .map(|n| String::from_utf8_lossy(&n.as_bytes()).to_string());
-                let filename = symbol.filename().map(|n| n.to_string_lossy().to_string());
-                let lineno = symbol.lineno();
-                profile.backtrace.push(HangProfileSymbol {
-                    name,
-                    filename,
-                    lineno,
-                });
-            });
+        for address in self.frame_addresses() {
+            resolve_frame(address, &mut profile.backtrace);
         }
         profile
     }
+
+    /// The addresses of the sampled frames, outermost first, which unlike the stack
+    /// itself can be sent to another thread to resolve their symbols.
+    pub fn frame_addresses(&self) -> Vec<usize> {
+        self.instruction_ptrs[..self.count]
+            .iter()
+            .rev()
+            .filter(|ip| !ip.is_null())
+            .map(|ip| *ip as usize)
+            .collect()
+    }
+}
+
+/// Resolves the symbols of the frame at the given address, which can be several
+/// when functions were inlined.
+pub fn resolve_frame(address: usize, symbols: &mut Vec<HangProfileSymbol>) {
+    backtrace::resolve(address as *mut std::ffi::c_void, |symbol| {
+        // TODO: use the demangled or C++ demangled symbols if available.
+        let name = symbol
+            .name()
+            .map(|n| String::from_utf8_lossy(&n.as_bytes()).to_string());
+        let filename = symbol.filename().map(|n| n.to_string_lossy().to_string());
+        let lineno = symbol.lineno();
+        symbols.push(HangProfileSymbol {
+            name,
+            filename,
+            lineno,
+        });
+    });
 }
@@ -11,6 +11,7 @@ use msg::constellation_msg::ScriptHangAnnotation;
 use msg::constellation_msg::TEST_PIPELINE_ID;
 use msg::constellation_msg::{HangAlert, HangAnnotation, HangMonitorAlert};
 use msg::constellation_msg::{MonitoredComponentId, MonitoredComponentType};
+use msg::constellation_msg::{SampledStack, SamplerControlMsg};
 use std::sync::Mutex;
 use std::thread;
 use std::time::Duration;
@@ -146,3 +147,47 @@ fn test_hang_monitoring_unregister() {
     // No new alert yet
     assert!(background_hang_monitor_receiver.try_recv().is_err());
 }
+
+#[test]
+// Only Linux and macOS can sample the stack of a thread.
+#[cfg(any(
+    target_os = "macos",
+    all(
+        target_os = "linux",
+        not(any(target_arch = "arm", target_arch = "aarch64"))
+    )
+))]
+fn test_sample_streaming() {
+    let _lock = SERIAL.lock().unwrap();
+
+    let (background_hang_monitor_ipc_sender, _background_hang_monitor_receiver) =
+        ipc::channel().expect("ipc channel failure");
+    let (sampler_sender, sampler_receiver) = ipc::channel().expect("ipc channel failure");
+
+    let background_hang_monitor_register =
+        HangMonitorRegister::init(background_hang_monitor_ipc_sender.clone(), sampler_receiver);
+    let background_hang_monitor = background_hang_monitor_register.register_component(
+        MonitoredComponentId(TEST_PIPELINE_ID, MonitoredComponentType::Script),
+        Duration::from_millis(10),
+        Duration::from_millis(1000),
+    );
+
+    let (stream_sender, stream_receiver) = ipc::channel::<SampledStack>().unwrap();
+    sampler_sender
+        .send(SamplerControlMsg::StartStreaming(
+            Duration::from_millis(10),
+            stream_sender,
+        ))
+        .unwrap();
+
+    // Samples are sent as they are taken.
+    let sample = stream_receiver.recv().unwrap();
+    let expected = MonitoredComponentId(TEST_PIPELINE_ID, MonitoredComponentType::Script);
+    assert_eq!(expected, sample.component);
+    assert!(sample.time > 0.);
+
+    sampler_sender
+        .send(SamplerControlMsg::StopStreaming)
+        .unwrap();
+    background_hang_monitor.unregister();
+}
@@ -113,7 +113,7 @@ pub struct Opts {
     pub profile_heartbeats: bool,
 
     /// `None` to disable debugger or `Some` with a port number to start a server to listen to
-    /// remote Firefox debugger connections.
+    /// remote Chrome DevTools Protocol connections.
     pub debugger_port: Option<u16>,
 
     /// `None` to disable devtools or `Some` with a port number to start a server to listen to
@@ -124,6 +124,10 @@ pub struct Opts {
     /// remote WebDriver commands.
     pub webdriver_port: Option<u16>,
 
+    /// `None` to disable WebDriver BiDi or `Some` with a port number to start a WebSocket
+    /// server to listen to remote WebDriver BiDi commands.
+    pub webdriver_bidi_port: Option<u16>,
+
     /// The initial requested size of the window.
     pub initial_window_size: Size2D<u32, DeviceIndependentPixel>,
 
@@ -451,6 +455,8 @@ fn print_debug_usage(app: &str) -> ! {
 pub enum OutputOptions {
     /// Database connection config (hostname, name, user, pass)
     FileName(String),
+    /// A file to write every profiled event to in the Chrome trace-event format, for Perfetto.
+    TraceEvents(String),
     Stdout(f64),
 }
 
@@ -493,6 +499,7 @@ pub fn default_opts() -> Opts {
         debugger_port: None,
         devtools_port: None,
         webdriver_port: None,
+        webdriver_bidi_port: None,
         initial_window_size: Size2D::new(1024, 740),
         multiprocess: false,
         background_hang_monitor: false,
@@ -536,10 +543,12 @@ pub fn from_cmdline_args(mut opts: Options, args: &[String]) -> ArgumentParsingR
     opts.optflagopt(
         "p",
         "profile",
-        "Time profiler flag and either a TSV output filename \
+        "Time profiler flag and either a TSV output filename, \
+         a JSON output filename for a Chrome trace-event file \
          OR an interval for output to Stdout (blank for Stdout with interval of 5s)",
         "10 \
-         OR time.tsv",
+         OR time.tsv \
+         OR trace.json",
     );
     opts.optflagopt(
         "",
@@ -597,7 +606,7 @@ pub fn from_cmdline_args(mut opts: Options, args: &[String]) -> ArgumentParsingR
     opts.optflagopt(
         "",
         "remote-debugging-port",
-        "Start remote debugger server on port",
+        "Start remote Chrome DevTools Protocol server on port",
         "2794",
     );
     opts.optflagopt("", "devtools", "Start remote devtools server on port", "0");
@@ -607,6 +616,12 @@ pub fn from_cmdline_args(mut opts: Options, args: &[String]) -> ArgumentParsingR
         "Start remote WebDriver server on port",
         "7000",
     );
+    opts.optflagopt(
+        "",
+        "webdriver-bidi",
+        "Start remote WebDriver BiDi server on port",
+        "7001",
+    );
     opts.optopt("", "resolution", "Set window resolution.", "1024x740");
     opts.optflag("M", "multiprocess", "Run in multiprocess mode");
     opts.optflag("B", "bhm", "Background Hang Monitor enabled");
@@ -741,6 +756,9 @@ pub fn from_cmdline_args(mut opts: Options, args: &[String]) -> ArgumentParsingR
                 Ok(interval) => Some(OutputOptions::Stdout(interval)),
                 Err(_) => match ServoUrl::parse(&argument) {
                     Ok(_) => panic!("influxDB isn't supported anymore"),
+                    Err(_) if argument.ends_with(".json") => {
+                        Some(OutputOptions::TraceEvents(argument))
+                    },
                     Err(_) => Some(OutputOptions::FileName(argument)),
                 },
             },
@@ -829,6 +847,12 @@ pub fn from_cmdline_args(mut opts: Options, args: &[String]) -> ArgumentParsingR
         })
     });
 
+    let webdriver_bidi_port = opt_match.opt_default("webdriver-bidi", "7001").map(|port| {
+        port.parse().unwrap_or_else(|err| {
+            args_fail(&format!("Error parsing option: --webdriver-bidi ({})", err))
+        })
+    });
+
     let initial_window_size = match opt_match.opt_str("resolution") {
         Some(res_string) => {
             let res: Vec<u32> = res_string
@@ -891,6 +915,7 @@ pub fn from_cmdline_args(mut opts: Options, args: &[String]) -> ArgumentParsingR
         debugger_port: debugger_port,
         devtools_port: devtools_port,
         webdriver_port: webdriver_port,
+        webdriver_bidi_port: webdriver_bidi_port,
         initial_window_size: initial_window_size,
         multiprocess: opt_match.opt_present("M"),
         background_hang_monitor: opt_match.opt_present("B"),
@@ -196,6 +196,9 @@ mod gen {
                 imagebitmap: {
                     enabled: bool,
                 },
+                indexeddb: {
+                    enabled: bool,
+                },
                 microdata: {
                     testing: {
                         enabled: bool,
@@ -454,6 +457,10 @@ mod gen {
                 columns: {
                     enabled: bool,
                 },
+                testing: {
+                    #[serde(default)]
+                    enabled: bool,
+                },
                 #[serde(default = "default_layout_threads")]
                 threads: i64,
                 viewport: {
@@ -473,6 +480,9 @@ mod gen {
                 }
             },
             network: {
+                cookie: {
+                    policy: String,
+                },
                 enforce_tls: {
                     enabled: bool,
                     localhost: bool,
@@ -481,9 +491,22 @@ mod gen {
                 http_cache: {
                     #[serde(rename = "network.http-cache.disabled")]
                     disabled: bool,
+                    disk: {
+                        #[serde(rename = "network.http-cache.disk.enabled")]
+                        enabled: bool,
+                        #[serde(rename = "network.http-cache.disk.max-size")]
+                        max_size: i64,
+                        #[serde(rename = "network.http-cache.disk.revalidate-on-startup")]
+                        revalidate_on_startup: bool,
+                    },
                 },
                 mime: {
                     sniff: bool,
+                },
+                proxy: {
+                    http: String,
+                    https: String,
+                    no_proxy: String,
                 }
             },
             session_history: {
@@ -114,6 +114,7 @@ use crossbeam_channel::{after, never, unbounded, Receiver, Sender};
 use devtools_traits::{ChromeToDevtoolsControlMsg, DevtoolsControlMsg};
 use embedder_traits::{Cursor, EmbedderMsg, EmbedderProxy, EventLoopWaker};
 use embedder_traits::{MediaSessionEvent, MediaSessionPlaybackState};
+use embedder_traits::{PromptCredentialsInput, PromptDefinition, PromptOrigin, PromptResult};
 use euclid::{default::Size2D as UntypedSize2D, Size2D};
 use gfx::font_cache_thread::FontCacheThread;
 use gfx_traits::Epoch;
@@ -134,12 +135,15 @@ use msg::constellation_msg::{
     BrowsingContextGroupId, BrowsingContextId, HistoryStateId, PipelineId,
     TopLevelBrowsingContextId,
 };
+use net_traits::indexeddb_thread::IndexedDBThreadMsg;
 use net_traits::pub_domains::reg_host;
 use net_traits::request::RequestBuilder;
 use net_traits::storage_thread::{StorageThreadMsg, StorageType};
 use net_traits::{self, FetchResponseMsg, IpcSend, ResourceThreads};
 use profile_traits::mem;
 use profile_traits::time;
+use script_traits::webdriver_msg::WebDriverWindowState;
+use script_traits::webdriver_msg::{WebDriverUserPromptAction, WebDriverUserPromptError};
 use script_traits::CompositorEvent::{MouseButtonEvent, MouseMoveEvent};
 use script_traits::{webdriver_msg, LogEntry, ScriptToConstellationChan, ServiceWorkerMsg};
 use script_traits::{
@@ -565,6 +569,11 @@ pub struct InitialConstellationState {
 struct WebDriverData {
     load_channel: Option<(PipelineId, IpcSender<webdriver_msg::LoadStatus>)>,
     resize_channel: Option<IpcSender<WindowSizeData>>,
+    /// Whether user prompts opened by content are kept here for webdriver
+    /// to handle, instead of being forwarded to the embedder.
+    intercept_user_prompts: bool,
+    /// The user prompt currently shown in each top-level browsing context.
+    user_prompts: HashMap<TopLevelBrowsingContextId, WebDriverUserPrompt>,
 }
 
 impl WebDriverData {
@@ -572,10 +581,20 @@ impl WebDriverData {
         WebDriverData {
             load_channel: None,
             resize_channel: None,
+            intercept_user_prompts: false,
+            user_prompts: HashMap::new(),
         }
     }
 }
 
+/// A user prompt kept for webdriver, the script thread that opened it is
+/// blocked until webdriver accepts or dismisses it.
+struct WebDriverUserPrompt {
+    definition: PromptDefinition,
+    /// The text sent to a `window.prompt()` dialog.
+    text: Option<String>,
+}
+
 /// When we are running reftests, we save an image to compare against a reference.
 /// This enum gives the possible states of preparing such an image.
 #[derive(Debug, PartialEq)]
@@ -856,6 +875,10 @@ where
         // service worker manager to communicate with constellation
         let (swmanager_sender, swmanager_receiver) = ipc::channel().expect("ipc channel failure");
 
+        if let Some(ref debugger_chan) = state.debugger_chan {
+            debugger::connect_constellation(debugger_chan, compositor_sender.clone());
+        }
+
         thread::Builder::new()
             .name("Constellation".to_owned())
             .spawn(move || {
@@ -912,6 +935,12 @@ where
                             (None, vec![])
                         }
                     };
+                if let Some(ref devtools_chan) = state.devtools_chan {
+                    for chan in &sampler_chan {
+                        let msg = ChromeToDevtoolsControlMsg::NewSampler(chan.clone());
+                        let _ = devtools_chan.send(DevtoolsControlMsg::FromChrome(msg));
+                    }
+                }
 
                 let (ipc_layout_sender, ipc_layout_receiver) =
                     ipc::channel().expect("ipc channel failure");
@@ -1180,7 +1209,7 @@ where
         // here, because it will be send on an ipc channel,
         // and ipc channels take onership of their data.
         // https://github.com/servo/ipc-channel/issues/138
-        load_data: LoadData,
+        mut load_data: LoadData,
         sandbox: IFrameSandboxState,
         is_private: bool,
         is_visible: bool,
@@ -1188,6 +1217,16 @@ where
         if self.shutting_down {
             return;
         }
+
+        // Cookies of nested documents are partitioned, or blocked, by their top-level site.
+        load_data.top_level_origin = match parent_pipeline_id {
+            Some(_) => self
+                .browsing_contexts
+                .get(&BrowsingContextId::from(top_level_browsing_context_id))
+                .and_then(|browsing_context| self.pipelines.get(&browsing_context.pipeline_id))
+                .map(|pipeline| pipeline.url.origin()),
+            None => None,
+        };
         debug!(
             "Creating new pipeline {} in browsing context {}.",
             pipeline_id, browsing_context_id
@@ -1294,6 +1333,10 @@ where
         };
 
         if let Some(sampler_chan) = pipeline.sampler_control_chan {
+            if let Some(ref chan) = self.devtools_chan {
+                let msg = ChromeToDevtoolsControlMsg::NewSampler(sampler_chan.clone());
+                let _ = chan.send(DevtoolsControlMsg::FromChrome(msg));
+            }
             self.sampling_profiler_control.push(sampler_chan);
         }
 
@@ -1819,9 +1862,22 @@ where
             FromScriptMsg::ScheduleBroadcast(router_id, message) => {
                 self.handle_schedule_broadcast(source_pipeline_id, router_id, message);
             },
-            FromScriptMsg::ForwardToEmbedder(embedder_msg) => {
-                self.embedder_proxy
-                    .send((Some(source_top_ctx_id), embedder_msg));
+            FromScriptMsg::ForwardToEmbedder(embedder_msg) => match embedder_msg {
+                EmbedderMsg::Prompt(definition, PromptOrigin::Untrusted)
+                    if self.webdriver.intercept_user_prompts =>
+                {
+                    let prompt = WebDriverUserPrompt {
+                        definition,
+                        text: None,
+                    };
+                    self.webdriver
+                        .user_prompts
+                        .insert(source_top_ctx_id, prompt);
+                }
+                embedder_msg => {
+                    self.embedder_proxy
+                        .send((Some(source_top_ctx_id), embedder_msg));
+                },
             },
             FromScriptMsg::PipelineExited => {
                 self.handle_pipeline_exited(source_pipeline_id);
@@ -2788,6 +2844,8 @@ where
         let (core_sender, core_receiver) = ipc::channel().expect("Failed to create IPC channel!");
         let (storage_sender, storage_receiver) =
             ipc::channel().expect("Failed to create IPC channel!");
+        let (indexeddb_sender, indexeddb_receiver) =
+            ipc::channel().expect("Failed to create IPC channel!");
 
         debug!("Exiting core resource threads.");
         if let Err(e) = self
@@ -2817,6 +2875,14 @@ where
             warn!("Exit storage thread failed ({})", e);
         }
 
+        debug!("Exiting IndexedDB thread.");
+        if let Err(e) = self
+            .public_resource_threads
+            .send(IndexedDBThreadMsg::Exit(indexeddb_sender))
+        {
+            warn!("Exit IndexedDB thread failed ({})", e);
+        }
+
         debug!("Exiting bluetooth thread.");
         if let Err(e) = self.bluetooth_thread.send(BluetoothRequest::Exit) {
             warn!("Exit bluetooth thread failed ({})", e);
@@ -2882,6 +2948,9 @@ where
         if let Err(e) = storage_receiver.recv() {
             warn!("Exit storage thread failed ({:?})", e);
         }
+        if let Err(e) = indexeddb_receiver.recv() {
+            warn!("Exit IndexedDB thread failed ({:?})", e);
+        }
 
         debug!("Asking compositor to complete shutdown.");
         self.compositor_proxy
@@ -3128,6 +3197,11 @@ where
         &mut self,
         top_level_browsing_context_id: TopLevelBrowsingContextId,
     ) {
+        // Don't leave the script thread waiting on a prompt that can no longer be handled.
+        let _ = self.handle_user_prompt_for_webdriver(
+            top_level_browsing_context_id,
+            WebDriverUserPromptAction::Dismiss,
+        );
         let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
         self.close_browsing_context(browsing_context_id, ExitPipelineMode::Normal);
         self.browsers.remove(&top_level_browsing_context_id);
@@ -4540,7 +4614,117 @@ where
                 self.compositor_proxy
                     .send(ToCompositorMsg::CreatePng(rect, reply));
             },
+            WebDriverCommandMsg::SetWindowState(top_level_browsing_context_id, state, reply) => {
+                self.webdriver.resize_channel = Some(reply);
+                let msg = match state {
+                    WebDriverWindowState::Maximized => EmbedderMsg::MaximizeWindow,
+                    WebDriverWindowState::Minimized => EmbedderMsg::MinimizeWindow,
+                    WebDriverWindowState::Fullscreen => EmbedderMsg::SetFullscreenState(true),
+                };
+                self.embedder_proxy
+                    .send((Some(top_level_browsing_context_id), msg));
+            },
+            WebDriverCommandMsg::NewWindow(reply) => {
+                let top_level_browsing_context_id = TopLevelBrowsingContextId::new();
+                let url = ServoUrl::parse("about:blank").expect("infallible");
+                self.handle_new_top_level_browsing_context(url, top_level_browsing_context_id);
+                let _ = reply.send(top_level_browsing_context_id);
+            },
+            WebDriverCommandMsg::CloseWindow(top_level_browsing_context_id) => {
+                self.handle_close_top_level_browsing_context(top_level_browsing_context_id);
+            },
+            WebDriverCommandMsg::GetTopLevelBrowsingContexts(reply) => {
+                let _ = reply.send(self.browsers.keys().cloned().collect());
+            },
+            WebDriverCommandMsg::InterceptUserPrompts(intercept) => {
+                self.webdriver.intercept_user_prompts = intercept;
+                if !intercept {
+                    // Hand the prompts that were left open over to the embedder.
+                    for (top_level_browsing_context_id, prompt) in
+                        self.webdriver.user_prompts.drain()
+                    {
+                        self.embedder_proxy.send((
+                            Some(top_level_browsing_context_id),
+                            EmbedderMsg::Prompt(prompt.definition, PromptOrigin::Untrusted),
+                        ));
+                    }
+                }
+            },
+            WebDriverCommandMsg::HandleUserPrompt(top_level_browsing_context_id, action, reply) => {
+                let result =
+                    self.handle_user_prompt_for_webdriver(top_level_browsing_context_id, action);
+                let _ = reply.send(result);
+            },
+        }
+    }
+
+    fn handle_user_prompt_for_webdriver(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        action: WebDriverUserPromptAction,
+    ) -> Result<Option<String>, WebDriverUserPromptError> {
+        let mut prompt = match self
+            .webdriver
+            .user_prompts
+            .remove(&top_level_browsing_context_id)
+        {
+            Some(prompt) => prompt,
+            None => return Err(WebDriverUserPromptError::NoSuchAlert),
+        };
+        let result = match action {
+            WebDriverUserPromptAction::GetText => {
+                let text = match prompt.definition {
+                    PromptDefinition::Alert(ref message, _) |
+                    PromptDefinition::OkCancel(ref message, _) |
+                    PromptDefinition::YesNo(ref message, _) |
+                    PromptDefinition::Input(ref message, _, _) => Some(message.clone()),
+                    PromptDefinition::Credentials(_) => None,
+                };
+                self.webdriver
+                    .user_prompts
+                    .insert(top_level_browsing_context_id, prompt);
+                return Ok(text);
+            },
+            WebDriverUserPromptAction::SendText(text) => {
+                let result = match prompt.definition {
+                    PromptDefinition::Input(..) => {
+                        prompt.text = Some(text);
+                        Ok(None)
+                    },
+                    _ => Err(WebDriverUserPromptError::ElementNotInteractable),
+                };
+                self.webdriver
+                    .user_prompts
+                    .insert(top_level_browsing_context_id, prompt);
+                return result;
+            },
+            WebDriverUserPromptAction::Accept => match prompt.definition {
+                PromptDefinition::Alert(_, sender) => sender.send(()),
+                PromptDefinition::OkCancel(_, sender) | PromptDefinition::YesNo(_, sender) => {
+                    sender.send(PromptResult::Primary)
+                },
+                PromptDefinition::Input(_, default, sender) => {
+                    sender.send(Some(prompt.text.unwrap_or(default)))
+                },
+                PromptDefinition::Credentials(sender) => {
+                    sender.send(PromptCredentialsInput::default())
+                },
+            },
+            WebDriverUserPromptAction::Dismiss => match prompt.definition {
+                PromptDefinition::Alert(_, sender) => sender.send(()),
+                PromptDefinition::OkCancel(_, sender) | PromptDefinition::YesNo(_, sender) => {
+                    sender.send(PromptResult::Secondary)
+                },
+                PromptDefinition::Input(_, _, sender) => sender.send(None),
+                PromptDefinition::Credentials(sender) => {
+                    sender.send(PromptCredentialsInput::default())
+                },
+            },
+        };
+        if let Err(e) = result {
+            warn!("Failed to close user prompt ({:?}).", e);
         }
+        Ok(None)
     }
 
     fn handle_change_browser_visibility(
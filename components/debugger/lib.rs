@@ -2,70 +2,1336 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+//! A remote protocol server, speaking a subset of the
+//! [Chrome DevTools Protocol](https://chromedevtools.github.io/devtools-protocol/)
+//! over a WebSocket so that CDP-based tools can drive Servo.
+//!
+//! `GET /json/version` and `GET /json/list` describe the WebSocket endpoints:
+//! `/devtools/browser`, and `/devtools/page/<targetId>` for every top-level
+//! browsing context. Commands sent to a page endpoint apply to that page. On
+//! the browser endpoint, pages are driven through the sessions created by
+//! `Target.attachToTarget`, which only supports flat sessions.
+//!
+//! The supported commands are:
+//!
+//! * `Browser.getVersion`
+//! * `Target.getTargets`, `Target.setDiscoverTargets`, `Target.attachToTarget`
+//!   and `Target.detachFromTarget`
+//! * `Page.enable`, `Page.disable`, `Page.navigate`, `Page.reload` and
+//!   `Page.captureScreenshot`
+//! * `Runtime.enable`, `Runtime.disable` and `Runtime.evaluate`
+//! * `DOM.enable`, `DOM.disable` and `DOM.getDocument`
+//! * `Network.enable` and `Network.disable`
+//!
+//! The supported events are `Target.targetCreated`, `Target.targetInfoChanged`,
+//! `Target.attachedToTarget`, `Page.frameStartedLoading`, `Page.frameNavigated`,
+//! `Page.loadEventFired`, `Page.frameStoppedLoading`, `Network.requestWillBeSent`,
+//! `Network.responseReceived` and `Network.loadingFinished`. Page and network
+//! events are only reported for top-level documents, to the sessions that
+//! enabled their domain.
+//!
+//! Commands are carried out with the same constellation messages as the
+//! WebDriver server, once the constellation connected to the server with
+//! `connect_constellation`. Events are copied from the messages that script
+//! and the resource threads send to devtools, see `tap_devtools_messages`.
+
+#[macro_use]
+extern crate crossbeam_channel;
 #[macro_use]
 extern crate log;
 
+use compositing::ConstellationMsg;
+use crossbeam_channel::Receiver;
+use devtools_traits::{ChromeToDevtoolsControlMsg, DevtoolsControlMsg, DevtoolsPageInfo};
+use devtools_traits::{NavigationState, NetworkEvent, ScriptToDevtoolsControlMsg};
+use euclid::{Point2D, Rect, Size2D};
+use image::{DynamicImage, ImageOutputFormat, RgbImage};
+use ipc_channel::ipc;
+use msg::constellation_msg::{BrowsingContextId, PipelineId, TopLevelBrowsingContextId};
+use pixels::PixelFormat;
+use script_traits::webdriver_msg::{WebDriverJSError, WebDriverJSValue, WebDriverScriptCommand};
+use script_traits::{LoadData, LoadOrigin, WebDriverCommandMsg};
+use serde_json::{json, Map, Value};
+use servo_url::ServoUrl;
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::thread;
-use ws::{self, Builder, CloseCode, Handler, Handshake};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use style_traits::CSSPixel;
+use uuid::Uuid;
+use ws::{self, Builder, CloseCode, Handler, Handshake, Request, Response};
+
+/// The version of the protocol the server implements a subset of.
+const PROTOCOL_VERSION: &str = "1.3";
+
+/// The WebSocket endpoint of the browser.
+const BROWSER_PATH: &str = "/devtools/browser";
+
+/// The prefix of the WebSocket endpoints of pages, followed by their target id.
+const PAGE_PATH: &str = "/devtools/page/";
+
+/// How long to keep asking for a screenshot while the page is not ready to be shown.
+const SCREENSHOT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Describes the nodes of the document for `DOM.getDocument`, down to the given
+/// depth, as a JSON string.
+const DESCRIBE_DOCUMENT: &str = "(function(maxDepth) {
+    let nextId = 1;
+    function describe(node, depth) {
+        const description = {
+            nodeId: nextId,
+            backendNodeId: nextId,
+            nodeType: node.nodeType,
+            nodeName: node.nodeName,
+            localName: node.localName || '',
+            nodeValue: node.nodeValue || '',
+            childNodeCount: node.childNodes.length,
+        };
+        nextId++;
+        if (node.attributes) {
+            description.attributes = [];
+            for (const attribute of node.attributes) {
+                description.attributes.push(attribute.name, attribute.value);
+            }
+        }
+        if (node.nodeType === Node.DOCUMENT_NODE) {
+            description.documentURL = node.URL;
+            description.baseURL = node.baseURI;
+        }
+        if (node.nodeType === Node.DOCUMENT_TYPE_NODE) {
+            description.publicId = node.publicId;
+            description.systemId = node.systemId;
+        }
+        if (maxDepth < 0 || depth < maxDepth) {
+            description.children = Array.from(node.childNodes, child => describe(child, depth + 1));
+        }
+        return description;
+    }
+    return JSON.stringify(describe(document, 0));
+})";
+
+/// An event CDP clients may be interested in, copied out of a message sent to devtools.
+#[derive(Debug)]
+pub enum DevtoolsEvent {
+    /// A new document was created in a browsing context.
+    NewGlobal(BrowsingContextId, PipelineId, DevtoolsPageInfo),
+    /// A browsing context started navigating to a URL.
+    NavigationStarted(BrowsingContextId, ServoUrl),
+    /// A browsing context completed navigating to a document.
+    Load(BrowsingContextId, PipelineId, DevtoolsPageInfo),
+    /// The title of a document changed.
+    TitleChanged(PipelineId, String),
+    /// A page sent an HTTP request.
+    RequestSent {
+        id: String,
+        pipeline_id: PipelineId,
+        url: ServoUrl,
+        method: String,
+        headers: Vec<(String, String)>,
+    },
+    /// A page received the response to an HTTP request.
+    ResponseReceived {
+        id: String,
+        pipeline_id: PipelineId,
+        status: Option<(u16, Vec<u8>)>,
+        headers: Vec<(String, String)>,
+        body_size: usize,
+    },
+}
+
+impl DevtoolsEvent {
+    fn from_devtools_message(message: &DevtoolsControlMsg) -> Option<DevtoolsEvent> {
+        match *message {
+            DevtoolsControlMsg::FromScript(ref message) => match *message {
+                ScriptToDevtoolsControlMsg::NewGlobal(
+                    (browsing_context, pipeline, None),
+                    _,
+                    ref info,
+                ) => Some(DevtoolsEvent::NewGlobal(
+                    browsing_context,
+                    pipeline,
+                    info.clone(),
+                )),
+                ScriptToDevtoolsControlMsg::Navigate(browsing_context, ref state) => {
+                    Some(match *state {
+                        NavigationState::Start(ref url) => {
+                            DevtoolsEvent::NavigationStarted(browsing_context, url.clone())
+                        },
+                        NavigationState::Stop(pipeline, ref info) => {
+                            DevtoolsEvent::Load(browsing_context, pipeline, info.clone())
+                        },
+                    })
+                },
+                ScriptToDevtoolsControlMsg::TitleChanged(pipeline, ref title) => {
+                    Some(DevtoolsEvent::TitleChanged(pipeline, title.clone()))
+                },
+                _ => None,
+            },
+            DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::NetworkEvent(
+                ref id,
+                ref event,
+            )) => Some(match *event {
+                NetworkEvent::HttpRequest(ref request) => DevtoolsEvent::RequestSent {
+                    id: id.clone(),
+                    pipeline_id: request.pipeline_id,
+                    url: request.url.clone(),
+                    method: request.method.as_str().to_owned(),
+                    headers: headers_to_vec(Some(&request.headers)),
+                },
+                NetworkEvent::HttpResponse(ref response) => DevtoolsEvent::ResponseReceived {
+                    id: id.clone(),
+                    pipeline_id: response.pipeline_id,
+                    status: response.status.clone(),
+                    headers: headers_to_vec(response.headers.as_ref()),
+                    body_size: response.body.as_ref().map_or(0, |body| body.len()),
+                },
+            }),
+            DevtoolsControlMsg::FromChrome(_) => None,
+        }
+    }
+}
+
+fn headers_to_vec(headers: Option<&http::HeaderMap>) -> Vec<(String, String)> {
+    headers
+        .into_iter()
+        .flat_map(|headers| headers.iter())
+        .map(|(name, value)| {
+            (
+                name.as_str().to_owned(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// Returns a channel to send devtools messages on in place of `devtools_chan`,
+/// and a receiver of the events found in those messages.
+///
+/// The messages are still forwarded to `devtools_chan`, if any.
+pub fn tap_devtools_messages(
+    devtools_chan: Option<crossbeam_channel::Sender<DevtoolsControlMsg>>,
+) -> (
+    crossbeam_channel::Sender<DevtoolsControlMsg>,
+    Receiver<DevtoolsEvent>,
+) {
+    let (tap_sender, tap_receiver) = crossbeam_channel::unbounded();
+    let (event_sender, event_receiver) = crossbeam_channel::unbounded();
+    thread::Builder::new()
+        .name("debugger-devtools-tap".to_owned())
+        .spawn(move || {
+            while let Ok(message) = tap_receiver.recv() {
+                if let Some(event) = DevtoolsEvent::from_devtools_message(&message) {
+                    let _ = event_sender.send(event);
+                }
+                let exit = match message {
+                    DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::ServerExitMsg) => {
+                        true
+                    },
+                    _ => false,
+                };
+                if let Some(ref devtools_chan) = devtools_chan {
+                    let _ = devtools_chan.send(message);
+                }
+                if exit {
+                    break;
+                }
+            }
+        })
+        .expect("Thread spawning failed");
+    (tap_sender, event_receiver)
+}
 
 enum Message {
     ShutdownServer,
+    /// The channel to carry out commands with.
+    Constellation(crossbeam_channel::Sender<ConstellationMsg>),
+    /// A WebSocket connection was opened on the given path.
+    Open(ws::Sender, String),
+    Command(ws::Sender, String),
+    Close(ws::Sender),
+    /// An HTTP request for one of the discovery endpoints, with the host it was sent
+    /// to, and where to send the response body.
+    Discover(String, String, crossbeam_channel::Sender<Option<Value>>),
 }
 
-pub struct Sender(crossbeam_channel::Sender<Message>);
+pub struct Sender(crossbeam_channel::Sender<Message>, SocketAddr);
+
+impl Sender {
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.1
+    }
+}
 
 struct Connection {
     sender: ws::Sender,
+    server: crossbeam_channel::Sender<Message>,
 }
 
 impl Handler for Connection {
-    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
+    fn on_request(&mut self, request: &Request) -> ws::Result<Response> {
+        if !request.resource().starts_with("/json") {
+            return Response::from_request(request);
+        }
+        let host = request
+            .header("Host")
+            .map(|host| String::from_utf8_lossy(host).into_owned())
+            .unwrap_or_default();
+        let (sender, receiver) = crossbeam_channel::bounded(1);
+        let _ = self.server.send(Message::Discover(
+            request.resource().to_owned(),
+            host,
+            sender,
+        ));
+        let mut response = match receiver.recv() {
+            Ok(Some(body)) => Response::new(200, "OK", body.to_string().into_bytes()),
+            _ => Response::new(404, "Not Found", vec![]),
+        };
+        response
+            .headers_mut()
+            .push(("Content-Type".to_owned(), b"application/json".to_vec()));
+        Ok(response)
+    }
+
+    fn on_open(&mut self, handshake: Handshake) -> ws::Result<()> {
         debug!("Connection opened.");
+        let path = handshake.request.resource().to_owned();
+        let _ = self.server.send(Message::Open(self.sender.clone(), path));
         Ok(())
     }
 
     fn on_close(&mut self, _: CloseCode, _: &str) {
         debug!("Connection closed.");
+        let _ = self.server.send(Message::Close(self.sender.clone()));
     }
 
     fn on_message(&mut self, message: ws::Message) -> ws::Result<()> {
-        self.sender.send(message)
+        let text = message.into_text()?;
+        let _ = self
+            .server
+            .send(Message::Command(self.sender.clone(), text));
+        Ok(())
     }
 }
 
-pub fn start_server(port: u16) -> Sender {
+/// Start a server listening on the given port, reporting the events found by
+/// `tap_devtools_messages` to its clients.
+pub fn start_server(port: u16, user_agent: String, events: Receiver<DevtoolsEvent>) -> Sender {
     debug!("Starting server.");
     let (sender, receiver) = crossbeam_channel::unbounded();
+    let server = sender.clone();
+    let socket = Builder::new()
+        .build(move |sender: ws::Sender| Connection {
+            sender: sender,
+            server: server.clone(),
+        })
+        .and_then(|socket| socket.bind(("127.0.0.1", port)))
+        .unwrap_or_else(|_| panic!("Unable to start debugger server"));
+    let address = socket.local_addr().unwrap();
+    let broadcaster = socket.broadcaster();
+    thread::Builder::new()
+        .name("debugger-websocket".to_owned())
+        .spawn(move || {
+            if let Err(error) = socket.run() {
+                warn!("Debugger server failed ({})", error);
+            }
+        })
+        .expect("Thread spawning failed");
     thread::Builder::new()
         .name("debugger".to_owned())
         .spawn(move || {
-            let socket = Builder::new()
-                .build(|sender: ws::Sender| Connection { sender: sender })
-                .unwrap();
-            let sender = socket.broadcaster();
-            thread::Builder::new()
-                .name("debugger-websocket".to_owned())
-                .spawn(move || {
-                    socket.listen(("127.0.0.1", port)).unwrap();
-                })
-                .expect("Thread spawning failed");
-            while let Ok(message) = receiver.recv() {
-                match message {
-                    Message::ShutdownServer => {
-                        break;
+            let mut server = Server::new(user_agent);
+            loop {
+                select! {
+                    recv(receiver) -> message => match message {
+                        Ok(Message::ShutdownServer) | Err(_) => break,
+                        Ok(message) => server.handle_message(message),
+                    },
+                    recv(events) -> event => match event {
+                        Ok(event) => server.handle_event(event),
+                        Err(_) => break,
                     },
                 }
             }
-            sender.shutdown().unwrap();
+            broadcaster.shutdown().unwrap();
         })
         .expect("Thread spawning failed");
-    Sender(sender)
+    Sender(sender, address)
+}
+
+/// Let the server carry out commands by sending messages to the constellation.
+pub fn connect_constellation(
+    sender: &Sender,
+    constellation_chan: crossbeam_channel::Sender<ConstellationMsg>,
+) {
+    if let Err(_) = sender.0.send(Message::Constellation(constellation_chan)) {
+        warn!("Failed to connect server to the constellation.");
+    }
 }
 
 pub fn shutdown_server(sender: &Sender) {
     debug!("Shutting down server.");
-    let &Sender(ref sender) = sender;
-    if let Err(_) = sender.send(Message::ShutdownServer) {
+    if let Err(_) = sender.0.send(Message::ShutdownServer) {
         warn!("Failed to shut down server.");
     }
 }
+
+/// <https://www.jsonrpc.org/specification#error_object>
+#[derive(Debug)]
+struct ProtocolError {
+    code: i64,
+    message: String,
+}
+
+impl ProtocolError {
+    fn new(code: i64, message: &str) -> ProtocolError {
+        ProtocolError {
+            code: code,
+            message: message.to_owned(),
+        }
+    }
+
+    fn invalid_params(message: &str) -> ProtocolError {
+        ProtocolError::new(-32602, message)
+    }
+
+    fn server_error(message: &str) -> ProtocolError {
+        ProtocolError::new(-32000, message)
+    }
+}
+
+type ProtocolResult<T> = Result<T, ProtocolError>;
+
+/// A debugging session, either the one of a connection or one attached to a target
+/// through it.
+struct Session {
+    /// The page that commands apply to, if any.
+    target: Option<TopLevelBrowsingContextId>,
+    page_events: bool,
+    network_events: bool,
+}
+
+impl Session {
+    fn new(target: Option<TopLevelBrowsingContextId>) -> Session {
+        Session {
+            target: target,
+            page_events: false,
+            network_events: false,
+        }
+    }
+}
+
+struct Client {
+    sender: ws::Sender,
+    /// The sessions of this connection, keyed by session id. The session of the
+    /// connection itself has no id.
+    sessions: HashMap<Option<String>, Session>,
+    /// Whether the client asked to be told about new targets.
+    discover_targets: bool,
+}
+
+struct Server {
+    user_agent: String,
+    constellation_chan: Option<crossbeam_channel::Sender<ConstellationMsg>>,
+    clients: HashMap<u32, Client>,
+    /// The ids given out for top-level browsing contexts, and back.
+    target_ids: HashMap<TopLevelBrowsingContextId, String>,
+    targets: HashMap<String, TopLevelBrowsingContextId>,
+    /// The title and URL of the active document of every browsing context.
+    documents: HashMap<BrowsingContextId, DevtoolsPageInfo>,
+    /// The browsing context of every pipeline with a document.
+    pipelines: HashMap<PipelineId, BrowsingContextId>,
+    /// The URL of the requests waiting for a response.
+    requests: HashMap<String, ServoUrl>,
+}
+
+impl Server {
+    fn new(user_agent: String) -> Server {
+        Server {
+            user_agent: user_agent,
+            constellation_chan: None,
+            clients: HashMap::new(),
+            target_ids: HashMap::new(),
+            targets: HashMap::new(),
+            documents: HashMap::new(),
+            pipelines: HashMap::new(),
+            requests: HashMap::new(),
+        }
+    }
+
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::ShutdownServer => unreachable!("Handled by the server loop"),
+            Message::Constellation(constellation_chan) => {
+                self.constellation_chan = Some(constellation_chan);
+            },
+            Message::Open(sender, path) => {
+                let target = if path.starts_with(PAGE_PATH) {
+                    self.targets.get(&path[PAGE_PATH.len()..]).cloned()
+                } else {
+                    None
+                };
+                let mut sessions = HashMap::new();
+                sessions.insert(None, Session::new(target));
+                self.clients.insert(
+                    sender.connection_id(),
+                    Client {
+                        sender: sender,
+                        sessions: sessions,
+                        discover_targets: false,
+                    },
+                );
+            },
+            Message::Command(sender, text) => {
+                let response = self.handle_command(sender.connection_id(), &text);
+                send(&sender, response);
+            },
+            Message::Close(sender) => {
+                self.clients.remove(&sender.connection_id());
+            },
+            Message::Discover(path, host, reply) => {
+                let _ = reply.send(self.discover(&path, &host));
+            },
+        }
+    }
+
+    /// The response to a request for a discovery endpoint, if it exists.
+    fn discover(&mut self, path: &str, host: &str) -> Option<Value> {
+        match path.trim_end_matches('/') {
+            "/json/version" => Some(json!({
+                "Browser": "Servo",
+                "Protocol-Version": PROTOCOL_VERSION,
+                "User-Agent": self.user_agent,
+                "webSocketDebuggerUrl": format!("ws://{}{}", host, BROWSER_PATH),
+            })),
+            "/json" | "/json/list" => Some(
+                self.top_level_browsing_context_ids()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|id| {
+                        let info = self.target_info(id);
+                        json!({
+                            "id": info["targetId"],
+                            "type": "page",
+                            "title": info["title"],
+                            "url": info["url"],
+                            "webSocketDebuggerUrl": format!(
+                                "ws://{}{}{}",
+                                host,
+                                PAGE_PATH,
+                                info["targetId"].as_str().unwrap(),
+                            ),
+                        })
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    fn handle_command(&mut self, connection: u32, text: &str) -> Value {
+        let command: Value = match serde_json::from_str(text) {
+            Ok(command) => command,
+            Err(_) => {
+                return error_response(
+                    Value::Null,
+                    ProtocolError::new(-32700, "Message must be a valid JSON"),
+                );
+            },
+        };
+        let id = match command.get("id").and_then(Value::as_u64) {
+            Some(id) => id,
+            None => {
+                return error_response(
+                    Value::Null,
+                    ProtocolError::new(-32600, "Message must have integer 'id' property"),
+                );
+            },
+        };
+        let session_id = command
+            .get("sessionId")
+            .and_then(Value::as_str)
+            .map(str::to_owned);
+        let method = command.get("method").and_then(Value::as_str).unwrap_or("");
+        let empty_params = Map::new();
+        let params = match command.get("params") {
+            Some(Value::Object(params)) => params,
+            Some(_) => {
+                return error_response(
+                    json!(id),
+                    ProtocolError::invalid_params("'params' must be an object"),
+                );
+            },
+            None => &empty_params,
+        };
+
+        let mut response = match self.handle_method(connection, &session_id, method, params) {
+            Ok(result) => json!({ "id": id, "result": result }),
+            Err(error) => error_response(json!(id), error),
+        };
+        if let Some(session_id) = session_id {
+            response["sessionId"] = json!(session_id);
+        }
+        response
+    }
+
+    fn handle_method(
+        &mut self,
+        connection: u32,
+        session_id: &Option<String>,
+        method: &str,
+        params: &Map<String, Value>,
+    ) -> ProtocolResult<Value> {
+        let target = self
+            .session_mut(connection, session_id)
+            .ok_or_else(|| ProtocolError::new(-32001, "Session with given id not found."))?
+            .target;
+        let page = || target.ok_or_else(|| ProtocolError::server_error("Not attached to a page"));
+
+        match method {
+            "Browser.getVersion" => Ok(self.handle_get_version()),
+            "Target.getTargets" => self.handle_get_targets(),
+            "Target.setDiscoverTargets" => self.handle_set_discover_targets(connection, params),
+            "Target.attachToTarget" => self.handle_attach_to_target(connection, session_id, params),
+            "Target.detachFromTarget" => self.handle_detach_from_target(connection, params),
+            "Page.enable" | "Page.disable" => {
+                if let Some(session) = self.session_mut(connection, session_id) {
+                    session.page_events = method == "Page.enable";
+                }
+                Ok(json!({}))
+            },
+            "Network.enable" | "Network.disable" => {
+                if let Some(session) = self.session_mut(connection, session_id) {
+                    session.network_events = method == "Network.enable";
+                }
+                Ok(json!({}))
+            },
+            "Runtime.enable" | "Runtime.disable" | "DOM.enable" | "DOM.disable" => Ok(json!({})),
+            "Page.navigate" => self.handle_navigate(page()?, params),
+            "Page.reload" => self.handle_reload(page()?),
+            "Page.captureScreenshot" => self.handle_capture_screenshot(page()?, params),
+            "Runtime.evaluate" => self.handle_evaluate(page()?, params),
+            "DOM.getDocument" => self.handle_get_document(page()?, params),
+            _ => Err(ProtocolError::new(
+                -32601,
+                &format!("'{}' wasn't found", method),
+            )),
+        }
+    }
+
+    // https://chromedevtools.github.io/devtools-protocol/tot/Browser/#method-getVersion
+    fn handle_get_version(&self) -> Value {
+        json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "product": "Servo",
+            "revision": "",
+            "userAgent": self.user_agent,
+            "jsVersion": "",
+        })
+    }
+
+    // https://chromedevtools.github.io/devtools-protocol/tot/Target/#method-getTargets
+    fn handle_get_targets(&mut self) -> ProtocolResult<Value> {
+        let target_infos = self
+            .top_level_browsing_context_ids()?
+            .into_iter()
+            .map(|id| self.target_info(id))
+            .collect::<Vec<_>>();
+        Ok(json!({ "targetInfos": target_infos }))
+    }
+
+    // https://chromedevtools.github.io/devtools-protocol/tot/Target/#method-setDiscoverTargets
+    fn handle_set_discover_targets(
+        &mut self,
+        connection: u32,
+        params: &Map<String, Value>,
+    ) -> ProtocolResult<Value> {
+        let discover = params
+            .get("discover")
+            .and_then(Value::as_bool)
+            .ok_or_else(|| ProtocolError::invalid_params("Missing discover"))?;
+        let target_infos = if discover {
+            self.top_level_browsing_context_ids()?
+                .into_iter()
+                .map(|id| self.target_info(id))
+                .collect()
+        } else {
+            vec![]
+        };
+        if let Some(client) = self.clients.get_mut(&connection) {
+            client.discover_targets = discover;
+            for target_info in target_infos {
+                let params = json!({ "targetInfo": target_info });
+                send_event(client, &None, "Target.targetCreated", params);
+            }
+        }
+        Ok(json!({}))
+    }
+
+    // https://chromedevtools.github.io/devtools-protocol/tot/Target/#method-attachToTarget
+    fn handle_attach_to_target(
+        &mut self,
+        connection: u32,
+        session_id: &Option<String>,
+        params: &Map<String, Value>,
+    ) -> ProtocolResult<Value> {
+        let target_id = params
+            .get("targetId")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ProtocolError::invalid_params("Missing targetId"))?;
+        let target = *self
+            .targets
+            .get(target_id)
+            .ok_or_else(|| ProtocolError::server_error("No target with given id found"))?;
+        if params.get("flatten").and_then(Value::as_bool) != Some(true) {
+            return Err(ProtocolError::invalid_params(
+                "Only flat sessions are supported",
+            ));
+        }
+
+        let target_info = self.target_info(target);
+        let new_session_id = Uuid::new_v4().to_string();
+        if let Some(client) = self.clients.get_mut(&connection) {
+            client
+                .sessions
+                .insert(Some(new_session_id.clone()), Session::new(Some(target)));
+            let params = json!({
+                "sessionId": new_session_id,
+                "targetInfo": target_info,
+                "waitingForDebugger": false,
+            });
+            send_event(client, session_id, "Target.attachedToTarget", params);
+        }
+        Ok(json!({ "sessionId": new_session_id }))
+    }
+
+    // https://chromedevtools.github.io/devtools-protocol/tot/Target/#method-detachFromTarget
+    fn handle_detach_from_target(
+        &mut self,
+        connection: u32,
+        params: &Map<String, Value>,
+    ) -> ProtocolResult<Value> {
+        let session_id = params
+            .get("sessionId")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ProtocolError::invalid_params("Missing sessionId"))?;
+        self.clients
+            .get_mut(&connection)
+            .and_then(|client| client.sessions.remove(&Some(session_id.to_owned())))
+            .ok_or_else(|| ProtocolError::server_error("No session with given id"))?;
+        Ok(json!({}))
+    }
+
+    // https://chromedevtools.github.io/devtools-protocol/tot/Page/#method-navigate
+    fn handle_navigate(
+        &mut self,
+        target: TopLevelBrowsingContextId,
+        params: &Map<String, Value>,
+    ) -> ProtocolResult<Value> {
+        let url = params
+            .get("url")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ProtocolError::invalid_params("Missing url"))?;
+        let url = ServoUrl::parse(url)
+            .map_err(|_| ProtocolError::server_error("Cannot navigate to invalid URL"))?;
+
+        // Clients wait for the page events rather than for the load to complete.
+        let (sender, _) = ipc::channel().unwrap();
+        let load_data = LoadData::new(LoadOrigin::WebDriver, url, None, None, None);
+        self.send_command(WebDriverCommandMsg::LoadUrl(target, load_data, sender))?;
+        Ok(json!({ "frameId": self.target_id(target) }))
+    }
+
+    // https://chromedevtools.github.io/devtools-protocol/tot/Page/#method-reload
+    fn handle_reload(&mut self, target: TopLevelBrowsingContextId) -> ProtocolResult<Value> {
+        let (sender, _) = ipc::channel().unwrap();
+        self.send_command(WebDriverCommandMsg::Refresh(target, sender))?;
+        Ok(json!({}))
+    }
+
+    // https://chromedevtools.github.io/devtools-protocol/tot/Page/#method-captureScreenshot
+    fn handle_capture_screenshot(
+        &mut self,
+        target: TopLevelBrowsingContextId,
+        params: &Map<String, Value>,
+    ) -> ProtocolResult<Value> {
+        let format = match params.get("format").and_then(Value::as_str) {
+            None | Some("png") => ImageOutputFormat::Png,
+            Some("jpeg") => {
+                let quality = params.get("quality").and_then(Value::as_u64).unwrap_or(80);
+                ImageOutputFormat::Jpeg(quality.min(100) as u8)
+            },
+            Some(_) => return Err(ProtocolError::invalid_params("Unsupported image format")),
+        };
+        let clip = match params.get("clip") {
+            Some(clip) => {
+                let dimension = |name: &str| {
+                    clip.get(name)
+                        .and_then(Value::as_f64)
+                        .map(|value| value as f32)
+                        .ok_or_else(|| ProtocolError::invalid_params("Invalid clip"))
+                };
+                Some(Rect::<f32, CSSPixel>::new(
+                    Point2D::new(dimension("x")?, dimension("y")?),
+                    Size2D::new(dimension("width")?, dimension("height")?),
+                ))
+            },
+            None => None,
+        };
+
+        let mut image = None;
+        let interval = Duration::from_secs(1);
+        for _ in 0..(SCREENSHOT_TIMEOUT.as_secs() / interval.as_secs()) {
+            let (sender, receiver) = ipc::channel().unwrap();
+            self.send_command(WebDriverCommandMsg::TakeScreenshot(target, clip, sender))?;
+            image = receiver
+                .recv()
+                .map_err(|_| ProtocolError::server_error("Servo is shutting down"))?;
+            if image.is_some() {
+                break;
+            }
+            thread::sleep(interval);
+        }
+        let image =
+            image.ok_or_else(|| ProtocolError::server_error("Taking screenshot timed out"))?;
+
+        // The compositor always sends RGB pixels.
+        if image.format != PixelFormat::RGB8 {
+            return Err(ProtocolError::server_error(
+                "Unexpected screenshot pixel format",
+            ));
+        }
+        let rgb = RgbImage::from_raw(image.width, image.height, image.bytes.to_vec())
+            .ok_or_else(|| ProtocolError::server_error("Invalid screenshot"))?;
+        let mut data = Vec::new();
+        DynamicImage::ImageRgb8(rgb)
+            .write_to(&mut data, format)
+            .map_err(|_| ProtocolError::server_error("Cannot encode screenshot"))?;
+        Ok(json!({ "data": base64::encode(&data) }))
+    }
+
+    // https://chromedevtools.github.io/devtools-protocol/tot/Runtime/#method-evaluate
+    fn handle_evaluate(
+        &mut self,
+        target: TopLevelBrowsingContextId,
+        params: &Map<String, Value>,
+    ) -> ProtocolResult<Value> {
+        let expression = params
+            .get("expression")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ProtocolError::invalid_params("Missing expression"))?;
+        let await_promise = params
+            .get("awaitPromise")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let return_by_value = params
+            .get("returnByValue")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+
+        // The expression is evaluated with an eval, and reports whether it
+        // completed normally along with its value or exception.
+        let expression = Value::from(expression).to_string();
+        let (sender, receiver) = ipc::channel().unwrap();
+        let command = if await_promise {
+            let script = format!(
+                "(function(callback) {{ \
+                     new Promise(function(resolve) {{ resolve(eval({})); }}).then( \
+                         function(value) {{ callback([true, value]); }}, \
+                         function(error) {{ callback([false, String(error)]); }}); \
+                 }})(window.webdriverCallback)",
+                expression
+            );
+            WebDriverScriptCommand::ExecuteAsyncScript(script, sender)
+        } else {
+            let script = format!(
+                "(function() {{ \
+                     try {{ return [true, eval({})]; }} \
+                     catch (error) {{ return [false, String(error)]; }} \
+                 }})()",
+                expression
+            );
+            WebDriverScriptCommand::ExecuteScript(script, sender)
+        };
+        self.send_command(WebDriverCommandMsg::ScriptCommand(
+            BrowsingContextId::from(target),
+            command,
+        ))?;
+
+        let completion = receiver
+            .recv()
+            .map_err(|_| ProtocolError::server_error("Servo is shutting down"))?;
+        let completion = match completion {
+            Ok(WebDriverJSValue::ArrayLike(mut completion)) if completion.len() == 2 => {
+                let value = completion.pop().unwrap();
+                match completion.pop().unwrap() {
+                    WebDriverJSValue::Boolean(normal) => Ok((normal, value)),
+                    _ => Err(WebDriverJSError::UnknownType),
+                }
+            },
+            Ok(_) => Err(WebDriverJSError::UnknownType),
+            Err(error) => Err(error),
+        };
+        match completion {
+            Ok((true, value)) => Ok(json!({ "result": remote_object(value, return_by_value) })),
+            Ok((false, error)) => {
+                let text = match error {
+                    WebDriverJSValue::String(text) => text,
+                    _ => String::new(),
+                };
+                let exception = json!({
+                    "type": "object",
+                    "subtype": "error",
+                    "className": "Error",
+                    "description": text,
+                });
+                Ok(json!({
+                    "result": exception,
+                    "exceptionDetails": {
+                        "exceptionId": 1,
+                        "text": "Uncaught",
+                        "lineNumber": 0,
+                        "columnNumber": 0,
+                        "exception": exception,
+                    },
+                }))
+            },
+            Err(WebDriverJSError::BrowsingContextNotFound) => Err(ProtocolError::server_error(
+                "Cannot find default execution context",
+            )),
+            Err(WebDriverJSError::UnknownType) => {
+                Err(ProtocolError::server_error("Unsupported return type"))
+            },
+            Err(error) => Err(ProtocolError::server_error(&format!(
+                "Evaluation failed ({:?})",
+                error
+            ))),
+        }
+    }
+
+    // https://chromedevtools.github.io/devtools-protocol/tot/DOM/#method-getDocument
+    fn handle_get_document(
+        &mut self,
+        target: TopLevelBrowsingContextId,
+        params: &Map<String, Value>,
+    ) -> ProtocolResult<Value> {
+        let depth = params.get("depth").and_then(Value::as_i64).unwrap_or(1);
+        let script = format!("{}({})", DESCRIBE_DOCUMENT, depth);
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.send_command(WebDriverCommandMsg::ScriptCommand(
+            BrowsingContextId::from(target),
+            WebDriverScriptCommand::ExecuteScript(script, sender),
+        ))?;
+        let document = receiver
+            .recv()
+            .map_err(|_| ProtocolError::server_error("Servo is shutting down"))?;
+        match document {
+            Ok(WebDriverJSValue::String(root)) => {
+                let root: Value = serde_json::from_str(&root)
+                    .map_err(|_| ProtocolError::server_error("Invalid document"))?;
+                Ok(json!({ "root": root }))
+            },
+            _ => Err(ProtocolError::server_error("Document is not available")),
+        }
+    }
+
+    fn handle_event(&mut self, event: DevtoolsEvent) {
+        match event {
+            DevtoolsEvent::NewGlobal(browsing_context_id, pipeline_id, info) => {
+                self.pipelines.insert(pipeline_id, browsing_context_id);
+                self.documents.insert(browsing_context_id, info.clone());
+                let is_new_target = !self
+                    .target_ids
+                    .keys()
+                    .any(|id| BrowsingContextId::from(*id) == browsing_context_id);
+                let target = match self
+                    .top_level_browsing_context_ids()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .find(|id| BrowsingContextId::from(*id) == browsing_context_id)
+                {
+                    Some(target) => target,
+                    None => return,
+                };
+                let target_info = self.target_info(target);
+                if is_new_target {
+                    let params = json!({ "targetInfo": target_info });
+                    self.emit_discovery("Target.targetCreated", params);
+                }
+                let params = json!({
+                    "frame": {
+                        "id": target_info["targetId"],
+                        "loaderId": pipeline_id.to_string(),
+                        "url": info.url.as_str(),
+                        "securityOrigin": info.url.origin().ascii_serialization(),
+                        "mimeType": "text/html",
+                    },
+                    "type": "Navigation",
+                });
+                self.emit_page_event(target, "Page.frameNavigated", params);
+            },
+            DevtoolsEvent::NavigationStarted(browsing_context_id, _) => {
+                let target = match self.target(browsing_context_id) {
+                    Some(target) => target,
+                    None => return,
+                };
+                let params = json!({ "frameId": self.target_id(target) });
+                self.emit_page_event(target, "Page.frameStartedLoading", params);
+            },
+            DevtoolsEvent::Load(browsing_context_id, pipeline_id, info) => {
+                self.pipelines.insert(pipeline_id, browsing_context_id);
+                self.documents.insert(browsing_context_id, info);
+                let target = match self.target(browsing_context_id) {
+                    Some(target) => target,
+                    None => return,
+                };
+                let params = json!({ "timestamp": timestamp() });
+                self.emit_page_event(target, "Page.loadEventFired", params);
+                let params = json!({ "frameId": self.target_id(target) });
+                self.emit_page_event(target, "Page.frameStoppedLoading", params);
+                let params = json!({ "targetInfo": self.target_info(target) });
+                self.emit_discovery("Target.targetInfoChanged", params);
+            },
+            DevtoolsEvent::TitleChanged(pipeline_id, title) => {
+                let browsing_context_id = match self.pipelines.get(&pipeline_id) {
+                    Some(browsing_context_id) => *browsing_context_id,
+                    None => return,
+                };
+                if let Some(document) = self.documents.get_mut(&browsing_context_id) {
+                    document.title = title;
+                }
+                if let Some(target) = self.target(browsing_context_id) {
+                    let params = json!({ "targetInfo": self.target_info(target) });
+                    self.emit_discovery("Target.targetInfoChanged", params);
+                }
+            },
+            DevtoolsEvent::RequestSent {
+                id,
+                pipeline_id,
+                url,
+                method,
+                headers,
+            } => {
+                self.requests.insert(id.clone(), url.clone());
+                let (target, document_url) = match self.pipeline_target(pipeline_id) {
+                    Some(target) => target,
+                    None => return,
+                };
+                let params = json!({
+                    "requestId": id,
+                    "loaderId": pipeline_id.to_string(),
+                    "documentURL": document_url,
+                    "request": {
+                        "url": url.as_str(),
+                        "method": method,
+                        "headers": headers_object(&headers),
+                    },
+                    "timestamp": timestamp(),
+                    "wallTime": timestamp(),
+                    "initiator": { "type": "other" },
+                    "type": "Other",
+                    "frameId": self.target_id(target),
+                });
+                self.emit_network_event(target, "Network.requestWillBeSent", params);
+            },
+            DevtoolsEvent::ResponseReceived {
+                id,
+                pipeline_id,
+                status,
+                headers,
+                body_size,
+            } => {
+                let url = self.requests.remove(&id);
+                let (target, _) = match self.pipeline_target(pipeline_id) {
+                    Some(target) => target,
+                    None => return,
+                };
+                let (status, status_text) = status.map_or((0, String::new()), |(code, text)| {
+                    (code, String::from_utf8_lossy(&text).into_owned())
+                });
+                let mime_type = headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                    .map_or("", |(_, value)| value.split(';').next().unwrap().trim());
+                let params = json!({
+                    "requestId": id,
+                    "loaderId": pipeline_id.to_string(),
+                    "timestamp": timestamp(),
+                    "type": "Other",
+                    "response": {
+                        "url": url.as_ref().map_or("", |url| url.as_str()),
+                        "status": status,
+                        "statusText": status_text,
+                        "headers": headers_object(&headers),
+                        "mimeType": mime_type,
+                    },
+                    "frameId": self.target_id(target),
+                });
+                self.emit_network_event(target, "Network.responseReceived", params);
+                let params = json!({
+                    "requestId": id,
+                    "timestamp": timestamp(),
+                    "encodedDataLength": body_size,
+                });
+                self.emit_network_event(target, "Network.loadingFinished", params);
+            },
+        }
+    }
+
+    fn session_mut(
+        &mut self,
+        connection: u32,
+        session_id: &Option<String>,
+    ) -> Option<&mut Session> {
+        self.clients
+            .get_mut(&connection)
+            .and_then(|client| client.sessions.get_mut(session_id))
+    }
+
+    fn send_command(&self, command: WebDriverCommandMsg) -> ProtocolResult<()> {
+        let constellation_chan = self
+            .constellation_chan
+            .as_ref()
+            .ok_or_else(|| ProtocolError::server_error("Servo is still starting up"))?;
+        constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(command))
+            .map_err(|_| ProtocolError::server_error("Servo is shutting down"))
+    }
+
+    fn top_level_browsing_context_ids(&self) -> ProtocolResult<Vec<TopLevelBrowsingContextId>> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.send_command(WebDriverCommandMsg::GetTopLevelBrowsingContexts(sender))?;
+        receiver
+            .recv()
+            .map_err(|_| ProtocolError::server_error("Servo is shutting down"))
+    }
+
+    /// The id of a target, given out the first time it is asked for. It is also
+    /// the id of the main frame of the target.
+    fn target_id(&mut self, id: TopLevelBrowsingContextId) -> String {
+        let targets = &mut self.targets;
+        self.target_ids
+            .entry(id)
+            .or_insert_with(|| {
+                let target_id = Uuid::new_v4().to_string();
+                targets.insert(target_id.clone(), id);
+                target_id
+            })
+            .clone()
+    }
+
+    /// The target a browsing context is the top-level browsing context of, if any.
+    fn target(&self, browsing_context_id: BrowsingContextId) -> Option<TopLevelBrowsingContextId> {
+        self.target_ids
+            .keys()
+            .find(|id| BrowsingContextId::from(**id) == browsing_context_id)
+            .cloned()
+    }
+
+    /// The target a pipeline is the top-level document of, with the URL of the document.
+    fn pipeline_target(
+        &self,
+        pipeline_id: PipelineId,
+    ) -> Option<(TopLevelBrowsingContextId, String)> {
+        let browsing_context_id = *self.pipelines.get(&pipeline_id)?;
+        let target = self.target(browsing_context_id)?;
+        let url = self
+            .documents
+            .get(&browsing_context_id)
+            .map_or(String::new(), |document| document.url.as_str().to_owned());
+        Some((target, url))
+    }
+
+    // https://chromedevtools.github.io/devtools-protocol/tot/Target/#type-TargetInfo
+    fn target_info(&mut self, id: TopLevelBrowsingContextId) -> Value {
+        let target_id = self.target_id(id);
+        let attached = self.clients.values().any(|client| {
+            client
+                .sessions
+                .iter()
+                .any(|(session_id, session)| session_id.is_some() && session.target == Some(id))
+        });
+        let (title, url) = match self.documents.get(&BrowsingContextId::from(id)) {
+            Some(document) => (document.title.as_str(), document.url.as_str()),
+            None => ("", "about:blank"),
+        };
+        json!({
+            "targetId": target_id,
+            "type": "page",
+            "title": title,
+            "url": url,
+            "attached": attached,
+            "canAccessOpener": false,
+        })
+    }
+
+    fn emit_page_event(&self, target: TopLevelBrowsingContextId, method: &str, params: Value) {
+        self.emit(
+            |session| session.page_events && session.target == Some(target),
+            method,
+            params,
+        );
+    }
+
+    fn emit_network_event(&self, target: TopLevelBrowsingContextId, method: &str, params: Value) {
+        self.emit(
+            |session| session.network_events && session.target == Some(target),
+            method,
+            params,
+        );
+    }
+
+    /// Send an event to every session for which `wanted` holds.
+    fn emit<F: Fn(&Session) -> bool>(&self, wanted: F, method: &str, params: Value) {
+        for client in self.clients.values() {
+            for (session_id, session) in &client.sessions {
+                if wanted(session) {
+                    send_event(client, session_id, method, params.clone());
+                }
+            }
+        }
+    }
+
+    /// Send a `Target` domain event to the clients that want to discover targets.
+    fn emit_discovery(&self, method: &str, params: Value) {
+        for client in self.clients.values() {
+            if client.discover_targets {
+                send_event(client, &None, method, params.clone());
+            }
+        }
+    }
+}
+
+fn send(sender: &ws::Sender, message: Value) {
+    if let Err(error) = sender.send(message.to_string()) {
+        warn!("Failed to send debugger message ({})", error);
+    }
+}
+
+fn send_event(client: &Client, session_id: &Option<String>, method: &str, params: Value) {
+    let mut event = json!({ "method": method, "params": params });
+    if let Some(session_id) = session_id {
+        event["sessionId"] = json!(session_id);
+    }
+    send(&client.sender, event);
+}
+
+fn error_response(id: Value, error: ProtocolError) -> Value {
+    json!({
+        "id": id,
+        "error": {
+            "code": error.code,
+            "message": error.message,
+        },
+    })
+}
+
+/// The current time in seconds since the epoch.
+fn timestamp() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+/// <https://chromedevtools.github.io/devtools-protocol/tot/Network/#type-Headers>
+fn headers_object(headers: &[(String, String)]) -> Map<String, Value> {
+    let mut object = Map::new();
+    for (name, value) in headers {
+        let value = match object.get(name).and_then(Value::as_str) {
+            // Repeated headers are joined with newlines.
+            Some(previous) => format!("{}\n{}", previous, value),
+            None => value.clone(),
+        };
+        object.insert(name.clone(), Value::from(value));
+    }
+    object
+}
+
+/// <https://chromedevtools.github.io/devtools-protocol/tot/Runtime/#type-RemoteObject>
+fn remote_object(value: WebDriverJSValue, by_value: bool) -> Value {
+    match value {
+        WebDriverJSValue::Undefined => json!({ "type": "undefined" }),
+        WebDriverJSValue::Null => json!({ "type": "object", "subtype": "null", "value": null }),
+        WebDriverJSValue::Boolean(value) => json!({ "type": "boolean", "value": value }),
+        WebDriverJSValue::Number(value) => {
+            let unserializable = if value.is_nan() {
+                Some("NaN")
+            } else if value == 0. && value.is_sign_negative() {
+                Some("-0")
+            } else if value.is_infinite() {
+                Some(if value > 0. { "Infinity" } else { "-Infinity" })
+            } else {
+                None
+            };
+            match unserializable {
+                Some(description) => json!({
+                    "type": "number",
+                    "unserializableValue": description,
+                    "description": description,
+                }),
+                None => json!({
+                    "type": "number",
+                    "value": value,
+                    "description": value.to_string(),
+                }),
+            }
+        },
+        WebDriverJSValue::String(value) => json!({ "type": "string", "value": value }),
+        WebDriverJSValue::Element(element) => json!({
+            "type": "object",
+            "subtype": "node",
+            "className": "Element",
+            "description": element.to_string(),
+        }),
+        WebDriverJSValue::Frame(_) | WebDriverJSValue::Window(_) => json!({
+            "type": "object",
+            "className": "Window",
+            "description": "Window",
+        }),
+        WebDriverJSValue::ArrayLike(values) => {
+            let mut object = json!({
+                "type": "object",
+                "subtype": "array",
+                "className": "Array",
+                "description": format!("Array({})", values.len()),
+            });
+            if by_value {
+                object["value"] = json_value(WebDriverJSValue::ArrayLike(values));
+            }
+            object
+        },
+        WebDriverJSValue::Object(properties) => {
+            let mut object = json!({
+                "type": "object",
+                "className": "Object",
+                "description": "Object",
+            });
+            if by_value {
+                object["value"] = json_value(WebDriverJSValue::Object(properties));
+            }
+            object
+        },
+    }
+}
+
+/// The JSON value of a JS value, for `returnByValue`.
+fn json_value(value: WebDriverJSValue) -> Value {
+    match value {
+        WebDriverJSValue::Undefined | WebDriverJSValue::Null => Value::Null,
+        WebDriverJSValue::Boolean(value) => Value::from(value),
+        WebDriverJSValue::Number(value) => json!(value),
+        WebDriverJSValue::String(value) => Value::from(value),
+        WebDriverJSValue::Element(element) => Value::from(element.to_string()),
+        WebDriverJSValue::Frame(_) | WebDriverJSValue::Window(_) => json!({}),
+        WebDriverJSValue::ArrayLike(values) => {
+            Value::Array(values.into_iter().map(json_value).collect())
+        },
+        WebDriverJSValue::Object(properties) => Value::Object(
+            properties
+                .into_iter()
+                .map(|(key, value)| (key, json_value(value)))
+                .collect(),
+        ),
+    }
+}
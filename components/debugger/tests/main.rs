@@ -0,0 +1,363 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use compositing::ConstellationMsg;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use devtools_traits::{DevtoolsControlMsg, DevtoolsPageInfo};
+use devtools_traits::{NavigationState, ScriptToDevtoolsControlMsg};
+use msg::constellation_msg::{BrowsingContextId, PipelineId, TopLevelBrowsingContextId};
+use msg::constellation_msg::{PipelineNamespace, PipelineNamespaceId};
+use script_traits::webdriver_msg::{LoadStatus, WebDriverJSValue, WebDriverScriptCommand};
+use script_traits::WebDriverCommandMsg;
+use serde_json::{json, Value};
+use servo_url::ServoUrl;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+use ws::{CloseCode, Handler, Handshake};
+
+/// A constellation with a single top-level browsing context, answering the
+/// commands of the debugger server.
+fn fake_constellation(
+    top_level_browsing_context_id: TopLevelBrowsingContextId,
+    loads: Sender<ServoUrl>,
+) -> Sender<ConstellationMsg> {
+    let (sender, receiver) = unbounded();
+    thread::spawn(move || {
+        while let Ok(ConstellationMsg::WebDriverCommand(command)) = receiver.recv() {
+            match command {
+                WebDriverCommandMsg::GetTopLevelBrowsingContexts(reply) => {
+                    reply.send(vec![top_level_browsing_context_id]).unwrap();
+                },
+                WebDriverCommandMsg::LoadUrl(_, load_data, reply) => {
+                    loads.send(load_data.url).unwrap();
+                    let _ = reply.send(LoadStatus::LoadComplete);
+                },
+                WebDriverCommandMsg::ScriptCommand(
+                    _,
+                    WebDriverScriptCommand::ExecuteScript(script, reply),
+                ) => {
+                    // Pretend to evaluate the expressions the tests use.
+                    let result = if script.contains("\"1 + 2\"") {
+                        WebDriverJSValue::ArrayLike(vec![
+                            WebDriverJSValue::Boolean(true),
+                            WebDriverJSValue::Number(3.),
+                        ])
+                    } else if script.contains("JSON.stringify") {
+                        WebDriverJSValue::String(
+                            json!({ "nodeId": 1, "nodeType": 9, "nodeName": "#document" })
+                                .to_string(),
+                        )
+                    } else {
+                        WebDriverJSValue::ArrayLike(vec![
+                            WebDriverJSValue::Boolean(false),
+                            WebDriverJSValue::String("ReferenceError".to_owned()),
+                        ])
+                    };
+                    reply.send(Ok(result)).unwrap();
+                },
+                _ => {},
+            }
+        }
+    });
+    sender
+}
+
+struct Browser {
+    devtools_chan: Sender<DevtoolsControlMsg>,
+    server: debugger::Sender,
+    loads: Receiver<ServoUrl>,
+    browsing_context_id: BrowsingContextId,
+    pipeline_id: PipelineId,
+}
+
+impl Browser {
+    fn start() -> Browser {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let top_level_browsing_context_id = TopLevelBrowsingContextId::new();
+        let (devtools_chan, events) = debugger::tap_devtools_messages(None);
+        let server = debugger::start_server(0, "Servo".to_owned(), events);
+        let (loads_sender, loads) = unbounded();
+        let constellation_chan = fake_constellation(top_level_browsing_context_id, loads_sender);
+        debugger::connect_constellation(&server, constellation_chan);
+        Browser {
+            devtools_chan,
+            server,
+            loads,
+            browsing_context_id: BrowsingContextId::from(top_level_browsing_context_id),
+            pipeline_id: PipelineId::new(),
+        }
+    }
+
+    fn address(&self) -> SocketAddr {
+        self.server.local_addr()
+    }
+
+    fn send_to_devtools(&self, message: ScriptToDevtoolsControlMsg) {
+        self.devtools_chan
+            .send(DevtoolsControlMsg::FromScript(message))
+            .unwrap();
+    }
+
+    fn page_info(url: &str) -> DevtoolsPageInfo {
+        DevtoolsPageInfo {
+            title: "Example".to_owned(),
+            url: ServoUrl::parse(url).unwrap(),
+        }
+    }
+
+    fn load_document(&self, url: &str) {
+        let (sender, _receiver) = ipc_channel::ipc::channel().unwrap();
+        self.send_to_devtools(ScriptToDevtoolsControlMsg::NewGlobal(
+            (self.browsing_context_id, self.pipeline_id, None),
+            sender,
+            Browser::page_info(url),
+        ));
+        self.send_to_devtools(ScriptToDevtoolsControlMsg::Navigate(
+            self.browsing_context_id,
+            NavigationState::Stop(self.pipeline_id, Browser::page_info(url)),
+        ));
+    }
+
+    /// Send a GET request for one of the discovery endpoints, and return the
+    /// response status line and body.
+    fn get(&self, path: &str) -> (String, String) {
+        let mut stream = TcpStream::connect(self.address()).unwrap();
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            path,
+            self.address()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response.lines().next().unwrap().to_owned();
+        let body = response.splitn(2, "\r\n\r\n").nth(1).unwrap().to_owned();
+        (status, body)
+    }
+}
+
+struct ClientHandler {
+    out: ws::Sender,
+    opened: Sender<ws::Sender>,
+    messages: Sender<Value>,
+}
+
+impl Handler for ClientHandler {
+    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
+        self.opened.send(self.out.clone()).unwrap();
+        Ok(())
+    }
+
+    fn on_message(&mut self, message: ws::Message) -> ws::Result<()> {
+        let message = serde_json::from_str(&message.into_text()?).unwrap();
+        self.messages.send(message).unwrap();
+        Ok(())
+    }
+
+    fn on_close(&mut self, _: CloseCode, _: &str) {}
+}
+
+struct Client {
+    out: ws::Sender,
+    messages: Receiver<Value>,
+    next_id: RefCell<u64>,
+    events: RefCell<VecDeque<Value>>,
+}
+
+impl Client {
+    fn connect(url: String) -> Client {
+        let (opened, opened_receiver) = unbounded();
+        let (messages_sender, messages) = unbounded();
+        thread::spawn(move || {
+            ws::connect(url, move |out| ClientHandler {
+                out,
+                opened: opened.clone(),
+                messages: messages_sender.clone(),
+            })
+            .unwrap();
+        });
+        Client {
+            out: opened_receiver.recv().unwrap(),
+            messages,
+            next_id: RefCell::new(0),
+            events: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    fn next_message(&self) -> Value {
+        self.messages
+            .recv_timeout(Duration::from_secs(5))
+            .expect("No message from the debugger server")
+    }
+
+    /// Send a command and return its response, keeping the events received
+    /// in the meantime.
+    fn command(&self, session_id: Option<&Value>, method: &str, params: Value) -> Value {
+        *self.next_id.borrow_mut() += 1;
+        let id = *self.next_id.borrow();
+        let mut command = json!({ "id": id, "method": method, "params": params });
+        if let Some(session_id) = session_id {
+            command["sessionId"] = session_id.clone();
+        }
+        self.out.send(command.to_string()).unwrap();
+        loop {
+            let message = self.next_message();
+            if message.get("id").is_none() {
+                self.events.borrow_mut().push_back(message);
+            } else {
+                assert_eq!(message["id"], id);
+                return message;
+            }
+        }
+    }
+
+    fn result(&self, session_id: Option<&Value>, method: &str, params: Value) -> Value {
+        let response = self.command(session_id, method, params);
+        assert!(response.get("error").is_none(), "{}", response);
+        response["result"].clone()
+    }
+
+    fn event(&self) -> Value {
+        let event = match self.events.borrow_mut().pop_front() {
+            Some(event) => event,
+            None => self.next_message(),
+        };
+        assert!(event.get("method").is_some(), "{}", event);
+        event
+    }
+}
+
+#[test]
+fn test_discovery_endpoints() {
+    let browser = Browser::start();
+    let (status, body) = browser.get("/json/version");
+    assert!(status.contains("200"), "{}", status);
+    let version: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(version["Browser"], "Servo");
+    assert_eq!(
+        version["webSocketDebuggerUrl"],
+        format!("ws://{}/devtools/browser", browser.address())
+    );
+
+    let (_, body) = browser.get("/json/list");
+    let targets: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(targets[0]["type"], "page");
+    let url = targets[0]["webSocketDebuggerUrl"].as_str().unwrap();
+    assert!(url.starts_with(&format!("ws://{}/devtools/page/", browser.address())));
+
+    let (status, _) = browser.get("/json/missing");
+    assert!(status.contains("404"), "{}", status);
+}
+
+#[test]
+fn test_errors() {
+    let browser = Browser::start();
+    let client = Client::connect(format!("ws://{}/devtools/browser", browser.address()));
+    assert_eq!(
+        client.result(None, "Browser.getVersion", json!({}))["product"],
+        "Servo"
+    );
+
+    let response = client.command(None, "Page.print", json!({}));
+    assert_eq!(response["error"]["code"], -32601);
+
+    // The browser endpoint is not attached to any page.
+    let response = client.command(None, "Runtime.evaluate", json!({ "expression": "1 + 2" }));
+    assert_eq!(response["error"]["code"], -32000);
+
+    let response = client.command(Some(&json!("missing")), "Page.enable", json!({}));
+    assert_eq!(response["error"]["code"], -32001);
+    assert_eq!(response["sessionId"], "missing");
+}
+
+#[test]
+fn test_page_endpoint() {
+    let browser = Browser::start();
+    let (_, body) = browser.get("/json/list");
+    let targets: Value = serde_json::from_str(&body).unwrap();
+    let client = Client::connect(
+        targets[0]["webSocketDebuggerUrl"]
+            .as_str()
+            .unwrap()
+            .to_owned(),
+    );
+
+    let result = client.result(None, "Runtime.evaluate", json!({ "expression": "1 + 2" }));
+    assert_eq!(
+        result["result"],
+        json!({ "type": "number", "value": 3., "description": "3" })
+    );
+
+    let result = client.result(None, "Runtime.evaluate", json!({ "expression": "missing" }));
+    assert_eq!(
+        result["exceptionDetails"]["exception"]["description"],
+        "ReferenceError"
+    );
+
+    let result = client.result(None, "DOM.getDocument", json!({}));
+    assert_eq!(result["root"]["nodeName"], "#document");
+
+    let result = client.result(
+        None,
+        "Page.navigate",
+        json!({ "url": "http://example.org/" }),
+    );
+    assert_eq!(result["frameId"], targets[0]["id"]);
+    assert_eq!(
+        browser.loads.recv().unwrap().as_str(),
+        "http://example.org/"
+    );
+}
+
+#[test]
+fn test_attached_session_events() {
+    let browser = Browser::start();
+    let client = Client::connect(format!("ws://{}/devtools/browser", browser.address()));
+    client.result(
+        None,
+        "Target.setDiscoverTargets",
+        json!({ "discover": true }),
+    );
+    let event = client.event();
+    assert_eq!(event["method"], "Target.targetCreated");
+    let target_id = event["params"]["targetInfo"]["targetId"].clone();
+
+    let result = client.result(
+        None,
+        "Target.attachToTarget",
+        json!({ "targetId": target_id, "flatten": true }),
+    );
+    let session_id = result["sessionId"].clone();
+    let event = client.event();
+    assert_eq!(event["method"], "Target.attachedToTarget");
+    assert_eq!(event["params"]["sessionId"], session_id);
+
+    let response = client.command(Some(&session_id), "Page.enable", json!({}));
+    assert_eq!(response["sessionId"], session_id);
+
+    browser.load_document("http://example.com/");
+    let event = client.event();
+    assert_eq!(event["method"], "Page.frameNavigated");
+    assert_eq!(event["sessionId"], session_id);
+    assert_eq!(event["params"]["frame"]["id"], target_id);
+    assert_eq!(event["params"]["frame"]["url"], "http://example.com/");
+    assert_eq!(client.event()["method"], "Page.loadEventFired");
+    assert_eq!(client.event()["method"], "Page.frameStoppedLoading");
+    let event = client.event();
+    assert_eq!(event["method"], "Target.targetInfoChanged");
+    assert_eq!(event["params"]["targetInfo"]["title"], "Example");
+
+    client.result(
+        None,
+        "Target.detachFromTarget",
+        json!({ "sessionId": session_id }),
+    );
+    let response = client.command(Some(&session_id), "Page.enable", json!({}));
+    assert_eq!(response["error"]["code"], -32001);
+}
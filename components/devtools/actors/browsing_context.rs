@@ -10,6 +10,7 @@
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
 use crate::actors::emulation::EmulationActor;
 use crate::actors::inspector::InspectorActor;
+use crate::actors::memory::MemoryActor;
 use crate::actors::performance::PerformanceActor;
 use crate::actors::profiler::ProfilerActor;
 use crate::actors::root::RootActor;
@@ -21,10 +22,12 @@ use devtools_traits::DevtoolScriptControlMsg::{self, WantsLiveNotifications};
 use devtools_traits::DevtoolsPageInfo;
 use devtools_traits::NavigationState;
 use ipc_channel::ipc::IpcSender;
-use msg::constellation_msg::{BrowsingContextId, PipelineId};
+use msg::constellation_msg::{BrowsingContextId, PipelineId, SamplerControlMsg};
+use profile_traits::mem::ProfilerChan;
 use serde_json::{Map, Value};
 use std::cell::{Cell, RefCell};
 use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
 
 #[derive(Serialize)]
 struct BrowsingContextTraits {
@@ -102,10 +105,10 @@ pub struct BrowsingContextActorMsg {
     profilerActor: String,
     performanceActor: String,
     styleSheetsActor: String,
+    memoryActor: String,
     traits: BrowsingContextTraits,
     // Part of the official protocol, but not yet implemented.
     /*storageActor: String,
-    memoryActor: String,
     framerateActor: String,
     reflowActor: String,
     cssPropertiesActor: String,
@@ -129,6 +132,7 @@ pub struct BrowsingContextActor {
     pub profiler: String,
     pub performance: String,
     pub styleSheets: String,
+    pub memory: String,
     pub thread: String,
     pub streams: RefCell<Vec<TcpStream>>,
     pub browsing_context_id: BrowsingContextId,
@@ -239,6 +243,8 @@ impl BrowsingContextActor {
         page_info: DevtoolsPageInfo,
         pipeline: PipelineId,
         script_sender: IpcSender<DevtoolScriptControlMsg>,
+        mem_profiler_chan: ProfilerChan,
+        samplers: Arc<Mutex<Vec<IpcSender<SamplerControlMsg>>>>,
         actors: &mut ActorRegistry,
     ) -> BrowsingContextActor {
         let emulation = EmulationActor::new(actors.new_name("emulation"));
@@ -254,16 +260,30 @@ impl BrowsingContextActor {
             browsing_context: name.clone(),
         };
 
-        let timeline =
-            TimelineActor::new(actors.new_name("timeline"), pipeline, script_sender.clone());
-
-        let profiler = ProfilerActor::new(actors.new_name("profiler"));
+        let timeline = TimelineActor::new(
+            actors.new_name("timeline"),
+            pipeline,
+            script_sender.clone(),
+            mem_profiler_chan.clone(),
+        );
+
+        let profiler = ProfilerActor::new(
+            actors.new_name("profiler"),
+            script_sender.clone(),
+            name.clone(),
+            samplers,
+        );
         let performance = PerformanceActor::new(actors.new_name("performance"));
+        let memory = MemoryActor::new(actors.new_name("memory"), mem_profiler_chan);
 
         // the strange switch between styleSheets and stylesheets is due
         // to an inconsistency in devtools. See Bug #1498893 in bugzilla
-        let styleSheets = StyleSheetsActor::new(actors.new_name("stylesheets"));
-        let thread = ThreadActor::new(actors.new_name("context"));
+        let styleSheets = StyleSheetsActor::new(
+            actors.new_name("stylesheets"),
+            script_sender.clone(),
+            name.clone(),
+        );
+        let thread = ThreadActor::new(actors.new_name("context"), script_sender.clone(), pipeline);
 
         let DevtoolsPageInfo { title, url } = page_info;
         let target = BrowsingContextActor {
@@ -278,6 +298,7 @@ impl BrowsingContextActor {
             profiler: profiler.name(),
             performance: performance.name(),
             styleSheets: styleSheets.name(),
+            memory: memory.name(),
             thread: thread.name(),
             streams: RefCell::new(Vec::new()),
             browsing_context_id: id,
@@ -290,6 +311,7 @@ impl BrowsingContextActor {
         actors.register(Box::new(profiler));
         actors.register(Box::new(performance));
         actors.register(Box::new(styleSheets));
+        actors.register(Box::new(memory));
         actors.register(Box::new(thread));
 
         let root = actors.find_mut::<RootActor>("root");
@@ -316,6 +338,7 @@ impl BrowsingContextActor {
             profilerActor: self.profiler.clone(),
             performanceActor: self.performance.clone(),
             styleSheetsActor: self.styleSheets.clone(),
+            memoryActor: self.memory.clone(),
         }
     }
 
@@ -0,0 +1,168 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Liberally derived from the [Firefox JS implementation]
+//! (https://searchfox.org/mozilla-central/source/devtools/server/actors/frame.js).
+//! Represents a stack frame of a paused debuggee, along with its scope chain.
+
+use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::actors::object::value_to_grip;
+use crate::protocol::JsonPacketStream;
+use devtools_traits::{EnvironmentInfo, FrameInfo};
+use serde_json::{Map, Value};
+use std::net::TcpStream;
+
+#[derive(Clone, Serialize)]
+pub struct FrameMsg {
+    actor: String,
+    depth: u32,
+    #[serde(rename = "type")]
+    type_: String,
+    displayName: Option<String>,
+    #[serde(rename = "where")]
+    where_: FrameLocation,
+}
+
+#[derive(Clone, Serialize)]
+struct FrameLocation {
+    actor: String,
+    line: u32,
+    column: u32,
+}
+
+#[derive(Serialize)]
+struct EnvironmentMsg {
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    function: Option<FunctionMsg>,
+    bindings: BindingsMsg,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<Box<EnvironmentMsg>>,
+}
+
+#[derive(Serialize)]
+struct FunctionMsg {
+    displayName: String,
+}
+
+#[derive(Serialize)]
+struct BindingsMsg {
+    arguments: Vec<Value>,
+    variables: Map<String, Value>,
+}
+
+#[derive(Serialize)]
+struct BindingMsg {
+    value: Value,
+}
+
+#[derive(Serialize)]
+struct GetEnvironmentReply<'a> {
+    from: String,
+    #[serde(flatten)]
+    environment: Option<&'a EnvironmentMsg>,
+}
+
+pub struct FrameActor {
+    name: String,
+    frame: FrameMsg,
+    environment: Option<EnvironmentMsg>,
+}
+
+impl Actor for FrameActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(
+        &self,
+        _registry: &ActorRegistry,
+        msg_type: &str,
+        _msg: &Map<String, Value>,
+        stream: &mut TcpStream,
+    ) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "getEnvironment" => {
+                let msg = GetEnvironmentReply {
+                    from: self.name(),
+                    environment: self.environment.as_ref(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
+
+impl FrameActor {
+    /// Create an actor for the frame at the given depth of a paused debuggee. The bindings
+    /// of its scopes are converted to grips eagerly, since the debuggee only sends a snapshot.
+    pub fn new(
+        registry: &ActorRegistry,
+        depth: u32,
+        frame: FrameInfo,
+        source_actor: String,
+    ) -> FrameActor {
+        let name = registry.new_name("frame");
+        let environment = frame
+            .environments
+            .into_iter()
+            .rev()
+            .fold(None, |parent, environment| {
+                Some(EnvironmentMsg::new(registry, environment, parent))
+            });
+        FrameActor {
+            frame: FrameMsg {
+                actor: name.clone(),
+                depth: depth,
+                type_: frame.type_,
+                displayName: frame.display_name,
+                where_: FrameLocation {
+                    actor: source_actor,
+                    line: frame.line,
+                    column: frame.column,
+                },
+            },
+            name: name,
+            environment: environment,
+        }
+    }
+
+    pub fn encodable(&self) -> FrameMsg {
+        self.frame.clone()
+    }
+}
+
+impl EnvironmentMsg {
+    fn new(
+        registry: &ActorRegistry,
+        environment: EnvironmentInfo,
+        parent: Option<EnvironmentMsg>,
+    ) -> EnvironmentMsg {
+        let variables = environment
+            .bindings
+            .into_iter()
+            .map(|(name, value)| {
+                let binding = BindingMsg {
+                    value: value_to_grip(value, registry),
+                };
+                (name, serde_json::to_value(binding).unwrap())
+            })
+            .collect();
+        EnvironmentMsg {
+            type_: environment.type_,
+            function: environment
+                .function_name
+                .map(|name| FunctionMsg { displayName: name }),
+            bindings: BindingsMsg {
+                arguments: vec![],
+                variables: variables,
+            },
+            parent: parent.map(Box::new),
+        }
+    }
+}
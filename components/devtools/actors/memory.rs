@@ -2,11 +2,20 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+//! Liberally derived from the [Firefox JS implementation]
+//! (https://searchfox.org/mozilla-central/source/devtools/server/actors/memory.js).
+//! Measures the memory used by the browser from the reports of the memory profiler.
+
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::protocol::JsonPacketStream;
+use ipc_channel::ipc;
+use profile_traits::mem::{ProfilerChan, ProfilerMsg, Report};
 use serde_json::{Map, Value};
+use std::cell::Cell;
 use std::net::TcpStream;
+use time::precise_time_ns;
 
-#[derive(Serialize)]
+#[derive(Default, Serialize)]
 pub struct TimelineMemoryReply {
     jsObjectSize: u64,
     jsStringSize: u64,
@@ -19,8 +28,75 @@ pub struct TimelineMemoryReply {
     nonJSMilliseconds: f64,
 }
 
+#[derive(Serialize)]
+struct MeasureReply {
+    from: String,
+    #[serde(flatten)]
+    measurement: TimelineMemoryReply,
+}
+
+#[derive(Serialize)]
+struct AttachedReply {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+}
+
+#[derive(Serialize)]
+struct GetStateReply {
+    from: String,
+    state: String,
+}
+
+#[derive(Serialize)]
+struct TakeCensusReply {
+    from: String,
+    report: ReportTree,
+}
+
+/// The explicit allocations of the browser, grouped by the paths of their reports.
+#[derive(Serialize)]
+struct ReportTree {
+    name: String,
+    bytes: u64,
+    children: Vec<ReportTree>,
+}
+
+impl ReportTree {
+    fn new(name: String) -> ReportTree {
+        ReportTree {
+            name: name,
+            bytes: 0,
+            children: vec![],
+        }
+    }
+
+    fn insert(&mut self, path: &[String], size: u64) {
+        self.bytes += size;
+        if let Some((name, rest)) = path.split_first() {
+            let index = match self.children.iter().position(|child| child.name == *name) {
+                Some(index) => index,
+                None => {
+                    self.children.push(ReportTree::new(name.clone()));
+                    self.children.len() - 1
+                },
+            };
+            self.children[index].insert(rest, size);
+        }
+    }
+
+    fn sort(&mut self) {
+        self.children.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+        for child in &mut self.children {
+            child.sort();
+        }
+    }
+}
+
 pub struct MemoryActor {
     pub name: String,
+    mem_profiler_chan: ProfilerChan,
+    attached: Cell<bool>,
 }
 
 impl Actor for MemoryActor {
@@ -31,38 +107,111 @@ impl Actor for MemoryActor {
     fn handle_message(
         &self,
         _registry: &ActorRegistry,
-        _msg_type: &str,
+        msg_type: &str,
         _msg: &Map<String, Value>,
-        _stream: &mut TcpStream,
+        stream: &mut TcpStream,
     ) -> Result<ActorMessageStatus, ()> {
-        Ok(ActorMessageStatus::Ignored)
+        Ok(match msg_type {
+            "attach" | "detach" => {
+                let attached = msg_type == "attach";
+                self.attached.set(attached);
+                let msg = AttachedReply {
+                    from: self.name(),
+                    type_: if attached { "attached" } else { "detached" }.to_owned(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "getState" => {
+                let state = if self.attached.get() {
+                    "attached"
+                } else {
+                    "detached"
+                };
+                let msg = GetStateReply {
+                    from: self.name(),
+                    state: state.to_owned(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "measure" => {
+                let msg = MeasureReply {
+                    from: self.name(),
+                    measurement: self.measure(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "takeCensus" => {
+                let mut report = ReportTree::new("explicit".to_owned());
+                for entry in self.explicit_reports() {
+                    report.insert(&entry.path[1..], entry.size as u64);
+                }
+                report.sort();
+                let msg = TakeCensusReply {
+                    from: self.name(),
+                    report: report,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
     }
 }
 
 impl MemoryActor {
     /// return name of actor
-    pub fn create(registry: &ActorRegistry) -> String {
+    pub fn create(registry: &ActorRegistry, mem_profiler_chan: ProfilerChan) -> String {
         let actor_name = registry.new_name("memory");
-        let actor = MemoryActor {
-            name: actor_name.clone(),
-        };
+        let actor = MemoryActor::new(actor_name.clone(), mem_profiler_chan);
 
         registry.register_later(Box::new(actor));
         actor_name
     }
 
+    pub fn new(name: String, mem_profiler_chan: ProfilerChan) -> MemoryActor {
+        MemoryActor {
+            name: name,
+            mem_profiler_chan: mem_profiler_chan,
+            attached: Cell::new(false),
+        }
+    }
+
+    /// The reports of the explicit allocations of the browser, which are empty if the memory
+    /// profiler isn't running.
+    fn explicit_reports(&self) -> Vec<Report> {
+        let (tx, rx) = ipc::channel().unwrap();
+        self.mem_profiler_chan.send(ProfilerMsg::Report(tx));
+        let mut reports = rx.recv().unwrap_or_default();
+        reports.retain(|report| report.path.first().map_or(false, |seg| seg == "explicit"));
+        reports
+    }
+
     pub fn measure(&self) -> TimelineMemoryReply {
-        //TODO:
-        TimelineMemoryReply {
-            jsObjectSize: 1,
-            jsStringSize: 1,
-            jsOtherSize: 1,
-            domSize: 1,
-            styleSize: 1,
-            otherSize: 1,
-            totalSize: 1,
-            jsMilliseconds: 1.1,
-            nonJSMilliseconds: 1.1,
+        let start = precise_time_ns();
+        let mut reply = TimelineMemoryReply::default();
+        for report in self.explicit_reports() {
+            let size = report.size as u64;
+            reply.totalSize += size;
+            // The reports of a script thread are found under "explicit/url(...)/js", and those
+            // of a layout thread under "explicit/url(...)/layout-thread". Nothing reports the
+            // size of the DOM or of JS strings yet.
+            let path: Vec<&str> = report.path.iter().map(|seg| &**seg).collect();
+            match path.get(2..) {
+                Some(["js", "gc-heap", "used"]) => reply.jsObjectSize += size,
+                Some(["js", ..]) => reply.jsOtherSize += size,
+                Some(["layout-thread", "stylist"]) => reply.styleSize += size,
+                _ => reply.otherSize += size,
+            }
         }
+        // All the reports are collected at once by the memory profiler, JS ones included.
+        reply.nonJSMilliseconds = (precise_time_ns() - start) as f64 / 1_000_000.;
+        reply
     }
 }
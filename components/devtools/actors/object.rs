@@ -3,7 +3,8 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
-use serde_json::{Map, Value};
+use devtools_traits::EvaluateJSReply;
+use serde_json::{Map, Number, Value};
 use std::net::TcpStream;
 
 pub struct ObjectActor {
@@ -44,3 +45,46 @@ impl ObjectActor {
         }
     }
 }
+
+/// Convert a value serialized by script into the grip the client expects, creating an
+/// object actor for it if needed.
+pub fn value_to_grip(value: EvaluateJSReply, registry: &ActorRegistry) -> Value {
+    let mut m = Map::new();
+    match value {
+        EvaluateJSReply::VoidValue => {
+            m.insert("type".to_owned(), Value::String("undefined".to_owned()));
+        },
+        EvaluateJSReply::NullValue => {
+            m.insert("type".to_owned(), Value::String("null".to_owned()));
+        },
+        EvaluateJSReply::BooleanValue(val) => return Value::Bool(val),
+        EvaluateJSReply::NumberValue(val) => {
+            if val.is_nan() {
+                m.insert("type".to_owned(), Value::String("NaN".to_owned()));
+            } else if val.is_infinite() {
+                if val < 0. {
+                    m.insert("type".to_owned(), Value::String("-Infinity".to_owned()));
+                } else {
+                    m.insert("type".to_owned(), Value::String("Infinity".to_owned()));
+                }
+            } else if val == 0. && val.is_sign_negative() {
+                m.insert("type".to_owned(), Value::String("-0".to_owned()));
+            } else {
+                return Value::Number(Number::from_f64(val).unwrap());
+            }
+        },
+        EvaluateJSReply::StringValue(s) => return Value::String(s),
+        EvaluateJSReply::ActorValue { class, uuid } => {
+            //TODO: make initial ActorValue message include these properties?
+            let actor = ObjectActor::new(registry, uuid);
+
+            m.insert("type".to_owned(), Value::String("object".to_owned()));
+            m.insert("class".to_owned(), Value::String(class));
+            m.insert("actor".to_owned(), Value::String(actor));
+            m.insert("extensible".to_owned(), Value::Bool(true));
+            m.insert("frozen".to_owned(), Value::Bool(false));
+            m.insert("sealed".to_owned(), Value::Bool(false));
+        },
+    }
+    Value::Object(m)
+}
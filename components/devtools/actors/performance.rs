@@ -67,8 +67,8 @@ impl Actor for PerformanceActor {
                             withMarkers: true,
                             withMemory: true,
                             withTicks: true,
-                            withAllocations: true,
-                            withJITOptimizations: true,
+                            withAllocations: false,
+                            withJITOptimizations: false,
                         },
                     },
                 };
@@ -2,12 +2,86 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+//! Liberally derived from the [Firefox JS implementation]
+//! (https://searchfox.org/mozilla-central/source/devtools/server/actors/perf.js).
+//! A sampling profiler of the JS stacks of a browsing context, and of the native stacks of
+//! its script and layout threads, streaming the samples to the client as they are taken.
+
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::actors::browsing_context::BrowsingContextActor;
+use crate::protocol::JsonPacketStream;
+use devtools_traits::{DebuggerCommand, DevtoolScriptControlMsg, JSSample};
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use msg::constellation_msg::{PipelineId, SampledStack, SamplerControlMsg};
 use serde_json::{Map, Value};
+use std::cell::{Cell, RefCell};
 use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+static DEFAULT_SAMPLING_INTERVAL: u64 = 10; //ms
+static SAMPLES_PULL_TIMEOUT: u64 = 200; //ms
+
+#[derive(Serialize)]
+struct StartedReply {
+    from: String,
+    started: bool,
+}
+
+#[derive(Serialize)]
+struct StoppedReply {
+    from: String,
+    stopped: bool,
+}
+
+#[derive(Serialize)]
+struct IsActiveReply {
+    from: String,
+    isActive: bool,
+}
+
+#[derive(Serialize)]
+struct GetFeaturesReply {
+    from: String,
+    features: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+struct SamplesReply {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    samples: Vec<SampleMsg>,
+}
+
+#[derive(Serialize)]
+struct SampleMsg {
+    thread: String,
+    /// Milliseconds since the profiler was started.
+    time: f64,
+    /// The sampled frames, youngest first.
+    frames: Vec<SampledFrameMsg>,
+}
+
+#[derive(Serialize)]
+struct SampledFrameMsg {
+    name: Option<String>,
+    url: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+}
 
 pub struct ProfilerActor {
     name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    browsing_context: String,
+    /// The native stack samplers of all the script and layout threads.
+    samplers: Arc<Mutex<Vec<IpcSender<SamplerControlMsg>>>>,
+    /// The pipeline being sampled, if the profiler is active.
+    pipeline: Cell<Option<PipelineId>>,
+    /// Whether the samples of the last recording are still being pulled.
+    is_active: RefCell<Arc<Mutex<bool>>>,
 }
 
 impl Actor for ProfilerActor {
@@ -17,17 +91,209 @@ impl Actor for ProfilerActor {
 
     fn handle_message(
         &self,
-        _registry: &ActorRegistry,
-        _msg_type: &str,
-        _msg: &Map<String, Value>,
-        _stream: &mut TcpStream,
+        registry: &ActorRegistry,
+        msg_type: &str,
+        msg: &Map<String, Value>,
+        stream: &mut TcpStream,
     ) -> Result<ActorMessageStatus, ()> {
-        Ok(ActorMessageStatus::Ignored)
+        Ok(match msg_type {
+            "startProfiler" => {
+                let started = self.pipeline.get().is_none();
+                if started {
+                    let interval = msg
+                        .get("interval")
+                        .and_then(|interval| interval.as_f64())
+                        .map_or(DEFAULT_SAMPLING_INTERVAL, |interval| {
+                            interval.max(1.) as u64
+                        });
+                    let pipeline = registry
+                        .find::<BrowsingContextActor>(&self.browsing_context)
+                        .active_pipeline
+                        .get();
+                    self.start(pipeline, interval, stream.try_clone().unwrap());
+                }
+                let msg = StartedReply {
+                    from: self.name(),
+                    started: started,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "stopProfiler" => {
+                let stopped = self.stop();
+                let msg = StoppedReply {
+                    from: self.name(),
+                    stopped: stopped,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "isActive" => {
+                let msg = IsActiveReply {
+                    from: self.name(),
+                    isActive: self.pipeline.get().is_some(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "getFeatures" => {
+                let msg = GetFeaturesReply {
+                    from: self.name(),
+                    features: vec!["js", "stackwalk"],
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
     }
 }
 
 impl ProfilerActor {
-    pub fn new(name: String) -> ProfilerActor {
-        ProfilerActor { name: name }
+    pub fn new(
+        name: String,
+        script_chan: IpcSender<DevtoolScriptControlMsg>,
+        browsing_context: String,
+        samplers: Arc<Mutex<Vec<IpcSender<SamplerControlMsg>>>>,
+    ) -> ProfilerActor {
+        ProfilerActor {
+            name: name,
+            script_chan: script_chan,
+            browsing_context: browsing_context,
+            samplers: samplers,
+            pipeline: Cell::new(None),
+            is_active: RefCell::new(Arc::new(Mutex::new(false))),
+        }
     }
+
+    fn start(&self, pipeline: PipelineId, interval: u64, stream: TcpStream) {
+        let (js_sender, js_receiver) = ipc::channel().unwrap();
+        let command = DebuggerCommand::StartSampling(interval as u32, js_sender);
+        let _ = self
+            .script_chan
+            .send(DevtoolScriptControlMsg::Debugger(pipeline, command));
+
+        let (native_sender, native_receiver) = ipc::channel().unwrap();
+        for sampler in &*self.samplers.lock().unwrap() {
+            let msg = SamplerControlMsg::StartStreaming(
+                Duration::from_millis(interval),
+                native_sender.clone(),
+            );
+            let _ = sampler.send(msg);
+        }
+
+        self.pipeline.set(Some(pipeline));
+        *self.is_active.borrow_mut() = Arc::new(Mutex::new(true));
+        self.pull_samples(pipeline, js_receiver, native_receiver, stream);
+    }
+
+    /// Returns whether the profiler was active.
+    fn stop(&self) -> bool {
+        let pipeline = match self.pipeline.take() {
+            Some(pipeline) => pipeline,
+            None => return false,
+        };
+        *self.is_active.borrow().lock().unwrap() = false;
+        let _ = self.script_chan.send(DevtoolScriptControlMsg::Debugger(
+            pipeline,
+            DebuggerCommand::StopSampling,
+        ));
+        for sampler in &*self.samplers.lock().unwrap() {
+            let _ = sampler.send(SamplerControlMsg::StopStreaming);
+        }
+        true
+    }
+
+    fn pull_samples(
+        &self,
+        pipeline: PipelineId,
+        js_receiver: IpcReceiver<JSSample>,
+        native_receiver: IpcReceiver<SampledStack>,
+        mut stream: TcpStream,
+    ) {
+        let name = self.name();
+        let is_active = self.is_active.borrow().clone();
+        let start_time = epoch_millis();
+
+        thread::Builder::new()
+            .name("PullProfilerSamples".to_owned())
+            .spawn(move || loop {
+                if !*is_active.lock().unwrap() {
+                    break;
+                }
+
+                let mut samples = vec![];
+                while let Ok(sample) = js_receiver.try_recv() {
+                    samples.push(SampleMsg::from_js(sample, start_time));
+                }
+                while let Ok(sample) = native_receiver.try_recv() {
+                    // The samplers are shared by all the pipelines of an event loop.
+                    if sample.component.0 == pipeline {
+                        samples.push(SampleMsg::from_native(sample, start_time));
+                    }
+                }
+                if !samples.is_empty() {
+                    let reply = SamplesReply {
+                        from: name.clone(),
+                        type_: "samples".to_owned(),
+                        samples: samples,
+                    };
+                    stream.write_json_packet(&reply);
+                }
+
+                thread::sleep(Duration::from_millis(SAMPLES_PULL_TIMEOUT));
+            })
+            .expect("Thread spawning failed");
+    }
+}
+
+impl SampleMsg {
+    fn from_js(sample: JSSample, start_time: f64) -> SampleMsg {
+        SampleMsg {
+            thread: "JS".to_owned(),
+            time: sample.time - start_time,
+            frames: sample
+                .frames
+                .into_iter()
+                .map(|frame| SampledFrameMsg {
+                    name: frame.function_name,
+                    url: Some(frame.url),
+                    line: Some(frame.line),
+                    column: Some(frame.column),
+                })
+                .collect(),
+        }
+    }
+
+    fn from_native(sample: SampledStack, start_time: f64) -> SampleMsg {
+        let component_type = sample.component.1;
+        SampleMsg {
+            thread: sample
+                .thread_name
+                .unwrap_or_else(|| format!("{:?}", component_type)),
+            time: sample.time - start_time,
+            frames: sample
+                .profile
+                .backtrace
+                .into_iter()
+                .map(|symbol| SampledFrameMsg {
+                    name: symbol.name,
+                    url: symbol.filename,
+                    line: symbol.lineno,
+                    column: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn epoch_millis() -> f64 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    since_epoch.as_secs_f64() * 1000.
 }
@@ -0,0 +1,74 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Liberally derived from the [Firefox JS implementation]
+//! (https://searchfox.org/mozilla-central/source/devtools/server/actors/source.js).
+//! Represents a single script source compiled in a debuggee.
+
+use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::protocol::JsonPacketStream;
+use devtools_traits::SourceInfo;
+use serde_json::{Map, Value};
+use std::net::TcpStream;
+
+#[derive(Serialize)]
+pub struct SourceForm {
+    actor: String,
+    url: String,
+    introductionType: Option<String>,
+    isBlackBoxed: bool,
+    sourceMapURL: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SourceReply {
+    from: String,
+    contentType: String,
+    source: String,
+}
+
+pub struct SourceActor {
+    pub name: String,
+    pub source: SourceInfo,
+}
+
+impl Actor for SourceActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(
+        &self,
+        _registry: &ActorRegistry,
+        msg_type: &str,
+        _msg: &Map<String, Value>,
+        stream: &mut TcpStream,
+    ) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "source" => {
+                let msg = SourceReply {
+                    from: self.name(),
+                    contentType: "text/javascript".to_owned(),
+                    source: self.source.text.clone().unwrap_or_default(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
+
+impl SourceActor {
+    pub fn encodable(&self) -> SourceForm {
+        SourceForm {
+            actor: self.name(),
+            url: self.source.url.clone(),
+            introductionType: self.source.introduction_type.clone(),
+            isBlackBoxed: false,
+            sourceMapURL: None,
+        }
+    }
+}
@@ -2,19 +2,65 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+//! Liberally derived from the [Firefox JS implementation]
+//! (https://searchfox.org/mozilla-central/source/devtools/server/actors/stylesheets.js).
+//! Lists the stylesheets of a document for the style editor, and lets it edit them live.
+
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::actors::browsing_context::BrowsingContextActor;
 use crate::protocol::JsonPacketStream;
+use devtools_traits::DevtoolScriptControlMsg::{self, GetStyleSheets, UpdateStyleSheet};
+use devtools_traits::StyleSheetInfo;
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
 use serde_json::{Map, Value};
+use std::cell::RefCell;
 use std::net::TcpStream;
 
 #[derive(Serialize)]
 struct GetStyleSheetsReply {
     from: String,
-    styleSheets: Vec<u32>, // TODO: real JSON structure.
+    styleSheets: Vec<StyleSheetForm>,
+}
+
+#[derive(Serialize)]
+struct StyleSheetForm {
+    actor: String,
+    href: Option<String>,
+    nodeHref: String,
+    disabled: bool,
+    title: Option<String>,
+    system: bool,
+    styleSheetIndex: usize,
+    ruleCount: u32,
+    sourceMapURL: Option<String>,
+}
+
+#[derive(Serialize)]
+struct GetTextReply {
+    from: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct UpdateReply {
+    from: String,
+}
+
+#[derive(Serialize)]
+struct StyleAppliedMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    kind: u32,
 }
 
 pub struct StyleSheetsActor {
     pub name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    browsing_context: String,
+    /// The stylesheet actors handed out by the last `getStyleSheets` request.
+    sheets: RefCell<Vec<String>>,
 }
 
 impl Actor for StyleSheetsActor {
@@ -23,16 +69,45 @@ impl Actor for StyleSheetsActor {
     }
     fn handle_message(
         &self,
-        _registry: &ActorRegistry,
+        registry: &ActorRegistry,
         msg_type: &str,
         _msg: &Map<String, Value>,
         stream: &mut TcpStream,
     ) -> Result<ActorMessageStatus, ()> {
         Ok(match msg_type {
             "getStyleSheets" => {
+                let browsing_context =
+                    registry.find::<BrowsingContextActor>(&self.browsing_context);
+                let pipeline = browsing_context.active_pipeline.get();
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(GetStyleSheets(pipeline, tx)).unwrap();
+                let stylesheets = rx.recv().map_err(|_| ())?;
+
+                for name in self.sheets.borrow_mut().drain(..) {
+                    registry.drop_actor_later(name);
+                }
+                let node_href = browsing_context.url.borrow().clone();
+                let forms = stylesheets
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, info)| {
+                        let sheet = StyleSheetActor {
+                            name: registry.new_name("stylesheet"),
+                            script_chan: self.script_chan.clone(),
+                            pipeline: pipeline,
+                            index: index,
+                            text: RefCell::new(info.text.clone()),
+                        };
+                        let form = sheet.encodable(&info, node_href.clone());
+                        self.sheets.borrow_mut().push(sheet.name());
+                        registry.register_later(Box::new(sheet));
+                        form
+                    })
+                    .collect();
+
                 let msg = GetStyleSheetsReply {
                     from: self.name(),
-                    styleSheets: vec![],
+                    styleSheets: forms,
                 };
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
@@ -44,7 +119,86 @@ impl Actor for StyleSheetsActor {
 }
 
 impl StyleSheetsActor {
-    pub fn new(name: String) -> StyleSheetsActor {
-        StyleSheetsActor { name: name }
+    pub fn new(
+        name: String,
+        script_chan: IpcSender<DevtoolScriptControlMsg>,
+        browsing_context: String,
+    ) -> StyleSheetsActor {
+        StyleSheetsActor {
+            name: name,
+            script_chan: script_chan,
+            browsing_context: browsing_context,
+            sheets: RefCell::new(vec![]),
+        }
+    }
+}
+
+/// A single stylesheet of a document, identified by its position in the document.
+struct StyleSheetActor {
+    name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+    index: usize,
+    /// The CSS text of the stylesheet, as last listed or edited.
+    text: RefCell<String>,
+}
+
+impl Actor for StyleSheetActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(
+        &self,
+        _registry: &ActorRegistry,
+        msg_type: &str,
+        msg: &Map<String, Value>,
+        stream: &mut TcpStream,
+    ) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "getText" => {
+                let msg = GetTextReply {
+                    from: self.name(),
+                    text: self.text.borrow().clone(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "update" => {
+                let text = msg.get("text").and_then(|text| text.as_str()).ok_or(())?;
+                self.script_chan
+                    .send(UpdateStyleSheet(self.pipeline, self.index, text.to_owned()))
+                    .unwrap();
+                *self.text.borrow_mut() = text.to_owned();
+
+                stream.write_json_packet(&UpdateReply { from: self.name() });
+                let msg = StyleAppliedMsg {
+                    from: self.name(),
+                    type_: "style-applied".to_owned(),
+                    kind: 0,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
+
+impl StyleSheetActor {
+    fn encodable(&self, info: &StyleSheetInfo, node_href: String) -> StyleSheetForm {
+        StyleSheetForm {
+            actor: self.name(),
+            href: info.href.clone(),
+            nodeHref: node_href,
+            disabled: info.disabled,
+            title: info.title.clone(),
+            system: false,
+            styleSheetIndex: self.index,
+            ruleCount: info.rule_count,
+            sourceMapURL: None,
+        }
     }
 }
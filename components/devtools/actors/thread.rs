@@ -2,9 +2,20 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+//! Liberally derived from the [Firefox JS implementation]
+//! (https://searchfox.org/mozilla-central/source/devtools/server/actors/thread.js).
+//! Drives the JS debugger of a script global: sources, breakpoints, pausing and stepping.
+
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::actors::frame::{FrameActor, FrameMsg};
+use crate::actors::source::{SourceActor, SourceForm};
 use crate::protocol::JsonPacketStream;
+use devtools_traits::{DebuggerCommand, DevtoolScriptControlMsg, PauseInfo, PauseReason};
+use devtools_traits::{ResumeLimit, SourceInfo};
+use ipc_channel::ipc::IpcSender;
+use msg::constellation_msg::PipelineId;
 use serde_json::{Map, Value};
+use std::cell::{Cell, RefCell};
 use std::net::TcpStream;
 
 #[derive(Serialize)]
@@ -50,26 +61,232 @@ struct ReconfigureReply {
 }
 
 #[derive(Serialize)]
-struct SourcesReply {
+struct ThreadDetachedReply {
     from: String,
-    sources: Vec<Source>,
+    #[serde(rename = "type")]
+    type_: String,
 }
 
 #[derive(Serialize)]
-enum Source {}
+struct SourcesReply {
+    from: String,
+    sources: Vec<SourceForm>,
+}
 
 #[derive(Serialize)]
 struct VoidAttachedReply {
     from: String,
 }
 
+#[derive(Serialize)]
+struct NewSourceMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    source: SourceForm,
+}
+
+#[derive(Serialize)]
+struct PausedMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    actor: String,
+    frame: Option<FrameMsg>,
+    why: WhyMsg,
+}
+
+#[derive(Serialize)]
+struct FramesReply {
+    from: String,
+    frames: Vec<FrameMsg>,
+}
+
+#[derive(Serialize)]
+struct NoSuchSourceReply {
+    from: String,
+    error: String,
+    message: String,
+}
+
+/// The state of a paused debuggee.
+struct Pause {
+    /// The name of the pause actor, which is only valid until the debuggee resumes.
+    actor: String,
+    /// The frame actors of the paused stack, youngest first.
+    frames: Vec<String>,
+}
+
 pub struct ThreadActor {
     name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    /// The pipeline whose JS debugger is driven by this actor.
+    pipeline: Cell<PipelineId>,
+    /// The source actors of the current pipeline, in the order they were compiled.
+    sources: RefCell<Vec<String>>,
+    streams: RefCell<Vec<TcpStream>>,
+    pause: RefCell<Option<Pause>>,
 }
 
 impl ThreadActor {
-    pub fn new(name: String) -> ThreadActor {
-        ThreadActor { name: name }
+    pub fn new(
+        name: String,
+        script_chan: IpcSender<DevtoolScriptControlMsg>,
+        pipeline: PipelineId,
+    ) -> ThreadActor {
+        ThreadActor {
+            name: name,
+            script_chan: script_chan,
+            pipeline: Cell::new(pipeline),
+            sources: RefCell::new(vec![]),
+            streams: RefCell::new(vec![]),
+            pause: RefCell::new(None),
+        }
+    }
+
+    fn send_command(&self, command: DebuggerCommand) {
+        let _ = self.script_chan.send(DevtoolScriptControlMsg::Debugger(
+            self.pipeline.get(),
+            command,
+        ));
+    }
+
+    /// Find the url of the source a breakpoint location refers to, either directly or
+    /// through the actor of a known source.
+    fn location_url(
+        &self,
+        registry: &ActorRegistry,
+        location: &Map<String, Value>,
+    ) -> Option<String> {
+        if let Some(url) = location.get("sourceUrl").and_then(|url| url.as_str()) {
+            return Some(url.to_owned());
+        }
+        let actor = location.get("sourceId").and_then(|id| id.as_str())?;
+        if !self.sources.borrow().iter().any(|source| source == actor) {
+            return None;
+        }
+        Some(registry.find::<SourceActor>(actor).source.url.clone())
+    }
+
+    fn handle_breakpoint(
+        &self,
+        registry: &ActorRegistry,
+        msg: &Map<String, Value>,
+        stream: &mut TcpStream,
+        set: bool,
+    ) {
+        let location = msg
+            .get("location")
+            .and_then(|location| location.as_object());
+        let url = location.and_then(|location| self.location_url(registry, location));
+        let (location, url) = match (location, url) {
+            (Some(location), Some(url)) => (location, url),
+            _ => {
+                let msg = NoSuchSourceReply {
+                    from: self.name(),
+                    error: "noSuchActor".to_owned(),
+                    message: "unknown breakpoint source".to_owned(),
+                };
+                return stream.write_json_packet(&msg);
+            },
+        };
+        let line = location
+            .get("line")
+            .and_then(|line| line.as_u64())
+            .unwrap_or(1) as u32;
+        let column = location
+            .get("column")
+            .and_then(|column| column.as_u64())
+            .unwrap_or(0) as u32;
+        self.send_command(if set {
+            DebuggerCommand::SetBreakpoint(url, line, column)
+        } else {
+            DebuggerCommand::RemoveBreakpoint(url, line, column)
+        });
+        stream.write_json_packet(&VoidAttachedReply { from: self.name() });
+    }
+
+    /// Forget the sources of the previous document once the given pipeline becomes active,
+    /// returning the source actors which are no longer needed.
+    pub(crate) fn navigate(&self, pipeline: PipelineId) -> Vec<String> {
+        self.pipeline.set(pipeline);
+        self.sources.borrow_mut().drain(..).collect()
+    }
+
+    /// Record a source compiled by the debuggee and notify attached clients.
+    pub(crate) fn new_source(&self, registry: &ActorRegistry, source: SourceInfo) -> SourceActor {
+        let actor = SourceActor {
+            name: registry.new_name("source"),
+            source: source,
+        };
+        self.sources.borrow_mut().push(actor.name());
+
+        let msg = NewSourceMsg {
+            from: self.name(),
+            type_: "newSource".to_owned(),
+            source: actor.encodable(),
+        };
+        for stream in &mut *self.streams.borrow_mut() {
+            stream.write_json_packet(&msg);
+        }
+        actor
+    }
+
+    /// Notify attached clients that the debuggee paused, returning the actors for the
+    /// frames of the paused stack.
+    pub(crate) fn paused(&self, registry: &ActorRegistry, pause: PauseInfo) -> Vec<FrameActor> {
+        let sources = self.sources.borrow();
+        let frames: Vec<_> = pause
+            .frames
+            .into_iter()
+            .enumerate()
+            .map(|(depth, frame)| {
+                let source = sources
+                    .iter()
+                    .find(|name| registry.find::<SourceActor>(name).source.id == frame.source_id)
+                    .cloned()
+                    .unwrap_or_default();
+                FrameActor::new(registry, depth as u32, frame, source)
+            })
+            .collect();
+
+        let actor = registry.new_name("pause");
+        let msg = PausedMsg {
+            from: self.name(),
+            type_: "paused".to_owned(),
+            actor: actor.clone(),
+            frame: frames.first().map(FrameActor::encodable),
+            why: WhyMsg {
+                type_: match pause.reason {
+                    PauseReason::Breakpoint => "breakpoint",
+                    PauseReason::DebuggerStatement => "debuggerStatement",
+                    PauseReason::Interrupted => "interrupted",
+                    PauseReason::ResumeLimit => "resumeLimit",
+                }
+                .to_owned(),
+            },
+        };
+        for stream in &mut *self.streams.borrow_mut() {
+            stream.write_json_packet(&msg);
+        }
+
+        *self.pause.borrow_mut() = Some(Pause {
+            actor: actor,
+            frames: frames.iter().map(|frame| frame.name()).collect(),
+        });
+        frames
+    }
+
+    /// Let a paused debuggee run again, dropping the actors that were only valid while it
+    /// was paused.
+    fn resume(&self, registry: &ActorRegistry, limit: Option<ResumeLimit>) {
+        if let Some(pause) = self.pause.borrow_mut().take() {
+            debug!("resuming from {}", pause.actor);
+            for frame in pause.frames {
+                registry.drop_actor_later(frame);
+            }
+            self.send_command(DebuggerCommand::Resume(limit));
+        }
     }
 }
 
@@ -82,7 +299,7 @@ impl Actor for ThreadActor {
         &self,
         registry: &ActorRegistry,
         msg_type: &str,
-        _msg: &Map<String, Value>,
+        msg: &Map<String, Value>,
         stream: &mut TcpStream,
     ) -> Result<ActorMessageStatus, ()> {
         Ok(match msg_type {
@@ -100,12 +317,37 @@ impl Actor for ThreadActor {
                         type_: "attached".to_owned(),
                     },
                 };
+                self.streams.borrow_mut().push(stream.try_clone().unwrap());
                 stream.write_json_packet(&msg);
                 stream.write_json_packet(&VoidAttachedReply { from: self.name() });
                 ActorMessageStatus::Processed
             },
 
+            //FIXME: The current implementation won't work for multiple connections. Need to ensure
+            //       that the correct stream is removed.
+            "detach" => {
+                self.streams.borrow_mut().pop();
+                self.resume(registry, None);
+                let msg = ThreadDetachedReply {
+                    from: self.name(),
+                    type_: "detached".to_owned(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
             "resume" => {
+                let limit = msg
+                    .get("resumeLimit")
+                    .and_then(|limit| limit.get("type"))
+                    .and_then(|limit| limit.as_str())
+                    .and_then(|limit| match limit {
+                        "next" => Some(ResumeLimit::Next),
+                        "step" => Some(ResumeLimit::Step),
+                        "finish" => Some(ResumeLimit::Finish),
+                        _ => None,
+                    });
+                self.resume(registry, limit);
                 let msg = ThreadResumedReply {
                     from: self.name(),
                     type_: "resumed".to_owned(),
@@ -116,6 +358,7 @@ impl Actor for ThreadActor {
             },
 
             "interrupt" => {
+                self.send_command(DebuggerCommand::Interrupt);
                 let msg = ThreadInterruptedReply {
                     from: self.name(),
                     type_: "interrupted".to_owned(),
@@ -132,7 +375,46 @@ impl Actor for ThreadActor {
             "sources" => {
                 let msg = SourcesReply {
                     from: self.name(),
-                    sources: vec![],
+                    sources: self
+                        .sources
+                        .borrow()
+                        .iter()
+                        .map(|name| registry.find::<SourceActor>(name).encodable())
+                        .collect(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "setBreakpoint" => {
+                self.handle_breakpoint(registry, msg, stream, true);
+                ActorMessageStatus::Processed
+            },
+
+            "removeBreakpoint" => {
+                self.handle_breakpoint(registry, msg, stream, false);
+                ActorMessageStatus::Processed
+            },
+
+            "frames" => {
+                let start = msg
+                    .get("start")
+                    .and_then(|start| start.as_u64())
+                    .unwrap_or(0);
+                let count = msg.get("count").and_then(|count| count.as_u64());
+                let frames = match *self.pause.borrow() {
+                    Some(ref pause) => pause
+                        .frames
+                        .iter()
+                        .skip(start as usize)
+                        .take(count.map_or(pause.frames.len(), |count| count as usize))
+                        .map(|name| registry.find::<FrameActor>(name).encodable())
+                        .collect(),
+                    None => vec![],
+                };
+                let msg = FramesReply {
+                    from: self.name(),
+                    frames: frames,
                 };
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
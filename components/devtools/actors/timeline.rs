@@ -11,6 +11,7 @@ use devtools_traits::DevtoolScriptControlMsg::{DropTimelineMarkers, SetTimelineM
 use devtools_traits::{PreciseTime, TimelineMarker, TimelineMarkerType};
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use msg::constellation_msg::PipelineId;
+use profile_traits::mem::ProfilerChan;
 use serde::{Serialize, Serializer};
 use serde_json::{Map, Value};
 use std::cell::RefCell;
@@ -26,6 +27,7 @@ pub struct TimelineActor {
     pipeline: PipelineId,
     is_recording: Arc<Mutex<bool>>,
     stream: RefCell<Option<TcpStream>>,
+    mem_profiler_chan: ProfilerChan,
 
     framerate_actor: RefCell<Option<String>>,
     memory_actor: RefCell<Option<String>>,
@@ -128,6 +130,7 @@ impl TimelineActor {
         name: String,
         pipeline: PipelineId,
         script_sender: IpcSender<DevtoolScriptControlMsg>,
+        mem_profiler_chan: ProfilerChan,
     ) -> TimelineActor {
         let marker_types = vec![TimelineMarkerType::Reflow, TimelineMarkerType::DOMEvent];
 
@@ -138,6 +141,7 @@ impl TimelineActor {
             script_sender: script_sender,
             is_recording: Arc::new(Mutex::new(false)),
             stream: RefCell::new(None),
+            mem_profiler_chan: mem_profiler_chan,
 
             framerate_actor: RefCell::new(None),
             memory_actor: RefCell::new(None),
@@ -204,7 +208,10 @@ impl Actor for TimelineActor {
                 // init memory actor
                 if let Some(with_memory) = msg.get("withMemory") {
                     if let Some(true) = with_memory.as_bool() {
-                        *self.memory_actor.borrow_mut() = Some(MemoryActor::create(registry));
+                        *self.memory_actor.borrow_mut() = Some(MemoryActor::create(
+                            registry,
+                            self.mem_profiler_chan.clone(),
+                        ));
                     }
                 }
 
@@ -35,10 +35,11 @@ use devtools_traits::{ChromeToDevtoolsControlMsg, ConsoleMessage, DevtoolsContro
 use devtools_traits::{
     DevtoolScriptControlMsg, DevtoolsPageInfo, LogLevel, NavigationState, NetworkEvent,
 };
-use devtools_traits::{PageError, ScriptToDevtoolsControlMsg, WorkerId};
+use devtools_traits::{PageError, PauseInfo, ScriptToDevtoolsControlMsg, SourceInfo, WorkerId};
 use embedder_traits::{EmbedderMsg, EmbedderProxy, PromptDefinition, PromptOrigin, PromptResult};
 use ipc_channel::ipc::{self, IpcSender};
-use msg::constellation_msg::{BrowsingContextId, PipelineId};
+use msg::constellation_msg::{BrowsingContextId, PipelineId, SamplerControlMsg};
+use profile_traits::mem::ProfilerChan;
 use std::borrow::ToOwned;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::HashMap;
@@ -53,6 +54,7 @@ mod actors {
     pub mod console;
     pub mod device;
     pub mod emulation;
+    pub mod frame;
     pub mod framerate;
     pub mod inspector;
     pub mod memory;
@@ -63,6 +65,7 @@ mod actors {
     pub mod process;
     pub mod profiler;
     pub mod root;
+    pub mod source;
     pub mod stylesheets;
     pub mod thread;
     pub mod timeline;
@@ -112,13 +115,17 @@ struct ResponseStartUpdateMsg {
 }
 
 /// Spin up a devtools server that listens for connections on the specified port.
-pub fn start_server(port: u16, embedder: EmbedderProxy) -> Sender<DevtoolsControlMsg> {
+pub fn start_server(
+    port: u16,
+    embedder: EmbedderProxy,
+    mem_profiler_chan: ProfilerChan,
+) -> Sender<DevtoolsControlMsg> {
     let (sender, receiver) = unbounded();
     {
         let sender = sender.clone();
         thread::Builder::new()
             .name("Devtools".to_owned())
-            .spawn(move || run_server(sender, receiver, port, embedder))
+            .spawn(move || run_server(sender, receiver, port, embedder, mem_profiler_chan))
             .expect("Thread spawning failed");
     }
     sender
@@ -129,6 +136,7 @@ fn run_server(
     receiver: Receiver<DevtoolsControlMsg>,
     port: u16,
     embedder: EmbedderProxy,
+    mem_profiler_chan: ProfilerChan,
 ) {
     let bound = TcpListener::bind(&("0.0.0.0", port)).ok().and_then(|l| {
         l.local_addr()
@@ -181,6 +189,8 @@ fn run_server(
 
     let mut actor_workers: HashMap<WorkerId, String> = HashMap::new();
 
+    let samplers: Arc<Mutex<Vec<IpcSender<SamplerControlMsg>>>> = Default::default();
+
     /// Process the input from a single devtools client until EOF.
     fn handle_client(actors: Arc<Mutex<ActorRegistry>>, mut stream: TcpStream) {
         debug!("connection established to {}", stream.peer_addr().unwrap());
@@ -228,11 +238,21 @@ fn run_server(
         state: NavigationState,
     ) {
         let actor_name = browsing_contexts.get(&browsing_context).unwrap();
-        actors
-            .lock()
-            .unwrap()
-            .find::<BrowsingContextActor>(actor_name)
-            .navigate(state);
+        let mut actors = actors.lock().unwrap();
+        let pipeline = match state {
+            NavigationState::Stop(pipeline, _) => Some(pipeline),
+            NavigationState::Start(_) => None,
+        };
+        let browsing_context = actors.find::<BrowsingContextActor>(actor_name);
+        browsing_context.navigate(state);
+        if let Some(pipeline) = pipeline {
+            let old_sources = actors
+                .find::<ThreadActor>(&browsing_context.thread)
+                .navigate(pipeline);
+            for source in old_sources {
+                actors.drop_actor(source);
+            }
+        }
     }
 
     fn handle_title_changed(
@@ -266,6 +286,8 @@ fn run_server(
         pipelines: &mut HashMap<PipelineId, BrowsingContextId>,
         actor_workers: &mut HashMap<WorkerId, String>,
         page_info: DevtoolsPageInfo,
+        mem_profiler_chan: &ProfilerChan,
+        samplers: &Arc<Mutex<Vec<IpcSender<SamplerControlMsg>>>>,
     ) {
         let mut actors = actors.lock().unwrap();
 
@@ -277,7 +299,8 @@ fn run_server(
             assert!(pipelines.get(&pipeline).is_some());
             assert!(browsing_contexts.get(&browsing_context).is_some());
 
-            let thread = ThreadActor::new(actors.new_name("context"));
+            let thread =
+                ThreadActor::new(actors.new_name("context"), script_sender.clone(), pipeline);
             let thread_name = thread.name();
             actors.register(Box::new(thread));
 
@@ -311,6 +334,8 @@ fn run_server(
                         page_info,
                         pipeline,
                         script_sender,
+                        mem_profiler_chan.clone(),
+                        samplers.clone(),
                         &mut *actors,
                     );
                     let name = browsing_context_actor.name();
@@ -382,6 +407,86 @@ fn run_server(
         console_actor.handle_console_api(console_message, id, &*actors);
     }
 
+    fn handle_new_source(
+        actors: Arc<Mutex<ActorRegistry>>,
+        id: PipelineId,
+        worker_id: Option<WorkerId>,
+        source: SourceInfo,
+        browsing_contexts: &HashMap<BrowsingContextId, String>,
+        actor_workers: &HashMap<WorkerId, String>,
+        pipelines: &HashMap<PipelineId, BrowsingContextId>,
+    ) {
+        let thread_actor_name = match find_thread_actor(
+            actors.clone(),
+            id,
+            worker_id,
+            actor_workers,
+            browsing_contexts,
+            pipelines,
+        ) {
+            Some(name) => name,
+            None => return,
+        };
+        let mut actors = actors.lock().unwrap();
+        let source_actor = actors
+            .find::<ThreadActor>(&thread_actor_name)
+            .new_source(&*actors, source);
+        actors.register(Box::new(source_actor));
+    }
+
+    fn handle_debugger_paused(
+        actors: Arc<Mutex<ActorRegistry>>,
+        id: PipelineId,
+        worker_id: Option<WorkerId>,
+        pause: PauseInfo,
+        browsing_contexts: &HashMap<BrowsingContextId, String>,
+        actor_workers: &HashMap<WorkerId, String>,
+        pipelines: &HashMap<PipelineId, BrowsingContextId>,
+    ) {
+        let thread_actor_name = match find_thread_actor(
+            actors.clone(),
+            id,
+            worker_id,
+            actor_workers,
+            browsing_contexts,
+            pipelines,
+        ) {
+            Some(name) => name,
+            None => return,
+        };
+        let mut actors = actors.lock().unwrap();
+        let frame_actors = actors
+            .find::<ThreadActor>(&thread_actor_name)
+            .paused(&*actors, pause);
+        for frame_actor in frame_actors {
+            actors.register(Box::new(frame_actor));
+        }
+    }
+
+    fn find_thread_actor(
+        actors: Arc<Mutex<ActorRegistry>>,
+        pipeline: PipelineId,
+        worker_id: Option<WorkerId>,
+        actor_workers: &HashMap<WorkerId, String>,
+        browsing_contexts: &HashMap<BrowsingContextId, String>,
+        pipelines: &HashMap<PipelineId, BrowsingContextId>,
+    ) -> Option<String> {
+        let actors = actors.lock().unwrap();
+        if let Some(worker_id) = worker_id {
+            let actor_name = actor_workers.get(&worker_id)?;
+            Some(actors.find::<WorkerActor>(actor_name).thread.clone())
+        } else {
+            let id = pipelines.get(&pipeline)?;
+            let actor_name = browsing_contexts.get(id)?;
+            Some(
+                actors
+                    .find::<BrowsingContextActor>(actor_name)
+                    .thread
+                    .clone(),
+            )
+        }
+    }
+
     fn find_console_actor(
         actors: Arc<Mutex<ActorRegistry>>,
         pipeline: PipelineId,
@@ -620,6 +725,8 @@ fn run_server(
                 &mut pipelines,
                 &mut actor_workers,
                 pageinfo,
+                &mem_profiler_chan,
+                &samplers,
             ),
             DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::Navigate(
                 browsing_context,
@@ -650,6 +757,32 @@ fn run_server(
                 &actor_workers,
                 &pipelines,
             ),
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::NewSource(
+                id,
+                worker_id,
+                source,
+            )) => handle_new_source(
+                actors.clone(),
+                id,
+                worker_id,
+                source,
+                &browsing_contexts,
+                &actor_workers,
+                &pipelines,
+            ),
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::DebuggerPaused(
+                id,
+                worker_id,
+                pause,
+            )) => handle_debugger_paused(
+                actors.clone(),
+                id,
+                worker_id,
+                pause,
+                &browsing_contexts,
+                &actor_workers,
+                &pipelines,
+            ),
             DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::ReportCSSError(
                 id,
                 css_error,
@@ -697,6 +830,9 @@ fn run_server(
                     network_event,
                 );
             },
+            DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::NewSampler(sampler)) => {
+                samplers.lock().unwrap().push(sampler)
+            },
             DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::ServerExitMsg) => break,
         }
     }
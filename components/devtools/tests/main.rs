@@ -0,0 +1,479 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crossbeam_channel::{unbounded, Sender};
+use devtools_traits::{DebuggerCommand, DevtoolScriptControlMsg, DevtoolsControlMsg};
+use devtools_traits::{DevtoolsPageInfo, EnvironmentInfo, EvaluateJSReply, FrameInfo};
+use devtools_traits::{PauseInfo, PauseReason, ResumeLimit, ScriptToDevtoolsControlMsg};
+use devtools_traits::{SourceInfo, WorkerId};
+use embedder_traits::{EmbedderMsg, EmbedderProxy, EventLoopWaker};
+use embedder_traits::{PromptDefinition, PromptResult};
+use ipc_channel::ipc::{self, IpcReceiver};
+use msg::constellation_msg::{BrowsingContextId, PipelineId, PipelineNamespace};
+use msg::constellation_msg::{PipelineNamespaceId, TopLevelBrowsingContextId};
+use profile_traits::mem::{ProfilerChan, ProfilerMsg};
+use serde_json::{json, Value};
+use servo_url::ServoUrl;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+struct DummyEventLoopWaker;
+
+impl EventLoopWaker for DummyEventLoopWaker {
+    fn wake(&self) {}
+    fn clone_box(&self) -> Box<dyn EventLoopWaker> {
+        Box::new(DummyEventLoopWaker)
+    }
+}
+
+/// A devtools server with a single document, whose script thread is played by the test.
+struct Browser {
+    devtools_chan: Sender<DevtoolsControlMsg>,
+    port: u16,
+    browsing_context_id: BrowsingContextId,
+    pipeline_id: PipelineId,
+    script: IpcReceiver<DevtoolScriptControlMsg>,
+    _mem_profiler: IpcReceiver<ProfilerMsg>,
+}
+
+impl Browser {
+    fn start() -> Browser {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let (embedder_sender, embedder_receiver) = unbounded();
+        let embedder = EmbedderProxy {
+            sender: embedder_sender,
+            event_loop_waker: Box::new(DummyEventLoopWaker),
+        };
+        let (mem_profiler_sender, mem_profiler) = ipc::channel().unwrap();
+        let devtools_chan = devtools::start_server(0, embedder, ProfilerChan(mem_profiler_sender));
+        let port = match embedder_receiver.recv().unwrap() {
+            (_, EmbedderMsg::OnDevtoolsStarted(Ok(port))) => port,
+            (_, msg) => panic!("Unexpected embedder message {:?}", msg),
+        };
+        // Accept every incoming connection.
+        thread::spawn(move || {
+            for (_, msg) in embedder_receiver {
+                if let EmbedderMsg::Prompt(PromptDefinition::YesNo(_, sender), _) = msg {
+                    let _ = sender.send(PromptResult::Primary);
+                }
+            }
+        });
+
+        let browsing_context_id = BrowsingContextId::from(TopLevelBrowsingContextId::new());
+        let pipeline_id = PipelineId::new();
+        let (script_sender, script) = ipc::channel().unwrap();
+        let browser = Browser {
+            devtools_chan,
+            port,
+            browsing_context_id,
+            pipeline_id,
+            script,
+            _mem_profiler: mem_profiler,
+        };
+        browser.send_to_devtools(ScriptToDevtoolsControlMsg::NewGlobal(
+            (browsing_context_id, pipeline_id, None),
+            script_sender,
+            page_info("http://example.com/"),
+        ));
+        browser
+    }
+
+    fn send_to_devtools(&self, message: ScriptToDevtoolsControlMsg) {
+        self.devtools_chan
+            .send(DevtoolsControlMsg::FromScript(message))
+            .unwrap();
+    }
+
+    /// Start a dedicated worker in the document, returning the receiver of the messages
+    /// sent to the worker's global.
+    fn start_worker(&self, worker_id: WorkerId) -> IpcReceiver<DevtoolScriptControlMsg> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.send_to_devtools(ScriptToDevtoolsControlMsg::NewGlobal(
+            (self.browsing_context_id, self.pipeline_id, Some(worker_id)),
+            sender,
+            page_info("http://example.com/worker.js"),
+        ));
+        receiver
+    }
+}
+
+fn page_info(url: &str) -> DevtoolsPageInfo {
+    DevtoolsPageInfo {
+        title: "Example".to_owned(),
+        url: ServoUrl::parse(url).unwrap(),
+    }
+}
+
+fn source(id: u32, url: &str) -> SourceInfo {
+    SourceInfo {
+        id,
+        url: url.to_owned(),
+        introduction_type: Some("scriptElement".to_owned()),
+        text: Some("function f(x) {\n  return x + 1;\n}\nf(1);\n".to_owned()),
+    }
+}
+
+/// A pause in `f(x)` at the given line, called from the global code of the source.
+fn pause_in_f(reason: PauseReason, source_id: u32, line: u32) -> PauseInfo {
+    PauseInfo {
+        reason,
+        frames: vec![
+            FrameInfo {
+                display_name: Some("f".to_owned()),
+                type_: "call".to_owned(),
+                source_id,
+                line,
+                column: 2,
+                environments: vec![
+                    EnvironmentInfo {
+                        type_: "function".to_owned(),
+                        function_name: Some("f".to_owned()),
+                        bindings: vec![("x".to_owned(), EvaluateJSReply::NumberValue(1.))],
+                    },
+                    EnvironmentInfo {
+                        type_: "object".to_owned(),
+                        function_name: None,
+                        bindings: vec![],
+                    },
+                ],
+            },
+            FrameInfo {
+                display_name: None,
+                type_: "global".to_owned(),
+                source_id,
+                line: 4,
+                column: 0,
+                environments: vec![],
+            },
+        ],
+    }
+}
+
+/// The next command sent to the JS debugger of a global, skipping other messages.
+fn next_debugger_command(script: &IpcReceiver<DevtoolScriptControlMsg>) -> DebuggerCommand {
+    loop {
+        if let DevtoolScriptControlMsg::Debugger(_, command) = script.recv().unwrap() {
+            return command;
+        }
+    }
+}
+
+/// A client of the remote debugging protocol.
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn connect(browser: &Browser) -> Client {
+        let stream = TcpStream::connect(("127.0.0.1", browser.port)).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut client = Client { stream };
+        let root = client.receive();
+        assert_eq!(root["from"], "root");
+        client
+    }
+
+    fn send(&mut self, to: &str, type_: &str, mut packet: Value) {
+        packet["to"] = json!(to);
+        packet["type"] = json!(type_);
+        let packet = packet.to_string();
+        write!(self.stream, "{}:{}", packet.len(), packet).unwrap();
+    }
+
+    /// Read the next packet, which is framed as `length:JSON`.
+    fn receive(&mut self) -> Value {
+        let mut length = vec![];
+        loop {
+            let mut byte = [0];
+            self.stream
+                .read_exact(&mut byte)
+                .expect("No packet from the devtools server");
+            match byte[0] {
+                b':' => break,
+                byte => length.push(byte),
+            }
+        }
+        let length = String::from_utf8(length).unwrap().parse().unwrap();
+        let mut packet = String::new();
+        (&mut self.stream)
+            .take(length)
+            .read_to_string(&mut packet)
+            .unwrap();
+        serde_json::from_str(&packet).unwrap()
+    }
+
+    fn request(&mut self, to: &str, type_: &str, packet: Value) -> Value {
+        self.send(to, type_, packet);
+        let reply = self.receive();
+        assert_eq!(reply["from"], to);
+        reply
+    }
+
+    /// Attach to the thread actor of the document.
+    fn attach_to_document(&mut self) -> String {
+        let tabs = self.request("root", "listTabs", json!({}));
+        let tab = tabs["tabs"][0]["actor"].as_str().unwrap().to_owned();
+        let attached = self.request(&tab, "attach", json!({}));
+        let thread = attached["threadActor"].as_str().unwrap().to_owned();
+        self.attach_to_thread(&thread);
+        thread
+    }
+
+    fn attach_to_thread(&mut self, thread: &str) {
+        let attached = self.request(thread, "attach", json!({}));
+        assert_eq!(attached["type"], "paused");
+        assert_eq!(attached["why"]["type"], "attached");
+        assert_eq!(self.receive(), json!({ "from": thread }));
+    }
+
+    fn resume(&mut self, thread: &str, limit: Option<&str>) {
+        let packet = match limit {
+            Some(limit) => json!({ "resumeLimit": { "type": limit } }),
+            None => json!({}),
+        };
+        let resumed = self.request(thread, "resume", packet);
+        assert_eq!(resumed["type"], "resumed");
+        assert_eq!(self.receive(), json!({ "from": thread }));
+    }
+}
+
+#[test]
+fn test_sources() {
+    let browser = Browser::start();
+    let mut client = Client::connect(&browser);
+    let thread = client.attach_to_document();
+
+    browser.send_to_devtools(ScriptToDevtoolsControlMsg::NewSource(
+        browser.pipeline_id,
+        None,
+        source(1, "http://example.com/script.js"),
+    ));
+    let new_source = client.receive();
+    assert_eq!(new_source["from"], json!(thread));
+    assert_eq!(new_source["type"], "newSource");
+    assert_eq!(new_source["source"]["url"], "http://example.com/script.js");
+    assert_eq!(new_source["source"]["introductionType"], "scriptElement");
+
+    let sources = client.request(&thread, "sources", json!({}));
+    let sources = sources["sources"].as_array().unwrap();
+    assert_eq!(sources.len(), 1);
+    assert_eq!(sources[0], new_source["source"]);
+
+    let source_actor = sources[0]["actor"].as_str().unwrap();
+    let text = client.request(source_actor, "source", json!({}));
+    assert_eq!(text["contentType"], "text/javascript");
+    assert_eq!(text["source"], json!(source(1, "").text));
+}
+
+#[test]
+fn test_set_breakpoint_and_pause() {
+    let browser = Browser::start();
+    let mut client = Client::connect(&browser);
+    let thread = client.attach_to_document();
+
+    browser.send_to_devtools(ScriptToDevtoolsControlMsg::NewSource(
+        browser.pipeline_id,
+        None,
+        source(1, "http://example.com/script.js"),
+    ));
+    let source_actor = client.receive()["source"]["actor"].clone();
+
+    let location = json!({ "sourceId": source_actor, "line": 2, "column": 2 });
+    let reply = client.request(&thread, "setBreakpoint", json!({ "location": location }));
+    assert_eq!(reply, json!({ "from": thread }));
+    match next_debugger_command(&browser.script) {
+        DebuggerCommand::SetBreakpoint(url, 2, 2) => {
+            assert_eq!(url, "http://example.com/script.js")
+        },
+        command => panic!("Unexpected command {:?}", command),
+    }
+
+    let location = json!({ "sourceId": "source-unknown", "line": 2 });
+    let reply = client.request(&thread, "setBreakpoint", json!({ "location": location }));
+    assert_eq!(reply["error"], "noSuchActor");
+
+    browser.send_to_devtools(ScriptToDevtoolsControlMsg::DebuggerPaused(
+        browser.pipeline_id,
+        None,
+        pause_in_f(PauseReason::Breakpoint, 1, 2),
+    ));
+    let paused = client.receive();
+    assert_eq!(paused["from"], json!(thread));
+    assert_eq!(paused["type"], "paused");
+    assert_eq!(paused["why"]["type"], "breakpoint");
+    assert_eq!(paused["frame"]["displayName"], "f");
+    assert_eq!(paused["frame"]["where"]["actor"], source_actor);
+    assert_eq!(paused["frame"]["where"]["line"], 2);
+
+    let location = json!({ "sourceUrl": "http://example.com/script.js", "line": 2 });
+    let reply = client.request(&thread, "removeBreakpoint", json!({ "location": location }));
+    assert_eq!(reply, json!({ "from": thread }));
+    match next_debugger_command(&browser.script) {
+        DebuggerCommand::RemoveBreakpoint(url, 2, 0) => {
+            assert_eq!(url, "http://example.com/script.js")
+        },
+        command => panic!("Unexpected command {:?}", command),
+    }
+}
+
+#[test]
+fn test_frames_and_scopes() {
+    let browser = Browser::start();
+    let mut client = Client::connect(&browser);
+    let thread = client.attach_to_document();
+
+    browser.send_to_devtools(ScriptToDevtoolsControlMsg::NewSource(
+        browser.pipeline_id,
+        None,
+        source(1, "http://example.com/script.js"),
+    ));
+    let source_actor = client.receive()["source"]["actor"].clone();
+    browser.send_to_devtools(ScriptToDevtoolsControlMsg::DebuggerPaused(
+        browser.pipeline_id,
+        None,
+        pause_in_f(PauseReason::DebuggerStatement, 1, 2),
+    ));
+    assert_eq!(client.receive()["why"]["type"], "debuggerStatement");
+
+    let frames = client.request(&thread, "frames", json!({ "start": 0, "count": 10 }));
+    let frames = frames["frames"].as_array().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0]["depth"], 0);
+    assert_eq!(frames[0]["type"], "call");
+    assert_eq!(frames[1]["depth"], 1);
+    assert_eq!(frames[1]["type"], "global");
+    assert_eq!(
+        frames[1]["where"],
+        json!({ "actor": source_actor, "line": 4, "column": 0 })
+    );
+
+    let older = client.request(&thread, "frames", json!({ "start": 1, "count": 1 }));
+    assert_eq!(older["frames"], json!([frames[1]]));
+
+    let frame = frames[0]["actor"].as_str().unwrap();
+    let environment = client.request(frame, "getEnvironment", json!({}));
+    assert_eq!(environment["type"], "function");
+    assert_eq!(environment["function"]["displayName"], "f");
+    assert_eq!(environment["bindings"]["variables"]["x"]["value"], 1.0);
+    assert_eq!(environment["parent"]["type"], "object");
+    assert!(environment["parent"].get("parent").is_none());
+
+    // The frames are only valid while paused.
+    client.resume(&thread, None);
+    let frames = client.request(&thread, "frames", json!({}));
+    assert_eq!(frames["frames"], json!([]));
+}
+
+#[test]
+fn test_step_and_resume() {
+    let browser = Browser::start();
+    let mut client = Client::connect(&browser);
+    let thread = client.attach_to_document();
+
+    browser.send_to_devtools(ScriptToDevtoolsControlMsg::NewSource(
+        browser.pipeline_id,
+        None,
+        source(1, "http://example.com/script.js"),
+    ));
+    client.receive();
+
+    let interrupted = client.request(&thread, "interrupt", json!({}));
+    assert_eq!(interrupted["type"], "interrupted");
+    assert!(matches!(
+        next_debugger_command(&browser.script),
+        DebuggerCommand::Interrupt
+    ));
+    browser.send_to_devtools(ScriptToDevtoolsControlMsg::DebuggerPaused(
+        browser.pipeline_id,
+        None,
+        pause_in_f(PauseReason::Interrupted, 1, 2),
+    ));
+    assert_eq!(client.receive()["why"]["type"], "interrupted");
+
+    for (limit, expected) in &[
+        ("next", ResumeLimit::Next),
+        ("step", ResumeLimit::Step),
+        ("finish", ResumeLimit::Finish),
+    ] {
+        client.resume(&thread, Some(limit));
+        match next_debugger_command(&browser.script) {
+            DebuggerCommand::Resume(Some(limit)) => assert_eq!(limit, *expected),
+            command => panic!("Unexpected command {:?}", command),
+        }
+        browser.send_to_devtools(ScriptToDevtoolsControlMsg::DebuggerPaused(
+            browser.pipeline_id,
+            None,
+            pause_in_f(PauseReason::ResumeLimit, 1, 3),
+        ));
+        let paused = client.receive();
+        assert_eq!(paused["why"]["type"], "resumeLimit");
+        assert_eq!(paused["frame"]["where"]["line"], 3);
+    }
+
+    client.resume(&thread, None);
+    assert!(matches!(
+        next_debugger_command(&browser.script),
+        DebuggerCommand::Resume(None)
+    ));
+}
+
+#[test]
+fn test_worker_thread() {
+    let browser = Browser::start();
+    let worker_id = WorkerId(Uuid::new_v4());
+    let worker_script = browser.start_worker(worker_id);
+    let mut client = Client::connect(&browser);
+    let document_thread = client.attach_to_document();
+
+    let workers = client.request("root", "listWorkers", json!({}));
+    let workers = workers["workers"].as_array().unwrap();
+    assert_eq!(workers.len(), 1);
+    assert_eq!(workers[0]["url"], "http://example.com/worker.js");
+    let worker = workers[0]["actor"].as_str().unwrap();
+    let connected = client.request(worker, "connect", json!({}));
+    let thread = connected["threadActor"].as_str().unwrap().to_owned();
+    assert_ne!(thread, document_thread);
+    client.attach_to_thread(&thread);
+
+    // Sources of the worker don't show up in the document, and the other way around.
+    browser.send_to_devtools(ScriptToDevtoolsControlMsg::NewSource(
+        browser.pipeline_id,
+        Some(worker_id),
+        source(1, "http://example.com/worker.js"),
+    ));
+    let new_source = client.receive();
+    assert_eq!(new_source["from"], json!(thread));
+    let source_actor = new_source["source"]["actor"].clone();
+    let sources = client.request(&document_thread, "sources", json!({}));
+    assert_eq!(sources["sources"], json!([]));
+
+    let location = json!({ "sourceId": source_actor, "line": 2 });
+    client.request(&thread, "setBreakpoint", json!({ "location": location }));
+    match next_debugger_command(&worker_script) {
+        DebuggerCommand::SetBreakpoint(url, 2, 0) => {
+            assert_eq!(url, "http://example.com/worker.js")
+        },
+        command => panic!("Unexpected command {:?}", command),
+    }
+
+    browser.send_to_devtools(ScriptToDevtoolsControlMsg::DebuggerPaused(
+        browser.pipeline_id,
+        Some(worker_id),
+        pause_in_f(PauseReason::Breakpoint, 1, 2),
+    ));
+    let paused = client.receive();
+    assert_eq!(paused["from"], json!(thread));
+    assert_eq!(paused["frame"]["where"]["actor"], source_actor);
+
+    client.resume(&thread, Some("step"));
+    assert!(matches!(
+        next_debugger_command(&worker_script),
+        DebuggerCommand::Resume(Some(ResumeLimit::Step))
+    ));
+}
@@ -21,7 +21,7 @@ extern crate serde;
 use http::method::Method;
 use http::HeaderMap;
 use ipc_channel::ipc::IpcSender;
-use msg::constellation_msg::{BrowsingContextId, PipelineId};
+use msg::constellation_msg::{BrowsingContextId, PipelineId, SamplerControlMsg};
 use servo_url::ServoUrl;
 use std::net::TcpStream;
 use time::{self, Duration, Tm};
@@ -63,6 +63,8 @@ pub enum ChromeToDevtoolsControlMsg {
     /// A network event occurred (request, reply, etc.). The actor with the
     /// provided name should be notified.
     NetworkEvent(String, NetworkEvent),
+    /// A sampler of the native stacks of script and layout threads was started.
+    NewSampler(IpcSender<SamplerControlMsg>),
 }
 
 /// The state of a page navigation.
@@ -100,6 +102,106 @@ pub enum ScriptToDevtoolsControlMsg {
 
     /// Report a page title change
     TitleChanged(PipelineId, String),
+
+    /// A new script source was compiled in the given global.
+    NewSource(PipelineId, Option<WorkerId>, SourceInfo),
+
+    /// The JS debugger paused execution of the given global.
+    DebuggerPaused(PipelineId, Option<WorkerId>, PauseInfo),
+}
+
+/// A script source known to the JS debugger of a script thread.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SourceInfo {
+    /// An identifier for the source, unique within its script thread.
+    pub id: u32,
+    pub url: String,
+    /// How the source was introduced, e.g. "scriptElement", "eval" or "Function".
+    pub introduction_type: Option<String>,
+    /// The text of the source, if it is still available.
+    pub text: Option<String>,
+}
+
+/// Why the JS debugger paused execution.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum PauseReason {
+    Breakpoint,
+    DebuggerStatement,
+    Interrupted,
+    ResumeLimit,
+}
+
+/// A snapshot of a stack frame of a paused global, youngest first.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FrameInfo {
+    pub display_name: Option<String>,
+    /// The kind of frame: "call", "eval", "global" or "module".
+    pub type_: String,
+    pub source_id: u32,
+    pub line: u32,
+    pub column: u32,
+    /// The scope chain of the frame, innermost first.
+    pub environments: Vec<EnvironmentInfo>,
+}
+
+/// A snapshot of a scope of a paused frame.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EnvironmentInfo {
+    /// The kind of environment: "function", "block", "with" or "object".
+    pub type_: String,
+    pub function_name: Option<String>,
+    pub bindings: Vec<(String, EvaluateJSReply)>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PauseInfo {
+    pub reason: PauseReason,
+    pub frames: Vec<FrameInfo>,
+}
+
+/// How far a paused global should run before pausing again.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ResumeLimit {
+    /// Step over the current line.
+    Next,
+    /// Step into the next line, including calls.
+    Step,
+    /// Step out of the current frame.
+    Finish,
+}
+
+/// Commands for the JS debugger of a script thread.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum DebuggerCommand {
+    /// Set a breakpoint at the given url, line and column.
+    SetBreakpoint(String, u32, u32),
+    /// Remove the breakpoint at the given url, line and column.
+    RemoveBreakpoint(String, u32, u32),
+    /// Pause as soon as any script runs.
+    Interrupt,
+    /// Resume a paused global, optionally pausing again at the given limit.
+    Resume(Option<ResumeLimit>),
+    /// Sample the JS stack of a global at most once per given number of milliseconds,
+    /// sending the samples on the given channel.
+    StartSampling(u32, IpcSender<JSSample>),
+    StopSampling,
+}
+
+/// A JS stack sampled while a global was running script.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JSSample {
+    /// When the sample was taken, in milliseconds since the Unix epoch.
+    pub time: f64,
+    /// The sampled frames, youngest first.
+    pub frames: Vec<SampledFrameInfo>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SampledFrameInfo {
+    pub function_name: Option<String>,
+    pub url: String,
+    pub line: u32,
+    pub column: u32,
 }
 
 /// Serialized JS return values
@@ -231,6 +333,24 @@ pub enum DevtoolScriptControlMsg {
     RequestAnimationFrame(PipelineId, String),
     /// Direct the given pipeline to reload the current page.
     Reload(PipelineId),
+    /// Send a command to the JS debugger of the given pipeline.
+    Debugger(PipelineId, DebuggerCommand),
+    /// Retrieve the stylesheets of the document of the given pipeline, in tree order.
+    GetStyleSheets(PipelineId, IpcSender<Vec<StyleSheetInfo>>),
+    /// Replace the contents of the stylesheet at the given index of the document of the
+    /// given pipeline by the given CSS text.
+    UpdateStyleSheet(PipelineId, usize, String),
+}
+
+/// The details of a document stylesheet, as shown in the style editor.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StyleSheetInfo {
+    pub href: Option<String>,
+    pub title: Option<String>,
+    pub disabled: bool,
+    pub rule_count: u32,
+    /// The serialization of the rules of the stylesheet.
+    pub text: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -123,6 +123,16 @@ pub enum PromptDefinition {
     YesNo(String, IpcSender<PromptResult>),
     /// Ask the user to enter text.
     Input(String, String, IpcSender<Option<String>>),
+    /// Ask the user to enter their username and password.
+    Credentials(IpcSender<PromptCredentialsInput>),
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PromptCredentialsInput {
+    /// Username for http request authentication
+    pub username: Option<String>,
+    /// Password for http request authentication
+    pub password: Option<String>,
 }
 
 #[derive(Deserialize, PartialEq, Serialize)]
@@ -182,6 +192,10 @@ pub enum EmbedderMsg {
     HistoryChanged(Vec<ServoUrl>, usize),
     /// Enter or exit fullscreen
     SetFullscreenState(bool),
+    /// Maximize the window
+    MaximizeWindow,
+    /// Minimize the window
+    MinimizeWindow,
     /// The load of a page has begun
     LoadStart,
     /// The load of a page has completed
@@ -230,6 +244,8 @@ impl Debug for EmbedderMsg {
             EmbedderMsg::CloseBrowser => write!(f, "CloseBrowser"),
             EmbedderMsg::HistoryChanged(..) => write!(f, "HistoryChanged"),
             EmbedderMsg::SetFullscreenState(..) => write!(f, "SetFullscreenState"),
+            EmbedderMsg::MaximizeWindow => write!(f, "MaximizeWindow"),
+            EmbedderMsg::MinimizeWindow => write!(f, "MinimizeWindow"),
             EmbedderMsg::LoadStart => write!(f, "LoadStart"),
             EmbedderMsg::LoadComplete => write!(f, "LoadComplete"),
             EmbedderMsg::Panic(..) => write!(f, "Panic"),
@@ -62,6 +62,7 @@ pub enum Resource {
     RippyPNG,
     MediaControlsCSS,
     MediaControlsJS,
+    DebuggerJS,
 }
 
 pub trait ResourceReaderMethods {
@@ -98,6 +99,7 @@ fn resources_for_tests() -> Box<dyn ResourceReaderMethods + Sync + Send> {
                 Resource::RippyPNG => "rippy.png",
                 Resource::MediaControlsCSS => "media-controls.css",
                 Resource::MediaControlsJS => "media-controls.js",
+                Resource::DebuggerJS => "debugger.js",
             };
             let mut path = env::current_exe().unwrap();
             path = path.canonicalize().unwrap();
@@ -44,6 +44,7 @@ use gfx::text::TextRun;
 use gfx_traits::{combine_id_with_fragment_type, FragmentType, StackingContextId};
 use ipc_channel::ipc;
 use msg::constellation_msg::PipelineId;
+use net_traits::image::base::Image as DecodedImage;
 use net_traits::image_cache::UsePlaceholder;
 use range::Range;
 use script_traits::IFrameSize;
@@ -71,7 +72,8 @@ use style::values::RGBA;
 use style_traits::ToCss;
 use webrender_api::units::{LayoutRect, LayoutTransform, LayoutVector2D};
 use webrender_api::{self, BorderDetails, BorderRadius, BorderSide, BoxShadowClipMode, ColorF};
-use webrender_api::{ColorU, ExternalScrollId, FilterOp, GlyphInstance, ImageRendering, LineStyle};
+use webrender_api::{ColorU, ExternalScrollId, FilterOp, FontInstanceKey, GlyphInstance};
+use webrender_api::{ImageKey, ImageRendering, LineStyle};
 use webrender_api::{NinePatchBorder, NinePatchBorderSource, NormalBorder, PropertyBinding};
 use webrender_api::{ScrollSensitivity, StickyOffsetBounds};
 
@@ -332,6 +334,9 @@ pub struct DisplayListBuildState<'a> {
 
     /// Stores text runs to answer text queries used to place a cursor inside text.
     pub indexable_text: IndexableText,
+
+    /// The decoded images of the image fragments in the display list, used to print it.
+    pub images: FnvHashMap<ImageKey, Arc<DecodedImage>>,
 }
 
 impl<'a> DisplayListBuildState<'a> {
@@ -352,6 +357,7 @@ impl<'a> DisplayListBuildState<'a> {
             ),
             iframe_sizes: Vec::new(),
             indexable_text: IndexableText::default(),
+            images: FnvHashMap::default(),
         }
     }
 
@@ -1856,6 +1862,7 @@ impl Fragment {
                 // Place the image into the display list.
                 if let Some(ref image) = image_fragment.image {
                     if let Some(id) = image.id {
+                        state.images.insert(id, image.clone());
                         let base = create_base_display_item(state);
                         state.add_image_item(
                             base,
@@ -3048,6 +3055,15 @@ impl IndexableText {
         self.inner.get(&node).map(|x| x.as_slice())
     }
 
+    /// Returns a text run for each font instance used by the text in the display list.
+    pub fn font_instances(&self) -> FnvHashMap<FontInstanceKey, Arc<TextRun>> {
+        self.inner
+            .values()
+            .flatten()
+            .map(|item| (item.text_run.font_key, item.text_run.clone()))
+            .collect()
+    }
+
     // Returns the text index within a node for the point of interest.
     pub fn text_index(&self, node: OpaqueNode, point_in_item: Point2D<Au>) -> Option<usize> {
         let item = self.inner.get(&node)?;
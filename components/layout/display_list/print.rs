@@ -0,0 +1,1223 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Paints display lists into paged PDF documents, which is how documents are printed.
+//!
+//! The whole display list is painted once into a form XObject, in CSS pixels. Every page then
+//! paints that form with the transform and clip that bring its slice of the document into the
+//! page's content area.
+
+use crate::display_list::items::{ClipScrollNodeType, DisplayItem, DisplayList};
+use crate::display_list::items::{DisplayListSection, StackingContext};
+use crate::display_list::IndexableText;
+use app_units::Au;
+use euclid::default::{Point2D, Rect, Size2D, Transform2D};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use fnv::FnvHashMap;
+use gfx::font::FontMetrics;
+use gfx::text::TextRun;
+use net_traits::image::base::Image;
+use pixels::PixelFormat;
+use script_traits::{PrintError, PrintSettings};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::io::Write as IoWrite;
+use std::sync::Arc;
+use webrender_api::units::{LayoutRect, LayoutTransform};
+use webrender_api::PropertyBinding;
+use webrender_api::{BorderDetails, BorderSide, BorderStyle, ColorF, FontInstanceKey, ImageKey};
+
+/// The number of PDF points in a CSS pixel.
+const POINTS_PER_PX: f32 = 0.75;
+
+/// Coordinates beyond this many pixels are clamped, so that unbounded clip rectangles stay
+/// representable.
+const MAX_COORDINATE: f32 = 1.0e6;
+
+/// The maximum number of tiles painted for a single repeating image.
+const MAX_TILES: usize = 10_000;
+
+/// Paints `display_list` into a PDF document, paginated according to `settings`.
+///
+/// `images` holds the decoded images of the image items in the display list; images that are
+/// missing from it are not painted, and neither are gradients, shadows and iframes.
+pub fn print_to_pdf(
+    display_list: &DisplayList,
+    text: &IndexableText,
+    images: &FnvHashMap<ImageKey, Arc<Image>>,
+    settings: &PrintSettings,
+) -> Result<Vec<u8>, PrintError> {
+    let bounds = display_list.bounds().to_untyped();
+    let pages = Pages::new(&bounds, settings);
+    let page_numbers: Vec<u32> = (1..=pages.count)
+        .filter(|&page| {
+            settings.page_ranges.is_empty() ||
+                settings
+                    .page_ranges
+                    .iter()
+                    .any(|&(start, end)| start <= page && page <= end)
+        })
+        .collect();
+    if page_numbers.is_empty() {
+        return Err(PrintError::NoPagesInRange);
+    }
+
+    let mut painter = Painter::new(display_list, text.font_instances(), images, settings);
+    painter.paint();
+
+    let mut writer = PdfWriter::new();
+    let catalog = writer.allocate();
+    let page_tree = writer.allocate();
+    let resources = painter.write_resources(&mut writer);
+    let document = writer.allocate();
+    writer.write_stream(
+        document,
+        &format!(
+            "/Type /XObject /Subtype /Form /BBox [{} {} {} {}] /Resources {}",
+            number(bounds.origin.x),
+            number(bounds.origin.y),
+            number(bounds.max_x()),
+            number(bounds.max_y()),
+            resources,
+        ),
+        painter.ops.as_bytes(),
+    );
+
+    let mut kids = vec![];
+    for page_number in page_numbers {
+        let page = writer.allocate();
+        let contents = writer.allocate();
+        writer.write_stream(contents, "", pages.ops(page_number - 1).as_bytes());
+        writer.write_object(
+            page,
+            &format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /XObject << /Document {} 0 R >> >> /Contents {} 0 R >>",
+                page_tree,
+                number(settings.page_size.width * POINTS_PER_PX),
+                number(settings.page_size.height * POINTS_PER_PX),
+                document,
+                contents,
+            ),
+        );
+        kids.push(format!("{} 0 R", page));
+    }
+    writer.write_object(
+        page_tree,
+        &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            kids.len()
+        ),
+    );
+    writer.write_object(
+        catalog,
+        &format!("<< /Type /Catalog /Pages {} 0 R >>", page_tree),
+    );
+    Ok(writer.finish(catalog))
+}
+
+/// How the document is sliced into pages.
+struct Pages {
+    /// The number of pages needed to print the whole document.
+    count: u32,
+    /// The factor by which the document is scaled onto the pages.
+    scale: f32,
+    /// The height of the slice of the document on each page, in document pixels.
+    slice_height: f32,
+    /// The area of each page that the document is painted in, in page pixels, with the origin
+    /// at the bottom left of the page.
+    content_box: Rect<f32>,
+    /// The distance from the top of each page to the content area, in page pixels.
+    margin_top: f32,
+    /// The offset of the document's origin.
+    origin: Point2D<f32>,
+}
+
+impl Pages {
+    fn new(bounds: &Rect<f32>, settings: &PrintSettings) -> Pages {
+        let margins = &settings.margins;
+        let content_size = Size2D::new(
+            settings.page_size.width - margins.left - margins.right,
+            settings.page_size.height - margins.top - margins.bottom,
+        );
+
+        let mut scale = settings.scale;
+        if settings.shrink_to_fit && bounds.size.width * scale > content_size.width {
+            scale = content_size.width / bounds.size.width;
+        }
+
+        let slice_height = content_size.height / scale;
+        let count = (bounds.size.height / slice_height).ceil().max(1.) as u32;
+        Pages {
+            count,
+            scale,
+            slice_height,
+            content_box: Rect::new(Point2D::new(margins.left, margins.bottom), content_size),
+            margin_top: margins.top,
+            origin: bounds.origin,
+        }
+    }
+
+    /// The content stream of the page with the given zero-based index.
+    fn ops(&self, index: u32) -> String {
+        let top = self.origin.y + index as f32 * self.slice_height;
+        let page_height = self.content_box.max_y() + self.margin_top;
+        let mut ops = String::new();
+        write_transform(
+            &mut ops,
+            &Transform2D::create_scale(POINTS_PER_PX, POINTS_PER_PX),
+        );
+        write_rect(&mut ops, &self.content_box);
+        ops.push_str("W n\n");
+        write_transform(
+            &mut ops,
+            &Transform2D::row_major(
+                self.scale,
+                0.,
+                0.,
+                -self.scale,
+                self.content_box.origin.x - self.origin.x * self.scale,
+                page_height - self.margin_top + top * self.scale,
+            ),
+        );
+        ops.push_str("/Document Do\n");
+        ops
+    }
+}
+
+/// A font program that can be embedded into the document.
+struct FontFile {
+    bytes: Vec<u8>,
+    /// Whether the glyphs are CFF outlines, rather than TrueType ones.
+    is_cff: bool,
+}
+
+/// A clip, as the corners of a quadrilateral in document coordinates.
+type Quad = [Point2D<f32>; 4];
+
+/// Translates display items into PDF content stream operators.
+struct Painter<'a> {
+    display_list: &'a DisplayList,
+    settings: &'a PrintSettings,
+    images: &'a FnvHashMap<ImageKey, Arc<Image>>,
+    font_instances: FnvHashMap<FontInstanceKey, Arc<TextRun>>,
+    /// The content stream.
+    ops: String,
+    /// The transforms from the coordinate space of each enclosing stacking context to the
+    /// document coordinate space.
+    transforms: Vec<Transform2D<f32>>,
+    /// The clips that apply to the content of each clip scroll node.
+    node_clips: Vec<Vec<Quad>>,
+    /// The fonts used so far, keyed by the address of their font template.
+    fonts: FnvHashMap<usize, usize>,
+    /// The font files of the fonts used so far, or `None` if they could not be embedded.
+    font_files: Vec<Option<(FontFile, Arc<TextRun>)>>,
+    /// The images used so far.
+    used_images: FnvHashMap<ImageKey, usize>,
+    /// The opacities used so far, out of 255.
+    alphas: BTreeSet<u8>,
+}
+
+impl<'a> Painter<'a> {
+    fn new(
+        display_list: &'a DisplayList,
+        font_instances: FnvHashMap<FontInstanceKey, Arc<TextRun>>,
+        images: &'a FnvHashMap<ImageKey, Arc<Image>>,
+        settings: &'a PrintSettings,
+    ) -> Painter<'a> {
+        Painter {
+            display_list,
+            settings,
+            images,
+            font_instances,
+            ops: String::new(),
+            transforms: vec![Transform2D::identity()],
+            node_clips: vec![vec![]; display_list.clip_scroll_nodes.len().max(2)],
+            fonts: FnvHashMap::default(),
+            font_files: vec![],
+            used_images: FnvHashMap::default(),
+            alphas: BTreeSet::new(),
+        }
+    }
+
+    fn transform(&self) -> Transform2D<f32> {
+        *self.transforms.last().unwrap()
+    }
+
+    fn paint(&mut self) {
+        let display_list = self.display_list;
+        for item in &display_list.list {
+            match *item {
+                DisplayItem::PushStackingContext(ref item) => {
+                    let clipping_and_scrolling = item.base.clipping_and_scrolling;
+                    let clip_index = clipping_and_scrolling
+                        .clipping
+                        .unwrap_or(clipping_and_scrolling.scrolling);
+                    let stacking_context = &item.stacking_context;
+                    if let Some(frame_index) = stacking_context.established_reference_frame {
+                        self.node_clips[frame_index.to_index()] =
+                            self.node_clips[clip_index.to_index()].clone();
+                    }
+                    let transform = stacking_context_transform(stacking_context)
+                        .post_transform(&self.transform());
+                    self.transforms.push(transform);
+                },
+                DisplayItem::PopStackingContext(_) => {
+                    self.transforms.pop();
+                },
+                DisplayItem::DefineClipScrollNode(ref item) => {
+                    let node = &display_list.clip_scroll_nodes[item.node_index.to_index()];
+                    let mut clips = self.node_clips[node.parent_index.to_index()].clone();
+                    match node.node_type {
+                        ClipScrollNodeType::Clip(_) | ClipScrollNodeType::ScrollFrame(..) => {
+                            clips.push(quad(&node.clip.main, &self.transform()));
+                        },
+                        ClipScrollNodeType::StickyFrame(_) | ClipScrollNodeType::Placeholder => {},
+                    }
+                    self.node_clips[item.node_index.to_index()] = clips;
+                },
+                _ => self.paint_item(item),
+            }
+        }
+    }
+
+    fn paint_item(&mut self, item: &DisplayItem) {
+        let base = item.base();
+        let is_background = match base.section {
+            DisplayListSection::BackgroundAndBorders |
+            DisplayListSection::BlockBackgroundsAndBorders => match *item {
+                DisplayItem::Rectangle(_) |
+                DisplayItem::Image(_) |
+                DisplayItem::RepeatingImage(_) => true,
+                _ => false,
+            },
+            DisplayListSection::Content | DisplayListSection::Outlines => false,
+        };
+        if is_background && !self.settings.background {
+            return;
+        }
+
+        let mut ops = String::new();
+        match *item {
+            DisplayItem::Rectangle(ref item) => {
+                let color = match item.item.color {
+                    PropertyBinding::Value(color) => color,
+                    PropertyBinding::Binding(_, color) => color,
+                };
+                if !self.set_color(&mut ops, color) {
+                    return;
+                }
+                write_rect(&mut ops, &item.item.bounds.to_untyped());
+                ops.push_str("f\n");
+            },
+            DisplayItem::Text(ref item) => {
+                let font = match self.font(item.item.font_key) {
+                    Some(font) => font,
+                    None => return,
+                };
+                if !self.set_color(&mut ops, item.item.color) {
+                    return;
+                }
+                let size = self.font_instances[&item.item.font_key]
+                    .actual_pt_size
+                    .to_f32_px();
+                let _ = writeln!(ops, "BT /F{} 1 Tf", font);
+                for glyph in &item.data {
+                    // The text matrix flips the glyphs back up, since the document's y axis
+                    // points down.
+                    let _ = writeln!(
+                        ops,
+                        "{} 0 0 {} {} {} Tm <{:04X}> Tj",
+                        number(size),
+                        number(-size),
+                        number(glyph.point.x),
+                        number(glyph.point.y),
+                        glyph.index,
+                    );
+                }
+                ops.push_str("ET\n");
+            },
+            DisplayItem::Image(ref item) => {
+                let image = match self.image(item.item.image_key) {
+                    Some(image) => image,
+                    None => return,
+                };
+                paint_image(&mut ops, image, &item.item.bounds.to_untyped());
+            },
+            DisplayItem::RepeatingImage(ref item) => {
+                let image = match self.image(item.item.image_key) {
+                    Some(image) => image,
+                    None => return,
+                };
+                let bounds = item.item.bounds.to_untyped();
+                let tile_size = item.item.stretch_size.to_untyped();
+                let step = tile_size + item.item.tile_spacing.to_untyped();
+                if tile_size.is_empty_or_negative() {
+                    return;
+                }
+                write_rect(&mut ops, &bounds);
+                ops.push_str("W n\n");
+                let mut tiles = 0;
+                let mut y = bounds.origin.y;
+                while y < bounds.max_y() && tiles < MAX_TILES {
+                    let mut x = bounds.origin.x;
+                    while x < bounds.max_x() && tiles < MAX_TILES {
+                        paint_image(&mut ops, image, &Rect::new(Point2D::new(x, y), tile_size));
+                        x += step.width;
+                        tiles += 1;
+                    }
+                    y += step.height;
+                }
+            },
+            DisplayItem::Border(ref item) => {
+                let border = match item.item.details {
+                    BorderDetails::Normal(ref border) => border,
+                    BorderDetails::NinePatch(_) => return,
+                };
+                let bounds = item.item.bounds.to_untyped();
+                let widths = item.item.widths;
+                let outer = quad_from_rect(&bounds, &Transform2D::identity());
+                let inner = quad_from_rect(
+                    &Rect::new(
+                        Point2D::new(bounds.origin.x + widths.left, bounds.origin.y + widths.top),
+                        Size2D::new(
+                            bounds.size.width - widths.left - widths.right,
+                            bounds.size.height - widths.top - widths.bottom,
+                        ),
+                    ),
+                    &Transform2D::identity(),
+                );
+                // Each side is the trapezoid between the outer and inner border edges.
+                let sides = [
+                    (&border.top, widths.top, 0, 1),
+                    (&border.right, widths.right, 1, 2),
+                    (&border.bottom, widths.bottom, 2, 3),
+                    (&border.left, widths.left, 3, 0),
+                ];
+                for &(side, width, start, end) in sides.iter() {
+                    if width <= 0. || !is_painted(side) {
+                        continue;
+                    }
+                    ops.push_str("q\n");
+                    if self.set_color(&mut ops, side.color) {
+                        write_path(
+                            &mut ops,
+                            &[outer[start], outer[end], inner[end], inner[start]],
+                        );
+                        ops.push_str("f\n");
+                    }
+                    ops.push_str("Q\n");
+                }
+            },
+            DisplayItem::Line(ref item) => {
+                if !self.set_color(&mut ops, item.item.color) {
+                    return;
+                }
+                write_rect(&mut ops, &item.item.area.to_untyped());
+                ops.push_str("f\n");
+            },
+            DisplayItem::Gradient(_) |
+            DisplayItem::RadialGradient(_) |
+            DisplayItem::BoxShadow(_) |
+            DisplayItem::PushTextShadow(_) |
+            DisplayItem::PopAllTextShadows(_) |
+            DisplayItem::Iframe(_) |
+            DisplayItem::PushStackingContext(_) |
+            DisplayItem::PopStackingContext(_) |
+            DisplayItem::DefineClipScrollNode(_) => return,
+        }
+
+        let clipping_and_scrolling = base.clipping_and_scrolling;
+        let clip_index = clipping_and_scrolling
+            .clipping
+            .unwrap_or(clipping_and_scrolling.scrolling);
+        self.ops.push_str("q\n");
+        for clip in &self.node_clips[clip_index.to_index()] {
+            write_path(&mut self.ops, clip);
+            self.ops.push_str("W n\n");
+        }
+        let transform = self.transform();
+        write_transform(&mut self.ops, &transform);
+        write_rect(&mut self.ops, &clamp(&base.clip_rect));
+        self.ops.push_str("W n\n");
+        self.ops.push_str(&ops);
+        self.ops.push_str("Q\n");
+    }
+
+    /// Sets the fill color, returning false if it is fully transparent.
+    fn set_color(&mut self, ops: &mut String, color: ColorF) -> bool {
+        let alpha = (color.a.max(0.).min(1.) * 255.).round() as u8;
+        if alpha == 0 {
+            return false;
+        }
+        let _ = writeln!(
+            ops,
+            "{} {} {} rg",
+            number(color.r),
+            number(color.g),
+            number(color.b)
+        );
+        if alpha < 255 {
+            self.alphas.insert(alpha);
+            let _ = writeln!(ops, "/GS{} gs", alpha);
+        }
+        true
+    }
+
+    /// Returns the index of the font used by the given font instance, if it can be embedded.
+    fn font(&mut self, font_key: FontInstanceKey) -> Option<usize> {
+        let text_run = self.font_instances.get(&font_key)?.clone();
+        let template = &*text_run.font_template as *const _ as usize;
+        if let Some(&index) = self.fonts.get(&template) {
+            return self.font_files[index].as_ref().map(|_| index);
+        }
+
+        let index = self.font_files.len();
+        let font_file = font_file(text_run.font_template.bytes());
+        if font_file.is_none() {
+            warn!("Not printing text in a font that can't be embedded.");
+        }
+        self.fonts.insert(template, index);
+        self.font_files
+            .push(font_file.map(|font_file| (font_file, text_run.clone())));
+        self.font_files[index].as_ref().map(|_| index)
+    }
+
+    /// Returns the index of the given image, if it is known.
+    fn image(&mut self, image_key: ImageKey) -> Option<usize> {
+        if !self.images.contains_key(&image_key) {
+            return None;
+        }
+        let count = self.used_images.len();
+        Some(*self.used_images.entry(image_key).or_insert(count))
+    }
+
+    /// Writes the fonts, images and graphics states used by the content stream, returning its
+    /// resource dictionary.
+    fn write_resources(&self, writer: &mut PdfWriter) -> String {
+        let mut fonts = String::new();
+        for (index, font) in self.font_files.iter().enumerate() {
+            if let Some((ref font_file, ref text_run)) = *font {
+                let id = write_font(
+                    writer,
+                    index,
+                    font_file,
+                    text_run.actual_pt_size,
+                    &text_run.font_metrics,
+                );
+                let _ = write!(fonts, "/F{} {} 0 R ", index, id);
+            }
+        }
+
+        let mut images = vec![None; self.used_images.len()];
+        for (image_key, &index) in &self.used_images {
+            images[index] = Some(write_image(writer, &self.images[image_key]));
+        }
+        let mut x_objects = String::new();
+        for (index, id) in images.into_iter().enumerate() {
+            let _ = write!(x_objects, "/Im{} {} 0 R ", index, id.unwrap());
+        }
+
+        let mut graphics_states = String::new();
+        for alpha in &self.alphas {
+            let alpha_value = number(*alpha as f32 / 255.);
+            let _ = write!(
+                graphics_states,
+                "/GS{} << /ca {} /CA {} >> ",
+                alpha, alpha_value, alpha_value
+            );
+        }
+
+        format!(
+            "<< /Font << {}>> /XObject << {}>> /ExtGState << {}>> >>",
+            fonts, x_objects, graphics_states
+        )
+    }
+}
+
+/// The transform from the coordinate space of the content of a stacking context to that of its
+/// parent.
+fn stacking_context_transform(stacking_context: &StackingContext) -> Transform2D<f32> {
+    let origin = stacking_context.bounds.origin;
+    let translation = Transform2D::create_translation(origin.x, origin.y);
+    if stacking_context.established_reference_frame.is_none() {
+        return translation;
+    }
+
+    // As in WebRender, the transform of a reference frame applies before its origin offset.
+    let transform = match (stacking_context.transform, stacking_context.perspective) {
+        (Some(transform), Some(perspective)) => transform.pre_transform(&perspective),
+        (Some(transform), None) => transform,
+        (None, Some(perspective)) => perspective,
+        (None, None) => LayoutTransform::identity(),
+    };
+    Transform2D::row_major(
+        transform.m11,
+        transform.m12,
+        transform.m21,
+        transform.m22,
+        transform.m41,
+        transform.m42,
+    )
+    .post_transform(&translation)
+}
+
+fn is_painted(side: &BorderSide) -> bool {
+    match side.style {
+        BorderStyle::None | BorderStyle::Hidden => false,
+        _ => side.color.a > 0.,
+    }
+}
+
+fn clamp(rect: &LayoutRect) -> Rect<f32> {
+    let clamp = |value: f32| value.max(-MAX_COORDINATE).min(MAX_COORDINATE);
+    let origin = Point2D::new(clamp(rect.origin.x), clamp(rect.origin.y));
+    Rect::new(
+        origin,
+        Size2D::new(
+            clamp(rect.max_x()) - origin.x,
+            clamp(rect.max_y()) - origin.y,
+        ),
+    )
+}
+
+fn quad(rect: &LayoutRect, transform: &Transform2D<f32>) -> Quad {
+    quad_from_rect(&clamp(rect), transform)
+}
+
+fn quad_from_rect(rect: &Rect<f32>, transform: &Transform2D<f32>) -> Quad {
+    [
+        transform.transform_point(rect.origin),
+        transform.transform_point(Point2D::new(rect.max_x(), rect.origin.y)),
+        transform.transform_point(Point2D::new(rect.max_x(), rect.max_y())),
+        transform.transform_point(Point2D::new(rect.origin.x, rect.max_y())),
+    ]
+}
+
+fn paint_image(ops: &mut String, image: usize, bounds: &Rect<f32>) {
+    // Images are painted into the unit square, with their first row at the top.
+    ops.push_str("q\n");
+    write_transform(
+        ops,
+        &Transform2D::row_major(
+            bounds.size.width,
+            0.,
+            0.,
+            -bounds.size.height,
+            bounds.origin.x,
+            bounds.max_y(),
+        ),
+    );
+    let _ = writeln!(ops, "/Im{} Do\nQ", image);
+}
+
+fn write_transform(ops: &mut String, transform: &Transform2D<f32>) {
+    let _ = writeln!(
+        ops,
+        "{} {} {} {} {} {} cm",
+        number(transform.m11),
+        number(transform.m12),
+        number(transform.m21),
+        number(transform.m22),
+        number(transform.m31),
+        number(transform.m32),
+    );
+}
+
+fn write_rect(ops: &mut String, rect: &Rect<f32>) {
+    let _ = writeln!(
+        ops,
+        "{} {} {} {} re",
+        number(rect.origin.x),
+        number(rect.origin.y),
+        number(rect.size.width),
+        number(rect.size.height),
+    );
+}
+
+fn write_path(ops: &mut String, points: &[Point2D<f32>]) {
+    for (index, point) in points.iter().enumerate() {
+        let operator = if index == 0 { "m" } else { "l" };
+        let _ = writeln!(ops, "{} {} {}", number(point.x), number(point.y), operator);
+    }
+    ops.push_str("h\n");
+}
+
+/// Formats a number for a PDF content stream, which does not allow exponents.
+fn number(value: f32) -> String {
+    let value = if value.is_finite() { value } else { 0. };
+    let formatted = format!("{:.3}", value);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    match formatted {
+        "" | "-" | "-0" => "0".to_owned(),
+        _ => formatted.to_owned(),
+    }
+}
+
+/// Returns the font program of a font file, extracting the first font of a font collection.
+fn font_file(bytes: Vec<u8>) -> Option<FontFile> {
+    match bytes.get(0..4)? {
+        b"\x00\x01\x00\x00" | b"true" => Some(FontFile {
+            bytes,
+            is_cff: false,
+        }),
+        b"OTTO" => Some(FontFile {
+            bytes,
+            is_cff: true,
+        }),
+        b"ttcf" => {
+            let offset = read_u32(&bytes, 12)? as usize;
+            first_font_in_collection(&bytes, offset)
+        },
+        _ => None,
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from(bytes[0]) << 8 | u16::from(bytes[1]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from(read_u16(bytes, offset)?) << 16 | u32::from(read_u16(bytes, offset + 2)?))
+}
+
+/// Copies the font whose table directory is at `offset` out of a font collection.
+fn first_font_in_collection(collection: &[u8], offset: usize) -> Option<FontFile> {
+    const HEADER_SIZE: usize = 12;
+    const TABLE_RECORD_SIZE: usize = 16;
+
+    let table_count = read_u16(collection, offset.checked_add(4)?)? as usize;
+    let directory_size = HEADER_SIZE + table_count * TABLE_RECORD_SIZE;
+    let mut directory = collection
+        .get(offset..offset.checked_add(directory_size)?)?
+        .to_vec();
+    let mut tables = vec![];
+    for index in 0..table_count {
+        let record = HEADER_SIZE + index * TABLE_RECORD_SIZE;
+        let table_offset = read_u32(&directory, record + 8)? as usize;
+        let table_length = read_u32(&directory, record + 12)? as usize;
+        let table = collection.get(table_offset..table_offset.checked_add(table_length)?)?;
+
+        // Tables are four-byte aligned.
+        let new_offset = (directory_size + tables.len()) as u32;
+        directory[record + 8..record + 12].copy_from_slice(&new_offset.to_be_bytes());
+        tables.extend_from_slice(table);
+        while tables.len() % 4 != 0 {
+            tables.push(0);
+        }
+    }
+
+    let is_cff = directory[0..4] == *b"OTTO";
+    directory.extend(tables);
+    Some(FontFile {
+        bytes: directory,
+        is_cff,
+    })
+}
+
+/// Writes a composite font that maps two-byte glyph indices to the glyphs of the font file.
+fn write_font(
+    writer: &mut PdfWriter,
+    index: usize,
+    font_file: &FontFile,
+    size: Au,
+    metrics: &FontMetrics,
+) -> usize {
+    let font = writer.allocate();
+    let descendant = writer.allocate();
+    let descriptor = writer.allocate();
+    let file = writer.allocate();
+
+    let (file_key, file_subtype) = if font_file.is_cff {
+        ("FontFile3", "/Subtype /OpenType ")
+    } else {
+        ("FontFile2", "")
+    };
+    writer.write_stream(
+        file,
+        &format!("{}/Length1 {}", file_subtype, font_file.bytes.len()),
+        &font_file.bytes,
+    );
+
+    // Font descriptor metrics are in thousandths of the font size.
+    let size = size.to_f32_px().max(1.);
+    let metric = |value: Au| number(value.to_f32_px() / size * 1000.);
+    writer.write_object(
+        descriptor,
+        &format!(
+            "<< /Type /FontDescriptor /FontName /ServoFont{} /Flags 32 \
+             /FontBBox [0 -{} {} {}] /ItalicAngle 0 /Ascent {} /Descent -{} \
+             /CapHeight {} /StemV 80 /{} {} 0 R >>",
+            index,
+            metric(metrics.descent),
+            metric(metrics.max_advance),
+            metric(metrics.ascent),
+            metric(metrics.ascent),
+            metric(metrics.descent),
+            metric(metrics.ascent),
+            file_key,
+            file,
+        ),
+    );
+
+    let (subtype, cid_to_gid_map) = if font_file.is_cff {
+        ("CIDFontType0", "")
+    } else {
+        ("CIDFontType2", "/CIDToGIDMap /Identity ")
+    };
+    writer.write_object(
+        descendant,
+        &format!(
+            "<< /Type /Font /Subtype /{} /BaseFont /ServoFont{} \
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+             {}/FontDescriptor {} 0 R >>",
+            subtype, index, cid_to_gid_map, descriptor,
+        ),
+    );
+    writer.write_object(
+        font,
+        &format!(
+            "<< /Type /Font /Subtype /Type0 /BaseFont /ServoFont{} /Encoding /Identity-H \
+             /DescendantFonts [{} 0 R] >>",
+            index, descendant,
+        ),
+    );
+    font
+}
+
+/// Writes an image as RGB samples, with a soft mask when it is not opaque.
+fn write_image(writer: &mut PdfWriter, image: &Image) -> usize {
+    let (channels, alpha_channel, color_channels): (usize, Option<usize>, [usize; 3]) =
+        match image.format {
+            PixelFormat::K8 => (1, None, [0, 0, 0]),
+            PixelFormat::KA8 => (2, Some(1), [0, 0, 0]),
+            PixelFormat::RGB8 => (3, None, [0, 1, 2]),
+            PixelFormat::RGBA8 => (4, Some(3), [0, 1, 2]),
+            PixelFormat::BGRA8 => (4, Some(3), [2, 1, 0]),
+        };
+    let mut samples = Vec::with_capacity(image.bytes.len() / channels * 3);
+    let mut alpha = Vec::with_capacity(image.bytes.len() / channels);
+    for pixel in image.bytes.chunks_exact(channels) {
+        samples.extend(color_channels.iter().map(|&channel| pixel[channel]));
+        alpha.push(alpha_channel.map_or(255, |channel| pixel[channel]));
+    }
+
+    let image_dictionary = format!(
+        "/Type /XObject /Subtype /Image /Width {} /Height {} /BitsPerComponent 8",
+        image.width, image.height,
+    );
+    let soft_mask = if alpha.iter().any(|&alpha| alpha != 255) {
+        let mask = writer.allocate();
+        writer.write_stream(
+            mask,
+            &format!("{} /ColorSpace /DeviceGray", image_dictionary),
+            &alpha,
+        );
+        format!(" /SMask {} 0 R", mask)
+    } else {
+        String::new()
+    };
+
+    let id = writer.allocate();
+    writer.write_stream(
+        id,
+        &format!("{} /ColorSpace /DeviceRGB{}", image_dictionary, soft_mask),
+        &samples,
+    );
+    id
+}
+
+/// Serializes the objects of a PDF document.
+struct PdfWriter {
+    buffer: Vec<u8>,
+    /// The byte offset of each object, indexed by object number minus one.
+    offsets: Vec<Option<usize>>,
+}
+
+impl PdfWriter {
+    fn new() -> PdfWriter {
+        PdfWriter {
+            // The comment with binary characters marks the file as binary.
+            buffer: b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec(),
+            offsets: vec![],
+        }
+    }
+
+    /// Allocates the number of a new object.
+    fn allocate(&mut self) -> usize {
+        self.offsets.push(None);
+        self.offsets.len()
+    }
+
+    fn write_object(&mut self, id: usize, object: &str) {
+        self.begin_object(id);
+        self.buffer.extend_from_slice(object.as_bytes());
+        self.buffer.extend_from_slice(b"\nendobj\n");
+    }
+
+    /// Writes a compressed stream object, with extra entries for its dictionary.
+    fn write_stream(&mut self, id: usize, dictionary: &str, data: &[u8]) {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        let data = encoder.finish().unwrap();
+
+        self.begin_object(id);
+        let dictionary = format!(
+            "<< {} /Filter /FlateDecode /Length {} >>\nstream\n",
+            dictionary,
+            data.len()
+        );
+        self.buffer.extend_from_slice(dictionary.as_bytes());
+        self.buffer.extend_from_slice(&data);
+        self.buffer.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn begin_object(&mut self, id: usize) {
+        self.offsets[id - 1] = Some(self.buffer.len());
+        self.buffer
+            .extend_from_slice(format!("{} 0 obj\n", id).as_bytes());
+    }
+
+    /// Writes the cross-reference table and trailer, and returns the document.
+    fn finish(mut self, root: usize) -> Vec<u8> {
+        let xref_offset = self.buffer.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            let offset = offset.expect("Object was allocated but never written.");
+            let _ = writeln!(xref, "{:010} 00000 n ", offset);
+        }
+        let _ = writeln!(
+            xref,
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF",
+            self.offsets.len() + 1,
+            root,
+            xref_offset
+        );
+        self.buffer.extend_from_slice(xref.as_bytes());
+        self.buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use euclid::SideOffsets2D;
+
+    fn settings(width: f32, height: f32, margin: f32) -> PrintSettings {
+        PrintSettings {
+            page_size: euclid::Size2D::new(width, height),
+            margins: SideOffsets2D::new_all_same(margin),
+            scale: 1.,
+            shrink_to_fit: false,
+            background: false,
+            page_ranges: vec![],
+        }
+    }
+
+    fn document(width: f32, height: f32) -> Rect<f32> {
+        Rect::new(Point2D::zero(), Size2D::new(width, height))
+    }
+
+    #[test]
+    fn pages_slice_the_document_into_their_content_area() {
+        let pages = Pages::new(&document(50., 500.), &settings(100., 200., 10.));
+        assert_eq!(pages.count, 3);
+        assert_eq!(pages.scale, 1.);
+        assert_eq!(pages.slice_height, 180.);
+        assert_eq!(
+            pages.content_box,
+            Rect::new(Point2D::new(10., 10.), Size2D::new(80., 180.))
+        );
+    }
+
+    #[test]
+    fn empty_documents_have_one_page() {
+        let pages = Pages::new(&document(0., 0.), &settings(100., 200., 10.));
+        assert_eq!(pages.count, 1);
+    }
+
+    #[test]
+    fn landscape_pages_hold_shorter_slices() {
+        let pages = Pages::new(&document(50., 500.), &settings(200., 100., 10.));
+        assert_eq!(pages.slice_height, 80.);
+        assert_eq!(pages.count, 7);
+    }
+
+    #[test]
+    fn shrink_to_fit_only_scales_wide_documents_down() {
+        let mut settings = settings(100., 200., 10.);
+        settings.shrink_to_fit = true;
+
+        let pages = Pages::new(&document(160., 500.), &settings);
+        assert_eq!(pages.scale, 0.5);
+        assert_eq!(pages.slice_height, 360.);
+        assert_eq!(pages.count, 2);
+
+        let pages = Pages::new(&document(40., 500.), &settings);
+        assert_eq!(pages.scale, 1.);
+    }
+
+    #[test]
+    fn pages_paint_their_slice_of_the_document() {
+        let pages = Pages::new(&document(50., 500.), &settings(100., 200., 10.));
+        assert_eq!(
+            pages.ops(1),
+            "0.75 0 0 0.75 0 0 cm\n10 10 80 180 re\nW n\n1 0 0 -1 10 370 cm\n/Document Do\n"
+        );
+    }
+
+    #[test]
+    fn numbers_have_no_exponents_or_trailing_zeros() {
+        assert_eq!(number(2.), "2");
+        assert_eq!(number(1.5), "1.5");
+        assert_eq!(number(-3.25), "-3.25");
+        assert_eq!(number(0.12345), "0.123");
+        assert_eq!(number(-0.0001), "0");
+        assert_eq!(number(1.0e10), "10000000000");
+        assert_eq!(number(std::f32::NAN), "0");
+        assert_eq!(number(std::f32::INFINITY), "0");
+    }
+
+    /// A font collection with one font, which has the given version and a single table.
+    fn collection(version: &[u8; 4], table: &[u8]) -> Vec<u8> {
+        const TABLE_OFFSET: u32 = 16 + 12 + 16;
+        let mut bytes = b"ttcf\x00\x01\x00\x00\x00\x00\x00\x01".to_vec();
+        bytes.extend_from_slice(&16u32.to_be_bytes());
+        bytes.extend_from_slice(version);
+        bytes.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(b"test\x00\x00\x00\x00");
+        bytes.extend_from_slice(&TABLE_OFFSET.to_be_bytes());
+        bytes.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(table);
+        bytes
+    }
+
+    #[test]
+    fn font_files_are_recognized_by_their_version() {
+        assert!(!font_file(b"\x00\x01\x00\x00".to_vec()).unwrap().is_cff);
+        assert!(!font_file(b"true".to_vec()).unwrap().is_cff);
+        assert!(font_file(b"OTTO".to_vec()).unwrap().is_cff);
+        assert!(font_file(b"wOFF".to_vec()).is_none());
+        assert!(font_file(b"OTT".to_vec()).is_none());
+        assert!(font_file(vec![]).is_none());
+    }
+
+    #[test]
+    fn the_first_font_of_a_collection_is_extracted() {
+        let font = font_file(collection(b"OTTO", b"abc")).unwrap();
+        assert!(font.is_cff);
+        assert_eq!(&font.bytes[0..4], b"OTTO");
+        assert_eq!(read_u32(&font.bytes, 12 + 8), Some(28));
+        assert_eq!(read_u32(&font.bytes, 12 + 12), Some(3));
+        assert_eq!(&font.bytes[28..], b"abc\x00");
+
+        let font = font_file(collection(b"\x00\x01\x00\x00", b"abcd")).unwrap();
+        assert!(!font.is_cff);
+        assert_eq!(&font.bytes[28..], b"abcd");
+    }
+
+    #[test]
+    fn truncated_collections_are_rejected() {
+        let collection = collection(b"OTTO", b"abc");
+        for length in 0..collection.len() {
+            assert!(font_file(collection[..length].to_vec()).is_none());
+        }
+    }
+
+    #[test]
+    fn collections_with_out_of_range_offsets_are_rejected() {
+        let mut font_offset = collection(b"OTTO", b"abc");
+        font_offset[12..16].copy_from_slice(&[0xFF; 4]);
+        assert!(font_file(font_offset).is_none());
+
+        let mut table_offset = collection(b"OTTO", b"abc");
+        table_offset[16 + 12 + 8..16 + 12 + 12].copy_from_slice(&[0xFF; 4]);
+        assert!(font_file(table_offset).is_none());
+
+        let mut table_length = collection(b"OTTO", b"abc");
+        table_length[16 + 12 + 12..16 + 12 + 16].copy_from_slice(&[0xFF; 4]);
+        assert!(first_font_in_collection(&table_length, 16).is_none());
+
+        let mut table_count = collection(b"OTTO", b"abc");
+        table_count[16 + 4..16 + 6].copy_from_slice(&[0xFF; 2]);
+        assert!(first_font_in_collection(&table_count, 16).is_none());
+        assert!(first_font_in_collection(&table_count, std::usize::MAX - 1).is_none());
+    }
+
+    fn metrics(ascent: i32, descent: i32) -> FontMetrics {
+        FontMetrics {
+            underline_size: Au(0),
+            underline_offset: Au(0),
+            strikeout_size: Au(0),
+            strikeout_offset: Au(0),
+            leading: Au(0),
+            x_height: Au(0),
+            em_size: Au(0),
+            ascent: Au(ascent),
+            descent: Au(descent),
+            max_advance: Au(0),
+            average_advance: Au(0),
+            line_gap: Au(0),
+        }
+    }
+
+    fn written_font(font_file: &FontFile, size: Au, metrics: &FontMetrics) -> String {
+        let mut writer = PdfWriter::new();
+        let font = write_font(&mut writer, 0, font_file, size, metrics);
+        String::from_utf8_lossy(&writer.finish(font)).into_owned()
+    }
+
+    #[test]
+    fn truetype_fonts_are_embedded_with_an_identity_glyph_map() {
+        let font_file = FontFile {
+            bytes: b"\x00\x01\x00\x00".to_vec(),
+            is_cff: false,
+        };
+        let pdf = written_font(&font_file, Au::from_px(10), &metrics(480, 120));
+        assert!(pdf.contains("<< /Length1 4 /Filter /FlateDecode"));
+        assert!(pdf.contains("/Subtype /CIDFontType2"));
+        assert!(pdf.contains("/CIDToGIDMap /Identity"));
+        assert!(pdf.contains("/FontFile2 4 0 R"));
+        assert!(pdf.contains("/Ascent 800 /Descent -200"));
+    }
+
+    #[test]
+    fn cff_fonts_are_embedded_as_opentype() {
+        let font_file = FontFile {
+            bytes: b"OTTO".to_vec(),
+            is_cff: true,
+        };
+        let pdf = written_font(&font_file, Au::from_px(10), &metrics(0, 0));
+        assert!(pdf.contains("<< /Subtype /OpenType /Length1 4 /Filter /FlateDecode"));
+        assert!(pdf.contains("/Subtype /CIDFontType0"));
+        assert!(!pdf.contains("/CIDToGIDMap"));
+        assert!(pdf.contains("/FontFile3 4 0 R"));
+    }
+
+    #[test]
+    fn fonts_without_a_size_have_finite_metrics() {
+        let font_file = FontFile {
+            bytes: vec![],
+            is_cff: false,
+        };
+        let pdf = written_font(&font_file, Au(0), &metrics(60, 60));
+        assert!(pdf.contains("/Ascent 1000 /Descent -1000"));
+        assert!(!pdf.contains("NaN"));
+        assert!(!pdf.contains("inf"));
+    }
+
+    #[test]
+    fn cross_reference_table_points_at_every_object() {
+        let mut writer = PdfWriter::new();
+        let first = writer.allocate();
+        let second = writer.allocate();
+        writer.write_object(second, "<< >>");
+        writer.write_stream(first, "", b"data");
+        let pdf = writer.finish(first);
+
+        // Offsets are in bytes, and the header comment is not UTF-8.
+        let startxref = pdf
+            .windows(10)
+            .rposition(|window| window == b"startxref\n")
+            .unwrap();
+        assert!(pdf[..startxref].ends_with(b"trailer\n<< /Size 3 /Root 1 0 R >>\n"));
+        let tail = std::str::from_utf8(&pdf[startxref..]).unwrap();
+        assert!(tail.ends_with("\n%%EOF\n"));
+        let xref_offset: usize = tail.lines().nth(1).unwrap().parse().unwrap();
+        let xref = std::str::from_utf8(&pdf[xref_offset..startxref]).unwrap();
+        assert!(xref.starts_with("xref\n0 3\n0000000000 65535 f \n"));
+        for (index, entry) in xref.lines().skip(3).take(2).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            let header = format!("{} 0 obj\n", index + 1);
+            assert!(pdf[offset..].starts_with(header.as_bytes()));
+        }
+    }
+
+    /// A display list with nothing but a root stacking context of the given size.
+    fn display_list(width: f32, height: f32) -> DisplayList {
+        let mut stacking_context = StackingContext::root();
+        stacking_context.bounds = LayoutRect::from_untyped(&document(width, height));
+        let (push, pop) = stacking_context.to_display_list_items();
+        DisplayList {
+            list: vec![push, pop],
+            clip_scroll_nodes: vec![],
+        }
+    }
+
+    /// The byte offsets of the objects in the cross-reference table of `pdf`.
+    fn object_offsets(pdf: &[u8]) -> Vec<usize> {
+        let startxref = pdf
+            .windows(10)
+            .rposition(|window| window == b"startxref\n")
+            .unwrap();
+        let tail = std::str::from_utf8(&pdf[startxref..]).unwrap();
+        let xref_offset: usize = tail.lines().nth(1).unwrap().parse().unwrap();
+        let xref = std::str::from_utf8(&pdf[xref_offset..startxref]).unwrap();
+        xref.lines()
+            .filter(|entry| entry.ends_with(" n "))
+            .map(|entry| entry[..10].parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn only_pages_in_the_requested_ranges_are_printed() {
+        let display_list = display_list(50., 1000.);
+        let text = IndexableText::default();
+        let images = FnvHashMap::default();
+        let mut settings = settings(100., 200., 0.);
+        settings.page_ranges = vec![(2, 3)];
+
+        // A slice is 200px high, so the document spans five pages.
+        let pdf = print_to_pdf(&display_list, &text, &images, &settings).unwrap();
+        let offsets = object_offsets(&pdf);
+        assert!(!offsets.is_empty());
+        for (index, &offset) in offsets.iter().enumerate() {
+            let header = format!("{} 0 obj\n", index + 1);
+            assert!(pdf[offset..].starts_with(header.as_bytes()));
+        }
+
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 2 >>"));
+        assert_eq!(pdf.matches("/Type /Page /Parent").count(), 2);
+    }
+
+    #[test]
+    fn landscape_page_sizes_are_written_to_the_media_box() {
+        let display_list = display_list(50., 50.);
+        let text = IndexableText::default();
+        let images = FnvHashMap::default();
+
+        let pdf = print_to_pdf(&display_list, &text, &images, &settings(200., 100., 10.)).unwrap();
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert!(pdf.contains("/MediaBox [0 0 150 75]"));
+    }
+
+    #[test]
+    fn documents_have_a_page_per_slice_in_the_requested_ranges() {
+        let display_list = DisplayList {
+            list: vec![],
+            clip_scroll_nodes: vec![],
+        };
+        let text = IndexableText::default();
+        let images = FnvHashMap::default();
+        let settings = settings(100., 200., 10.);
+
+        let pdf = print_to_pdf(&display_list, &text, &images, &settings).unwrap();
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with("%PDF-1.7\n"));
+        assert!(pdf.contains("/Type /Pages /Kids [4 0 R] /Count 1 >>"));
+        assert!(pdf.contains("/MediaBox [0 0 75 150]"));
+
+        let mut settings = settings;
+        settings.page_ranges = vec![(2, 3)];
+        assert_eq!(
+            print_to_pdf(&display_list, &text, &images, &settings),
+            Err(PrintError::NoPagesInRange)
+        );
+    }
+}
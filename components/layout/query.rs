@@ -17,8 +17,10 @@ use crate::wrapper::LayoutNodeLayoutData;
 use app_units::Au;
 use euclid::default::{Point2D, Rect, Size2D, Vector2D};
 use euclid::Size2D as TypedSize2D;
+use fnv::FnvHashMap;
 use ipc_channel::ipc::IpcSender;
 use msg::constellation_msg::PipelineId;
+use net_traits::image::base::Image;
 use script_layout_interface::rpc::TextIndexResponse;
 use script_layout_interface::rpc::{ContentBoxResponse, ContentBoxesResponse, LayoutRPC};
 use script_layout_interface::rpc::{NodeGeometryResponse, NodeScrollIdResponse};
@@ -41,7 +43,7 @@ use style::logical_geometry::{BlockFlowDirection, InlineBaseDirection, WritingMo
 use style::properties::{style_structs, LonghandId, PropertyDeclarationId, PropertyId};
 use style::selector_parser::PseudoElement;
 use style_traits::{CSSPixel, ToCss};
-use webrender_api::ExternalScrollId;
+use webrender_api::{ExternalScrollId, ImageKey};
 
 /// Mutable data belonging to the LayoutThread.
 ///
@@ -55,6 +57,9 @@ pub struct LayoutThreadData {
 
     pub indexable_text: IndexableText,
 
+    /// The decoded images of the image fragments in the display list.
+    pub images: FnvHashMap<ImageKey, Arc<Image>>,
+
     /// A queued response for the union of the content boxes of a node.
     pub content_box_response: Option<Rect<Au>>,
 
@@ -0,0 +1,162 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Query containers: the elements with a `container-type` whose size, as of
+//! the last layout, `@container` rules and container-relative lengths of their
+//! descendants are evaluated against.
+//!
+//! <https://drafts.csswg.org/css-contain-3/#container-queries>
+
+use crate::dom_traversal::{iter_child_nodes, NodeExt};
+use crate::flow::FragmentTree;
+use crate::fragments::{BoxFragment, Fragment};
+use crate::wrapper::GetStyleAndLayoutData;
+use app_units::Au;
+use fxhash::FxHashMap;
+use script_layout_interface::wrapper_traits::LayoutNode;
+use style::computed_values::container_type::T as ContainerType;
+use style::dom::OpaqueNode;
+use style::stylesheets::container_rule::ContainerSize;
+use style::values::computed::ContainerName;
+use style::Atom;
+
+/// An element that was a query container in the last layout.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct QueryContainer {
+    size: ContainerSize,
+    name: ContainerName,
+}
+
+impl QueryContainer {
+    fn new(fragment: &BoxFragment) -> Self {
+        let style = &fragment.style;
+        let writing_mode = style.writing_mode;
+        let inline_size = Some(Au::from_f32_px(fragment.content_rect.size.inline.px()));
+        let block_size = match style.get_box().container_type {
+            ContainerType::Size => Some(Au::from_f32_px(fragment.content_rect.size.block.px())),
+            ContainerType::InlineSize | ContainerType::Normal => None,
+        };
+        let (width, height) = if writing_mode.is_vertical() {
+            (block_size, inline_size)
+        } else {
+            (inline_size, block_size)
+        };
+        QueryContainer {
+            size: ContainerSize {
+                width,
+                height,
+                writing_mode,
+            },
+            name: style.get_box().container_name.clone(),
+        }
+    }
+}
+
+/// Returns the size of the given element as a query container in the last
+/// layout, if it was one and, when a name is given, has that name.
+pub fn query_container_size<'dom>(
+    node: impl GetStyleAndLayoutData<'dom>,
+    name: Option<&Atom>,
+) -> Option<ContainerSize> {
+    let data = node.get_style_and_layout_data()?;
+    let layout_data = data.layout_data.borrow();
+    let container = layout_data.query_container.as_ref()?;
+    if name.map_or(false, |name| !container.name.contains(name)) {
+        return None;
+    }
+    Some(container.size)
+}
+
+/// Records the query containers laid out in the given fragment tree on their
+/// element, and returns the outermost elements whose record changed since the
+/// last layout: the styles of their descendants may depend on it, so they need
+/// to be restyled and laid out again.
+///
+/// When the descendants of `restyled_containers` were restyled since the last
+/// layout, only the containers nested in them are returned: containment keeps
+/// the size of the other ones from depending on the restyled elements.
+pub fn update_query_containers<'dom, Node>(
+    root_element: Node,
+    fragment_tree: &FragmentTree,
+    restyled_containers: Option<&[Node]>,
+) -> Vec<Node>
+where
+    Node: 'dom + Copy + LayoutNode<'dom> + Send + Sync,
+{
+    let mut containers = FxHashMap::default();
+    fragment_tree.find(|fragment, _| {
+        if let Fragment::Box(fragment) = fragment {
+            // Boxes generated by pseudo-elements have the tag of their
+            // originating element, but are not query containers for it.
+            if fragment.style.pseudo().is_none() &&
+                fragment.style.get_box().container_type != ContainerType::Normal
+            {
+                // An element that generates multiple boxes, like an inline
+                // element broken across lines, uses its first one.
+                containers
+                    .entry(fragment.tag)
+                    .or_insert_with(|| QueryContainer::new(fragment));
+            }
+        }
+        None::<()>
+    });
+
+    let mut changed = Vec::new();
+    update_query_containers_in_subtree(
+        root_element,
+        &mut containers,
+        restyled_containers,
+        restyled_containers.is_none(),
+        &mut changed,
+    );
+    changed
+}
+
+fn update_query_containers_in_subtree<'dom, Node>(
+    element: Node,
+    containers: &mut FxHashMap<OpaqueNode, QueryContainer>,
+    restyled_containers: Option<&[Node]>,
+    mut in_restyled_subtree: bool,
+    changed: &mut Vec<Node>,
+) where
+    Node: NodeExt<'dom>,
+{
+    let data = match element.get_style_and_layout_data() {
+        Some(data) => data,
+        // Never styled, and neither are its descendants.
+        None => return,
+    };
+    let container = containers.remove(&element.as_opaque());
+    let mut layout_data = data.layout_data.borrow_mut();
+    if in_restyled_subtree && layout_data.query_container != container {
+        layout_data.query_container = container;
+        changed.push(element);
+        // Its descendants are restyled along with it, so the containers
+        // nested in it are laid out again before being checked.
+        in_restyled_subtree = false;
+    }
+    drop(layout_data);
+
+    if let Some(restyled_containers) = restyled_containers {
+        let element = element.as_opaque();
+        if restyled_containers
+            .iter()
+            .any(|container| container.as_opaque() == element)
+        {
+            in_restyled_subtree = true;
+        }
+    }
+
+    for child in iter_child_nodes(element) {
+        if child.is_element() {
+            update_query_containers_in_subtree(
+                child,
+                containers,
+                restyled_containers,
+                in_restyled_subtree,
+                changed,
+            );
+        }
+    }
+}
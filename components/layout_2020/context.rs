@@ -3,6 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::display_list::WebRenderImageInfo;
+use crate::incremental::BoxConstructionStatistics;
 use crate::opaque_node::OpaqueNodeMethods;
 use fnv::FnvHashMap;
 use gfx::font_cache_thread::FontCacheThread;
@@ -24,6 +25,12 @@ pub struct LayoutContext<'a> {
     pub use_rayon: bool,
     pub origin: ImmutableOrigin,
 
+    /// Whether boxes and fragments from the previous reflow may be reused.
+    pub incremental_layout: bool,
+
+    /// Counts of the boxes built by this reflow, for testing incremental layout.
+    pub box_construction_statistics: BoxConstructionStatistics,
+
     /// Bits shared by the layout and style system.
     pub style_context: SharedStyleContext<'a>,
 
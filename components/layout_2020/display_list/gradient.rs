@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use style::color::mix::{mix, ColorInterpolationMethod};
 use style::properties::ComputedValues;
 use style::values::computed::image::{EndingShape, Gradient, LineDirection};
 use style::values::computed::{Color, Length, LengthPercentage, Position};
@@ -275,11 +276,11 @@ fn fixup_stops(
     for item in items {
         match item {
             GradientItem::SimpleColorStop(color) => stops.push(ColorStop {
-                color: super::rgba(style.resolve_color(*color)),
+                color: style.resolve_absolute_color(*color),
                 position: None,
             }),
             GradientItem::ComplexColorStop { color, position } => stops.push(ColorStop {
-                color: super::rgba(style.resolve_color(*color)),
+                color: style.resolve_absolute_color(*color),
                 position: Some(if gradient_line_length.px() == 0. {
                     0.
                 } else {
@@ -315,14 +316,11 @@ fn fixup_stops(
         }
     }
 
-    let mut wr_stops = Vec::with_capacity(stops.len());
+    let mut positioned_stops = Vec::with_capacity(stops.len());
     let mut iter = stops.iter().enumerate();
     let (_, first) = iter.next().unwrap();
     let first_stop_position = first.position.unwrap();
-    wr_stops.push(wr::GradientStop {
-        offset: first_stop_position,
-        color: first.color,
-    });
+    positioned_stops.push((first_stop_position, first.color));
 
     let mut last_positioned_stop_index = 0;
     let mut last_positioned_stop_position = first_stop_position;
@@ -334,17 +332,42 @@ fn fixup_stops(
                 for j in 1..step_count {
                     let color = stops[last_positioned_stop_index + j].color;
                     let offset = last_positioned_stop_position + j as f32 * step;
-                    wr_stops.push(wr::GradientStop { offset, color })
+                    positioned_stops.push((offset, color))
                 }
             }
             last_positioned_stop_index = i;
             last_positioned_stop_position = position;
-            wr_stops.push(wr::GradientStop {
-                offset: position,
-                color: stop.color,
-            })
+            positioned_stops.push((position, stop.color))
         }
     }
 
+    // WebRender interpolates in sRGB. When the colors must be interpolated
+    // in another color space, approximate that with piece-wise linear
+    // segments, like for color transition hints above.
+    // https://drafts.csswg.org/css-images-4/#coloring-gradient-line
+    let method =
+        ColorInterpolationMethod::default_for(positioned_stops.iter().map(|(_, color)| color));
+    let mut wr_stops = Vec::with_capacity(positioned_stops.len());
+    for (i, &(offset, color)) in positioned_stops.iter().enumerate() {
+        if method != ColorInterpolationMethod::SRGB && i > 0 {
+            let (previous_offset, previous_color) = positioned_stops[i - 1];
+            for j in 1..INTERMEDIATE_STOP_COUNT + 1 {
+                let progress = j as f32 / (INTERMEDIATE_STOP_COUNT + 1) as f32;
+                let color = mix(&method, &previous_color, 1. - progress, &color, progress);
+                wr_stops.push(wr::GradientStop {
+                    offset: previous_offset + (offset - previous_offset) * progress,
+                    color: super::rgba(color.to_rgba()),
+                })
+            }
+        }
+        wr_stops.push(wr::GradientStop {
+            offset,
+            color: super::rgba(color.to_rgba()),
+        })
+    }
     wr_stops
 }
+
+/// The number of stops inserted between two color stops to approximate
+/// interpolating in a color space other than sRGB.
+const INTERMEDIATE_STOP_COUNT: usize = 9;
@@ -465,11 +465,17 @@ impl<'a> BuilderForBoxFragment<'a> {
 
     fn build_border(&mut self, builder: &mut DisplayListBuilder) {
         let b = self.fragment.style.get_border();
+        // Use the border widths of the fragment rather than those of the style,
+        // since table cells with collapsed borders only get part of them.
+        let used_widths = self
+            .fragment
+            .border
+            .to_physical(self.fragment.style.writing_mode);
         let widths = SideOffsets2D::new(
-            b.border_top_width.px(),
-            b.border_right_width.px(),
-            b.border_bottom_width.px(),
-            b.border_left_width.px(),
+            used_widths.top.px(),
+            used_widths.right.px(),
+            used_widths.bottom.px(),
+            used_widths.left.px(),
         );
         if widths == SideOffsets2D::zero() {
             return;
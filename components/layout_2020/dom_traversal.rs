@@ -20,27 +20,30 @@ use servo_arc::Arc as ServoArc;
 use std::borrow::Cow;
 use std::marker::PhantomData as marker;
 use std::sync::{Arc, Mutex};
+use style::computed_values::list_style_type::T as ListStyleType;
 use style::dom::{OpaqueNode, TNode};
 use style::properties::ComputedValues;
 use style::selector_parser::PseudoElement;
 use style::values::generics::counters::Content;
 use style::values::generics::counters::ContentItem;
+use style::values::generics::url::UrlOrNone;
 
 #[derive(Clone, Copy, Debug)]
 pub enum WhichPseudoElement {
+    Marker,
     Before,
     After,
 }
 
 pub(super) enum Contents {
-    /// Refers to a DOM subtree, plus `::before` and `::after` pseudo-elements.
+    /// Refers to a DOM subtree, plus `::marker`, `::before`, and `::after` pseudo-elements.
     OfElement,
 
     /// Example: an `<img src=…>` element.
     /// <https://drafts.csswg.org/css2/conform.html#replaced-element>
     Replaced(ReplacedContent),
 
-    /// Content of a `::before` or `::after` pseudo-element that is being generated.
+    /// Content of a `::marker`, `::before`, or `::after` pseudo-element that is being generated.
     /// <https://drafts.csswg.org/css2/generate.html#content>
     OfPseudoElement(Vec<PseudoElementContentItem>),
 }
@@ -52,7 +55,6 @@ pub(super) enum NonReplacedContents {
 
 pub(super) enum PseudoElementContentItem {
     Text(String),
-    #[allow(dead_code)]
     Replaced(ReplacedContent),
 }
 
@@ -78,13 +80,67 @@ where
     );
 }
 
+/// A child that was found during a traversal but whose handling was deferred,
+/// for example until it is known which anonymous box should wrap it.
+pub(super) enum DeferredChild<'dom, Node> {
+    Text {
+        node: Node,
+        text: Cow<'dom, str>,
+        parent_style: ServoArc<ComputedValues>,
+    },
+    /// Or pseudo-element
+    Element {
+        node: Node,
+        style: ServoArc<ComputedValues>,
+        display: DisplayGeneratingBox,
+        contents: Contents,
+        box_slot: BoxSlot<'dom>,
+    },
+}
+
+impl<'dom, Node> DeferredChild<'dom, Node>
+where
+    Node: 'dom,
+{
+    pub fn replay(self, handler: &mut impl TraversalHandler<'dom, Node>) {
+        match self {
+            DeferredChild::Text {
+                node,
+                text,
+                parent_style,
+            } => handler.handle_text(node, text, &parent_style),
+            DeferredChild::Element {
+                node,
+                style,
+                display,
+                contents,
+                box_slot,
+            } => handler.handle_element(node, &style, display, contents, box_slot),
+        }
+    }
+}
+
+/// https://drafts.csswg.org/css-text/#white-space
+pub(crate) fn is_only_document_white_space(string: &str) -> bool {
+    string
+        .bytes()
+        .all(|byte| matches!(byte, b' ' | b'\n' | b'\t' | b'\r' | b'\x0C'))
+}
+
 fn traverse_children_of<'dom, Node>(
     parent_element: Node,
+    parent_style: &ComputedValues,
     context: &LayoutContext,
     handler: &mut impl TraversalHandler<'dom, Node>,
 ) where
     Node: NodeExt<'dom>,
 {
+    if let Display::GeneratingBox(DisplayGeneratingBox::OutsideInside {
+        list_item: true, ..
+    }) = Display::from(parent_style.get_box().display)
+    {
+        traverse_pseudo_element(WhichPseudoElement::Marker, parent_element, context, handler);
+    }
     traverse_pseudo_element(WhichPseudoElement::Before, parent_element, context, handler);
 
     for child in iter_child_nodes(parent_element) {
@@ -116,10 +172,11 @@ fn traverse_element<'dom, Node>(
                 element.unset_all_boxes()
             } else {
                 element.element_box_slot().set(LayoutBox::DisplayContents);
-                traverse_children_of(element, context, handler)
+                traverse_children_of(element, &style, context, handler)
             }
         },
         Display::GeneratingBox(display) => {
+            context.box_construction_statistics.note_box();
             let contents = replaced.map_or(Contents::OfElement, Contents::Replaced);
             let box_slot = element.element_box_slot();
             handler.handle_element(element, &style, display, contents, box_slot);
@@ -139,13 +196,14 @@ fn traverse_pseudo_element<'dom, Node>(
         match Display::from(style.get_box().display) {
             Display::None => element.unset_pseudo_element_box(which),
             Display::Contents => {
-                let items = generate_pseudo_element_content(&style, element, context);
+                let items = generate_pseudo_element_content(which, &style, element, context);
                 let box_slot = element.pseudo_element_box_slot(which);
                 box_slot.set(LayoutBox::DisplayContents);
                 traverse_pseudo_element_contents(element, &style, context, handler, items);
             },
             Display::GeneratingBox(display) => {
-                let items = generate_pseudo_element_content(&style, element, context);
+                context.box_construction_statistics.note_box();
+                let items = generate_pseudo_element_content(which, &style, element, context);
                 let box_slot = element.pseudo_element_box_slot(which);
                 let contents = Contents::OfPseudoElement(items);
                 handler.handle_element(element, &style, display, contents, box_slot);
@@ -185,6 +243,7 @@ fn traverse_pseudo_element_contents<'dom, Node>(
                 let display_inline = DisplayGeneratingBox::OutsideInside {
                     outside: DisplayOutside::Inline,
                     inside: DisplayInside::Flow,
+                    list_item: false,
                 };
                 // `display` is not inherited, so we get the initial value
                 debug_assert!(
@@ -246,7 +305,9 @@ impl NonReplacedContents {
         Node: NodeExt<'dom>,
     {
         match self {
-            NonReplacedContents::OfElement => traverse_children_of(node, context, handler),
+            NonReplacedContents::OfElement => {
+                traverse_children_of(node, inherited_style, context, handler)
+            },
             NonReplacedContents::OfPseudoElement(items) => {
                 traverse_pseudo_element_contents(node, inherited_style, context, handler, items)
             },
@@ -254,7 +315,7 @@ impl NonReplacedContents {
     }
 }
 
-fn pseudo_element_style<'dom, Node>(
+pub(crate) fn pseudo_element_style<'dom, Node>(
     which: WhichPseudoElement,
     element: Node,
     context: &LayoutContext,
@@ -262,22 +323,45 @@ fn pseudo_element_style<'dom, Node>(
 where
     Node: NodeExt<'dom>,
 {
-    match which {
-        WhichPseudoElement::Before => element.to_threadsafe().get_before_pseudo(),
-        WhichPseudoElement::After => element.to_threadsafe().get_after_pseudo(),
+    let pseudo_element = match which {
+        WhichPseudoElement::Marker => return marker_style(element),
+        WhichPseudoElement::Before => element.to_threadsafe().get_before_pseudo()?,
+        WhichPseudoElement::After => element.to_threadsafe().get_after_pseudo()?,
+    };
+    let style = pseudo_element.style(context.shared_context());
+    if style.ineffective_content_property() {
+        None
+    } else {
+        Some(style)
+    }
+}
+
+fn marker_style<'dom, Node>(element: Node) -> Option<ServoArc<ComputedValues>>
+where
+    Node: NodeExt<'dom>,
+{
+    let style = element.to_threadsafe().as_element()?.marker_style()?;
+    // With `content: normal`, the marker is generated from `list-style`,
+    // which may not generate anything either.
+    // https://drafts.csswg.org/css-lists/#content-property
+    let list = style.get_list();
+    let generates_nothing = match style.get_counters().content {
+        Content::Normal => {
+            list.list_style_type == ListStyleType::None && list.list_style_image == UrlOrNone::None
+        },
+        Content::None => true,
+        Content::Items(ref items) => items.is_empty(),
+    };
+    if generates_nothing {
+        None
+    } else {
+        Some(style)
     }
-    .and_then(|pseudo_element| {
-        let style = pseudo_element.style(context.shared_context());
-        if style.ineffective_content_property() {
-            None
-        } else {
-            Some(style)
-        }
-    })
 }
 
 /// https://www.w3.org/TR/CSS2/generate.html#propdef-content
 fn generate_pseudo_element_content<'dom, Node>(
+    which: WhichPseudoElement,
     pseudo_element_style: &ComputedValues,
     element: Node,
     context: &LayoutContext,
@@ -285,6 +369,13 @@ fn generate_pseudo_element_content<'dom, Node>(
 where
     Node: NodeExt<'dom>,
 {
+    // Counters were rendered ahead of box construction, in the order they appear in `content`.
+    let mut counters = element
+        .layout_data_mut()
+        .generated_counters
+        .of(which)
+        .to_vec()
+        .into_iter();
     match &pseudo_element_style.get_counters().content {
         Content::Items(ref items) => {
             let mut vec = vec![];
@@ -311,17 +402,36 @@ where
                             vec.push(PseudoElementContentItem::Replaced(replaced_content));
                         }
                     },
+                    ContentItem::Counter(..) | ContentItem::Counters(..) => {
+                        if let Some(text) = counters.next() {
+                            vec.push(PseudoElementContentItem::Text(text));
+                        }
+                    },
                     _ => (),
                 }
             }
             vec
         },
-        Content::Normal | Content::None => unreachable!(),
+        // Only `::marker` is generated with `content: normal`,
+        // from its `list-style-image` if it is loaded or else from its `list-style-type`.
+        // https://drafts.csswg.org/css-lists/#content-property
+        Content::Normal => {
+            if let UrlOrNone::Url(image_url) = &pseudo_element_style.get_list().list_style_image {
+                if let Some(replaced_content) =
+                    ReplacedContent::from_image_url(element, context, image_url)
+                {
+                    return vec![PseudoElementContentItem::Replaced(replaced_content)];
+                }
+            }
+            counters.map(PseudoElementContentItem::Text).collect()
+        },
+        Content::None => unreachable!(),
     }
 }
 
 pub struct BoxSlot<'dom> {
     slot: Option<ArcRefCell<Option<LayoutBox>>>,
+    reusable_box: Option<LayoutBox>,
     marker: marker<&'dom ()>,
 }
 
@@ -329,12 +439,38 @@ impl BoxSlot<'_> {
     pub(crate) fn new(slot: ArcRefCell<Option<LayoutBox>>) -> Self {
         *slot.borrow_mut() = None;
         let slot = Some(slot);
-        Self { slot, marker }
+        let reusable_box = None;
+        Self {
+            slot,
+            reusable_box,
+            marker,
+        }
+    }
+
+    /// Like `new`, but keeps the previous box of the slot so that it can be reused
+    /// (and set again) instead of building a new one.
+    pub(crate) fn new_with_reusable_box(slot: ArcRefCell<Option<LayoutBox>>) -> Self {
+        let reusable_box = slot.borrow_mut().take();
+        let slot = Some(slot);
+        Self {
+            slot,
+            reusable_box,
+            marker,
+        }
     }
 
     pub(crate) fn dummy() -> Self {
         let slot = None;
-        Self { slot, marker }
+        let reusable_box = None;
+        Self {
+            slot,
+            reusable_box,
+            marker,
+        }
+    }
+
+    pub(crate) fn take_reusable_box(&mut self) -> Option<LayoutBox> {
+        self.reusable_box.take()
     }
 
     pub(crate) fn set(mut self, box_: LayoutBox) {
@@ -367,13 +503,16 @@ pub(crate) trait NodeExt<'dom>: 'dom + Copy + LayoutNode<'dom> + Send + Sync {
     fn parent_node(self) -> Option<Self>;
     fn style(self, context: &LayoutContext) -> ServoArc<ComputedValues>;
 
+    /// The `colspan` and `rowspan` attributes of a table cell element, or 1.
+    fn table_cell_spans(self) -> (u32, u32);
+
     fn as_opaque(self) -> OpaqueNode;
     fn layout_data_mut(self) -> AtomicRefMut<'dom, LayoutDataForElement>;
     fn element_box_slot(&self) -> BoxSlot<'dom>;
     fn pseudo_element_box_slot(&self, which: WhichPseudoElement) -> BoxSlot<'dom>;
     fn unset_pseudo_element_box(self, which: WhichPseudoElement);
 
-    /// Remove boxes for the element itself, and its `:before` and `:after` if any.
+    /// Remove boxes for the element itself, and its `::marker`, `:before`, and `:after` if any.
     fn unset_all_boxes(self);
 }
 
@@ -443,6 +582,11 @@ where
         self.to_threadsafe().style(context.shared_context())
     }
 
+    fn table_cell_spans(self) -> (u32, u32) {
+        let node = self.to_threadsafe();
+        (node.get_colspan(), node.get_rowspan())
+    }
+
     fn as_opaque(self) -> OpaqueNode {
         self.opaque()
     }
@@ -455,12 +599,18 @@ where
     }
 
     fn element_box_slot(&self) -> BoxSlot<'dom> {
-        BoxSlot::new(self.layout_data_mut().self_box.clone())
+        let data = self.layout_data_mut();
+        if data.self_box_is_reusable {
+            BoxSlot::new_with_reusable_box(data.self_box.clone())
+        } else {
+            BoxSlot::new(data.self_box.clone())
+        }
     }
 
     fn pseudo_element_box_slot(&self, which: WhichPseudoElement) -> BoxSlot<'dom> {
         let data = self.layout_data_mut();
         let cell = match which {
+            WhichPseudoElement::Marker => &data.pseudo_marker_box,
             WhichPseudoElement::Before => &data.pseudo_before_box,
             WhichPseudoElement::After => &data.pseudo_after_box,
         };
@@ -470,6 +620,7 @@ where
     fn unset_pseudo_element_box(self, which: WhichPseudoElement) {
         let data = self.layout_data_mut();
         let cell = match which {
+            WhichPseudoElement::Marker => &data.pseudo_marker_box,
             WhichPseudoElement::Before => &data.pseudo_before_box,
             WhichPseudoElement::After => &data.pseudo_after_box,
         };
@@ -479,6 +630,7 @@ where
     fn unset_all_boxes(self) {
         let mut data = self.layout_data_mut();
         *data.self_box.borrow_mut() = None;
+        *data.pseudo_marker_box.borrow_mut() = None;
         *data.pseudo_before_box.borrow_mut() = None;
         *data.pseudo_after_box.borrow_mut() = None;
         // Stylo already takes care of removing all layout data
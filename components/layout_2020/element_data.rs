@@ -3,18 +3,41 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::cell::ArcRefCell;
+use crate::container_queries::QueryContainer;
+use crate::flexbox::FlexLevelBox;
 use crate::flow::inline::InlineLevelBox;
 use crate::flow::BlockLevelBox;
+use crate::grid::GridLevelBox;
+use crate::lists::GeneratedCounters;
+use crate::table::TableLevelBox;
+use style::values::specified::text::TextDecorationLine;
 
 #[derive(Default)]
 pub struct LayoutDataForElement {
     pub(super) self_box: ArcRefCell<Option<LayoutBox>>,
     pub(super) pseudo_before_box: ArcRefCell<Option<LayoutBox>>,
     pub(super) pseudo_after_box: ArcRefCell<Option<LayoutBox>>,
+    pub(super) pseudo_marker_box: ArcRefCell<Option<LayoutBox>>,
+    pub(super) generated_counters: GeneratedCounters,
+
+    /// Whether `self_box` can be reused by the next box construction,
+    /// see `incremental::mark_reusable_boxes`.
+    pub(super) self_box_is_reusable: bool,
+
+    /// The text decorations propagated by ancestors that the block-level box
+    /// of this element was last built with.
+    pub(super) propagated_text_decoration_line: TextDecorationLine,
+
+    /// The size and names of this element as a query container in the last layout,
+    /// see `container_queries::update_query_containers`.
+    pub(super) query_container: Option<QueryContainer>,
 }
 
 pub(super) enum LayoutBox {
     DisplayContents,
     BlockLevel(ArcRefCell<BlockLevelBox>),
     InlineLevel(ArcRefCell<InlineLevelBox>),
+    FlexLevel(ArcRefCell<FlexLevelBox>),
+    GridLevel(ArcRefCell<GridLevelBox>),
+    TableLevel(TableLevelBox),
 }
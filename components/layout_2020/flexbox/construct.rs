@@ -0,0 +1,274 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::{FlexContainer, FlexLevelBox};
+use crate::cell::ArcRefCell;
+use crate::context::LayoutContext;
+use crate::dom_traversal::{
+    is_only_document_white_space, BoxSlot, Contents, DeferredChild, NodeExt, NonReplacedContents,
+    TraversalHandler,
+};
+use crate::element_data::LayoutBox;
+use crate::formatting_contexts::IndependentFormattingContext;
+use crate::positioned::AbsolutelyPositionedBox;
+use crate::sizing::{BoxContentSizes, ContentSizes, ContentSizesRequest};
+use crate::style_ext::DisplayGeneratingBox;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use servo_arc::Arc;
+use std::borrow::Cow;
+use style::computed_values::flex_direction::T as FlexDirection;
+use style::computed_values::flex_wrap::T as FlexWrap;
+use style::properties::ComputedValues;
+use style::selector_parser::PseudoElement;
+use style::values::specified::text::TextDecorationLine;
+
+impl FlexContainer {
+    pub fn construct<'dom>(
+        context: &LayoutContext,
+        node: impl NodeExt<'dom>,
+        style: &Arc<ComputedValues>,
+        contents: NonReplacedContents,
+        content_sizes: ContentSizesRequest,
+        propagated_text_decoration_line: TextDecorationLine,
+    ) -> (Self, BoxContentSizes) {
+        let text_decoration_line =
+            propagated_text_decoration_line | style.clone_text_decoration_line();
+        let mut builder = FlexContainerBuilder {
+            context,
+            node,
+            style,
+            text_decoration_line,
+            contiguous_text_runs: Vec::new(),
+            jobs: Vec::new(),
+            has_text_runs: false,
+        };
+        contents.traverse(context, node, style, &mut builder);
+        let container = builder.finish();
+        let content_sizes = content_sizes.compute(|| container.inline_content_sizes(style));
+        (container, content_sizes)
+    }
+
+    /// https://drafts.csswg.org/css-flexbox/#intrinsic-main-sizes
+    /// https://drafts.csswg.org/css-flexbox/#intrinsic-cross-sizes
+    fn inline_content_sizes(&self, style: &ComputedValues) -> ContentSizes {
+        let box_style = style.get_position();
+        let inline_is_main = match box_style.flex_direction {
+            FlexDirection::Row | FlexDirection::RowReverse => true,
+            FlexDirection::Column | FlexDirection::ColumnReverse => false,
+        };
+        let is_single_line = box_style.flex_wrap == FlexWrap::Nowrap;
+
+        let mut content_sizes = ContentSizes::zero();
+        for child in &self.children {
+            let item = match &*child.borrow() {
+                FlexLevelBox::FlexItem(item) => item.content_sizes.outer_inline(&item.style),
+                FlexLevelBox::OutOfFlowAbsolutelyPositionedBox(_) => continue,
+            };
+            if inline_is_main {
+                // Flex items are laid out next to each other on a single line,
+                // unless wrapping lets each of them go on its own line.
+                content_sizes.max_content += item.max_content;
+                if is_single_line {
+                    content_sizes.min_content += item.min_content;
+                } else {
+                    content_sizes.min_content.max_assign(item.min_content);
+                }
+            } else {
+                content_sizes.max_assign(&item);
+            }
+        }
+        content_sizes
+    }
+}
+
+/// https://drafts.csswg.org/css-flexbox/#flex-items
+struct FlexContainerBuilder<'a, 'dom, Node> {
+    context: &'a LayoutContext<'a>,
+    node: Node,
+    style: &'a Arc<ComputedValues>,
+    text_decoration_line: TextDecorationLine,
+    contiguous_text_runs: Vec<TextRun<'dom, Node>>,
+    /// To be run in parallel with rayon in `finish`
+    jobs: Vec<FlexLevelJob<'dom, Node>>,
+    has_text_runs: bool,
+}
+
+enum FlexLevelJob<'dom, Node> {
+    /// Or pseudo-element
+    Element {
+        node: Node,
+        style: Arc<ComputedValues>,
+        display: DisplayGeneratingBox,
+        contents: Contents,
+        box_slot: BoxSlot<'dom>,
+    },
+    TextRuns(Vec<TextRun<'dom, Node>>),
+}
+
+struct TextRun<'dom, Node> {
+    node: Node,
+    text: Cow<'dom, str>,
+    parent_style: Arc<ComputedValues>,
+}
+
+impl<'a, 'dom, Node: 'dom> TraversalHandler<'dom, Node> for FlexContainerBuilder<'a, 'dom, Node>
+where
+    Node: NodeExt<'dom>,
+{
+    fn handle_text(
+        &mut self,
+        node: Node,
+        text: Cow<'dom, str>,
+        parent_style: &Arc<ComputedValues>,
+    ) {
+        self.contiguous_text_runs.push(TextRun {
+            node,
+            text,
+            parent_style: parent_style.clone(),
+        })
+    }
+
+    /// Or pseudo-element
+    fn handle_element(
+        &mut self,
+        node: Node,
+        style: &Arc<ComputedValues>,
+        display: DisplayGeneratingBox,
+        contents: Contents,
+        box_slot: BoxSlot<'dom>,
+    ) {
+        // FIXME: are text runs considered "contiguous" if they are only separated
+        // by an out-of-flow abspos element?
+        // (That is, are they wrapped in the same anonymous flex item, or each its own?)
+        self.wrap_any_text_in_anonymous_block_container();
+
+        self.jobs.push(FlexLevelJob::Element {
+            node,
+            style: style.clone(),
+            display,
+            contents,
+            box_slot,
+        })
+    }
+}
+
+impl<'a, 'dom, Node: 'dom> FlexContainerBuilder<'a, 'dom, Node>
+where
+    Node: NodeExt<'dom>,
+{
+    fn wrap_any_text_in_anonymous_block_container(&mut self) {
+        let runs = std::mem::take(&mut self.contiguous_text_runs);
+        // “if the entire sequence of child text runs contains only white space
+        //  (i.e. characters that can be affected by the white-space property)
+        //  it is instead not rendered”
+        if !runs
+            .iter()
+            .all(|run| is_only_document_white_space(&run.text))
+        {
+            self.jobs.push(FlexLevelJob::TextRuns(runs));
+            self.has_text_runs = true;
+        }
+    }
+
+    fn finish(mut self) -> FlexContainer {
+        self.wrap_any_text_in_anonymous_block_container();
+
+        let context = self.context;
+        let container_node = self.node;
+        let text_decoration_line = self.text_decoration_line;
+        let anonymous_style = if self.has_text_runs {
+            Some(
+                context
+                    .shared_context()
+                    .stylist
+                    .style_for_anonymous::<Node::ConcreteElement>(
+                        &context.shared_context().guards,
+                        &PseudoElement::ServoText,
+                        &self.style,
+                    ),
+            )
+        } else {
+            None
+        };
+
+        let finish_job = |job: FlexLevelJob<'dom, Node>| match job {
+            FlexLevelJob::TextRuns(runs) => ArcRefCell::new(FlexLevelBox::FlexItem(
+                IndependentFormattingContext::construct_for_anonymous_box(
+                    context,
+                    container_node,
+                    anonymous_style.clone().unwrap(),
+                    runs.into_iter().map(|run| DeferredChild::Text {
+                        node: run.node,
+                        text: run.text,
+                        parent_style: run.parent_style,
+                    }),
+                    // Flex layout uses the content sizes of every flex item.
+                    ContentSizesRequest::Inline,
+                    text_decoration_line,
+                ),
+            )),
+            FlexLevelJob::Element {
+                node,
+                style,
+                display,
+                contents,
+                box_slot,
+            } => {
+                let display_inside = match display {
+                    DisplayGeneratingBox::OutsideInside { inside, .. } => inside,
+                    // The style crate blockifies flex items, see below.
+                    DisplayGeneratingBox::LayoutInternal(_) => unreachable!(),
+                };
+                let box_ = if style.get_box().position.is_absolutely_positioned() {
+                    // https://drafts.csswg.org/css-flexbox/#abspos-items
+                    ArcRefCell::new(FlexLevelBox::OutOfFlowAbsolutelyPositionedBox(Arc::new(
+                        AbsolutelyPositionedBox::construct(
+                            context,
+                            node,
+                            style,
+                            display_inside,
+                            contents,
+                        ),
+                    )))
+                } else {
+                    // The style crate has already blockified the `display` of flex items:
+                    // https://drafts.csswg.org/css-flexbox/#flex-items
+                    ArcRefCell::new(FlexLevelBox::FlexItem(
+                        IndependentFormattingContext::construct(
+                            context,
+                            node,
+                            style,
+                            display_inside,
+                            contents,
+                            ContentSizesRequest::Inline,
+                            text_decoration_line,
+                        ),
+                    ))
+                };
+                box_slot.set(LayoutBox::FlexLevel(box_.clone()));
+                box_
+            },
+        };
+
+        let jobs = std::mem::take(&mut self.jobs);
+        let mut children: Vec<_> = if context.use_rayon {
+            jobs.into_par_iter().map(finish_job).collect()
+        } else {
+            jobs.into_iter().map(finish_job).collect()
+        };
+
+        // https://drafts.csswg.org/css-flexbox/#order-modified-document-order
+        // This is a stable sort, so items with the same `order` stay in document order.
+        children.sort_by_key(|child| match &*child.borrow() {
+            FlexLevelBox::FlexItem(item) => item.style.clone_order(),
+
+            // “Absolutely-positioned children of a flex container are treated
+            //  as having order: 0 for the purpose of determining their painting order
+            //  relative to flex items.”
+            FlexLevelBox::OutOfFlowAbsolutelyPositionedBox(_) => 0,
+        });
+
+        FlexContainer { children }
+    }
+}
@@ -0,0 +1,251 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-flexbox/#box-model
+
+use crate::geom::flow_relative::{Rect, Sides, Vec2};
+use std::ops::{Add, Sub};
+use style::computed_values::flex_direction::T as FlexDirection;
+use style::computed_values::flex_wrap::T as FlexWrap;
+
+#[derive(Clone, Copy, Debug)]
+pub(super) struct FlexRelativeVec2<T> {
+    pub main: T,
+    pub cross: T,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(super) struct FlexRelativeSides<T> {
+    pub cross_start: T,
+    pub main_start: T,
+    pub cross_end: T,
+    pub main_end: T,
+}
+
+pub(super) struct FlexRelativeRect<T> {
+    pub start_corner: FlexRelativeVec2<T>,
+    pub size: FlexRelativeVec2<T>,
+}
+
+impl<T> Add for FlexRelativeVec2<T>
+where
+    T: Add,
+{
+    type Output = FlexRelativeVec2<T::Output>;
+    fn add(self, rhs: Self) -> Self::Output {
+        FlexRelativeVec2 {
+            main: self.main + rhs.main,
+            cross: self.cross + rhs.cross,
+        }
+    }
+}
+
+impl<T> Sub for FlexRelativeVec2<T>
+where
+    T: Sub,
+{
+    type Output = FlexRelativeVec2<T::Output>;
+    fn sub(self, rhs: Self) -> Self::Output {
+        FlexRelativeVec2 {
+            main: self.main - rhs.main,
+            cross: self.cross - rhs.cross,
+        }
+    }
+}
+
+impl<T> FlexRelativeSides<T> {
+    pub fn sum_by_axis(self) -> FlexRelativeVec2<T::Output>
+    where
+        T: Add,
+    {
+        FlexRelativeVec2 {
+            main: self.main_start + self.main_end,
+            cross: self.cross_start + self.cross_end,
+        }
+    }
+
+    pub fn map<U>(&self, f: impl Fn(&T) -> U) -> FlexRelativeSides<U> {
+        FlexRelativeSides {
+            cross_start: f(&self.cross_start),
+            main_start: f(&self.main_start),
+            cross_end: f(&self.cross_end),
+            main_end: f(&self.main_end),
+        }
+    }
+}
+
+impl<T> Add for FlexRelativeSides<T>
+where
+    T: Add,
+{
+    type Output = FlexRelativeSides<T::Output>;
+    fn add(self, rhs: Self) -> Self::Output {
+        FlexRelativeSides {
+            cross_start: self.cross_start + rhs.cross_start,
+            main_start: self.main_start + rhs.main_start,
+            cross_end: self.cross_end + rhs.cross_end,
+            main_end: self.main_end + rhs.main_end,
+        }
+    }
+}
+
+/// One of the two bits set by the `flex-direction` property
+/// (the other is "forward" v.s. reverse.)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum FlexAxis {
+    /// The main axis is the inline axis of the container (not necessarily of flex items!),
+    /// cross is block.
+    Row,
+    /// The main axis is the block axis, cross is inline.
+    Column,
+}
+
+/// How the flow-relative axes and sides of the flex container map to its flex-relative ones,
+/// as set by `flex-direction` and `flex-wrap`.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct FlexAxes {
+    pub flex_axis: FlexAxis,
+
+    /// Whether main-start is the inline-end (for rows) or block-end (for columns) side.
+    pub main_is_reversed: bool,
+
+    /// Whether cross-start is the block-end (for rows) or inline-end (for columns) side.
+    pub cross_is_reversed: bool,
+}
+
+impl FlexAxes {
+    pub fn new(flex_direction: FlexDirection, flex_wrap: FlexWrap) -> Self {
+        let (flex_axis, main_is_reversed) = match flex_direction {
+            FlexDirection::Row => (FlexAxis::Row, false),
+            FlexDirection::RowReverse => (FlexAxis::Row, true),
+            FlexDirection::Column => (FlexAxis::Column, false),
+            FlexDirection::ColumnReverse => (FlexAxis::Column, true),
+        };
+        FlexAxes {
+            flex_axis,
+            main_is_reversed,
+            cross_is_reversed: flex_wrap == FlexWrap::WrapReverse,
+        }
+    }
+
+    pub fn vec2_to_flex_relative<T>(&self, flow_relative: Vec2<T>) -> FlexRelativeVec2<T> {
+        let Vec2 { inline, block } = flow_relative;
+        match self.flex_axis {
+            FlexAxis::Row => FlexRelativeVec2 {
+                main: inline,
+                cross: block,
+            },
+            FlexAxis::Column => FlexRelativeVec2 {
+                main: block,
+                cross: inline,
+            },
+        }
+    }
+
+    pub fn vec2_to_flow_relative<T>(&self, flex_relative: FlexRelativeVec2<T>) -> Vec2<T> {
+        let FlexRelativeVec2 { main, cross } = flex_relative;
+        match self.flex_axis {
+            FlexAxis::Row => Vec2 {
+                inline: main,
+                block: cross,
+            },
+            FlexAxis::Column => Vec2 {
+                block: main,
+                inline: cross,
+            },
+        }
+    }
+
+    pub fn sides_to_flex_relative<T>(&self, flow_relative: Sides<T>) -> FlexRelativeSides<T> {
+        let Sides {
+            inline_start,
+            inline_end,
+            block_start,
+            block_end,
+        } = flow_relative;
+        let (main_start, main_end, cross_start, cross_end) = match self.flex_axis {
+            FlexAxis::Row => (inline_start, inline_end, block_start, block_end),
+            FlexAxis::Column => (block_start, block_end, inline_start, inline_end),
+        };
+        let (main_start, main_end) = swap_if(self.main_is_reversed, main_start, main_end);
+        let (cross_start, cross_end) = swap_if(self.cross_is_reversed, cross_start, cross_end);
+        FlexRelativeSides {
+            cross_start,
+            main_start,
+            cross_end,
+            main_end,
+        }
+    }
+
+    pub fn sides_to_flow_relative<T>(&self, flex_relative: FlexRelativeSides<T>) -> Sides<T> {
+        let FlexRelativeSides {
+            cross_start,
+            main_start,
+            cross_end,
+            main_end,
+        } = flex_relative;
+        let (main_start, main_end) = swap_if(self.main_is_reversed, main_start, main_end);
+        let (cross_start, cross_end) = swap_if(self.cross_is_reversed, cross_start, cross_end);
+        match self.flex_axis {
+            FlexAxis::Row => Sides {
+                inline_start: main_start,
+                inline_end: main_end,
+                block_start: cross_start,
+                block_end: cross_end,
+            },
+            FlexAxis::Column => Sides {
+                block_start: main_start,
+                block_end: main_end,
+                inline_start: cross_start,
+                inline_end: cross_end,
+            },
+        }
+    }
+
+    /// Convert a rectangle positioned from the main-start and cross-start sides
+    /// of a flex container’s content box of the given size.
+    pub fn rect_to_flow_relative<T>(
+        &self,
+        base_rect_size: FlexRelativeVec2<T>,
+        rect: FlexRelativeRect<T>,
+    ) -> Rect<T>
+    where
+        T: Copy + Add<Output = T> + Sub<Output = T>,
+    {
+        // Position along each axis from the flow-relative start side
+        let flip = |reversed: bool, start: T, size: T, base_size: T| {
+            if reversed {
+                base_size - start - size
+            } else {
+                start
+            }
+        };
+        let start_corner = FlexRelativeVec2 {
+            main: flip(
+                self.main_is_reversed,
+                rect.start_corner.main,
+                rect.size.main,
+                base_rect_size.main,
+            ),
+            cross: flip(
+                self.cross_is_reversed,
+                rect.start_corner.cross,
+                rect.size.cross,
+                base_rect_size.cross,
+            ),
+        };
+        Rect {
+            start_corner: self.vec2_to_flow_relative(start_corner),
+            size: self.vec2_to_flow_relative(rect.size),
+        }
+    }
+}
+
+fn swap_if<T>(condition: bool, a: T, b: T) -> (T, T) {
+    if condition {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
@@ -0,0 +1,1015 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-flexbox/#layout-algorithm
+
+use super::geom::{FlexAxes, FlexAxis, FlexRelativeRect, FlexRelativeSides, FlexRelativeVec2};
+use super::{FlexContainer, FlexLevelBox};
+use crate::cell::ArcRefCell;
+use crate::context::LayoutContext;
+use crate::formatting_contexts::{IndependentFormattingContext, IndependentLayout};
+use crate::fragments::{
+    AbsoluteOrFixedPositionedFragment, BoxFragment, CollapsedBlockMargins, Fragment,
+};
+use crate::geom::flow_relative::{Sides, Vec2};
+use crate::geom::LengthOrAuto;
+use crate::positioned::{AbsolutelyPositionedBox, PositioningContext};
+use crate::sizing::content_block_size_limits;
+use crate::style_ext::ComputedValuesExt;
+use crate::ContainingBlock;
+use style::computed_values::align_content::T as AlignContent;
+use style::computed_values::align_items::T as AlignItems;
+use style::computed_values::align_self::T as AlignSelf;
+use style::computed_values::box_sizing::T as BoxSizing;
+use style::computed_values::flex_wrap::T as FlexWrap;
+use style::computed_values::justify_content::T as JustifyContent;
+use style::computed_values::overflow_x::T as Overflow;
+use style::values::computed::{Length, Size};
+use style::values::generics::flex::GenericFlexBasis as FlexBasis;
+use style::Zero;
+
+/// Layout parameters and intermediate results about a flex container,
+/// grouped to avoid passing around many parameters
+struct FlexContext<'a> {
+    layout_context: &'a LayoutContext<'a>,
+    positioning_context: &'a mut PositioningContext,
+    containing_block: &'a ContainingBlock<'a>, // For items
+    axes: FlexAxes,
+    container_is_single_line: bool,
+    container_min_cross_size: Length,
+    container_max_cross_size: Option<Length>,
+    container_definite_inner_size: FlexRelativeVec2<Option<Length>>,
+    align_items: AlignItems,
+    align_content: AlignContent,
+    justify_content: JustifyContent,
+}
+
+/// A flex item with some intermediate results
+struct FlexItem<'a> {
+    box_: &'a IndependentFormattingContext,
+    tree_rank: usize,
+    content_box_size: FlexRelativeVec2<LengthOrAuto>,
+    content_min_size: FlexRelativeVec2<Length>,
+    content_max_size: FlexRelativeVec2<Option<Length>>,
+    padding: Sides<Length>,
+    border: Sides<Length>,
+    margin: FlexRelativeSides<LengthOrAuto>,
+
+    /// Sum of padding and border in each axis
+    padding_border_sums: FlexRelativeVec2<Length>,
+
+    /// Sum of padding, border, and margin (with `auto` assumed to be zero) in each axis.
+    /// This is the difference between an outer and inner size.
+    pbm_auto_is_zero: FlexRelativeVec2<Length>,
+
+    /// The used size of replaced content, as if it were an inline element.
+    replaced_used_size: Option<Vec2<Length>>,
+
+    /// https://drafts.csswg.org/css-flexbox/#algo-main-item
+    flex_base_size: Length,
+
+    /// https://drafts.csswg.org/css-flexbox/#algo-main-item
+    hypothetical_main_size: Length,
+
+    align_self: AlignSelf,
+
+    /// The most recent layout of this item, reused when the same sizes are needed again.
+    cached_layout: Option<FlexItemLayoutResult>,
+}
+
+/// The result of laying out the contents of a flex item
+struct FlexItemLayoutResult {
+    /// The sizes that were given for the content box, `None` for `auto`
+    given_size: FlexRelativeVec2<Option<Length>>,
+
+    /// The size of the content box: given sizes, and content sizes for `auto`
+    content_size: FlexRelativeVec2<Length>,
+
+    fragments: Vec<Fragment>,
+    positioning_context: PositioningContext,
+}
+
+/// A flex line with some intermediate results
+struct FlexLine<'items, 'boxes> {
+    items: &'items mut [FlexItem<'boxes>],
+    outer_hypothetical_main_sizes_sum: Length,
+
+    /// https://drafts.csswg.org/css-flexbox/#algo-flex
+    used_main_sizes: Vec<Length>,
+
+    /// https://drafts.csswg.org/css-flexbox/#algo-cross-item
+    hypothetical_cross_sizes: Vec<Length>,
+
+    /// https://drafts.csswg.org/css-flexbox/#algo-cross-line
+    cross_size: Length,
+}
+
+impl FlexContainer {
+    pub(crate) fn layout(
+        &self,
+        layout_context: &LayoutContext,
+        positioning_context: &mut PositioningContext,
+        containing_block: &ContainingBlock,
+        tree_rank: usize,
+    ) -> IndependentLayout {
+        let mut content_block_size = Length::zero();
+        let fragments =
+            positioning_context.adjust_static_positions(tree_rank, |positioning_context| {
+                let (fragments, block_size) = layout(
+                    layout_context,
+                    positioning_context,
+                    containing_block,
+                    &self.children,
+                );
+                content_block_size = block_size;
+                fragments
+            });
+        IndependentLayout {
+            fragments,
+            content_block_size,
+        }
+    }
+}
+
+/// Return one fragment per child (indexed by tree rank, for `adjust_static_positions`),
+/// and the content block size of the flex container.
+fn layout(
+    layout_context: &LayoutContext,
+    positioning_context: &mut PositioningContext,
+    containing_block: &ContainingBlock,
+    children: &[ArcRefCell<FlexLevelBox>],
+) -> (Vec<Fragment>, Length) {
+    let children: Vec<_> = children.iter().map(|child| child.borrow()).collect();
+    let mut fragments_by_tree_rank = Vec::with_capacity(children.len());
+    let mut flex_item_boxes = Vec::new();
+    for (tree_rank, child) in children.iter().enumerate() {
+        match &**child {
+            FlexLevelBox::FlexItem(box_) => flex_item_boxes.push((tree_rank, box_)),
+            FlexLevelBox::OutOfFlowAbsolutelyPositionedBox(box_) => {
+                // FIXME: the static position should be determined as if the box
+                // were the sole flex item: https://drafts.csswg.org/css-flexbox/#abspos-items
+                let hoisted_box =
+                    AbsolutelyPositionedBox::to_hoisted(box_.clone(), Vec2::zero(), tree_rank);
+                let hoisted_fragment = hoisted_box.fragment.clone();
+                positioning_context.push(hoisted_box);
+                fragments_by_tree_rank.push((
+                    tree_rank,
+                    Fragment::AbsoluteOrFixedPositioned(AbsoluteOrFixedPositionedFragment {
+                        hoisted_fragment,
+                        position: box_.contents.style.clone_position(),
+                    }),
+                ));
+            },
+        }
+    }
+
+    let container_style = containing_block.style;
+    let position_style = container_style.get_position();
+    let axes = FlexAxes::new(position_style.flex_direction, position_style.flex_wrap);
+    let (container_min_block_size, container_max_block_size) =
+        content_block_size_limits(containing_block);
+    let (container_min_main_size, container_max_main_size) = match axes.flex_axis {
+        // The inline size of the container is always definite
+        FlexAxis::Row => (Length::zero(), None),
+        FlexAxis::Column => (container_min_block_size, container_max_block_size),
+    };
+    let (container_min_cross_size, container_max_cross_size) = match axes.flex_axis {
+        FlexAxis::Row => (container_min_block_size, container_max_block_size),
+        FlexAxis::Column => (Length::zero(), None),
+    };
+    let mut flex_context = FlexContext {
+        layout_context,
+        positioning_context,
+        containing_block,
+        axes,
+        container_is_single_line: position_style.flex_wrap == FlexWrap::Nowrap,
+        container_min_cross_size,
+        container_max_cross_size,
+        container_definite_inner_size: axes.vec2_to_flex_relative(Vec2 {
+            inline: Some(containing_block.inline_size),
+            block: containing_block.block_size.non_auto(),
+        }),
+        align_items: position_style.align_items,
+        align_content: position_style.align_content,
+        justify_content: position_style.justify_content,
+    };
+
+    let mut flex_items: Vec<_> = flex_item_boxes
+        .into_iter()
+        .map(|(tree_rank, box_)| FlexItem::new(&flex_context, box_, tree_rank))
+        .collect();
+
+    // “Determine the main size of the flex container”
+    // https://drafts.csswg.org/css-flexbox/#algo-main-container
+    let available_main_size = flex_context
+        .container_definite_inner_size
+        .main
+        .or(container_max_main_size);
+    let mut flex_lines = collect_flex_lines(
+        &mut flex_items,
+        flex_context.container_is_single_line,
+        available_main_size,
+    );
+    let container_main_size = match flex_context.container_definite_inner_size.main {
+        Some(size) => size,
+        None => flex_lines
+            .iter()
+            .map(|line| line.outer_hypothetical_main_sizes_sum)
+            .fold(Length::zero(), Length::max)
+            .clamp_between_extremums(container_min_main_size, container_max_main_size),
+    };
+
+    // https://drafts.csswg.org/css-flexbox/#algo-flex
+    // https://drafts.csswg.org/css-flexbox/#algo-cross-item
+    // https://drafts.csswg.org/css-flexbox/#algo-cross-line
+    for line in &mut flex_lines {
+        line.used_main_sizes = line.resolve_flexible_lengths(container_main_size);
+        line.hypothetical_cross_sizes = line
+            .items
+            .iter_mut()
+            .zip(&line.used_main_sizes)
+            .map(|(item, &used_main_size)| {
+                item.hypothetical_cross_size(used_main_size, &flex_context)
+            })
+            .collect();
+        line.cross_size = line.cross_size(&flex_context);
+    }
+
+    // “Determine the flex container’s used cross size”
+    // https://drafts.csswg.org/css-flexbox/#algo-cross-container
+    let line_cross_sizes_sum = flex_lines
+        .iter()
+        .fold(Length::zero(), |sum, line| sum + line.cross_size);
+    let container_cross_size = flex_context
+        .container_definite_inner_size
+        .cross
+        .unwrap_or_else(|| {
+            line_cross_sizes_sum.clamp_between_extremums(
+                flex_context.container_min_cross_size,
+                flex_context.container_max_cross_size,
+            )
+        });
+    let mut remaining_cross_space = container_cross_size - line_cross_sizes_sum;
+
+    // “Handle 'align-content: stretch'”
+    // https://drafts.csswg.org/css-flexbox/#algo-line-stretch
+    if !flex_context.container_is_single_line &&
+        flex_context.align_content == AlignContent::Stretch &&
+        remaining_cross_space > Length::zero()
+    {
+        let extra = remaining_cross_space / flex_lines.len() as f32;
+        for line in &mut flex_lines {
+            line.cross_size += extra;
+        }
+        remaining_cross_space = Length::zero();
+    }
+
+    // “Distribute any remaining free space” among lines, per `align-content`
+    // https://drafts.csswg.org/css-flexbox/#algo-line-align
+    let (mut line_cross_position, line_cross_gap) = if flex_context.container_is_single_line {
+        (Length::zero(), Length::zero())
+    } else {
+        let line_count = flex_lines.len();
+        match flex_context.align_content {
+            AlignContent::FlexStart | AlignContent::Stretch => (Length::zero(), Length::zero()),
+            AlignContent::FlexEnd => (remaining_cross_space, Length::zero()),
+            AlignContent::Center => (remaining_cross_space / 2., Length::zero()),
+            AlignContent::SpaceBetween => {
+                if remaining_cross_space > Length::zero() && line_count > 1 {
+                    (
+                        Length::zero(),
+                        remaining_cross_space / (line_count - 1) as f32,
+                    )
+                } else {
+                    (Length::zero(), Length::zero())
+                }
+            },
+            AlignContent::SpaceAround => {
+                if remaining_cross_space > Length::zero() {
+                    let gap = remaining_cross_space / line_count as f32;
+                    (gap / 2., gap)
+                } else {
+                    (remaining_cross_space / 2., Length::zero())
+                }
+            },
+        }
+    };
+
+    let container_content_size = FlexRelativeVec2 {
+        main: container_main_size,
+        cross: container_cross_size,
+    };
+    for line in flex_lines {
+        let line_cross_size = line.cross_size;
+        fragments_by_tree_rank.extend(line.finish(
+            &mut flex_context,
+            container_content_size,
+            line_cross_position,
+        ));
+        line_cross_position += line_cross_size + line_cross_gap;
+    }
+
+    fragments_by_tree_rank.sort_by_key(|&(tree_rank, _)| tree_rank);
+    let fragments = fragments_by_tree_rank
+        .into_iter()
+        .map(|(_, fragment)| fragment)
+        .collect();
+    let content_block_size = match axes.flex_axis {
+        FlexAxis::Row => container_cross_size,
+        FlexAxis::Column => container_main_size,
+    };
+    (fragments, content_block_size)
+}
+
+/// https://drafts.csswg.org/css-flexbox/#algo-line-break
+fn collect_flex_lines<'items, 'boxes>(
+    flex_items: &'items mut [FlexItem<'boxes>],
+    container_is_single_line: bool,
+    available_main_size: Option<Length>,
+) -> Vec<FlexLine<'items, 'boxes>> {
+    let outer_hypothetical_main_size =
+        |item: &FlexItem| item.hypothetical_main_size + item.pbm_auto_is_zero.main;
+    let available_main_size = match available_main_size {
+        Some(size) if !container_is_single_line => size,
+        _ => {
+            let outer_hypothetical_main_sizes_sum =
+                flex_items.iter().fold(Length::zero(), |sum, item| {
+                    sum + outer_hypothetical_main_size(item)
+                });
+            return vec![FlexLine::new(flex_items, outer_hypothetical_main_sizes_sum)];
+        },
+    };
+
+    let mut lines = Vec::new();
+    let mut remaining_items = flex_items;
+    while !remaining_items.is_empty() {
+        // “Collect consecutive items one by one until the first time that the next
+        //  collected item would not fit into the flex container’s inner main size”
+        let mut line_size = Length::zero();
+        let mut line_len = 0;
+        for item in remaining_items.iter() {
+            let item_size = outer_hypothetical_main_size(item);
+            // “If the very first uncollected item wouldn't fit, collect just it into the line.”
+            if line_len > 0 && line_size + item_size > available_main_size {
+                break;
+            }
+            line_size += item_size;
+            line_len += 1;
+        }
+        let (line_items, rest) = std::mem::take(&mut remaining_items).split_at_mut(line_len);
+        remaining_items = rest;
+        lines.push(FlexLine::new(line_items, line_size));
+    }
+    lines
+}
+
+impl<'items, 'boxes> FlexLine<'items, 'boxes> {
+    fn new(
+        items: &'items mut [FlexItem<'boxes>],
+        outer_hypothetical_main_sizes_sum: Length,
+    ) -> Self {
+        FlexLine {
+            items,
+            outer_hypothetical_main_sizes_sum,
+            used_main_sizes: Vec::new(),
+            hypothetical_cross_sizes: Vec::new(),
+            cross_size: Length::zero(),
+        }
+    }
+
+    /// Return the *main size* of each item
+    /// https://drafts.csswg.org/css-flexbox/#resolve-flexible-lengths
+    fn resolve_flexible_lengths(&self, container_main_size: Length) -> Vec<Length> {
+        let items = &*self.items;
+
+        // “Determine the used flex factor”
+        let grow = self.outer_hypothetical_main_sizes_sum < container_main_size;
+        let flex_factor = |item: &FlexItem| {
+            let position = item.box_.style.get_position();
+            if grow {
+                position.flex_grow.0
+            } else {
+                position.flex_shrink.0
+            }
+        };
+
+        // “Size inflexible items”
+        let mut target_main_sizes: Vec<_> = items.iter().map(|item| item.flex_base_size).collect();
+        let mut frozen: Vec<_> = items
+            .iter()
+            .map(|item| {
+                flex_factor(item) == 0. ||
+                    (grow && item.flex_base_size > item.hypothetical_main_size) ||
+                    (!grow && item.flex_base_size < item.hypothetical_main_size)
+            })
+            .collect();
+        for ((item, target_main_size), &frozen) in
+            items.iter().zip(&mut target_main_sizes).zip(&frozen)
+        {
+            if frozen {
+                *target_main_size = item.hypothetical_main_size
+            }
+        }
+
+        // “Calculate initial free space”
+        let free_space = |target_main_sizes: &[Length], frozen: &[bool]| {
+            let outer_sizes_sum = items.iter().zip(target_main_sizes).zip(frozen).fold(
+                Length::zero(),
+                |sum, ((item, &target), &frozen)| {
+                    let inner_size = if frozen { target } else { item.flex_base_size };
+                    sum + inner_size + item.pbm_auto_is_zero.main
+                },
+            );
+            container_main_size - outer_sizes_sum
+        };
+        let initial_free_space = free_space(&target_main_sizes, &frozen);
+
+        // “Loop”
+        while frozen.iter().any(|&frozen| !frozen) {
+            // “Calculate the remaining free space”
+            let mut remaining_free_space = free_space(&target_main_sizes, &frozen);
+            let unfrozen = || items.iter().zip(&frozen).filter(|(_, &frozen)| !frozen);
+            let unfrozen_flex_factors_sum: f32 =
+                unfrozen().map(|(item, _)| flex_factor(item)).sum();
+            if unfrozen_flex_factors_sum < 1. {
+                let scaled = initial_free_space * unfrozen_flex_factors_sum;
+                if scaled.abs() < remaining_free_space.abs() {
+                    remaining_free_space = scaled
+                }
+            }
+
+            // “Distribute free space proportional to the flex factors”
+            if remaining_free_space != Length::zero() {
+                let scaled_flex_factor = |item: &FlexItem| {
+                    if grow {
+                        flex_factor(item)
+                    } else {
+                        // “scaled flex shrink factor”
+                        flex_factor(item) * item.flex_base_size.px()
+                    }
+                };
+                let scaled_flex_factors_sum: f32 =
+                    unfrozen().map(|(item, _)| scaled_flex_factor(item)).sum();
+                if scaled_flex_factors_sum > 0. {
+                    for ((item, target_main_size), &frozen) in
+                        items.iter().zip(&mut target_main_sizes).zip(&frozen)
+                    {
+                        if !frozen {
+                            let ratio = scaled_flex_factor(item) / scaled_flex_factors_sum;
+                            *target_main_size = item.flex_base_size + remaining_free_space * ratio
+                        }
+                    }
+                }
+            }
+
+            // “Fix min/max violations”
+            let mut total_violation = Length::zero();
+            let violations: Vec<_> = items
+                .iter()
+                .zip(&mut target_main_sizes)
+                .zip(&frozen)
+                .map(|((item, target_main_size), &frozen)| {
+                    if frozen {
+                        return Length::zero();
+                    }
+                    let unclamped = *target_main_size;
+                    *target_main_size = unclamped.clamp_between_extremums(
+                        item.content_min_size.main,
+                        item.content_max_size.main,
+                    );
+                    let violation = *target_main_size - unclamped;
+                    total_violation += violation;
+                    violation
+                })
+                .collect();
+
+            // “Freeze over-flexed items”
+            for (is_frozen, violation) in frozen.iter_mut().zip(violations) {
+                if total_violation > Length::zero() {
+                    // “Freeze all the items with min violations.”
+                    *is_frozen |= violation > Length::zero()
+                } else if total_violation < Length::zero() {
+                    // “Freeze all the items with max violations.”
+                    *is_frozen |= violation < Length::zero()
+                } else {
+                    // “Freeze all items.”
+                    *is_frozen = true
+                }
+            }
+        }
+        target_main_sizes
+    }
+
+    /// https://drafts.csswg.org/css-flexbox/#algo-cross-line
+    fn cross_size(&self, flex_context: &FlexContext) -> Length {
+        if flex_context.container_is_single_line {
+            if let Some(size) = flex_context.container_definite_inner_size.cross {
+                return size;
+            }
+        }
+        let outer_hypothetical_cross_sizes_max = self
+            .items
+            .iter()
+            .zip(&self.hypothetical_cross_sizes)
+            .fold(Length::zero(), |max, (item, &cross_size)| {
+                // FIXME: baseline alignment
+                max.max(cross_size + item.pbm_auto_is_zero.cross)
+            });
+        if flex_context.container_is_single_line {
+            outer_hypothetical_cross_sizes_max.clamp_between_extremums(
+                flex_context.container_min_cross_size,
+                flex_context.container_max_cross_size,
+            )
+        } else {
+            outer_hypothetical_cross_sizes_max
+        }
+    }
+
+    /// Align the items of this line along both axes and lay them out with their used sizes,
+    /// returning their fragments with their tree rank.
+    /// https://drafts.csswg.org/css-flexbox/#algo-main-align
+    /// https://drafts.csswg.org/css-flexbox/#algo-cross-align
+    fn finish(
+        self,
+        flex_context: &mut FlexContext,
+        container_content_size: FlexRelativeVec2<Length>,
+        line_cross_position: Length,
+    ) -> Vec<(usize, Fragment)> {
+        let axes = flex_context.axes;
+
+        // “Resolve the flexible lengths of all the flex items to find their used main size”,
+        // then their used cross size:
+        // https://drafts.csswg.org/css-flexbox/#algo-stretch
+        let used_sizes: Vec<_> = self
+            .items
+            .iter()
+            .zip(&self.used_main_sizes)
+            .zip(&self.hypothetical_cross_sizes)
+            .map(|((item, &main), &hypothetical_cross)| {
+                let cross = if item.will_stretch() {
+                    (self.cross_size - item.pbm_auto_is_zero.cross).clamp_between_extremums(
+                        item.content_min_size.cross,
+                        item.content_max_size.cross,
+                    )
+                } else {
+                    hypothetical_cross
+                };
+                FlexRelativeVec2 { main, cross }
+            })
+            .collect();
+
+        // “Distribute any remaining free space”, first to `auto` margins
+        let outer_sizes_sum = self
+            .items
+            .iter()
+            .zip(&used_sizes)
+            .fold(Length::zero(), |sum, (item, size)| {
+                sum + size.main + item.pbm_auto_is_zero.main
+            });
+        let mut free_space = container_content_size.main - outer_sizes_sum;
+        let auto_margins_count = self
+            .items
+            .iter()
+            .map(|item| {
+                item.margin.main_start.is_auto() as u32 + item.margin.main_end.is_auto() as u32
+            })
+            .sum::<u32>();
+        let main_auto_margin = if free_space > Length::zero() && auto_margins_count > 0 {
+            let each = free_space / auto_margins_count as f32;
+            free_space = Length::zero();
+            each
+        } else {
+            Length::zero()
+        };
+
+        // … then per `justify-content`
+        let item_count = self.items.len();
+        let (mut main_position, main_gap) = match flex_context.justify_content {
+            JustifyContent::FlexStart | JustifyContent::Stretch => (Length::zero(), Length::zero()),
+            JustifyContent::FlexEnd => (free_space, Length::zero()),
+            JustifyContent::Center => (free_space / 2., Length::zero()),
+            JustifyContent::SpaceBetween => {
+                if free_space > Length::zero() && item_count > 1 {
+                    (Length::zero(), free_space / (item_count - 1) as f32)
+                } else {
+                    (Length::zero(), Length::zero())
+                }
+            },
+            JustifyContent::SpaceAround => {
+                if free_space > Length::zero() {
+                    let gap = free_space / item_count as f32;
+                    (gap / 2., gap)
+                } else {
+                    (free_space / 2., Length::zero())
+                }
+            },
+        };
+
+        let mut fragments = Vec::with_capacity(item_count);
+        for (item, used_size) in self.items.iter_mut().zip(used_sizes) {
+            let margin = item.resolve_margins(main_auto_margin, used_size.cross, self.cross_size);
+            let cross_position = item.align_along_cross_axis(used_size.cross, self.cross_size);
+            let padding_border = axes.sides_to_flex_relative(&item.padding + &item.border);
+            let content_rect = FlexRelativeRect {
+                start_corner: FlexRelativeVec2 {
+                    main: main_position + margin.main_start + padding_border.main_start,
+                    cross: line_cross_position +
+                        cross_position +
+                        margin.cross_start +
+                        padding_border.cross_start,
+                },
+                size: used_size,
+            };
+            main_position += margin.main_start +
+                item.padding_border_sums.main +
+                used_size.main +
+                margin.main_end +
+                main_gap;
+
+            item.layout(
+                FlexRelativeVec2 {
+                    main: Some(used_size.main),
+                    cross: Some(used_size.cross),
+                },
+                flex_context,
+            );
+            let FlexItemLayoutResult {
+                fragments: children,
+                positioning_context,
+                ..
+            } = item.cached_layout.take().unwrap();
+            let mut fragment = BoxFragment::new(
+                item.box_.tag,
+                item.box_.style.clone(),
+                children,
+                axes.rect_to_flow_relative(container_content_size, content_rect),
+                item.padding.clone(),
+                item.border.clone(),
+                axes.sides_to_flow_relative(margin),
+                CollapsedBlockMargins::zero(),
+            );
+            flex_context.positioning_context.append_laid_out_box(
+                flex_context.layout_context,
+                flex_context.containing_block,
+                positioning_context,
+                &mut fragment,
+            );
+            fragments.push((item.tree_rank, Fragment::Box(fragment)));
+        }
+        fragments
+    }
+}
+
+impl<'a> FlexItem<'a> {
+    fn new(
+        flex_context: &FlexContext,
+        box_: &'a IndependentFormattingContext,
+        tree_rank: usize,
+    ) -> Self {
+        let containing_block = flex_context.containing_block;
+        let axes = flex_context.axes;
+        let style = &*box_.style;
+
+        // https://drafts.csswg.org/css-writing-modes/#orthogonal-flows
+        assert_eq!(
+            containing_block.style.writing_mode, style.writing_mode,
+            "Mixed writing modes are not supported yet"
+        );
+
+        let pbm = style.padding_border_margin(containing_block);
+        let content_box_size =
+            axes.vec2_to_flex_relative(style.content_box_size(containing_block, &pbm));
+        let content_min_size =
+            axes.vec2_to_flex_relative(style.content_min_box_size(containing_block, &pbm));
+        let content_max_size =
+            axes.vec2_to_flex_relative(style.content_max_box_size(containing_block, &pbm));
+        let margin = axes.sides_to_flex_relative(pbm.margin.clone());
+        let padding_border_sums = axes.vec2_to_flex_relative(pbm.padding_border_sums.clone());
+        let pbm_auto_is_zero =
+            padding_border_sums + margin.map(|m| m.auto_is(Length::zero)).sum_by_axis();
+        let replaced_used_size = box_
+            .as_replaced()
+            .ok()
+            .map(|replaced| replaced.used_size_as_if_inline_element(containing_block, style, &pbm));
+        let align_self = match style.clone_align_self() {
+            AlignSelf::Auto => match flex_context.align_items {
+                AlignItems::Stretch => AlignSelf::Stretch,
+                AlignItems::FlexStart => AlignSelf::FlexStart,
+                AlignItems::FlexEnd => AlignSelf::FlexEnd,
+                AlignItems::Center => AlignSelf::Center,
+                AlignItems::Baseline => AlignSelf::Baseline,
+            },
+            align_self => align_self,
+        };
+
+        let mut item = FlexItem {
+            box_,
+            tree_rank,
+            content_box_size,
+            content_min_size: FlexRelativeVec2 {
+                // Resolved below, since it may depend on other fields
+                main: Length::zero(),
+                cross: content_min_size.cross.auto_is(Length::zero),
+            },
+            content_max_size,
+            padding: pbm.padding,
+            border: pbm.border,
+            margin,
+            padding_border_sums,
+            pbm_auto_is_zero,
+            replaced_used_size,
+            flex_base_size: Length::zero(),
+            hypothetical_main_size: Length::zero(),
+            align_self,
+            cached_layout: None,
+        };
+        item.content_min_size.main = match content_min_size.main {
+            LengthOrAuto::LengthPercentage(min) => min,
+            LengthOrAuto::Auto => item.automatic_min_main_size(flex_context),
+        };
+        item.flex_base_size = item.determine_flex_base_size(flex_context);
+        item.hypothetical_main_size = item
+            .flex_base_size
+            .clamp_between_extremums(item.content_min_size.main, item.content_max_size.main);
+        item
+    }
+
+    /// https://drafts.csswg.org/css-flexbox/#algo-main-item
+    fn determine_flex_base_size(&mut self, flex_context: &FlexContext) -> Length {
+        let style = &self.box_.style;
+        let used_flex_basis = match &style.get_position().flex_basis {
+            FlexBasis::Content => None,
+            FlexBasis::Size(Size::LengthPercentage(length_percentage)) => {
+                let container_main_size = flex_context.container_definite_inner_size.main;
+                length_percentage
+                    .0
+                    .maybe_percentage_relative_to(container_main_size)
+                    .map(|basis| match style.get_position().box_sizing {
+                        BoxSizing::ContentBox => basis,
+                        BoxSizing::BorderBox => {
+                            (basis - self.padding_border_sums.main).max(Length::zero())
+                        },
+                    })
+            },
+            // “When specified on a flex item, the `auto` keyword retrieves
+            //  the value of the main size property as the used `flex-basis`.”
+            FlexBasis::Size(Size::Auto) => self.content_box_size.main.non_auto(),
+        };
+
+        // “If the used flex basis is `content` or depends on its available space, …”
+        // size the item with its max-content main size.
+        used_flex_basis.unwrap_or_else(|| self.max_content_main_size(flex_context))
+    }
+
+    /// https://drafts.csswg.org/css-flexbox/#min-size-auto
+    fn automatic_min_main_size(&mut self, flex_context: &FlexContext) -> Length {
+        let style = &self.box_.style;
+        let box_style = style.get_box();
+        let (overflow_inline, overflow_block) = if style.writing_mode.is_horizontal() {
+            (box_style.overflow_x, box_style.overflow_y)
+        } else {
+            (box_style.overflow_y, box_style.overflow_x)
+        };
+        let main_overflow = match flex_context.axes.flex_axis {
+            FlexAxis::Row => overflow_inline,
+            FlexAxis::Column => overflow_block,
+        };
+        // “for flex items that are not scroll containers”
+        if main_overflow != Overflow::Visible {
+            return Length::zero();
+        }
+
+        // “The content size suggestion is the min-content size in the main axis,
+        //  […] further clamped by the max main size property if that is definite.”
+        let content_size_suggestion = self
+            .min_content_main_size(flex_context)
+            .clamp_below_max(self.content_max_size.main);
+
+        // “If the item’s computed main size property is definite, then the content-based
+        //  minimum size is the smaller of its specified size suggestion and its content
+        //  size suggestion”
+        match self.content_box_size.main {
+            LengthOrAuto::LengthPercentage(specified) => specified.min(content_size_suggestion),
+            LengthOrAuto::Auto => content_size_suggestion,
+        }
+    }
+
+    fn max_content_main_size(&mut self, flex_context: &FlexContext) -> Length {
+        if let Some(size) = &self.replaced_used_size {
+            return flex_context.axes.vec2_to_flex_relative(size.clone()).main;
+        }
+        match flex_context.axes.flex_axis {
+            FlexAxis::Row => self.box_.content_sizes.expect_inline().max_content,
+            FlexAxis::Column => self.column_content_block_size(flex_context),
+        }
+    }
+
+    fn min_content_main_size(&mut self, flex_context: &FlexContext) -> Length {
+        if let Some(size) = &self.replaced_used_size {
+            return flex_context.axes.vec2_to_flex_relative(size.clone()).main;
+        }
+        match flex_context.axes.flex_axis {
+            FlexAxis::Row => self.box_.content_sizes.expect_inline().min_content,
+            FlexAxis::Column => self.column_content_block_size(flex_context),
+        }
+    }
+
+    /// The block size of the contents of an item in a column flex container,
+    /// laid out before the size of its flex line is known.
+    fn column_content_block_size(&mut self, flex_context: &FlexContext) -> Length {
+        let cross_size = if self.will_stretch() && flex_context.container_is_single_line {
+            (flex_context.containing_block.inline_size - self.pbm_auto_is_zero.cross)
+                .clamp_between_extremums(self.content_min_size.cross, self.content_max_size.cross)
+        } else {
+            self.fit_content_cross_size(flex_context)
+        };
+        self.layout(
+            FlexRelativeVec2 {
+                main: None,
+                cross: Some(cross_size),
+            },
+            flex_context,
+        )
+        .main
+    }
+
+    /// The cross size of an item in a column flex container, treating `auto` as `fit-content`
+    fn fit_content_cross_size(&self, flex_context: &FlexContext) -> Length {
+        let content_cross_size = match self.content_box_size.cross {
+            LengthOrAuto::LengthPercentage(cross_size) => cross_size,
+            LengthOrAuto::Auto => match &self.replaced_used_size {
+                Some(size) => size.inline,
+                None => self.box_.content_sizes.shrink_to_fit(
+                    flex_context.containing_block.inline_size - self.pbm_auto_is_zero.cross,
+                ),
+            },
+        };
+        content_cross_size
+            .clamp_between_extremums(self.content_min_size.cross, self.content_max_size.cross)
+    }
+
+    /// https://drafts.csswg.org/css-flexbox/#algo-cross-item
+    fn hypothetical_cross_size(
+        &mut self,
+        used_main_size: Length,
+        flex_context: &FlexContext,
+    ) -> Length {
+        match flex_context.axes.flex_axis {
+            FlexAxis::Row => {
+                let content_cross_size = match self.content_box_size.cross {
+                    LengthOrAuto::LengthPercentage(cross_size) => cross_size,
+                    LengthOrAuto::Auto => {
+                        self.layout(
+                            FlexRelativeVec2 {
+                                main: Some(used_main_size),
+                                cross: None,
+                            },
+                            flex_context,
+                        )
+                        .cross
+                    },
+                };
+                content_cross_size.clamp_between_extremums(
+                    self.content_min_size.cross,
+                    self.content_max_size.cross,
+                )
+            },
+            FlexAxis::Column => self.fit_content_cross_size(flex_context),
+        }
+    }
+
+    /// https://drafts.csswg.org/css-flexbox/#algo-stretch
+    fn will_stretch(&self) -> bool {
+        self.align_self == AlignSelf::Stretch &&
+            self.content_box_size.cross.is_auto() &&
+            !self.margin.cross_start.is_auto() &&
+            !self.margin.cross_end.is_auto()
+    }
+
+    /// Resolve `auto` margins in both axes
+    /// https://drafts.csswg.org/css-flexbox/#algo-main-align
+    /// https://drafts.csswg.org/css-flexbox/#algo-cross-margins
+    fn resolve_margins(
+        &self,
+        main_auto_margin: Length,
+        used_cross_size: Length,
+        line_cross_size: Length,
+    ) -> FlexRelativeSides<Length> {
+        let free_cross_space = line_cross_size - used_cross_size - self.pbm_auto_is_zero.cross;
+        let cross_auto_margin = match (&self.margin.cross_start, &self.margin.cross_end) {
+            (LengthOrAuto::Auto, LengthOrAuto::Auto) => free_cross_space / 2.,
+            (LengthOrAuto::Auto, _) | (_, LengthOrAuto::Auto) => free_cross_space,
+            _ => Length::zero(),
+        }
+        .max(Length::zero());
+        FlexRelativeSides {
+            main_start: self.margin.main_start.auto_is(|| main_auto_margin),
+            main_end: self.margin.main_end.auto_is(|| main_auto_margin),
+            cross_start: self.margin.cross_start.auto_is(|| cross_auto_margin),
+            cross_end: self.margin.cross_end.auto_is(|| cross_auto_margin),
+        }
+    }
+
+    /// Return the position of the cross-start margin edge of this item
+    /// from the cross-start edge of its line.
+    /// https://drafts.csswg.org/css-flexbox/#algo-cross-align
+    fn align_along_cross_axis(&self, used_cross_size: Length, line_cross_size: Length) -> Length {
+        if self.margin.cross_start.is_auto() || self.margin.cross_end.is_auto() {
+            // Already taken care of by `resolve_margins`
+            return Length::zero();
+        }
+        let free_space = line_cross_size - used_cross_size - self.pbm_auto_is_zero.cross;
+        match self.align_self {
+            // FIXME: baseline alignment
+            AlignSelf::Auto | AlignSelf::Stretch | AlignSelf::FlexStart | AlignSelf::Baseline => {
+                Length::zero()
+            },
+            AlignSelf::FlexEnd => free_space,
+            AlignSelf::Center => free_space / 2.,
+        }
+    }
+
+    /// Lay out the contents of this item with the given sizes for its content box,
+    /// unless the most recent layout can be reused. `None` stands for `auto`,
+    /// which is only supported in the block axis.
+    ///
+    /// Return the size of the content box.
+    fn layout(
+        &mut self,
+        given_size: FlexRelativeVec2<Option<Length>>,
+        flex_context: &FlexContext,
+    ) -> FlexRelativeVec2<Length> {
+        if let Some(cached) = &self.cached_layout {
+            // An `auto` size that was resolved to the same length can be reused as-is.
+            let reusable = |given: Option<Length>, cached_given: Option<Length>, cached: Length| {
+                given == cached_given || (cached_given.is_none() && given == Some(cached))
+            };
+            if reusable(
+                given_size.main,
+                cached.given_size.main,
+                cached.content_size.main,
+            ) && reusable(
+                given_size.cross,
+                cached.given_size.cross,
+                cached.content_size.cross,
+            ) {
+                return cached.content_size;
+            }
+        }
+
+        let flow_size = flex_context.axes.vec2_to_flow_relative(given_size);
+        let inline_size = flow_size
+            .inline
+            .expect("The inline size of flex items is known before their layout");
+        let mut positioning_context = flex_context
+            .positioning_context
+            .new_for_subtree(&self.box_.style);
+        let (fragments, block_size) = match self.box_.as_replaced() {
+            Ok(replaced) => {
+                let block_size = flow_size.block.unwrap_or_else(|| {
+                    let natural_size = self.replaced_used_size.as_ref().unwrap();
+                    if natural_size.inline > Length::zero() {
+                        // Preserve the aspect ratio
+                        natural_size.block * (inline_size.px() / natural_size.inline.px())
+                    } else {
+                        natural_size.block
+                    }
+                });
+                let size = Vec2 {
+                    inline: inline_size,
+                    block: block_size,
+                };
+                (replaced.make_fragments(&self.box_.style, size), block_size)
+            },
+            Err(non_replaced) => {
+                let containing_block_for_children = ContainingBlock {
+                    inline_size,
+                    block_size: flow_size
+                        .block
+                        .map_or(LengthOrAuto::Auto, LengthOrAuto::LengthPercentage),
+                    style: &self.box_.style,
+                };
+                let layout = non_replaced.layout(
+                    flex_context.layout_context,
+                    &mut positioning_context,
+                    &containing_block_for_children,
+                    self.tree_rank,
+                );
+                let block_size = flow_size.block.unwrap_or(layout.content_block_size);
+                (layout.fragments, block_size)
+            },
+        };
+        let content_size = flex_context.axes.vec2_to_flex_relative(Vec2 {
+            inline: inline_size,
+            block: block_size,
+        });
+        self.cached_layout = Some(FlexItemLayoutResult {
+            given_size,
+            content_size,
+            fragments,
+            positioning_context,
+        });
+        content_size
+    }
+}
@@ -0,0 +1,27 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Flex layout: https://drafts.csswg.org/css-flexbox/
+
+use crate::cell::ArcRefCell;
+use crate::formatting_contexts::IndependentFormattingContext;
+use crate::positioned::AbsolutelyPositionedBox;
+use servo_arc::Arc;
+
+mod construct;
+mod geom;
+mod layout;
+
+#[derive(Debug, Serialize)]
+pub(crate) struct FlexContainer {
+    /// The children of the flex container,
+    /// in https://drafts.csswg.org/css-flexbox/#order-modified-document-order
+    children: Vec<ArcRefCell<FlexLevelBox>>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) enum FlexLevelBox {
+    FlexItem(IndependentFormattingContext),
+    OutOfFlowAbsolutelyPositionedBox(Arc<AbsolutelyPositionedBox>),
+}
@@ -4,11 +4,14 @@
 
 use crate::cell::ArcRefCell;
 use crate::context::LayoutContext;
-use crate::dom_traversal::{BoxSlot, Contents, NodeExt, NonReplacedContents, TraversalHandler};
+use crate::dom_traversal::{
+    is_only_document_white_space, BoxSlot, Contents, DeferredChild, NodeExt, NonReplacedContents,
+    TraversalHandler,
+};
 use crate::element_data::LayoutBox;
 use crate::flow::float::FloatBox;
 use crate::flow::inline::{InlineBox, InlineFormattingContext, InlineLevelBox, TextRun};
-use crate::flow::{BlockContainer, BlockFormattingContext, BlockLevelBox};
+use crate::flow::{BlockContainer, BlockFormattingContext, BlockLevelBox, OutsideMarker};
 use crate::formatting_contexts::IndependentFormattingContext;
 use crate::positioned::AbsolutelyPositionedBox;
 use crate::sizing::{BoxContentSizes, ContentSizes, ContentSizesRequest};
@@ -47,6 +50,31 @@ impl BlockFormattingContext {
         };
         (bfc, inline_content_sizes)
     }
+
+    /// Construct the formatting context of an anonymous block container
+    /// which only wraps the given children, such as an anonymous flex item or table cell.
+    pub fn construct_for_anonymous_box<'dom, Node>(
+        context: &LayoutContext,
+        root: Node,
+        style: &Arc<ComputedValues>,
+        children: impl IntoIterator<Item = DeferredChild<'dom, Node>>,
+        content_sizes: ContentSizesRequest,
+        text_decoration_line: TextDecorationLine,
+    ) -> (Self, BoxContentSizes)
+    where
+        Node: NodeExt<'dom>,
+    {
+        let mut builder = BlockContainerBuilder::new(context, root, style, text_decoration_line);
+        for child in children {
+            child.replay(&mut builder);
+        }
+        let (contents, contains_floats, content_sizes) = builder.finish(content_sizes);
+        let bfc = Self {
+            contents,
+            contains_floats: contains_floats == ContainsFloats::Yes,
+        };
+        (bfc, content_sizes)
+    }
 }
 
 struct BlockLevelJob<'dom, Node> {
@@ -71,6 +99,18 @@ enum BlockLevelCreator {
         display_inside: DisplayInside,
         contents: Contents,
     },
+    /// Already constructed, since its children were found during the traversal of its parent
+    AnonymousTable(IndependentFormattingContext),
+    /// The `::marker` of a list item with `list-style-position: outside`
+    OutsideMarker {
+        contents: NonReplacedContents,
+    },
+    /// The box built by the previous box construction, if it can be reused.
+    /// Otherwise, the box is built again as with `kind`.
+    Reused {
+        box_: ArcRefCell<BlockLevelBox>,
+        kind: Box<BlockLevelCreator>,
+    },
 }
 
 /// A block container that may still have to be constructed.
@@ -139,6 +179,10 @@ struct BlockContainerBuilder<'dom, 'style, Node> {
     /// boxes, if any (see `end_ongoing_inline_formatting_context`).
     anonymous_style: Option<Arc<ComputedValues>>,
 
+    /// Consecutive table-internal children found so far which are not in a table,
+    /// to be wrapped in an anonymous table (see `end_ongoing_anonymous_table`).
+    ongoing_anonymous_table_children: Vec<DeferredChild<'dom, Node>>,
+
     /// Whether the resulting block container contains any float box.
     contains_floats: ContainsFloats,
 }
@@ -154,91 +198,44 @@ impl BlockContainer {
     ) -> (BlockContainer, ContainsFloats, BoxContentSizes) {
         let text_decoration_line =
             propagated_text_decoration_line | block_container_style.clone_text_decoration_line();
-        let mut builder = BlockContainerBuilder {
-            context,
-            root,
-            block_container_style,
-            block_level_boxes: Vec::new(),
-            ongoing_inline_formatting_context: InlineFormattingContext::new(text_decoration_line),
-            ongoing_inline_boxes_stack: Vec::new(),
-            anonymous_style: None,
-            contains_floats: ContainsFloats::No,
-        };
-
+        let mut builder =
+            BlockContainerBuilder::new(context, root, block_container_style, text_decoration_line);
         contents.traverse(context, root, block_container_style, &mut builder);
+        builder.finish(content_sizes)
+    }
 
-        debug_assert!(builder.ongoing_inline_boxes_stack.is_empty());
-
-        if !builder
-            .ongoing_inline_formatting_context
-            .inline_level_boxes
-            .is_empty()
-        {
-            if builder.block_level_boxes.is_empty() {
-                let content_sizes = content_sizes.compute(|| {
-                    builder
-                        .ongoing_inline_formatting_context
-                        .inline_content_sizes(context)
-                });
-                let container = BlockContainer::InlineFormattingContext(
-                    builder.ongoing_inline_formatting_context,
-                );
-                return (container, builder.contains_floats, content_sizes);
-            }
-            builder.end_ongoing_inline_formatting_context();
+    /// Whether this block container contains float boxes of its block formatting context,
+    /// like `BlockContainerBuilder::contains_floats` when it was constructed.
+    fn contains_floats(&self) -> ContainsFloats {
+        fn inline_level_boxes_contain_floats(boxes: &[ArcRefCell<InlineLevelBox>]) -> bool {
+            boxes.iter().any(|box_| match &*box_.borrow() {
+                InlineLevelBox::OutOfFlowFloatBox(_) => true,
+                InlineLevelBox::InlineBox(inline_box) => {
+                    inline_level_boxes_contain_floats(&inline_box.children)
+                },
+                _ => false,
+            })
         }
 
-        struct Accumulator {
-            contains_floats: ContainsFloats,
-            outer_content_sizes_of_children: ContentSizes,
-        }
-        let mut acc = Accumulator {
-            contains_floats: builder.contains_floats,
-            outer_content_sizes_of_children: ContentSizes::zero(),
-        };
-        let mapfold = |acc: &mut Accumulator, creator: BlockLevelJob<'dom, _>| {
-            let (block_level_box, box_contains_floats) = creator.finish(
-                context,
-                content_sizes.if_requests_inline(|| &mut acc.outer_content_sizes_of_children),
-            );
-            acc.contains_floats |= box_contains_floats;
-            block_level_box
-        };
-        let block_level_boxes = if context.use_rayon {
-            builder
-                .block_level_boxes
-                .into_par_iter()
-                .mapfold_reduce_into(
-                    &mut acc,
-                    mapfold,
-                    || Accumulator {
-                        contains_floats: ContainsFloats::No,
-                        outer_content_sizes_of_children: ContentSizes::zero(),
+        let contains_floats = match self {
+            BlockContainer::BlockLevelBoxes(boxes) => {
+                boxes.iter().any(|box_| match &*box_.borrow() {
+                    BlockLevelBox::OutOfFlowFloatBox(_) => true,
+                    BlockLevelBox::SameFormattingContextBlock { contents, .. } => {
+                        contents.contains_floats() == ContainsFloats::Yes
                     },
-                    |left, right| {
-                        left.contains_floats |= right.contains_floats;
-                        if content_sizes.requests_inline() {
-                            left.outer_content_sizes_of_children
-                                .max_assign(&right.outer_content_sizes_of_children)
-                        }
-                    },
-                )
-                .collect()
-        } else {
-            builder
-                .block_level_boxes
-                .into_iter()
-                .map(|x| mapfold(&mut acc, x))
-                .collect()
+                    _ => false,
+                })
+            },
+            BlockContainer::InlineFormattingContext(ifc) => {
+                inline_level_boxes_contain_floats(&ifc.inline_level_boxes)
+            },
         };
-        let container = BlockContainer::BlockLevelBoxes(block_level_boxes);
-
-        let Accumulator {
-            contains_floats,
-            outer_content_sizes_of_children,
-        } = acc;
-        let content_sizes = content_sizes.compute(|| outer_content_sizes_of_children);
-        (container, contains_floats, content_sizes)
+        if contains_floats {
+            ContainsFloats::Yes
+        } else {
+            ContainsFloats::No
+        }
     }
 }
 
@@ -254,35 +251,52 @@ where
         contents: Contents,
         box_slot: BoxSlot<'dom>,
     ) {
-        match display {
-            DisplayGeneratingBox::OutsideInside { outside, inside } => match outside {
-                DisplayOutside::Inline => box_slot.set(LayoutBox::InlineLevel(
-                    self.handle_inline_level_element(node, style, inside, contents),
-                )),
-                DisplayOutside::Block => {
-                    let box_style = style.get_box();
-                    // Floats and abspos cause blockification, so they only happen in this case.
-                    // https://drafts.csswg.org/css2/visuren.html#dis-pos-flo
-                    if box_style.position.is_absolutely_positioned() {
-                        self.handle_absolutely_positioned_element(
-                            node,
-                            style.clone(),
-                            inside,
-                            contents,
-                            box_slot,
-                        )
-                    } else if box_style.float.is_floating() {
-                        self.handle_float_element(node, style.clone(), inside, contents, box_slot)
-                    } else {
-                        self.handle_block_level_element(
-                            node,
-                            style.clone(),
-                            inside,
-                            contents,
-                            box_slot,
-                        )
-                    }
-                },
+        let (outside, inside) = match display {
+            DisplayGeneratingBox::OutsideInside {
+                outside, inside, ..
+            } => (outside, inside),
+            DisplayGeneratingBox::LayoutInternal(_) => {
+                // Misparented table-internal boxes are wrapped in an anonymous table,
+                // together with their table-internal siblings:
+                // https://drafts.csswg.org/css-tables/#fixup-algorithm
+                self.ongoing_anonymous_table_children
+                    .push(DeferredChild::Element {
+                        node,
+                        style: style.clone(),
+                        display,
+                        contents,
+                        box_slot,
+                    });
+                return;
+            },
+        };
+        self.end_ongoing_anonymous_table();
+
+        match outside {
+            DisplayOutside::Inline => box_slot.set(LayoutBox::InlineLevel(
+                self.handle_inline_level_element(node, style, inside, contents),
+            )),
+            DisplayOutside::Block => {
+                let box_style = style.get_box();
+                // Floats and abspos cause blockification, so they only happen in this case.
+                // https://drafts.csswg.org/css2/visuren.html#dis-pos-flo
+                // The same goes for markers outside of their list item.
+                // https://drafts.csswg.org/css-lists/#list-style-position-property
+                if style.pseudo().map_or(false, |pseudo| pseudo.is_marker()) {
+                    self.handle_outside_marker(node, style.clone(), contents, box_slot)
+                } else if box_style.position.is_absolutely_positioned() {
+                    self.handle_absolutely_positioned_element(
+                        node,
+                        style.clone(),
+                        inside,
+                        contents,
+                        box_slot,
+                    )
+                } else if box_style.float.is_floating() {
+                    self.handle_float_element(node, style.clone(), inside, contents, box_slot)
+                } else {
+                    self.handle_block_level_element(node, style.clone(), inside, contents, box_slot)
+                }
             },
         }
     }
@@ -293,6 +307,21 @@ where
         input: Cow<'dom, str>,
         parent_style: &Arc<ComputedValues>,
     ) {
+        if !self.ongoing_anonymous_table_children.is_empty() {
+            // White space between table-internal boxes is part of their anonymous table,
+            // which then ignores it.
+            if is_only_document_white_space(&input) {
+                self.ongoing_anonymous_table_children
+                    .push(DeferredChild::Text {
+                        node,
+                        text: input,
+                        parent_style: parent_style.clone(),
+                    });
+                return;
+            }
+            self.end_ongoing_anonymous_table();
+        }
+
         let (leading_whitespace, mut input) = self.handle_leading_whitespace(&input);
         if leading_whitespace || !input.is_empty() {
             // This text node should be pushed either to the next ongoing
@@ -352,10 +381,106 @@ where
     }
 }
 
-impl<'dom, Node> BlockContainerBuilder<'dom, '_, Node>
+impl<'dom, 'style, Node> BlockContainerBuilder<'dom, 'style, Node>
 where
     Node: NodeExt<'dom>,
 {
+    fn new(
+        context: &'style LayoutContext<'style>,
+        root: Node,
+        block_container_style: &'style Arc<ComputedValues>,
+        text_decoration_line: TextDecorationLine,
+    ) -> Self {
+        BlockContainerBuilder {
+            context,
+            root,
+            block_container_style,
+            block_level_boxes: Vec::new(),
+            ongoing_inline_formatting_context: InlineFormattingContext::new(text_decoration_line),
+            ongoing_inline_boxes_stack: Vec::new(),
+            anonymous_style: None,
+            ongoing_anonymous_table_children: Vec::new(),
+            contains_floats: ContainsFloats::No,
+        }
+    }
+
+    fn finish(
+        mut self,
+        content_sizes: ContentSizesRequest,
+    ) -> (BlockContainer, ContainsFloats, BoxContentSizes) {
+        let context = self.context;
+        self.end_ongoing_anonymous_table();
+
+        debug_assert!(self.ongoing_inline_boxes_stack.is_empty());
+
+        if !self
+            .ongoing_inline_formatting_context
+            .inline_level_boxes
+            .is_empty()
+        {
+            if self.block_level_boxes.is_empty() {
+                let content_sizes = content_sizes.compute(|| {
+                    self.ongoing_inline_formatting_context
+                        .inline_content_sizes(context)
+                });
+                let container =
+                    BlockContainer::InlineFormattingContext(self.ongoing_inline_formatting_context);
+                return (container, self.contains_floats, content_sizes);
+            }
+            self.end_ongoing_inline_formatting_context();
+        }
+
+        struct Accumulator {
+            contains_floats: ContainsFloats,
+            outer_content_sizes_of_children: ContentSizes,
+        }
+        let mut acc = Accumulator {
+            contains_floats: self.contains_floats,
+            outer_content_sizes_of_children: ContentSizes::zero(),
+        };
+        let mapfold = |acc: &mut Accumulator, creator: BlockLevelJob<'dom, _>| {
+            let (block_level_box, box_contains_floats) = creator.finish(
+                context,
+                content_sizes.if_requests_inline(|| &mut acc.outer_content_sizes_of_children),
+            );
+            acc.contains_floats |= box_contains_floats;
+            block_level_box
+        };
+        let block_level_boxes = if context.use_rayon {
+            self.block_level_boxes
+                .into_par_iter()
+                .mapfold_reduce_into(
+                    &mut acc,
+                    mapfold,
+                    || Accumulator {
+                        contains_floats: ContainsFloats::No,
+                        outer_content_sizes_of_children: ContentSizes::zero(),
+                    },
+                    |left, right| {
+                        left.contains_floats |= right.contains_floats;
+                        if content_sizes.requests_inline() {
+                            left.outer_content_sizes_of_children
+                                .max_assign(&right.outer_content_sizes_of_children)
+                        }
+                    },
+                )
+                .collect()
+        } else {
+            self.block_level_boxes
+                .into_iter()
+                .map(|x| mapfold(&mut acc, x))
+                .collect()
+        };
+        let container = BlockContainer::BlockLevelBoxes(block_level_boxes);
+
+        let Accumulator {
+            contains_floats,
+            outer_content_sizes_of_children,
+        } = acc;
+        let content_sizes = content_sizes.compute(|| outer_content_sizes_of_children);
+        (container, contains_floats, content_sizes)
+    }
+
     /// Returns:
     ///
     /// * Whether this text run has preserved (non-collapsible) leading whitespace
@@ -441,6 +566,7 @@ where
                 &style,
                 self,
             );
+            self.end_ongoing_anonymous_table();
 
             let mut inline_box = self
                 .ongoing_inline_boxes_stack
@@ -472,7 +598,7 @@ where
         style: Arc<ComputedValues>,
         display_inside: DisplayInside,
         contents: Contents,
-        box_slot: BoxSlot<'dom>,
+        mut box_slot: BoxSlot<'dom>,
     ) {
         // We just found a block level element, all ongoing inline level boxes
         // need to be split around it. We iterate on the fragmented inline
@@ -523,9 +649,14 @@ where
 
         let kind = match contents.try_into() {
             Ok(contents) => match display_inside {
-                DisplayInside::Flow => BlockLevelCreator::SameFormattingContextBlock(
-                    IntermediateBlockContainer::Deferred(contents, propagated_text_decoration_line),
-                ),
+                DisplayInside::Flow if !style.establishes_layout_containment() => {
+                    BlockLevelCreator::SameFormattingContextBlock(
+                        IntermediateBlockContainer::Deferred(
+                            contents,
+                            propagated_text_decoration_line,
+                        ),
+                    )
+                },
                 _ => BlockLevelCreator::Independent {
                     display_inside,
                     contents: contents.into(),
@@ -541,6 +672,8 @@ where
                 }
             },
         };
+        let kind =
+            reuse_block_level_box(node, &mut box_slot, kind, propagated_text_decoration_line);
         self.block_level_boxes.push(BlockLevelJob {
             node,
             box_slot,
@@ -549,6 +682,27 @@ where
         });
     }
 
+    fn handle_outside_marker(
+        &mut self,
+        node: Node,
+        style: Arc<ComputedValues>,
+        contents: Contents,
+        box_slot: BoxSlot<'dom>,
+    ) {
+        // The marker is the first box generated by its list item.
+        debug_assert!(!self.has_ongoing_inline_formatting_context());
+        let contents = match contents.try_into() {
+            Ok(contents) => contents,
+            Err(_) => unreachable!("Pseudo-elements are never replaced elements"),
+        };
+        self.block_level_boxes.push(BlockLevelJob {
+            node,
+            box_slot,
+            style,
+            kind: BlockLevelCreator::OutsideMarker { contents },
+        });
+    }
+
     fn handle_absolutely_positioned_element(
         &mut self,
         node: Node,
@@ -654,6 +808,67 @@ where
         });
     }
 
+    /// Wrap the ongoing table-internal children in an anonymous table, which is
+    /// inline-level if their parent is an inline box and block-level otherwise.
+    fn end_ongoing_anonymous_table(&mut self) {
+        if self.ongoing_anonymous_table_children.is_empty() {
+            return;
+        }
+        let children = std::mem::take(&mut self.ongoing_anonymous_table_children);
+
+        let context = self.context;
+        let parent_style = match self.ongoing_inline_boxes_stack.last() {
+            Some(inline_box) => &inline_box.style,
+            None => self.block_container_style,
+        };
+        let anonymous_style = |pseudo, parent_style: &ComputedValues| {
+            context
+                .shared_context()
+                .stylist
+                .style_for_anonymous::<Node::ConcreteElement>(
+                    &context.shared_context().guards,
+                    pseudo,
+                    parent_style,
+                )
+        };
+        // Like the wrapper box of tables, the anonymous table does not inherit margins.
+        let wrapper_style =
+            anonymous_style(&PseudoElement::ServoAnonymousTableWrapper, parent_style);
+        let style = anonymous_style(&PseudoElement::ServoAnonymousTable, &wrapper_style);
+
+        if self.ongoing_inline_boxes_stack.is_empty() {
+            let propagated_text_decoration_line =
+                self.ongoing_inline_formatting_context.text_decoration_line;
+            self.end_ongoing_inline_formatting_context();
+            let contents = IndependentFormattingContext::construct_anonymous_table(
+                context,
+                self.root,
+                style.clone(),
+                children,
+                ContentSizesRequest::Inline,
+                propagated_text_decoration_line,
+            );
+            self.block_level_boxes.push(BlockLevelJob {
+                node: self.root,
+                box_slot: BoxSlot::dummy(),
+                style,
+                kind: BlockLevelCreator::AnonymousTable(contents),
+            });
+        } else {
+            let contents = IndependentFormattingContext::construct_anonymous_table(
+                context,
+                self.root,
+                style,
+                children,
+                ContentSizesRequest::Inline,
+                // Text decorations are not propagated to atomic inline-level descendants.
+                TextDecorationLine::NONE,
+            );
+            self.current_inline_level_boxes()
+                .push(ArcRefCell::new(InlineLevelBox::Atomic(contents)));
+        }
+    }
+
     fn current_inline_level_boxes(&mut self) -> &mut Vec<ArcRefCell<InlineLevelBox>> {
         match self.ongoing_inline_boxes_stack.last_mut() {
             Some(last) => &mut last.children,
@@ -670,6 +885,46 @@ where
     }
 }
 
+/// Takes the block-level box built for an element by the previous box construction
+/// if it can be reused, keeping `kind` in case a new one has to be built anyway
+/// (see `BlockLevelJob::finish`).
+fn reuse_block_level_box<'dom>(
+    node: impl NodeExt<'dom>,
+    box_slot: &mut BoxSlot<'dom>,
+    kind: BlockLevelCreator,
+    propagated_text_decoration_line: TextDecorationLine,
+) -> BlockLevelCreator {
+    // Text decorations are the part of a block-level box that comes from its ancestors.
+    let previous_text_decoration_line = std::mem::replace(
+        &mut node.layout_data_mut().propagated_text_decoration_line,
+        propagated_text_decoration_line,
+    );
+    let box_ = match box_slot.take_reusable_box() {
+        Some(LayoutBox::BlockLevel(box_))
+            if previous_text_decoration_line == propagated_text_decoration_line =>
+        {
+            box_
+        },
+        _ => return kind,
+    };
+    let is_same_kind = match (&*box_.borrow(), &kind) {
+        (
+            BlockLevelBox::SameFormattingContextBlock { .. },
+            BlockLevelCreator::SameFormattingContextBlock(_),
+        ) |
+        (BlockLevelBox::Independent(_), BlockLevelCreator::Independent { .. }) => true,
+        _ => false,
+    };
+    if is_same_kind {
+        BlockLevelCreator::Reused {
+            box_,
+            kind: Box::new(kind),
+        }
+    } else {
+        kind
+    }
+}
+
 impl<'dom, Node> BlockLevelJob<'dom, Node>
 where
     Node: NodeExt<'dom>,
@@ -753,6 +1008,76 @@ where
                 ));
                 (block_level_box, ContainsFloats::Yes)
             },
+            BlockLevelCreator::AnonymousTable(contents) => {
+                if let Some(to) = max_assign_in_flow_outer_content_sizes_to {
+                    to.max_assign(&contents.content_sizes.outer_inline(&contents.style))
+                }
+                (
+                    ArcRefCell::new(BlockLevelBox::Independent(contents)),
+                    ContainsFloats::No,
+                )
+            },
+            // Outside markers do not contribute to the content sizes of their list item.
+            BlockLevelCreator::OutsideMarker { contents } => {
+                let (block_container, _, content_sizes) = BlockContainer::construct(
+                    context,
+                    node,
+                    &style,
+                    contents,
+                    ContentSizesRequest::Inline,
+                    TextDecorationLine::NONE,
+                );
+                let block_level_box =
+                    ArcRefCell::new(BlockLevelBox::OutsideMarker(OutsideMarker {
+                        tag: node.as_opaque(),
+                        style,
+                        block_container,
+                        inline_content_sizes: content_sizes.expect_inline().clone(),
+                    }));
+                (block_level_box, ContainsFloats::No)
+            },
+            BlockLevelCreator::Reused { box_, kind } => {
+                let requests_content_sizes = max_assign_in_flow_outer_content_sizes_to.is_some() &&
+                    !style.inline_size_is_length();
+                let contains_floats = match &*box_.borrow() {
+                    // Content sizes are not kept in the box tree for these boxes.
+                    BlockLevelBox::SameFormattingContextBlock { contents, .. }
+                        if !requests_content_sizes =>
+                    {
+                        Some(contents.contains_floats())
+                    },
+                    BlockLevelBox::Independent(contents)
+                        if !requests_content_sizes ||
+                            matches!(contents.content_sizes, BoxContentSizes::Inline(_)) =>
+                    {
+                        Some(ContainsFloats::No)
+                    },
+                    _ => None,
+                };
+                let contains_floats = match contains_floats {
+                    Some(contains_floats) => contains_floats,
+                    None => {
+                        let job = BlockLevelJob {
+                            node,
+                            box_slot: self.box_slot,
+                            style,
+                            kind: *kind,
+                        };
+                        return job.finish(context, max_assign_in_flow_outer_content_sizes_to);
+                    },
+                };
+                if let Some(to) = max_assign_in_flow_outer_content_sizes_to {
+                    let outer_content_sizes = match &*box_.borrow() {
+                        BlockLevelBox::Independent(contents) => {
+                            contents.content_sizes.outer_inline(&contents.style)
+                        },
+                        _ => BoxContentSizes::NoneWereRequested.outer_inline(&style),
+                    };
+                    to.max_assign(&outer_content_sizes)
+                }
+                context.box_construction_statistics.note_reused_box();
+                (box_, contains_floats)
+            },
         };
         self.box_slot
             .set(LayoutBox::BlockLevel(block_level_box.clone()));
@@ -288,7 +288,7 @@ impl InlineFormattingContext {
                             match Display::from(box_.contents.style.get_box().original_display) {
                                 Display::GeneratingBox(DisplayGeneratingBox::OutsideInside {
                                     outside,
-                                    inside: _,
+                                    ..
                                 }) => Vec2 {
                                     inline: match outside {
                                         DisplayOutside::Inline => ifc.inline_position,
@@ -296,6 +296,13 @@ impl InlineFormattingContext {
                                     },
                                     block: ifc.lines.next_line_block_position,
                                 },
+                                // Treat the hypothetical box of layout-internal boxes as block-level.
+                                Display::GeneratingBox(DisplayGeneratingBox::LayoutInternal(_)) => {
+                                    Vec2 {
+                                        inline: Length::zero(),
+                                        block: ifc.lines.next_line_block_position,
+                                    }
+                                },
                                 Display::Contents => {
                                     panic!("display:contents does not generate an abspos box")
                                 },
@@ -14,6 +14,7 @@ use crate::fragments::{CollapsedBlockMargins, CollapsedMargin, Fragment};
 use crate::geom::flow_relative::{Rect, Sides, Vec2};
 use crate::positioned::{AbsolutelyPositionedBox, PositioningContext};
 use crate::replaced::ReplacedContent;
+use crate::sizing::ContentSizes;
 use crate::style_ext::{ComputedValuesExt, PaddingBorderMargin};
 use crate::ContainingBlock;
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
@@ -54,6 +55,18 @@ pub(crate) enum BlockLevelBox {
     OutOfFlowAbsolutelyPositionedBox(Arc<AbsolutelyPositionedBox>),
     OutOfFlowFloatBox(FloatBox),
     Independent(IndependentFormattingContext),
+    OutsideMarker(OutsideMarker),
+}
+
+/// The `::marker` of a list item with `list-style-position: outside`.
+/// https://drafts.csswg.org/css-lists/#list-style-position-property
+#[derive(Debug, Serialize)]
+pub(crate) struct OutsideMarker {
+    pub tag: OpaqueNode,
+    #[serde(skip_serializing)]
+    pub style: Arc<ComputedValues>,
+    pub block_container: BlockContainer,
+    pub inline_content_sizes: ContentSizes,
 }
 
 struct FlowLayout {
@@ -330,10 +343,69 @@ impl BlockLevelBox {
                     containing_block.style.writing_mode,
                 ))
             },
+            BlockLevelBox::OutsideMarker(marker) => marker.layout(
+                layout_context,
+                positioning_context,
+                containing_block,
+                tree_rank,
+            ),
         }
     }
 }
 
+impl OutsideMarker {
+    /// The marker is placed before the start of the content box of its list item,
+    /// without affecting the placement of other boxes.
+    fn layout(
+        &self,
+        layout_context: &LayoutContext,
+        positioning_context: &mut PositioningContext,
+        containing_block: &ContainingBlock,
+        tree_rank: usize,
+    ) -> Fragment {
+        let inline_size = self.inline_content_sizes.max_content;
+        let containing_block_for_children = ContainingBlock {
+            inline_size,
+            block_size: LengthOrAuto::Auto,
+            style: &self.style,
+        };
+        let flow_layout = self.block_container.layout(
+            layout_context,
+            positioning_context,
+            &containing_block_for_children,
+            tree_rank,
+            None,
+            CollapsibleWithParentStartMargin(false),
+        );
+        let content_rect = Rect {
+            start_corner: Vec2 {
+                block: Length::zero(),
+                inline: -inline_size,
+            },
+            size: Vec2 {
+                block: flow_layout.content_block_size,
+                inline: inline_size,
+            },
+        };
+        let fragment = BoxFragment::new(
+            self.tag,
+            self.style.clone(),
+            flow_layout.fragments,
+            content_rect,
+            Sides::zero(),
+            Sides::zero(),
+            Sides::zero(),
+            CollapsedBlockMargins::zero(),
+        );
+        // An anonymous fragment is skipped when placing block-level fragments.
+        Fragment::Anonymous(AnonymousFragment::new(
+            Rect::zero(),
+            vec![Fragment::Box(fragment)],
+            containing_block.style.writing_mode,
+        ))
+    }
+}
+
 enum NonReplacedContents<'a> {
     SameFormattingContextBlock(&'a BlockContainer),
     EstablishesAnIndependentFormattingContext(NonReplacedIFC<'a>),
@@ -362,17 +434,35 @@ fn layout_in_flow_non_replaced_block_level(
     let solve_inline_margins = |inline_size| {
         solve_inline_margins_for_in_flow_block_level(containing_block, &pbm, inline_size)
     };
+    let table_content_sizes = match &block_level_kind {
+        NonReplacedContents::EstablishesAnIndependentFormattingContext(non_replaced) => {
+            non_replaced.table_content_sizes()
+        },
+        NonReplacedContents::SameFormattingContextBlock(_) => None,
+    };
     let (mut inline_size, mut inline_margins) =
         if let Some(inline_size) = box_size.inline.non_auto() {
             (inline_size, solve_inline_margins(inline_size))
         } else {
             let margin_inline_start = pbm.margin.inline_start.auto_is(Length::zero);
             let margin_inline_end = pbm.margin.inline_end.auto_is(Length::zero);
-            let inline_size = containing_block.inline_size -
+            let available_inline_size = containing_block.inline_size -
                 pbm.padding_border_sums.inline -
                 margin_inline_start -
                 margin_inline_end;
-            (inline_size, (margin_inline_start, margin_inline_end))
+            match table_content_sizes {
+                // https://drafts.csswg.org/css-tables/#auto-table-layout
+                Some(content_sizes) => {
+                    let inline_size = available_inline_size
+                        .min(content_sizes.max_content)
+                        .max(content_sizes.min_content);
+                    (inline_size, solve_inline_margins(inline_size))
+                },
+                None => (
+                    available_inline_size,
+                    (margin_inline_start, margin_inline_end),
+                ),
+            }
         };
     if let Some(max_inline_size) = max_box_size.inline {
         if inline_size > max_inline_size {
@@ -380,8 +470,13 @@ fn layout_in_flow_non_replaced_block_level(
             inline_margins = solve_inline_margins(inline_size);
         }
     }
-    if inline_size < min_box_size.inline {
-        inline_size = min_box_size.inline;
+    // A table is never narrower than its min-content inline size.
+    let min_inline_size = match table_content_sizes {
+        Some(content_sizes) => min_box_size.inline.max(content_sizes.min_content),
+        None => min_box_size.inline,
+    };
+    if inline_size < min_inline_size {
+        inline_size = min_inline_size;
         inline_margins = solve_inline_margins(inline_size);
     }
 
@@ -17,6 +17,8 @@ use crate::formatting_contexts::IndependentFormattingContext;
 use crate::fragments::Fragment;
 use crate::geom::flow_relative::Vec2;
 use crate::geom::{PhysicalPoint, PhysicalRect, PhysicalSize};
+use crate::incremental;
+use crate::lists;
 use crate::positioned::AbsolutelyPositionedBox;
 use crate::positioned::PositioningContext;
 use crate::replaced::ReplacedContent;
@@ -75,6 +77,10 @@ impl BoxTree {
     where
         Node: 'dom + Copy + LayoutNode<'dom> + Send + Sync,
     {
+        incremental::mark_reusable_boxes(root_element, context.incremental_layout);
+        // Counters depend on the whole preceding document, so they are resolved
+        // before the (parallel) construction of boxes that refer to them.
+        lists::resolve_counters(context, root_element);
         let (contains_floats, boxes) = construct_for_root_element(&context, root_element);
 
         // Zero box for `:root { display: none }`, one for the root element otherwise.
@@ -110,8 +116,13 @@ fn construct_for_root_element<'dom>(
         },
         // The root element is blockified, ignore DisplayOutside
         Display::GeneratingBox(DisplayGeneratingBox::OutsideInside { inside, .. }) => inside,
+        Display::GeneratingBox(DisplayGeneratingBox::LayoutInternal(_)) => {
+            // Unreachable because the root element is blockified
+            unreachable!()
+        },
     };
 
+    context.box_construction_statistics.note_box();
     let contents =
         ReplacedContent::for_element(root_element).map_or(Contents::OfElement, Contents::Replaced);
     let (contains_floats, root_box) = if box_style.position.is_absolutely_positioned() {
@@ -3,13 +3,17 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::context::LayoutContext;
-use crate::dom_traversal::{Contents, NodeExt};
+use crate::dom_traversal::{Contents, DeferredChild, NodeExt};
+use crate::flexbox::FlexContainer;
 use crate::flow::BlockFormattingContext;
 use crate::fragments::Fragment;
+use crate::grid::GridContainer;
+use crate::incremental::LayoutCache;
 use crate::positioned::PositioningContext;
 use crate::replaced::ReplacedContent;
-use crate::sizing::{BoxContentSizes, ContentSizesRequest};
-use crate::style_ext::DisplayInside;
+use crate::sizing::{BoxContentSizes, ContentSizes, ContentSizesRequest};
+use crate::style_ext::{ComputedValuesExt, DisplayInside};
+use crate::table::Table;
 use crate::ContainingBlock;
 use servo_arc::Arc;
 use std::convert::TryInto;
@@ -17,6 +21,7 @@ use style::dom::OpaqueNode;
 use style::properties::ComputedValues;
 use style::values::computed::Length;
 use style::values::specified::text::TextDecorationLine;
+use style::Zero;
 
 /// https://drafts.csswg.org/css-display/#independent-formatting-context
 #[derive(Debug, Serialize)]
@@ -29,6 +34,9 @@ pub(crate) struct IndependentFormattingContext {
     pub content_sizes: BoxContentSizes,
 
     contents: IndependentFormattingContextContents,
+
+    #[serde(skip_serializing)]
+    layout_cache: LayoutCache,
 }
 
 pub(crate) struct IndependentLayout {
@@ -43,16 +51,22 @@ pub(crate) struct IndependentLayout {
 #[derive(Debug, Serialize)]
 enum IndependentFormattingContextContents {
     Flow(BlockFormattingContext),
+    Flex(FlexContainer),
+    Grid(GridContainer),
+    Table(Table),
 
     // Not called FC in specs, but behaves close enough
     Replaced(ReplacedContent),
     // Other layout modes go here
 }
 
-pub(crate) struct NonReplacedIFC<'a>(NonReplacedIFCKind<'a>);
+pub(crate) struct NonReplacedIFC<'a>(NonReplacedIFCKind<'a>, &'a LayoutCache);
 
 enum NonReplacedIFCKind<'a> {
     Flow(&'a BlockFormattingContext),
+    Flex(&'a FlexContainer),
+    Grid(&'a GridContainer),
+    Table(&'a Table),
 }
 
 impl IndependentFormattingContext {
@@ -62,10 +76,19 @@ impl IndependentFormattingContext {
         style: Arc<ComputedValues>,
         display_inside: DisplayInside,
         contents: Contents,
-        content_sizes: ContentSizesRequest,
+        mut content_sizes: ContentSizesRequest,
         propagated_text_decoration_line: TextDecorationLine,
     ) -> Self {
-        match contents.try_into() {
+        // With inline-size containment, the content sizes are not computed from the contents.
+        let contained_content_sizes = if style.inline_size_is_contained() {
+            Some(std::mem::replace(
+                &mut content_sizes,
+                ContentSizesRequest::None,
+            ))
+        } else {
+            None
+        };
+        let mut ifc = match contents.try_into() {
             Ok(non_replaced) => match display_inside {
                 DisplayInside::Flow | DisplayInside::FlowRoot => {
                     let (bfc, content_sizes) = BlockFormattingContext::construct(
@@ -81,8 +104,53 @@ impl IndependentFormattingContext {
                         style,
                         content_sizes,
                         contents: IndependentFormattingContextContents::Flow(bfc),
+                        layout_cache: Default::default(),
+                    }
+                },
+                DisplayInside::Flex => {
+                    let (fc, content_sizes) = FlexContainer::construct(
+                        context,
+                        node,
+                        &style,
+                        non_replaced,
+                        content_sizes,
+                        propagated_text_decoration_line,
+                    );
+                    Self {
+                        tag: node.as_opaque(),
+                        style,
+                        content_sizes,
+                        contents: IndependentFormattingContextContents::Flex(fc),
+                        layout_cache: Default::default(),
+                    }
+                },
+                DisplayInside::Grid => {
+                    let (gc, content_sizes) = GridContainer::construct(
+                        context,
+                        node,
+                        &style,
+                        non_replaced,
+                        content_sizes,
+                        propagated_text_decoration_line,
+                    );
+                    Self {
+                        tag: node.as_opaque(),
+                        style,
+                        content_sizes,
+                        contents: IndependentFormattingContextContents::Grid(gc),
+                        layout_cache: Default::default(),
                     }
                 },
+                DisplayInside::Table => {
+                    let table = Table::construct(
+                        context,
+                        node,
+                        &style,
+                        non_replaced,
+                        propagated_text_decoration_line,
+                    );
+                    Self::for_table(node, style, content_sizes, table)
+                },
             },
             Err(replaced) => {
                 let content_sizes = content_sizes.compute(|| replaced.inline_content_sizes(&style));
@@ -91,8 +159,82 @@ impl IndependentFormattingContext {
                     style,
                     content_sizes,
                     contents: IndependentFormattingContextContents::Replaced(replaced),
+                    layout_cache: Default::default(),
                 }
             },
+        };
+        if let Some(content_sizes) = contained_content_sizes {
+            ifc.content_sizes = content_sizes.compute(ContentSizes::zero);
+        }
+        ifc
+    }
+
+    /// Construct an anonymous block container box that wraps the given children,
+    /// such as contiguous text runs in a flex container.
+    pub fn construct_for_anonymous_box<'dom, Node>(
+        context: &LayoutContext,
+        node: Node,
+        style: Arc<ComputedValues>,
+        children: impl IntoIterator<Item = DeferredChild<'dom, Node>>,
+        content_sizes: ContentSizesRequest,
+        propagated_text_decoration_line: TextDecorationLine,
+    ) -> Self
+    where
+        Node: NodeExt<'dom>,
+    {
+        let (bfc, content_sizes) = BlockFormattingContext::construct_for_anonymous_box(
+            context,
+            node,
+            &style,
+            children,
+            content_sizes,
+            propagated_text_decoration_line,
+        );
+        Self {
+            tag: node.as_opaque(),
+            style,
+            content_sizes,
+            contents: IndependentFormattingContextContents::Flow(bfc),
+            layout_cache: Default::default(),
+        }
+    }
+
+    /// Construct an anonymous table box that wraps the given misparented table-internal
+    /// children: https://drafts.csswg.org/css-tables/#fixup-algorithm
+    pub fn construct_anonymous_table<'dom, Node>(
+        context: &LayoutContext,
+        node: Node,
+        style: Arc<ComputedValues>,
+        children: impl IntoIterator<Item = DeferredChild<'dom, Node>>,
+        content_sizes: ContentSizesRequest,
+        propagated_text_decoration_line: TextDecorationLine,
+    ) -> Self
+    where
+        Node: NodeExt<'dom>,
+    {
+        let table = Table::construct_anonymous(
+            context,
+            node,
+            &style,
+            children,
+            propagated_text_decoration_line,
+        );
+        Self::for_table(node, style, content_sizes, table)
+    }
+
+    fn for_table<'dom>(
+        node: impl NodeExt<'dom>,
+        style: Arc<ComputedValues>,
+        content_sizes: ContentSizesRequest,
+        table: Table,
+    ) -> Self {
+        let content_sizes = content_sizes.compute(|| table.content_sizes().clone());
+        Self {
+            tag: node.as_opaque(),
+            style,
+            content_sizes,
+            contents: IndependentFormattingContextContents::Table(table),
+            layout_cache: Default::default(),
         }
     }
 
@@ -102,12 +244,26 @@ impl IndependentFormattingContext {
         use self::NonReplacedIFCKind as Kind;
         match &self.contents {
             Contents::Replaced(r) => Ok(r),
-            Contents::Flow(f) => Err(NR(Kind::Flow(f))),
+            Contents::Flow(f) => Err(NR(Kind::Flow(f), &self.layout_cache)),
+            Contents::Flex(f) => Err(NR(Kind::Flex(f), &self.layout_cache)),
+            Contents::Grid(g) => Err(NR(Kind::Grid(g), &self.layout_cache)),
+            Contents::Table(t) => Err(NR(Kind::Table(t), &self.layout_cache)),
         }
     }
 }
 
 impl NonReplacedIFC<'_> {
+    /// Tables are never narrower than their min-content inline size,
+    /// and an `auto` inline size shrinks to fit them even when they are block-level.
+    pub fn table_content_sizes(&self) -> Option<&ContentSizes> {
+        match &self.0 {
+            NonReplacedIFCKind::Table(table) => Some(table.content_sizes()),
+            NonReplacedIFCKind::Flow(_) |
+            NonReplacedIFCKind::Flex(_) |
+            NonReplacedIFCKind::Grid(_) => None,
+        }
+    }
+
     pub fn layout(
         &self,
         layout_context: &LayoutContext,
@@ -115,13 +271,42 @@ impl NonReplacedIFC<'_> {
         containing_block: &ContainingBlock,
         tree_rank: usize,
     ) -> IndependentLayout {
-        match &self.0 {
+        let layout = |positioning_context: &mut PositioningContext| match &self.0 {
             NonReplacedIFCKind::Flow(bfc) => bfc.layout(
                 layout_context,
                 positioning_context,
                 containing_block,
                 tree_rank,
             ),
+            NonReplacedIFCKind::Flex(fc) => fc.layout(
+                layout_context,
+                positioning_context,
+                containing_block,
+                tree_rank,
+            ),
+            NonReplacedIFCKind::Grid(gc) => gc.layout(
+                layout_context,
+                positioning_context,
+                containing_block,
+                tree_rank,
+            ),
+            NonReplacedIFCKind::Table(table) => table.layout(
+                layout_context,
+                positioning_context,
+                containing_block,
+                tree_rank,
+            ),
+        };
+        let mut independent_layout = if layout_context.incremental_layout {
+            self.1.layout(positioning_context, containing_block, layout)
+        } else {
+            layout(positioning_context)
+        };
+        // With size containment, an `auto` block size is that of the box without contents,
+        // which overflow it instead. The containing block is the one of those contents.
+        if containing_block.style.block_size_is_contained() {
+            independent_layout.content_block_size = Length::zero();
         }
+        independent_layout
     }
 }
@@ -294,6 +294,17 @@ impl flow_relative::Sides<LengthOrAuto> {
     }
 }
 
+impl flow_relative::Sides<Length> {
+    pub fn zero() -> Self {
+        Self {
+            inline_start: Length::zero(),
+            inline_end: Length::zero(),
+            block_start: Length::zero(),
+            block_end: Length::zero(),
+        }
+    }
+}
+
 impl<T> Add<&'_ flow_relative::Sides<T>> for &'_ flow_relative::Sides<T>
 where
     T: Add<Output = T> + Copy,
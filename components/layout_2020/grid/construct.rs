@@ -0,0 +1,280 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::placement::GridPlacement;
+use super::track_sizing::{self, AvailableSpace, TrackSizingItem};
+use super::{GridContainer, GridLevelBox};
+use crate::cell::ArcRefCell;
+use crate::context::LayoutContext;
+use crate::dom_traversal::{
+    is_only_document_white_space, BoxSlot, Contents, DeferredChild, NodeExt, NonReplacedContents,
+    TraversalHandler,
+};
+use crate::element_data::LayoutBox;
+use crate::formatting_contexts::IndependentFormattingContext;
+use crate::geom::flow_relative::Vec2;
+use crate::positioned::AbsolutelyPositionedBox;
+use crate::sizing::{BoxContentSizes, ContentSizes, ContentSizesRequest};
+use crate::style_ext::DisplayGeneratingBox;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use servo_arc::Arc;
+use std::borrow::Cow;
+use style::properties::ComputedValues;
+use style::selector_parser::PseudoElement;
+use style::values::specified::text::TextDecorationLine;
+
+impl GridContainer {
+    pub fn construct<'dom>(
+        context: &LayoutContext,
+        node: impl NodeExt<'dom>,
+        style: &Arc<ComputedValues>,
+        contents: NonReplacedContents,
+        content_sizes: ContentSizesRequest,
+        propagated_text_decoration_line: TextDecorationLine,
+    ) -> (Self, BoxContentSizes) {
+        let text_decoration_line =
+            propagated_text_decoration_line | style.clone_text_decoration_line();
+        let mut builder = GridContainerBuilder {
+            context,
+            node,
+            style,
+            text_decoration_line,
+            contiguous_text_runs: Vec::new(),
+            jobs: Vec::new(),
+            has_text_runs: false,
+        };
+        contents.traverse(context, node, style, &mut builder);
+        let container = builder.finish();
+        let content_sizes = content_sizes.compute(|| container.inline_content_sizes(style));
+        (container, content_sizes)
+    }
+
+    /// https://drafts.csswg.org/css-grid/#intrinsic-sizes
+    fn inline_content_sizes(&self, style: &ComputedValues) -> ContentSizes {
+        let children: Vec<_> = self.children.iter().map(|child| child.borrow()).collect();
+        let items: Vec<_> = children
+            .iter()
+            .filter_map(|child| match &**child {
+                GridLevelBox::GridItem(item) => Some(item),
+                GridLevelBox::OutOfFlowAbsolutelyPositionedBox(_) => None,
+            })
+            .collect();
+
+        // Auto-repeated tracks are only repeated once when the available space is unknown.
+        let placement = GridPlacement::new(
+            style,
+            Vec2 {
+                inline: None,
+                block: None,
+            },
+            items.iter().map(|item| &*item.style),
+        );
+        let column_gap = track_sizing::resolve_gap(&style.get_position().column_gap, None);
+        let sizing_items: Vec<_> = items
+            .iter()
+            .zip(&placement.areas)
+            .map(|(item, area)| TrackSizingItem::for_columns(item, &area.columns))
+            .collect();
+        let columns_size = |available_space| {
+            let sizes = track_sizing::size_tracks(
+                &placement.columns,
+                column_gap,
+                available_space,
+                &sizing_items,
+            );
+            track_sizing::sum_with_gaps(&sizes, column_gap)
+        };
+        ContentSizes {
+            min_content: columns_size(AvailableSpace::MinContent),
+            max_content: columns_size(AvailableSpace::MaxContent),
+        }
+    }
+}
+
+/// https://drafts.csswg.org/css-grid/#grid-items
+struct GridContainerBuilder<'a, 'dom, Node> {
+    context: &'a LayoutContext<'a>,
+    node: Node,
+    style: &'a Arc<ComputedValues>,
+    text_decoration_line: TextDecorationLine,
+    contiguous_text_runs: Vec<TextRun<'dom, Node>>,
+    /// To be run in parallel with rayon in `finish`
+    jobs: Vec<GridLevelJob<'dom, Node>>,
+    has_text_runs: bool,
+}
+
+enum GridLevelJob<'dom, Node> {
+    /// Or pseudo-element
+    Element {
+        node: Node,
+        style: Arc<ComputedValues>,
+        display: DisplayGeneratingBox,
+        contents: Contents,
+        box_slot: BoxSlot<'dom>,
+    },
+    TextRuns(Vec<TextRun<'dom, Node>>),
+}
+
+struct TextRun<'dom, Node> {
+    node: Node,
+    text: Cow<'dom, str>,
+    parent_style: Arc<ComputedValues>,
+}
+
+impl<'a, 'dom, Node: 'dom> TraversalHandler<'dom, Node> for GridContainerBuilder<'a, 'dom, Node>
+where
+    Node: NodeExt<'dom>,
+{
+    fn handle_text(
+        &mut self,
+        node: Node,
+        text: Cow<'dom, str>,
+        parent_style: &Arc<ComputedValues>,
+    ) {
+        self.contiguous_text_runs.push(TextRun {
+            node,
+            text,
+            parent_style: parent_style.clone(),
+        })
+    }
+
+    /// Or pseudo-element
+    fn handle_element(
+        &mut self,
+        node: Node,
+        style: &Arc<ComputedValues>,
+        display: DisplayGeneratingBox,
+        contents: Contents,
+        box_slot: BoxSlot<'dom>,
+    ) {
+        self.wrap_any_text_in_anonymous_block_container();
+
+        self.jobs.push(GridLevelJob::Element {
+            node,
+            style: style.clone(),
+            display,
+            contents,
+            box_slot,
+        })
+    }
+}
+
+impl<'a, 'dom, Node: 'dom> GridContainerBuilder<'a, 'dom, Node>
+where
+    Node: NodeExt<'dom>,
+{
+    fn wrap_any_text_in_anonymous_block_container(&mut self) {
+        let runs = std::mem::take(&mut self.contiguous_text_runs);
+        // “if the entire sequence of child text runs contains only white space
+        //  (i.e. characters that can be affected by the white-space property)
+        //  it is instead not rendered”
+        if !runs
+            .iter()
+            .all(|run| is_only_document_white_space(&run.text))
+        {
+            self.jobs.push(GridLevelJob::TextRuns(runs));
+            self.has_text_runs = true;
+        }
+    }
+
+    fn finish(mut self) -> GridContainer {
+        self.wrap_any_text_in_anonymous_block_container();
+
+        let context = self.context;
+        let container_node = self.node;
+        let text_decoration_line = self.text_decoration_line;
+        let anonymous_style = if self.has_text_runs {
+            Some(
+                context
+                    .shared_context()
+                    .stylist
+                    .style_for_anonymous::<Node::ConcreteElement>(
+                        &context.shared_context().guards,
+                        &PseudoElement::ServoText,
+                        &self.style,
+                    ),
+            )
+        } else {
+            None
+        };
+
+        let finish_job = |job: GridLevelJob<'dom, Node>| match job {
+            GridLevelJob::TextRuns(runs) => ArcRefCell::new(GridLevelBox::GridItem(
+                IndependentFormattingContext::construct_for_anonymous_box(
+                    context,
+                    container_node,
+                    anonymous_style.clone().unwrap(),
+                    runs.into_iter().map(|run| DeferredChild::Text {
+                        node: run.node,
+                        text: run.text,
+                        parent_style: run.parent_style,
+                    }),
+                    // Grid layout uses the content sizes of every grid item.
+                    ContentSizesRequest::Inline,
+                    text_decoration_line,
+                ),
+            )),
+            GridLevelJob::Element {
+                node,
+                style,
+                display,
+                contents,
+                box_slot,
+            } => {
+                let display_inside = match display {
+                    DisplayGeneratingBox::OutsideInside { inside, .. } => inside,
+                    // The style crate blockifies grid items, see below.
+                    DisplayGeneratingBox::LayoutInternal(_) => unreachable!(),
+                };
+                let box_ = if style.get_box().position.is_absolutely_positioned() {
+                    // https://drafts.csswg.org/css-grid/#abspos-items
+                    ArcRefCell::new(GridLevelBox::OutOfFlowAbsolutelyPositionedBox(Arc::new(
+                        AbsolutelyPositionedBox::construct(
+                            context,
+                            node,
+                            style,
+                            display_inside,
+                            contents,
+                        ),
+                    )))
+                } else {
+                    // The style crate has already blockified the `display` of grid items:
+                    // https://drafts.csswg.org/css-grid/#grid-item-display
+                    ArcRefCell::new(GridLevelBox::GridItem(
+                        IndependentFormattingContext::construct(
+                            context,
+                            node,
+                            style,
+                            display_inside,
+                            contents,
+                            ContentSizesRequest::Inline,
+                            text_decoration_line,
+                        ),
+                    ))
+                };
+                box_slot.set(LayoutBox::GridLevel(box_.clone()));
+                box_
+            },
+        };
+
+        let jobs = std::mem::take(&mut self.jobs);
+        let mut children: Vec<_> = if context.use_rayon {
+            jobs.into_par_iter().map(finish_job).collect()
+        } else {
+            jobs.into_iter().map(finish_job).collect()
+        };
+
+        // https://drafts.csswg.org/css-grid/#order-property
+        // This is a stable sort, so items with the same `order` stay in document order.
+        children.sort_by_key(|child| match &*child.borrow() {
+            GridLevelBox::GridItem(item) => item.style.clone_order(),
+
+            // Absolutely-positioned children do not take part in grid placement,
+            // and are painted as if they had `order: 0`.
+            GridLevelBox::OutOfFlowAbsolutelyPositionedBox(_) => 0,
+        });
+
+        GridContainer { children }
+    }
+}
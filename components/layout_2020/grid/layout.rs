@@ -0,0 +1,656 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-grid/#layout-algorithm
+
+use super::placement::{GridArea, GridPlacement};
+use super::track_sizing::{self, AvailableSpace, TrackSizingItem};
+use super::{GridContainer, GridLevelBox};
+use crate::cell::ArcRefCell;
+use crate::context::LayoutContext;
+use crate::formatting_contexts::{IndependentFormattingContext, IndependentLayout};
+use crate::fragments::{
+    AbsoluteOrFixedPositionedFragment, BoxFragment, CollapsedBlockMargins, Fragment,
+};
+use crate::geom::flow_relative::{Rect, Sides, Vec2};
+use crate::geom::LengthOrAuto;
+use crate::positioned::{AbsolutelyPositionedBox, PositioningContext};
+use crate::sizing::content_block_size_limits;
+use crate::style_ext::ComputedValuesExt;
+use crate::ContainingBlock;
+use std::ops::Range;
+use style::computed_values::align_content::T as AlignContent;
+use style::computed_values::align_items::T as AlignItems;
+use style::computed_values::align_self::T as AlignSelf;
+use style::computed_values::justify_content::T as JustifyContent;
+use style::computed_values::justify_items::T as JustifyItems;
+use style::computed_values::justify_self::T as JustifySelf;
+use style::values::computed::{Length, TrackSize};
+use style::Zero;
+
+/// Layout parameters about a grid container,
+/// grouped to avoid passing around many parameters
+struct GridContext<'a> {
+    layout_context: &'a LayoutContext<'a>,
+    positioning_context: &'a mut PositioningContext,
+    containing_block: &'a ContainingBlock<'a>, // For items
+}
+
+/// A grid item with some intermediate results
+struct GridItem<'a> {
+    box_: &'a IndependentFormattingContext,
+    tree_rank: usize,
+    area: GridArea,
+    content_box_size: Vec2<LengthOrAuto>,
+    content_min_size: Vec2<Length>,
+    content_max_size: Vec2<Option<Length>>,
+    padding: Sides<Length>,
+    border: Sides<Length>,
+    margin: Sides<LengthOrAuto>,
+
+    /// Sum of padding, border, and margin (with `auto` assumed to be zero) in each axis.
+    /// This is the difference between an outer and inner size.
+    pbm_auto_is_zero: Vec2<Length>,
+
+    /// The used size of replaced content, as if it were an inline element.
+    replaced_used_size: Option<Vec2<Length>>,
+
+    justify_self: SelfAlignment,
+    align_self: SelfAlignment,
+
+    /// The most recent layout of this item, reused when the same sizes are needed again.
+    cached_layout: Option<GridItemLayoutResult>,
+}
+
+/// The result of laying out the contents of a grid item
+struct GridItemLayoutResult {
+    inline_size: Length,
+
+    /// The block size that was given for the content box, `None` for `auto`
+    given_block_size: Option<Length>,
+
+    /// The given block size, or the content block size for `auto`
+    block_size: Length,
+
+    fragments: Vec<Fragment>,
+    positioning_context: PositioningContext,
+}
+
+/// https://drafts.csswg.org/css-align/#self-alignment
+#[derive(Clone, Copy, PartialEq)]
+enum SelfAlignment {
+    Stretch,
+    Start,
+    End,
+    Center,
+}
+
+/// https://drafts.csswg.org/css-align/#content-distribution
+#[derive(Clone, Copy)]
+enum ContentDistribution {
+    Start,
+    End,
+    Center,
+    SpaceBetween,
+    SpaceAround,
+}
+
+/// The tracks of one axis of the grid, after track sizing and content distribution
+struct SizedTracks {
+    positions: Vec<Length>,
+    sizes: Vec<Length>,
+}
+
+impl GridContainer {
+    pub(crate) fn layout(
+        &self,
+        layout_context: &LayoutContext,
+        positioning_context: &mut PositioningContext,
+        containing_block: &ContainingBlock,
+        tree_rank: usize,
+    ) -> IndependentLayout {
+        let mut content_block_size = Length::zero();
+        let fragments =
+            positioning_context.adjust_static_positions(tree_rank, |positioning_context| {
+                let (fragments, block_size) = layout(
+                    layout_context,
+                    positioning_context,
+                    containing_block,
+                    &self.children,
+                );
+                content_block_size = block_size;
+                fragments
+            });
+        IndependentLayout {
+            fragments,
+            content_block_size,
+        }
+    }
+}
+
+/// Return one fragment per child (indexed by tree rank, for `adjust_static_positions`),
+/// and the content block size of the grid container.
+fn layout(
+    layout_context: &LayoutContext,
+    positioning_context: &mut PositioningContext,
+    containing_block: &ContainingBlock,
+    children: &[ArcRefCell<GridLevelBox>],
+) -> (Vec<Fragment>, Length) {
+    let children: Vec<_> = children.iter().map(|child| child.borrow()).collect();
+    let mut fragments_by_tree_rank = Vec::with_capacity(children.len());
+    let mut grid_item_boxes = Vec::new();
+    for (tree_rank, child) in children.iter().enumerate() {
+        match &**child {
+            GridLevelBox::GridItem(box_) => grid_item_boxes.push((tree_rank, box_)),
+            GridLevelBox::OutOfFlowAbsolutelyPositionedBox(box_) => {
+                // FIXME: the static position and containing block should be determined
+                // by the grid area: https://drafts.csswg.org/css-grid/#abspos-items
+                let hoisted_box =
+                    AbsolutelyPositionedBox::to_hoisted(box_.clone(), Vec2::zero(), tree_rank);
+                let hoisted_fragment = hoisted_box.fragment.clone();
+                positioning_context.push(hoisted_box);
+                fragments_by_tree_rank.push((
+                    tree_rank,
+                    Fragment::AbsoluteOrFixedPositioned(AbsoluteOrFixedPositionedFragment {
+                        hoisted_fragment,
+                        position: box_.contents.style.clone_position(),
+                    }),
+                ));
+            },
+        }
+    }
+
+    let container_style = containing_block.style;
+    let position_style = container_style.get_position();
+    let definite_block_size = containing_block.block_size.non_auto();
+    let placement = GridPlacement::new(
+        container_style,
+        Vec2 {
+            inline: Some(containing_block.inline_size),
+            block: definite_block_size,
+        },
+        grid_item_boxes.iter().map(|(_, box_)| &*box_.style),
+    );
+    let mut grid_context = GridContext {
+        layout_context,
+        positioning_context,
+        containing_block,
+    };
+    let mut items: Vec<_> = grid_item_boxes
+        .into_iter()
+        .zip(placement.areas)
+        .map(|((tree_rank, box_), area)| GridItem::new(&grid_context, box_, tree_rank, area))
+        .collect();
+
+    // Size the columns first, with the min-content and max-content contributions of items.
+    let column_gap = track_sizing::resolve_gap(
+        &position_style.column_gap,
+        Some(containing_block.inline_size),
+    );
+    let column_items: Vec<_> = items
+        .iter()
+        .map(|item| TrackSizingItem::for_columns(item.box_, &item.area.columns))
+        .collect();
+    let column_sizes = track_sizing::size_tracks(
+        &placement.columns,
+        column_gap,
+        AvailableSpace::Definite(containing_block.inline_size),
+        &column_items,
+    );
+    // FIXME: `justify-content: normal` is not supported, and it behaves as `stretch`
+    // in grid containers, so `auto` columns only stretch with `justify-content: stretch`.
+    let columns = SizedTracks::new(
+        &placement.columns,
+        column_sizes,
+        column_gap,
+        containing_block.inline_size,
+        position_style.justify_content == JustifyContent::Stretch,
+        match position_style.justify_content {
+            JustifyContent::FlexStart | JustifyContent::Stretch => ContentDistribution::Start,
+            JustifyContent::FlexEnd => ContentDistribution::End,
+            JustifyContent::Center => ContentDistribution::Center,
+            JustifyContent::SpaceBetween => ContentDistribution::SpaceBetween,
+            JustifyContent::SpaceAround => ContentDistribution::SpaceAround,
+        },
+    );
+
+    // Then size the rows, with the block size of items laid out in their column span.
+    let row_gap = track_sizing::resolve_gap(&position_style.row_gap, definite_block_size);
+    let row_items: Vec<_> = items
+        .iter_mut()
+        .map(|item| {
+            let inline_size = item.inline_size(columns.span_size(&item.area.columns));
+            let outer_block_size =
+                item.block_size(inline_size, None, &mut grid_context) + item.pbm_auto_is_zero.block;
+            TrackSizingItem {
+                span: item.area.rows.clone(),
+                min_content: outer_block_size,
+                max_content: outer_block_size,
+            }
+        })
+        .collect();
+    let row_sizes = track_sizing::size_tracks(
+        &placement.rows,
+        row_gap,
+        // An `auto` block size of the grid container is its max-content size.
+        definite_block_size.map_or(AvailableSpace::MaxContent, AvailableSpace::Definite),
+        &row_items,
+    );
+    let content_block_size = definite_block_size.unwrap_or_else(|| {
+        let (min, max) = content_block_size_limits(containing_block);
+        track_sizing::sum_with_gaps(&row_sizes, row_gap).clamp_between_extremums(min, max)
+    });
+    let rows = SizedTracks::new(
+        &placement.rows,
+        row_sizes,
+        row_gap,
+        content_block_size,
+        position_style.align_content == AlignContent::Stretch,
+        match position_style.align_content {
+            AlignContent::FlexStart | AlignContent::Stretch => ContentDistribution::Start,
+            AlignContent::FlexEnd => ContentDistribution::End,
+            AlignContent::Center => ContentDistribution::Center,
+            AlignContent::SpaceBetween => ContentDistribution::SpaceBetween,
+            AlignContent::SpaceAround => ContentDistribution::SpaceAround,
+        },
+    );
+
+    for item in &mut items {
+        let area = Rect {
+            start_corner: Vec2 {
+                inline: columns.positions[item.area.columns.start],
+                block: rows.positions[item.area.rows.start],
+            },
+            size: Vec2 {
+                inline: columns.span_size(&item.area.columns),
+                block: rows.span_size(&item.area.rows),
+            },
+        };
+        let fragment = item.finish(area, &mut grid_context);
+        fragments_by_tree_rank.push((item.tree_rank, fragment));
+    }
+
+    fragments_by_tree_rank.sort_by_key(|&(tree_rank, _)| tree_rank);
+    let fragments = fragments_by_tree_rank
+        .into_iter()
+        .map(|(_, fragment)| fragment)
+        .collect();
+    (fragments, content_block_size)
+}
+
+impl SizedTracks {
+    /// “Stretch auto Tracks” if requested, then distribute the remaining free space
+    /// of the grid container per `justify-content` or `align-content`.
+    fn new(
+        tracks: &[TrackSize],
+        mut sizes: Vec<Length>,
+        gap: Length,
+        container_size: Length,
+        stretch_auto_tracks: bool,
+        distribution: ContentDistribution,
+    ) -> Self {
+        let mut free_space = container_size - track_sizing::sum_with_gaps(&sizes, gap);
+        if stretch_auto_tracks {
+            track_sizing::stretch_auto_tracks(tracks, &mut sizes, free_space);
+            free_space = container_size - track_sizing::sum_with_gaps(&sizes, gap);
+        }
+
+        let track_count = sizes.len();
+        let (mut position, extra_gap) = match distribution {
+            ContentDistribution::Start => (Length::zero(), Length::zero()),
+            ContentDistribution::End => (free_space, Length::zero()),
+            ContentDistribution::Center => (free_space / 2., Length::zero()),
+            ContentDistribution::SpaceBetween => {
+                if free_space > Length::zero() && track_count > 1 {
+                    (Length::zero(), free_space / (track_count - 1) as f32)
+                } else {
+                    (Length::zero(), Length::zero())
+                }
+            },
+            ContentDistribution::SpaceAround => {
+                if free_space > Length::zero() {
+                    let extra_gap = free_space / track_count as f32;
+                    (extra_gap / 2., extra_gap)
+                } else {
+                    (free_space / 2., Length::zero())
+                }
+            },
+        };
+        let positions = sizes
+            .iter()
+            .map(|&size| {
+                let start = position;
+                position += size + gap + extra_gap;
+                start
+            })
+            .collect();
+        SizedTracks { positions, sizes }
+    }
+
+    /// The size of the given tracks, including the gaps between them
+    fn span_size(&self, span: &Range<usize>) -> Length {
+        let last = span.end - 1;
+        self.positions[last] + self.sizes[last] - self.positions[span.start]
+    }
+}
+
+impl<'a> GridItem<'a> {
+    fn new(
+        grid_context: &GridContext,
+        box_: &'a IndependentFormattingContext,
+        tree_rank: usize,
+        area: GridArea,
+    ) -> Self {
+        let containing_block = grid_context.containing_block;
+        let style = &*box_.style;
+
+        // https://drafts.csswg.org/css-writing-modes/#orthogonal-flows
+        assert_eq!(
+            containing_block.style.writing_mode, style.writing_mode,
+            "Mixed writing modes are not supported yet"
+        );
+
+        // FIXME: percentages should resolve against the grid area, not the grid container.
+        let pbm = style.padding_border_margin(containing_block);
+        let content_box_size = style.content_box_size(containing_block, &pbm);
+        // FIXME: `auto` minimum sizes should be content-based:
+        // https://drafts.csswg.org/css-grid/#min-size-auto
+        let content_min_size = style
+            .content_min_box_size(containing_block, &pbm)
+            .auto_is(Length::zero);
+        let content_max_size = style.content_max_box_size(containing_block, &pbm);
+        let margin_auto_is_zero = pbm.margin.auto_is(Length::zero);
+        let pbm_auto_is_zero = Vec2 {
+            inline: pbm.padding_border_sums.inline + margin_auto_is_zero.inline_sum(),
+            block: pbm.padding_border_sums.block + margin_auto_is_zero.block_sum(),
+        };
+        let replaced_used_size = box_
+            .as_replaced()
+            .ok()
+            .map(|replaced| replaced.used_size_as_if_inline_element(containing_block, style, &pbm));
+
+        let is_replaced = replaced_used_size.is_some();
+        let container_position_style = containing_block.style.get_position();
+        let justify_self = match style.clone_justify_self() {
+            JustifySelf::Auto => match container_position_style.justify_items {
+                JustifyItems::Normal => JustifySelf::Normal,
+                JustifyItems::Stretch => JustifySelf::Stretch,
+                JustifyItems::Start => JustifySelf::Start,
+                JustifyItems::End => JustifySelf::End,
+                JustifyItems::Center => JustifySelf::Center,
+            },
+            justify_self => justify_self,
+        };
+        let justify_self = match justify_self {
+            // “For replaced elements, `normal` behaves as `start`”
+            JustifySelf::Normal if is_replaced => SelfAlignment::Start,
+            JustifySelf::Auto | JustifySelf::Normal | JustifySelf::Stretch => {
+                SelfAlignment::Stretch
+            },
+            JustifySelf::Start => SelfAlignment::Start,
+            JustifySelf::End => SelfAlignment::End,
+            JustifySelf::Center => SelfAlignment::Center,
+        };
+        let align_self = match style.clone_align_self() {
+            AlignSelf::Auto => match container_position_style.align_items {
+                AlignItems::Stretch => AlignSelf::Stretch,
+                AlignItems::FlexStart => AlignSelf::FlexStart,
+                AlignItems::FlexEnd => AlignSelf::FlexEnd,
+                AlignItems::Center => AlignSelf::Center,
+                AlignItems::Baseline => AlignSelf::Baseline,
+            },
+            align_self => align_self,
+        };
+        let align_self = match align_self {
+            // `stretch` is the initial value of `align-items` in Servo, where other engines
+            // have `normal`, which behaves as `start` for replaced elements.
+            AlignSelf::Stretch if is_replaced => SelfAlignment::Start,
+            AlignSelf::Auto | AlignSelf::Stretch => SelfAlignment::Stretch,
+            // FIXME: baseline alignment
+            AlignSelf::FlexStart | AlignSelf::Baseline => SelfAlignment::Start,
+            AlignSelf::FlexEnd => SelfAlignment::End,
+            AlignSelf::Center => SelfAlignment::Center,
+        };
+
+        GridItem {
+            box_,
+            tree_rank,
+            area,
+            content_box_size,
+            content_min_size,
+            content_max_size,
+            padding: pbm.padding,
+            border: pbm.border,
+            margin: pbm.margin,
+            pbm_auto_is_zero,
+            replaced_used_size,
+            justify_self,
+            align_self,
+            cached_layout: None,
+        }
+    }
+
+    /// https://drafts.csswg.org/css-align/#valdef-justify-self-stretch
+    fn stretches_inline(&self) -> bool {
+        self.justify_self == SelfAlignment::Stretch &&
+            self.content_box_size.inline.is_auto() &&
+            !self.margin.inline_start.is_auto() &&
+            !self.margin.inline_end.is_auto()
+    }
+
+    fn stretches_block(&self) -> bool {
+        self.align_self == SelfAlignment::Stretch &&
+            self.content_box_size.block.is_auto() &&
+            !self.margin.block_start.is_auto() &&
+            !self.margin.block_end.is_auto()
+    }
+
+    /// The inline size of the content box, in a grid area of the given inline size
+    fn inline_size(&self, area_inline_size: Length) -> Length {
+        let available = area_inline_size - self.pbm_auto_is_zero.inline;
+        let inline_size = match self.content_box_size.inline {
+            LengthOrAuto::LengthPercentage(inline_size) => inline_size,
+            LengthOrAuto::Auto if self.stretches_inline() => available,
+            LengthOrAuto::Auto => match &self.replaced_used_size {
+                Some(size) => size.inline,
+                None => self.box_.content_sizes.shrink_to_fit(available),
+            },
+        };
+        inline_size
+            .clamp_between_extremums(self.content_min_size.inline, self.content_max_size.inline)
+    }
+
+    /// The block size of the content box, in a grid area of the given block size if known
+    fn block_size(
+        &mut self,
+        inline_size: Length,
+        area_block_size: Option<Length>,
+        grid_context: &mut GridContext,
+    ) -> Length {
+        let block_size = match (self.content_box_size.block, area_block_size) {
+            (LengthOrAuto::LengthPercentage(block_size), _) => block_size,
+            (LengthOrAuto::Auto, Some(area_block_size)) if self.stretches_block() => {
+                area_block_size - self.pbm_auto_is_zero.block
+            },
+            (LengthOrAuto::Auto, _) => self.layout(inline_size, None, grid_context),
+        };
+        block_size.clamp_between_extremums(self.content_min_size.block, self.content_max_size.block)
+    }
+
+    /// Lay out this item in its grid area, resolving `auto` margins and self-alignment,
+    /// and return its fragment.
+    fn finish(&mut self, area: Rect<Length>, grid_context: &mut GridContext) -> Fragment {
+        let inline_size = self.inline_size(area.size.inline);
+        let block_size = self.block_size(inline_size, Some(area.size.block), grid_context);
+        self.layout(inline_size, Some(block_size), grid_context);
+        let GridItemLayoutResult {
+            fragments: children,
+            positioning_context,
+            ..
+        } = self.cached_layout.take().unwrap();
+
+        let (inline_start, inline_end, inline_offset) = align_in_axis(
+            area.size.inline - inline_size - self.pbm_auto_is_zero.inline,
+            &self.margin.inline_start,
+            &self.margin.inline_end,
+            self.justify_self,
+        );
+        let (block_start, block_end, block_offset) = align_in_axis(
+            area.size.block - block_size - self.pbm_auto_is_zero.block,
+            &self.margin.block_start,
+            &self.margin.block_end,
+            self.align_self,
+        );
+        let margin = Sides {
+            inline_start,
+            inline_end,
+            block_start,
+            block_end,
+        };
+        let content_rect = Rect {
+            start_corner: Vec2 {
+                inline: area.start_corner.inline +
+                    inline_offset +
+                    margin.inline_start +
+                    self.padding.inline_start +
+                    self.border.inline_start,
+                block: area.start_corner.block +
+                    block_offset +
+                    margin.block_start +
+                    self.padding.block_start +
+                    self.border.block_start,
+            },
+            size: Vec2 {
+                inline: inline_size,
+                block: block_size,
+            },
+        };
+        let mut fragment = BoxFragment::new(
+            self.box_.tag,
+            self.box_.style.clone(),
+            children,
+            content_rect,
+            self.padding.clone(),
+            self.border.clone(),
+            margin,
+            CollapsedBlockMargins::zero(),
+        );
+        grid_context.positioning_context.append_laid_out_box(
+            grid_context.layout_context,
+            grid_context.containing_block,
+            positioning_context,
+            &mut fragment,
+        );
+        Fragment::Box(fragment)
+    }
+
+    /// Lay out the contents of this item with the given sizes for its content box,
+    /// unless the most recent layout can be reused. `None` stands for an `auto` block size.
+    ///
+    /// Return the block size of the content box.
+    fn layout(
+        &mut self,
+        inline_size: Length,
+        block_size: Option<Length>,
+        grid_context: &mut GridContext,
+    ) -> Length {
+        if let Some(cached) = &self.cached_layout {
+            // An `auto` size that was resolved to the same length can be reused as-is.
+            if cached.inline_size == inline_size &&
+                (cached.given_block_size == block_size ||
+                    (cached.given_block_size.is_none() &&
+                        block_size == Some(cached.block_size)))
+            {
+                return cached.block_size;
+            }
+        }
+
+        let mut positioning_context = grid_context
+            .positioning_context
+            .new_for_subtree(&self.box_.style);
+        let (fragments, used_block_size) = match self.box_.as_replaced() {
+            Ok(replaced) => {
+                let used_block_size = block_size.unwrap_or_else(|| {
+                    let natural_size = self.replaced_used_size.as_ref().unwrap();
+                    if natural_size.inline > Length::zero() {
+                        // Preserve the aspect ratio
+                        natural_size.block * (inline_size.px() / natural_size.inline.px())
+                    } else {
+                        natural_size.block
+                    }
+                });
+                let size = Vec2 {
+                    inline: inline_size,
+                    block: used_block_size,
+                };
+                (
+                    replaced.make_fragments(&self.box_.style, size),
+                    used_block_size,
+                )
+            },
+            Err(non_replaced) => {
+                let containing_block_for_children = ContainingBlock {
+                    inline_size,
+                    block_size: block_size
+                        .map_or(LengthOrAuto::Auto, LengthOrAuto::LengthPercentage),
+                    style: &self.box_.style,
+                };
+                let layout = non_replaced.layout(
+                    grid_context.layout_context,
+                    &mut positioning_context,
+                    &containing_block_for_children,
+                    self.tree_rank,
+                );
+                (
+                    layout.fragments,
+                    block_size.unwrap_or(layout.content_block_size),
+                )
+            },
+        };
+        self.cached_layout = Some(GridItemLayoutResult {
+            inline_size,
+            given_block_size: block_size,
+            block_size: used_block_size,
+            fragments,
+            positioning_context,
+        });
+        used_block_size
+    }
+}
+
+/// Resolve `auto` margins, or else self-alignment, in one axis of a grid area,
+/// given the free space around the margin box with `auto` margins assumed to be zero.
+///
+/// Return the used start and end margins,
+/// and the offset of the margin box from the start of the grid area.
+/// https://drafts.csswg.org/css-grid/#auto-margins
+fn align_in_axis(
+    free_space: Length,
+    margin_start: &LengthOrAuto,
+    margin_end: &LengthOrAuto,
+    alignment: SelfAlignment,
+) -> (Length, Length, Length) {
+    let auto_margin_space = free_space.max(Length::zero());
+    match (margin_start, margin_end) {
+        (LengthOrAuto::Auto, LengthOrAuto::Auto) => (
+            auto_margin_space / 2.,
+            auto_margin_space / 2.,
+            Length::zero(),
+        ),
+        (LengthOrAuto::Auto, LengthOrAuto::LengthPercentage(end)) => {
+            (auto_margin_space, *end, Length::zero())
+        },
+        (LengthOrAuto::LengthPercentage(start), LengthOrAuto::Auto) => {
+            (*start, auto_margin_space, Length::zero())
+        },
+        (LengthOrAuto::LengthPercentage(start), LengthOrAuto::LengthPercentage(end)) => {
+            let offset = match alignment {
+                SelfAlignment::Stretch | SelfAlignment::Start => Length::zero(),
+                SelfAlignment::End => free_space,
+                SelfAlignment::Center => free_space / 2.,
+            };
+            (*start, *end, offset)
+        },
+    }
+}
@@ -0,0 +1,28 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Grid layout: https://drafts.csswg.org/css-grid/
+
+use crate::cell::ArcRefCell;
+use crate::formatting_contexts::IndependentFormattingContext;
+use crate::positioned::AbsolutelyPositionedBox;
+use servo_arc::Arc;
+
+mod construct;
+mod layout;
+mod placement;
+mod track_sizing;
+
+#[derive(Debug, Serialize)]
+pub(crate) struct GridContainer {
+    /// The children of the grid container,
+    /// in https://drafts.csswg.org/css-grid/#order-modified-document-order
+    children: Vec<ArcRefCell<GridLevelBox>>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) enum GridLevelBox {
+    GridItem(IndependentFormattingContext),
+    OutOfFlowAbsolutelyPositionedBox(Arc<AbsolutelyPositionedBox>),
+}
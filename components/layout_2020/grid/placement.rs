@@ -0,0 +1,714 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-grid/#grid-definition
+//! https://drafts.csswg.org/css-grid/#placement
+
+use super::track_sizing::resolve_gap;
+use crate::geom::flow_relative::Vec2;
+use std::collections::HashMap;
+use std::ops::Range;
+use style::properties::ComputedValues;
+use style::values::computed::{GridAutoFlow, GridLine, GridTemplateAreas};
+use style::values::computed::{GridTemplateComponent, ImplicitGridTracks, Length, TrackList};
+use style::values::computed::{TrackBreadth, TrackSize};
+use style::values::generics::grid::{RepeatCount, TrackListValue};
+use style::values::CustomIdent;
+use style::{Atom, Zero};
+
+/// The implementation limit on grid line numbers, spans and repetitions:
+/// https://drafts.csswg.org/css-grid/#overlarge-grids
+const MAX_GRID_LINE: i32 = 10000;
+
+/// The tracks of a grid container in both axes, and the grid area of each of its items.
+pub(super) struct GridPlacement {
+    /// The sizing function of each column, including implicit columns
+    pub columns: Vec<TrackSize>,
+
+    /// The sizing function of each row, including implicit rows
+    pub rows: Vec<TrackSize>,
+
+    /// One per grid item, in the order that items were given to `GridPlacement::new`
+    pub areas: Vec<GridArea>,
+}
+
+/// The tracks occupied by a grid item, as indices into `GridPlacement::{columns, rows}`
+#[derive(Clone)]
+pub(super) struct GridArea {
+    pub columns: Range<usize>,
+    pub rows: Range<usize>,
+}
+
+/// One axis of the explicit grid: https://drafts.csswg.org/css-grid/#explicit-grids
+struct ExplicitGridAxis {
+    tracks: Vec<TrackSize>,
+
+    /// The names of each of the `tracks.len() + 1` grid lines
+    line_names: Vec<Vec<Atom>>,
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+    Start,
+    End,
+}
+
+/// A single grid-placement property, resolved against the explicit grid.
+/// Line numbers are zero-based from the start edge of the explicit grid,
+/// and are negative for implicit lines before it.
+enum LinePlacement {
+    Line(i32),
+    Span(i32, Option<Atom>),
+    Auto,
+}
+
+/// The placement of a grid item in one axis, before auto-placement
+enum AxisPlacement {
+    /// Start and end lines, with the same numbering as `LinePlacement::Line`
+    Definite(i32, i32),
+    /// Automatic position with a number of tracks to span
+    Auto(usize),
+}
+
+impl GridPlacement {
+    pub fn new<'a>(
+        container_style: &ComputedValues,
+        definite_inner_size: Vec2<Option<Length>>,
+        items: impl Iterator<Item = &'a ComputedValues>,
+    ) -> Self {
+        let position = container_style.get_position();
+
+        // FIXME: the number of auto repetitions should also take a definite max size
+        // of the grid container into account.
+        let mut explicit_columns = ExplicitGridAxis::new(
+            &position.grid_template_columns,
+            definite_inner_size.inline,
+            resolve_gap(&position.column_gap, definite_inner_size.inline),
+        );
+        let mut explicit_rows = ExplicitGridAxis::new(
+            &position.grid_template_rows,
+            definite_inner_size.block,
+            resolve_gap(&position.row_gap, definite_inner_size.block),
+        );
+        if let GridTemplateAreas::Areas(areas) = &position.grid_template_areas {
+            let areas = &*areas.0;
+            explicit_rows.add_areas(
+                areas.strings.len(),
+                &position.grid_auto_rows,
+                areas
+                    .areas
+                    .iter()
+                    .map(|area| (&area.name, area.rows.start, area.rows.end)),
+            );
+            explicit_columns.add_areas(
+                areas.width as usize,
+                &position.grid_auto_columns,
+                areas
+                    .areas
+                    .iter()
+                    .map(|area| (&area.name, area.columns.start, area.columns.end)),
+            );
+        }
+
+        let items: Vec<_> = items
+            .map(|style| {
+                let position = style.get_position();
+                Vec2 {
+                    inline: explicit_columns
+                        .resolve_placement(&position.grid_column_start, &position.grid_column_end),
+                    block: explicit_rows
+                        .resolve_placement(&position.grid_row_start, &position.grid_row_end),
+                }
+            })
+            .collect();
+
+        // The auto-placement algorithm is written for `grid-auto-flow: row`,
+        // where items fill each row (the minor axis) before moving to the next one.
+        // It is used with axes swapped for `grid-auto-flow: column`.
+        let auto_flow = position.grid_auto_flow;
+        let column_flow = auto_flow.contains(GridAutoFlow::COLUMN);
+        let (major_tracks, minor_tracks) = if column_flow {
+            (explicit_columns.tracks.len(), explicit_rows.tracks.len())
+        } else {
+            (explicit_rows.tracks.len(), explicit_columns.tracks.len())
+        };
+        let items: Vec<_> = items
+            .into_iter()
+            .map(|Vec2 { inline, block }| {
+                if column_flow {
+                    (inline, block)
+                } else {
+                    (block, inline)
+                }
+            })
+            .collect();
+        let (major_areas, minor_areas, major_offset, minor_offset) = auto_place(
+            &items,
+            minor_tracks,
+            auto_flow.contains(GridAutoFlow::DENSE),
+        );
+
+        let track_count = |areas: &[Range<usize>], offset: usize, explicit_tracks: usize| {
+            areas
+                .iter()
+                .map(|area| area.end)
+                .fold(offset + explicit_tracks, usize::max)
+        };
+        let major_count = track_count(&major_areas, major_offset, major_tracks);
+        let minor_count = track_count(&minor_areas, minor_offset, minor_tracks);
+        let (rows, columns, areas) = if column_flow {
+            (
+                explicit_rows.with_implicit_tracks(
+                    &position.grid_auto_rows,
+                    minor_offset,
+                    minor_count,
+                ),
+                explicit_columns.with_implicit_tracks(
+                    &position.grid_auto_columns,
+                    major_offset,
+                    major_count,
+                ),
+                major_areas
+                    .into_iter()
+                    .zip(minor_areas)
+                    .map(|(columns, rows)| GridArea { columns, rows })
+                    .collect(),
+            )
+        } else {
+            (
+                explicit_rows.with_implicit_tracks(
+                    &position.grid_auto_rows,
+                    major_offset,
+                    major_count,
+                ),
+                explicit_columns.with_implicit_tracks(
+                    &position.grid_auto_columns,
+                    minor_offset,
+                    minor_count,
+                ),
+                major_areas
+                    .into_iter()
+                    .zip(minor_areas)
+                    .map(|(rows, columns)| GridArea { columns, rows })
+                    .collect(),
+            )
+        };
+        GridPlacement {
+            columns,
+            rows,
+            areas,
+        }
+    }
+}
+
+/// https://drafts.csswg.org/css-grid/#auto-placement-algo
+///
+/// Takes the `(major, minor)` placement of each item and returns their areas
+/// in each axis, and the number of implicit tracks before the explicit grid in each axis.
+fn auto_place(
+    items: &[(AxisPlacement, AxisPlacement)],
+    explicit_minor_tracks: usize,
+    dense: bool,
+) -> (Vec<Range<usize>>, Vec<Range<usize>>, usize, usize) {
+    // Shift line numbers so that implicit lines before the explicit grid are not negative.
+    let offset = |placement: &AxisPlacement| match placement {
+        AxisPlacement::Definite(start, _) => (-start).max(0) as usize,
+        AxisPlacement::Auto(_) => 0,
+    };
+    let major_offset = items
+        .iter()
+        .map(|(major, _)| offset(major))
+        .max()
+        .unwrap_or(0);
+    let minor_offset = items
+        .iter()
+        .map(|(_, minor)| offset(minor))
+        .max()
+        .unwrap_or(0);
+    let definite = |placement: &AxisPlacement, offset: usize| match placement {
+        AxisPlacement::Definite(start, end) => {
+            Some((*start + offset as i32) as usize..(*end + offset as i32) as usize)
+        },
+        AxisPlacement::Auto(_) => None,
+    };
+    let span = |placement: &AxisPlacement| match placement {
+        AxisPlacement::Definite(start, end) => (end - start) as usize,
+        AxisPlacement::Auto(span) => *span,
+    };
+
+    let mut grid = OccupancyGrid::default();
+    let mut areas: Vec<Option<(Range<usize>, Range<usize>)>> = vec![None; items.len()];
+
+    // “Position anything that’s not auto-positioned.”
+    for ((major, minor), area) in items.iter().zip(&mut areas) {
+        if let (Some(major), Some(minor)) =
+            (definite(major, major_offset), definite(minor, minor_offset))
+        {
+            grid.occupy(&major, &minor);
+            *area = Some((major, minor));
+        }
+    }
+
+    // “Process the items locked to a given row.”
+    let mut cursors_by_major_start = HashMap::new();
+    for ((major, minor), area) in items.iter().zip(&mut areas) {
+        if area.is_some() {
+            continue;
+        }
+        let major = match definite(major, major_offset) {
+            Some(major) => major,
+            None => continue,
+        };
+        let minor_span = span(minor);
+        let cursor = cursors_by_major_start.entry(major.start).or_insert(0);
+        let mut minor_start = if dense { 0 } else { *cursor };
+        while !grid.is_free(&major, &(minor_start..minor_start + minor_span)) {
+            minor_start += 1;
+        }
+        let minor = minor_start..minor_start + minor_span;
+        *cursor = minor.end;
+        grid.occupy(&major, &minor);
+        *area = Some((major, minor));
+    }
+
+    // “Determine the columns in the implicit grid.”
+    let minor_track_count = items
+        .iter()
+        .zip(&areas)
+        .map(|((_, minor), area)| match area {
+            Some((_, minor)) => minor.end,
+            None => definite(minor, minor_offset).map_or_else(|| span(minor), |minor| minor.end),
+        })
+        .fold(minor_offset + explicit_minor_tracks, usize::max);
+
+    // “Position the remaining grid items.”
+    let mut cursor_major = 0;
+    let mut cursor_minor = 0;
+    for ((major, minor), area) in items.iter().zip(&mut areas) {
+        if area.is_some() {
+            continue;
+        }
+        let major_span = span(major);
+        if dense {
+            cursor_major = 0;
+            cursor_minor = 0;
+        }
+        match definite(minor, minor_offset) {
+            Some(minor) => {
+                if !dense && minor.start < cursor_minor {
+                    cursor_major += 1;
+                }
+                cursor_minor = minor.start;
+                while !grid.is_free(&(cursor_major..cursor_major + major_span), &minor) {
+                    cursor_major += 1;
+                }
+            },
+            None => {
+                let minor_span = span(minor);
+                loop {
+                    if cursor_minor + minor_span > minor_track_count {
+                        cursor_major += 1;
+                        cursor_minor = 0;
+                        continue;
+                    }
+                    if grid.is_free(
+                        &(cursor_major..cursor_major + major_span),
+                        &(cursor_minor..cursor_minor + minor_span),
+                    ) {
+                        break;
+                    }
+                    cursor_minor += 1;
+                }
+            },
+        }
+        let major = cursor_major..cursor_major + major_span;
+        let minor = cursor_minor..cursor_minor + span(minor);
+        grid.occupy(&major, &minor);
+        *area = Some((major, minor));
+    }
+
+    let (major_areas, minor_areas): (Vec<_>, Vec<_>) =
+        areas.into_iter().map(Option::unwrap).unzip();
+    (major_areas, minor_areas, major_offset, minor_offset)
+}
+
+/// Which grid cells are occupied, indexed by major then minor track
+#[derive(Default)]
+struct OccupancyGrid {
+    cells: Vec<Vec<bool>>,
+}
+
+impl OccupancyGrid {
+    fn is_free(&self, major: &Range<usize>, minor: &Range<usize>) -> bool {
+        self.cells[major.start.min(self.cells.len())..major.end.min(self.cells.len())]
+            .iter()
+            .all(|row| minor.clone().all(|i| !row.get(i).cloned().unwrap_or(false)))
+    }
+
+    fn occupy(&mut self, major: &Range<usize>, minor: &Range<usize>) {
+        if self.cells.len() < major.end {
+            self.cells.resize_with(major.end, Vec::new);
+        }
+        for row in &mut self.cells[major.clone()] {
+            if row.len() < minor.end {
+                row.resize(minor.end, false);
+            }
+            for cell in &mut row[minor.clone()] {
+                *cell = true
+            }
+        }
+    }
+}
+
+impl ExplicitGridAxis {
+    fn new(
+        template: &GridTemplateComponent,
+        definite_available_size: Option<Length>,
+        gap: Length,
+    ) -> Self {
+        let mut axis = ExplicitGridAxis {
+            tracks: Vec::new(),
+            line_names: vec![Vec::new()],
+        };
+        let list = match template {
+            GridTemplateComponent::TrackList(list) => list,
+            // FIXME: subgrids are not supported.
+            GridTemplateComponent::None | GridTemplateComponent::Subgrid(_) => return axis,
+        };
+
+        axis.add_line_names(&list.line_names[0]);
+        for (index, value) in list.values.iter().enumerate() {
+            match value {
+                TrackListValue::TrackSize(size) => {
+                    if axis.tracks.len() < MAX_GRID_LINE as usize {
+                        axis.add_track(size)
+                    }
+                },
+                TrackListValue::TrackRepeat(repeat) => {
+                    let count = match repeat.count {
+                        RepeatCount::Number(count) => repeat_number(count),
+                        // FIXME: empty `auto-fit` tracks should collapse.
+                        RepeatCount::AutoFill | RepeatCount::AutoFit => {
+                            auto_repeat_count(list, definite_available_size, gap)
+                        },
+                    };
+                    for _ in 0..count {
+                        if axis.tracks.len() >= MAX_GRID_LINE as usize {
+                            break;
+                        }
+                        for (names, size) in repeat.line_names.iter().zip(&*repeat.track_sizes) {
+                            axis.add_line_names(names);
+                            axis.add_track(size);
+                        }
+                        if let Some(names) = repeat.line_names.get(repeat.track_sizes.len()) {
+                            axis.add_line_names(names);
+                        }
+                    }
+                },
+            }
+            axis.add_line_names(&list.line_names[index + 1]);
+        }
+        axis
+    }
+
+    /// Add a track and the grid line after it
+    fn add_track(&mut self, size: &TrackSize) {
+        self.tracks.push(size.clone());
+        self.line_names.push(Vec::new());
+    }
+
+    /// Add names to the last grid line
+    fn add_line_names(&mut self, names: &[CustomIdent]) {
+        let line = self.line_names.last_mut().unwrap();
+        line.extend(names.iter().map(|name| name.0.clone()));
+    }
+
+    /// Extend the explicit grid to the tracks of `grid-template-areas`,
+    /// and give their grid lines implicit names: https://drafts.csswg.org/css-grid/#implicit-named-lines
+    ///
+    /// Areas are given with one-based lines.
+    fn add_areas<'a>(
+        &mut self,
+        track_count: usize,
+        auto_tracks: &ImplicitGridTracks,
+        areas: impl Iterator<Item = (&'a Atom, u32, u32)>,
+    ) {
+        // “Any rows/columns defined by grid-template-areas but not sized by
+        //  grid-template-rows/columns take their size from the grid-auto-rows/columns properties.”
+        let sized_track_count = self.tracks.len();
+        for index in sized_track_count..track_count {
+            let size = implicit_track_size(auto_tracks, (index - sized_track_count) as isize);
+            self.add_track(&size);
+        }
+        for (name, start, end) in areas {
+            self.line_names[start as usize - 1].push(Atom::from(format!("{}-start", name)));
+            self.line_names[end as usize - 1].push(Atom::from(format!("{}-end", name)));
+        }
+    }
+
+    /// The tracks of the explicit grid, with `before` implicit tracks before it
+    /// and as many implicit tracks after it as needed to have `count` tracks in total.
+    fn with_implicit_tracks(
+        &self,
+        auto_tracks: &ImplicitGridTracks,
+        before: usize,
+        count: usize,
+    ) -> Vec<TrackSize> {
+        (0..count)
+            .map(|index| {
+                let index = index as isize - before as isize;
+                if index < 0 {
+                    implicit_track_size(auto_tracks, index)
+                } else if (index as usize) < self.tracks.len() {
+                    self.tracks[index as usize].clone()
+                } else {
+                    implicit_track_size(auto_tracks, index - self.tracks.len() as isize)
+                }
+            })
+            .collect()
+    }
+
+    /// The last line of the explicit grid
+    fn end_line(&self) -> i32 {
+        self.tracks.len() as i32
+    }
+
+    fn lines_named<'a>(&'a self, name: &'a Atom) -> impl DoubleEndedIterator<Item = i32> + 'a {
+        self.line_names
+            .iter()
+            .enumerate()
+            .filter(move |(_, names)| names.contains(name))
+            .map(|(index, _)| index as i32)
+    }
+
+    /// https://drafts.csswg.org/css-grid/#line-placement
+    fn resolve_line(&self, line: &GridLine, side: Side) -> LinePlacement {
+        let name = if line.ident.is_empty() {
+            None
+        } else {
+            Some(&line.ident)
+        };
+        if line.is_span {
+            let span = line.line_num.max(1).min(MAX_GRID_LINE);
+            return LinePlacement::Span(span, name.cloned());
+        }
+        let line = match (name, line.line_num) {
+            (None, 0) => return LinePlacement::Auto,
+            (None, n) if n > 0 => n - 1,
+            (None, n) => self.end_line().saturating_add(1).saturating_add(n),
+            (Some(name), 0) => {
+                // “First attempt to match the grid area’s edge to a named grid area”
+                let suffix = match side {
+                    Side::Start => "start",
+                    Side::End => "end",
+                };
+                let area_edge = Atom::from(format!("{}-{}", name, suffix));
+                match self.lines_named(&area_edge).next() {
+                    Some(line) => line,
+                    // “Otherwise, treat this as if the integer 1 had been specified
+                    //  along with the <custom-ident>.”
+                    None => self.nth_line_named(name, 1),
+                }
+            },
+            (Some(name), n) => self.nth_line_named(name, n),
+        };
+        LinePlacement::Line(clamp_line(line))
+    }
+
+    /// The `n`th line with the given name, counting from the end if `n` is negative
+    fn nth_line_named(&self, name: &Atom, n: i32) -> i32 {
+        // “If there are not enough lines with that name, all implicit grid lines
+        //  are assumed to have that name for the purpose of finding this position.”
+        let lines: Vec<_> = self.lines_named(name).collect();
+        let count = lines.len() as i32;
+        if n > 0 {
+            if n <= count {
+                lines[(n - 1) as usize]
+            } else {
+                self.end_line().saturating_add(n - count)
+            }
+        } else if n >= -count {
+            lines[(count + n) as usize]
+        } else {
+            n.saturating_add(count)
+        }
+    }
+
+    /// The line that is `span` lines (with the given name, if any) away from `from`,
+    /// in the given direction.
+    fn span_from(&self, from: i32, span: i32, name: Option<&Atom>, forward: bool) -> i32 {
+        clamp_line(self.unclamped_span_from(from, span, name, forward))
+    }
+
+    fn unclamped_span_from(&self, from: i32, span: i32, name: Option<&Atom>, forward: bool) -> i32 {
+        let name = match name {
+            Some(name) => name,
+            None if forward => return from.saturating_add(span),
+            None => return from.saturating_sub(span),
+        };
+        // “If there are not enough lines with that name, all implicit grid lines
+        //  on the side of the explicit grid corresponding to the search direction
+        //  are assumed to have that name for the purpose of counting this span.”
+        let lines: Vec<_> = if forward {
+            self.lines_named(name).filter(|&line| line > from).collect()
+        } else {
+            self.lines_named(name)
+                .rev()
+                .filter(|&line| line < from)
+                .collect()
+        };
+        let count = lines.len() as i32;
+        if span <= count {
+            lines[(span - 1) as usize]
+        } else if forward {
+            from.max(self.end_line()).saturating_add(span - count)
+        } else {
+            from.min(0).saturating_sub(span - count)
+        }
+    }
+
+    /// https://drafts.csswg.org/css-grid/#grid-placement-errors
+    fn resolve_placement(&self, start: &GridLine, end: &GridLine) -> AxisPlacement {
+        use LinePlacement::*;
+        match (
+            self.resolve_line(start, Side::Start),
+            self.resolve_line(end, Side::End),
+        ) {
+            (Line(start), Line(end)) => {
+                // “If the placement for a grid item contains two lines, and the start line
+                //  is further end-ward than the end line, swap the two lines. If the start line
+                //  is equal to the end line, remove the end line.”
+                if end < start {
+                    AxisPlacement::Definite(end, start)
+                } else if end == start {
+                    AxisPlacement::Definite(start, start + 1)
+                } else {
+                    AxisPlacement::Definite(start, end)
+                }
+            },
+            // “If the grid area would be placed completely outside the limited grid,
+            //  its span must be truncated to 1 and the area repositioned into
+            //  the last grid track on that side of the grid.”
+            (Line(start), Span(span, name)) => {
+                match self.span_from(start, span, name.as_ref(), true) {
+                    end if end > start => AxisPlacement::Definite(start, end),
+                    _ => AxisPlacement::Definite(start - 1, start),
+                }
+            },
+            (Span(span, name), Line(end)) => {
+                match self.span_from(end, span, name.as_ref(), false) {
+                    start if start < end => AxisPlacement::Definite(start, end),
+                    _ => AxisPlacement::Definite(end, end + 1),
+                }
+            },
+            (Line(start), Auto) => AxisPlacement::Definite(start, start + 1),
+            (Auto, Line(end)) => AxisPlacement::Definite(end - 1, end),
+            // “If the placement contains two spans, remove the one contributed
+            //  by the end grid-placement property.”
+            // “If the placement contains only a span for a named line,
+            //  replace it with a span of 1.”
+            (Span(span, name), _) | (Auto, Span(span, name)) => {
+                AxisPlacement::Auto(if name.is_some() { 1 } else { span as usize })
+            },
+            (Auto, Auto) => AxisPlacement::Auto(1),
+        }
+    }
+}
+
+/// The size of an implicit track, given its index from the end of the explicit grid.
+/// Negative indices are for tracks before the explicit grid.
+/// https://drafts.csswg.org/css-grid/#auto-tracks
+fn implicit_track_size(auto_tracks: &ImplicitGridTracks, index: isize) -> TrackSize {
+    let sizes = &auto_tracks.0;
+    if sizes.is_empty() {
+        TrackSize::default()
+    } else {
+        sizes[index.rem_euclid(sizes.len() as isize) as usize].clone()
+    }
+}
+
+/// The number of repetitions for an integer `repeat()` count
+fn repeat_number(count: i32) -> usize {
+    count.max(1).min(MAX_GRID_LINE) as usize
+}
+
+/// Clamp a line number to the implementation limit, on either side of the explicit grid
+fn clamp_line(line: i32) -> i32 {
+    line.max(-MAX_GRID_LINE).min(MAX_GRID_LINE)
+}
+
+/// The number of repetitions for `auto-fill` and `auto-fit`:
+/// https://drafts.csswg.org/css-grid/#auto-repeat
+fn auto_repeat_count(
+    list: &TrackList,
+    definite_available_size: Option<Length>,
+    gap: Length,
+) -> usize {
+    let available_size = match definite_available_size {
+        Some(size) => size,
+        None => return 1,
+    };
+
+    // “treating each track as its max track sizing function if that is definite
+    //  or as its minimum track sizing function otherwise”
+    let fixed_size = |size: &TrackSize| {
+        let definite = |breadth: &TrackBreadth| match breadth {
+            TrackBreadth::Breadth(length) => Some(length.percentage_relative_to(available_size)),
+            _ => None,
+        };
+        match size {
+            TrackSize::Breadth(breadth) => definite(breadth),
+            TrackSize::Minmax(min, max) => definite(max).or_else(|| definite(min)),
+            TrackSize::FitContent(_) => None,
+        }
+    };
+    let sum_sizes = |sizes: &[TrackSize]| {
+        sizes.iter().try_fold(Length::zero(), |sum, size| {
+            fixed_size(size).map(|size| sum + size)
+        })
+    };
+
+    let count = || {
+        let mut other_tracks_count = 0;
+        let mut other_tracks_size = Length::zero();
+        let mut repeated_tracks_count = 0;
+        let mut repeated_tracks_size = Length::zero();
+        for (index, value) in list.values.iter().enumerate() {
+            match value {
+                TrackListValue::TrackSize(size) => {
+                    other_tracks_count += 1;
+                    other_tracks_size += fixed_size(size)?;
+                },
+                TrackListValue::TrackRepeat(repeat) if index == list.auto_repeat_index => {
+                    repeated_tracks_count += repeat.track_sizes.len();
+                    repeated_tracks_size += sum_sizes(&*repeat.track_sizes)?;
+                },
+                TrackListValue::TrackRepeat(repeat) => {
+                    let count = match repeat.count {
+                        RepeatCount::Number(count) => repeat_number(count),
+                        RepeatCount::AutoFill | RepeatCount::AutoFit => 1,
+                    };
+                    other_tracks_count += count * repeat.track_sizes.len();
+                    other_tracks_size += sum_sizes(&*repeat.track_sizes)? * count as f32;
+                },
+            }
+        }
+        // With `n` repetitions, the tracks and the gaps between them must fit in the
+        // available space: `other + n * repeated + (other_count + n * repeated_count - 1) * gap`
+        let space_for_repetitions =
+            available_size + gap - other_tracks_size - gap * other_tracks_count as f32;
+        let repetition_size = repeated_tracks_size + gap * repeated_tracks_count as f32;
+        if repetition_size <= Length::zero() {
+            return None;
+        }
+        Some(
+            (space_for_repetitions.px() / repetition_size.px())
+                .floor()
+                .max(0.)
+                .min(MAX_GRID_LINE as f32) as usize,
+        )
+    };
+    // “if any number of repetitions would overflow, then 1 repetition”
+    count().unwrap_or(1).max(1)
+}
@@ -0,0 +1,556 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-grid/#algo-track-sizing
+
+use crate::formatting_contexts::IndependentFormattingContext;
+use std::ops::Range;
+use style::values::computed::length::NonNegativeLengthPercentageOrNormal;
+use style::values::computed::{Length, TrackBreadth, TrackSize};
+use style::Zero;
+
+#[derive(Clone, Copy)]
+pub(super) enum AvailableSpace {
+    Definite(Length),
+    MinContent,
+    MaxContent,
+}
+
+/// The contributions of a grid item to the size of the tracks it spans in one axis
+pub(super) struct TrackSizingItem {
+    pub span: Range<usize>,
+    pub min_content: Length,
+    pub max_content: Length,
+}
+
+impl TrackSizingItem {
+    /// The contributions of a grid item to the columns it spans,
+    /// from its outer min-content and max-content inline sizes
+    pub fn for_columns(item: &IndependentFormattingContext, columns: &Range<usize>) -> Self {
+        let outer = item.content_sizes.outer_inline(&item.style);
+        TrackSizingItem {
+            span: columns.clone(),
+            min_content: outer.min_content,
+            max_content: outer.max_content,
+        }
+    }
+}
+
+/// https://drafts.csswg.org/css-grid/#min-track-sizing-function
+#[derive(PartialEq)]
+enum MinSizing {
+    Fixed(Length),
+    MinContent,
+    MaxContent,
+    Auto,
+}
+
+/// https://drafts.csswg.org/css-grid/#max-track-sizing-function
+enum MaxSizing {
+    Fixed(Length),
+    MinContent,
+    MaxContent,
+    Auto,
+    FitContent(Length),
+    Flex(f32),
+}
+
+struct Track {
+    min: MinSizing,
+    max: MaxSizing,
+    base_size: Length,
+    /// `None` is infinite
+    growth_limit: Option<Length>,
+}
+
+/// Which size of tracks is increased when distributing extra space
+#[derive(Clone, Copy)]
+enum AffectedSize {
+    BaseSize,
+    GrowthLimit,
+}
+
+/// Return the used size of each track.
+pub(super) fn size_tracks(
+    tracks: &[TrackSize],
+    gap: Length,
+    available_space: AvailableSpace,
+    items: &[TrackSizingItem],
+) -> Vec<Length> {
+    let percentage_basis = match available_space {
+        AvailableSpace::Definite(size) => Some(size),
+        AvailableSpace::MinContent | AvailableSpace::MaxContent => None,
+    };
+
+    // “Initialize Track Sizes”
+    // https://drafts.csswg.org/css-grid/#algo-init
+    let mut tracks: Vec<_> = tracks
+        .iter()
+        .map(|size| Track::new(size, percentage_basis))
+        .collect();
+
+    // “Resolve Intrinsic Track Sizes”
+    // https://drafts.csswg.org/css-grid/#algo-content
+    let spans_flexible_track = |tracks: &[Track], item: &TrackSizingItem| {
+        tracks[item.span.clone()].iter().any(Track::is_flexible)
+    };
+    let under_max_content = match available_space {
+        AvailableSpace::MaxContent => true,
+        AvailableSpace::Definite(_) | AvailableSpace::MinContent => false,
+    };
+    // FIXME: the minimum contribution of an item is approximated by its min-content contribution.
+    for item in items {
+        if item.span.len() != 1 || spans_flexible_track(&tracks, item) {
+            continue;
+        }
+        let track = &mut tracks[item.span.start];
+        match track.min {
+            MinSizing::Fixed(_) => {},
+            MinSizing::MinContent => track.base_size.max_assign(item.min_content),
+            MinSizing::MaxContent => track.base_size.max_assign(item.max_content),
+            MinSizing::Auto if under_max_content => track.base_size.max_assign(item.max_content),
+            MinSizing::Auto => track.base_size.max_assign(item.min_content),
+        }
+        let contribution = match track.max {
+            MaxSizing::Fixed(_) | MaxSizing::Flex(_) => continue,
+            MaxSizing::MinContent => item.min_content,
+            MaxSizing::MaxContent | MaxSizing::Auto => item.max_content,
+            MaxSizing::FitContent(limit) => item.max_content.min(limit),
+        };
+        track.growth_limit = Some(match track.growth_limit {
+            Some(limit) => limit.max(contribution),
+            None => contribution,
+        });
+    }
+    for track in &mut tracks {
+        track.growth_limit_at_least_base_size();
+    }
+
+    // “Increase sizes to accommodate spanning items crossing content-sized tracks”,
+    // one group of items with the same span at a time, from the smallest span.
+    let mut spanning_items: Vec<_> = items
+        .iter()
+        .filter(|item| item.span.len() > 1 && !spans_flexible_track(&tracks, item))
+        .collect();
+    spanning_items.sort_by_key(|item| item.span.len());
+    for group in group_by_span(&spanning_items) {
+        distribute_extra_space(
+            &mut tracks,
+            gap,
+            group,
+            AffectedSize::BaseSize,
+            |track| track.min.is_intrinsic(),
+            |item| item.min_content,
+        );
+        distribute_extra_space(
+            &mut tracks,
+            gap,
+            group,
+            AffectedSize::BaseSize,
+            |track| {
+                track.min == MinSizing::MaxContent ||
+                    (under_max_content && track.min == MinSizing::Auto)
+            },
+            |item| item.max_content,
+        );
+        distribute_extra_space(
+            &mut tracks,
+            gap,
+            group,
+            AffectedSize::GrowthLimit,
+            |track| track.max.is_intrinsic(),
+            |item| item.min_content,
+        );
+        distribute_extra_space(
+            &mut tracks,
+            gap,
+            group,
+            AffectedSize::GrowthLimit,
+            |track| match track.max {
+                MaxSizing::MaxContent | MaxSizing::Auto | MaxSizing::FitContent(_) => true,
+                _ => false,
+            },
+            |item| item.max_content,
+        );
+    }
+
+    // “Increase sizes to accommodate spanning items crossing flexible tracks”
+    let flexible_items: Vec<_> = items
+        .iter()
+        .filter(|item| spans_flexible_track(&tracks, item))
+        .collect();
+    distribute_extra_space(
+        &mut tracks,
+        gap,
+        &flexible_items,
+        AffectedSize::BaseSize,
+        |track| track.is_flexible() && track.min.is_intrinsic(),
+        |item| item.min_content,
+    );
+
+    // “If any track still has an infinite growth limit,
+    //  set its growth limit to its base size.”
+    for track in &mut tracks {
+        track.growth_limit.get_or_insert(track.base_size);
+    }
+
+    // “Maximize Tracks”
+    // https://drafts.csswg.org/css-grid/#algo-grow-tracks
+    match available_space {
+        AvailableSpace::Definite(size) => {
+            let free_space = size - sum_with_gaps(&base_sizes(&tracks), gap);
+            distribute_up_to_growth_limits(&mut tracks, free_space);
+        },
+        AvailableSpace::MaxContent => {
+            for track in &mut tracks {
+                track.base_size = track.growth_limit.unwrap();
+            }
+        },
+        AvailableSpace::MinContent => {},
+    }
+
+    // “Expand Flexible Tracks”
+    // https://drafts.csswg.org/css-grid/#algo-flex-tracks
+    if tracks.iter().any(Track::is_flexible) {
+        let all_tracks = 0..tracks.len();
+        let flex_fraction = match available_space {
+            // “If the free space is zero or if sizing the grid container
+            //  under a min-content constraint, the used flex fraction is zero.”
+            AvailableSpace::MinContent => Length::zero(),
+            AvailableSpace::Definite(size) => find_size_of_fr(&tracks, all_tracks, gap, size),
+            AvailableSpace::MaxContent => {
+                let from_tracks = tracks.iter().filter_map(|track| match track.max {
+                    MaxSizing::Flex(flex) if flex > 1. => Some(track.base_size / flex),
+                    MaxSizing::Flex(_) => Some(track.base_size),
+                    _ => None,
+                });
+                let from_items = items.iter().filter_map(|item| {
+                    if spans_flexible_track(&tracks, item) {
+                        Some(find_size_of_fr(
+                            &tracks,
+                            item.span.clone(),
+                            gap,
+                            item.max_content,
+                        ))
+                    } else {
+                        None
+                    }
+                });
+                from_tracks
+                    .chain(from_items)
+                    .fold(Length::zero(), Length::max)
+            },
+        };
+        for track in &mut tracks {
+            if let MaxSizing::Flex(flex) = track.max {
+                track.base_size.max_assign(flex_fraction * flex);
+            }
+        }
+    }
+
+    base_sizes(&tracks)
+}
+
+/// “Stretch auto Tracks”
+/// https://drafts.csswg.org/css-grid/#algo-stretch
+pub(super) fn stretch_auto_tracks(tracks: &[TrackSize], sizes: &mut [Length], free_space: Length) {
+    let is_auto = |size: &TrackSize| match size {
+        TrackSize::Breadth(max) | TrackSize::Minmax(_, max) => *max == TrackBreadth::Auto,
+        TrackSize::FitContent(_) => false,
+    };
+    let auto_count = tracks.iter().filter(|size| is_auto(size)).count();
+    if auto_count == 0 || free_space <= Length::zero() {
+        return;
+    }
+    let each = free_space / auto_count as f32;
+    for (size, track) in sizes.iter_mut().zip(tracks) {
+        if is_auto(track) {
+            *size += each
+        }
+    }
+}
+
+/// The sum of the given track sizes and of the gaps between them
+pub(super) fn sum_with_gaps(sizes: &[Length], gap: Length) -> Length {
+    let gaps = gap * sizes.len().saturating_sub(1) as f32;
+    sizes.iter().fold(gaps, |sum, &size| sum + size)
+}
+
+/// https://drafts.csswg.org/css-align/#column-row-gap
+pub(super) fn resolve_gap(
+    gap: &NonNegativeLengthPercentageOrNormal,
+    percentage_basis: Option<Length>,
+) -> Length {
+    match gap {
+        // “In grid containers, `normal` computes to a used value of 0px”
+        NonNegativeLengthPercentageOrNormal::Normal => Length::zero(),
+        // Percentages against an indefinite size resolve to zero for intrinsic sizing.
+        NonNegativeLengthPercentageOrNormal::LengthPercentage(gap) => gap
+            .0
+            .maybe_percentage_relative_to(percentage_basis)
+            .unwrap_or_else(Length::zero),
+    }
+}
+
+impl Track {
+    fn new(size: &TrackSize, percentage_basis: Option<Length>) -> Self {
+        // “If the size of the grid container depends on the size of its tracks,
+        //  then the <percentage> must be treated as auto”
+        let resolve = |breadth: &TrackBreadth| match breadth {
+            TrackBreadth::Breadth(length) => length.maybe_percentage_relative_to(percentage_basis),
+            _ => None,
+        };
+        let min_sizing = |breadth: &TrackBreadth| match breadth {
+            TrackBreadth::Breadth(_) => resolve(breadth).map_or(MinSizing::Auto, MinSizing::Fixed),
+            TrackBreadth::MinContent => MinSizing::MinContent,
+            TrackBreadth::MaxContent => MinSizing::MaxContent,
+            // “If the track was sized with a <flex> value, its minimum is `auto`”
+            TrackBreadth::Auto | TrackBreadth::Fr(_) => MinSizing::Auto,
+        };
+        let max_sizing = |breadth: &TrackBreadth| match breadth {
+            TrackBreadth::Breadth(_) => resolve(breadth).map_or(MaxSizing::Auto, MaxSizing::Fixed),
+            TrackBreadth::Fr(flex) => MaxSizing::Flex(*flex),
+            TrackBreadth::MinContent => MaxSizing::MinContent,
+            TrackBreadth::MaxContent => MaxSizing::MaxContent,
+            TrackBreadth::Auto => MaxSizing::Auto,
+        };
+        let (min, max) = match size {
+            TrackSize::Breadth(breadth) => (min_sizing(breadth), max_sizing(breadth)),
+            TrackSize::Minmax(min, max) => (min_sizing(min), max_sizing(max)),
+            TrackSize::FitContent(limit) => (
+                MinSizing::Auto,
+                resolve(limit).map_or(MaxSizing::MaxContent, MaxSizing::FitContent),
+            ),
+        };
+        let base_size = match min {
+            MinSizing::Fixed(size) => size,
+            _ => Length::zero(),
+        };
+        let growth_limit = match max {
+            MaxSizing::Fixed(size) => Some(size),
+            _ => None,
+        };
+        let mut track = Track {
+            min,
+            max,
+            base_size,
+            growth_limit,
+        };
+        track.growth_limit_at_least_base_size();
+        track
+    }
+
+    fn is_flexible(&self) -> bool {
+        match self.max {
+            MaxSizing::Flex(_) => true,
+            _ => false,
+        }
+    }
+
+    fn growth_limit_at_least_base_size(&mut self) {
+        if let Some(limit) = &mut self.growth_limit {
+            limit.max_assign(self.base_size)
+        }
+    }
+
+    fn affected_size(&self, affected: AffectedSize) -> Length {
+        match affected {
+            AffectedSize::BaseSize => self.base_size,
+            AffectedSize::GrowthLimit => self.growth_limit.unwrap_or(self.base_size),
+        }
+    }
+}
+
+impl MinSizing {
+    fn is_intrinsic(&self) -> bool {
+        match self {
+            MinSizing::Fixed(_) => false,
+            MinSizing::MinContent | MinSizing::MaxContent | MinSizing::Auto => true,
+        }
+    }
+}
+
+impl MaxSizing {
+    fn is_intrinsic(&self) -> bool {
+        match self {
+            MaxSizing::Fixed(_) | MaxSizing::Flex(_) => false,
+            MaxSizing::MinContent |
+            MaxSizing::MaxContent |
+            MaxSizing::Auto |
+            MaxSizing::FitContent(_) => true,
+        }
+    }
+}
+
+/// Split items sorted by span into groups with the same span
+fn group_by_span<'a>(items: &'a [&'a TrackSizingItem]) -> Vec<&'a [&'a TrackSizingItem]> {
+    let mut groups = Vec::new();
+    let mut rest = items;
+    while let Some(first) = rest.first() {
+        let len = rest
+            .iter()
+            .take_while(|item| item.span.len() == first.span.len())
+            .count();
+        let (group, remaining) = rest.split_at(len);
+        groups.push(group);
+        rest = remaining;
+    }
+    groups
+}
+
+/// https://drafts.csswg.org/css-grid/#extra-space
+fn distribute_extra_space(
+    tracks: &mut [Track],
+    gap: Length,
+    items: &[&TrackSizingItem],
+    affected_size: AffectedSize,
+    is_affected: impl Fn(&Track) -> bool,
+    contribution: impl Fn(&TrackSizingItem) -> Length,
+) {
+    let mut planned_increases = vec![Length::zero(); tracks.len()];
+    for item in items {
+        let affected_tracks: Vec<_> = item
+            .span
+            .clone()
+            .filter(|&index| is_affected(&tracks[index]))
+            .collect();
+        if affected_tracks.is_empty() {
+            continue;
+        }
+        // “Find the space to distribute”
+        let spanned_sizes: Vec<_> = tracks[item.span.clone()]
+            .iter()
+            .map(|track| track.affected_size(affected_size))
+            .collect();
+        let space = contribution(item) - sum_with_gaps(&spanned_sizes, gap);
+        if space <= Length::zero() {
+            continue;
+        }
+
+        // “Distribute space up to limits”, then beyond them.
+        let mut increases = vec![Length::zero(); affected_tracks.len()];
+        let mut remaining = space;
+        let mut unfrozen: Vec<_> = (0..affected_tracks.len()).collect();
+        while remaining > Length::zero() && !unfrozen.is_empty() {
+            let share = remaining / unfrozen.len() as f32;
+            let mut still_unfrozen = Vec::with_capacity(unfrozen.len());
+            for &i in &unfrozen {
+                let track = &tracks[affected_tracks[i]];
+                let limit = match affected_size {
+                    AffectedSize::BaseSize => track
+                        .growth_limit
+                        .map(|limit| limit - track.base_size - increases[i]),
+                    AffectedSize::GrowthLimit => None,
+                };
+                let increase = match limit {
+                    Some(limit) if limit <= share => limit.max(Length::zero()),
+                    _ => {
+                        still_unfrozen.push(i);
+                        share
+                    },
+                };
+                increases[i] += increase;
+                remaining = remaining - increase;
+            }
+            if still_unfrozen.len() == unfrozen.len() {
+                break;
+            }
+            unfrozen = still_unfrozen;
+        }
+        if unfrozen.is_empty() && remaining > Length::zero() {
+            let share = remaining / affected_tracks.len() as f32;
+            for increase in &mut increases {
+                *increase += share
+            }
+        }
+
+        for (&index, &increase) in affected_tracks.iter().zip(&increases) {
+            planned_increases[index].max_assign(increase)
+        }
+    }
+
+    // “Update the tracks’ affected sizes”
+    for (track, &increase) in tracks.iter_mut().zip(&planned_increases) {
+        if increase == Length::zero() {
+            continue;
+        }
+        match affected_size {
+            AffectedSize::BaseSize => track.base_size += increase,
+            AffectedSize::GrowthLimit => {
+                track.growth_limit = Some(track.affected_size(affected_size) + increase)
+            },
+        }
+        track.growth_limit_at_least_base_size();
+    }
+}
+
+/// Distribute free space equally to the base sizes of tracks,
+/// freezing them as they reach their growth limits.
+fn distribute_up_to_growth_limits(tracks: &mut [Track], mut free_space: Length) {
+    let mut unfrozen: Vec<_> = (0..tracks.len()).collect();
+    while free_space > Length::zero() && !unfrozen.is_empty() {
+        let share = free_space / unfrozen.len() as f32;
+        let mut still_unfrozen = Vec::with_capacity(unfrozen.len());
+        for &index in &unfrozen {
+            let track = &mut tracks[index];
+            let room = track.growth_limit.unwrap() - track.base_size;
+            let increase = if room <= share {
+                room.max(Length::zero())
+            } else {
+                still_unfrozen.push(index);
+                share
+            };
+            track.base_size += increase;
+            free_space = free_space - increase;
+        }
+        if still_unfrozen.len() == unfrozen.len() {
+            break;
+        }
+        unfrozen = still_unfrozen;
+    }
+}
+
+/// https://drafts.csswg.org/css-grid/#algo-find-fr-size
+fn find_size_of_fr(
+    tracks: &[Track],
+    range: Range<usize>,
+    gap: Length,
+    space_to_fill: Length,
+) -> Length {
+    let tracks = &tracks[range];
+    let gaps = gap * tracks.len().saturating_sub(1) as f32;
+    let mut inflexible: Vec<_> = tracks.iter().map(|track| !track.is_flexible()).collect();
+    loop {
+        let mut leftover_space = space_to_fill - gaps;
+        let mut flex_factor_sum = 0.;
+        for (track, &inflexible) in tracks.iter().zip(&inflexible) {
+            match track.max {
+                MaxSizing::Flex(flex) if !inflexible => flex_factor_sum += flex,
+                _ => leftover_space = leftover_space - track.base_size,
+            }
+        }
+        // “If the flex factor sum is less than 1, set it to 1 instead.”
+        let hypothetical_fr_size = leftover_space.max(Length::zero()) / flex_factor_sum.max(1.);
+
+        // “If the product of the hypothetical fr size and a flexible track’s flex factor
+        //  is less than the track’s base size, restart this algorithm
+        //  treating all such tracks as inflexible.”
+        let mut restart = false;
+        for (track, inflexible) in tracks.iter().zip(&mut inflexible) {
+            if let MaxSizing::Flex(flex) = track.max {
+                if !*inflexible && hypothetical_fr_size * flex < track.base_size {
+                    *inflexible = true;
+                    restart = true;
+                }
+            }
+        }
+        if !restart {
+            return hypothetical_fr_size;
+        }
+    }
+}
+
+fn base_sizes(tracks: &[Track]) -> Vec<Length> {
+    tracks.iter().map(|track| track.base_size).collect()
+}
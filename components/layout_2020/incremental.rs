@@ -0,0 +1,196 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Incremental layout: reusing the boxes and fragments of the previous reflow
+//! for the parts of the document that did not change since.
+
+use crate::cell::ArcRefCell;
+use crate::dom_traversal::{iter_child_nodes, NodeExt};
+use crate::formatting_contexts::IndependentLayout;
+use crate::fragments::{AnonymousFragment, DebugId, Fragment};
+use crate::geom::flow_relative::{Rect, Vec2};
+use crate::geom::PhysicalRect;
+use crate::positioned::PositioningContext;
+use crate::wrapper::GetStyleAndLayoutData;
+use crate::ContainingBlock;
+use atomic_refcell::AtomicRefCell;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use style::logical_geometry::WritingMode;
+use style::selector_parser::RestyleDamage;
+use style::values::computed::{Length, LengthOrAuto};
+
+/// Finds the elements whose box from the previous box construction can be reused as-is,
+/// then clears the restyle damage of every element, since it is now accounted for.
+///
+/// A box is built from the style and DOM subtree of its element, so it can be reused
+/// when neither the element nor any of its descendants were damaged by restyling.
+/// Box construction still checks what a box gets from outside of its subtree,
+/// like the text decorations propagated by its ancestors.
+///
+/// Returns whether the given element and all of its descendants are undamaged.
+pub(crate) fn mark_reusable_boxes<'dom>(element: impl NodeExt<'dom>, incremental: bool) -> bool {
+    let data = match element.get_style_and_layout_data() {
+        Some(data) => data,
+        // Never styled, so there is no previous box either.
+        None => return true,
+    };
+    let mut undamaged = true;
+    for child in iter_child_nodes(element) {
+        if child.is_element() {
+            undamaged &= mark_reusable_boxes(child, incremental);
+        }
+    }
+    let mut element_data = data.style_data.element_data.borrow_mut();
+    undamaged &= element_data.damage.is_empty();
+    element_data.damage = RestyleDamage::empty();
+
+    let mut layout_data = data.layout_data.borrow_mut();
+    layout_data.self_box_is_reusable =
+        incremental && undamaged && layout_data.self_box.borrow().is_some();
+    undamaged
+}
+
+/// Prevents reusing the boxes of the given element and of its ancestors,
+/// for changes that restyle damage does not account for.
+pub(crate) fn invalidate_reusable_boxes<'dom>(element: impl NodeExt<'dom>) {
+    let mut next = Some(element);
+    while let Some(element) = next {
+        let mut layout_data = element.layout_data_mut();
+        if !layout_data.self_box_is_reusable {
+            // The boxes of ancestors of a box that cannot be reused cannot be reused either.
+            return;
+        }
+        layout_data.self_box_is_reusable = false;
+        next = element.parent_node().filter(|parent| parent.is_element());
+    }
+}
+
+/// The number of boxes that box construction built or reused in one reflow,
+/// exposed to test incremental layout.
+#[derive(Default)]
+pub struct BoxConstructionStatistics {
+    /// Boxes of elements and pseudo-elements, whether built or reused.
+    boxes: AtomicUsize,
+
+    /// Boxes that were reused from the previous box construction.
+    reused_boxes: AtomicUsize,
+}
+
+impl BoxConstructionStatistics {
+    pub(crate) fn note_box(&self) {
+        self.boxes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn note_reused_box(&self) {
+        self.reused_boxes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The number of boxes of elements and pseudo-elements that were built
+    /// rather than reused from the previous box construction.
+    pub fn rebuilt_boxes(&self) -> usize {
+        self.boxes.load(Ordering::Relaxed) - self.reused_boxes.load(Ordering::Relaxed)
+    }
+}
+
+/// The result of the last layout of an independent formatting context.
+///
+/// Its box only outlives a reflow when it is reused, which means that nothing in its subtree
+/// changed. Laying it out again in a containing block of the same size then gives the same
+/// fragments, which can be shared with the previous fragment tree instead.
+#[derive(Default)]
+pub(crate) struct LayoutCache(AtomicRefCell<Option<CachedLayout>>);
+
+struct CachedLayout {
+    inline_size: Length,
+    block_size: LengthOrAuto,
+    content_block_size: Length,
+    children: Vec<ArcRefCell<Fragment>>,
+    scrollable_overflow: PhysicalRect<Length>,
+    mode: WritingMode,
+}
+
+impl LayoutCache {
+    /// Returns the cached layout if it was for a containing block of the same size,
+    /// or lays out with `layout` otherwise.
+    pub(crate) fn layout(
+        &self,
+        positioning_context: &mut PositioningContext,
+        containing_block: &ContainingBlock,
+        layout: impl FnOnce(&mut PositioningContext) -> IndependentLayout,
+    ) -> IndependentLayout {
+        if let Some(cached) = &*self.0.borrow() {
+            if cached.inline_size == containing_block.inline_size &&
+                cached.block_size == containing_block.block_size
+            {
+                return cached.to_layout();
+            }
+        }
+
+        // Absolutely positioned descendants are laid out with their containing block,
+        // which may be outside of this formatting context. Only cache layouts without any.
+        let mut own_positioning_context = PositioningContext::new_for_rayon(
+            positioning_context.collects_for_nearest_positioned_ancestor(),
+        );
+        let independent_layout = layout(&mut own_positioning_context);
+        if !own_positioning_context.is_empty() {
+            positioning_context.append(own_positioning_context);
+            *self.0.borrow_mut() = None;
+            return independent_layout;
+        }
+
+        let anonymous_fragment = AnonymousFragment::new(
+            Rect {
+                start_corner: Vec2::zero(),
+                size: Vec2 {
+                    inline: containing_block.inline_size,
+                    block: independent_layout.content_block_size,
+                },
+            },
+            independent_layout.fragments,
+            containing_block.style.writing_mode,
+        );
+        let cached = CachedLayout {
+            inline_size: containing_block.inline_size,
+            block_size: containing_block.block_size,
+            content_block_size: independent_layout.content_block_size,
+            children: anonymous_fragment.children.clone(),
+            scrollable_overflow: anonymous_fragment.scrollable_overflow,
+            mode: anonymous_fragment.mode,
+        };
+        *self.0.borrow_mut() = Some(cached);
+        IndependentLayout {
+            fragments: vec![Fragment::Anonymous(anonymous_fragment)],
+            content_block_size: independent_layout.content_block_size,
+        }
+    }
+}
+
+impl CachedLayout {
+    fn to_layout(&self) -> IndependentLayout {
+        let anonymous_fragment = AnonymousFragment {
+            debug_id: DebugId::new(),
+            rect: Rect {
+                start_corner: Vec2::zero(),
+                size: Vec2 {
+                    inline: self.inline_size,
+                    block: self.content_block_size,
+                },
+            },
+            children: self.children.clone(),
+            mode: self.mode,
+            scrollable_overflow: self.scrollable_overflow,
+        };
+        IndependentLayout {
+            fragments: vec![Fragment::Anonymous(anonymous_fragment)],
+            content_block_size: self.content_block_size,
+        }
+    }
+}
+
+impl fmt::Debug for LayoutCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LayoutCache")
+    }
+}
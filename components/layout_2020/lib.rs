@@ -10,23 +10,29 @@ extern crate log;
 extern crate serde;
 
 mod cell;
+pub mod container_queries;
 pub mod context;
 pub mod data;
 pub mod display_list;
 mod dom_traversal;
 pub mod element_data;
+mod flexbox;
 mod flow;
 mod formatting_contexts;
 mod fragments;
 mod geom;
+mod grid;
+mod incremental;
 #[macro_use]
 pub mod layout_debug;
+mod lists;
 mod opaque_node;
 mod positioned;
 pub mod query;
 mod replaced;
 mod sizing;
 mod style_ext;
+mod table;
 pub mod traversal;
 pub mod wrapper;
 
@@ -0,0 +1,397 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Lists and counters: https://drafts.csswg.org/css-lists/
+
+use crate::context::LayoutContext;
+use crate::dom_traversal::{iter_child_nodes, pseudo_element_style, NodeExt, WhichPseudoElement};
+use crate::incremental;
+use crate::replaced::ReplacedContent;
+use crate::style_ext::{Display, DisplayGeneratingBox};
+use fxhash::FxHashMap;
+use style::computed_values::list_style_type::T as ListStyleType;
+use style::properties::ComputedValues;
+use style::values::generics::counters::{Content, ContentItem};
+use style::Atom;
+
+/// The counters that the generated content of an element refers to,
+/// rendered as text in the order in which that content refers to them.
+#[derive(Default, PartialEq)]
+pub(crate) struct GeneratedCounters {
+    marker: Vec<String>,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+impl GeneratedCounters {
+    pub(crate) fn of(&self, which: WhichPseudoElement) -> &[String] {
+        match which {
+            WhichPseudoElement::Marker => &self.marker,
+            WhichPseudoElement::Before => &self.before,
+            WhichPseudoElement::After => &self.after,
+        }
+    }
+}
+
+/// Resolves the counters used by generated content in the whole document,
+/// and stores them in the layout data of each element.
+///
+/// The value of a counter depends on everything that precedes an element in document order,
+/// so unlike the rest of box construction this is a sequential traversal.
+pub(crate) fn resolve_counters<'dom>(context: &LayoutContext, root_element: impl NodeExt<'dom>) {
+    let mut counters = CountersInScope {
+        counters: FxHashMap::default(),
+        list_item: Atom::from("list-item"),
+    };
+    counters.visit_element(context, root_element, 0)
+}
+
+/// https://drafts.csswg.org/css-lists/#creating-a-counter
+struct CountersInScope {
+    /// The instances of each counter, outermost first.
+    counters: FxHashMap<Atom, Vec<CounterInstance>>,
+
+    /// https://drafts.csswg.org/css-lists/#list-item-counter
+    list_item: Atom,
+}
+
+struct CounterInstance {
+    /// The depth in the DOM of the element that created this instance.
+    /// Its scope is that element, its following siblings, and their descendants.
+    depth: usize,
+    value: i32,
+}
+
+impl CountersInScope {
+    fn visit_element<'dom>(
+        &mut self,
+        context: &LayoutContext,
+        element: impl NodeExt<'dom>,
+        depth: usize,
+    ) {
+        let style = element.style(context);
+        let display = Display::from(style.get_box().display);
+        // Elements that do not generate a box do not affect counters.
+        if display == Display::None {
+            return;
+        }
+        let is_list_item = matches!(
+            display,
+            Display::GeneratingBox(DisplayGeneratingBox::OutsideInside {
+                list_item: true, ..
+            })
+        );
+        self.apply_counter_properties(&style, is_list_item, depth);
+
+        // The children and pseudo-elements of replaced elements are not rendered.
+        if ReplacedContent::for_element(element).is_some() {
+            return;
+        }
+        let mut generated_counters = GeneratedCounters::default();
+        if is_list_item {
+            generated_counters.marker =
+                self.visit_pseudo_element(WhichPseudoElement::Marker, context, element, depth);
+        }
+        generated_counters.before =
+            self.visit_pseudo_element(WhichPseudoElement::Before, context, element, depth);
+        for child in iter_child_nodes(element) {
+            if child.is_element() {
+                self.visit_element(context, child, depth + 1)
+            }
+        }
+        generated_counters.after =
+            self.visit_pseudo_element(WhichPseudoElement::After, context, element, depth);
+        self.end_scopes_of_children(depth);
+
+        let mut layout_data = element.layout_data_mut();
+        if layout_data.generated_counters != generated_counters {
+            layout_data.generated_counters = generated_counters;
+            // Restyle damage does not account for counters changed by other elements.
+            drop(layout_data);
+            incremental::invalidate_reusable_boxes(element);
+        }
+    }
+
+    fn visit_pseudo_element<'dom>(
+        &mut self,
+        which: WhichPseudoElement,
+        context: &LayoutContext,
+        element: impl NodeExt<'dom>,
+        depth: usize,
+    ) -> Vec<String> {
+        let style = match pseudo_element_style(which, element, context) {
+            Some(style) => style,
+            None => return Vec::new(),
+        };
+        if Display::from(style.get_box().display) == Display::None {
+            return Vec::new();
+        }
+        // Pseudo-elements are children of their originating element.
+        self.apply_counter_properties(&style, false, depth + 1);
+
+        match &style.get_counters().content {
+            Content::Items(items) => items
+                .iter()
+                .filter_map(|item| match item {
+                    ContentItem::Counter(name, list_style_type) => {
+                        Some(self.render_innermost(&name.0, *list_style_type))
+                    },
+                    ContentItem::Counters(name, separator, list_style_type) => {
+                        Some(self.render_all(&name.0, separator, *list_style_type))
+                    },
+                    _ => None,
+                })
+                .collect(),
+            // Only `::marker` is generated with `content: normal`.
+            // https://drafts.csswg.org/css-lists/#content-property
+            Content::Normal => {
+                let list_style_type = style.get_list().list_style_type;
+                let list_item = self.render_innermost(&self.list_item, list_style_type);
+                let suffix = if is_symbolic(list_style_type) {
+                    "\u{a0}"
+                } else {
+                    ".\u{a0}"
+                };
+                vec![list_item + suffix]
+            },
+            Content::None => unreachable!(),
+        }
+    }
+
+    /// https://drafts.csswg.org/css-lists/#counter-properties
+    fn apply_counter_properties(
+        &mut self,
+        style: &ComputedValues,
+        is_list_item: bool,
+        depth: usize,
+    ) {
+        let counters = style.get_counters();
+        for pair in counters.counter_reset.iter() {
+            self.reset(&pair.name.0, pair.value, depth)
+        }
+        for pair in counters.counter_increment.iter() {
+            self.increment(&pair.name.0, pair.value, depth)
+        }
+
+        // List items increment the `list-item` counter,
+        // unless their `counter-increment` already mentions it.
+        if is_list_item &&
+            !counters
+                .counter_increment
+                .iter()
+                .any(|pair| pair.name.0 == self.list_item)
+        {
+            let list_item = self.list_item.clone();
+            self.increment(&list_item, 1, depth)
+        }
+    }
+
+    fn reset(&mut self, name: &Atom, value: i32, depth: usize) {
+        let instances = self.counters.entry(name.clone()).or_default();
+        match instances.last_mut() {
+            // Resetting a counter created by a preceding sibling does not nest a new instance.
+            Some(instance) if instance.depth == depth => instance.value = value,
+            _ => instances.push(CounterInstance { depth, value }),
+        }
+    }
+
+    fn increment(&mut self, name: &Atom, amount: i32, depth: usize) {
+        let instances = self.counters.entry(name.clone()).or_default();
+        match instances.last_mut() {
+            Some(instance) => instance.value = instance.value.saturating_add(amount),
+            // Incrementing a counter that is not in scope instantiates it first.
+            None => instances.push(CounterInstance {
+                depth,
+                value: amount,
+            }),
+        }
+    }
+
+    /// Ends the scope of the counters created by the children of an element at `depth`.
+    fn end_scopes_of_children(&mut self, depth: usize) {
+        for instances in self.counters.values_mut() {
+            if let Some(position) = instances.iter().position(|instance| instance.depth > depth) {
+                instances.truncate(position)
+            }
+        }
+    }
+
+    fn instances(&self, name: &Atom) -> &[CounterInstance] {
+        self.counters
+            .get(name)
+            .map_or(&[][..], |instances| &instances[..])
+    }
+
+    /// https://drafts.csswg.org/css-lists/#funcdef-counter
+    fn render_innermost(&self, name: &Atom, list_style_type: ListStyleType) -> String {
+        let value = self
+            .instances(name)
+            .last()
+            .map_or(0, |instance| instance.value);
+        let mut string = String::new();
+        push_representation(value, list_style_type, &mut string);
+        string
+    }
+
+    /// https://drafts.csswg.org/css-lists/#funcdef-counters
+    fn render_all(&self, name: &Atom, separator: &str, list_style_type: ListStyleType) -> String {
+        let instances = self.instances(name);
+        if instances.is_empty() {
+            return self.render_innermost(name, list_style_type);
+        }
+        let mut string = String::new();
+        for (i, instance) in instances.iter().enumerate() {
+            if i > 0 {
+                string.push_str(separator)
+            }
+            push_representation(instance.value, list_style_type, &mut string)
+        }
+        string
+    }
+}
+
+// Decimal styles per CSS-COUNTER-STYLES § 6.1:
+static DECIMAL: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+static ARABIC_INDIC: [char; 10] = ['٠', '١', '٢', '٣', '٤', '٥', '٦', '٧', '٨', '٩'];
+static BENGALI: [char; 10] = ['০', '১', '২', '৩', '৪', '৫', '৬', '৭', '৮', '৯'];
+static CAMBODIAN: [char; 10] = ['០', '១', '២', '៣', '៤', '៥', '៦', '៧', '៨', '៩'];
+static CJK_DECIMAL: [char; 10] = ['〇', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+static DEVANAGARI: [char; 10] = ['०', '१', '२', '३', '४', '५', '६', '७', '८', '९'];
+static GUJARATI: [char; 10] = ['૦', '૧', '૨', '૩', '૪', '૫', '૬', '૭', '૮', '૯'];
+static GURMUKHI: [char; 10] = ['੦', '੧', '੨', '੩', '੪', '੫', '੬', '੭', '੮', '੯'];
+static KANNADA: [char; 10] = ['೦', '೧', '೨', '೩', '೪', '೫', '೬', '೭', '೮', '೯'];
+static LAO: [char; 10] = ['໐', '໑', '໒', '໓', '໔', '໕', '໖', '໗', '໘', '໙'];
+static MALAYALAM: [char; 10] = ['൦', '൧', '൨', '൩', '൪', '൫', '൬', '൭', '൮', '൯'];
+static MONGOLIAN: [char; 10] = ['᠐', '᠑', '᠒', '᠓', '᠔', '᠕', '᠖', '᠗', '᠘', '᠙'];
+static MYANMAR: [char; 10] = ['၀', '၁', '၂', '၃', '၄', '၅', '၆', '၇', '၈', '၉'];
+static ORIYA: [char; 10] = ['୦', '୧', '୨', '୩', '୪', '୫', '୬', '୭', '୮', '୯'];
+static PERSIAN: [char; 10] = ['۰', '۱', '۲', '۳', '۴', '۵', '۶', '۷', '۸', '۹'];
+static TELUGU: [char; 10] = ['౦', '౧', '౨', '౩', '౪', '౫', '౬', '౭', '౮', '౯'];
+static THAI: [char; 10] = ['๐', '๑', '๒', '๓', '๔', '๕', '๖', '๗', '๘', '๙'];
+static TIBETAN: [char; 10] = ['༠', '༡', '༢', '༣', '༤', '༥', '༦', '༧', '༨', '༩'];
+
+// Alphabetic styles per CSS-COUNTER-STYLES § 6.2:
+static LOWER_ALPHA: [char; 26] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z',
+];
+static UPPER_ALPHA: [char; 26] = [
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
+    'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+];
+static CJK_EARTHLY_BRANCH: [char; 12] = [
+    '子', '丑', '寅', '卯', '辰', '巳', '午', '未', '申', '酉', '戌', '亥',
+];
+static CJK_HEAVENLY_STEM: [char; 10] = ['甲', '乙', '丙', '丁', '戊', '己', '庚', '辛', '壬', '癸'];
+static LOWER_GREEK: [char; 24] = [
+    'α', 'β', 'γ', 'δ', 'ε', 'ζ', 'η', 'θ', 'ι', 'κ', 'λ', 'μ', 'ν', 'ξ', 'ο', 'π', 'ρ', 'σ', 'τ',
+    'υ', 'φ', 'χ', 'ψ', 'ω',
+];
+static HIRAGANA: [char; 48] = [
+    'あ', 'い', 'う', 'え', 'お', 'か', 'き', 'く', 'け', 'こ', 'さ', 'し', 'す', 'せ', 'そ', 'た',
+    'ち', 'つ', 'て', 'と', 'な', 'に', 'ぬ', 'ね', 'の', 'は', 'ひ', 'ふ', 'へ', 'ほ', 'ま', 'み',
+    'む', 'め', 'も', 'や', 'ゆ', 'よ', 'ら', 'り', 'る', 'れ', 'ろ', 'わ', 'ゐ', 'ゑ', 'を', 'ん',
+];
+static HIRAGANA_IROHA: [char; 47] = [
+    'い', 'ろ', 'は', 'に', 'ほ', 'へ', 'と', 'ち', 'り', 'ぬ', 'る', 'を', 'わ', 'か', 'よ', 'た',
+    'れ', 'そ', 'つ', 'ね', 'な', 'ら', 'む', 'う', 'ゐ', 'の', 'お', 'く', 'や', 'ま', 'け', 'ふ',
+    'こ', 'え', 'て', 'あ', 'さ', 'き', 'ゆ', 'め', 'み', 'し', 'ゑ', 'ひ', 'も', 'せ', 'す',
+];
+static KATAKANA: [char; 48] = [
+    'ア', 'イ', 'ウ', 'エ', 'オ', 'カ', 'キ', 'ク', 'ケ', 'コ', 'サ', 'シ', 'ス', 'セ', 'ソ', 'タ',
+    'チ', 'ツ', 'テ', 'ト', 'ナ', 'ニ', 'ヌ', 'ネ', 'ノ', 'ハ', 'ヒ', 'フ', 'ヘ', 'ホ', 'マ', 'ミ',
+    'ム', 'メ', 'モ', 'ヤ', 'ユ', 'ヨ', 'ラ', 'リ', 'ル', 'レ', 'ロ', 'ワ', 'ヰ', 'ヱ', 'ヲ', 'ン',
+];
+static KATAKANA_IROHA: [char; 47] = [
+    'イ', 'ロ', 'ハ', 'ニ', 'ホ', 'ヘ', 'ト', 'チ', 'リ', 'ヌ', 'ル', 'ヲ', 'ワ', 'カ', 'ヨ', 'タ',
+    'レ', 'ソ', 'ツ', 'ネ', 'ナ', 'ラ', 'ム', 'ウ', 'ヰ', 'ノ', 'オ', 'ク', 'ヤ', 'マ', 'ケ', 'フ',
+    'コ', 'エ', 'テ', 'ア', 'サ', 'キ', 'ユ', 'メ', 'ミ', 'シ', 'ヱ', 'ヒ', 'モ', 'セ', 'ス',
+];
+
+/// Whether this style renders the same symbol for every counter value.
+/// https://drafts.csswg.org/css-counter-styles/#simple-symbolic
+fn is_symbolic(list_style_type: ListStyleType) -> bool {
+    matches!(
+        list_style_type,
+        ListStyleType::None |
+            ListStyleType::Disc |
+            ListStyleType::Circle |
+            ListStyleType::Square |
+            ListStyleType::DisclosureOpen |
+            ListStyleType::DisclosureClosed
+    )
+}
+
+/// Appends the representation of `value` in the counter style given by `list_style_type`.
+/// https://drafts.csswg.org/css-counter-styles/#generate-a-counter
+fn push_representation(value: i32, list_style_type: ListStyleType, string: &mut String) {
+    match list_style_type {
+        ListStyleType::None => {},
+        ListStyleType::Disc => string.push('•'),
+        ListStyleType::Circle => string.push('◦'),
+        ListStyleType::Square => string.push('▪'),
+        ListStyleType::DisclosureOpen => string.push('▾'),
+        ListStyleType::DisclosureClosed => string.push('‣'),
+        ListStyleType::Decimal => push_numeric(value, &DECIMAL, string),
+        ListStyleType::ArabicIndic => push_numeric(value, &ARABIC_INDIC, string),
+        ListStyleType::Bengali => push_numeric(value, &BENGALI, string),
+        ListStyleType::Cambodian | ListStyleType::Khmer => push_numeric(value, &CAMBODIAN, string),
+        ListStyleType::CjkDecimal => push_numeric(value, &CJK_DECIMAL, string),
+        ListStyleType::Devanagari => push_numeric(value, &DEVANAGARI, string),
+        ListStyleType::Gujarati => push_numeric(value, &GUJARATI, string),
+        ListStyleType::Gurmukhi => push_numeric(value, &GURMUKHI, string),
+        ListStyleType::Kannada => push_numeric(value, &KANNADA, string),
+        ListStyleType::Lao => push_numeric(value, &LAO, string),
+        ListStyleType::Malayalam => push_numeric(value, &MALAYALAM, string),
+        ListStyleType::Mongolian => push_numeric(value, &MONGOLIAN, string),
+        ListStyleType::Myanmar => push_numeric(value, &MYANMAR, string),
+        ListStyleType::Oriya => push_numeric(value, &ORIYA, string),
+        ListStyleType::Persian => push_numeric(value, &PERSIAN, string),
+        ListStyleType::Telugu => push_numeric(value, &TELUGU, string),
+        ListStyleType::Thai => push_numeric(value, &THAI, string),
+        ListStyleType::Tibetan => push_numeric(value, &TIBETAN, string),
+        ListStyleType::LowerAlpha => push_alphabetic(value, &LOWER_ALPHA, string),
+        ListStyleType::UpperAlpha => push_alphabetic(value, &UPPER_ALPHA, string),
+        ListStyleType::CjkEarthlyBranch => push_alphabetic(value, &CJK_EARTHLY_BRANCH, string),
+        ListStyleType::CjkHeavenlyStem => push_alphabetic(value, &CJK_HEAVENLY_STEM, string),
+        ListStyleType::LowerGreek => push_alphabetic(value, &LOWER_GREEK, string),
+        ListStyleType::Hiragana => push_alphabetic(value, &HIRAGANA, string),
+        ListStyleType::HiraganaIroha => push_alphabetic(value, &HIRAGANA_IROHA, string),
+        ListStyleType::Katakana => push_alphabetic(value, &KATAKANA, string),
+        ListStyleType::KatakanaIroha => push_alphabetic(value, &KATAKANA_IROHA, string),
+    }
+}
+
+/// https://drafts.csswg.org/css-counter-styles/#alphabetic-system
+fn push_alphabetic(value: i32, symbols: &[char], string: &mut String) {
+    // Alphabetic systems are only defined for positive values,
+    // fall back to `decimal` for the others.
+    if value < 1 {
+        return push_numeric(value, &DECIMAL, string);
+    }
+    let mut value = value as usize;
+    let mut representation = Vec::new();
+    while value != 0 {
+        value -= 1;
+        representation.push(symbols[value % symbols.len()]);
+        value /= symbols.len();
+    }
+    string.extend(representation.into_iter().rev())
+}
+
+/// https://drafts.csswg.org/css-counter-styles/#numeric-system
+fn push_numeric(value: i32, symbols: &[char], string: &mut String) {
+    // https://drafts.csswg.org/css-counter-styles/#counter-style-negative
+    if value < 0 {
+        string.push('-');
+    }
+    let mut value = (value as i64).abs() as usize;
+    let mut representation = vec![symbols[value % symbols.len()]];
+    value /= symbols.len();
+    while value != 0 {
+        representation.push(symbols[value % symbols.len()]);
+        value /= symbols.len();
+    }
+    string.extend(representation.into_iter().rev())
+}
@@ -168,6 +168,15 @@ impl PositioningContext {
         self.for_nearest_positioned_ancestor.is_some()
     }
 
+    /// Whether no absolutely positioned box was hoisted to this context.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.for_nearest_containing_block_for_all_descendants
+            .is_empty() &&
+            self.for_nearest_positioned_ancestor
+                .as_ref()
+                .map_or(true, |boxes| boxes.is_empty())
+    }
+
     pub(crate) fn new_for_style(style: &ComputedValues) -> Option<Self> {
         if style.establishes_containing_block_for_all_descendants() {
             Some(Self::new_for_containing_block_for_all_descendants())
@@ -181,6 +190,40 @@ impl PositioningContext {
         }
     }
 
+    /// A new context in which to lay out a box with the given style,
+    /// to be passed back to `append_laid_out_box` once its fragment is known.
+    /// This allows laying out the same box more than once and keeping only the last result.
+    pub(crate) fn new_for_subtree(&self, style: &ComputedValues) -> Self {
+        Self::new_for_style(style)
+            .unwrap_or_else(|| Self::new_for_rayon(self.collects_for_nearest_positioned_ancestor()))
+    }
+
+    /// Finish the layout of a box laid out in `other`, a context created with
+    /// `new_for_subtree`: lay out the hoisted boxes for which the box is the containing
+    /// block and apply relative positioning, or pass them up the tree otherwise.
+    pub(crate) fn append_laid_out_box(
+        &mut self,
+        layout_context: &LayoutContext,
+        containing_block: &ContainingBlock,
+        mut other: Self,
+        fragment: &mut BoxFragment,
+    ) {
+        let style = fragment.style.clone();
+        if !style.establishes_containing_block() {
+            return self.append(other);
+        }
+
+        other.layout_collected_children(layout_context, fragment);
+        vec_append_owned(
+            &mut self.for_nearest_containing_block_for_all_descendants,
+            other.for_nearest_containing_block_for_all_descendants,
+        );
+
+        if style.clone_position() == Position::Relative {
+            fragment.content_rect.start_corner += &relative_adjustement(&style, containing_block);
+        }
+    }
+
     /// Given `fragment_layout_fn`, a closure which lays out a fragment in a provided
     /// `PositioningContext`, create a new positioning context if necessary for the fragment and
     /// lay out the fragment and all its children. Returns the newly created `BoxFragment`.
@@ -343,6 +386,24 @@ impl PositioningContext {
         fragments
     }
 
+    /// Move the static positions of all hoisted boxes in this context by `offset`,
+    /// for boxes laid out in a subtree that is only positioned afterwards.
+    pub(crate) fn translate_static_positions(&mut self, offset: &Vec2<Length>) {
+        let nearest = self.for_nearest_positioned_ancestor.iter_mut().flatten();
+        let all = self
+            .for_nearest_containing_block_for_all_descendants
+            .iter_mut();
+        for hoisted_box in nearest.chain(all) {
+            let offsets = &mut hoisted_box.box_offsets;
+            if let AbsoluteBoxOffsets::StaticStart { start } = &mut offsets.inline {
+                *start += offset.inline;
+            }
+            if let AbsoluteBoxOffsets::StaticStart { start } = &mut offsets.block {
+                *start += offset.block;
+            }
+        }
+    }
+
     pub(crate) fn layout_initial_containing_block_children(
         &mut self,
         layout_context: &LayoutContext,
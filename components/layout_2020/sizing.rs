@@ -5,6 +5,7 @@
 //! https://drafts.csswg.org/css-sizing/
 
 use crate::style_ext::ComputedValuesExt;
+use crate::ContainingBlock;
 use style::properties::longhands::box_sizing::computed_value::T as BoxSizing;
 use style::properties::ComputedValues;
 use style::values::computed::{Length, LengthPercentage, Percentage};
@@ -97,7 +98,7 @@ pub(crate) enum BoxContentSizes {
 }
 
 impl BoxContentSizes {
-    fn expect_inline(&self) -> &ContentSizes {
+    pub(crate) fn expect_inline(&self) -> &ContentSizes {
         match self {
             Self::NoneWereRequested => panic!("Accessing content size that was not requested"),
             Self::Inline(s) => s,
@@ -188,3 +189,33 @@ impl BoxContentSizes {
             .min(inline.max_content)
     }
 }
+
+/// The min and max block sizes of the content box of a flex or grid container,
+/// which only matter when its block size is not definite.
+pub(crate) fn content_block_size_limits(
+    containing_block: &ContainingBlock,
+) -> (Length, Option<Length>) {
+    // FIXME: percentages should resolve against the block size of the containing block
+    // of the container, which is not known here.
+    let style = containing_block.style;
+    let min = style
+        .min_box_size()
+        .block
+        .non_auto()
+        .and_then(|min| min.to_length())
+        .unwrap_or_else(Length::zero);
+    let max = style.max_box_size().block.and_then(|max| max.to_length());
+    match style.get_position().box_sizing {
+        BoxSizing::ContentBox => (min, max),
+        BoxSizing::BorderBox => {
+            let padding_border = style
+                .padding_border_margin(containing_block)
+                .padding_border_sums
+                .block;
+            (
+                (min - padding_border).max(Length::zero()),
+                max.map(|max| max - padding_border),
+            )
+        },
+    }
+}
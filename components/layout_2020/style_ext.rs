@@ -5,6 +5,7 @@
 use crate::geom::flow_relative;
 use crate::geom::{LengthOrAuto, LengthPercentageOrAuto, PhysicalSides, PhysicalSize};
 use crate::ContainingBlock;
+use style::computed_values::container_type::T as ContainerType;
 use style::computed_values::mix_blend_mode::T as ComputedMixBlendMode;
 use style::computed_values::position::T as ComputedPosition;
 use style::computed_values::transform_style::T as ComputedTransformStyle;
@@ -32,10 +33,24 @@ pub(crate) enum DisplayGeneratingBox {
     OutsideInside {
         outside: DisplayOutside,
         inside: DisplayInside,
-        // list_item: bool,
+        list_item: bool,
     },
     // Layout-internal display types go here:
     // https://drafts.csswg.org/css-display-3/#layout-specific-display
+    LayoutInternal(DisplayLayoutInternal),
+}
+
+/// https://drafts.csswg.org/css-display-3/#layout-specific-display
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub(crate) enum DisplayLayoutInternal {
+    TableCaption,
+    TableCell,
+    TableColumn,
+    TableColumnGroup,
+    TableFooterGroup,
+    TableHeaderGroup,
+    TableRow,
+    TableRowGroup,
 }
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -48,6 +63,9 @@ pub(crate) enum DisplayOutside {
 pub(crate) enum DisplayInside {
     Flow,
     FlowRoot,
+    Flex,
+    Grid,
+    Table,
 }
 
 /// Percentages resolved but not `auto` margins
@@ -92,6 +110,9 @@ pub(crate) trait ComputedValuesExt {
     fn establishes_containing_block(&self) -> bool;
     fn establishes_containing_block_for_all_descendants(&self) -> bool;
     fn background_is_transparent(&self) -> bool;
+    fn establishes_layout_containment(&self) -> bool;
+    fn inline_size_is_contained(&self) -> bool;
+    fn block_size_is_contained(&self) -> bool;
     fn get_webrender_primitive_flags(&self) -> wr::PrimitiveFlags;
 }
 
@@ -379,6 +400,28 @@ impl ComputedValuesExt for ComputedValues {
                 .all(|layer| matches!(layer, ComputedImageLayer::None))
     }
 
+    /// Whether boxes with this style have layout containment, and so establish an
+    /// independent formatting context. Query containers have it.
+    /// <https://drafts.csswg.org/css-contain-2/#containment-layout>
+    fn establishes_layout_containment(&self) -> bool {
+        self.get_box().container_type != ContainerType::Normal
+    }
+
+    /// Whether boxes with this style have inline-size containment, which gives them
+    /// the intrinsic inline sizes of an empty box. This keeps the inline size of query
+    /// containers from depending on the contents that are styled against it.
+    /// <https://drafts.csswg.org/css-contain-3/#containment-inline-size>
+    fn inline_size_is_contained(&self) -> bool {
+        self.get_box().container_type != ContainerType::Normal && size_containment_applies(self)
+    }
+
+    /// Whether boxes with this style have size containment, which also gives them
+    /// the `auto` block size of an empty box.
+    /// <https://drafts.csswg.org/css-contain-2/#containment-size>
+    fn block_size_is_contained(&self) -> bool {
+        self.get_box().container_type == ContainerType::Size && size_containment_applies(self)
+    }
+
     /// Generate appropriate WebRender `PrimitiveFlags` that should be used
     /// for display items generated by the `Fragment` which owns this style.
     fn get_webrender_primitive_flags(&self) -> wr::PrimitiveFlags {
@@ -389,31 +432,87 @@ impl ComputedValuesExt for ComputedValues {
     }
 }
 
+/// Size containment has no effect on tables and internal table boxes. It has none on
+/// non-atomic inline boxes either, but only boxes that establish an independent
+/// formatting context are checked for it.
+/// <https://drafts.csswg.org/css-contain-2/#containment-size>
+fn size_containment_applies(style: &ComputedValues) -> bool {
+    match Display::from(style.get_box().display) {
+        Display::GeneratingBox(DisplayGeneratingBox::OutsideInside { inside, .. }) => {
+            inside != DisplayInside::Table
+        },
+        Display::GeneratingBox(DisplayGeneratingBox::LayoutInternal(internal)) => {
+            internal == DisplayLayoutInternal::TableCaption
+        },
+        Display::None | Display::Contents => false,
+    }
+}
+
 impl From<stylo::Display> for Display {
     fn from(packed: stylo::Display) -> Self {
         let inside = match packed.inside() {
             stylo::DisplayInside::Flow => DisplayInside::Flow,
             stylo::DisplayInside::FlowRoot => DisplayInside::FlowRoot,
+            stylo::DisplayInside::Flex => DisplayInside::Flex,
+            stylo::DisplayInside::Grid => DisplayInside::Grid,
+            stylo::DisplayInside::Table => DisplayInside::Table,
 
             // These should not be values of DisplayInside, but oh well
             stylo::DisplayInside::None => return Display::None,
             stylo::DisplayInside::Contents => return Display::Contents,
+
+            // Internal table boxes are only ever paired with `DisplayOutside::InternalTable`
+            stylo::DisplayInside::TableRowGroup => {
+                return Display::layout_internal(DisplayLayoutInternal::TableRowGroup)
+            },
+            stylo::DisplayInside::TableHeaderGroup => {
+                return Display::layout_internal(DisplayLayoutInternal::TableHeaderGroup)
+            },
+            stylo::DisplayInside::TableFooterGroup => {
+                return Display::layout_internal(DisplayLayoutInternal::TableFooterGroup)
+            },
+            stylo::DisplayInside::TableRow => {
+                return Display::layout_internal(DisplayLayoutInternal::TableRow)
+            },
+            stylo::DisplayInside::TableCell => {
+                return Display::layout_internal(DisplayLayoutInternal::TableCell)
+            },
+            stylo::DisplayInside::TableColumn => {
+                return Display::layout_internal(DisplayLayoutInternal::TableColumn)
+            },
+            stylo::DisplayInside::TableColumnGroup => {
+                return Display::layout_internal(DisplayLayoutInternal::TableColumnGroup)
+            },
         };
         let outside = match packed.outside() {
             stylo::DisplayOutside::Block => DisplayOutside::Block,
             stylo::DisplayOutside::Inline => DisplayOutside::Inline,
 
+            // A table caption is a flow root, but it is only laid out by its table.
+            stylo::DisplayOutside::TableCaption => {
+                return Display::layout_internal(DisplayLayoutInternal::TableCaption)
+            },
+            stylo::DisplayOutside::InternalTable => {
+                unreachable!("internal table boxes are handled with their DisplayInside")
+            },
+
             // This should not be a value of DisplayInside, but oh well
             stylo::DisplayOutside::None => return Display::None,
         };
         Display::GeneratingBox(DisplayGeneratingBox::OutsideInside {
             outside,
             inside,
-            // list_item: packed.is_list_item(),
+            list_item: packed.is_list_item(),
         })
     }
 }
 
+impl Display {
+    fn layout_internal(internal: DisplayLayoutInternal) -> Self {
+        Display::GeneratingBox(DisplayGeneratingBox::LayoutInternal(internal))
+    }
+}
+
 fn size_to_length(size: &Size) -> LengthPercentageOrAuto {
     match size {
         Size::LengthPercentage(length) => LengthPercentageOrAuto::LengthPercentage(&length.0),
@@ -0,0 +1,629 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-tables/#table-structure
+
+use super::{Table, TableCell, TableColumn, TableLevelBox, TableRow, TableRowGroup};
+use crate::cell::ArcRefCell;
+use crate::context::LayoutContext;
+use crate::dom_traversal::{
+    is_only_document_white_space, BoxSlot, Contents, DeferredChild, NodeExt, NonReplacedContents,
+    TraversalHandler,
+};
+use crate::element_data::LayoutBox;
+use crate::formatting_contexts::IndependentFormattingContext;
+use crate::sizing::{ContentSizes, ContentSizesRequest};
+use crate::style_ext::{DisplayGeneratingBox, DisplayInside, DisplayLayoutInternal};
+use servo_arc::Arc;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use style::properties::ComputedValues;
+use style::selector_parser::PseudoElement;
+use style::values::specified::text::TextDecorationLine;
+
+/// https://html.spec.whatwg.org/multipage/#dom-tdth-colspan
+const MAX_COLSPAN: u32 = 1000;
+
+/// https://html.spec.whatwg.org/multipage/#dom-tdth-rowspan
+const MAX_ROWSPAN: u32 = 65534;
+
+impl Table {
+    pub fn construct<'dom>(
+        context: &LayoutContext,
+        node: impl NodeExt<'dom>,
+        style: &Arc<ComputedValues>,
+        contents: NonReplacedContents,
+        propagated_text_decoration_line: TextDecorationLine,
+    ) -> Self {
+        let text_decoration_line =
+            propagated_text_decoration_line | style.clone_text_decoration_line();
+        let mut builder = TableBuilder::new(context, node, style, text_decoration_line);
+        contents.traverse(context, node, style, &mut builder);
+        builder.finish()
+    }
+
+    /// Construct an anonymous table around children that were found outside of a table.
+    pub fn construct_anonymous<'dom, Node>(
+        context: &LayoutContext,
+        node: Node,
+        style: &Arc<ComputedValues>,
+        children: impl IntoIterator<Item = DeferredChild<'dom, Node>>,
+        propagated_text_decoration_line: TextDecorationLine,
+    ) -> Self
+    where
+        Node: NodeExt<'dom>,
+    {
+        let mut builder = TableBuilder::new(context, node, style, propagated_text_decoration_line);
+        for child in children {
+            child.replay(&mut builder);
+        }
+        builder.finish()
+    }
+}
+
+fn anonymous_style<'dom, Node>(
+    context: &LayoutContext,
+    pseudo: PseudoElement,
+    parent_style: &ComputedValues,
+) -> Arc<ComputedValues>
+where
+    Node: NodeExt<'dom>,
+{
+    context
+        .shared_context()
+        .stylist
+        .style_for_anonymous::<Node::ConcreteElement>(
+            &context.shared_context().guards,
+            &pseudo,
+            parent_style,
+        )
+}
+
+/// Children that are not proper table children are wrapped in anonymous boxes,
+/// with any white space in between them. White space on its own is dropped:
+/// https://drafts.csswg.org/css-tables/#fixup-algorithm
+fn push_anonymous_box_child<'dom, Node>(
+    children: &mut Vec<DeferredChild<'dom, Node>>,
+    child: DeferredChild<'dom, Node>,
+) {
+    if let DeferredChild::Text { text, .. } = &child {
+        if children.is_empty() && is_only_document_white_space(text) {
+            return;
+        }
+    }
+    children.push(child)
+}
+
+struct TableBuilder<'a, 'dom, Node> {
+    context: &'a LayoutContext<'a>,
+    node: Node,
+    style: &'a Arc<ComputedValues>,
+    text_decoration_line: TextDecorationLine,
+    captions: Vec<ArcRefCell<IndependentFormattingContext>>,
+    columns: Vec<TableColumn>,
+    header: Option<TableRowGroup>,
+    bodies: Vec<TableRowGroup>,
+    footer: Option<TableRowGroup>,
+    /// Consecutive rows, cells and other children that are not in a row group
+    ongoing_rows_children: Vec<DeferredChild<'dom, Node>>,
+}
+
+impl<'a, 'dom, Node> TableBuilder<'a, 'dom, Node>
+where
+    Node: NodeExt<'dom>,
+{
+    fn new(
+        context: &'a LayoutContext<'a>,
+        node: Node,
+        style: &'a Arc<ComputedValues>,
+        text_decoration_line: TextDecorationLine,
+    ) -> Self {
+        Self {
+            context,
+            node,
+            style,
+            text_decoration_line,
+            captions: Vec::new(),
+            columns: Vec::new(),
+            header: None,
+            bodies: Vec::new(),
+            footer: None,
+            ongoing_rows_children: Vec::new(),
+        }
+    }
+
+    fn end_ongoing_rows(&mut self) {
+        if self.ongoing_rows_children.is_empty() {
+            return;
+        }
+        let mut builder = RowGroupBuilder::new(
+            self.context,
+            self.node,
+            self.style,
+            self.text_decoration_line,
+        );
+        for child in std::mem::take(&mut self.ongoing_rows_children) {
+            child.replay(&mut builder);
+        }
+        self.bodies.push(TableRowGroup {
+            tag: None,
+            style: None,
+            rows: builder.finish(),
+        });
+    }
+
+    fn construct_row_group(
+        &self,
+        node: Node,
+        style: &Arc<ComputedValues>,
+        contents: Contents,
+    ) -> TableRowGroup {
+        let text_decoration_line = self.text_decoration_line | style.clone_text_decoration_line();
+        let mut builder = RowGroupBuilder::new(self.context, node, style, text_decoration_line);
+        // Replaced elements have no children that could be rows.
+        if let Ok(contents) = NonReplacedContents::try_from(contents) {
+            contents.traverse(self.context, node, style, &mut builder);
+        }
+        TableRowGroup {
+            tag: Some(node.as_opaque()),
+            style: Some(style.clone()),
+            rows: builder.finish(),
+        }
+    }
+
+    fn finish(mut self) -> Table {
+        self.end_ongoing_rows();
+        let row_groups: Vec<_> = self
+            .header
+            .into_iter()
+            .chain(self.bodies)
+            .chain(self.footer)
+            .collect();
+        let column_count = row_groups
+            .iter()
+            .flat_map(|group| &group.rows)
+            .flat_map(|row| &row.cells)
+            .map(|cell| {
+                let cell = cell.borrow();
+                cell.column + cell.colspan
+            })
+            .chain(std::iter::once(self.columns.len()))
+            .max()
+            .unwrap_or(0);
+        let mut table = Table {
+            captions: self.captions,
+            columns: self.columns,
+            row_groups,
+            column_count,
+            column_content_sizes: Vec::new(),
+            content_sizes: ContentSizes::zero(),
+        };
+        table.compute_content_sizes(self.style);
+        table
+    }
+}
+
+impl<'a, 'dom, Node: 'dom> TraversalHandler<'dom, Node> for TableBuilder<'a, 'dom, Node>
+where
+    Node: NodeExt<'dom>,
+{
+    fn handle_text(
+        &mut self,
+        node: Node,
+        text: Cow<'dom, str>,
+        parent_style: &Arc<ComputedValues>,
+    ) {
+        push_anonymous_box_child(
+            &mut self.ongoing_rows_children,
+            DeferredChild::Text {
+                node,
+                text,
+                parent_style: parent_style.clone(),
+            },
+        )
+    }
+
+    /// Or pseudo-element
+    fn handle_element(
+        &mut self,
+        node: Node,
+        style: &Arc<ComputedValues>,
+        display: DisplayGeneratingBox,
+        contents: Contents,
+        box_slot: BoxSlot<'dom>,
+    ) {
+        let internal = match display {
+            DisplayGeneratingBox::LayoutInternal(internal) => internal,
+            DisplayGeneratingBox::OutsideInside { .. } => {
+                return self.ongoing_rows_children.push(DeferredChild::Element {
+                    node,
+                    style: style.clone(),
+                    display,
+                    contents,
+                    box_slot,
+                })
+            },
+        };
+        match internal {
+            DisplayLayoutInternal::TableCaption => {
+                let caption = ArcRefCell::new(IndependentFormattingContext::construct(
+                    self.context,
+                    node,
+                    style.clone(),
+                    DisplayInside::FlowRoot,
+                    contents,
+                    // Table layout uses the content sizes of captions.
+                    ContentSizesRequest::Inline,
+                    self.text_decoration_line,
+                ));
+                box_slot.set(LayoutBox::TableLevel(TableLevelBox::Caption(
+                    caption.clone(),
+                )));
+                self.captions.push(caption)
+            },
+            DisplayLayoutInternal::TableColumn => {
+                self.columns.push(TableColumn {
+                    style: style.clone(),
+                });
+                box_slot.set(LayoutBox::TableLevel(TableLevelBox::Internal))
+            },
+            DisplayLayoutInternal::TableColumnGroup => {
+                let columns_before = self.columns.len();
+                if let Ok(contents) = NonReplacedContents::try_from(contents) {
+                    let mut builder = ColumnGroupBuilder {
+                        columns: &mut self.columns,
+                    };
+                    contents.traverse(self.context, node, style, &mut builder);
+                }
+                // A column group without columns represents a single column.
+                // FIXME: support the `span` attribute of column groups and columns.
+                if self.columns.len() == columns_before {
+                    self.columns.push(TableColumn {
+                        style: style.clone(),
+                    });
+                }
+                box_slot.set(LayoutBox::TableLevel(TableLevelBox::Internal))
+            },
+            DisplayLayoutInternal::TableHeaderGroup |
+            DisplayLayoutInternal::TableRowGroup |
+            DisplayLayoutInternal::TableFooterGroup => {
+                self.end_ongoing_rows();
+                let group = self.construct_row_group(node, style, contents);
+                // Only the first header and footer groups are repeated at the start and end
+                // of the table, others are treated like body groups.
+                match internal {
+                    DisplayLayoutInternal::TableHeaderGroup if self.header.is_none() => {
+                        self.header = Some(group)
+                    },
+                    DisplayLayoutInternal::TableFooterGroup if self.footer.is_none() => {
+                        self.footer = Some(group)
+                    },
+                    _ => self.bodies.push(group),
+                }
+                box_slot.set(LayoutBox::TableLevel(TableLevelBox::Internal))
+            },
+            DisplayLayoutInternal::TableRow | DisplayLayoutInternal::TableCell => {
+                self.ongoing_rows_children.push(DeferredChild::Element {
+                    node,
+                    style: style.clone(),
+                    display,
+                    contents,
+                    box_slot,
+                })
+            },
+        }
+    }
+}
+
+/// Only columns are kept out of the children of a column group:
+/// https://drafts.csswg.org/css-tables/#fixup-algorithm
+struct ColumnGroupBuilder<'a> {
+    columns: &'a mut Vec<TableColumn>,
+}
+
+impl<'a, 'dom, Node: 'dom> TraversalHandler<'dom, Node> for ColumnGroupBuilder<'a> {
+    fn handle_text(&mut self, _: Node, _: Cow<'dom, str>, _: &Arc<ComputedValues>) {}
+
+    /// Or pseudo-element
+    fn handle_element(
+        &mut self,
+        _: Node,
+        style: &Arc<ComputedValues>,
+        display: DisplayGeneratingBox,
+        _: Contents,
+        box_slot: BoxSlot<'dom>,
+    ) {
+        if display == DisplayGeneratingBox::LayoutInternal(DisplayLayoutInternal::TableColumn) {
+            self.columns.push(TableColumn {
+                style: style.clone(),
+            });
+        }
+        box_slot.set(LayoutBox::TableLevel(TableLevelBox::Internal))
+    }
+}
+
+struct RowGroupBuilder<'a, 'dom, Node> {
+    context: &'a LayoutContext<'a>,
+    node: Node,
+    /// The style of the row group, or of the table for rows outside of any row group
+    style: &'a Arc<ComputedValues>,
+    text_decoration_line: TextDecorationLine,
+    rows: Vec<TableRow>,
+    /// Consecutive children that are not rows
+    ongoing_cells_children: Vec<DeferredChild<'dom, Node>>,
+}
+
+impl<'a, 'dom, Node> RowGroupBuilder<'a, 'dom, Node>
+where
+    Node: NodeExt<'dom>,
+{
+    fn new(
+        context: &'a LayoutContext<'a>,
+        node: Node,
+        style: &'a Arc<ComputedValues>,
+        text_decoration_line: TextDecorationLine,
+    ) -> Self {
+        Self {
+            context,
+            node,
+            style,
+            text_decoration_line,
+            rows: Vec::new(),
+            ongoing_cells_children: Vec::new(),
+        }
+    }
+
+    fn end_ongoing_cells(&mut self) {
+        if self.ongoing_cells_children.is_empty() {
+            return;
+        }
+        let style = anonymous_style::<Node>(
+            self.context,
+            PseudoElement::ServoAnonymousTableRow,
+            self.style,
+        );
+        let mut builder =
+            RowBuilder::new(self.context, self.node, &style, self.text_decoration_line);
+        for child in std::mem::take(&mut self.ongoing_cells_children) {
+            child.replay(&mut builder);
+        }
+        let cells = builder.finish();
+        self.rows.push(TableRow {
+            tag: self.node.as_opaque(),
+            style,
+            cells,
+        })
+    }
+
+    /// Returns the rows of the group, after placing their cells in the grid.
+    fn finish(mut self) -> Vec<TableRow> {
+        self.end_ongoing_cells();
+        place_cells(&self.rows);
+        self.rows
+    }
+}
+
+impl<'a, 'dom, Node: 'dom> TraversalHandler<'dom, Node> for RowGroupBuilder<'a, 'dom, Node>
+where
+    Node: NodeExt<'dom>,
+{
+    fn handle_text(
+        &mut self,
+        node: Node,
+        text: Cow<'dom, str>,
+        parent_style: &Arc<ComputedValues>,
+    ) {
+        push_anonymous_box_child(
+            &mut self.ongoing_cells_children,
+            DeferredChild::Text {
+                node,
+                text,
+                parent_style: parent_style.clone(),
+            },
+        )
+    }
+
+    /// Or pseudo-element
+    fn handle_element(
+        &mut self,
+        node: Node,
+        style: &Arc<ComputedValues>,
+        display: DisplayGeneratingBox,
+        contents: Contents,
+        box_slot: BoxSlot<'dom>,
+    ) {
+        if display != DisplayGeneratingBox::LayoutInternal(DisplayLayoutInternal::TableRow) {
+            return self.ongoing_cells_children.push(DeferredChild::Element {
+                node,
+                style: style.clone(),
+                display,
+                contents,
+                box_slot,
+            });
+        }
+        self.end_ongoing_cells();
+        let text_decoration_line = self.text_decoration_line | style.clone_text_decoration_line();
+        let mut builder = RowBuilder::new(self.context, node, style, text_decoration_line);
+        // Replaced elements have no children that could be cells.
+        if let Ok(contents) = NonReplacedContents::try_from(contents) {
+            contents.traverse(self.context, node, style, &mut builder);
+        }
+        let cells = builder.finish();
+        self.rows.push(TableRow {
+            tag: node.as_opaque(),
+            style: style.clone(),
+            cells,
+        });
+        box_slot.set(LayoutBox::TableLevel(TableLevelBox::Internal))
+    }
+}
+
+struct RowBuilder<'a, 'dom, Node> {
+    context: &'a LayoutContext<'a>,
+    node: Node,
+    style: &'a Arc<ComputedValues>,
+    text_decoration_line: TextDecorationLine,
+    cells: Vec<ArcRefCell<TableCell>>,
+    /// Consecutive children that are not cells
+    ongoing_anonymous_cell_children: Vec<DeferredChild<'dom, Node>>,
+}
+
+impl<'a, 'dom, Node> RowBuilder<'a, 'dom, Node>
+where
+    Node: NodeExt<'dom>,
+{
+    fn new(
+        context: &'a LayoutContext<'a>,
+        node: Node,
+        style: &'a Arc<ComputedValues>,
+        text_decoration_line: TextDecorationLine,
+    ) -> Self {
+        Self {
+            context,
+            node,
+            style,
+            text_decoration_line,
+            cells: Vec::new(),
+            ongoing_anonymous_cell_children: Vec::new(),
+        }
+    }
+
+    fn end_ongoing_anonymous_cell(&mut self) {
+        if self.ongoing_anonymous_cell_children.is_empty() {
+            return;
+        }
+        let style = anonymous_style::<Node>(
+            self.context,
+            PseudoElement::ServoAnonymousTableCell,
+            self.style,
+        );
+        let contents = IndependentFormattingContext::construct_for_anonymous_box(
+            self.context,
+            self.node,
+            style,
+            std::mem::take(&mut self.ongoing_anonymous_cell_children),
+            ContentSizesRequest::Inline,
+            self.text_decoration_line,
+        );
+        self.cells
+            .push(ArcRefCell::new(TableCell::new(contents, 1, 1)))
+    }
+
+    fn finish(mut self) -> Vec<ArcRefCell<TableCell>> {
+        self.end_ongoing_anonymous_cell();
+        self.cells
+    }
+}
+
+impl<'a, 'dom, Node: 'dom> TraversalHandler<'dom, Node> for RowBuilder<'a, 'dom, Node>
+where
+    Node: NodeExt<'dom>,
+{
+    fn handle_text(
+        &mut self,
+        node: Node,
+        text: Cow<'dom, str>,
+        parent_style: &Arc<ComputedValues>,
+    ) {
+        push_anonymous_box_child(
+            &mut self.ongoing_anonymous_cell_children,
+            DeferredChild::Text {
+                node,
+                text,
+                parent_style: parent_style.clone(),
+            },
+        )
+    }
+
+    /// Or pseudo-element
+    fn handle_element(
+        &mut self,
+        node: Node,
+        style: &Arc<ComputedValues>,
+        display: DisplayGeneratingBox,
+        contents: Contents,
+        box_slot: BoxSlot<'dom>,
+    ) {
+        if display != DisplayGeneratingBox::LayoutInternal(DisplayLayoutInternal::TableCell) {
+            return self
+                .ongoing_anonymous_cell_children
+                .push(DeferredChild::Element {
+                    node,
+                    style: style.clone(),
+                    display,
+                    contents,
+                    box_slot,
+                });
+        }
+        self.end_ongoing_anonymous_cell();
+        // Pseudo-elements and non-HTML elements have no `colspan` or `rowspan`.
+        let (colspan, rowspan) = match contents {
+            Contents::OfElement => node.table_cell_spans(),
+            Contents::Replaced(_) | Contents::OfPseudoElement(_) => (1, 1),
+        };
+        let contents = IndependentFormattingContext::construct(
+            self.context,
+            node,
+            style.clone(),
+            // Table cells establish a block formatting context.
+            DisplayInside::FlowRoot,
+            contents,
+            // Table layout uses the content sizes of every cell.
+            ContentSizesRequest::Inline,
+            self.text_decoration_line,
+        );
+        let cell = ArcRefCell::new(TableCell::new(contents, colspan, rowspan));
+        box_slot.set(LayoutBox::TableLevel(TableLevelBox::Cell(cell.clone())));
+        self.cells.push(cell)
+    }
+}
+
+impl TableCell {
+    /// The column of the cell is only known once its row group is complete,
+    /// see `place_cells`.
+    fn new(contents: IndependentFormattingContext, colspan: u32, rowspan: u32) -> Self {
+        Self {
+            contents,
+            column: 0,
+            colspan: colspan.max(1).min(MAX_COLSPAN) as usize,
+            // Zero means that the cell spans all remaining rows of its row group.
+            rowspan: rowspan.min(MAX_ROWSPAN) as usize,
+        }
+    }
+}
+
+/// Assign a column to every cell of a row group, skipping slots that are taken by cells
+/// from previous rows, and resolve row spans to the rows that exist in the group:
+/// https://html.spec.whatwg.org/multipage/#algorithm-for-processing-rows
+fn place_cells(rows: &[TableRow]) {
+    // For each column, the number of rows starting with the current one
+    // that are taken by a cell from a previous row.
+    let mut taken_rows: Vec<usize> = Vec::new();
+    for (row_index, row) in rows.iter().enumerate() {
+        let remaining_rows = rows.len() - row_index;
+        let mut column = 0;
+        for cell in &row.cells {
+            let mut cell = cell.borrow_mut();
+            while taken_rows.get(column).map_or(false, |&rows| rows > 0) {
+                column += 1;
+            }
+            cell.column = column;
+            cell.rowspan = match cell.rowspan {
+                0 => remaining_rows,
+                rowspan => rowspan.min(remaining_rows),
+            };
+            let end = column + cell.colspan;
+            if taken_rows.len() < end {
+                taken_rows.resize(end, 0);
+            }
+            for rows in &mut taken_rows[column..end] {
+                *rows = cell.rowspan;
+            }
+            column = end;
+        }
+        for rows in &mut taken_rows {
+            *rows = rows.saturating_sub(1);
+        }
+    }
+}
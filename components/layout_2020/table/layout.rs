@@ -0,0 +1,906 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-tables/#table-layout
+
+use super::{Table, TableCell};
+use crate::cell::ArcRefCell;
+use crate::context::LayoutContext;
+use crate::formatting_contexts::{IndependentFormattingContext, IndependentLayout};
+use crate::fragments::{AnonymousFragment, BoxFragment, CollapsedBlockMargins, Fragment};
+use crate::geom::flow_relative::{Rect, Sides, Vec2};
+use crate::positioned::PositioningContext;
+use crate::sizing::ContentSizes;
+use crate::style_ext::ComputedValuesExt;
+use crate::ContainingBlock;
+use servo_arc::Arc;
+use style::computed_values::border_collapse::T as BorderCollapse;
+use style::computed_values::box_sizing::T as BoxSizing;
+use style::computed_values::caption_side::T as CaptionSide;
+use style::computed_values::table_layout::T as TableLayout;
+use style::dom::OpaqueNode;
+use style::properties::ComputedValues;
+use style::values::computed::{Length, LengthOrAuto};
+use style::values::generics::box_::{VerticalAlign, VerticalAlignKeyword};
+use style::Zero;
+
+/// Layout parameters of a table, grouped to avoid passing around many parameters
+struct TableContext<'a> {
+    layout_context: &'a LayoutContext<'a>,
+    positioning_context: &'a mut PositioningContext,
+    containing_block: &'a ContainingBlock<'a>,
+    tree_rank: usize,
+    /// https://drafts.csswg.org/css-tables/#border-spacing-property
+    spacing: Vec2<Length>,
+}
+
+impl Table {
+    pub(crate) fn content_sizes(&self) -> &ContentSizes {
+        &self.content_sizes
+    }
+
+    fn cells(&self) -> impl Iterator<Item = &ArcRefCell<TableCell>> {
+        self.row_groups
+            .iter()
+            .flat_map(|group| &group.rows)
+            .flat_map(|row| &row.cells)
+    }
+
+    /// https://drafts.csswg.org/css-tables/#computing-column-measures
+    pub(super) fn compute_content_sizes(&mut self, style: &ComputedValues) {
+        let spacing = border_spacing(style);
+        let mut min_content = vec![Length::zero(); self.column_count];
+        let mut max_content = vec![Length::zero(); self.column_count];
+        let mut spanning_cells = Vec::new();
+        for cell in self.cells() {
+            let cell = cell.borrow();
+            let sizes = cell_content_sizes(&cell.contents);
+            if cell.colspan == 1 {
+                min_content[cell.column].max_assign(sizes.min_content);
+                max_content[cell.column].max_assign(sizes.max_content);
+            } else {
+                spanning_cells.push((cell.column, cell.colspan, sizes))
+            }
+        }
+        for (column, size) in self.columns.iter().zip(&mut max_content) {
+            // Percentages are treated as `auto`.
+            let inline_size = column.style.box_size().inline.non_auto();
+            if let Some(inline_size) = inline_size.and_then(|lp| lp.to_length()) {
+                size.max_assign(inline_size)
+            }
+        }
+
+        // Cells that span fewer columns are distributed first, so that cells spanning
+        // more columns are distributed over sizes that already account for them.
+        spanning_cells.sort_by_key(|&(_, colspan, _)| colspan);
+        for (column, colspan, sizes) in spanning_cells {
+            let columns = column..column + colspan;
+            let inner_spacing = spacing.inline * (colspan - 1) as f32;
+            let weights = max_content[columns.clone()].to_vec();
+            grow_columns(
+                &mut min_content[columns.clone()],
+                &weights,
+                sizes.min_content - inner_spacing,
+            );
+            grow_columns(
+                &mut max_content[columns],
+                &weights,
+                sizes.max_content - inner_spacing,
+            );
+        }
+
+        let mut content_sizes = ContentSizes::zero();
+        self.column_content_sizes = min_content
+            .into_iter()
+            .zip(max_content)
+            .map(|(min_content, max_content)| {
+                let max_content = max_content.max(min_content);
+                content_sizes.min_content += min_content;
+                content_sizes.max_content += max_content;
+                ContentSizes {
+                    min_content,
+                    max_content,
+                }
+            })
+            .collect();
+        if self.column_count > 0 {
+            let total_spacing = spacing.inline * (self.column_count + 1) as f32;
+            content_sizes.min_content += total_spacing;
+            content_sizes.max_content += total_spacing;
+        }
+
+        // FIXME: captions should be sized against the table wrapper box,
+        // which also contains the borders of the table grid box.
+        for caption in &self.captions {
+            let caption = caption.borrow();
+            let outer = caption.content_sizes.outer_inline(&caption.style);
+            content_sizes.min_content.max_assign(outer.min_content);
+        }
+        content_sizes
+            .max_content
+            .max_assign(content_sizes.min_content);
+        self.content_sizes = content_sizes;
+    }
+
+    pub(crate) fn layout(
+        &self,
+        layout_context: &LayoutContext,
+        positioning_context: &mut PositioningContext,
+        containing_block: &ContainingBlock,
+        tree_rank: usize,
+    ) -> IndependentLayout {
+        let mut table_context = TableContext {
+            layout_context,
+            positioning_context,
+            containing_block,
+            tree_rank,
+            spacing: border_spacing(containing_block.style),
+        };
+        let spacing = table_context.spacing.clone();
+        let column_sizes = self.column_inline_sizes(&table_context);
+
+        let mut column_starts = Vec::with_capacity(self.column_count);
+        let mut grid_inline_size = spacing.inline;
+        for size in &column_sizes {
+            column_starts.push(grid_inline_size);
+            grid_inline_size += *size + spacing.inline;
+        }
+        if self.column_count == 0 {
+            grid_inline_size = Length::zero();
+        }
+
+        // FIXME: captions belong outside of the borders of the table grid box.
+        let mut fragments = Vec::new();
+        let grid_block_start = self.layout_captions(
+            CaptionSide::Top,
+            &mut table_context,
+            &mut fragments,
+            Length::zero(),
+        );
+
+        let row_count = self.row_groups.iter().map(|group| group.rows.len()).sum();
+        let cells = self.layout_cells(&mut table_context, &column_starts, &column_sizes, row_count);
+        let mut row_sizes = self.row_block_sizes(&cells, spacing.block);
+
+        let mut grid_block_size = row_sizes
+            .iter()
+            .fold(Length::zero(), |sum, &size| sum + size + spacing.block);
+        if row_count > 0 {
+            grid_block_size += spacing.block;
+        }
+        // Captions after the grid are not known yet, so their block size can only be
+        // included in a definite block size by laying them out twice.
+        // FIXME: do so, or let the table wrapper box size the table grid box.
+        if let LengthOrAuto::LengthPercentage(block_size) = containing_block.block_size {
+            let extra = block_size - grid_block_start - grid_block_size;
+            if row_count > 0 && extra > Length::zero() {
+                for size in &mut row_sizes {
+                    *size += extra / row_count as f32;
+                }
+                grid_block_size += extra;
+            }
+        }
+
+        let mut row_starts = Vec::with_capacity(row_count);
+        let mut row_start = grid_block_start + spacing.block;
+        for size in &row_sizes {
+            row_starts.push(row_start);
+            row_start += *size + spacing.block;
+        }
+
+        let grid_fragments = self.make_grid_fragments(
+            &mut table_context,
+            cells,
+            &row_starts,
+            &row_sizes,
+            grid_inline_size,
+        );
+        fragments.extend(grid_fragments);
+
+        let content_block_size = self.layout_captions(
+            CaptionSide::Bottom,
+            &mut table_context,
+            &mut fragments,
+            grid_block_start + grid_block_size,
+        );
+        IndependentLayout {
+            fragments,
+            content_block_size,
+        }
+    }
+
+    /// Lay out the captions on the given side one after the other from `block_start`,
+    /// and return where they end.
+    fn layout_captions(
+        &self,
+        side: CaptionSide,
+        table_context: &mut TableContext,
+        fragments: &mut Vec<Fragment>,
+        block_start: Length,
+    ) -> Length {
+        let mut block_position = block_start;
+        for caption in &self.captions {
+            let caption = caption.borrow();
+            if caption.style.get_inherited_table().caption_side != side {
+                continue;
+            }
+            let (fragment, block_size) = layout_caption(&caption, table_context, block_position);
+            fragments.push(fragment);
+            block_position += block_size;
+        }
+        block_position
+    }
+
+    /// The border box inline size of each column, not including border spacing.
+    fn column_inline_sizes(&self, table_context: &TableContext) -> Vec<Length> {
+        if self.column_count == 0 {
+            return Vec::new();
+        }
+        let containing_block = table_context.containing_block;
+        let spacing = table_context.spacing.inline;
+        let available = containing_block.inline_size - spacing * (self.column_count + 1) as f32;
+        let style = containing_block.style;
+        match style.get_table().table_layout {
+            TableLayout::Fixed if style.box_size().inline.non_auto().is_some() => {
+                self.fixed_column_inline_sizes(containing_block, spacing, available)
+            },
+            TableLayout::Fixed | TableLayout::Auto => self.auto_column_inline_sizes(available),
+        }
+    }
+
+    /// https://drafts.csswg.org/css-tables/#fixed-table-layout
+    fn fixed_column_inline_sizes(
+        &self,
+        containing_block: &ContainingBlock,
+        spacing: Length,
+        available: Length,
+    ) -> Vec<Length> {
+        let mut sizes = vec![None; self.column_count];
+        for (size, column) in sizes.iter_mut().zip(&self.columns) {
+            *size = column
+                .style
+                .box_size()
+                .inline
+                .non_auto()
+                .map(|lp| lp.percentage_relative_to(containing_block.inline_size));
+        }
+        // Only the cells of the first row are used to size the remaining columns.
+        let first_row = self.row_groups.iter().flat_map(|group| &group.rows).next();
+        for cell in first_row.into_iter().flat_map(|row| &row.cells) {
+            let cell = cell.borrow();
+            let style = &cell.contents.style;
+            let inline_size = match style.box_size().inline.non_auto() {
+                Some(lp) => lp.percentage_relative_to(containing_block.inline_size),
+                None => continue,
+            };
+            let border_box_inline_size = match style.get_position().box_sizing {
+                BoxSizing::ContentBox => {
+                    let pbm = style.padding_border_margin(containing_block);
+                    inline_size + pbm.padding_border_sums.inline
+                },
+                BoxSizing::BorderBox => inline_size,
+            };
+            let inner_spacing = spacing * (cell.colspan - 1) as f32;
+            let per_column = (border_box_inline_size - inner_spacing) / cell.colspan as f32;
+            for size in &mut sizes[cell.column..cell.column + cell.colspan] {
+                if size.is_none() {
+                    *size = Some(per_column.max(Length::zero()))
+                }
+            }
+        }
+
+        let assigned = sizes
+            .iter()
+            .flatten()
+            .fold(Length::zero(), |sum, &size| sum + size);
+        let unassigned_count = sizes.iter().filter(|size| size.is_none()).count();
+        let remaining = (available - assigned).max(Length::zero());
+        let mut sizes: Vec<_> = sizes
+            .into_iter()
+            .map(|size| size.unwrap_or_else(|| remaining / unassigned_count as f32))
+            .collect();
+        if unassigned_count == 0 {
+            let weights = sizes.clone();
+            grow_columns(&mut sizes, &weights, available);
+        }
+        sizes
+    }
+
+    /// https://drafts.csswg.org/css-tables/#width-distribution-algorithm
+    fn auto_column_inline_sizes(&self, available: Length) -> Vec<Length> {
+        let columns = &self.column_content_sizes;
+        let mut min_content = Length::zero();
+        let mut max_content = Length::zero();
+        for column in columns {
+            min_content += column.min_content;
+            max_content += column.max_content;
+        }
+        if available >= max_content {
+            let mut sizes: Vec<_> = columns.iter().map(|column| column.max_content).collect();
+            let weights = sizes.clone();
+            grow_columns(&mut sizes, &weights, available);
+            sizes
+        } else if available > min_content {
+            let ratio = (available - min_content).px() / (max_content - min_content).px();
+            columns
+                .iter()
+                .map(|column| {
+                    column.min_content + (column.max_content - column.min_content) * ratio
+                })
+                .collect()
+        } else {
+            columns.iter().map(|column| column.min_content).collect()
+        }
+    }
+
+    fn layout_cells(
+        &self,
+        table_context: &mut TableContext,
+        column_starts: &[Length],
+        column_sizes: &[Length],
+        row_count: usize,
+    ) -> Vec<CellLayout> {
+        let containing_block = table_context.containing_block;
+        let spacing = table_context.spacing.inline;
+        let table_style = containing_block.style;
+        let collapsed_borders = match table_style.get_inherited_table().border_collapse {
+            BorderCollapse::Separate => None,
+            BorderCollapse::Collapse => Some(CollapsedBorders::new(self, row_count)),
+        };
+
+        let mut cells = Vec::new();
+        let mut first_row = 0;
+        for group in &self.row_groups {
+            for (row_index, row) in group.rows.iter().enumerate() {
+                for cell in &row.cells {
+                    let cell = cell.borrow();
+                    let columns = cell.column..cell.column + cell.colspan;
+                    let rows = first_row + row_index..first_row + row_index + cell.rowspan;
+                    let style = &cell.contents.style;
+                    let mut pbm = style.padding_border_margin(containing_block);
+                    if let Some(collapsed_borders) = &collapsed_borders {
+                        pbm.border = collapsed_borders.cell_border(
+                            &table_style.border_width(),
+                            &pbm.border,
+                            rows.clone(),
+                            columns.clone(),
+                        );
+                        pbm.padding_border_sums = Vec2 {
+                            inline: pbm.padding.inline_sum() + pbm.border.inline_sum(),
+                            block: pbm.padding.block_sum() + pbm.border.block_sum(),
+                        };
+                    }
+                    let border_box_inline_size = column_sizes[columns.clone()]
+                        .iter()
+                        .fold(spacing * (cell.colspan - 1) as f32, |sum, &size| sum + size);
+                    let inline_size = (border_box_inline_size - pbm.padding_border_sums.inline)
+                        .max(Length::zero());
+
+                    let mut cell_positioning_context =
+                        table_context.positioning_context.new_for_subtree(style);
+                    let (fragments, content_block_size) = match cell.contents.as_replaced() {
+                        Ok(replaced) => {
+                            let size = replaced.used_size_as_if_inline_element(
+                                containing_block,
+                                style,
+                                &pbm,
+                            );
+                            let size = Vec2 {
+                                inline: inline_size,
+                                block: size.block,
+                            };
+                            (replaced.make_fragments(style, size.clone()), size.block)
+                        },
+                        Err(non_replaced) => {
+                            let containing_block_for_children = ContainingBlock {
+                                inline_size,
+                                block_size: LengthOrAuto::Auto,
+                                style,
+                            };
+                            let layout = non_replaced.layout(
+                                table_context.layout_context,
+                                &mut cell_positioning_context,
+                                &containing_block_for_children,
+                                table_context.tree_rank,
+                            );
+                            (layout.fragments, layout.content_block_size)
+                        },
+                    };
+                    let block_size = style
+                        .content_box_size(containing_block, &pbm)
+                        .block
+                        .auto_is(Length::zero)
+                        .max(content_block_size);
+
+                    cells.push(CellLayout {
+                        tag: cell.contents.tag,
+                        style: style.clone(),
+                        row: first_row + row_index,
+                        rowspan: cell.rowspan,
+                        inline_start: column_starts[cell.column],
+                        inline_size,
+                        content_block_size,
+                        block_size,
+                        padding: pbm.padding,
+                        border: pbm.border,
+                        fragments,
+                        positioning_context: cell_positioning_context,
+                    });
+                }
+            }
+            first_row += group.rows.len();
+        }
+        cells
+    }
+
+    /// https://drafts.csswg.org/css-tables/#row-layout
+    fn row_block_sizes(&self, cells: &[CellLayout], spacing: Length) -> Vec<Length> {
+        let mut sizes: Vec<_> = self
+            .row_groups
+            .iter()
+            .flat_map(|group| &group.rows)
+            .map(|row| {
+                // Percentages are treated as `auto`.
+                row.style
+                    .box_size()
+                    .block
+                    .non_auto()
+                    .and_then(|lp| lp.to_length())
+                    .unwrap_or_else(Length::zero)
+            })
+            .collect();
+        for cell in cells.iter().filter(|cell| cell.rowspan == 1) {
+            sizes[cell.row].max_assign(cell.border_box_block_size());
+        }
+        // Cells that span several rows only make the last of them taller, if needed.
+        for cell in cells.iter().filter(|cell| cell.rowspan > 1) {
+            let rows = cell.row..cell.row + cell.rowspan;
+            let spanned = sizes[rows.clone()]
+                .iter()
+                .fold(spacing * (cell.rowspan - 1) as f32, |sum, &size| sum + size);
+            let excess = cell.border_box_block_size() - spanned;
+            if excess > Length::zero() {
+                sizes[rows.end - 1] += excess;
+            }
+        }
+        sizes
+    }
+
+    fn make_grid_fragments(
+        &self,
+        table_context: &mut TableContext,
+        cells: Vec<CellLayout>,
+        row_starts: &[Length],
+        row_sizes: &[Length],
+        grid_inline_size: Length,
+    ) -> Vec<Fragment> {
+        let spacing = table_context.spacing.block;
+        let mut cells = cells.into_iter().peekable();
+        let mut fragments = Vec::new();
+        let mut first_row = 0;
+        for group in &self.row_groups {
+            let rows = first_row..first_row + group.rows.len();
+            first_row = rows.end;
+            if rows.start == rows.end {
+                continue;
+            }
+            let group_start = row_starts[rows.start];
+            let group_end = row_starts[rows.end - 1] + row_sizes[rows.end - 1];
+
+            let mut row_fragments = Vec::new();
+            for (index, row) in rows.zip(&group.rows) {
+                let mut cell_fragments = Vec::new();
+                while cells.peek().map_or(false, |cell| cell.row == index) {
+                    let cell = cells.next().unwrap();
+                    let spanned_rows = index..index + cell.rowspan;
+                    let block_size = row_sizes[spanned_rows.clone()]
+                        .iter()
+                        .fold(spacing * (cell.rowspan - 1) as f32, |sum, &size| sum + size);
+                    let row_origin = Vec2 {
+                        inline: Length::zero(),
+                        block: row_starts[index],
+                    };
+                    cell_fragments.push(cell.make_fragment(table_context, block_size, &row_origin));
+                }
+                let rect = Rect {
+                    start_corner: Vec2 {
+                        inline: Length::zero(),
+                        block: row_starts[index] - group_start,
+                    },
+                    size: Vec2 {
+                        inline: grid_inline_size,
+                        block: row_sizes[index],
+                    },
+                };
+                row_fragments.push(Fragment::Box(BoxFragment::new(
+                    row.tag,
+                    row.style.clone(),
+                    cell_fragments,
+                    rect,
+                    Sides::zero(),
+                    Sides::zero(),
+                    Sides::zero(),
+                    CollapsedBlockMargins::zero(),
+                )));
+            }
+
+            let rect = Rect {
+                start_corner: Vec2 {
+                    inline: Length::zero(),
+                    block: group_start,
+                },
+                size: Vec2 {
+                    inline: grid_inline_size,
+                    block: group_end - group_start,
+                },
+            };
+            fragments.push(match (group.tag, &group.style) {
+                (Some(tag), Some(style)) => Fragment::Box(BoxFragment::new(
+                    tag,
+                    style.clone(),
+                    row_fragments,
+                    rect,
+                    Sides::zero(),
+                    Sides::zero(),
+                    Sides::zero(),
+                    CollapsedBlockMargins::zero(),
+                )),
+                _ => Fragment::Anonymous(AnonymousFragment::new(
+                    rect,
+                    row_fragments,
+                    table_context.containing_block.style.writing_mode,
+                )),
+            });
+        }
+        fragments
+    }
+}
+
+/// A cell that was laid out at the inline size of its columns,
+/// but whose rows are not sized yet.
+struct CellLayout {
+    tag: OpaqueNode,
+    style: Arc<ComputedValues>,
+    /// The first row of the cell, counting rows of all row groups
+    row: usize,
+    rowspan: usize,
+    /// The start of the border box of the cell, relative to the table
+    inline_start: Length,
+    /// The inline size of the content box
+    inline_size: Length,
+    /// The block size of the contents of the cell
+    content_block_size: Length,
+    /// The block size of the content box, before it is stretched to its rows
+    block_size: Length,
+    padding: Sides<Length>,
+    border: Sides<Length>,
+    fragments: Vec<Fragment>,
+    positioning_context: PositioningContext,
+}
+
+impl CellLayout {
+    fn border_box_block_size(&self) -> Length {
+        self.block_size + self.padding.block_sum() + self.border.block_sum()
+    }
+
+    /// Make a fragment for the cell relative to its row, given the block size
+    /// of the border box that spans its rows.
+    fn make_fragment(
+        mut self,
+        table_context: &mut TableContext,
+        border_box_block_size: Length,
+        row_origin: &Vec2<Length>,
+    ) -> Fragment {
+        let block_size = border_box_block_size - self.padding.block_sum() - self.border.block_sum();
+        // https://drafts.csswg.org/css2/tables.html#height-layout
+        // FIXME: align the baselines of cells with `vertical-align: baseline`,
+        // they are aligned to the top for now.
+        let free_space = (block_size - self.content_block_size).max(Length::zero());
+        let offset = match self.style.get_box().vertical_align {
+            VerticalAlign::Keyword(VerticalAlignKeyword::Middle) => free_space / 2.,
+            VerticalAlign::Keyword(VerticalAlignKeyword::Bottom) => free_space,
+            _ => Length::zero(),
+        };
+        let mut children = self.fragments;
+        if offset != Length::zero() {
+            let rect = Rect {
+                start_corner: Vec2 {
+                    inline: Length::zero(),
+                    block: offset,
+                },
+                size: Vec2 {
+                    inline: self.inline_size,
+                    block: self.content_block_size,
+                },
+            };
+            children = vec![Fragment::Anonymous(AnonymousFragment::new(
+                rect,
+                children,
+                self.style.writing_mode,
+            ))];
+            self.positioning_context.translate_static_positions(&Vec2 {
+                inline: Length::zero(),
+                block: offset,
+            });
+        }
+
+        let content_rect = Rect {
+            start_corner: Vec2 {
+                inline: self.inline_start + self.border.inline_start + self.padding.inline_start,
+                block: self.border.block_start + self.padding.block_start,
+            },
+            size: Vec2 {
+                inline: self.inline_size,
+                block: block_size,
+            },
+        };
+        let mut fragment = BoxFragment::new(
+            self.tag,
+            self.style,
+            children,
+            content_rect,
+            self.padding,
+            self.border,
+            Sides::zero(),
+            CollapsedBlockMargins::zero(),
+        );
+        append_positioning_context(
+            table_context,
+            self.positioning_context,
+            &mut fragment,
+            row_origin,
+        );
+        Fragment::Box(fragment)
+    }
+}
+
+/// Lay out a caption at the given block position, and return its fragment
+/// together with the block size of its margin box.
+fn layout_caption(
+    caption: &IndependentFormattingContext,
+    table_context: &mut TableContext,
+    block_start: Length,
+) -> (Fragment, Length) {
+    let containing_block = table_context.containing_block;
+    let style = &caption.style;
+    let pbm = style.padding_border_margin(containing_block);
+    let margin = pbm.margin.auto_is(Length::zero);
+    let box_size = style.content_box_size(containing_block, &pbm);
+    let inline_size = box_size.inline.auto_is(|| {
+        (containing_block.inline_size - pbm.padding_border_sums.inline - margin.inline_sum())
+            .max(Length::zero())
+    });
+
+    let mut caption_positioning_context = table_context.positioning_context.new_for_subtree(style);
+    let (children, size) = match caption.as_replaced() {
+        Ok(replaced) => {
+            let size = replaced.used_size_as_if_inline_element(containing_block, style, &pbm);
+            (replaced.make_fragments(style, size.clone()), size)
+        },
+        Err(non_replaced) => {
+            let containing_block_for_children = ContainingBlock {
+                inline_size,
+                block_size: box_size.block,
+                style,
+            };
+            let layout = non_replaced.layout(
+                table_context.layout_context,
+                &mut caption_positioning_context,
+                &containing_block_for_children,
+                table_context.tree_rank,
+            );
+            let size = Vec2 {
+                inline: inline_size,
+                block: box_size.block.auto_is(|| layout.content_block_size),
+            };
+            (layout.fragments, size)
+        },
+    };
+
+    let margin_box_block_size = margin.block_sum() + pbm.padding_border_sums.block + size.block;
+    let content_rect = Rect {
+        start_corner: Vec2 {
+            inline: margin.inline_start + pbm.border.inline_start + pbm.padding.inline_start,
+            block: block_start +
+                margin.block_start +
+                pbm.border.block_start +
+                pbm.padding.block_start,
+        },
+        size,
+    };
+    let mut fragment = BoxFragment::new(
+        caption.tag,
+        style.clone(),
+        children,
+        content_rect,
+        pbm.padding,
+        pbm.border,
+        margin,
+        CollapsedBlockMargins::zero(),
+    );
+    append_positioning_context(
+        table_context,
+        caption_positioning_context,
+        &mut fragment,
+        &Vec2::zero(),
+    );
+    (Fragment::Box(fragment), margin_box_block_size)
+}
+
+/// Append the boxes that were hoisted while laying out `fragment`, which is nested
+/// in anonymous or table-internal fragments starting at `origin`.
+///
+/// The static positions of hoisted boxes are relative to the content box of the fragment
+/// that they were found in, but the fragments of a table are nested deeper than
+/// `adjust_static_positions` expects, so they are made relative to the table here.
+fn append_positioning_context(
+    table_context: &mut TableContext,
+    child_positioning_context: PositioningContext,
+    fragment: &mut BoxFragment,
+    origin: &Vec2<Length>,
+) {
+    let positioning_context = &mut table_context.positioning_context;
+    let mut for_fragment = PositioningContext::new_for_rayon(
+        positioning_context.collects_for_nearest_positioned_ancestor(),
+    );
+    for_fragment.append_laid_out_box(
+        table_context.layout_context,
+        table_context.containing_block,
+        child_positioning_context,
+        fragment,
+    );
+    for_fragment.translate_static_positions(&(origin + &fragment.content_rect.start_corner));
+    positioning_context.append(for_fragment);
+}
+
+/// The border spacing in each axis, or zero in the collapsing border model.
+fn border_spacing(style: &ComputedValues) -> Vec2<Length> {
+    let inherited_table = style.get_inherited_table();
+    match inherited_table.border_collapse {
+        BorderCollapse::Collapse => Vec2::zero(),
+        BorderCollapse::Separate => {
+            let horizontal = Length::from(inherited_table.border_spacing.horizontal());
+            let vertical = Length::from(inherited_table.border_spacing.vertical());
+            if style.writing_mode.is_horizontal() {
+                Vec2 {
+                    inline: horizontal,
+                    block: vertical,
+                }
+            } else {
+                Vec2 {
+                    inline: vertical,
+                    block: horizontal,
+                }
+            }
+        },
+    }
+}
+
+/// The min-content and max-content inline sizes of the border box of a cell.
+/// https://drafts.csswg.org/css-tables/#outer-min-content
+fn cell_content_sizes(contents: &IndependentFormattingContext) -> ContentSizes {
+    let style = &contents.style;
+    let content_sizes = contents.content_sizes.expect_inline();
+    // Percentages of padding are treated as zero.
+    let padding = style.padding().percentages_relative_to(Length::zero());
+    let padding_border = padding.inline_sum() + style.border_width().inline_sum();
+    let min_content = content_sizes.min_content + padding_border;
+    // Percentages are treated as `auto`.
+    let inline_size = style.box_size().inline.non_auto();
+    let max_content = match inline_size.and_then(|lp| lp.to_length()) {
+        Some(inline_size) => match style.get_position().box_sizing {
+            BoxSizing::ContentBox => inline_size + padding_border,
+            BoxSizing::BorderBox => inline_size,
+        },
+        None => content_sizes.max_content + padding_border,
+    };
+    ContentSizes {
+        min_content,
+        max_content: max_content.max(min_content),
+    }
+}
+
+/// Grow `sizes` so that they add up to at least `total`, in proportion to `weights`,
+/// or equally if all weights are zero.
+fn grow_columns(sizes: &mut [Length], weights: &[Length], total: Length) {
+    let sum = |lengths: &[Length]| {
+        lengths
+            .iter()
+            .fold(Length::zero(), |sum, &length| sum + length)
+    };
+    let excess = total - sum(sizes);
+    if sizes.is_empty() || excess <= Length::zero() {
+        return;
+    }
+    let weights_sum = sum(weights);
+    let count = sizes.len();
+    for (size, weight) in sizes.iter_mut().zip(weights) {
+        *size += if weights_sum > Length::zero() {
+            excess * (weight.px() / weights_sum.px())
+        } else {
+            excess / count as f32
+        };
+    }
+}
+
+/// The border widths of the cells in each slot of the grid,
+/// to resolve the borders that cells share in the collapsing border model:
+/// https://drafts.csswg.org/css-tables/#border-collapse-property
+///
+/// FIXME: rows, row groups, columns and column groups also take part in border conflict
+/// resolution, as do `border-style` and `border-color`. Only widths are resolved here,
+/// and each edge is only compared with the neighbor of the first slot of the cell.
+struct CollapsedBorders {
+    slots: Vec<Vec<Option<Sides<Length>>>>,
+}
+
+impl CollapsedBorders {
+    fn new(table: &Table, row_count: usize) -> Self {
+        let mut slots = vec![vec![None; table.column_count]; row_count];
+        let mut first_row = 0;
+        for group in &table.row_groups {
+            for (row_index, row) in group.rows.iter().enumerate() {
+                for cell in &row.cells {
+                    let cell = cell.borrow();
+                    let border = cell.contents.style.border_width();
+                    let rows = first_row + row_index..first_row + row_index + cell.rowspan;
+                    for row_slots in &mut slots[rows] {
+                        for slot in &mut row_slots[cell.column..cell.column + cell.colspan] {
+                            *slot = Some(border.clone());
+                        }
+                    }
+                }
+            }
+            first_row += group.rows.len();
+        }
+        Self { slots }
+    }
+
+    fn neighbor(&self, row: usize, column: usize) -> Option<&Sides<Length>> {
+        self.slots.get(row)?.get(column)?.as_ref()
+    }
+
+    /// The part of the resolved borders that a cell paints. Borders between cells
+    /// are split between them, and borders on the edges of the table are painted
+    /// by the table as far as its own border is wide enough.
+    fn cell_border(
+        &self,
+        table_border: &Sides<Length>,
+        own: &Sides<Length>,
+        rows: std::ops::Range<usize>,
+        columns: std::ops::Range<usize>,
+    ) -> Sides<Length> {
+        let outer = |own: Length, table: Length| (own - table).max(Length::zero());
+        let inner = |own: Length, neighbor: Option<Length>| {
+            own.max(neighbor.unwrap_or_else(Length::zero)) / 2.
+        };
+        let column_count = self.slots.first().map_or(0, |row| row.len());
+        Sides {
+            inline_start: if columns.start == 0 {
+                outer(own.inline_start, table_border.inline_start)
+            } else {
+                let neighbor = self.neighbor(rows.start, columns.start - 1);
+                inner(own.inline_start, neighbor.map(|n| n.inline_end))
+            },
+            inline_end: if columns.end == column_count {
+                outer(own.inline_end, table_border.inline_end)
+            } else {
+                let neighbor = self.neighbor(rows.start, columns.end);
+                inner(own.inline_end, neighbor.map(|n| n.inline_start))
+            },
+            block_start: if rows.start == 0 {
+                outer(own.block_start, table_border.block_start)
+            } else {
+                let neighbor = self.neighbor(rows.start - 1, columns.start);
+                inner(own.block_start, neighbor.map(|n| n.block_end))
+            },
+            block_end: if rows.end == self.slots.len() {
+                outer(own.block_end, table_border.block_end)
+            } else {
+                let neighbor = self.neighbor(rows.end, columns.start);
+                inner(own.block_end, neighbor.map(|n| n.block_start))
+            },
+        }
+    }
+}
@@ -0,0 +1,88 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Table layout: https://drafts.csswg.org/css-tables/
+
+use crate::cell::ArcRefCell;
+use crate::formatting_contexts::IndependentFormattingContext;
+use crate::sizing::ContentSizes;
+use servo_arc::Arc;
+use style::dom::OpaqueNode;
+use style::properties::ComputedValues;
+
+mod construct;
+mod layout;
+
+/// A table grid box together with its captions,
+/// which CSS wraps in a table wrapper box.
+#[derive(Debug, Serialize)]
+pub(crate) struct Table {
+    captions: Vec<ArcRefCell<IndependentFormattingContext>>,
+
+    /// Columns generated by `table-column` and `table-column-group` boxes.
+    /// There may be more columns in the grid, generated by cells.
+    columns: Vec<TableColumn>,
+
+    /// The header group first, then body groups and rows that are not in any group
+    /// in document order, then the footer group.
+    row_groups: Vec<TableRowGroup>,
+
+    /// The number of columns in the grid
+    column_count: usize,
+
+    /// The min-content and max-content inline size of each column of the grid,
+    /// not including border spacing.
+    column_content_sizes: Vec<ContentSizes>,
+
+    /// The min-content and max-content inline size of the content box of the table
+    content_sizes: ContentSizes,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct TableColumn {
+    #[serde(skip_serializing)]
+    style: Arc<ComputedValues>,
+}
+
+/// Consecutive rows, either from a row group box
+/// or from rows that are direct children of the table.
+#[derive(Debug, Serialize)]
+pub(crate) struct TableRowGroup {
+    /// `None` for rows that are not in a row group box
+    tag: Option<OpaqueNode>,
+    #[serde(skip_serializing)]
+    style: Option<Arc<ComputedValues>>,
+    rows: Vec<TableRow>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct TableRow {
+    tag: OpaqueNode,
+    #[serde(skip_serializing)]
+    style: Arc<ComputedValues>,
+    cells: Vec<ArcRefCell<TableCell>>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct TableCell {
+    contents: IndependentFormattingContext,
+
+    /// The index of the first column that this cell spans
+    column: usize,
+
+    /// The number of columns that this cell spans
+    colspan: usize,
+
+    /// The number of rows that this cell spans, within its row group
+    rowspan: usize,
+}
+
+/// Table parts that can be found by the box slot of their element.
+pub(crate) enum TableLevelBox {
+    Caption(ArcRefCell<IndependentFormattingContext>),
+    Cell(ArcRefCell<TableCell>),
+    /// Rows, row groups, columns and column groups, which only exist as part of their table,
+    /// and elements that table fixup discards.
+    Internal,
+}
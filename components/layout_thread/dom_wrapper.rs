@@ -767,6 +767,17 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
         None
     }
 
+    fn first_element_child(&self) -> Option<ServoLayoutElement<'le>> {
+        let mut child = self.as_node().first_child();
+        while let Some(node) = child {
+            if let Some(element) = node.as_element() {
+                return Some(element);
+            }
+            child = node.next_sibling();
+        }
+        None
+    }
+
     fn attr_matches(
         &self,
         ns: &NamespaceConstraint<&Namespace>,
@@ -1355,6 +1366,12 @@ impl<'le> ::selectors::Element for ServoThreadSafeLayoutElement<'le> {
         None
     }
 
+    // Skips non-element nodes
+    fn first_element_child(&self) -> Option<Self> {
+        warn!("ServoThreadSafeLayoutElement::first_element_child called");
+        None
+    }
+
     fn is_html_slot_element(&self) -> bool {
         self.element.is_html_slot_element()
     }
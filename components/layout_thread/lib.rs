@@ -42,8 +42,8 @@ use layout::context::malloc_size_of_persistent_local_context;
 use layout::context::LayoutContext;
 use layout::context::RegisteredPainter;
 use layout::context::RegisteredPainters;
-use layout::display_list::items::{OpaqueNode, WebRenderImageInfo};
-use layout::display_list::{IndexableText, ToLayout};
+use layout::display_list::items::{DisplayList, OpaqueNode, WebRenderImageInfo};
+use layout::display_list::{print_to_pdf, IndexableText, ToLayout};
 use layout::flow::{Flow, GetBaseFlow, ImmutableFlowUtils, MutableOwnedFlowUtils};
 use layout::flow_ref::FlowRef;
 use layout::incremental::{RelayoutMode, SpecialRestyleDamage};
@@ -82,7 +82,7 @@ use script_layout_interface::rpc::{LayoutRPC, OffsetParentResponse};
 use script_layout_interface::wrapper_traits::LayoutNode;
 use script_traits::{ConstellationControlMsg, LayoutControlMsg, LayoutMsg as ConstellationMsg};
 use script_traits::{DrawAPaintImageResult, IFrameSizeMsg, PaintWorkletError, WindowSizeType};
-use script_traits::{Painter, WebrenderIpcSender};
+use script_traits::{Painter, PrintError, PrintSettings, WebrenderIpcSender};
 use script_traits::{ScrollState, UntrustedNodeAddress, WindowSizeData};
 use servo_arc::Arc as ServoArc;
 use servo_atoms::Atom;
@@ -104,6 +104,7 @@ use style::dom::{ShowSubtree, ShowSubtreeDataAndPrimaryValues, TDocument, TEleme
 use style::driver;
 use style::error_reporting::RustLogReporter;
 use style::global_style_data::{GLOBAL_STYLE_DATA, STYLE_THREAD_POOL};
+use style::invalidation::element::relative_selector::invalidate_relative_selector_anchors;
 use style::invalidation::element::restyle_hints::RestyleHint;
 use style::logical_geometry::LogicalPoint;
 use style::media_queries::{Device, MediaList, MediaType};
@@ -559,6 +560,7 @@ impl LayoutThread {
                 constellation_chan: constellation_chan,
                 display_list: None,
                 indexable_text: IndexableText::default(),
+                images: FnvHashMap::default(),
                 content_box_response: None,
                 content_boxes_response: Vec::new(),
                 client_rect_response: Rect::zero(),
@@ -652,7 +654,9 @@ impl LayoutThread {
                 LayoutHangAnnotation::UpdateScrollStateFromScript
             },
             Msg::RegisterPaint(..) => LayoutHangAnnotation::RegisterPaint,
+            Msg::RegisterProperty(..) => LayoutHangAnnotation::RegisterProperty,
             Msg::SetNavigationStart(..) => LayoutHangAnnotation::SetNavigationStart,
+            Msg::Print(..) => LayoutHangAnnotation::Print,
         };
         self.background_hang_monitor
             .as_ref()
@@ -806,6 +810,9 @@ impl LayoutThread {
                 };
                 self.registered_painters.0.insert(name, registered_painter);
             },
+            Msg::RegisterProperty(registration) => {
+                self.stylist.register_custom_property(registration);
+            },
             Msg::PrepareToExit(response_chan) => {
                 self.prepare_to_exit(response_chan);
                 return false;
@@ -819,11 +826,44 @@ impl LayoutThread {
             Msg::SetNavigationStart(time) => {
                 self.paint_time_metrics.set_navigation_start(time);
             },
+            Msg::Print(settings, sender) => {
+                let rw_data = possibly_locked_rw_data.lock();
+                let _ = sender.send(self.print(&settings, &*rw_data));
+            },
         }
 
         true
     }
 
+    /// Paints the current display list into a PDF document.
+    fn print(
+        &self,
+        settings: &PrintSettings,
+        rw_data: &LayoutThreadData,
+    ) -> Result<Vec<u8>, PrintError> {
+        // The display list only refers to background images by their WebRender image keys,
+        // so look up the decoded images of the URLs that layout has seen.
+        let mut images = rw_data.images.clone();
+        let origin = self.url.origin();
+        for &(ref url, _) in self.webrender_image_cache.read().keys() {
+            if let Some(image) = self
+                .image_cache
+                .get_image(url.clone(), origin.clone(), None)
+            {
+                if let Some(key) = image.id {
+                    images.insert(key, image);
+                }
+            }
+        }
+
+        let empty_display_list = DisplayList {
+            list: vec![],
+            clip_scroll_nodes: vec![],
+        };
+        let display_list = rw_data.display_list.as_ref().unwrap_or(&empty_display_list);
+        print_to_pdf(display_list, &rw_data.indexable_text, &images, settings)
+    }
+
     fn collect_reports<'a, 'b>(
         &self,
         reports_chan: ReportsChan,
@@ -1116,6 +1156,8 @@ impl LayoutThread {
                             &mut build_state.indexable_text,
                             IndexableText::default(),
                         );
+                        rw_data.images =
+                            std::mem::replace(&mut build_state.images, FnvHashMap::default());
                         rw_data.display_list = Some(build_state.to_display_list());
                     }
                 }
@@ -1421,6 +1463,15 @@ impl LayoutThread {
                 .unwrap()
         };
 
+        // The anchors of :has() selectors that the restyled elements may match
+        // can be outside of the dirty subtree, so invalidate them beforehand.
+        let dirty_root = invalidate_relative_selector_anchors(
+            &elements_with_snapshot,
+            dirty_root,
+            &self.stylist,
+            &map,
+        );
+
         let traversal = RecalcStyleAndConstructFlows::new(layout_context);
         let token = {
             let shared =
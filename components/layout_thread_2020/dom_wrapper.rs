@@ -33,6 +33,7 @@
 use atomic_refcell::{AtomicRef, AtomicRefCell, AtomicRefMut};
 use gfx_traits::ByteIndex;
 use html5ever::{LocalName, Namespace};
+use layout::container_queries;
 use layout::element_data::LayoutDataForElement;
 use layout::wrapper::GetStyleAndLayoutData;
 use msg::constellation_msg::{BrowsingContextId, PipelineId};
@@ -88,6 +89,7 @@ use style::shared_lock::{
     Locked as StyleLocked, SharedRwLock as StyleSharedRwLock, SharedRwLockReadGuard,
 };
 use style::str::is_whitespace;
+use style::stylesheets::container_rule::ContainerSize;
 use style::stylist::CascadeData;
 use style::CaseSensitivityExt;
 
@@ -641,6 +643,18 @@ impl<'le> TElement for ServoLayoutElement<'le> {
         false
     }
 
+    fn query_container_size(&self, name: Option<&Atom>) -> Option<ContainerSize> {
+        let mut current = self.traversal_parent();
+        while let Some(ancestor) = current {
+            let size = container_queries::query_container_size(ancestor.as_node(), name);
+            if size.is_some() {
+                return size;
+            }
+            current = ancestor.traversal_parent();
+        }
+        None
+    }
+
     fn synthesize_presentational_hints_for_legacy_attributes<V>(
         &self,
         _visited_handling: VisitedHandlingMode,
@@ -775,6 +789,17 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
         None
     }
 
+    fn first_element_child(&self) -> Option<ServoLayoutElement<'le>> {
+        let mut child = self.as_node().first_child();
+        while let Some(node) = child {
+            if let Some(element) = node.as_element() {
+                return Some(element);
+            }
+            child = node.next_sibling();
+        }
+        None
+    }
+
     fn attr_matches(
         &self,
         ns: &NamespaceConstraint<&Namespace>,
@@ -1366,6 +1391,12 @@ impl<'le> ::selectors::Element for ServoThreadSafeLayoutElement<'le> {
         None
     }
 
+    // Skips non-element nodes
+    fn first_element_child(&self) -> Option<Self> {
+        warn!("ServoThreadSafeLayoutElement::first_element_child called");
+        None
+    }
+
     fn is_html_slot_element(&self) -> bool {
         self.element.is_html_slot_element()
     }
@@ -33,6 +33,7 @@ use gfx::font_context;
 use gfx_traits::{node_id_from_scroll_id, Epoch};
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use ipc_channel::router::ROUTER;
+use layout::container_queries;
 use layout::context::LayoutContext;
 use layout::display_list::{DisplayListBuilder, WebRenderImageInfo};
 use layout::layout_debug;
@@ -66,7 +67,7 @@ use script_layout_interface::rpc::TextIndexResponse;
 use script_layout_interface::rpc::{LayoutRPC, OffsetParentResponse};
 use script_traits::{ConstellationControlMsg, LayoutControlMsg, LayoutMsg as ConstellationMsg};
 use script_traits::{DrawAPaintImageResult, PaintWorkletError};
-use script_traits::{Painter, WebrenderIpcSender};
+use script_traits::{Painter, PrintError, WebrenderIpcSender};
 use script_traits::{ScrollState, UntrustedNodeAddress, WindowSizeData};
 use servo_arc::Arc as ServoArc;
 use servo_atoms::Atom;
@@ -89,6 +90,8 @@ use style::dom::{TDocument, TElement, TNode};
 use style::driver;
 use style::error_reporting::RustLogReporter;
 use style::global_style_data::{GLOBAL_STYLE_DATA, STYLE_THREAD_POOL};
+use style::invalidation::element::relative_selector::invalidate_relative_selector_anchors;
+use style::invalidation::element::restyle_hints::RestyleHint;
 use style::media_queries::{Device, MediaList, MediaType};
 use style::properties::PropertyId;
 use style::selector_parser::SnapshotMap;
@@ -104,6 +107,12 @@ use style_traits::CSSPixel;
 use style_traits::DevicePixel;
 use style_traits::SpeculativePainter;
 
+/// The maximum number of times a reflow lays out the document. Each pass after
+/// the first one restyles query containers one level of nesting deeper, but the
+/// size of some containers, like tables, can still depend on their contents, so
+/// it could keep changing between layouts.
+const MAX_LAYOUT_PASSES: usize = 8;
+
 /// Information needed by the layout thread.
 pub struct LayoutThread {
     /// The ID of the pipeline that we belong to.
@@ -221,6 +230,9 @@ pub struct LayoutThread {
     /// Emits notifications when there is a relayout.
     relayout_event: bool,
 
+    /// True to turn off incremental layout.
+    nonincremental_layout: bool,
+
     /// True if each step of layout is traced to an external JSON file
     /// for debugging purposes.
     trace_layout: bool,
@@ -255,7 +267,7 @@ impl LayoutThreadFactory for LayoutThread {
         dump_style_tree: bool,
         dump_rule_tree: bool,
         relayout_event: bool,
-        _nonincremental_layout: bool,
+        nonincremental_layout: bool,
         trace_layout: bool,
         dump_flow_tree: bool,
     ) {
@@ -300,6 +312,7 @@ impl LayoutThreadFactory for LayoutThread {
                         load_webfonts_synchronously,
                         window_size,
                         relayout_event,
+                        nonincremental_layout,
                         dump_display_list,
                         dump_display_list_json,
                         dump_style_tree,
@@ -469,6 +482,7 @@ impl LayoutThread {
         load_webfonts_synchronously: bool,
         window_size: WindowSizeData,
         relayout_event: bool,
+        nonincremental_layout: bool,
         dump_display_list: bool,
         dump_display_list_json: bool,
         dump_style_tree: bool,
@@ -544,6 +558,7 @@ impl LayoutThread {
             busy,
             load_webfonts_synchronously,
             relayout_event,
+            nonincremental_layout,
             dump_display_list,
             dump_display_list_json,
             dump_style_tree,
@@ -594,6 +609,8 @@ impl LayoutThread {
             webrender_image_cache: self.webrender_image_cache.clone(),
             pending_images: Mutex::new(vec![]),
             use_rayon: STYLE_THREAD_POOL.pool().is_some(),
+            incremental_layout: !self.nonincremental_layout,
+            box_construction_statistics: Default::default(),
         }
     }
 
@@ -616,7 +633,9 @@ impl LayoutThread {
                 LayoutHangAnnotation::UpdateScrollStateFromScript
             },
             Msg::RegisterPaint(..) => LayoutHangAnnotation::RegisterPaint,
+            Msg::RegisterProperty(..) => LayoutHangAnnotation::RegisterProperty,
             Msg::SetNavigationStart(..) => LayoutHangAnnotation::SetNavigationStart,
+            Msg::Print(..) => LayoutHangAnnotation::Print,
         };
         self.background_hang_monitor
             .as_ref()
@@ -754,6 +773,9 @@ impl LayoutThread {
                 self.url = final_url;
             },
             Msg::RegisterPaint(_name, _properties, _painter) => {},
+            Msg::RegisterProperty(registration) => {
+                self.stylist.register_custom_property(registration);
+            },
             Msg::PrepareToExit(response_chan) => {
                 self.prepare_to_exit(response_chan);
                 return false;
@@ -767,6 +789,9 @@ impl LayoutThread {
             Msg::SetNavigationStart(time) => {
                 self.paint_time_metrics.set_navigation_start(time);
             },
+            Msg::Print(_settings, sender) => {
+                let _ = sender.send(Err(PrintError::Unsupported));
+            },
         }
 
         true
@@ -827,7 +852,7 @@ impl LayoutThread {
             self.dump_style_tree,
             self.dump_rule_tree,
             self.relayout_event,
-            true,                // nonincremental_layout
+            self.nonincremental_layout,
             self.trace_layout,   // trace_layout
             self.dump_flow_tree, // dump_flow_tree
         );
@@ -1072,47 +1097,108 @@ impl LayoutThread {
                 .unwrap()
         };
 
-        let traversal = RecalcStyle::new(layout_context);
-        let token = {
-            let shared = DomTraversal::<ServoLayoutElement>::shared_context(&traversal);
-            RecalcStyle::pre_traverse(dirty_root, shared)
-        };
+        // The anchors of :has() selectors that the restyled elements may match
+        // can be outside of the dirty subtree, so invalidate them beforehand.
+        let dirty_root = invalidate_relative_selector_anchors(
+            &elements_with_snapshot,
+            dirty_root,
+            &self.stylist,
+            &map,
+        );
 
         let rayon_pool = STYLE_THREAD_POOL.pool();
         let rayon_pool = rayon_pool.as_ref();
 
-        let box_tree = if token.should_traverse() {
-            driver::traverse_dom(&traversal, token, rayon_pool);
-
-            let root_node = root_element.as_node();
-            let build_box_tree = || BoxTree::construct(traversal.context(), root_node);
-            let box_tree = if let Some(pool) = rayon_pool {
-                pool.install(build_box_tree)
+        // Restyles the dirty elements under the given root, then builds the box tree,
+        // reusing the boxes of the elements that restyling did not damage.
+        let restyle_and_construct_box_tree = |layout_context, dirty_root| {
+            let traversal = RecalcStyle::new(layout_context);
+            let token = {
+                let shared = DomTraversal::<ServoLayoutElement>::shared_context(&traversal);
+                RecalcStyle::pre_traverse(dirty_root, shared)
+            };
+            let box_tree = if token.should_traverse() {
+                driver::traverse_dom(&traversal, token, rayon_pool);
+
+                let root_node = root_element.as_node();
+                let build_box_tree = || BoxTree::construct(traversal.context(), root_node);
+                let box_tree = if let Some(pool) = rayon_pool {
+                    pool.install(build_box_tree)
+                } else {
+                    build_box_tree()
+                };
+
+                Some(Arc::new(box_tree))
             } else {
-                build_box_tree()
+                None
             };
-
-            Some(Arc::new(box_tree))
-        } else {
-            None
+            (box_tree, traversal.destroy())
         };
 
-        layout_context = traversal.destroy();
+        let (box_tree, new_layout_context) =
+            restyle_and_construct_box_tree(layout_context, dirty_root);
+        layout_context = new_layout_context;
 
-        if let Some(box_tree) = box_tree {
+        if let Some(mut box_tree) = box_tree {
             let viewport_size = Size2D::new(
                 self.viewport_size.width.to_f32_px(),
                 self.viewport_size.height.to_f32_px(),
             );
-            let run_layout = || box_tree.layout(&layout_context, viewport_size);
-            let fragment_tree = Arc::new(if let Some(pool) = rayon_pool {
-                pool.install(run_layout)
-            } else {
-                run_layout()
-            });
+            let mut restyled_containers = None;
+            let mut layout_passes = 0;
+            let fragment_tree = loop {
+                let run_layout = || box_tree.layout(&layout_context, viewport_size);
+                let fragment_tree = Arc::new(if let Some(pool) = rayon_pool {
+                    pool.install(run_layout)
+                } else {
+                    run_layout()
+                });
+                layout_passes += 1;
+
+                // Styles that depend on the size of query containers were computed
+                // with their size in the previous layout, so the descendants of the
+                // containers whose size changed are restyled and laid out again.
+                // Containment keeps the size of a container from depending on its
+                // contents, so only containers nested in the restyled ones can change
+                // size after that: a document without nested containers is restyled
+                // once, and each pass goes one level of nesting deeper. Containers
+                // excluded from containment could change size on every pass, hence
+                // the limit.
+                let changed_containers = container_queries::update_query_containers(
+                    root_element.as_node(),
+                    &fragment_tree,
+                    restyled_containers.as_ref().map(Vec::as_slice),
+                );
+                if changed_containers.is_empty() || layout_passes == MAX_LAYOUT_PASSES {
+                    break fragment_tree;
+                }
+                for container in &changed_containers {
+                    let container = container.as_element().unwrap();
+                    if let Some(mut style_data) = container.mutate_data() {
+                        style_data.hint.insert(RestyleHint::RESTYLE_DESCENDANTS);
+                    }
+                    let mut next = container.traversal_parent();
+                    while let Some(ancestor) = next {
+                        unsafe { ancestor.set_dirty_descendants() };
+                        next = ancestor.traversal_parent();
+                    }
+                }
+                restyled_containers = Some(changed_containers);
+
+                let (new_box_tree, new_layout_context) =
+                    restyle_and_construct_box_tree(layout_context, root_element);
+                layout_context = new_layout_context;
+                if let Some(new_box_tree) = new_box_tree {
+                    box_tree = new_box_tree;
+                }
+            };
             *self.box_tree.borrow_mut() = Some(box_tree);
             *self.fragment_tree.borrow_mut() = Some(fragment_tree);
         }
+        // Reported even when nothing was restyled, so that it always counts the boxes
+        // rebuilt by this reflow rather than by the last one that built a box tree.
+        data.result.borrow_mut().as_mut().unwrap().rebuilt_box_count =
+            Some(layout_context.box_construction_statistics.rebuilt_boxes());
 
         for element in elements_with_snapshot {
             unsafe { element.unset_snapshot_flags() }
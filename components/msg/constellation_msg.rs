@@ -519,7 +519,9 @@ pub enum LayoutHangAnnotation {
     SetScrollStates,
     UpdateScrollStateFromScript,
     RegisterPaint,
+    RegisterProperty,
     SetNavigationStart,
+    Print,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
@@ -703,9 +705,24 @@ pub trait BackgroundHangMonitor {
 }
 
 /// Messages to control the sampling profiler.
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub enum SamplerControlMsg {
     /// Enable the sampler, with a given sampling rate and max total sampling duration.
     Enable(Duration, Duration),
     Disable,
+    /// Stream the samples taken at a given sampling rate on the given channel, as they
+    /// are taken, until streaming is stopped.
+    StartStreaming(Duration, IpcSender<SampledStack>),
+    StopStreaming,
+}
+
+/// The native stack of a monitored component, sampled by a streaming sampling profiler.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SampledStack {
+    pub component: MonitoredComponentId,
+    /// The name of the thread of the component, if any.
+    pub thread_name: Option<String>,
+    /// When the sample was taken, in milliseconds since the Unix epoch.
+    pub time: f64,
+    pub profile: HangProfile,
 }
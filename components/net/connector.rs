@@ -3,14 +3,20 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::hosts::replace_host;
-use hyper::client::connect::{Connect, Destination};
+use crate::proxy::ProxyConfig;
+use hyper::client::connect::{Connect, Connected, Destination};
 use hyper::client::HttpConnector as HyperHttpConnector;
 use hyper::rt::Future;
 use hyper::{Body, Client};
 use hyper_openssl::HttpsConnector;
 use openssl::ssl::{SslConnector, SslConnectorBuilder, SslMethod, SslOptions};
 use openssl::x509;
-use tokio::prelude::future::Executor;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio::prelude::future::{self, Executor, Loop};
 
 pub const BUF_SIZE: usize = 32768;
 pub const ALPN_H2_H1: &'static [u8] = b"\x02h2\x08http/1.1";
@@ -30,25 +36,38 @@ const SIGNATURE_ALGORITHMS: &'static str = concat!(
     "RSA+SHA512:RSA+SHA384:RSA+SHA256"
 );
 
+/// The largest response to a CONNECT request that we are willing to read.
+const MAX_TUNNEL_RESPONSE_SIZE: usize = 8192;
+
+/// The error used to report that a proxy refused to open a tunnel without credentials.
+#[derive(Debug)]
+pub struct ProxyAuthenticationRequired;
+
+impl fmt::Display for ProxyAuthenticationRequired {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Proxy authentication required")
+    }
+}
+
+impl Error for ProxyAuthenticationRequired {}
+
 pub struct HttpConnector {
     inner: HyperHttpConnector,
+    proxy_config: Arc<ProxyConfig>,
 }
 
 impl HttpConnector {
-    fn new() -> HttpConnector {
+    fn new(proxy_config: Arc<ProxyConfig>) -> HttpConnector {
         let mut inner = HyperHttpConnector::new(4);
         inner.enforce_http(false);
         inner.set_happy_eyeballs_timeout(None);
-        HttpConnector { inner }
+        HttpConnector {
+            inner,
+            proxy_config,
+        }
     }
-}
-
-impl Connect for HttpConnector {
-    type Transport = <HyperHttpConnector as Connect>::Transport;
-    type Error = <HyperHttpConnector as Connect>::Error;
-    type Future = <HyperHttpConnector as Connect>::Future;
 
-    fn connect(&self, dest: Destination) -> Self::Future {
+    fn connect_directly(&self, dest: Destination) -> <HyperHttpConnector as Connect>::Future {
         // Perform host replacement when making the actual TCP connection.
         let mut new_dest = dest.clone();
         let addr = replace_host(dest.host());
@@ -57,6 +76,124 @@ impl Connect for HttpConnector {
     }
 }
 
+impl Connect for HttpConnector {
+    type Transport = <HyperHttpConnector as Connect>::Transport;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = (TcpStream, Connected), Error = io::Error> + Send>;
+
+    fn connect(&self, dest: Destination) -> Self::Future {
+        let proxy = match self.proxy_config.proxy_for(dest.scheme(), dest.host()) {
+            Some(proxy) => proxy,
+            None => return Box::new(self.connect_directly(dest)),
+        };
+
+        let mut proxy_dest = dest.clone();
+        let proxied = proxy_dest
+            .set_scheme("http")
+            .and_then(|_| proxy_dest.set_host(proxy.host()));
+        if let Err(error) = proxied {
+            return Box::new(future::err(io::Error::new(io::ErrorKind::Other, error)));
+        }
+        proxy_dest.set_port(proxy.port());
+        let connecting = self.connect_directly(proxy_dest);
+
+        if dest.scheme() != "https" {
+            // Requests are sent to the proxy itself, with an absolute URI.
+            return Box::new(connecting.map(|(stream, connected)| (stream, connected.proxy(true))));
+        }
+
+        // Open a tunnel through the proxy, the TLS handshake then happens over it.
+        let authority = format!("{}:{}", dest.host(), dest.port().unwrap_or(443));
+        let mut connect_request = format!(
+            "CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n",
+            authority = authority
+        );
+        if let Some(credentials) = proxy.credentials() {
+            connect_request.push_str(&format!(
+                "Proxy-Authorization: {}\r\n",
+                credentials.to_header_value()
+            ));
+        }
+        connect_request.push_str("\r\n");
+        Box::new(connecting.and_then(move |(stream, connected)| {
+            tunnel(stream, connect_request.into_bytes()).map(move |stream| (stream, connected))
+        }))
+    }
+}
+
+/// Send a CONNECT request on a connection to a proxy,
+/// and resolve to the connection once the proxy has established the tunnel.
+fn tunnel(
+    stream: TcpStream,
+    connect_request: Vec<u8>,
+) -> impl Future<Item = TcpStream, Error = io::Error> {
+    tokio::io::write_all(stream, connect_request)
+        .and_then(|(stream, _)| {
+            future::loop_fn((stream, vec![]), |(stream, mut response)| {
+                tokio::io::read(stream, vec![0; 1024]).and_then(move |(stream, buffer, read)| {
+                    if read == 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "Proxy closed the connection while opening a tunnel",
+                        ));
+                    }
+                    response.extend_from_slice(&buffer[..read]);
+                    if response.windows(4).any(|window| window == b"\r\n\r\n") {
+                        Ok(Loop::Break((stream, response)))
+                    } else if response.len() > MAX_TUNNEL_RESPONSE_SIZE {
+                        Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "Proxy response to CONNECT is too large",
+                        ))
+                    } else {
+                        Ok(Loop::Continue((stream, response)))
+                    }
+                })
+            })
+        })
+        .and_then(|(stream, response)| {
+            // The status code is the second token of the status line, e.g. "HTTP/1.1 200 OK".
+            let status = String::from_utf8_lossy(&response)
+                .split_whitespace()
+                .nth(1)
+                .and_then(|status| status.parse::<u16>().ok());
+            match status {
+                Some(200..=299) => Ok(stream),
+                Some(407) => Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    ProxyAuthenticationRequired,
+                )),
+                Some(status) => Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("Proxy refused to open a tunnel, with status {}", status),
+                )),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid proxy response to CONNECT",
+                )),
+            }
+        })
+}
+
+/// Whether a request failed because a proxy required authentication to open a tunnel.
+pub fn is_proxy_authentication_error(error: &(dyn Error + 'static)) -> bool {
+    let mut source = Some(error);
+    while let Some(error) = source {
+        if error.is::<ProxyAuthenticationRequired>() {
+            return true;
+        }
+        if let Some(io_error) = error.downcast_ref::<io::Error>() {
+            if let Some(inner) = io_error.get_ref() {
+                if inner.is::<ProxyAuthenticationRequired>() {
+                    return true;
+                }
+            }
+        }
+        source = error.source();
+    }
+    false
+}
+
 pub type Connector = HttpsConnector<HttpConnector>;
 pub type TlsConfig = SslConnectorBuilder;
 
@@ -107,11 +244,16 @@ pub fn create_tls_config(certs: &str, alpn: &[u8]) -> TlsConfig {
     cfg
 }
 
-pub fn create_http_client<E>(tls_config: TlsConfig, executor: E) -> Client<Connector, Body>
+pub fn create_http_client<E>(
+    tls_config: TlsConfig,
+    proxy_config: Arc<ProxyConfig>,
+    executor: E,
+) -> Client<Connector, Body>
 where
     E: Executor<Box<dyn Future<Error = (), Item = ()> + Send + 'static>> + Sync + Send + 'static,
 {
-    let connector = HttpsConnector::with_connector(HttpConnector::new(), tls_config).unwrap();
+    let connector =
+        HttpsConnector::with_connector(HttpConnector::new(proxy_config), tls_config).unwrap();
 
     Client::builder()
         .http1_title_case_headers(true)
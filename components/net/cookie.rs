@@ -36,6 +36,12 @@ pub struct Cookie {
     )]
     pub last_access: Tm,
     pub expiry_time: Option<Serde<Tm>>,
+    /// Whether the cookie was set with the `Partitioned` attribute.
+    #[serde(default)]
+    pub partitioned: bool,
+    /// The top-level site a partitioned cookie is keyed by, assigned once it is stored.
+    #[serde(default)]
+    pub partition_key: Option<String>,
 }
 
 impl Cookie {
@@ -44,10 +50,22 @@ impl Cookie {
         request: &ServoUrl,
         source: CookieSource,
     ) -> Option<Cookie> {
-        cookie_rs::Cookie::parse(cookie_str)
+        // cookie-rs ignores the attributes it doesn't know about.
+        let partitioned = has_partitioned_attribute(&cookie_str);
+        let mut cookie = cookie_rs::Cookie::parse(cookie_str)
             .ok()
             .map(|cookie| Cookie::new_wrapped(cookie, request, source))
-            .unwrap_or(None)
+            .unwrap_or(None)?;
+
+        // https://github.com/privacycg/CHIPS#opt-in-cross-site-cookies
+        // Partitioned cookies must be set with the Secure attribute.
+        if partitioned {
+            if !cookie.cookie.secure().unwrap_or(false) {
+                return None;
+            }
+            cookie.partitioned = true;
+        }
+        Some(cookie)
     }
 
     /// <http://tools.ietf.org/html/rfc6265#section-5.3>
@@ -136,6 +154,8 @@ impl Cookie {
             creation_time: now(),
             last_access: now(),
             expiry_time: expiry_time.map(Serde),
+            partitioned: false,
+            partition_key: None,
         })
     }
 
@@ -227,3 +247,11 @@ impl Cookie {
         true
     }
 }
+
+/// Whether the attributes of a cookie string include `Partitioned`.
+fn has_partitioned_attribute(cookie_str: &str) -> bool {
+    cookie_str.split(';').skip(1).any(|attribute| {
+        let name = attribute.split('=').next().unwrap_or("").trim();
+        name.eq_ignore_ascii_case("Partitioned")
+    })
+}
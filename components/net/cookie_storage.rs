@@ -4,21 +4,58 @@
 
 //! Implementation of cookie storage as specified in
 //! http://tools.ietf.org/html/rfc6265
+//!
+//! Cookies set with the `Partitioned` attribute are additionally keyed by the site of the
+//! top-level document they were set under, as specified in
+//! https://github.com/privacycg/CHIPS
 
 use crate::cookie::Cookie;
 use net_traits::pub_domains::reg_suffix;
 use net_traits::CookieSource;
-use servo_url::ServoUrl;
+use servo_url::{ImmutableOrigin, ServoUrl};
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use time::{self, Tm};
 
+/// Which cookies can be used in a third-party context,
+/// where the site of a request differs from the site of its top-level document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CookiePolicy {
+    /// All cookies can be set and sent.
+    AllowAll,
+    /// Only partitioned cookies can be set and sent in a third-party context.
+    BlockThirdParty,
+}
+
+impl Default for CookiePolicy {
+    fn default() -> CookiePolicy {
+        CookiePolicy::AllowAll
+    }
+}
+
+impl CookiePolicy {
+    /// Parse the value of the `network.cookie.policy` pref.
+    pub fn from_pref(value: &str) -> CookiePolicy {
+        match value {
+            "allow-all" => CookiePolicy::AllowAll,
+            "block-third-party" => CookiePolicy::BlockThirdParty,
+            _ => {
+                let policy = CookiePolicy::default();
+                warn!("Unknown cookie policy {}, using {:?}", value, policy);
+                policy
+            },
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CookieStorage {
     version: u32,
     cookies_map: HashMap<String, Vec<Cookie>>,
     max_per_host: usize,
+    #[serde(skip)]
+    policy: CookiePolicy,
 }
 
 impl CookieStorage {
@@ -27,9 +64,18 @@ impl CookieStorage {
             version: 1,
             cookies_map: HashMap::new(),
             max_per_host: max_cookies,
+            policy: CookiePolicy::default(),
         }
     }
 
+    pub fn policy(&self) -> CookiePolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: CookiePolicy) {
+        self.policy = policy;
+    }
+
     // http://tools.ietf.org/html/rfc6265#section-5.3
     pub fn remove(
         &mut self,
@@ -50,6 +96,7 @@ impl CookieStorage {
                 let existing_path = c.cookie.path().as_ref().unwrap().to_owned();
 
                 c.cookie.name() == cookie.cookie.name() &&
+                    c.partition_key == cookie.partition_key &&
                     c.cookie.secure().unwrap_or(false) &&
                     (Cookie::domain_match(new_domain, existing_domain) ||
                         Cookie::domain_match(existing_domain, new_domain)) &&
@@ -65,7 +112,8 @@ impl CookieStorage {
         let position = cookies.iter().position(|c| {
             c.cookie.domain() == cookie.cookie.domain() &&
                 c.cookie.path() == cookie.cookie.path() &&
-                c.cookie.name() == cookie.cookie.name()
+                c.cookie.name() == cookie.cookie.name() &&
+                c.partition_key == cookie.partition_key
         });
 
         if let Some(ind) = position {
@@ -92,13 +140,37 @@ impl CookieStorage {
         }
     }
 
+    /// Store a cookie set by `url`, when it is the top-level document.
+    pub fn push(&mut self, cookie: Cookie, url: &ServoUrl, source: CookieSource) {
+        self.push_for_top_level(cookie, url, Some(&url.origin()), source)
+    }
+
+    /// Store a cookie set by `url`, in the context of a top-level document
+    /// with the given origin, or of an unknown one if it is `None`.
     // http://tools.ietf.org/html/rfc6265#section-5.3
-    pub fn push(&mut self, mut cookie: Cookie, url: &ServoUrl, source: CookieSource) {
+    pub fn push_for_top_level(
+        &mut self,
+        mut cookie: Cookie,
+        url: &ServoUrl,
+        top_level_origin: Option<&ImmutableOrigin>,
+        source: CookieSource,
+    ) {
         // https://www.ietf.org/id/draft-ietf-httpbis-cookie-alone-01.txt Step 1
         if cookie.cookie.secure().unwrap_or(false) && !url.is_secure_scheme() {
             return;
         }
 
+        let (partition_key, third_party) = cookie_context(url, top_level_origin);
+        if cookie.partitioned {
+            match partition_key {
+                Some(partition_key) => cookie.partition_key = Some(partition_key),
+                // Partitioned cookies can't be keyed by an opaque origin.
+                None => return,
+            }
+        } else if third_party && self.policy == CookiePolicy::BlockThirdParty {
+            return;
+        }
+
         let old_cookie = self.remove(&cookie, url, source);
         if old_cookie.is_err() {
             // This new cookie is not allowed to overwrite an existing one.
@@ -145,6 +217,18 @@ impl CookieStorage {
         }
     }
 
+    /// Delete the cookies named `name` that are associated with the domain of `url`.
+    pub fn delete_cookie(&mut self, url: &ServoUrl, name: &str) {
+        let domain = reg_host(url.host_str().unwrap_or(""));
+        if let Entry::Occupied(mut entry) = self.cookies_map.entry(domain) {
+            let cookies = entry.get_mut();
+            cookies.retain(|c| c.cookie.name() != name);
+            if cookies.len() == 0 {
+                entry.remove_entry();
+            }
+        }
+    }
+
     pub fn remove_expired_cookies_for_url(&mut self, url: &ServoUrl) {
         let domain = reg_host(url.host_str().unwrap_or(""));
         if let Entry::Occupied(mut entry) = self.cookies_map.entry(domain) {
@@ -156,8 +240,22 @@ impl CookieStorage {
         }
     }
 
-    // http://tools.ietf.org/html/rfc6265#section-5.4
+    /// The cookies to send to `url`, when it is the top-level document.
     pub fn cookies_for_url(&mut self, url: &ServoUrl, source: CookieSource) -> Option<String> {
+        self.cookies_for_url_and_top_level(url, Some(&url.origin()), source)
+    }
+
+    /// The cookies to send to `url`, in the context of a top-level document
+    /// with the given origin, or of an unknown one if it is `None`.
+    // http://tools.ietf.org/html/rfc6265#section-5.4
+    pub fn cookies_for_url_and_top_level(
+        &mut self,
+        url: &ServoUrl,
+        top_level_origin: Option<&ImmutableOrigin>,
+        source: CookieSource,
+    ) -> Option<String> {
+        let (partition_key, third_party) = cookie_context(url, top_level_origin);
+        let block_unpartitioned = third_party && self.policy == CookiePolicy::BlockThirdParty;
         let filterer = |c: &&mut Cookie| -> bool {
             info!(
                 " === SENT COOKIE : {} {} {:?} {:?}",
@@ -171,7 +269,8 @@ impl CookieStorage {
                 c.appropriate_for_url(url, source)
             );
             // Step 1
-            c.appropriate_for_url(url, source)
+            c.appropriate_for_url(url, source) &&
+                is_cookie_in_context(c, &partition_key, block_unpartitioned)
         };
         // Step 2
         let domain = reg_host(url.host_str().unwrap_or(""));
@@ -201,17 +300,25 @@ impl CookieStorage {
         }
     }
 
+    /// The cookies of `url`, in the context of a top-level document
+    /// with the given origin, or of an unknown one if it is `None`.
     pub fn cookies_data_for_url<'a>(
         &'a mut self,
         url: &'a ServoUrl,
+        top_level_origin: Option<&ImmutableOrigin>,
         source: CookieSource,
     ) -> impl Iterator<Item = cookie_rs::Cookie<'static>> + 'a {
+        let (partition_key, third_party) = cookie_context(url, top_level_origin);
+        let block_unpartitioned = third_party && self.policy == CookiePolicy::BlockThirdParty;
         let domain = reg_host(url.host_str().unwrap_or(""));
         let cookies = self.cookies_map.entry(domain).or_insert(vec![]);
 
         cookies
             .iter_mut()
-            .filter(move |c| c.appropriate_for_url(url, source))
+            .filter(move |c| {
+                c.appropriate_for_url(url, source) &&
+                    is_cookie_in_context(c, &partition_key, block_unpartitioned)
+            })
             .map(|c| {
                 c.touch();
                 c.cookie.clone()
@@ -223,6 +330,46 @@ fn reg_host<'a>(url: &'a str) -> String {
     reg_suffix(url).to_lowercase()
 }
 
+/// The site of an origin, made of its scheme and registrable domain,
+/// or `None` for opaque origins, which aren't same-site with anything.
+fn site_for_origin(origin: &ImmutableOrigin) -> Option<String> {
+    match *origin {
+        ImmutableOrigin::Tuple(ref scheme, ref host, _) => {
+            Some(format!("{}://{}", scheme, reg_host(&host.to_string())))
+        },
+        ImmutableOrigin::Opaque(_) => None,
+    }
+}
+
+/// The key of the partition used by cookies of `url` loaded under a top-level document
+/// with the given origin, and whether that is a third-party context.
+///
+/// Without a top-level origin, the context is unknown: it is treated as a third-party
+/// context without a partition, rather than trusted to be first-party.
+fn cookie_context(
+    url: &ServoUrl,
+    top_level_origin: Option<&ImmutableOrigin>,
+) -> (Option<String>, bool) {
+    let top_level_site = match top_level_origin {
+        Some(top_level_origin) => site_for_origin(top_level_origin),
+        None => return (None, true),
+    };
+    let third_party = top_level_site.is_none() || top_level_site != site_for_origin(&url.origin());
+    (top_level_site, third_party)
+}
+
+fn is_cookie_in_context(
+    cookie: &Cookie,
+    partition_key: &Option<String>,
+    block_unpartitioned: bool,
+) -> bool {
+    if cookie.partitioned {
+        partition_key.is_some() && cookie.partition_key == *partition_key
+    } else {
+        !block_unpartitioned
+    }
+}
+
 fn is_cookie_expired(cookie: &Cookie) -> bool {
     match cookie.expiry_time {
         Some(ref t) => t.to_timespec() <= time::get_time(),
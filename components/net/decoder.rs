@@ -37,8 +37,9 @@ use hyper::{self, Body, Chunk, Response};
 use libflate::non_blocking::gzip;
 use std::cmp;
 use std::fmt;
-use std::io::{self, Read};
+use std::io::{self, BufReader, Read};
 use std::mem;
+use zstd::stream::read::Decoder as ZstdDecoder;
 
 pub enum Error {
     Io(io::Error),
@@ -71,6 +72,7 @@ enum DecoderType {
     Gzip,
     Brotli,
     Deflate,
+    Zstd,
 }
 
 enum Inner {
@@ -82,6 +84,8 @@ enum Inner {
     Deflate(Deflate),
     /// A `Brotli` decoder will uncompress the brotli-encoded response content before returning it.
     Brotli(Brotli),
+    /// A `Zstd` decoder will uncompress the zstd-encoded response content before returning it.
+    Zstd(Zstd),
     /// A decoder that doesn't have a value yet.
     Pending(Pending),
 }
@@ -149,6 +153,8 @@ impl Decoder {
                     Some(DecoderType::Brotli)
                 } else if enc == HeaderValue::from_static("deflate") {
                     Some(DecoderType::Deflate)
+                } else if enc == HeaderValue::from_static("zstd") {
+                    Some(DecoderType::Zstd)
                 } else {
                     None
                 }
@@ -171,12 +177,13 @@ impl Stream for Decoder {
             Inner::Pending(ref mut future) => match future.poll() {
                 Ok(Async::Ready(inner)) => inner,
                 Ok(Async::NotReady) => return Ok(Async::NotReady),
-                Err(e) => return Err(e.into()),
+                Err(e) => return Err(e),
             },
             Inner::PlainText(ref mut body) => return body.poll().map_err(|e| e.into()),
             Inner::Gzip(ref mut decoder) => return decoder.poll(),
             Inner::Brotli(ref mut decoder) => return decoder.poll(),
             Inner::Deflate(ref mut decoder) => return decoder.poll(),
+            Inner::Zstd(ref mut decoder) => return decoder.poll(),
         };
 
         self.inner = new_value;
@@ -186,13 +193,13 @@ impl Stream for Decoder {
 
 impl Future for Pending {
     type Item = Inner;
-    type Error = hyper::error::Error;
+    type Error = Error;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
         let body_state = match self.body.poll_stream() {
             Ok(Async::Ready(state)) => state,
             Ok(Async::NotReady) => return Ok(Async::NotReady),
-            Err(e) => return Err(e),
+            Err(e) => return Err(e.into()),
         };
 
         let body = mem::replace(&mut self.body, ReadableChunks::new(Body::empty()));
@@ -205,6 +212,7 @@ impl Future for Pending {
                 DecoderType::Gzip => Inner::Gzip(Gzip::new(body)),
                 DecoderType::Brotli => Inner::Brotli(Brotli::new(body)),
                 DecoderType::Deflate => Inner::Deflate(Deflate::new(body)),
+                DecoderType::Zstd => Inner::Zstd(Zstd::new(body)?),
             })),
         }
     }
@@ -307,6 +315,31 @@ impl Stream for Deflate {
     }
 }
 
+/// A zstd decoder that reads from a `zstd::stream::read::Decoder` into a `BytesMut` and emits
+/// the results as a `Chunk`.
+struct Zstd {
+    inner: Box<ZstdDecoder<BufReader<ReadableChunks<Body>>>>,
+    buf: BytesMut,
+}
+
+impl Zstd {
+    fn new(stream: ReadableChunks<Body>) -> io::Result<Self> {
+        Ok(Self {
+            buf: BytesMut::with_capacity(INIT_BUFFER_SIZE),
+            inner: Box::new(ZstdDecoder::new(stream)?),
+        })
+    }
+}
+
+impl Stream for Zstd {
+    type Item = Chunk;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        poll_with_read(&mut self.inner, &mut self.buf)
+    }
+}
+
 /// A `Read`able wrapper over a stream of chunks.
 pub struct ReadableChunks<S> {
     state: ReadState,
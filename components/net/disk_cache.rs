@@ -0,0 +1,367 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#![deny(missing_docs)]
+
+//! A persistent store backing the memory cache in `http_cache`.
+//!
+//! Each stored resource is kept as two files in the cache directory:
+//! `<id>.json` holds the headers and metadata, `<id>.body` holds the raw body.
+//! An `index.json` file maps URLs to entries, and records the information
+//! needed to enforce the size limit, using least-recently-used eviction.
+
+use http::{header, HeaderMap};
+use net_traits::response::HttpsState;
+use serde::{Deserialize, Serialize};
+use servo_url::ServoUrl;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// The version of the on-disk format, bumped whenever it changes incompatibly.
+const DISK_CACHE_VERSION: u32 = 1;
+
+const INDEX_FILE_NAME: &'static str = "index.json";
+
+/// The serialized form of a cached resource, minus its body.
+#[derive(Deserialize, Serialize)]
+pub(crate) struct PersistedResource {
+    #[serde(
+        deserialize_with = "::hyper_serde::deserialize",
+        serialize_with = "::hyper_serde::serialize"
+    )]
+    pub request_headers: HeaderMap,
+    #[serde(
+        deserialize_with = "::hyper_serde::deserialize",
+        serialize_with = "::hyper_serde::serialize"
+    )]
+    pub headers: HeaderMap,
+    pub final_url: ServoUrl,
+    pub content_type: Option<String>,
+    pub charset: Option<String>,
+    pub metadata_status: Option<(u16, Vec<u8>)>,
+    pub location_url: Option<Result<ServoUrl, String>>,
+    pub https_state: HttpsState,
+    pub status: Option<(u16, String)>,
+    pub raw_status: Option<(u16, Vec<u8>)>,
+    pub url_list: Vec<ServoUrl>,
+    /// The freshness lifetime, in seconds.
+    pub expires: i64,
+    /// The time of the last validation, in seconds since the epoch.
+    pub last_validated: i64,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct IndexEntry {
+    /// The name shared by the metadata and body files, without extension.
+    id: String,
+    /// The size of the body, in bytes.
+    size: u64,
+    /// A logical timestamp of the last use, used for LRU eviction.
+    last_access: u64,
+    /// The time after which the resource is stale, in seconds since the epoch.
+    stale_at: i64,
+    /// Whether the resource carries an `ETag` or `Last-Modified` validator.
+    has_validator: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Index {
+    version: u32,
+    /// Incremented on every access.
+    clock: u64,
+    /// Entries, keyed by serialized URL.
+    entries: HashMap<String, IndexEntry>,
+}
+
+impl Index {
+    fn new() -> Index {
+        Index {
+            version: DISK_CACHE_VERSION,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn total_size(&self) -> u64 {
+        self.entries.values().map(|entry| entry.size).sum()
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+/// A size-limited, least-recently-used, store of cached resources on disk.
+pub struct DiskCache {
+    /// The directory containing the index and entry files.
+    directory: PathBuf,
+    /// The maximum combined size of the stored bodies, in bytes.
+    max_size: u64,
+    /// Whether resources stored by a previous session must be revalidated before use.
+    revalidate_persisted: bool,
+    index: Mutex<Index>,
+}
+
+impl DiskCache {
+    /// Open, or create, the disk cache located in `directory`.
+    ///
+    /// Entries that are missing files, or which are stale and cannot be revalidated,
+    /// are removed. If `revalidate_on_startup` is set,
+    /// all entries found are considered stale on first use in this session.
+    pub fn open(
+        directory: PathBuf,
+        max_size: u64,
+        revalidate_on_startup: bool,
+    ) -> io::Result<DiskCache> {
+        fs::create_dir_all(&directory)?;
+        let index = match fs::read(directory.join(INDEX_FILE_NAME)) {
+            Ok(bytes) => match serde_json::from_slice::<Index>(&bytes) {
+                Ok(ref index) if index.version != DISK_CACHE_VERSION => {
+                    warn!("Discarding http cache with version {}", index.version);
+                    Index::new()
+                },
+                Ok(index) => index,
+                Err(why) => {
+                    warn!("Could not decode http cache index: {}", why);
+                    Index::new()
+                },
+            },
+            Err(ref why) if why.kind() == io::ErrorKind::NotFound => Index::new(),
+            Err(why) => return Err(why),
+        };
+        let cache = DiskCache {
+            directory,
+            max_size,
+            revalidate_persisted: revalidate_on_startup,
+            index: Mutex::new(index),
+        };
+        cache.prune();
+        Ok(cache)
+    }
+
+    /// The combined size, in bytes, of the stored bodies.
+    pub fn size(&self) -> u64 {
+        self.index.lock().unwrap().total_size()
+    }
+
+    /// Whether an entry is stored for this url.
+    pub fn contains(&self, url: &ServoUrl) -> bool {
+        self.index
+            .lock()
+            .unwrap()
+            .entries
+            .contains_key(url.as_str())
+    }
+
+    /// Write the index to disk, persisting the recency of entries.
+    pub fn flush(&self) {
+        let index = self.index.lock().unwrap();
+        self.write_index(&*index);
+    }
+
+    /// Load the resource stored for this url, and its body.
+    pub(crate) fn load(&self, url: &ServoUrl) -> Option<(PersistedResource, Vec<u8>)> {
+        let id = self
+            .index
+            .lock()
+            .unwrap()
+            .entries
+            .get(url.as_str())?
+            .id
+            .clone();
+        // Read the files without holding on to the index,
+        // which is only updated once the entry is known to be usable.
+        let loaded = fs::read(self.metadata_path(&id))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<PersistedResource>(&bytes).ok())
+            .and_then(|resource| {
+                fs::read(self.body_path(&id))
+                    .ok()
+                    .map(|body| (resource, body))
+            });
+        let mut index = self.index.lock().unwrap();
+        match loaded {
+            Some((mut resource, body)) => {
+                if self.revalidate_persisted {
+                    resource.expires = 0;
+                }
+                let clock = index.tick();
+                if let Some(entry) = index.entries.get_mut(url.as_str()) {
+                    entry.last_access = clock;
+                }
+                Some((resource, body))
+            },
+            None => {
+                // The entry might have been replaced while its files were read.
+                if index.entries.get(url.as_str()).map(|entry| &entry.id) != Some(&id) {
+                    return None;
+                }
+                warn!("Removing unreadable http cache entry for {}", url);
+                if let Some(entry) = index.entries.remove(url.as_str()) {
+                    self.remove_files(&entry.id);
+                }
+                self.write_index(&*index);
+                None
+            },
+        }
+    }
+
+    /// Store a resource for this url, replacing any previous entry,
+    /// and evicting the least recently used entries if the size limit is exceeded.
+    pub(crate) fn store(&self, url: &ServoUrl, resource: &PersistedResource, body: &[u8]) {
+        let size = body.len() as u64;
+        if size > self.max_size {
+            debug!("{} is too large to be stored in the http cache", url);
+            self.remove(url);
+            return;
+        }
+        let metadata = match serde_json::to_vec(resource) {
+            Ok(metadata) => metadata,
+            Err(why) => {
+                warn!("Could not encode http cache entry: {}", why);
+                return;
+            },
+        };
+        let id = Uuid::new_v4().to_simple().to_string();
+        let written = write_atomically(&self.body_path(&id), body)
+            .and_then(|_| write_atomically(&self.metadata_path(&id), &metadata));
+        if let Err(why) = written {
+            warn!("Could not write http cache entry for {}: {}", url, why);
+            self.remove_files(&id);
+            return;
+        }
+
+        let has_validator = resource.headers.contains_key(header::ETAG) ||
+            resource.headers.contains_key(header::LAST_MODIFIED);
+        let mut index = self.index.lock().unwrap();
+        let entry = IndexEntry {
+            id,
+            size,
+            last_access: index.tick(),
+            stale_at: resource.last_validated.saturating_add(resource.expires),
+            has_validator,
+        };
+        if let Some(previous) = index.entries.insert(url.as_str().to_owned(), entry) {
+            self.remove_files(&previous.id);
+        }
+        self.evict(&mut *index, url.as_str());
+        self.write_index(&*index);
+    }
+
+    /// Mark the entry for this url as used, if there is one.
+    pub(crate) fn touch(&self, url: &ServoUrl) {
+        let mut index = self.index.lock().unwrap();
+        let clock = index.tick();
+        if let Some(entry) = index.entries.get_mut(url.as_str()) {
+            entry.last_access = clock;
+        }
+    }
+
+    /// Remove the entry for this url, if there is one.
+    pub(crate) fn remove(&self, url: &ServoUrl) {
+        let mut index = self.index.lock().unwrap();
+        if let Some(entry) = index.entries.remove(url.as_str()) {
+            self.remove_files(&entry.id);
+            self.write_index(&*index);
+        }
+    }
+
+    /// Evict least recently used entries, other than `keep`, until the size limit is met.
+    fn evict(&self, index: &mut Index, keep: &str) {
+        let mut total_size = index.total_size();
+        while total_size > self.max_size {
+            let lru = index
+                .entries
+                .iter()
+                .filter(|&(url, _)| url != keep)
+                .min_by_key(|&(_, entry)| entry.last_access)
+                .map(|(url, _)| url.clone());
+            let url = match lru {
+                Some(url) => url,
+                None => break,
+            };
+            if let Some(entry) = index.entries.remove(&url) {
+                debug!("Evicting {} from the http cache", url);
+                total_size -= entry.size;
+                self.remove_files(&entry.id);
+            }
+        }
+    }
+
+    /// Bring the directory and the index back in sync with each other,
+    /// and drop entries that could never be used again.
+    fn prune(&self) {
+        let mut index = self.index.lock().unwrap();
+        let now = time::now().to_timespec().sec;
+        let directory = &self.directory;
+        let metadata_path = |id: &str| self.metadata_path(id);
+        let body_path = |id: &str| self.body_path(id);
+        index.entries.retain(|url, entry| {
+            if entry.stale_at <= now && !entry.has_validator {
+                debug!("Pruning stale http cache entry for {}", url);
+                return false;
+            }
+            metadata_path(&entry.id).is_file() && body_path(&entry.id).is_file()
+        });
+
+        // Remove files no longer referenced by the index,
+        // including leftovers of interrupted writes.
+        if let Ok(dir_entries) = fs::read_dir(directory) {
+            for dir_entry in dir_entries.filter_map(Result::ok) {
+                let file_name = dir_entry.file_name();
+                let file_name = match file_name.to_str() {
+                    Some(file_name) => file_name,
+                    None => continue,
+                };
+                if file_name == INDEX_FILE_NAME {
+                    continue;
+                }
+                let id = file_name.split('.').next().unwrap_or("");
+                if file_name.ends_with(".tmp") ||
+                    !index.entries.values().any(|entry| entry.id == id)
+                {
+                    let _ = fs::remove_file(dir_entry.path());
+                }
+            }
+        }
+
+        self.evict(&mut *index, "");
+        self.write_index(&*index);
+    }
+
+    fn write_index(&self, index: &Index) {
+        let written = serde_json::to_vec(index)
+            .map_err(|why| io::Error::new(io::ErrorKind::Other, why))
+            .and_then(|bytes| write_atomically(&self.directory.join(INDEX_FILE_NAME), &bytes));
+        if let Err(why) = written {
+            warn!("Could not write http cache index: {}", why);
+        }
+    }
+
+    fn remove_files(&self, id: &str) {
+        let _ = fs::remove_file(self.metadata_path(id));
+        let _ = fs::remove_file(self.body_path(id));
+    }
+
+    fn metadata_path(&self, id: &str) -> PathBuf {
+        self.directory.join(format!("{}.json", id))
+    }
+
+    fn body_path(&self, id: &str) -> PathBuf {
+        self.directory.join(format!("{}.body", id))
+    }
+}
+
+/// Write to a temporary file first, so that a crash never leaves a truncated file behind.
+fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    fs::write(&temporary_path, bytes)?;
+    fs::rename(&temporary_path, path)
+}
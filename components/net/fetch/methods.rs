@@ -478,7 +478,9 @@ pub fn main_fetch(
     }
 
     // Step 21.
-    if request.body.is_some() && matches!(request.current_url().scheme(), "http" | "https") {
+    if (request.body.is_some() || request.body_chunks.is_some()) &&
+        matches!(request.current_url().scheme(), "http" | "https")
+    {
         // XXXManishearth: We actually should be calling process_request
         // in http_network_fetch. However, we can't yet follow the request
         // upload progress, so I'm keeping it here for now and pretending
@@ -498,8 +500,16 @@ pub fn main_fetch(
     // Step 24.
     target.process_response_eof(&response);
 
-    if let Ok(http_cache) = context.state.http_cache.write() {
-        http_cache.update_awaiting_consumers(&request, &response);
+    let disk_cache_store = match context.state.http_cache.write() {
+        Ok(http_cache) => {
+            http_cache.update_awaiting_consumers(&request, &response);
+            http_cache.prepare_persist(&request)
+        },
+        Err(_) => None,
+    };
+    // Write to the disk cache without holding on to the memory cache.
+    if let Some(disk_cache_store) = disk_cache_store {
+        disk_cache_store.store();
     }
 
     // Steps 25-27.
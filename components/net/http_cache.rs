@@ -5,12 +5,14 @@
 #![deny(missing_docs)]
 
 //! A memory cache implementing the logic specified in <http://tools.ietf.org/html/rfc7234>
-//! and <http://tools.ietf.org/html/rfc7232>.
+//! and <http://tools.ietf.org/html/rfc7232>, optionally backed by a `DiskCache`.
 
+use crate::disk_cache::{DiskCache, PersistedResource};
 use crate::fetch::methods::{Data, DoneChannel};
 use crossbeam_channel::{unbounded, Sender};
 use headers::{
-    CacheControl, ContentRange, Expires, HeaderMapExt, LastModified, Pragma, Range, Vary,
+    CacheControl, ContentLength, ContentRange, Expires, HeaderMapExt, LastModified, Pragma, Range,
+    Vary,
 };
 use http::header::HeaderValue;
 use http::{header, HeaderMap};
@@ -59,6 +61,8 @@ struct CachedResource {
     body: Arc<Mutex<ResponseBody>>,
     aborted: Arc<AtomicBool>,
     awaiting_body: Arc<Mutex<Vec<Sender<Data>>>>,
+    /// Whether the disk cache holds the current state of this resource.
+    persisted: Arc<AtomicBool>,
     data: Measurable<MeasurableCachedResource>,
 }
 
@@ -80,6 +84,7 @@ impl MallocSizeOf for CachedResource {
         self.body.unconditional_size_of(ops) +
             self.aborted.unconditional_size_of(ops) +
             self.awaiting_body.unconditional_size_of(ops) +
+            self.persisted.unconditional_size_of(ops) +
             self.data.size_of(ops)
     }
 }
@@ -126,6 +131,51 @@ pub struct CachedResponse {
 pub struct HttpCache {
     /// cached responses.
     entries: HashMap<CacheKey, Vec<CachedResource>>,
+    /// The store persisting responses across sessions, if any.
+    #[ignore_malloc_size_of = "Stored on disk"]
+    disk_cache: Option<Arc<DiskCache>>,
+}
+
+/// A pending read of the disk cache, see `HttpCache::prepare_load_from_disk`.
+pub struct DiskCacheLoad {
+    entry_key: CacheKey,
+    disk_cache: Arc<DiskCache>,
+}
+
+impl DiskCacheLoad {
+    /// Read the resource from the disk cache, if it is still there.
+    pub fn load(self) -> Option<LoadedFromDisk> {
+        let (persisted, body) = self.disk_cache.load(&self.entry_key.url)?;
+        debug!("loaded {:?} from the disk cache", self.entry_key.url);
+        Some(LoadedFromDisk {
+            entry_key: self.entry_key,
+            resource: cached_resource_from_persisted(persisted, body),
+        })
+    }
+}
+
+/// A resource read from the disk cache, see `HttpCache::insert_loaded_from_disk`.
+pub struct LoadedFromDisk {
+    entry_key: CacheKey,
+    resource: CachedResource,
+}
+
+/// A pending write to the disk cache, see `HttpCache::prepare_persist`.
+pub struct DiskCacheStore {
+    url: ServoUrl,
+    disk_cache: Arc<DiskCache>,
+    /// The resource to write, or none if it is already on disk.
+    resource: Option<(PersistedResource, Vec<u8>)>,
+}
+
+impl DiskCacheStore {
+    /// Write the resource to the disk cache, or mark it as recently used.
+    pub fn store(self) {
+        match self.resource {
+            Some((persisted, body)) => self.disk_cache.store(&self.url, &persisted, &body),
+            None => self.disk_cache.touch(&self.url),
+        }
+    }
 }
 
 /// Determine if a response is cacheable by default <https://tools.ietf.org/html/rfc7231#section-6.1>
@@ -363,6 +413,8 @@ fn create_resource_with_bytes_from_resource(
         body: Arc::new(Mutex::new(ResponseBody::Done(bytes.to_owned()))),
         aborted: Arc::new(AtomicBool::new(false)),
         awaiting_body: Arc::new(Mutex::new(vec![])),
+        // Partial resources constructed on the fly are never persisted.
+        persisted: Arc::new(AtomicBool::new(true)),
         data: Measurable(MeasurableCachedResource {
             metadata: resource.data.metadata.clone(),
             location_url: resource.data.location_url.clone(),
@@ -582,11 +634,163 @@ fn handle_range_request(
     None
 }
 
+/// Whether a resource is complete, and can be written to the disk cache.
+fn is_persistable(resource: &CachedResource) -> bool {
+    if resource.aborted.load(Ordering::Acquire) {
+        return false;
+    }
+    match resource.data.raw_status {
+        // Partial content is only ever used to answer range requests in this session.
+        Some((206, _)) | None => return false,
+        _ => {},
+    }
+    let body = resource.body.lock().unwrap();
+    let body = match *body {
+        ResponseBody::Done(ref body) => body,
+        ResponseBody::Empty | ResponseBody::Receiving(_) => return false,
+    };
+    // A body cut short by a network error is also marked as done,
+    // use the Content-Length to avoid persisting a truncated one.
+    let headers = resource.data.metadata.headers.lock().unwrap();
+    match headers.typed_get::<ContentLength>() {
+        Some(ContentLength(length)) => length == body.len() as u64,
+        None => true,
+    }
+}
+
+/// Create the serialized form of a cached resource, and take a copy of its body.
+fn persisted_resource_from_cached(
+    resource: &CachedResource,
+) -> Option<(PersistedResource, Vec<u8>)> {
+    let body = match *resource.body.lock().unwrap() {
+        ResponseBody::Done(ref body) => body.clone(),
+        ResponseBody::Empty | ResponseBody::Receiving(_) => return None,
+    };
+    let metadata = &resource.data.metadata;
+    let persisted = PersistedResource {
+        request_headers: resource.request_headers.lock().unwrap().clone(),
+        headers: metadata.headers.lock().unwrap().clone(),
+        final_url: metadata.data.final_url.clone(),
+        content_type: metadata.data.content_type.clone(),
+        charset: metadata.data.charset.clone(),
+        metadata_status: metadata.data.status.clone(),
+        location_url: resource.data.location_url.clone(),
+        https_state: resource.data.https_state,
+        status: resource
+            .data
+            .status
+            .as_ref()
+            .map(|&(ref code, ref reason)| (code.as_u16(), reason.clone())),
+        raw_status: resource.data.raw_status.clone(),
+        url_list: resource.data.url_list.clone(),
+        expires: resource.data.expires.num_seconds(),
+        last_validated: resource.data.last_validated.to_timespec().sec,
+    };
+    Some((persisted, body))
+}
+
+/// Re-create a cached resource from its serialized form, and its body.
+fn cached_resource_from_persisted(persisted: PersistedResource, body: Vec<u8>) -> CachedResource {
+    let status = persisted.status.and_then(|(code, reason)| {
+        StatusCode::from_u16(code)
+            .ok()
+            .map(|status_code| (status_code, reason))
+    });
+    CachedResource {
+        request_headers: Arc::new(Mutex::new(persisted.request_headers)),
+        body: Arc::new(Mutex::new(ResponseBody::Done(body))),
+        aborted: Arc::new(AtomicBool::new(false)),
+        awaiting_body: Arc::new(Mutex::new(vec![])),
+        persisted: Arc::new(AtomicBool::new(true)),
+        data: Measurable(MeasurableCachedResource {
+            metadata: CachedMetadata {
+                headers: Arc::new(Mutex::new(persisted.headers)),
+                data: Measurable(MeasurableCachedMetadata {
+                    final_url: persisted.final_url,
+                    content_type: persisted.content_type,
+                    charset: persisted.charset,
+                    status: persisted.metadata_status,
+                }),
+            },
+            location_url: persisted.location_url,
+            https_state: persisted.https_state,
+            status,
+            raw_status: persisted.raw_status,
+            url_list: persisted.url_list,
+            expires: Duration::seconds(persisted.expires),
+            last_validated: time::at(Timespec::new(persisted.last_validated, 0)),
+        }),
+    }
+}
+
 impl HttpCache {
     /// Create a new memory cache instance.
     pub fn new() -> HttpCache {
         HttpCache {
             entries: HashMap::new(),
+            disk_cache: None,
+        }
+    }
+
+    /// Create a new memory cache instance, backed by a disk cache.
+    pub fn with_disk_cache(disk_cache: DiskCache) -> HttpCache {
+        HttpCache {
+            entries: HashMap::new(),
+            disk_cache: Some(Arc::new(disk_cache)),
+        }
+    }
+
+    /// Prepare loading the resource persisted by a previous session for this request, if any.
+    /// The disk is only read by `DiskCacheLoad::load`, without access to the memory cache.
+    pub fn prepare_load_from_disk(&self, request: &Request) -> Option<DiskCacheLoad> {
+        if request.method != Method::GET {
+            return None;
+        }
+        let disk_cache = self.disk_cache.as_ref()?;
+        let entry_key = CacheKey::new(&request);
+        if self.entries.contains_key(&entry_key) {
+            return None;
+        }
+        Some(DiskCacheLoad {
+            entry_key,
+            disk_cache: disk_cache.clone(),
+        })
+    }
+
+    /// Insert a resource loaded from the disk cache into the memory cache,
+    /// where `construct_response` can find it,
+    /// unless a response for the same request was stored in the meantime.
+    pub fn insert_loaded_from_disk(&mut self, loaded: LoadedFromDisk) {
+        self.entries
+            .entry(loaded.entry_key)
+            .or_insert_with(|| vec![loaded.resource]);
+    }
+
+    /// Prepare writing the complete resources stored for this request to the disk cache,
+    /// unless they are already there, in which case they are marked as recently used.
+    /// The disk is only written by `DiskCacheStore::store`, without access to the memory cache.
+    pub fn prepare_persist(&self, request: &Request) -> Option<DiskCacheStore> {
+        let disk_cache = self.disk_cache.as_ref()?;
+        let entry_key = CacheKey::new(&request);
+        let cached_resources = self.entries.get(&entry_key)?;
+        // The disk cache holds a single resource per key, the most recently stored complete one.
+        let resource = cached_resources.iter().rev().find(|r| is_persistable(r))?;
+        let resource = if resource.persisted.swap(true, Ordering::AcqRel) {
+            None
+        } else {
+            Some(persisted_resource_from_cached(resource)?)
+        };
+        Some(DiskCacheStore {
+            url: entry_key.url,
+            disk_cache: disk_cache.clone(),
+            resource,
+        })
+    }
+
+    /// Write the state of the disk cache, if any, that is only kept in memory.
+    pub fn flush(&self) {
+        if let Some(ref disk_cache) = self.disk_cache {
+            disk_cache.flush();
         }
     }
 
@@ -796,6 +1000,9 @@ impl HttpCache {
                 constructed_response.raw_status = cached_resource.data.raw_status.clone();
                 constructed_response.url_list = cached_resource.data.url_list.clone();
                 cached_resource.data.expires = get_response_expiry(&constructed_response);
+                cached_resource.data.last_validated = time::now();
+                // The headers and freshness have changed, write them out again.
+                cached_resource.persisted.store(false, Ordering::Release);
                 let mut stored_headers = cached_resource.data.metadata.headers.lock().unwrap();
                 stored_headers.extend(response.headers);
                 constructed_response.headers = stored_headers.clone();
@@ -810,8 +1017,12 @@ impl HttpCache {
         if let Some(cached_resources) = self.entries.get_mut(&entry_key) {
             for cached_resource in cached_resources.iter_mut() {
                 cached_resource.data.expires = Duration::seconds(0i64);
+                cached_resource.persisted.store(false, Ordering::Release);
             }
         }
+        if let Some(ref disk_cache) = self.disk_cache {
+            disk_cache.remove(url);
+        }
     }
 
     /// Invalidation.
@@ -885,6 +1096,7 @@ impl HttpCache {
             body: response.body.clone(),
             aborted: response.aborted.clone(),
             awaiting_body: Arc::new(Mutex::new(vec![])),
+            persisted: Arc::new(AtomicBool::new(false)),
             data: Measurable(MeasurableCachedResource {
                 metadata: cacheable_metadata,
                 location_url: response.location_url.clone(),
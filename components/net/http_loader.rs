@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::connector::{create_http_client, Connector, TlsConfig};
+use crate::connector::{create_http_client, is_proxy_authentication_error, Connector, TlsConfig};
 use crate::cookie;
 use crate::cookie_storage::CookieStorage;
 use crate::decoder::Decoder;
@@ -10,12 +10,17 @@ use crate::fetch::cors_cache::CorsCache;
 use crate::fetch::methods::{main_fetch, Data, DoneChannel, FetchContext, Target};
 use crate::hsts::HstsList;
 use crate::http_cache::{CacheKey, HttpCache};
-use crate::resource_thread::AuthCache;
+use crate::proxy::{ProxyConfig, ProxyCredentials};
+use crate::resource_thread::{AuthCache, AuthCacheEntry};
 use crossbeam_channel::{unbounded, Sender};
 use devtools_traits::{
     ChromeToDevtoolsControlMsg, DevtoolsControlMsg, HttpRequest as DevtoolsHttpRequest,
 };
 use devtools_traits::{HttpResponse as DevtoolsHttpResponse, NetworkEvent};
+use embedder_traits::PromptOrigin;
+use embedder_traits::{EmbedderMsg, EmbedderProxy, PromptCredentialsInput, PromptDefinition};
+use futures::stream;
+use futures::sync::mpsc;
 use headers::authorization::Basic;
 use headers::{AccessControlAllowCredentials, AccessControlAllowHeaders, HeaderMapExt};
 use headers::{
@@ -32,14 +37,17 @@ use http::header::{
 use http::{HeaderMap, Request as HyperRequest};
 use hyper::{Body, Client, Method, Response as HyperResponse, StatusCode};
 use hyper_serde::Serde;
+use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::router::ROUTER;
 use msg::constellation_msg::{HistoryStateId, PipelineId};
 use net_traits::pub_domains::reg_suffix;
 use net_traits::quality::{quality_to_value, Quality, QualityItem};
 use net_traits::request::Origin::Origin as SpecificOrigin;
 use net_traits::request::{is_cors_safelisted_method, is_cors_safelisted_request_header};
+use net_traits::request::{BodyChunkRequest, BodyChunkResponse};
 use net_traits::request::{CacheMode, CredentialsMode, Destination, Origin};
 use net_traits::request::{RedirectMode, Referrer, Request, RequestBuilder, RequestMode};
-use net_traits::request::{ResponseTainting, ServiceWorkersMode};
+use net_traits::request::{ResponseTainting, ServiceWorkersMode, Window};
 use net_traits::response::{HttpsState, Response, ResponseBody, ResponseType};
 use net_traits::{CookieSource, FetchMetadata, NetworkError, ReferrerPolicy};
 use net_traits::{
@@ -83,11 +91,19 @@ pub struct HttpState {
     pub http_cache_state: Mutex<HashMap<CacheKey, Arc<(Mutex<HttpCacheEntryState>, Condvar)>>>,
     pub auth_cache: RwLock<AuthCache>,
     pub history_states: RwLock<HashMap<HistoryStateId, Vec<u8>>>,
+    pub proxy_config: Arc<ProxyConfig>,
     pub client: Client<Connector, Body>,
+    /// Used to prompt the user for credentials.
+    pub embedder_proxy: Mutex<EmbedderProxy>,
 }
 
 impl HttpState {
-    pub fn new(tls_config: TlsConfig) -> HttpState {
+    pub fn new(
+        tls_config: TlsConfig,
+        proxy_config: ProxyConfig,
+        embedder_proxy: EmbedderProxy,
+    ) -> HttpState {
+        let proxy_config = Arc::new(proxy_config);
         HttpState {
             hsts_list: RwLock::new(HstsList::new()),
             cookie_jar: RwLock::new(CookieStorage::new(150)),
@@ -95,7 +111,13 @@ impl HttpState {
             history_states: RwLock::new(HashMap::new()),
             http_cache: RwLock::new(HttpCache::new()),
             http_cache_state: Mutex::new(HashMap::new()),
-            client: create_http_client(tls_config, HANDLE.lock().unwrap().executor()),
+            client: create_http_client(
+                tls_config,
+                proxy_config.clone(),
+                HANDLE.lock().unwrap().executor(),
+            ),
+            proxy_config,
+            embedder_proxy: Mutex::new(embedder_proxy),
         }
     }
 }
@@ -149,7 +171,7 @@ fn set_default_accept_encoding(headers: &mut HeaderMap) {
     // TODO(eijebong): Change this once typed headers are done
     headers.insert(
         header::ACCEPT_ENCODING,
-        HeaderValue::from_static("gzip, deflate, br"),
+        HeaderValue::from_static("gzip, deflate, br, zstd"),
     );
 }
 
@@ -282,14 +304,27 @@ pub fn determine_request_referrer(
     }
 }
 
+/// The origin of the top-level document a request is made for. A document request without one
+/// is a top-level navigation, whose document is its own top-level document.
+fn top_level_origin(request: &Request, url: &ServoUrl) -> Option<ImmutableOrigin> {
+    match request.top_level_origin {
+        Some(ref top_level_origin) => Some(top_level_origin.clone()),
+        None if request.destination == Destination::Document => Some(url.origin()),
+        None => None,
+    }
+}
+
 pub fn set_request_cookies(
     url: &ServoUrl,
+    top_level_origin: Option<&ImmutableOrigin>,
     headers: &mut HeaderMap,
     cookie_jar: &RwLock<CookieStorage>,
 ) {
     let mut cookie_jar = cookie_jar.write().unwrap();
     cookie_jar.remove_expired_cookies_for_url(url);
-    if let Some(cookie_list) = cookie_jar.cookies_for_url(url, CookieSource::HTTP) {
+    if let Some(cookie_list) =
+        cookie_jar.cookies_for_url_and_top_level(url, top_level_origin, CookieSource::HTTP)
+    {
         headers.insert(
             header::COOKIE,
             HeaderValue::from_bytes(cookie_list.as_bytes()).unwrap(),
@@ -297,23 +332,29 @@ pub fn set_request_cookies(
     }
 }
 
-fn set_cookie_for_url(cookie_jar: &RwLock<CookieStorage>, request: &ServoUrl, cookie_val: &str) {
+fn set_cookie_for_url(
+    cookie_jar: &RwLock<CookieStorage>,
+    request: &ServoUrl,
+    top_level_origin: Option<&ImmutableOrigin>,
+    cookie_val: &str,
+) {
     let mut cookie_jar = cookie_jar.write().unwrap();
     let source = CookieSource::HTTP;
 
     if let Some(cookie) = cookie::Cookie::from_cookie_string(cookie_val.into(), request, source) {
-        cookie_jar.push(cookie, request, source);
+        cookie_jar.push_for_top_level(cookie, request, top_level_origin, source);
     }
 }
 
 fn set_cookies_from_headers(
     url: &ServoUrl,
+    top_level_origin: Option<&ImmutableOrigin>,
     headers: &HeaderMap,
     cookie_jar: &RwLock<CookieStorage>,
 ) {
     for cookie in headers.get_all(header::SET_COOKIE) {
         if let Ok(cookie_str) = std::str::from_utf8(cookie.as_bytes()) {
-            set_cookie_for_url(&cookie_jar, &url, &cookie_str);
+            set_cookie_for_url(&cookie_jar, &url, top_level_origin, &cookie_str);
         }
     }
 }
@@ -393,12 +434,60 @@ fn auth_from_cache(
     }
 }
 
+/// Use the credentials stored in the auth cache for a proxy,
+/// unless they are the ones that were just rejected.
+fn proxy_credentials_from_cache(
+    auth_cache: &RwLock<AuthCache>,
+    proxy_url: &ServoUrl,
+    rejected: Option<&ProxyCredentials>,
+) -> Option<ProxyCredentials> {
+    let auth_cache = auth_cache.read().unwrap();
+    let entry = auth_cache
+        .entries
+        .get(&proxy_url.origin().ascii_serialization())?;
+    let credentials = ProxyCredentials {
+        user_name: entry.user_name.clone(),
+        password: entry.password.clone(),
+    };
+    if rejected == Some(&credentials) {
+        return None;
+    }
+    Some(credentials)
+}
+
+/// Ask the user for credentials through the embedder.
+fn prompt_user_for_credentials(embedder_proxy: &Mutex<EmbedderProxy>) -> Option<ProxyCredentials> {
+    let (ipc_sender, ipc_receiver) = ipc::channel().expect("Failed to create IPC channel!");
+    embedder_proxy.lock().unwrap().send((
+        None,
+        EmbedderMsg::Prompt(
+            PromptDefinition::Credentials(ipc_sender),
+            PromptOrigin::Trusted,
+        ),
+    ));
+    match ipc_receiver.recv() {
+        Ok(PromptCredentialsInput {
+            username: Some(user_name),
+            password,
+        }) => Some(ProxyCredentials {
+            user_name,
+            password: password.unwrap_or_default(),
+        }),
+        Ok(_) => None,
+        Err(e) => {
+            warn!("Failed to receive credentials from embedder ({:?}).", e);
+            None
+        },
+    }
+}
+
 fn obtain_response(
     client: &Client<Connector, Body>,
     url: &ServoUrl,
     method: &Method,
     headers: &HeaderMap,
     data: &Option<Vec<u8>>,
+    body_chunks: &Option<IpcSender<BodyChunkRequest>>,
     pipeline_id: &Option<PipelineId>,
     request_id: Option<&str>,
     is_xhr: bool,
@@ -437,7 +526,10 @@ fn obtain_response(
                 .replace("{", "%7B")
                 .replace("}", "%7D"),
         )
-        .body(request_body.clone().into());
+        .body(match *body_chunks {
+            Some(ref body_chunks) => stream_request_body(body_chunks),
+            None => request_body.clone().into(),
+        });
 
     // TODO: We currently don't know when the handhhake before the connection is done
     // so our best bet would be to set `secure_connection_start` here when we are currently
@@ -473,6 +565,15 @@ fn obtain_response(
     Box::new(
         client
             .request(request)
+            .or_else(|error| {
+                // A proxy refusing to open a tunnel is answered like a 407 response.
+                if !is_proxy_authentication_error(&error) {
+                    return Err(error);
+                }
+                let mut response = HyperResponse::new(Body::empty());
+                *response.status_mut() = StatusCode::PROXY_AUTHENTICATION_REQUIRED;
+                Ok(response)
+            })
             .and_then(move |res| {
                 let send_end = precise_time_ms();
 
@@ -510,6 +611,34 @@ fn obtain_response(
     )
 }
 
+/// A request body sent to the network as its chunks are read by the owner of the request.
+/// The body is cut short with an error if the reader fails, or goes away before it is done.
+fn stream_request_body(body_chunks: &IpcSender<BodyChunkRequest>) -> Body {
+    let (chunk_sender, chunk_receiver) = mpsc::unbounded();
+    let (ipc_sender, ipc_receiver) = ipc::channel().unwrap();
+    ROUTER.add_route(
+        ipc_receiver.to_opaque(),
+        Box::new(move |message| {
+            let chunk = message.to().unwrap_or(BodyChunkResponse::Error);
+            let _ = chunk_sender.unbounded_send(chunk);
+        }),
+    );
+    let _ = body_chunks.send(BodyChunkRequest::Connect(ipc_sender));
+
+    let chunks = chunk_receiver
+        .map_err(|()| "The request body channel failed")
+        .chain(stream::once(Ok(BodyChunkResponse::Error)))
+        .take_while(|chunk| match *chunk {
+            BodyChunkResponse::Done => Ok(false),
+            _ => Ok(true),
+        })
+        .and_then(|chunk| match chunk {
+            BodyChunkResponse::Chunk(bytes) => Ok(bytes),
+            _ => Err("The request body could not be read"),
+        });
+    Body::wrap_stream(chunks)
+}
+
 /// [HTTP fetch](https://fetch.spec.whatwg.org#http-fetch)
 pub fn http_fetch(
     request: &mut Request,
@@ -801,7 +930,7 @@ pub fn http_redirect_fetch(
         .status
         .as_ref()
         .map_or(true, |s| s.0 != StatusCode::SEE_OTHER) &&
-        request.body.as_ref().map_or(false, |b| b.is_empty())
+        (request.body.as_ref().map_or(false, |b| b.is_empty()) || request.body_chunks.is_some())
     {
         return Response::network_error(NetworkError::Internal("Request body is not done".into()));
     }
@@ -827,6 +956,7 @@ pub fn http_redirect_fetch(
         // Step 11.1
         request.method = Method::GET;
         request.body = None;
+        request.body_chunks = None;
         // Step 11.2
         for name in &[
             CONTENT_ENCODING,
@@ -933,6 +1063,8 @@ fn http_network_or_cache_fetch(
     };
 
     let content_length_value = match http_request.body {
+        // Step 5.6, the length of a body given as a stream is unknown.
+        None if http_request.body_chunks.is_some() => None,
         None => match http_request.method {
             // Step 5.5
             Method::POST | Method::PUT => Some(0),
@@ -1042,6 +1174,7 @@ fn http_network_or_cache_fetch(
         // XXXManishearth http_loader has block_cookies: support content blocking here too
         set_request_cookies(
             &current_url,
+            top_level_origin(http_request, &current_url).as_ref(),
             &mut http_request.headers,
             &context.state.cookie_jar,
         );
@@ -1075,7 +1208,25 @@ fn http_network_or_cache_fetch(
     }
 
     // Step 5.18
-    // TODO If there’s a proxy-authentication entry, use it as appropriate.
+    if let Some(proxy) = context.state.proxy_config.proxy_for_url(&current_url) {
+        if proxy.credentials().is_none() {
+            if let Some(credentials) =
+                proxy_credentials_from_cache(&context.state.auth_cache, proxy.url(), None)
+            {
+                proxy.set_credentials(credentials);
+            }
+        }
+        // Tunnelled requests are authenticated by the connector, when opening the tunnel.
+        if current_url.scheme() == "http" {
+            if let Some(credentials) = proxy.credentials() {
+                if let Ok(value) = HeaderValue::from_str(&credentials.to_header_value()) {
+                    http_request
+                        .headers
+                        .insert(header::PROXY_AUTHORIZATION, value);
+                }
+            }
+        }
+    }
 
     // If the cache is not ready to construct a response, wait.
     //
@@ -1112,6 +1263,18 @@ fn http_network_or_cache_fetch(
             }
         }
 
+        // Bring in a response stored by a previous session, if there is one,
+        // only holding on to the cache while checking for, and inserting, the response.
+        let disk_cache_load = match context.state.http_cache.read() {
+            Ok(http_cache) => http_cache.prepare_load_from_disk(&http_request),
+            Err(_) => None,
+        };
+        if let Some(loaded) = disk_cache_load.and_then(|load| load.load()) {
+            if let Ok(mut http_cache) = context.state.http_cache.write() {
+                http_cache.insert_loaded_from_disk(loaded);
+            }
+        }
+
         // Step 5.19
         if let Ok(http_cache) = context.state.http_cache.read() {
             if let Some(response_from_cache) =
@@ -1403,26 +1566,64 @@ fn http_network_or_cache_fetch(
     // Step 11
     if let Some((StatusCode::PROXY_AUTHENTICATION_REQUIRED, _)) = response.status.as_ref() {
         // Step 1
-        if request_has_no_window {
+        if http_request.window == Window::NoWindow {
             return Response::network_error(NetworkError::Internal(
                 "Can't find Window object".into(),
             ));
         }
 
+        // Only the proxies we were configured with can be authenticated with.
+        let proxy = match context
+            .state
+            .proxy_config
+            .proxy_for_url(&http_request.current_url())
+        {
+            Some(proxy) => proxy,
+            None => return response,
+        };
+
         // Step 2
         // TODO: Spec says requires testing on Proxy-Authenticate headers
 
         // Step 3
-        // FIXME: Prompt the user for proxy authentication credentials
+        // Credentials stored in the auth cache are tried before prompting the user,
+        // unless they are the ones that were just rejected.
+        let rejected = proxy.credentials();
+        let credentials = match proxy_credentials_from_cache(
+            &context.state.auth_cache,
+            proxy.url(),
+            rejected.as_ref(),
+        ) {
+            Some(credentials) => credentials,
+            None => match prompt_user_for_credentials(&context.state.embedder_proxy) {
+                Some(credentials) => {
+                    context.state.auth_cache.write().unwrap().entries.insert(
+                        proxy.url().origin().ascii_serialization(),
+                        AuthCacheEntry {
+                            user_name: credentials.user_name.clone(),
+                            password: credentials.password.clone(),
+                        },
+                    );
+                    credentials
+                },
+                // The user declined to enter credentials.
+                None => return response,
+            },
+        };
+        proxy.set_credentials(credentials);
 
-        // Wrong, but will have to do until we are able to prompt the user
-        // otherwise this creates an infinite loop
-        // We basically pretend that the user declined to enter credentials
-        return response;
+        // Make sure this is set to None,
+        // since we're about to start a new `http_network_or_cache_fetch`.
+        *done_chan = None;
 
         // Step 4
-        // return http_network_or_cache_fetch(request, authentication_fetch_flag,
-        //                                    cors_flag, done_chan, context);
+        response = http_network_or_cache_fetch(
+            http_request,
+            authentication_fetch_flag,
+            cors_flag,
+            done_chan,
+            context,
+        );
     }
 
     // Step 12
@@ -1479,6 +1680,11 @@ fn http_network_fetch(
     // Step 5
     let url = request.current_url();
 
+    // The fetch may have been aborted before a connection was even made.
+    if context.cancellation_listener.lock().unwrap().cancelled() {
+        return Response::network_error(NetworkError::Internal("Fetch aborted".into()));
+    }
+
     let request_id = context
         .devtools_chan
         .as_ref()
@@ -1501,6 +1707,7 @@ fn http_network_fetch(
         &request.method,
         &request.headers,
         &request.body,
+        &request.body_chunks,
         &request.pipeline_id,
         request_id.as_ref().map(Deref::deref),
         is_xhr,
@@ -1693,7 +1900,12 @@ fn http_network_fetch(
     // TODO this step isn't possible yet
     // Step 15
     if credentials_flag {
-        set_cookies_from_headers(&url, &response.headers, &context.state.cookie_jar);
+        set_cookies_from_headers(
+            &url,
+            top_level_origin(request, &url).as_ref(),
+            &response.headers,
+            &context.state.cookie_jar,
+        );
     }
     context
         .state
@@ -0,0 +1,534 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The in-memory representation of the indexed databases of an origin,
+//! and the operations run against them by transactions.
+
+use net_traits::indexeddb_thread::{AsyncOperation, AsyncResponse, BackendError, BackendResult};
+use net_traits::indexeddb_thread::{CursorIteration, DatabaseInfo, IndexInfo, IndexedDBRecord};
+use net_traits::indexeddb_thread::{IndexedDBCursorDirection, IndexedDBKeyRange, IndexedDBKeyType};
+use net_traits::indexeddb_thread::{KeyPath, ObjectStoreInfo};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+
+/// The largest number a key generator can produce, 2^53.
+/// https://w3c.github.io/IndexedDB/#key-generator-construct
+const MAX_GENERATED_KEY: u64 = 1 << 53;
+
+/// https://w3c.github.io/IndexedDB/#database-construct
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Database {
+    pub name: String,
+    pub version: u64,
+    /// Object stores are persisted in files of their own, so that a transaction
+    /// only rewrites the stores in its scope.
+    #[serde(skip)]
+    pub object_stores: BTreeMap<String, ObjectStore>,
+}
+
+/// https://w3c.github.io/IndexedDB/#object-store-construct
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ObjectStore {
+    pub name: String,
+    pub key_path: Option<KeyPath>,
+    pub auto_increment: bool,
+    /// The current number of the key generator, if the store has one.
+    current_number: u64,
+    #[serde(with = "sorted_entries")]
+    records: BTreeMap<IndexedDBKeyType, Vec<u8>>,
+    indexes: BTreeMap<String, Index>,
+}
+
+/// https://w3c.github.io/IndexedDB/#index-construct
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Index {
+    info: IndexInfo,
+    /// The primary keys of the records referenced by each index key.
+    #[serde(with = "sorted_entries")]
+    records: BTreeMap<IndexedDBKeyType, BTreeSet<IndexedDBKeyType>>,
+}
+
+/// Keys are not strings, so maps keyed by them are persisted as lists of entries.
+mod sorted_entries {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<K, V, S>(map: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Ord,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let entries: Vec<(K, V)> = Vec::deserialize(deserializer)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+/// The bounds of a key range, or `None` if the range can not contain any key.
+fn bounds(
+    range: &IndexedDBKeyRange,
+) -> Option<(Bound<&IndexedDBKeyType>, Bound<&IndexedDBKeyType>)> {
+    if let (Some(lower), Some(upper)) = (&range.lower, &range.upper) {
+        if lower > upper || (lower == upper && (range.lower_open || range.upper_open)) {
+            return None;
+        }
+    }
+    let lower = match range.lower {
+        Some(ref key) if range.lower_open => Bound::Excluded(key),
+        Some(ref key) => Bound::Included(key),
+        None => Bound::Unbounded,
+    };
+    let upper = match range.upper {
+        Some(ref key) if range.upper_open => Bound::Excluded(key),
+        Some(ref key) => Bound::Included(key),
+        None => Bound::Unbounded,
+    };
+    Some((lower, upper))
+}
+
+/// The entries of `map` whose key is in `range`, in ascending order.
+fn entries_in<'a, V>(
+    map: &'a BTreeMap<IndexedDBKeyType, V>,
+    range: Option<&IndexedDBKeyRange>,
+) -> Box<dyn DoubleEndedIterator<Item = (&'a IndexedDBKeyType, &'a V)> + 'a> {
+    match range {
+        None => Box::new(map.iter()),
+        Some(range) => match bounds(range) {
+            Some(bounds) => Box::new(map.range::<IndexedDBKeyType, _>(bounds)),
+            None => Box::new(std::iter::empty::<(&IndexedDBKeyType, &V)>()),
+        },
+    }
+}
+
+impl Database {
+    pub fn new(name: String) -> Database {
+        Database {
+            name,
+            version: 0,
+            object_stores: BTreeMap::new(),
+        }
+    }
+
+    pub fn info(&self) -> DatabaseInfo {
+        DatabaseInfo {
+            name: self.name.clone(),
+            version: self.version,
+            object_stores: self.object_stores.values().map(|s| s.info()).collect(),
+        }
+    }
+
+    fn object_store(&self, name: &str) -> BackendResult<&ObjectStore> {
+        self.object_stores.get(name).ok_or(BackendError::NotFound)
+    }
+
+    fn object_store_mut(&mut self, name: &str) -> BackendResult<&mut ObjectStore> {
+        self.object_stores
+            .get_mut(name)
+            .ok_or(BackendError::NotFound)
+    }
+
+    /// Run an operation of a running transaction against this database.
+    pub fn execute(&mut self, operation: AsyncOperation) -> BackendResult<AsyncResponse> {
+        match operation {
+            AsyncOperation::CreateObjectStore(info) => {
+                if self.object_stores.contains_key(&info.name) {
+                    return Err(BackendError::Constraint);
+                }
+                self.object_stores
+                    .insert(info.name.clone(), ObjectStore::new(info));
+                Ok(AsyncResponse::Void)
+            },
+            AsyncOperation::DeleteObjectStore(name) => {
+                self.object_stores
+                    .remove(&name)
+                    .ok_or(BackendError::NotFound)?;
+                Ok(AsyncResponse::Void)
+            },
+            AsyncOperation::CreateIndex {
+                store,
+                index,
+                entries,
+            } => {
+                self.object_store_mut(&store)?
+                    .create_index(index, entries)?;
+                Ok(AsyncResponse::Void)
+            },
+            AsyncOperation::DeleteIndex { store, name } => {
+                self.object_store_mut(&store)?
+                    .indexes
+                    .remove(&name)
+                    .ok_or(BackendError::NotFound)?;
+                Ok(AsyncResponse::Void)
+            },
+            AsyncOperation::Put {
+                store,
+                key,
+                value,
+                index_keys,
+                overwrite,
+            } => self
+                .object_store_mut(&store)?
+                .put(key, value, index_keys, overwrite)
+                .map(AsyncResponse::Key),
+            AsyncOperation::Get {
+                store,
+                index,
+                range,
+                key_only,
+            } => {
+                let store = self.object_store(&store)?;
+                let record = store
+                    .records_in(index.as_ref(), Some(&range))?
+                    .next()
+                    .map(|(key, primary_key)| store.record(key, primary_key, key_only));
+                Ok(AsyncResponse::Record(record))
+            },
+            AsyncOperation::GetAll {
+                store,
+                index,
+                range,
+                count,
+                key_only,
+            } => {
+                let store = self.object_store(&store)?;
+                let count = count.map_or(usize::max_value(), |count| count as usize);
+                let records = store
+                    .records_in(index.as_ref(), range.as_ref())?
+                    .take(count)
+                    .map(|(key, primary_key)| store.record(key, primary_key, key_only))
+                    .collect();
+                Ok(AsyncResponse::Records(records))
+            },
+            AsyncOperation::Count {
+                store,
+                index,
+                range,
+            } => {
+                let count = self
+                    .object_store(&store)?
+                    .records_in(index.as_ref(), range.as_ref())?
+                    .count();
+                Ok(AsyncResponse::Count(count as u64))
+            },
+            AsyncOperation::Delete { store, range } => {
+                self.object_store_mut(&store)?.delete(&range);
+                Ok(AsyncResponse::Void)
+            },
+            AsyncOperation::Clear(store) => {
+                self.object_store_mut(&store)?.clear();
+                Ok(AsyncResponse::Void)
+            },
+            AsyncOperation::Iterate(iteration) => {
+                let record = self.object_store(&iteration.store)?.iterate(&iteration)?;
+                Ok(AsyncResponse::Record(record))
+            },
+        }
+    }
+}
+
+impl ObjectStore {
+    fn new(info: ObjectStoreInfo) -> ObjectStore {
+        ObjectStore {
+            name: info.name,
+            key_path: info.key_path,
+            auto_increment: info.auto_increment,
+            current_number: 1,
+            records: BTreeMap::new(),
+            indexes: BTreeMap::new(),
+        }
+    }
+
+    fn info(&self) -> ObjectStoreInfo {
+        ObjectStoreInfo {
+            name: self.name.clone(),
+            key_path: self.key_path.clone(),
+            auto_increment: self.auto_increment,
+            indexes: self.indexes.values().map(|i| i.info.clone()).collect(),
+        }
+    }
+
+    /// https://w3c.github.io/IndexedDB/#generate-a-key
+    fn generate_key(&mut self) -> BackendResult<IndexedDBKeyType> {
+        if self.current_number > MAX_GENERATED_KEY {
+            return Err(BackendError::Constraint);
+        }
+        let key = self.current_number;
+        self.current_number += 1;
+        Ok(IndexedDBKeyType::Number(key as f64))
+    }
+
+    /// https://w3c.github.io/IndexedDB/#possibly-update-the-key-generator
+    fn possibly_update_key_generator(&mut self, key: &IndexedDBKeyType) {
+        if let IndexedDBKeyType::Number(number) = *key {
+            let value = number.min(MAX_GENERATED_KEY as f64).floor();
+            if value >= self.current_number as f64 {
+                self.current_number = value as u64 + 1;
+            }
+        }
+    }
+
+    /// https://w3c.github.io/IndexedDB/#store-a-record-into-an-object-store
+    fn put(
+        &mut self,
+        key: Option<IndexedDBKeyType>,
+        value: Vec<u8>,
+        mut index_keys: Vec<(String, Vec<IndexedDBKeyType>)>,
+        overwrite: bool,
+    ) -> BackendResult<IndexedDBKeyType> {
+        // Step 1.
+        let key = match key {
+            Some(key) => {
+                if self.auto_increment {
+                    self.possibly_update_key_generator(&key);
+                }
+                key
+            },
+            None => {
+                let key = self.generate_key()?;
+                // Script could not evaluate the key path of an index on the generated key,
+                // which is only known now.
+                if let Some(ref key_path) = self.key_path {
+                    for index in self.indexes.values() {
+                        if index.info.key_path == *key_path {
+                            index_keys.retain(|(name, _)| *name != index.info.name);
+                            index_keys.push((index.info.name.clone(), vec![key.clone()]));
+                        }
+                    }
+                }
+                key
+            },
+        };
+
+        // Step 2.
+        if !overwrite && self.records.contains_key(&key) {
+            return Err(BackendError::Constraint);
+        }
+
+        // Step 6.3, checked upfront so that a failed operation leaves the store unchanged.
+        for (name, keys) in &index_keys {
+            let index = match self.indexes.get(name) {
+                Some(index) => index,
+                None => continue,
+            };
+            if !index.info.unique {
+                continue;
+            }
+            let taken = keys.iter().any(|index_key| {
+                index.records.get(index_key).map_or(false, |primary_keys| {
+                    primary_keys.iter().any(|primary_key| *primary_key != key)
+                })
+            });
+            if taken {
+                return Err(BackendError::Constraint);
+            }
+        }
+
+        // Step 3.
+        self.remove_record(&key);
+
+        // Step 4.
+        self.records.insert(key.clone(), value);
+
+        // Step 5.
+        for (name, keys) in index_keys {
+            if let Some(index) = self.indexes.get_mut(&name) {
+                for index_key in keys {
+                    index
+                        .records
+                        .entry(index_key)
+                        .or_insert_with(BTreeSet::new)
+                        .insert(key.clone());
+                }
+            }
+        }
+
+        Ok(key)
+    }
+
+    /// Remove a record, and the index records referencing it.
+    fn remove_record(&mut self, key: &IndexedDBKeyType) {
+        if self.records.remove(key).is_none() {
+            return;
+        }
+        for index in self.indexes.values_mut() {
+            for primary_keys in index.records.values_mut() {
+                primary_keys.remove(key);
+            }
+            index
+                .records
+                .retain(|_, primary_keys| !primary_keys.is_empty());
+        }
+    }
+
+    /// https://w3c.github.io/IndexedDB/#delete-records-from-an-object-store
+    fn delete(&mut self, range: &IndexedDBKeyRange) {
+        let keys: Vec<IndexedDBKeyType> = entries_in(&self.records, Some(range))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            self.remove_record(&key);
+        }
+    }
+
+    /// https://w3c.github.io/IndexedDB/#clear-an-object-store
+    fn clear(&mut self) {
+        self.records.clear();
+        for index in self.indexes.values_mut() {
+            index.records.clear();
+        }
+    }
+
+    /// https://w3c.github.io/IndexedDB/#dom-idbobjectstore-createindex
+    fn create_index(
+        &mut self,
+        info: IndexInfo,
+        entries: Vec<(IndexedDBKeyType, Vec<IndexedDBKeyType>)>,
+    ) -> BackendResult<()> {
+        if self.indexes.contains_key(&info.name) {
+            return Err(BackendError::Constraint);
+        }
+        let mut records: BTreeMap<IndexedDBKeyType, BTreeSet<IndexedDBKeyType>> = BTreeMap::new();
+        for (primary_key, keys) in entries {
+            for index_key in keys {
+                let primary_keys = records.entry(index_key).or_insert_with(BTreeSet::new);
+                if info.unique && !primary_keys.is_empty() {
+                    return Err(BackendError::Constraint);
+                }
+                primary_keys.insert(primary_key.clone());
+            }
+        }
+        self.indexes
+            .insert(info.name.clone(), Index { info, records });
+        Ok(())
+    }
+
+    /// The `(key, primary key)` pairs in range, sorted by key and then primary key,
+    /// of either this store or one of its indexes.
+    fn records_in<'a>(
+        &'a self,
+        index: Option<&String>,
+        range: Option<&IndexedDBKeyRange>,
+    ) -> BackendResult<
+        Box<dyn DoubleEndedIterator<Item = (&'a IndexedDBKeyType, &'a IndexedDBKeyType)> + 'a>,
+    > {
+        match index {
+            None => Ok(Box::new(
+                entries_in(&self.records, range).map(|(key, _)| (key, key)),
+            )),
+            Some(name) => {
+                let index = self.indexes.get(name).ok_or(BackendError::NotFound)?;
+                Ok(Box::new(entries_in(&index.records, range).flat_map(
+                    |(key, primary_keys)| {
+                        primary_keys
+                            .iter()
+                            .map(move |primary_key| (key, primary_key))
+                    },
+                )))
+            },
+        }
+    }
+
+    fn record(
+        &self,
+        key: &IndexedDBKeyType,
+        primary_key: &IndexedDBKeyType,
+        key_only: bool,
+    ) -> IndexedDBRecord {
+        IndexedDBRecord {
+            key: key.clone(),
+            primary_key: primary_key.clone(),
+            value: if key_only {
+                None
+            } else {
+                self.records.get(primary_key).cloned()
+            },
+        }
+    }
+
+    /// https://w3c.github.io/IndexedDB/#iterate-a-cursor
+    fn iterate(&self, iteration: &CursorIteration) -> BackendResult<Option<IndexedDBRecord>> {
+        let records: Vec<(&IndexedDBKeyType, &IndexedDBKeyType)> = self
+            .records_in(iteration.index.as_ref(), iteration.range.as_ref())?
+            .collect();
+        let is_index = iteration.index.is_some();
+        let mut position = iteration.position.clone();
+        let mut object_store_position = iteration.object_store_position.clone();
+        let mut found = None;
+        let mut count = iteration.count.max(1);
+
+        while count > 0 {
+            let matches = |&&(key, primary_key): &&(&IndexedDBKeyType, &IndexedDBKeyType),
+                           forward: bool,
+                           unique: bool| {
+                let before_target = |a: &IndexedDBKeyType, b: &IndexedDBKeyType| {
+                    if forward {
+                        a < b
+                    } else {
+                        a > b
+                    }
+                };
+                if let Some(ref target) = iteration.key {
+                    if before_target(key, target) {
+                        return false;
+                    }
+                    if let Some(ref target_primary_key) = iteration.primary_key {
+                        if key == target && before_target(primary_key, target_primary_key) {
+                            return false;
+                        }
+                    }
+                }
+                match position {
+                    Some(ref position) if is_index && !unique => {
+                        let object_store_position = object_store_position.as_ref();
+                        !before_target(key, position) &&
+                            (key != position ||
+                                object_store_position.map_or(true, |object_store_position| {
+                                    before_target(object_store_position, primary_key)
+                                }))
+                    },
+                    Some(ref position) => before_target(position, key),
+                    None => true,
+                }
+            };
+
+            let record = match iteration.direction {
+                IndexedDBCursorDirection::Next => records.iter().find(|r| matches(r, true, false)),
+                IndexedDBCursorDirection::NextUnique => {
+                    records.iter().find(|r| matches(r, true, true))
+                },
+                IndexedDBCursorDirection::Prev => {
+                    records.iter().rev().find(|r| matches(r, false, false))
+                },
+                IndexedDBCursorDirection::PrevUnique => records
+                    .iter()
+                    .rev()
+                    .find(|r| matches(r, false, true))
+                    // The first record with the same key.
+                    .and_then(|&(key, _)| records.iter().find(|&&(other, _)| other == key)),
+            };
+
+            let &(key, primary_key) = match record {
+                Some(record) => record,
+                None => return Ok(None),
+            };
+            position = Some(key.clone());
+            if is_index {
+                object_store_position = Some(primary_key.clone());
+            }
+            found = Some((key, primary_key));
+            count -= 1;
+        }
+
+        Ok(found.map(|(key, primary_key)| self.record(key, primary_key, iteration.key_only)))
+    }
+}
@@ -0,0 +1,793 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::indexeddb_backend::{Database, ObjectStore};
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use net_traits::indexeddb_thread::{AsyncOperation, AsyncResponse, BackendError, BackendResult};
+use net_traits::indexeddb_thread::{ConnectionEvent, IndexedDBThreadMsg, IndexedDBTxnId};
+use net_traits::indexeddb_thread::{IndexedDBTxnMode, OpenResponse};
+use servo_url::ImmutableOrigin;
+use std::borrow::ToOwned;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+pub trait IndexedDBThreadFactory {
+    fn new(config_dir: Option<PathBuf>) -> Self;
+}
+
+impl IndexedDBThreadFactory for IpcSender<IndexedDBThreadMsg> {
+    /// Create an IndexedDB thread
+    fn new(config_dir: Option<PathBuf>) -> IpcSender<IndexedDBThreadMsg> {
+        let (chan, port) = ipc::channel().unwrap();
+        thread::Builder::new()
+            .name("IndexedDBManager".to_owned())
+            .spawn(move || {
+                IndexedDBManager::new(port, config_dir).start();
+            })
+            .expect("Thread spawning failed");
+        chan
+    }
+}
+
+/// Databases are partitioned by origin, and then identified by their name.
+type DatabaseKey = (String, String);
+
+fn database_key(origin: &ImmutableOrigin, name: String) -> DatabaseKey {
+    (origin.ascii_serialization(), name)
+}
+
+/// The directory, in the config dir, where each database is persisted in a directory of its own.
+const DATABASES_DIR: &str = "indexeddb";
+
+/// The file, in the directory of a database, that holds its origin, name, version,
+/// and the names of its object stores.
+const DATABASE_FILE: &str = "database.json";
+
+/// The directory, in the directory of a database, where each object store is persisted
+/// in a file of its own.
+const OBJECT_STORES_DIR: &str = "stores";
+
+fn file_name(name: &str) -> String {
+    base64::encode_config(name, base64::URL_SAFE_NO_PAD)
+}
+
+fn database_dir(config_dir: &Path, key: &DatabaseKey) -> PathBuf {
+    config_dir
+        .join(DATABASES_DIR)
+        .join(file_name(&format!("{}\n{}", key.0, key.1)))
+}
+
+fn object_store_path(database_dir: &Path, name: &str) -> PathBuf {
+    database_dir
+        .join(OBJECT_STORES_DIR)
+        .join(file_name(name))
+        .with_extension("json")
+}
+
+/// Read the persisted databases of all the origins. A database that can not be read
+/// is moved aside, so that it is neither lost nor overwritten by a new database.
+fn read_databases(config_dir: &Path) -> HashMap<String, BTreeMap<String, Database>> {
+    let mut databases = HashMap::new();
+    let entries = match fs::read_dir(config_dir.join(DATABASES_DIR)) {
+        Ok(entries) => entries,
+        Err(ref why) if why.kind() == io::ErrorKind::NotFound => return databases,
+        Err(why) => {
+            warn!("Couldn't read the IndexedDB directory: {}", why);
+            return databases;
+        },
+    };
+    for entry in entries {
+        let dir = match entry {
+            Ok(entry) => entry.path(),
+            Err(why) => {
+                warn!("Couldn't read the IndexedDB directory: {}", why);
+                continue;
+            },
+        };
+        // Database directory names never have an extension,
+        // unlike the databases that were moved aside.
+        if !dir.is_dir() || dir.extension().is_some() {
+            continue;
+        }
+        match read_database(&dir) {
+            Ok((origin, database)) => {
+                databases
+                    .entry(origin)
+                    .or_insert_with(BTreeMap::new)
+                    .insert(database.name.clone(), database);
+            },
+            Err(why) => {
+                warn!("Couldn't read {}: {}", dir.display(), why);
+                let quarantine = dir.with_extension("corrupt");
+                let _ = fs::remove_dir_all(&quarantine);
+                if let Err(why) = fs::rename(&dir, &quarantine) {
+                    warn!("Couldn't move {} aside: {}", dir.display(), why);
+                }
+            },
+        }
+    }
+    databases
+}
+
+fn read_database(dir: &Path) -> io::Result<(String, Database)> {
+    let file = File::open(dir.join(DATABASE_FILE))?;
+    let (origin, mut database, names): (String, Database, Vec<String>) =
+        serde_json::from_reader(BufReader::new(file))?;
+    for name in names {
+        let file = File::open(object_store_path(dir, &name))?;
+        let object_store: ObjectStore = serde_json::from_reader(BufReader::new(file))?;
+        database.object_stores.insert(name, object_store);
+    }
+    Ok((origin, database))
+}
+
+/// Replace the contents of files, only once all of them are completely written,
+/// so that a failed write leaves their previous contents in place.
+fn write_files(files: &[(PathBuf, Vec<u8>)]) -> io::Result<()> {
+    for (path, contents) in files {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let partial_path = path.with_extension("partial");
+        if let Err(why) = fs::write(&partial_path, contents) {
+            let _ = fs::remove_file(&partial_path);
+            return Err(why);
+        }
+    }
+    for (path, _) in files {
+        fs::rename(path.with_extension("partial"), path)?;
+    }
+    Ok(())
+}
+
+/// https://w3c.github.io/IndexedDB/#connection
+struct Connection {
+    database: DatabaseKey,
+    events: IpcSender<ConnectionEvent>,
+    /// https://w3c.github.io/IndexedDB/#connection-close-pending-flag
+    close_pending: bool,
+}
+
+/// What to restore when a transaction is aborted.
+enum Snapshot {
+    /// The whole database, or `None` if it was created by the upgrade transaction.
+    Database(Option<Database>),
+    /// The object stores in the scope of a readwrite transaction.
+    ObjectStores(Vec<ObjectStore>),
+}
+
+/// https://w3c.github.io/IndexedDB/#transaction-construct
+struct Transaction {
+    id: IndexedDBTxnId,
+    database: DatabaseKey,
+    scope: Vec<String>,
+    mode: IndexedDBTxnMode,
+    /// Set once no overlapping transaction created earlier is still running.
+    started: bool,
+    operations: VecDeque<(IpcSender<BackendResult<AsyncResponse>>, AsyncOperation)>,
+    commit_requested: bool,
+    snapshot: Option<Snapshot>,
+}
+
+impl Transaction {
+    /// https://w3c.github.io/IndexedDB/#transaction-lifetime
+    /// Two transactions can run concurrently unless their scopes overlap
+    /// and one of them can write.
+    fn conflicts_with(&self, other: &Transaction) -> bool {
+        if self.database != other.database {
+            return false;
+        }
+        if self.mode == IndexedDBTxnMode::Readonly && other.mode == IndexedDBTxnMode::Readonly {
+            return false;
+        }
+        if self.mode == IndexedDBTxnMode::Versionchange ||
+            other.mode == IndexedDBTxnMode::Versionchange
+        {
+            return true;
+        }
+        self.scope.iter().any(|name| other.scope.contains(name))
+    }
+}
+
+/// A request waiting in the connection queue of a database.
+/// https://w3c.github.io/IndexedDB/#connection-queue
+enum PendingRequest {
+    Open {
+        sender: IpcSender<OpenResponse>,
+        events: IpcSender<ConnectionEvent>,
+        version: Option<u64>,
+        notified: bool,
+        blocked: bool,
+    },
+    Delete {
+        sender: IpcSender<OpenResponse>,
+        notified: bool,
+        blocked: bool,
+    },
+}
+
+struct IndexedDBManager {
+    port: IpcReceiver<IndexedDBThreadMsg>,
+    /// The databases of each origin, keyed by name.
+    databases: HashMap<String, BTreeMap<String, Database>>,
+    connections: HashMap<u64, Connection>,
+    next_connection_id: u64,
+    /// Transactions, in the order they were created in.
+    transactions: Vec<Transaction>,
+    pending_requests: HashMap<DatabaseKey, VecDeque<PendingRequest>>,
+    config_dir: Option<PathBuf>,
+}
+
+impl IndexedDBManager {
+    fn new(port: IpcReceiver<IndexedDBThreadMsg>, config_dir: Option<PathBuf>) -> IndexedDBManager {
+        IndexedDBManager {
+            port,
+            databases: config_dir
+                .as_ref()
+                .map_or_else(HashMap::new, |config_dir| read_databases(config_dir)),
+            connections: HashMap::new(),
+            next_connection_id: 1,
+            transactions: Vec::new(),
+            pending_requests: HashMap::new(),
+            config_dir,
+        }
+    }
+}
+
+impl IndexedDBManager {
+    fn start(&mut self) {
+        loop {
+            match self.port.recv().unwrap() {
+                IndexedDBThreadMsg::Open {
+                    sender,
+                    events,
+                    origin,
+                    name,
+                    version,
+                } => self.queue_request(
+                    database_key(&origin, name),
+                    PendingRequest::Open {
+                        sender,
+                        events,
+                        version,
+                        notified: false,
+                        blocked: false,
+                    },
+                ),
+                IndexedDBThreadMsg::DeleteDatabase(sender, origin, name) => self.queue_request(
+                    database_key(&origin, name),
+                    PendingRequest::Delete {
+                        sender,
+                        notified: false,
+                        blocked: false,
+                    },
+                ),
+                IndexedDBThreadMsg::Databases(sender, origin) => {
+                    self.databases_of_origin(sender, origin)
+                },
+                IndexedDBThreadMsg::Close(connection) => {
+                    if let Some(connection) = self.connections.get_mut(&connection) {
+                        connection.close_pending = true;
+                    }
+                },
+                IndexedDBThreadMsg::CreateTransaction { id, scope, mode } => {
+                    self.create_transaction(id, scope, mode)
+                },
+                IndexedDBThreadMsg::Operation(id, sender, operation) => {
+                    match self.transactions.iter_mut().find(|txn| txn.id == id) {
+                        Some(txn) => txn.operations.push_back((sender, operation)),
+                        None => {
+                            let _ = sender.send(Err(BackendError::Abort));
+                        },
+                    }
+                },
+                IndexedDBThreadMsg::Commit(id) => {
+                    if let Some(txn) = self.transactions.iter_mut().find(|txn| txn.id == id) {
+                        txn.commit_requested = true;
+                    }
+                },
+                IndexedDBThreadMsg::Abort(id) => self.abort_transaction(id, BackendError::Abort),
+                IndexedDBThreadMsg::Exit(sender) => {
+                    // Nothing to do since committed transactions are saved eagerly.
+                    let _ = sender.send(());
+                    break;
+                },
+            }
+            self.make_progress();
+        }
+    }
+
+    /// Persist the committed state of a database, or forget it if it was deleted.
+    /// Only the object stores in `scope` are written, or the whole database if it is `None`.
+    fn save_state(&self, key: &DatabaseKey, scope: Option<&[String]>) -> io::Result<()> {
+        let dir = match self.config_dir {
+            Some(ref config_dir) => database_dir(config_dir, key),
+            None => return Ok(()),
+        };
+        let database = match self.database(key) {
+            Some(database) => database,
+            None => {
+                return match fs::remove_dir_all(&dir) {
+                    Err(ref why) if why.kind() == io::ErrorKind::NotFound => Ok(()),
+                    result => result,
+                };
+            },
+        };
+
+        let mut files = vec![];
+        let stores = database
+            .object_stores
+            .iter()
+            .filter(|&(name, _)| scope.map_or(true, |scope| scope.contains(name)));
+        for (name, object_store) in stores {
+            let contents = serde_json::to_vec(object_store)?;
+            files.push((object_store_path(&dir, name), contents));
+        }
+        if scope.is_none() {
+            // Written last, so that it never lists object stores that are not written yet.
+            let names: Vec<&String> = database.object_stores.keys().collect();
+            let contents = serde_json::to_vec(&(&key.0, database, names))?;
+            files.push((dir.join(DATABASE_FILE), contents));
+        }
+        write_files(&files)?;
+
+        if scope.is_none() {
+            // Remove the files of the object stores that were deleted.
+            let entries = match fs::read_dir(dir.join(OBJECT_STORES_DIR)) {
+                Ok(entries) => entries,
+                Err(ref why) if why.kind() == io::ErrorKind::NotFound => return Ok(()),
+                Err(why) => return Err(why),
+            };
+            for entry in entries {
+                let path = entry?.path();
+                if !files.iter().any(|(written, _)| *written == path) {
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn database(&self, key: &DatabaseKey) -> Option<&Database> {
+        self.databases
+            .get(&key.0)
+            .and_then(|databases| databases.get(&key.1))
+    }
+
+    fn database_mut(&mut self, key: &DatabaseKey) -> Option<&mut Database> {
+        self.databases
+            .get_mut(&key.0)
+            .and_then(|databases| databases.get_mut(&key.1))
+    }
+
+    fn databases_of_origin(&self, sender: IpcSender<Vec<(String, u64)>>, origin: ImmutableOrigin) {
+        let databases =
+            self.databases
+                .get(&origin.ascii_serialization())
+                .map_or(vec![], |databases| {
+                    databases
+                        .values()
+                        .filter(|database| database.version > 0)
+                        .map(|database| (database.name.clone(), database.version))
+                        .collect()
+                });
+        let _ = sender.send(databases);
+    }
+
+    fn queue_request(&mut self, key: DatabaseKey, request: PendingRequest) {
+        self.pending_requests
+            .entry(key)
+            .or_insert_with(VecDeque::new)
+            .push_back(request);
+    }
+
+    fn create_transaction(
+        &mut self,
+        id: IndexedDBTxnId,
+        scope: Vec<String>,
+        mode: IndexedDBTxnMode,
+    ) {
+        let database = match self.connections.get(&id.connection) {
+            Some(connection) => connection.database.clone(),
+            None => {
+                warn!("Transaction created on a closed connection");
+                return;
+            },
+        };
+        self.transactions.push(Transaction {
+            id,
+            database,
+            scope,
+            mode,
+            started: false,
+            operations: VecDeque::new(),
+            commit_requested: false,
+            snapshot: None,
+        });
+    }
+
+    /// Start transactions, run their operations, finish them, and serve the connection
+    /// queues, until nothing can proceed anymore.
+    fn make_progress(&mut self) {
+        loop {
+            let mut progress = self.start_transactions();
+            progress |= self.run_transactions();
+            progress |= self.close_connections();
+            progress |= self.process_pending_requests();
+            if !progress {
+                break;
+            }
+        }
+    }
+
+    fn start_transactions(&mut self) -> bool {
+        let mut progress = false;
+        for i in 0..self.transactions.len() {
+            if self.transactions[i].started {
+                continue;
+            }
+            let blocked = self.transactions[..i]
+                .iter()
+                .any(|earlier| earlier.conflicts_with(&self.transactions[i]));
+            if blocked {
+                continue;
+            }
+            let snapshot = match self.transactions[i].mode {
+                IndexedDBTxnMode::Readwrite => {
+                    let database = self.database(&self.transactions[i].database);
+                    let stores = self.transactions[i]
+                        .scope
+                        .iter()
+                        .filter_map(|name| database.and_then(|db| db.object_stores.get(name)))
+                        .cloned()
+                        .collect();
+                    Some(Snapshot::ObjectStores(stores))
+                },
+                _ => None,
+            };
+            let txn = &mut self.transactions[i];
+            txn.snapshot = snapshot;
+            txn.started = true;
+            progress = true;
+        }
+        progress
+    }
+
+    fn run_transactions(&mut self) -> bool {
+        let mut progress = false;
+        let mut finished = vec![];
+        for i in 0..self.transactions.len() {
+            if !self.transactions[i].started {
+                continue;
+            }
+            while let Some((sender, operation)) = self.transactions[i].operations.pop_front() {
+                let key = self.transactions[i].database.clone();
+                let result = match self.database_mut(&key) {
+                    Some(database) => database.execute(operation),
+                    None => Err(BackendError::NotFound),
+                };
+                let _ = sender.send(result);
+                progress = true;
+            }
+            if self.transactions[i].commit_requested {
+                finished.push(self.transactions[i].id);
+            }
+        }
+        for id in finished {
+            self.commit_transaction(id);
+            progress = true;
+        }
+        progress
+    }
+
+    /// https://w3c.github.io/IndexedDB/#commit-a-transaction
+    fn commit_transaction(&mut self, id: IndexedDBTxnId) {
+        let position = match self.transactions.iter().position(|txn| txn.id == id) {
+            Some(position) => position,
+            None => return,
+        };
+        let txn = &self.transactions[position];
+        let scope = match txn.mode {
+            IndexedDBTxnMode::Readonly => None,
+            IndexedDBTxnMode::Readwrite => Some(Some(&txn.scope[..])),
+            IndexedDBTxnMode::Versionchange => Some(None),
+        };
+        if let Some(scope) = scope {
+            if let Err(why) = self.save_state(&txn.database, scope) {
+                warn!(
+                    "Couldn't save IndexedDB database {:?}: {}",
+                    txn.database, why
+                );
+                self.abort_transaction(id, BackendError::Unknown);
+                return;
+            }
+        }
+        self.transactions.remove(position);
+        self.notify_transaction_finished(id, Ok(()));
+    }
+
+    /// https://w3c.github.io/IndexedDB/#abort-a-transaction
+    fn abort_transaction(&mut self, id: IndexedDBTxnId, error: BackendError) {
+        let position = match self.transactions.iter().position(|txn| txn.id == id) {
+            Some(position) => position,
+            None => return,
+        };
+        let txn = self.transactions.remove(position);
+        match txn.snapshot {
+            Some(Snapshot::Database(Some(database))) => {
+                if let Some(databases) = self.databases.get_mut(&txn.database.0) {
+                    databases.insert(txn.database.1.clone(), database);
+                }
+            },
+            Some(Snapshot::Database(None)) => {
+                if let Some(databases) = self.databases.get_mut(&txn.database.0) {
+                    databases.remove(&txn.database.1);
+                }
+            },
+            Some(Snapshot::ObjectStores(stores)) => {
+                if let Some(database) = self.database_mut(&txn.database) {
+                    for store in stores {
+                        database.object_stores.insert(store.name.clone(), store);
+                    }
+                }
+            },
+            None => {},
+        }
+        self.notify_transaction_finished(id, Err(error));
+        if txn.mode == IndexedDBTxnMode::Versionchange {
+            // https://w3c.github.io/IndexedDB/#abort-an-upgrade-transaction
+            // The connection opened by the aborted upgrade is closed.
+            self.connections.remove(&id.connection);
+        }
+    }
+
+    fn notify_transaction_finished(&mut self, id: IndexedDBTxnId, result: BackendResult<()>) {
+        let event = ConnectionEvent::TransactionFinished {
+            serial: id.serial,
+            result,
+        };
+        if let Some(connection) = self.connections.get(&id.connection) {
+            let _ = connection.events.send(event);
+        }
+    }
+
+    /// https://w3c.github.io/IndexedDB/#close-a-database-connection
+    /// A connection is closed once its close pending flag is set
+    /// and all its transactions are finished.
+    fn close_connections(&mut self) -> bool {
+        let transactions = &self.transactions;
+        let closed: Vec<u64> = self
+            .connections
+            .iter()
+            .filter(|&(id, connection)| {
+                connection.close_pending && !transactions.iter().any(|txn| txn.id.connection == *id)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &closed {
+            self.connections.remove(id);
+        }
+        !closed.is_empty()
+    }
+
+    /// Close a connection whose script is gone, aborting its transactions.
+    fn drop_connection(&mut self, connection: u64) {
+        let transactions: Vec<IndexedDBTxnId> = self
+            .transactions
+            .iter()
+            .filter(|txn| txn.id.connection == connection)
+            .map(|txn| txn.id)
+            .collect();
+        for id in transactions {
+            self.abort_transaction(id, BackendError::Abort);
+        }
+        self.connections.remove(&connection);
+    }
+
+    fn process_pending_requests(&mut self) -> bool {
+        let keys: Vec<DatabaseKey> = self.pending_requests.keys().cloned().collect();
+        let mut progress = false;
+        for key in keys {
+            while self.process_pending_request(&key) {
+                progress = true;
+            }
+            if self
+                .pending_requests
+                .get(&key)
+                .map_or(false, |queue| queue.is_empty())
+            {
+                self.pending_requests.remove(&key);
+            }
+        }
+        progress
+    }
+
+    /// Serve the request at the front of the connection queue of a database,
+    /// returning whether it was served.
+    fn process_pending_request(&mut self, key: &DatabaseKey) -> bool {
+        // Wait for a running upgrade transaction to finish.
+        let upgrading = self
+            .transactions
+            .iter()
+            .any(|txn| txn.database == *key && txn.mode == IndexedDBTxnMode::Versionchange);
+        if upgrading {
+            return false;
+        }
+        let old_version = self.database(key).map_or(0, |database| database.version);
+        let mut request = match self
+            .pending_requests
+            .get_mut(key)
+            .and_then(|q| q.pop_front())
+        {
+            Some(request) => request,
+            None => return false,
+        };
+        let served = match request {
+            PendingRequest::Open {
+                ref sender,
+                ref events,
+                version,
+                ref mut notified,
+                ref mut blocked,
+            } => {
+                // https://w3c.github.io/IndexedDB/#open-a-database
+                let version = version.unwrap_or(cmp::max(old_version, 1));
+                if version < old_version {
+                    let _ = sender.send(OpenResponse::Error(BackendError::Version));
+                    true
+                } else if version == old_version {
+                    let connection = self.open_connection(key, events.clone());
+                    let info = self.database(key).unwrap().info();
+                    let _ = sender.send(OpenResponse::Success { connection, info });
+                    true
+                } else if self.wait_for_connections(
+                    key,
+                    sender,
+                    old_version,
+                    Some(version),
+                    notified,
+                    blocked,
+                ) {
+                    false
+                } else {
+                    self.run_upgrade(key, sender, events.clone(), old_version, version);
+                    true
+                }
+            },
+            PendingRequest::Delete {
+                ref sender,
+                ref mut notified,
+                ref mut blocked,
+            } => {
+                // https://w3c.github.io/IndexedDB/#delete-a-database
+                if self.wait_for_connections(key, sender, old_version, None, notified, blocked) {
+                    false
+                } else {
+                    if let Some(databases) = self.databases.get_mut(&key.0) {
+                        databases.remove(&key.1);
+                    }
+                    if let Err(why) = self.save_state(key, None) {
+                        warn!("Couldn't delete IndexedDB database {:?}: {}", key, why);
+                    }
+                    let _ = sender.send(OpenResponse::Deleted { old_version });
+                    true
+                }
+            },
+        };
+        if !served {
+            self.pending_requests
+                .get_mut(key)
+                .unwrap()
+                .push_front(request);
+        }
+        served
+    }
+
+    /// Fire versionchange at the other connections to a database, once,
+    /// and report the request as blocked while some of them are still open.
+    /// Returns whether the request has to keep waiting.
+    fn wait_for_connections(
+        &mut self,
+        key: &DatabaseKey,
+        sender: &IpcSender<OpenResponse>,
+        old_version: u64,
+        new_version: Option<u64>,
+        notified: &mut bool,
+        blocked: &mut bool,
+    ) -> bool {
+        let open: Vec<u64> = self
+            .connections
+            .iter()
+            .filter(|&(_, connection)| connection.database == *key)
+            .map(|(id, _)| *id)
+            .collect();
+        if !*notified {
+            *notified = true;
+            for id in open {
+                let connection = &self.connections[&id];
+                if connection.close_pending {
+                    continue;
+                }
+                let event = ConnectionEvent::VersionChange {
+                    old_version,
+                    new_version,
+                };
+                if connection.events.send(event).is_err() {
+                    self.drop_connection(id);
+                }
+            }
+        }
+        let still_open = self
+            .connections
+            .values()
+            .any(|connection| connection.database == *key);
+        if still_open && !*blocked {
+            *blocked = true;
+            let _ = sender.send(OpenResponse::Blocked {
+                old_version,
+                new_version,
+            });
+        }
+        still_open
+    }
+
+    fn open_connection(&mut self, key: &DatabaseKey, events: IpcSender<ConnectionEvent>) -> u64 {
+        let id = self.next_connection_id;
+        self.next_connection_id += 1;
+        self.connections.insert(
+            id,
+            Connection {
+                database: key.clone(),
+                events,
+                close_pending: false,
+            },
+        );
+        id
+    }
+
+    /// https://w3c.github.io/IndexedDB/#run-an-upgrade-transaction
+    fn run_upgrade(
+        &mut self,
+        key: &DatabaseKey,
+        sender: &IpcSender<OpenResponse>,
+        events: IpcSender<ConnectionEvent>,
+        old_version: u64,
+        new_version: u64,
+    ) {
+        let previous = self.database(key).cloned();
+        let database = self
+            .databases
+            .entry(key.0.clone())
+            .or_insert_with(BTreeMap::new)
+            .entry(key.1.clone())
+            .or_insert_with(|| Database::new(key.1.clone()));
+        database.version = new_version;
+        let info = database.info();
+
+        let connection = self.open_connection(key, events);
+        self.transactions.push(Transaction {
+            id: IndexedDBTxnId {
+                connection,
+                serial: 0,
+            },
+            database: key.clone(),
+            scope: info.object_stores.iter().map(|s| s.name.clone()).collect(),
+            mode: IndexedDBTxnMode::Versionchange,
+            started: true,
+            operations: VecDeque::new(),
+            commit_requested: false,
+            snapshot: Some(Snapshot::Database(previous)),
+        });
+        let _ = sender.send(OpenResponse::Upgrade {
+            connection,
+            old_version,
+            new_version,
+            info,
+        });
+    }
+}
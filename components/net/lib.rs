@@ -22,13 +22,17 @@ pub mod cookie;
 pub mod cookie_storage;
 mod data_loader;
 mod decoder;
+pub mod disk_cache;
 pub mod filemanager_thread;
 mod hosts;
 pub mod hsts;
 pub mod http_cache;
 pub mod http_loader;
 pub mod image_cache;
+mod indexeddb_backend;
+pub mod indexeddb_thread;
 pub mod mime_classifier;
+pub mod proxy;
 pub mod resource_thread;
 mod storage_thread;
 pub mod subresource_integrity;
@@ -0,0 +1,197 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Selection of the HTTP proxy to use for a given URL,
+//! configured through prefs or the `http_proxy`, `https_proxy` and `no_proxy` environment variables.
+
+use servo_url::ServoUrl;
+use std::env;
+use std::sync::RwLock;
+
+/// Basic credentials used to authenticate with a proxy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProxyCredentials {
+    pub user_name: String,
+    pub password: String,
+}
+
+impl ProxyCredentials {
+    /// The value of a `Proxy-Authorization` header carrying these credentials.
+    pub fn to_header_value(&self) -> String {
+        let credentials = format!("{}:{}", self.user_name, self.password);
+        format!("Basic {}", base64::encode(&credentials))
+    }
+}
+
+/// A proxy server, and the credentials currently used to authenticate with it.
+#[derive(Debug)]
+pub struct Proxy {
+    url: ServoUrl,
+    credentials: RwLock<Option<ProxyCredentials>>,
+}
+
+impl Proxy {
+    /// Parse a proxy from a URL, which may omit the scheme and contain credentials,
+    /// as is usual for the environment variables.
+    pub fn parse(input: &str) -> Option<Proxy> {
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+        let url = if input.contains("://") {
+            ServoUrl::parse(input)
+        } else {
+            ServoUrl::parse(&format!("http://{}", input))
+        };
+        let mut url = match url {
+            Ok(url) => url,
+            Err(error) => {
+                warn!("Ignoring invalid proxy {}: {}", input, error);
+                return None;
+            },
+        };
+        if url.scheme() != "http" {
+            warn!("Ignoring proxy {} with unsupported scheme", input);
+            return None;
+        }
+        let credentials = if url.username().is_empty() {
+            None
+        } else {
+            Some(ProxyCredentials {
+                user_name: url.username().to_owned(),
+                password: url.password().unwrap_or("").to_owned(),
+            })
+        };
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+        Some(Proxy {
+            url,
+            credentials: RwLock::new(credentials),
+        })
+    }
+
+    /// The URL of the proxy, without credentials.
+    pub fn url(&self) -> &ServoUrl {
+        &self.url
+    }
+
+    pub fn host(&self) -> &str {
+        self.url.host_str().unwrap_or("")
+    }
+
+    pub fn port(&self) -> u16 {
+        self.url.port_or_known_default().unwrap_or(80)
+    }
+
+    pub fn credentials(&self) -> Option<ProxyCredentials> {
+        self.credentials.read().unwrap().clone()
+    }
+
+    pub fn set_credentials(&self, credentials: ProxyCredentials) {
+        *self.credentials.write().unwrap() = Some(credentials);
+    }
+}
+
+/// A list of hosts which must be connected to directly, in the `no_proxy` format:
+/// comma-separated host names, matching themselves and their subdomains, or `*`.
+#[derive(Debug, Default)]
+struct NoProxy {
+    matches_all: bool,
+    hosts: Vec<String>,
+}
+
+impl NoProxy {
+    fn parse(input: &str) -> NoProxy {
+        let mut no_proxy = NoProxy::default();
+        for entry in input
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            if entry == "*" {
+                no_proxy.matches_all = true;
+                continue;
+            }
+            // Ports are not taken into account.
+            let host = match entry.rfind(':') {
+                Some(index)
+                    if !entry.ends_with(']') && entry[index + 1..].parse::<u16>().is_ok() =>
+                {
+                    &entry[..index]
+                },
+                _ => entry,
+            };
+            no_proxy
+                .hosts
+                .push(host.trim_start_matches('.').to_ascii_lowercase());
+        }
+        no_proxy
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        if self.matches_all {
+            return true;
+        }
+        let host = host.to_ascii_lowercase();
+        self.hosts.iter().any(|entry| {
+            host == *entry ||
+                (host.ends_with(&**entry) && host[..host.len() - entry.len()].ends_with('.'))
+        })
+    }
+}
+
+/// The proxies to use for plain and secure HTTP requests.
+#[derive(Debug, Default)]
+pub struct ProxyConfig {
+    http: Option<Proxy>,
+    https: Option<Proxy>,
+    no_proxy: NoProxy,
+}
+
+impl ProxyConfig {
+    /// Create a configuration from explicit values, as found in prefs or environment variables.
+    pub fn new(http: &str, https: &str, no_proxy: &str) -> ProxyConfig {
+        ProxyConfig {
+            http: Proxy::parse(http),
+            https: Proxy::parse(https),
+            no_proxy: NoProxy::parse(no_proxy),
+        }
+    }
+
+    /// Read the configuration from the `network.proxy` prefs,
+    /// falling back to the conventional environment variables for those that are empty.
+    pub fn from_prefs() -> ProxyConfig {
+        fn pref_or_env(pref: String, name: &str) -> String {
+            if !pref.is_empty() {
+                return pref;
+            }
+            env::var(name)
+                .or_else(|_| env::var(name.to_ascii_uppercase()))
+                .unwrap_or_default()
+        }
+        ProxyConfig::new(
+            &pref_or_env(pref!(network.proxy.http), "http_proxy"),
+            &pref_or_env(pref!(network.proxy.https), "https_proxy"),
+            &pref_or_env(pref!(network.proxy.no_proxy), "no_proxy"),
+        )
+    }
+
+    /// The proxy to use to connect to a destination, if any.
+    pub fn proxy_for(&self, scheme: &str, host: &str) -> Option<&Proxy> {
+        let proxy = match scheme {
+            "http" => self.http.as_ref(),
+            "https" => self.https.as_ref(),
+            _ => None,
+        }?;
+        if self.no_proxy.matches(host) {
+            return None;
+        }
+        Some(proxy)
+    }
+
+    /// The proxy to use to fetch a URL, if any.
+    pub fn proxy_for_url(&self, url: &ServoUrl) -> Option<&Proxy> {
+        self.proxy_for(url.scheme(), url.host_str()?)
+    }
+}
@@ -6,13 +6,16 @@
 
 use crate::connector::{create_http_client, create_tls_config, ALPN_H2_H1};
 use crate::cookie;
-use crate::cookie_storage::CookieStorage;
+use crate::cookie_storage::{CookiePolicy, CookieStorage};
+use crate::disk_cache::DiskCache;
 use crate::fetch::cors_cache::CorsCache;
 use crate::fetch::methods::{fetch, CancellationListener, FetchContext};
 use crate::filemanager_thread::FileManager;
 use crate::hsts::HstsList;
 use crate::http_cache::HttpCache;
 use crate::http_loader::{http_redirect_fetch, HttpState, HANDLE};
+use crate::indexeddb_thread::IndexedDBThreadFactory;
+use crate::proxy::ProxyConfig;
 use crate::storage_thread::StorageThreadFactory;
 use crate::websocket_loader;
 use crossbeam_channel::Sender;
@@ -24,6 +27,7 @@ use ipc_channel::ipc::{self, IpcReceiver, IpcReceiverSet, IpcSender};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use net_traits::blob_url_store::parse_blob_url;
 use net_traits::filemanager_thread::FileTokenCheck;
+use net_traits::indexeddb_thread::IndexedDBThreadMsg;
 use net_traits::request::{Destination, RequestBuilder};
 use net_traits::response::{Response, ResponseInit};
 use net_traits::storage_thread::StorageThreadMsg;
@@ -68,11 +72,13 @@ pub fn new_resource_threads(
         embedder_proxy,
         config_dir.clone(),
         certificate_path,
+        CookiePolicy::from_pref(&pref!(network.cookie.policy)),
     );
-    let storage: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(config_dir);
+    let storage: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(config_dir.clone());
+    let indexeddb: IpcSender<IndexedDBThreadMsg> = IndexedDBThreadFactory::new(config_dir);
     (
-        ResourceThreads::new(public_core, storage.clone()),
-        ResourceThreads::new(private_core, storage),
+        ResourceThreads::new(public_core, storage.clone(), indexeddb.clone()),
+        ResourceThreads::new(private_core, storage, indexeddb),
     )
 }
 
@@ -85,6 +91,7 @@ pub fn new_core_resource_thread(
     embedder_proxy: EmbedderProxy,
     config_dir: Option<PathBuf>,
     certificate_path: Option<String>,
+    cookie_policy: CookiePolicy,
 ) -> (CoreResourceThread, CoreResourceThread) {
     let (public_setup_chan, public_setup_port) = ipc::channel().unwrap();
     let (private_setup_chan, private_setup_port) = ipc::channel().unwrap();
@@ -97,7 +104,7 @@ pub fn new_core_resource_thread(
                 user_agent,
                 devtools_chan,
                 time_profiler_chan,
-                embedder_proxy,
+                embedder_proxy.clone(),
                 certificate_path.clone(),
             );
 
@@ -105,6 +112,8 @@ pub fn new_core_resource_thread(
                 resource_manager,
                 config_dir,
                 certificate_path,
+                embedder_proxy,
+                cookie_policy,
             };
 
             mem_profiler_chan.run_with_memory_reporting(
@@ -122,27 +131,45 @@ struct ResourceChannelManager {
     resource_manager: CoreResourceManager,
     config_dir: Option<PathBuf>,
     certificate_path: Option<String>,
+    embedder_proxy: EmbedderProxy,
+    cookie_policy: CookiePolicy,
 }
 
 fn create_http_states(
     config_dir: Option<&Path>,
     certificate_path: Option<String>,
+    embedder_proxy: EmbedderProxy,
+    cookie_policy: CookiePolicy,
 ) -> (Arc<HttpState>, Arc<HttpState>) {
     let mut hsts_list = HstsList::from_servo_preload();
     let mut auth_cache = AuthCache::new();
-    let http_cache = HttpCache::new();
+    let mut http_cache = HttpCache::new();
     let mut cookie_jar = CookieStorage::new(150);
     if let Some(config_dir) = config_dir {
         read_json_from_file(&mut auth_cache, config_dir, "auth_cache.json");
         read_json_from_file(&mut hsts_list, config_dir, "hsts_list.json");
         read_json_from_file(&mut cookie_jar, config_dir, "cookie_jar.json");
+        if pref!(network.http_cache.disk.enabled) {
+            match DiskCache::open(
+                config_dir.join("http_cache"),
+                pref!(network.http_cache.disk.max_size) as u64,
+                pref!(network.http_cache.disk.revalidate_on_startup),
+            ) {
+                Ok(disk_cache) => http_cache = HttpCache::with_disk_cache(disk_cache),
+                Err(why) => warn!("Couldn't open the http cache on disk: {}", why),
+            }
+        }
     }
 
+    // The policy isn't persisted, as the pref may have changed since.
+    cookie_jar.set_policy(cookie_policy);
+
     let certs = match certificate_path {
         Some(ref path) => fs::read_to_string(path).expect("Couldn't not find certificate file"),
         None => resources::read_string(Resource::SSLCertificates),
     };
 
+    let proxy_config = Arc::new(ProxyConfig::from_prefs());
     let http_state = HttpState {
         hsts_list: RwLock::new(hsts_list),
         cookie_jar: RwLock::new(cookie_jar),
@@ -152,21 +179,31 @@ fn create_http_states(
         http_cache_state: Mutex::new(HashMap::new()),
         client: create_http_client(
             create_tls_config(&certs, ALPN_H2_H1),
+            proxy_config.clone(),
             HANDLE.lock().unwrap().executor(),
         ),
+        proxy_config,
+        embedder_proxy: Mutex::new(embedder_proxy.clone()),
     };
 
+    // Credentials entered in a private session must not leak into the public one.
+    let private_proxy_config = Arc::new(ProxyConfig::from_prefs());
+    let mut private_cookie_jar = CookieStorage::new(150);
+    private_cookie_jar.set_policy(cookie_policy);
     let private_http_state = HttpState {
         hsts_list: RwLock::new(HstsList::from_servo_preload()),
-        cookie_jar: RwLock::new(CookieStorage::new(150)),
+        cookie_jar: RwLock::new(private_cookie_jar),
         auth_cache: RwLock::new(AuthCache::new()),
         history_states: RwLock::new(HashMap::new()),
         http_cache: RwLock::new(HttpCache::new()),
         http_cache_state: Mutex::new(HashMap::new()),
         client: create_http_client(
             create_tls_config(&certs, ALPN_H2_H1),
+            private_proxy_config.clone(),
             HANDLE.lock().unwrap().executor(),
         ),
+        proxy_config: private_proxy_config,
+        embedder_proxy: Mutex::new(embedder_proxy),
     };
 
     (Arc::new(http_state), Arc::new(private_http_state))
@@ -183,6 +220,8 @@ impl ResourceChannelManager {
         let (public_http_state, private_http_state) = create_http_states(
             self.config_dir.as_ref().map(Deref::deref),
             self.certificate_path.clone(),
+            self.embedder_proxy.clone(),
+            self.cookie_policy,
         );
 
         let mut rx_set = IpcReceiverSet::new().unwrap();
@@ -276,39 +315,55 @@ impl ResourceChannelManager {
                     .clear_storage(&request);
                 return true;
             },
+            CoreResourceMsg::DeleteCookie(request, name) => {
+                http_state
+                    .cookie_jar
+                    .write()
+                    .unwrap()
+                    .delete_cookie(&request, &name);
+                return true;
+            },
             CoreResourceMsg::FetchRedirect(req_init, res_init, sender, cancel_chan) => self
                 .resource_manager
                 .fetch(req_init, Some(res_init), sender, http_state, cancel_chan),
-            CoreResourceMsg::SetCookieForUrl(request, cookie, source) => self
-                .resource_manager
-                .set_cookie_for_url(&request, cookie.into_inner(), source, http_state),
-            CoreResourceMsg::SetCookiesForUrl(request, cookies, source) => {
-                for cookie in cookies {
-                    self.resource_manager.set_cookie_for_url(
-                        &request,
-                        cookie.into_inner(),
-                        source,
-                        http_state,
-                    );
-                }
+            CoreResourceMsg::SetCookieForUrl(request, top_level_origin, cookie, source) => {
+                self.resource_manager.set_cookie_for_url(
+                    &request,
+                    top_level_origin.as_ref(),
+                    cookie.into_inner(),
+                    source,
+                    http_state,
+                )
+            },
+            CoreResourceMsg::SetCookieStringForUrl(request, top_level_origin, cookie, source) => {
+                self.resource_manager.set_cookie_string_for_url(
+                    &request,
+                    top_level_origin.as_ref(),
+                    cookie,
+                    source,
+                    http_state,
+                )
             },
-            CoreResourceMsg::GetCookiesForUrl(url, consumer, source) => {
+            CoreResourceMsg::GetCookiesForUrl(url, top_level_origin, consumer, source) => {
                 let mut cookie_jar = http_state.cookie_jar.write().unwrap();
                 cookie_jar.remove_expired_cookies_for_url(&url);
-                consumer
-                    .send(cookie_jar.cookies_for_url(&url, source))
-                    .unwrap();
+                let cookies = cookie_jar.cookies_for_url_and_top_level(
+                    &url,
+                    top_level_origin.as_ref(),
+                    source,
+                );
+                consumer.send(cookies).unwrap();
             },
             CoreResourceMsg::NetworkMediator(mediator_chan, origin) => {
                 self.resource_manager
                     .sw_managers
                     .insert(origin, mediator_chan);
             },
-            CoreResourceMsg::GetCookiesDataForUrl(url, consumer, source) => {
+            CoreResourceMsg::GetCookiesDataForUrl(url, top_level_origin, consumer, source) => {
                 let mut cookie_jar = http_state.cookie_jar.write().unwrap();
                 cookie_jar.remove_expired_cookies_for_url(&url);
                 let cookies = cookie_jar
-                    .cookies_data_for_url(&url, source)
+                    .cookies_data_for_url(&url, top_level_origin.as_ref(), source)
                     .map(Serde)
                     .collect();
                 consumer.send(cookies).unwrap();
@@ -349,6 +404,10 @@ impl ResourceChannelManager {
                         Ok(hsts) => write_json_to_file(&*hsts, config_dir, "hsts_list.json"),
                         Err(_) => warn!("Error writing hsts list to disk"),
                     }
+                    match http_state.http_cache.read() {
+                        Ok(http_cache) => http_cache.flush(),
+                        Err(_) => warn!("Error writing http cache to disk"),
+                    }
                 }
                 self.resource_manager.exit();
                 let _ = sender.send(());
@@ -597,13 +656,30 @@ impl CoreResourceManager {
     fn set_cookie_for_url(
         &mut self,
         request: &ServoUrl,
+        top_level_origin: Option<&ImmutableOrigin>,
         cookie: cookie_rs::Cookie<'static>,
         source: CookieSource,
         http_state: &Arc<HttpState>,
     ) {
         if let Some(cookie) = cookie::Cookie::new_wrapped(cookie, request, source) {
             let mut cookie_jar = http_state.cookie_jar.write().unwrap();
-            cookie_jar.push(cookie, request, source)
+            cookie_jar.push_for_top_level(cookie, request, top_level_origin, source)
+        }
+    }
+
+    /// Store the cookie of a cookie string, parsed like a `Set-Cookie` header
+    /// so that attributes unknown to cookie-rs, such as `Partitioned`, are kept.
+    fn set_cookie_string_for_url(
+        &mut self,
+        request: &ServoUrl,
+        top_level_origin: Option<&ImmutableOrigin>,
+        cookie: String,
+        source: CookieSource,
+        http_state: &Arc<HttpState>,
+    ) {
+        if let Some(cookie) = cookie::Cookie::from_cookie_string(cookie, request, source) {
+            let mut cookie_jar = http_state.cookie_jar.write().unwrap();
+            cookie_jar.push_for_top_level(cookie, request, top_level_origin, source)
         }
     }
 
@@ -3,7 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use net::cookie::Cookie;
-use net::cookie_storage::CookieStorage;
+use net::cookie_storage::{CookiePolicy, CookieStorage};
 use net_traits::CookieSource;
 use servo_url::ServoUrl;
 
@@ -475,3 +475,191 @@ fn test_cookie_eviction_all_nonsecure_new_nonsecure() {
         "extra2=bar; extra3=bar; extra4=bar; extra5=bar; foo=bar"
     );
 }
+
+fn push_cookie_for_top_level(
+    storage: &mut CookieStorage,
+    url: &ServoUrl,
+    top_level_url: &ServoUrl,
+    cookie_str: &str,
+) {
+    let source = CookieSource::HTTP;
+    if let Some(cookie) = Cookie::from_cookie_string(cookie_str.to_owned(), url, source) {
+        storage.push_for_top_level(cookie, url, Some(&top_level_url.origin()), source);
+    }
+}
+
+fn cookies_for_top_level(
+    storage: &mut CookieStorage,
+    url: &ServoUrl,
+    top_level_url: &ServoUrl,
+) -> Option<String> {
+    storage.cookies_for_url_and_top_level(url, Some(&top_level_url.origin()), CookieSource::HTTP)
+}
+
+#[test]
+fn test_partitioned_cookie_requires_secure() {
+    let url = &ServoUrl::parse("https://embedded.example.org/").unwrap();
+    let source = CookieSource::HTTP;
+    assert!(Cookie::from_cookie_string("foo=bar; Partitioned".to_owned(), url, source).is_none());
+    let cookie =
+        Cookie::from_cookie_string("foo=bar; Secure; Partitioned".to_owned(), url, source).unwrap();
+    assert!(cookie.partitioned);
+    let cookie = Cookie::from_cookie_string("foo=bar; Secure".to_owned(), url, source).unwrap();
+    assert!(!cookie.partitioned);
+}
+
+#[test]
+fn test_partitioned_cookie_is_keyed_by_top_level_site() {
+    let mut storage = CookieStorage::new(150);
+    let url = ServoUrl::parse("https://embedded.example.org/").unwrap();
+    let first_top_level = ServoUrl::parse("https://www.first.com/").unwrap();
+    let same_site_top_level = ServoUrl::parse("https://other.first.com/page").unwrap();
+    let second_top_level = ServoUrl::parse("https://second.com/").unwrap();
+
+    push_cookie_for_top_level(
+        &mut storage,
+        &url,
+        &first_top_level,
+        "foo=first; Secure; Partitioned",
+    );
+    push_cookie_for_top_level(
+        &mut storage,
+        &url,
+        &second_top_level,
+        "foo=second; Secure; Partitioned",
+    );
+
+    assert_eq!(
+        cookies_for_top_level(&mut storage, &url, &same_site_top_level).unwrap(),
+        "foo=first"
+    );
+    assert_eq!(
+        cookies_for_top_level(&mut storage, &url, &second_top_level).unwrap(),
+        "foo=second"
+    );
+    // Partitioned cookies set in third-party contexts aren't sent to the site itself.
+    assert!(storage.cookies_for_url(&url, CookieSource::HTTP).is_none());
+}
+
+#[test]
+fn test_third_party_cookies_allowed_by_default() {
+    let mut storage = CookieStorage::new(150);
+    let url = ServoUrl::parse("https://embedded.example.org/").unwrap();
+    let top_level = ServoUrl::parse("https://www.first.com/").unwrap();
+
+    push_cookie_for_top_level(&mut storage, &url, &top_level, "foo=bar");
+
+    assert_eq!(
+        cookies_for_top_level(&mut storage, &url, &top_level).unwrap(),
+        "foo=bar"
+    );
+    assert_eq!(
+        storage.cookies_for_url(&url, CookieSource::HTTP).unwrap(),
+        "foo=bar"
+    );
+}
+
+#[test]
+fn test_block_third_party_cookie_policy() {
+    let mut storage = CookieStorage::new(150);
+    storage.set_policy(CookiePolicy::BlockThirdParty);
+    let url = ServoUrl::parse("https://embedded.example.org/").unwrap();
+    let top_level = ServoUrl::parse("https://www.first.com/").unwrap();
+    let same_site_top_level = ServoUrl::parse("https://www.example.org/").unwrap();
+
+    // Unpartitioned cookies can't be set in a third-party context...
+    push_cookie_for_top_level(&mut storage, &url, &top_level, "blocked=bar");
+    assert!(storage.cookies_for_url(&url, CookieSource::HTTP).is_none());
+
+    // ... nor sent in one, but still work when the top-level document is same-site.
+    push_cookie_for_top_level(&mut storage, &url, &same_site_top_level, "first=bar");
+    assert!(cookies_for_top_level(&mut storage, &url, &top_level).is_none());
+    assert_eq!(
+        storage.cookies_for_url(&url, CookieSource::HTTP).unwrap(),
+        "first=bar"
+    );
+
+    // Partitioned cookies remain available to third parties.
+    push_cookie_for_top_level(
+        &mut storage,
+        &url,
+        &top_level,
+        "partitioned=bar; Secure; Partitioned",
+    );
+    assert_eq!(
+        cookies_for_top_level(&mut storage, &url, &top_level).unwrap(),
+        "partitioned=bar"
+    );
+}
+
+#[test]
+fn test_unknown_top_level_origin_is_a_third_party_context() {
+    let mut storage = CookieStorage::new(150);
+    storage.set_policy(CookiePolicy::BlockThirdParty);
+    let url = ServoUrl::parse("https://embedded.example.org/").unwrap();
+
+    // Unpartitioned cookies can't be set without knowing the top-level document...
+    let cookie = Cookie::from_cookie_string("unknown=bar".to_owned(), &url, CookieSource::HTTP);
+    storage.push_for_top_level(cookie.unwrap(), &url, None, CookieSource::HTTP);
+    assert!(storage.cookies_for_url(&url, CookieSource::HTTP).is_none());
+
+    // ... nor sent.
+    let cookie = Cookie::from_cookie_string("first=bar".to_owned(), &url, CookieSource::HTTP);
+    storage.push(cookie.unwrap(), &url, CookieSource::HTTP);
+    assert!(storage
+        .cookies_for_url_and_top_level(&url, None, CookieSource::HTTP)
+        .is_none());
+    assert_eq!(
+        storage.cookies_for_url(&url, CookieSource::HTTP).unwrap(),
+        "first=bar"
+    );
+}
+
+#[test]
+fn test_unknown_cookie_policy_pref_allows_all_cookies() {
+    assert_eq!(
+        CookiePolicy::from_pref("block-third-party"),
+        CookiePolicy::BlockThirdParty
+    );
+    assert_eq!(CookiePolicy::from_pref("bogus"), CookiePolicy::AllowAll);
+}
+
+#[test]
+fn test_partitioned_cookies_are_persisted_with_their_partition() {
+    let mut storage = CookieStorage::new(150);
+    let url = ServoUrl::parse("https://embedded.example.org/").unwrap();
+    let top_level = ServoUrl::parse("https://www.first.com/").unwrap();
+    push_cookie_for_top_level(
+        &mut storage,
+        &url,
+        &top_level,
+        "foo=bar; Secure; Partitioned; Max-Age=3600",
+    );
+
+    let json = serde_json::to_string(&storage).unwrap();
+    let mut storage: CookieStorage = serde_json::from_str(&json).unwrap();
+
+    assert!(storage.cookies_for_url(&url, CookieSource::HTTP).is_none());
+    assert_eq!(
+        cookies_for_top_level(&mut storage, &url, &top_level).unwrap(),
+        "foo=bar"
+    );
+}
+
+#[test]
+fn test_delete_cookie_only_deletes_cookies_with_that_name() {
+    let mut storage = CookieStorage::new(150);
+    let url = ServoUrl::parse("http://example.com/").unwrap();
+    add_cookie_to_storage(&mut storage, &url, "foo=bar");
+    add_cookie_to_storage(&mut storage, &url, "foo=baz; Path=/path");
+    add_cookie_to_storage(&mut storage, &url, "qux=bar");
+
+    storage.delete_cookie(&url, "foo");
+    assert_eq!(
+        storage.cookies_for_url(&url, CookieSource::HTTP).unwrap(),
+        "qux=bar"
+    );
+
+    storage.delete_cookie(&url, "qux");
+    assert!(storage.cookies_for_url(&url, CookieSource::HTTP).is_none());
+}
@@ -27,6 +27,7 @@ use net::fetch::cors_cache::CorsCache;
 use net::fetch::methods::{self, CancellationListener, FetchContext};
 use net::filemanager_thread::FileManager;
 use net::hsts::HstsEntry;
+use net::proxy::ProxyConfig;
 use net::resource_thread::CoreResourceThreadPool;
 use net::test::HttpState;
 use net_traits::filemanager_thread::FileTokenCheck;
@@ -685,7 +686,11 @@ fn test_fetch_with_hsts() {
     let tls_config = create_tls_config(&certs, ALPN_H2_H1);
 
     let mut context = FetchContext {
-        state: Arc::new(HttpState::new(tls_config)),
+        state: Arc::new(HttpState::new(
+            tls_config,
+            ProxyConfig::default(),
+            create_embedder_proxy(),
+        )),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: None,
         filemanager: FileManager::new(create_embedder_proxy(), Weak::new()),
@@ -738,7 +743,11 @@ fn test_load_adds_host_to_hsts_list_when_url_is_https() {
     let tls_config = create_tls_config(&certs, ALPN_H2_H1);
 
     let mut context = FetchContext {
-        state: Arc::new(HttpState::new(tls_config)),
+        state: Arc::new(HttpState::new(
+            tls_config,
+            ProxyConfig::default(),
+            create_embedder_proxy(),
+        )),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: None,
         filemanager: FileManager::new(create_embedder_proxy(), Weak::new()),
@@ -1163,7 +1172,7 @@ fn test_fetch_with_devtools() {
 
     headers.insert(
         header::ACCEPT_ENCODING,
-        HeaderValue::from_static("gzip, deflate, br"),
+        HeaderValue::from_static("gzip, deflate, br, zstd"),
     );
 
     headers.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
@@ -2,15 +2,29 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::{fetch_with_context, make_server, new_fetch_context};
 use crossbeam_channel::unbounded;
-use http::header::{HeaderValue, EXPIRES};
+use headers::{CacheControl, HeaderMapExt};
+use http::header::{self, HeaderValue, EXPIRES};
 use http::StatusCode;
+use hyper::body::Body;
+use hyper::{Request as HyperRequest, Response as HyperResponse};
 use msg::constellation_msg::TEST_PIPELINE_ID;
+use net::disk_cache::DiskCache;
+use net::fetch::methods::FetchContext;
 use net::http_cache::HttpCache;
-use net_traits::request::{Origin, Request};
+use net_traits::request::{Origin, Referrer, Request};
 use net_traits::response::{HttpsState, Response, ResponseBody};
 use net_traits::{ResourceFetchTiming, ResourceTimingType};
 use servo_url::ServoUrl;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
 
 #[test]
 fn test_refreshing_resource_sets_done_chan_the_appropriate_value() {
@@ -49,3 +63,152 @@ fn test_refreshing_resource_sets_done_chan_the_appropriate_value() {
         }
     })
 }
+
+static MESSAGE: &'static [u8] = b"Yay!";
+
+fn temporary_cache_directory() -> PathBuf {
+    env::temp_dir().join(format!("servo-http-cache-{}", Uuid::new_v4()))
+}
+
+/// Create a fetch context whose http cache is backed by the disk cache in `directory`,
+/// as if starting a new session.
+fn new_disk_cache_context(
+    directory: &Path,
+    max_size: u64,
+    revalidate_on_startup: bool,
+) -> FetchContext {
+    let context = new_fetch_context(None, None, None);
+    let disk_cache =
+        DiskCache::open(directory.to_owned(), max_size, revalidate_on_startup).unwrap();
+    *context.state.http_cache.write().unwrap() = HttpCache::with_disk_cache(disk_cache);
+    context
+}
+
+fn fetch_body(url: ServoUrl, context: &mut FetchContext) -> Vec<u8> {
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url, Some(origin), None, HttpsState::None);
+    request.referrer = Referrer::NoReferrer;
+    let response = fetch_with_context(&mut request, context);
+    assert!(!response.is_network_error());
+    let body = response.body.lock().unwrap();
+    match *body {
+        ResponseBody::Done(ref body) => body.clone(),
+        _ => panic!("Expected a complete body"),
+    }
+}
+
+#[test]
+fn test_disk_cache_serves_response_stored_by_previous_session() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        response
+            .headers_mut()
+            .typed_insert(CacheControl::new().with_max_age(Duration::from_secs(3600)));
+        *response.body_mut() = MESSAGE.to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+    let directory = temporary_cache_directory();
+
+    let mut first_session = new_disk_cache_context(&directory, 1024, false);
+    assert_eq!(fetch_body(url.clone(), &mut first_session), MESSAGE);
+    first_session.state.http_cache.read().unwrap().flush();
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    let mut second_session = new_disk_cache_context(&directory, 1024, false);
+    assert_eq!(fetch_body(url.clone(), &mut second_session), MESSAGE);
+    let _ = server.close();
+
+    // The second session was served from disk.
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    let _ = fs::remove_dir_all(&directory);
+}
+
+#[test]
+fn test_disk_cache_revalidates_persisted_response_on_startup() {
+    let full_requests = Arc::new(AtomicUsize::new(0));
+    let conditional_requests = Arc::new(AtomicUsize::new(0));
+    let full_counter = full_requests.clone();
+    let conditional_counter = conditional_requests.clone();
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        response
+            .headers_mut()
+            .typed_insert(CacheControl::new().with_max_age(Duration::from_secs(3600)));
+        response
+            .headers_mut()
+            .insert(header::ETAG, HeaderValue::from_static("\"servo\""));
+        match request.headers().get(header::IF_NONE_MATCH) {
+            Some(etag) if etag == "\"servo\"" => {
+                conditional_counter.fetch_add(1, Ordering::SeqCst);
+                *response.status_mut() = StatusCode::NOT_MODIFIED;
+            },
+            _ => {
+                full_counter.fetch_add(1, Ordering::SeqCst);
+                *response.body_mut() = MESSAGE.to_vec().into();
+            },
+        }
+    };
+    let (server, url) = make_server(handler);
+    let directory = temporary_cache_directory();
+
+    let mut first_session = new_disk_cache_context(&directory, 1024, true);
+    assert_eq!(fetch_body(url.clone(), &mut first_session), MESSAGE);
+    first_session.state.http_cache.read().unwrap().flush();
+
+    let mut second_session = new_disk_cache_context(&directory, 1024, true);
+    assert_eq!(fetch_body(url.clone(), &mut second_session), MESSAGE);
+    // Once revalidated, the response is fresh for the rest of the session.
+    assert_eq!(fetch_body(url.clone(), &mut second_session), MESSAGE);
+    let _ = server.close();
+
+    assert_eq!(full_requests.load(Ordering::SeqCst), 1);
+    assert_eq!(conditional_requests.load(Ordering::SeqCst), 1);
+    let _ = fs::remove_dir_all(&directory);
+}
+
+#[test]
+fn test_disk_cache_evicts_least_recently_used_response() {
+    let requests = Arc::new(Mutex::new(HashMap::new()));
+    let counter = requests.clone();
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        *counter
+            .lock()
+            .unwrap()
+            .entry(request.uri().path().to_owned())
+            .or_insert(0) += 1;
+        response
+            .headers_mut()
+            .typed_insert(CacheControl::new().with_max_age(Duration::from_secs(3600)));
+        *response.body_mut() = MESSAGE.to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+    let (a, b, c) = (
+        url.join("a").unwrap(),
+        url.join("b").unwrap(),
+        url.join("c").unwrap(),
+    );
+    let directory = temporary_cache_directory();
+
+    // Room for two bodies only.
+    let max_size = 2 * MESSAGE.len() as u64;
+    let mut first_session = new_disk_cache_context(&directory, max_size, false);
+    fetch_body(a.clone(), &mut first_session);
+    fetch_body(b.clone(), &mut first_session);
+    // Using `a` again, from memory, makes `b` the least recently used.
+    fetch_body(a.clone(), &mut first_session);
+    fetch_body(c.clone(), &mut first_session);
+    first_session.state.http_cache.read().unwrap().flush();
+
+    let mut second_session = new_disk_cache_context(&directory, max_size, false);
+    fetch_body(a.clone(), &mut second_session);
+    fetch_body(b.clone(), &mut second_session);
+    fetch_body(c.clone(), &mut second_session);
+    let _ = server.close();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.get("/a"), Some(&1));
+    assert_eq!(requests.get("/b"), Some(&2));
+    assert_eq!(requests.get("/c"), Some(&1));
+    let _ = fs::remove_dir_all(&directory);
+}
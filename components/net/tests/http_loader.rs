@@ -24,9 +24,11 @@ use http::uri::Authority;
 use http::{Method, StatusCode};
 use hyper::body::Body;
 use hyper::{Request as HyperRequest, Response as HyperResponse};
+use ipc_channel::ipc;
 use msg::constellation_msg::TEST_PIPELINE_ID;
 use net::cookie::Cookie;
-use net::cookie_storage::CookieStorage;
+use net::cookie_storage::{CookiePolicy, CookieStorage};
+use net::fetch::methods::CancellationListener;
 use net::http_loader::determine_request_referrer;
 use net::resource_thread::AuthCacheEntry;
 use net::test::replace_host_table;
@@ -110,7 +112,7 @@ fn test_check_default_headers_loaded_in_every_request() {
 
     headers.insert(
         header::ACCEPT_ENCODING,
-        HeaderValue::from_static("gzip, deflate, br"),
+        HeaderValue::from_static("gzip, deflate, br, zstd"),
     );
 
     headers.typed_insert(Host::from(
@@ -255,7 +257,7 @@ fn test_request_and_response_data_with_network_messages() {
 
     headers.insert(
         header::ACCEPT_ENCODING,
-        HeaderValue::from_static("gzip, deflate, br"),
+        HeaderValue::from_static("gzip, deflate, br, zstd"),
     );
 
     headers.insert(
@@ -494,6 +496,103 @@ fn test_load_should_decode_the_response_as_gzip_when_response_headers_have_conte
     );
 }
 
+/// Incompressible content spanning several zstd blocks.
+fn zstd_test_content() -> Vec<u8> {
+    let mut state: u32 = 1;
+    (0..512 * 1024)
+        .map(|_| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 16) as u8
+        })
+        .collect()
+}
+
+fn fetch_zstd_response(
+    handler: impl Fn(HyperRequest<Body>, &mut HyperResponse<Body>) + Send + Sync + 'static,
+) -> (bool, ResponseBody) {
+    let (server, url) = make_server(handler);
+
+    let mut request = RequestBuilder::new(url.clone())
+        .method(Method::GET)
+        .body(None)
+        .destination(Destination::Document)
+        .origin(mock_origin())
+        .pipeline_id(Some(TEST_PIPELINE_ID))
+        .build();
+
+    let response = fetch(&mut request, None);
+
+    let _ = server.close();
+
+    let internal_response = response.internal_response.unwrap();
+    let is_success = internal_response.status.clone().unwrap().0.is_success();
+    let body = internal_response.body.lock().unwrap().clone();
+    (is_success, body)
+}
+
+#[test]
+fn test_load_should_decode_the_response_as_zstd_when_response_headers_have_content_encoding_zstd() {
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_ENCODING, HeaderValue::from_static("zstd"));
+        let encoded_content = zstd::encode_all(&b"Yay!"[..], 0).unwrap();
+        *response.body_mut() = encoded_content.into();
+    };
+
+    let (is_success, body) = fetch_zstd_response(handler);
+
+    assert!(is_success);
+    assert_eq!(body, ResponseBody::Done(b"Yay!".to_vec()));
+}
+
+#[test]
+fn test_load_should_decode_a_chunked_zstd_response() {
+    let content = zstd_test_content();
+    let encoded_content = zstd::encode_all(&*content, 0).unwrap();
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_ENCODING, HeaderValue::from_static("zstd"));
+        // Chunk boundaries deliberately don't line up with zstd blocks.
+        let chunks: Vec<Vec<u8>> = encoded_content
+            .chunks(1000)
+            .map(|chunk| chunk.to_vec())
+            .collect();
+        *response.body_mut() =
+            Body::wrap_stream(futures::stream::iter_ok::<_, std::io::Error>(chunks));
+    };
+
+    let (is_success, body) = fetch_zstd_response(handler);
+
+    assert!(is_success);
+    assert_eq!(body, ResponseBody::Done(content));
+}
+
+#[test]
+fn test_load_should_end_a_truncated_zstd_response_with_the_decoded_prefix() {
+    let content = zstd_test_content();
+    let encoded_content = zstd::encode_all(&*content, 0).unwrap();
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_ENCODING, HeaderValue::from_static("zstd"));
+        let truncated_content = encoded_content[..encoded_content.len() / 2].to_vec();
+        *response.body_mut() = truncated_content.into();
+    };
+
+    let (is_success, body) = fetch_zstd_response(handler);
+
+    assert!(is_success);
+    match body {
+        ResponseBody::Done(body) => {
+            assert!(body.len() < content.len());
+            assert!(content.starts_with(&body));
+        },
+        _ => panic!("Expected the response body to be complete"),
+    }
+}
+
 #[test]
 fn test_load_doesnt_send_request_body_on_any_redirect() {
     let post_handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
@@ -628,6 +727,61 @@ fn test_load_sets_cookies_in_the_resource_manager_when_it_get_set_cookie_header_
     );
 }
 
+#[test]
+fn test_load_ignores_set_cookie_header_in_third_party_context_when_blocking_third_party_cookies() {
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        assert!(request.headers().get(header::COOKIE).is_none());
+        response.headers_mut().insert(
+            header::SET_COOKIE,
+            HeaderValue::from_static("mozillaIs=theBest"),
+        );
+        *response.body_mut() = b"Yay!".to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let mut context = new_fetch_context(None, None, None);
+    {
+        let mut cookie_jar = context.state.cookie_jar.write().unwrap();
+        cookie_jar.set_policy(CookiePolicy::BlockThirdParty);
+        let cookie = Cookie::new_wrapped(
+            CookiePair::new("mozillaIs".to_owned(), "theWorst".to_owned()),
+            &url,
+            CookieSource::HTTP,
+        )
+        .unwrap();
+        cookie_jar.push(cookie, &url, CookieSource::HTTP);
+    }
+
+    let top_level_url = ServoUrl::parse("http://top-level.test/").unwrap();
+    let mut request = RequestBuilder::new(url.clone())
+        .method(Method::GET)
+        .body(None)
+        .destination(Destination::Document)
+        .origin(mock_origin())
+        .pipeline_id(Some(TEST_PIPELINE_ID))
+        .credentials_mode(CredentialsMode::Include)
+        .top_level_origin(Some(top_level_url.origin()))
+        .build();
+
+    let response = fetch_with_context(&mut request, &mut context);
+
+    let _ = server.close();
+
+    assert!(response
+        .internal_response
+        .unwrap()
+        .status
+        .unwrap()
+        .0
+        .is_success());
+
+    assert_cookie_for_domain(
+        &context.state.cookie_jar,
+        url.as_str(),
+        Some("mozillaIs=theWorst"),
+    );
+}
+
 #[test]
 fn test_load_sets_requests_cookies_header_for_url_by_getting_cookies_from_the_resource_manager() {
     let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
@@ -964,7 +1118,7 @@ fn test_load_sets_default_accept_encoding_to_gzip_and_deflate() {
                 .unwrap()
                 .to_str()
                 .unwrap(),
-            "gzip, deflate, br"
+            "gzip, deflate, br, zstd"
         );
         *response.body_mut() = b"Yay!".to_vec().into();
     };
@@ -1463,3 +1617,35 @@ fn test_determine_request_referrer_longer_than_4k() {
 
     assert_eq!(referer.unwrap().as_str(), "http://example.com/");
 }
+
+#[test]
+fn test_fetch_cancelled_before_connecting_does_not_reach_the_server() {
+    let handled = Arc::new(AtomicBool::new(false));
+    let handled_clone = handled.clone();
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        handled_clone.store(true, Ordering::SeqCst);
+        *response.body_mut() = b"Yay!".to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let (cancel_sender, cancel_receiver) = ipc::channel().unwrap();
+    cancel_sender.send(()).unwrap();
+    let mut context = new_fetch_context(None, None, None);
+    context.cancellation_listener =
+        Arc::new(Mutex::new(CancellationListener::new(Some(cancel_receiver))));
+
+    let mut request = RequestBuilder::new(url.clone())
+        .method(Method::GET)
+        .body(None)
+        .destination(Destination::Document)
+        .origin(mock_origin())
+        .pipeline_id(Some(TEST_PIPELINE_ID))
+        .build();
+
+    let response = fetch_with_context(&mut request, &mut context);
+
+    let _ = server.close();
+
+    assert!(response.is_network_error());
+    assert!(!handled.load(Ordering::SeqCst));
+}
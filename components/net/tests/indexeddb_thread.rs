@@ -0,0 +1,463 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use net::indexeddb_thread::IndexedDBThreadFactory;
+use net_traits::indexeddb_thread::{AsyncOperation, AsyncResponse, BackendError};
+use net_traits::indexeddb_thread::{BackendResult, ConnectionEvent, CursorIteration};
+use net_traits::indexeddb_thread::{IndexInfo, IndexedDBCursorDirection, IndexedDBKeyRange};
+use net_traits::indexeddb_thread::{IndexedDBKeyType, IndexedDBThreadMsg, IndexedDBTxnId};
+use net_traits::indexeddb_thread::{IndexedDBTxnMode, KeyPath, ObjectStoreInfo, OpenResponse};
+use servo_url::{ImmutableOrigin, ServoUrl};
+use std::env;
+use std::fs;
+use uuid::Uuid;
+
+fn origin(url: &str) -> ImmutableOrigin {
+    ServoUrl::parse(url).unwrap().origin()
+}
+
+fn open(
+    thread: &IpcSender<IndexedDBThreadMsg>,
+    origin: ImmutableOrigin,
+    name: &str,
+    version: Option<u64>,
+) -> (IpcReceiver<OpenResponse>, IpcReceiver<ConnectionEvent>) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    let (events_sender, events_receiver) = ipc::channel().unwrap();
+    thread
+        .send(IndexedDBThreadMsg::Open {
+            sender,
+            events: events_sender,
+            origin,
+            name: name.to_owned(),
+            version,
+        })
+        .unwrap();
+    (receiver, events_receiver)
+}
+
+fn execute(
+    thread: &IpcSender<IndexedDBThreadMsg>,
+    id: IndexedDBTxnId,
+    operation: AsyncOperation,
+) -> BackendResult<AsyncResponse> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    thread
+        .send(IndexedDBThreadMsg::Operation(id, sender, operation))
+        .unwrap();
+    receiver.recv().unwrap()
+}
+
+fn upgrade_connection(receiver: &IpcReceiver<OpenResponse>) -> u64 {
+    match receiver.recv().unwrap() {
+        OpenResponse::Upgrade { connection, .. } => connection,
+        response => panic!("Unexpected response {:?}", response),
+    }
+}
+
+fn store_info(name: &str) -> ObjectStoreInfo {
+    ObjectStoreInfo {
+        name: name.to_owned(),
+        key_path: None,
+        auto_increment: false,
+        indexes: vec![],
+    }
+}
+
+fn put(key: f64, value: &[u8], index_keys: Vec<(String, Vec<IndexedDBKeyType>)>) -> AsyncOperation {
+    AsyncOperation::Put {
+        store: "store".to_owned(),
+        key: Some(IndexedDBKeyType::Number(key)),
+        value: value.to_vec(),
+        index_keys,
+        overwrite: false,
+    }
+}
+
+fn finish(
+    thread: &IpcSender<IndexedDBThreadMsg>,
+    events: &IpcReceiver<ConnectionEvent>,
+    id: IndexedDBTxnId,
+) -> BackendResult<()> {
+    thread.send(IndexedDBThreadMsg::Commit(id)).unwrap();
+    match events.recv().unwrap() {
+        ConnectionEvent::TransactionFinished { serial, result } => {
+            assert_eq!(serial, id.serial);
+            result
+        },
+        event => panic!("Unexpected event {:?}", event),
+    }
+}
+
+/// Opens a database with an object store named "store".
+fn create_database(
+    thread: &IpcSender<IndexedDBThreadMsg>,
+    origin: ImmutableOrigin,
+) -> (u64, IpcReceiver<ConnectionEvent>) {
+    let (receiver, events) = open(thread, origin, "db", Some(1));
+    let connection = upgrade_connection(&receiver);
+    let upgrade = IndexedDBTxnId {
+        connection,
+        serial: 0,
+    };
+    execute(
+        thread,
+        upgrade,
+        AsyncOperation::CreateObjectStore(store_info("store")),
+    )
+    .unwrap();
+    assert_eq!(finish(thread, &events, upgrade), Ok(()));
+    (connection, events)
+}
+
+#[test]
+fn test_store_and_get_record() {
+    let thread: IpcSender<IndexedDBThreadMsg> = IndexedDBThreadFactory::new(None);
+    let (connection, events) = create_database(&thread, origin("https://example.com"));
+
+    let id = IndexedDBTxnId {
+        connection,
+        serial: 1,
+    };
+    thread
+        .send(IndexedDBThreadMsg::CreateTransaction {
+            id,
+            scope: vec!["store".to_owned()],
+            mode: IndexedDBTxnMode::Readwrite,
+        })
+        .unwrap();
+    match execute(&thread, id, put(1.0, b"value", vec![])) {
+        Ok(AsyncResponse::Key(key)) => assert_eq!(key, IndexedDBKeyType::Number(1.0)),
+        response => panic!("Unexpected response {:?}", response),
+    }
+    let get = AsyncOperation::Get {
+        store: "store".to_owned(),
+        index: None,
+        range: IndexedDBKeyRange::only(IndexedDBKeyType::Number(1.0)),
+        key_only: false,
+    };
+    match execute(&thread, id, get) {
+        Ok(AsyncResponse::Record(Some(record))) => {
+            assert_eq!(record.value, Some(b"value".to_vec()))
+        },
+        response => panic!("Unexpected response {:?}", response),
+    }
+    assert_eq!(finish(&thread, &events, id), Ok(()));
+}
+
+#[test]
+fn test_cursor_iteration() {
+    let thread: IpcSender<IndexedDBThreadMsg> = IndexedDBThreadFactory::new(None);
+    let (connection, events) = create_database(&thread, origin("https://example.com"));
+
+    let id = IndexedDBTxnId {
+        connection,
+        serial: 1,
+    };
+    thread
+        .send(IndexedDBThreadMsg::CreateTransaction {
+            id,
+            scope: vec!["store".to_owned()],
+            mode: IndexedDBTxnMode::Readwrite,
+        })
+        .unwrap();
+    for key in &[2.0, 1.0, 3.0] {
+        execute(&thread, id, put(*key, b"", vec![])).unwrap();
+    }
+
+    let iterate = |direction, position| {
+        AsyncOperation::Iterate(CursorIteration {
+            store: "store".to_owned(),
+            index: None,
+            range: None,
+            direction,
+            position,
+            object_store_position: None,
+            key: None,
+            primary_key: None,
+            count: 1,
+            key_only: true,
+        })
+    };
+    let key_of = |response| match response {
+        Ok(AsyncResponse::Record(record)) => record.map(|record| record.key),
+        response => panic!("Unexpected response {:?}", response),
+    };
+    let next = execute(&thread, id, iterate(IndexedDBCursorDirection::Next, None));
+    assert_eq!(key_of(next), Some(IndexedDBKeyType::Number(1.0)));
+    let next = execute(
+        &thread,
+        id,
+        iterate(
+            IndexedDBCursorDirection::Next,
+            Some(IndexedDBKeyType::Number(1.0)),
+        ),
+    );
+    assert_eq!(key_of(next), Some(IndexedDBKeyType::Number(2.0)));
+    let prev = execute(&thread, id, iterate(IndexedDBCursorDirection::Prev, None));
+    assert_eq!(key_of(prev), Some(IndexedDBKeyType::Number(3.0)));
+    let end = execute(
+        &thread,
+        id,
+        iterate(
+            IndexedDBCursorDirection::Next,
+            Some(IndexedDBKeyType::Number(3.0)),
+        ),
+    );
+    assert_eq!(key_of(end), None);
+    assert_eq!(finish(&thread, &events, id), Ok(()));
+}
+
+#[test]
+fn test_unique_index_constraint() {
+    let thread: IpcSender<IndexedDBThreadMsg> = IndexedDBThreadFactory::new(None);
+    let (receiver, events) = open(&thread, origin("https://example.com"), "db", Some(1));
+    let upgrade = IndexedDBTxnId {
+        connection: upgrade_connection(&receiver),
+        serial: 0,
+    };
+    execute(
+        &thread,
+        upgrade,
+        AsyncOperation::CreateObjectStore(store_info("store")),
+    )
+    .unwrap();
+    let index = IndexInfo {
+        name: "index".to_owned(),
+        key_path: KeyPath::String("name".to_owned()),
+        unique: true,
+        multi_entry: false,
+    };
+    execute(
+        &thread,
+        upgrade,
+        AsyncOperation::CreateIndex {
+            store: "store".to_owned(),
+            index,
+            entries: vec![],
+        },
+    )
+    .unwrap();
+
+    let index_keys = || {
+        vec![(
+            "index".to_owned(),
+            vec![IndexedDBKeyType::String("a".to_owned())],
+        )]
+    };
+    assert!(execute(&thread, upgrade, put(1.0, b"", index_keys())).is_ok());
+    match execute(&thread, upgrade, put(2.0, b"", index_keys())) {
+        Err(error) => assert_eq!(error, BackendError::Constraint),
+        response => panic!("Unexpected response {:?}", response),
+    }
+    assert_eq!(finish(&thread, &events, upgrade), Ok(()));
+}
+
+#[test]
+fn test_aborted_upgrade_is_reverted() {
+    let thread: IpcSender<IndexedDBThreadMsg> = IndexedDBThreadFactory::new(None);
+    let (receiver, events) = open(&thread, origin("https://example.com"), "db", Some(1));
+    let upgrade = IndexedDBTxnId {
+        connection: upgrade_connection(&receiver),
+        serial: 0,
+    };
+    execute(
+        &thread,
+        upgrade,
+        AsyncOperation::CreateObjectStore(store_info("store")),
+    )
+    .unwrap();
+    thread.send(IndexedDBThreadMsg::Abort(upgrade)).unwrap();
+    match events.recv().unwrap() {
+        ConnectionEvent::TransactionFinished { result, .. } => {
+            assert_eq!(result, Err(BackendError::Abort))
+        },
+        event => panic!("Unexpected event {:?}", event),
+    }
+
+    let (receiver, _events) = open(&thread, origin("https://example.com"), "db", None);
+    match receiver.recv().unwrap() {
+        OpenResponse::Upgrade {
+            old_version, info, ..
+        } => {
+            assert_eq!(old_version, 0);
+            assert!(info.object_stores.is_empty());
+        },
+        response => panic!("Unexpected response {:?}", response),
+    }
+}
+
+#[test]
+fn test_upgrade_blocked_by_open_connection() {
+    let thread: IpcSender<IndexedDBThreadMsg> = IndexedDBThreadFactory::new(None);
+    let (connection, events) = create_database(&thread, origin("https://example.com"));
+
+    let (receiver, _new_events) = open(&thread, origin("https://example.com"), "db", Some(2));
+    match events.recv().unwrap() {
+        ConnectionEvent::VersionChange {
+            old_version,
+            new_version,
+        } => {
+            assert_eq!(old_version, 1);
+            assert_eq!(new_version, Some(2));
+        },
+        event => panic!("Unexpected event {:?}", event),
+    }
+    match receiver.recv().unwrap() {
+        OpenResponse::Blocked { .. } => {},
+        response => panic!("Unexpected response {:?}", response),
+    }
+
+    thread.send(IndexedDBThreadMsg::Close(connection)).unwrap();
+    match receiver.recv().unwrap() {
+        OpenResponse::Upgrade {
+            old_version,
+            new_version,
+            ..
+        } => {
+            assert_eq!(old_version, 1);
+            assert_eq!(new_version, 2);
+        },
+        response => panic!("Unexpected response {:?}", response),
+    }
+}
+
+#[test]
+fn test_databases_are_partitioned_by_origin() {
+    let thread: IpcSender<IndexedDBThreadMsg> = IndexedDBThreadFactory::new(None);
+    let _connection = create_database(&thread, origin("https://example.com"));
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    thread
+        .send(IndexedDBThreadMsg::Databases(
+            sender.clone(),
+            origin("https://example.com"),
+        ))
+        .unwrap();
+    assert_eq!(receiver.recv().unwrap(), vec![("db".to_owned(), 1)]);
+
+    thread
+        .send(IndexedDBThreadMsg::Databases(
+            sender,
+            origin("https://example.org"),
+        ))
+        .unwrap();
+    assert!(receiver.recv().unwrap().is_empty());
+}
+
+#[test]
+fn test_databases_are_persisted() {
+    let config_dir = env::temp_dir().join(format!("servo-indexeddb-{}", Uuid::new_v4()));
+    let thread: IpcSender<IndexedDBThreadMsg> =
+        IndexedDBThreadFactory::new(Some(config_dir.clone()));
+    let (connection, events) = create_database(&thread, origin("https://example.com"));
+
+    let id = IndexedDBTxnId {
+        connection,
+        serial: 1,
+    };
+    thread
+        .send(IndexedDBThreadMsg::CreateTransaction {
+            id,
+            scope: vec!["store".to_owned()],
+            mode: IndexedDBTxnMode::Readwrite,
+        })
+        .unwrap();
+    // Keys that JSON can not represent as numbers.
+    execute(&thread, id, put(f64::INFINITY, b"infinity", vec![])).unwrap();
+    execute(&thread, id, put(f64::NEG_INFINITY, b"-infinity", vec![])).unwrap();
+    assert_eq!(finish(&thread, &events, id), Ok(()));
+    let (sender, receiver) = ipc::channel().unwrap();
+    thread.send(IndexedDBThreadMsg::Exit(sender)).unwrap();
+    receiver.recv().unwrap();
+
+    let thread: IpcSender<IndexedDBThreadMsg> =
+        IndexedDBThreadFactory::new(Some(config_dir.clone()));
+    let (receiver, _events) = open(&thread, origin("https://example.com"), "db", None);
+    let connection = match receiver.recv().unwrap() {
+        OpenResponse::Success { connection, .. } => connection,
+        response => panic!("Unexpected response {:?}", response),
+    };
+    let id = IndexedDBTxnId {
+        connection,
+        serial: 1,
+    };
+    thread
+        .send(IndexedDBThreadMsg::CreateTransaction {
+            id,
+            scope: vec!["store".to_owned()],
+            mode: IndexedDBTxnMode::Readonly,
+        })
+        .unwrap();
+    let get = AsyncOperation::Get {
+        store: "store".to_owned(),
+        index: None,
+        range: IndexedDBKeyRange::only(IndexedDBKeyType::Number(f64::INFINITY)),
+        key_only: false,
+    };
+    match execute(&thread, id, get) {
+        Ok(AsyncResponse::Record(Some(record))) => {
+            assert_eq!(record.value, Some(b"infinity".to_vec()))
+        },
+        response => panic!("Unexpected response {:?}", response),
+    }
+    let _ = fs::remove_dir_all(config_dir);
+}
+
+#[test]
+fn test_write_failures_abort_the_transaction() {
+    // The config dir is a file, so nothing can be written in it.
+    let config_dir = env::temp_dir().join(format!("servo-indexeddb-{}", Uuid::new_v4()));
+    fs::write(&config_dir, b"").unwrap();
+    let thread: IpcSender<IndexedDBThreadMsg> =
+        IndexedDBThreadFactory::new(Some(config_dir.clone()));
+    let (receiver, events) = open(&thread, origin("https://example.com"), "db", Some(1));
+    let upgrade = IndexedDBTxnId {
+        connection: upgrade_connection(&receiver),
+        serial: 0,
+    };
+    execute(
+        &thread,
+        upgrade,
+        AsyncOperation::CreateObjectStore(store_info("store")),
+    )
+    .unwrap();
+    assert_eq!(
+        finish(&thread, &events, upgrade),
+        Err(BackendError::Unknown)
+    );
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    thread
+        .send(IndexedDBThreadMsg::Databases(
+            sender,
+            origin("https://example.com"),
+        ))
+        .unwrap();
+    assert!(receiver.recv().unwrap().is_empty());
+    let _ = fs::remove_file(config_dir);
+}
+
+#[test]
+fn test_unreadable_databases_are_moved_aside() {
+    let config_dir = env::temp_dir().join(format!("servo-indexeddb-{}", Uuid::new_v4()));
+    let database_dir = config_dir.join("indexeddb").join("unreadable");
+    fs::create_dir_all(&database_dir).unwrap();
+    fs::write(database_dir.join("database.json"), b"{").unwrap();
+
+    let thread: IpcSender<IndexedDBThreadMsg> =
+        IndexedDBThreadFactory::new(Some(config_dir.clone()));
+    let (sender, receiver) = ipc::channel().unwrap();
+    thread
+        .send(IndexedDBThreadMsg::Databases(
+            sender,
+            origin("https://example.com"),
+        ))
+        .unwrap();
+    assert!(receiver.recv().unwrap().is_empty());
+    assert!(!database_dir.exists());
+    assert!(database_dir.with_extension("corrupt").is_dir());
+    let _ = fs::remove_dir_all(config_dir);
+}
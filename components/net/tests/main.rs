@@ -16,7 +16,9 @@ mod filemanager_thread;
 mod hsts;
 mod http_cache;
 mod http_loader;
+mod indexeddb_thread;
 mod mime_classifier;
+mod proxy;
 mod resource_thread;
 mod subresource_integrity;
 
@@ -33,6 +35,7 @@ use net::connector::{create_tls_config, ALPN_H2_H1};
 use net::fetch::cors_cache::CorsCache;
 use net::fetch::methods::{self, CancellationListener, FetchContext};
 use net::filemanager_thread::FileManager;
+use net::proxy::ProxyConfig;
 use net::resource_thread::CoreResourceThreadPool;
 use net::test::HttpState;
 use net_traits::filemanager_thread::FileTokenCheck;
@@ -95,7 +98,11 @@ fn new_fetch_context(
     let sender = fc.unwrap_or_else(|| create_embedder_proxy());
 
     FetchContext {
-        state: Arc::new(HttpState::new(tls_config)),
+        state: Arc::new(HttpState::new(
+            tls_config,
+            ProxyConfig::default(),
+            sender.clone(),
+        )),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: dc,
         filemanager: FileManager::new(sender, pool_handle.unwrap_or_else(|| Weak::new())),
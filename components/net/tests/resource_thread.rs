@@ -4,11 +4,13 @@
 
 use crate::create_embedder_proxy;
 use ipc_channel::ipc;
+use net::cookie_storage::CookiePolicy;
 use net::resource_thread::new_core_resource_thread;
 use net::test::parse_hostsfile;
-use net_traits::CoreResourceMsg;
+use net_traits::{CookieSource, CoreResourceMsg};
 use profile_traits::mem::ProfilerChan as MemProfilerChan;
 use profile_traits::time::ProfilerChan;
+use servo_url::ServoUrl;
 use std::net::IpAddr;
 
 fn ip(s: &str) -> IpAddr {
@@ -28,11 +30,83 @@ fn test_exit() {
         create_embedder_proxy(),
         None,
         None,
+        CookiePolicy::default(),
     );
     resource_thread.send(CoreResourceMsg::Exit(sender)).unwrap();
     receiver.recv().unwrap();
 }
 
+#[test]
+fn test_document_cookies_are_blocked_in_third_party_context() {
+    let (tx, _rx) = ipc::channel().unwrap();
+    let (mtx, _mrx) = ipc::channel().unwrap();
+    let (resource_thread, _private_resource_thread) = new_core_resource_thread(
+        "".into(),
+        None,
+        ProfilerChan(tx),
+        MemProfilerChan(mtx),
+        create_embedder_proxy(),
+        None,
+        None,
+        CookiePolicy::BlockThirdParty,
+    );
+
+    let url = ServoUrl::parse("https://frame.test/").unwrap();
+    let top_level_origin = ServoUrl::parse("https://top-level.test/").unwrap().origin();
+    let set_cookie = |top_level_origin, cookie: &str| {
+        resource_thread
+            .send(CoreResourceMsg::SetCookieStringForUrl(
+                url.clone(),
+                top_level_origin,
+                cookie.to_owned(),
+                CookieSource::NonHTTP,
+            ))
+            .unwrap();
+    };
+    let get_cookies = |top_level_origin| {
+        let (sender, receiver) = ipc::channel().unwrap();
+        resource_thread
+            .send(CoreResourceMsg::GetCookiesForUrl(
+                url.clone(),
+                top_level_origin,
+                sender,
+                CookieSource::NonHTTP,
+            ))
+            .unwrap();
+        receiver.recv().unwrap()
+    };
+
+    // `document.cookie` in an iframe of another site can neither set nor read cookies,
+    // and neither can a document whose top-level origin is unknown.
+    set_cookie(Some(top_level_origin.clone()), "third=party");
+    set_cookie(None, "unknown=context");
+    set_cookie(Some(url.origin()), "first=party");
+    assert_eq!(get_cookies(Some(top_level_origin.clone())), None);
+    assert_eq!(get_cookies(None), None);
+    assert_eq!(
+        get_cookies(Some(url.origin())),
+        Some("first=party".to_owned())
+    );
+
+    // Unless they are partitioned, in which case they are only seen under the same top-level site.
+    set_cookie(
+        Some(top_level_origin.clone()),
+        "partitioned=cookie; Secure; Partitioned",
+    );
+    assert_eq!(
+        get_cookies(Some(top_level_origin)),
+        Some("partitioned=cookie".to_owned())
+    );
+    assert_eq!(
+        get_cookies(Some(url.origin())),
+        Some("first=party".to_owned())
+    );
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    resource_thread.send(CoreResourceMsg::Exit(sender)).unwrap();
+    receiver.recv().unwrap();
+}
+
 #[test]
 fn test_parse_hostsfile() {
     let mock_hosts_file_content = "127.0.0.1 foo.bar.com\n127.0.0.2 servo.test.server";
@@ -15,7 +15,7 @@ use net_traits::request::{RequestBuilder, RequestMode};
 use net_traits::{CookieSource, MessageData};
 use net_traits::{WebSocketDomAction, WebSocketNetworkEvent};
 use openssl::ssl::SslStream;
-use servo_url::ServoUrl;
+use servo_url::{ImmutableOrigin, ServoUrl};
 use std::fs;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -35,6 +35,7 @@ struct Client<'a> {
     protocols: &'a [String],
     http_state: &'a Arc<HttpState>,
     resource_url: &'a ServoUrl,
+    top_level_origin: Option<&'a ImmutableOrigin>,
     event_sender: &'a IpcSender<WebSocketNetworkEvent>,
     protocol_in_use: Option<String>,
     certificate_path: Option<String>,
@@ -64,8 +65,11 @@ impl<'a> Handler for Client<'a> {
 
         let mut cookie_jar = self.http_state.cookie_jar.write().unwrap();
         cookie_jar.remove_expired_cookies_for_url(self.resource_url);
-        if let Some(cookie_list) = cookie_jar.cookies_for_url(self.resource_url, CookieSource::HTTP)
-        {
+        if let Some(cookie_list) = cookie_jar.cookies_for_url_and_top_level(
+            self.resource_url,
+            self.top_level_origin,
+            CookieSource::HTTP,
+        ) {
             req.headers_mut()
                 .push(("Cookie".into(), cookie_list.as_bytes().to_owned()))
         }
@@ -89,7 +93,12 @@ impl<'a> Handler for Client<'a> {
                 if let Some(cookie) =
                     Cookie::from_cookie_string(s.into(), self.resource_url, CookieSource::HTTP)
                 {
-                    jar.push(cookie, self.resource_url, CookieSource::HTTP);
+                    jar.push_for_top_level(
+                        cookie,
+                        self.resource_url,
+                        self.top_level_origin,
+                        CookieSource::HTTP,
+                    );
                 }
             }
         }
@@ -226,6 +235,7 @@ pub fn init(
                 protocols: &protocols,
                 http_state: &http_state,
                 resource_url: &req_builder.url,
+                top_level_origin: req_builder.top_level_origin.as_ref(),
                 event_sender: &resource_event_sender,
                 protocol_in_use: None,
                 certificate_path,
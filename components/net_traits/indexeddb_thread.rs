@@ -0,0 +1,385 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use ipc_channel::ipc::IpcSender;
+use servo_url::ImmutableOrigin;
+use std::cmp::Ordering;
+
+/// https://w3c.github.io/IndexedDB/#key-construct
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub enum IndexedDBKeyType {
+    Number(#[serde(with = "f64_bits")] f64),
+    Date(#[serde(with = "f64_bits")] f64),
+    String(String),
+    Binary(Vec<u8>),
+    Array(Vec<IndexedDBKeyType>),
+}
+
+/// Numbers are serialized by their bits, since JSON can not represent infinite ones.
+mod f64_bits {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.to_bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        u64::deserialize(deserializer).map(f64::from_bits)
+    }
+}
+
+impl IndexedDBKeyType {
+    /// The relative order of key types, as used when comparing keys of different types.
+    fn type_order(&self) -> u8 {
+        match *self {
+            IndexedDBKeyType::Number(_) => 0,
+            IndexedDBKeyType::Date(_) => 1,
+            IndexedDBKeyType::String(_) => 2,
+            IndexedDBKeyType::Binary(_) => 3,
+            IndexedDBKeyType::Array(_) => 4,
+        }
+    }
+}
+
+/// https://w3c.github.io/IndexedDB/#compare-two-keys
+impl Ord for IndexedDBKeyType {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (IndexedDBKeyType::Number(a), IndexedDBKeyType::Number(b)) |
+            (IndexedDBKeyType::Date(a), IndexedDBKeyType::Date(b)) => {
+                // Keys are never NaN, so this can not fail.
+                a.partial_cmp(b).unwrap_or(Ordering::Equal)
+            },
+            // Strings are compared by code unit.
+            (IndexedDBKeyType::String(a), IndexedDBKeyType::String(b)) => {
+                a.encode_utf16().cmp(b.encode_utf16())
+            },
+            (IndexedDBKeyType::Binary(a), IndexedDBKeyType::Binary(b)) => a.cmp(b),
+            (IndexedDBKeyType::Array(a), IndexedDBKeyType::Array(b)) => a.cmp(b),
+            _ => self.type_order().cmp(&other.type_order()),
+        }
+    }
+}
+
+impl PartialOrd for IndexedDBKeyType {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for IndexedDBKeyType {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for IndexedDBKeyType {}
+
+/// https://w3c.github.io/IndexedDB/#range-construct
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct IndexedDBKeyRange {
+    pub lower: Option<IndexedDBKeyType>,
+    pub upper: Option<IndexedDBKeyType>,
+    pub lower_open: bool,
+    pub upper_open: bool,
+}
+
+impl IndexedDBKeyRange {
+    /// A range containing only the given key.
+    pub fn only(key: IndexedDBKeyType) -> IndexedDBKeyRange {
+        IndexedDBKeyRange {
+            lower: Some(key.clone()),
+            upper: Some(key),
+            lower_open: false,
+            upper_open: false,
+        }
+    }
+
+    /// A range containing every key.
+    pub fn unbounded() -> IndexedDBKeyRange {
+        IndexedDBKeyRange {
+            lower: None,
+            upper: None,
+            lower_open: false,
+            upper_open: false,
+        }
+    }
+
+    /// https://w3c.github.io/IndexedDB/#in
+    pub fn contains(&self, key: &IndexedDBKeyType) -> bool {
+        let above_lower = match self.lower {
+            Some(ref lower) if self.lower_open => key > lower,
+            Some(ref lower) => key >= lower,
+            None => true,
+        };
+        let below_upper = match self.upper {
+            Some(ref upper) if self.upper_open => key < upper,
+            Some(ref upper) => key <= upper,
+            None => true,
+        };
+        above_lower && below_upper
+    }
+}
+
+/// https://w3c.github.io/IndexedDB/#key-path-construct
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum KeyPath {
+    String(String),
+    Sequence(Vec<String>),
+}
+
+/// https://w3c.github.io/IndexedDB/#transaction-mode
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum IndexedDBTxnMode {
+    Readonly,
+    Readwrite,
+    Versionchange,
+}
+
+/// https://w3c.github.io/IndexedDB/#cursor-direction
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum IndexedDBCursorDirection {
+    Next,
+    NextUnique,
+    Prev,
+    PrevUnique,
+}
+
+/// Identifies a transaction: the connection it was created from,
+/// and a serial number unique to that connection.
+/// The upgrade transaction of a connection always has serial number 0.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize)]
+pub struct IndexedDBTxnId {
+    pub connection: u64,
+    pub serial: u64,
+}
+
+/// The metadata of an index.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct IndexInfo {
+    pub name: String,
+    pub key_path: KeyPath,
+    pub unique: bool,
+    pub multi_entry: bool,
+}
+
+/// The metadata of an object store.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct ObjectStoreInfo {
+    pub name: String,
+    pub key_path: Option<KeyPath>,
+    pub auto_increment: bool,
+    pub indexes: Vec<IndexInfo>,
+}
+
+/// The metadata of a database, as seen by a connection when it is opened.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct DatabaseInfo {
+    pub name: String,
+    pub version: u64,
+    pub object_stores: Vec<ObjectStoreInfo>,
+}
+
+/// A record read from an object store or an index.
+/// For object store records, `key` and `primary_key` are the same.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IndexedDBRecord {
+    pub key: IndexedDBKeyType,
+    pub primary_key: IndexedDBKeyType,
+    /// The structured-clone serialization of the value,
+    /// or `None` if only the keys were requested.
+    pub value: Option<Vec<u8>>,
+}
+
+/// The errors the backend can report, to be turned into DOMExceptions by script.
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum BackendError {
+    /// A uniqueness requirement was violated.
+    Constraint,
+    /// The requested object store or index does not exist.
+    NotFound,
+    /// The requested version is lower than the current version.
+    Version,
+    /// The transaction was aborted.
+    Abort,
+    /// The operation failed for reasons unrelated to the database itself,
+    /// such as a failure to write it to disk.
+    Unknown,
+}
+
+pub type BackendResult<T> = Result<T, BackendError>;
+
+/// The parameters of https://w3c.github.io/IndexedDB/#iterate-a-cursor
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CursorIteration {
+    pub store: String,
+    pub index: Option<String>,
+    pub range: Option<IndexedDBKeyRange>,
+    pub direction: IndexedDBCursorDirection,
+    /// The cursor's current position, `None` before the first iteration.
+    pub position: Option<IndexedDBKeyType>,
+    /// The cursor's object store position, only used by index cursors.
+    pub object_store_position: Option<IndexedDBKeyType>,
+    /// The key to continue to, if any.
+    pub key: Option<IndexedDBKeyType>,
+    /// The primary key to continue to, if any.
+    pub primary_key: Option<IndexedDBKeyType>,
+    /// The number of records to step over.
+    pub count: u32,
+    pub key_only: bool,
+}
+
+/// An operation placed against a transaction.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum AsyncOperation {
+    /// Create an object store, only valid in an upgrade transaction.
+    CreateObjectStore(ObjectStoreInfo),
+    /// Delete the named object store, only valid in an upgrade transaction.
+    DeleteObjectStore(String),
+    /// Create an index, populated with the given index keys for each primary key,
+    /// only valid in an upgrade transaction.
+    CreateIndex {
+        store: String,
+        index: IndexInfo,
+        entries: Vec<(IndexedDBKeyType, Vec<IndexedDBKeyType>)>,
+    },
+    /// Delete an index, only valid in an upgrade transaction.
+    DeleteIndex { store: String, name: String },
+    /// https://w3c.github.io/IndexedDB/#store-a-record-into-an-object-store
+    /// If no key is given, one is taken from the key generator of the store.
+    Put {
+        store: String,
+        key: Option<IndexedDBKeyType>,
+        value: Vec<u8>,
+        index_keys: Vec<(String, Vec<IndexedDBKeyType>)>,
+        overwrite: bool,
+    },
+    /// Retrieve the first record in range, from the store or one of its indexes.
+    Get {
+        store: String,
+        index: Option<String>,
+        range: IndexedDBKeyRange,
+        key_only: bool,
+    },
+    /// Retrieve up to `count` records in range, from the store or one of its indexes.
+    GetAll {
+        store: String,
+        index: Option<String>,
+        range: Option<IndexedDBKeyRange>,
+        count: Option<u32>,
+        key_only: bool,
+    },
+    /// Count the records in range, in the store or one of its indexes.
+    Count {
+        store: String,
+        index: Option<String>,
+        range: Option<IndexedDBKeyRange>,
+    },
+    /// Delete the records in range from the store.
+    Delete {
+        store: String,
+        range: IndexedDBKeyRange,
+    },
+    /// Delete every record of the store.
+    Clear(String),
+    /// Move a cursor to its next record.
+    Iterate(CursorIteration),
+}
+
+/// The result of an `AsyncOperation`.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum AsyncResponse {
+    Void,
+    Key(IndexedDBKeyType),
+    Record(Option<IndexedDBRecord>),
+    Records(Vec<IndexedDBRecord>),
+    Count(u64),
+}
+
+/// Replies to a request to open or delete a database.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum OpenResponse {
+    /// Other connections are still open after having been sent a versionchange event.
+    Blocked {
+        old_version: u64,
+        new_version: Option<u64>,
+    },
+    /// A connection was opened, and its upgrade transaction is running.
+    Upgrade {
+        connection: u64,
+        old_version: u64,
+        new_version: u64,
+        info: DatabaseInfo,
+    },
+    /// A connection was opened.
+    Success {
+        connection: u64,
+        info: DatabaseInfo,
+    },
+    /// The database was deleted.
+    Deleted {
+        old_version: u64,
+    },
+    Error(BackendError),
+}
+
+/// Events sent to an open connection.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum ConnectionEvent {
+    /// Another connection wants to upgrade or delete the database.
+    VersionChange {
+        old_version: u64,
+        new_version: Option<u64>,
+    },
+    /// A transaction of this connection committed or aborted.
+    TransactionFinished {
+        serial: u64,
+        result: BackendResult<()>,
+    },
+}
+
+/// Request operations on the indexed databases of an origin.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum IndexedDBThreadMsg {
+    /// https://w3c.github.io/IndexedDB/#open-a-database
+    Open {
+        sender: IpcSender<OpenResponse>,
+        events: IpcSender<ConnectionEvent>,
+        origin: ImmutableOrigin,
+        name: String,
+        version: Option<u64>,
+    },
+
+    /// https://w3c.github.io/IndexedDB/#delete-a-database
+    DeleteDatabase(IpcSender<OpenResponse>, ImmutableOrigin, String),
+
+    /// Gets the names and versions of the databases of an origin
+    Databases(IpcSender<Vec<(String, u64)>>, ImmutableOrigin),
+
+    /// https://w3c.github.io/IndexedDB/#close-a-database-connection
+    Close(u64),
+
+    /// Creates a transaction, which starts once no overlapping transaction is running
+    CreateTransaction {
+        id: IndexedDBTxnId,
+        scope: Vec<String>,
+        mode: IndexedDBTxnMode,
+    },
+
+    /// Places an operation against a transaction, replying once it has run
+    Operation(
+        IndexedDBTxnId,
+        IpcSender<BackendResult<AsyncResponse>>,
+        AsyncOperation,
+    ),
+
+    /// Commits a transaction once all its operations have run
+    Commit(IndexedDBTxnId),
+
+    /// Aborts a transaction, reverting its changes
+    Abort(IndexedDBTxnId),
+
+    /// send a reply when done cleaning up thread resources and then shut it down
+    Exit(IpcSender<()>),
+}
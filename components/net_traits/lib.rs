@@ -16,6 +16,7 @@ extern crate malloc_size_of_derive;
 extern crate serde;
 
 use crate::filemanager_thread::FileManagerThreadMsg;
+use crate::indexeddb_thread::IndexedDBThreadMsg;
 use crate::request::{Request, RequestBuilder};
 use crate::response::{HttpsState, Response, ResponseInit};
 use crate::storage_thread::StorageThreadMsg;
@@ -37,6 +38,7 @@ use webrender_api::{ImageData, ImageDescriptor, ImageKey};
 pub mod blob_url_store;
 pub mod filemanager_thread;
 pub mod image_cache;
+pub mod indexeddb_thread;
 pub mod pub_domains;
 pub mod quality;
 pub mod request;
@@ -332,13 +334,19 @@ where
 pub struct ResourceThreads {
     core_thread: CoreResourceThread,
     storage_thread: IpcSender<StorageThreadMsg>,
+    indexeddb_thread: IpcSender<IndexedDBThreadMsg>,
 }
 
 impl ResourceThreads {
-    pub fn new(c: CoreResourceThread, s: IpcSender<StorageThreadMsg>) -> ResourceThreads {
+    pub fn new(
+        c: CoreResourceThread,
+        s: IpcSender<StorageThreadMsg>,
+        i: IpcSender<IndexedDBThreadMsg>,
+    ) -> ResourceThreads {
         ResourceThreads {
             core_thread: c,
             storage_thread: s,
+            indexeddb_thread: i,
         }
     }
 }
@@ -363,6 +371,16 @@ impl IpcSend<StorageThreadMsg> for ResourceThreads {
     }
 }
 
+impl IpcSend<IndexedDBThreadMsg> for ResourceThreads {
+    fn send(&self, msg: IndexedDBThreadMsg) -> IpcSendResult {
+        self.indexeddb_thread.send(msg)
+    }
+
+    fn sender(&self) -> IpcSender<IndexedDBThreadMsg> {
+        self.indexeddb_thread.clone()
+    }
+}
+
 // Ignore the sub-fields
 malloc_size_of_is_0!(ResourceThreads);
 
@@ -418,19 +436,34 @@ pub enum CoreResourceMsg {
         IpcSender<FetchResponseMsg>,
         /* cancel_chan */ Option<IpcReceiver<()>>,
     ),
-    /// Store a cookie for a given originating URL
-    SetCookieForUrl(ServoUrl, Serde<Cookie<'static>>, CookieSource),
-    /// Store a set of cookies for a given originating URL
-    SetCookiesForUrl(ServoUrl, Vec<Serde<Cookie<'static>>>, CookieSource),
-    /// Retrieve the stored cookies for a given URL
-    GetCookiesForUrl(ServoUrl, IpcSender<Option<String>>, CookieSource),
-    /// Get a cookie by name for a given originating URL
+    /// Store a cookie for a given originating URL, under the origin of its top-level document
+    SetCookieForUrl(
+        ServoUrl,
+        Option<ImmutableOrigin>,
+        Serde<Cookie<'static>>,
+        CookieSource,
+    ),
+    /// Store the cookie of a cookie string for a given originating URL, under the origin of its
+    /// top-level document
+    SetCookieStringForUrl(ServoUrl, Option<ImmutableOrigin>, String, CookieSource),
+    /// Retrieve the stored cookies for a given URL, under the origin of its top-level document
+    GetCookiesForUrl(
+        ServoUrl,
+        Option<ImmutableOrigin>,
+        IpcSender<Option<String>>,
+        CookieSource,
+    ),
+    /// Get a cookie by name for a given originating URL, under the origin of its
+    /// top-level document
     GetCookiesDataForUrl(
         ServoUrl,
+        Option<ImmutableOrigin>,
         IpcSender<Vec<Serde<Cookie<'static>>>>,
         CookieSource,
     ),
     DeleteCookies(ServoUrl),
+    /// Delete the cookies with the given name for a given originating URL
+    DeleteCookie(ServoUrl, String),
     /// Get a history state by a given history state id
     GetHistoryState(HistoryStateId, IpcSender<Option<Vec<u8>>>),
     /// Set a history state for a given history state id
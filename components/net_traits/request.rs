@@ -8,6 +8,7 @@ use crate::ResourceTimingType;
 use content_security_policy::{self as csp, CspList};
 use http::HeaderMap;
 use hyper::Method;
+use ipc_channel::ipc::IpcSender;
 use mime::Mime;
 use msg::constellation_msg::PipelineId;
 use servo_url::{ImmutableOrigin, ServoUrl};
@@ -115,6 +116,24 @@ pub enum ParserMetadata {
     NotParserInserted,
 }
 
+/// A message to the reader of a request body given as a stream.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum BodyChunkRequest {
+    /// Start reading the body, sending its chunks to the given channel.
+    Connect(IpcSender<BodyChunkResponse>),
+}
+
+/// A message from the reader of a request body given as a stream.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum BodyChunkResponse {
+    /// The next chunk of the body.
+    Chunk(Vec<u8>),
+    /// The body has been read in full.
+    Done,
+    /// The body could not be read, or was already read.
+    Error,
+}
+
 #[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
 pub struct RequestBuilder {
     #[serde(
@@ -132,6 +151,9 @@ pub struct RequestBuilder {
     pub headers: HeaderMap,
     pub unsafe_request: bool,
     pub body: Option<Vec<u8>>,
+    /// The reader of the body, when it is given as a stream rather than as bytes.
+    #[ignore_malloc_size_of = "Channels are hard"]
+    pub body_chunks: Option<IpcSender<BodyChunkRequest>>,
     pub service_workers_mode: ServiceWorkersMode,
     // TODO: client object
     pub destination: Destination,
@@ -158,6 +180,9 @@ pub struct RequestBuilder {
     pub parser_metadata: ParserMetadata,
     pub initiator: Initiator,
     pub https_state: HttpsState,
+    /// The origin of the top-level document the request is made for, if it isn't
+    /// the request's own URL. Used to partition and block third-party cookies.
+    pub top_level_origin: Option<ImmutableOrigin>,
 }
 
 impl RequestBuilder {
@@ -168,6 +193,7 @@ impl RequestBuilder {
             headers: HeaderMap::new(),
             unsafe_request: false,
             body: None,
+            body_chunks: None,
             service_workers_mode: ServiceWorkersMode::All,
             destination: Destination::None,
             synchronous: false,
@@ -187,6 +213,7 @@ impl RequestBuilder {
             initiator: Initiator::None,
             csp_list: None,
             https_state: HttpsState::None,
+            top_level_origin: None,
         }
     }
 
@@ -215,6 +242,14 @@ impl RequestBuilder {
         self
     }
 
+    pub fn body_chunks(
+        mut self,
+        body_chunks: Option<IpcSender<BodyChunkRequest>>,
+    ) -> RequestBuilder {
+        self.body_chunks = body_chunks;
+        self
+    }
+
     pub fn destination(mut self, destination: Destination) -> RequestBuilder {
         self.destination = destination;
         self
@@ -285,6 +320,11 @@ impl RequestBuilder {
         self
     }
 
+    pub fn top_level_origin(mut self, top_level_origin: Option<ImmutableOrigin>) -> RequestBuilder {
+        self.top_level_origin = top_level_origin;
+        self
+    }
+
     pub fn build(self) -> Request {
         let mut request = Request::new(
             self.url.clone(),
@@ -297,6 +337,7 @@ impl RequestBuilder {
         request.headers = self.headers;
         request.unsafe_request = self.unsafe_request;
         request.body = self.body;
+        request.body_chunks = self.body_chunks;
         request.service_workers_mode = self.service_workers_mode;
         request.destination = self.destination;
         request.synchronous = self.synchronous;
@@ -317,6 +358,7 @@ impl RequestBuilder {
         request.integrity_metadata = self.integrity_metadata;
         request.parser_metadata = self.parser_metadata;
         request.csp_list = self.csp_list;
+        request.top_level_origin = self.top_level_origin;
         request
     }
 }
@@ -339,6 +381,10 @@ pub struct Request {
     pub unsafe_request: bool,
     /// <https://fetch.spec.whatwg.org/#concept-request-body>
     pub body: Option<Vec<u8>>,
+    /// The reader of the body, when it is a stream, which has no
+    /// [source](https://fetch.spec.whatwg.org/#concept-body-source) and can only be read once.
+    #[ignore_malloc_size_of = "Channels are hard"]
+    pub body_chunks: Option<IpcSender<BodyChunkRequest>>,
     // TODO: client object
     pub window: Window,
     // TODO: target browsing context
@@ -390,6 +436,8 @@ pub struct Request {
     #[ignore_malloc_size_of = "Defined in rust-content-security-policy"]
     pub csp_list: Option<CspList>,
     pub https_state: HttpsState,
+    /// The origin of the top-level document, if it isn't the one loaded by this request.
+    pub top_level_origin: Option<ImmutableOrigin>,
 }
 
 impl Request {
@@ -406,6 +454,7 @@ impl Request {
             headers: HeaderMap::new(),
             unsafe_request: false,
             body: None,
+            body_chunks: None,
             window: Window::Client,
             keep_alive: false,
             service_workers_mode: ServiceWorkersMode::All,
@@ -429,6 +478,7 @@ impl Request {
             response_tainting: ResponseTainting::Basic,
             csp_list: None,
             https_state: https_state,
+            top_level_origin: None,
         }
     }
 
@@ -17,3 +17,4 @@ mod heartbeats;
 pub mod mem;
 pub mod time;
 pub mod trace_dump;
+pub mod trace_events;
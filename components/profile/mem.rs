@@ -7,8 +7,8 @@
 use crate::time::duration_from_seconds;
 use ipc_channel::ipc::{self, IpcReceiver};
 use ipc_channel::router::ROUTER;
-use profile_traits::mem::ReportsChan;
 use profile_traits::mem::{ProfilerChan, ProfilerMsg, ReportKind, Reporter, ReporterRequest};
+use profile_traits::mem::{Report, ReportsChan};
 use std::borrow::ToOwned;
 use std::cmp::Ordering;
 use std::collections::HashMap;
@@ -121,6 +121,11 @@ impl Profiler {
                 true
             },
 
+            ProfilerMsg::Report(sender) => {
+                let _ = sender.send(self.collect_reports());
+                true
+            },
+
             ProfilerMsg::Exit => false,
         }
     }
@@ -130,6 +135,18 @@ impl Profiler {
         println!("Begin memory reports {}", elapsed.as_secs());
         println!("|");
 
+        let mut forest = ReportsForest::new();
+        for report in self.collect_reports() {
+            forest.insert(&report.path, report.size);
+        }
+        forest.print();
+
+        println!("|");
+        println!("End memory reports");
+        println!("");
+    }
+
+    fn collect_reports(&self) -> Vec<Report> {
         // Collect reports from memory reporters.
         //
         // This serializes the report-gathering. It might be worth creating a new scoped thread for
@@ -141,7 +158,7 @@ impl Profiler {
         // use that to compute the special "jemalloc-heap-unclassified" and
         // "system-heap-unclassified" values.
 
-        let mut forest = ReportsForest::new();
+        let mut all_reports = vec![];
 
         let mut jemalloc_heap_reported_size = 0;
         let mut system_heap_reported_size = 0;
@@ -186,32 +203,28 @@ impl Profiler {
                             system_heap_allocated_size = Some(report.size);
                         }
                     }
-
-                    // Insert the report.
-                    forest.insert(&report.path, report.size);
                 }
+                all_reports.extend(reports);
             }
         }
 
-        // Compute and insert the heap-unclassified values.
+        // Compute and add the heap-unclassified values.
         if let Some(jemalloc_heap_allocated_size) = jemalloc_heap_allocated_size {
-            forest.insert(
-                &path!["explicit", "jemalloc-heap-unclassified"],
-                jemalloc_heap_allocated_size - jemalloc_heap_reported_size,
-            );
+            all_reports.push(Report {
+                path: path!["explicit", "jemalloc-heap-unclassified"],
+                kind: ReportKind::ExplicitJemallocHeapSize,
+                size: jemalloc_heap_allocated_size - jemalloc_heap_reported_size,
+            });
         }
         if let Some(system_heap_allocated_size) = system_heap_allocated_size {
-            forest.insert(
-                &path!["explicit", "system-heap-unclassified"],
-                system_heap_allocated_size - system_heap_reported_size,
-            );
+            all_reports.push(Report {
+                path: path!["explicit", "system-heap-unclassified"],
+                kind: ReportKind::ExplicitSystemHeapSize,
+                size: system_heap_allocated_size - system_heap_reported_size,
+            });
         }
 
-        forest.print();
-
-        println!("|");
-        println!("End memory reports");
-        println!("");
+        all_reports
     }
 }
 
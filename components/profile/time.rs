@@ -6,10 +6,11 @@
 
 use crate::heartbeats;
 use crate::trace_dump::TraceDump;
+use crate::trace_events::TraceEvents;
 use ipc_channel::ipc::{self, IpcReceiver};
 use profile_traits::energy::{energy_interval_ms, read_energy_uj};
 use profile_traits::time::{
-    ProfilerCategory, ProfilerChan, ProfilerData, ProfilerMsg, TimerMetadata,
+    ProfilerCategory, ProfilerChan, ProfilerData, ProfilerMsg, ProfilerThread, TimerMetadata,
 };
 use profile_traits::time::{TimerMetadataFrameType, TimerMetadataReflowType};
 use servo_config::opts::OutputOptions;
@@ -94,68 +95,72 @@ impl Formattable for ProfilerCategory {
             ProfilerCategory::LayoutTextShaping => "| + ",
             _ => "",
         };
-        let name = match *self {
-            ProfilerCategory::Compositing => "Compositing",
-            ProfilerCategory::LayoutPerform => "Layout",
-            ProfilerCategory::LayoutStyleRecalc => "Style Recalc",
-            ProfilerCategory::LayoutTextShaping => "Text Shaping",
-            ProfilerCategory::LayoutRestyleDamagePropagation => "Restyle Damage Propagation",
-            ProfilerCategory::LayoutNonIncrementalReset => "Non-incremental reset (temporary)",
-            ProfilerCategory::LayoutSelectorMatch => "Selector Matching",
-            ProfilerCategory::LayoutTreeBuilder => "Tree Building",
-            ProfilerCategory::LayoutDamagePropagate => "Damage Propagation",
-            ProfilerCategory::LayoutDisplayListSorting => "Sorting Display List",
-            ProfilerCategory::LayoutGeneratedContent => "Generated Content Resolution",
-            ProfilerCategory::LayoutFloatPlacementSpeculation => "Float Placement Speculation",
-            ProfilerCategory::LayoutMain => "Primary Layout Pass",
-            ProfilerCategory::LayoutStoreOverflow => "Store Overflow",
-            ProfilerCategory::LayoutParallelWarmup => "Parallel Warmup",
-            ProfilerCategory::LayoutDispListBuild => "Display List Construction",
-            ProfilerCategory::NetHTTPRequestResponse => "Network HTTP Request/Response",
-            ProfilerCategory::PaintingPerTile => "Painting Per Tile",
-            ProfilerCategory::PaintingPrepBuff => "Buffer Prep",
-            ProfilerCategory::Painting => "Painting",
-            ProfilerCategory::ImageDecoding => "Image Decoding",
-            ProfilerCategory::ImageSaving => "Image Saving",
-            ProfilerCategory::ScriptAttachLayout => "Script Attach Layout",
-            ProfilerCategory::ScriptConstellationMsg => "Script Constellation Msg",
-            ProfilerCategory::ScriptDevtoolsMsg => "Script Devtools Msg",
-            ProfilerCategory::ScriptDocumentEvent => "Script Document Event",
-            ProfilerCategory::ScriptDomEvent => "Script Dom Event",
-            ProfilerCategory::ScriptEvaluate => "Script JS Evaluate",
-            ProfilerCategory::ScriptFileRead => "Script File Read",
-            ProfilerCategory::ScriptHistoryEvent => "Script History Event",
-            ProfilerCategory::ScriptImageCacheMsg => "Script Image Cache Msg",
-            ProfilerCategory::ScriptInputEvent => "Script Input Event",
-            ProfilerCategory::ScriptNetworkEvent => "Script Network Event",
-            ProfilerCategory::ScriptParseHTML => "Script Parse HTML",
-            ProfilerCategory::ScriptParseXML => "Script Parse XML",
-            ProfilerCategory::ScriptPlannedNavigation => "Script Planned Navigation",
-            ProfilerCategory::ScriptPortMessage => "Script Port Message",
-            ProfilerCategory::ScriptResize => "Script Resize",
-            ProfilerCategory::ScriptEvent => "Script Event",
-            ProfilerCategory::ScriptUpdateReplacedElement => "Script Update Replaced Element",
-            ProfilerCategory::ScriptSetScrollState => "Script Set Scroll State",
-            ProfilerCategory::ScriptSetViewport => "Script Set Viewport",
-            ProfilerCategory::ScriptTimerEvent => "Script Timer Event",
-            ProfilerCategory::ScriptStylesheetLoad => "Script Stylesheet Load",
-            ProfilerCategory::ScriptWebSocketEvent => "Script Web Socket Event",
-            ProfilerCategory::ScriptWorkerEvent => "Script Worker Event",
-            ProfilerCategory::ScriptServiceWorkerEvent => "Script Service Worker Event",
-            ProfilerCategory::ScriptEnterFullscreen => "Script Enter Fullscreen",
-            ProfilerCategory::ScriptExitFullscreen => "Script Exit Fullscreen",
-            ProfilerCategory::ScriptWebVREvent => "Script WebVR Event",
-            ProfilerCategory::ScriptWorkletEvent => "Script Worklet Event",
-            ProfilerCategory::ScriptPerformanceEvent => "Script Performance Event",
-            ProfilerCategory::ScriptWebGPUMsg => "Script WebGPU Message",
-            ProfilerCategory::TimeToFirstPaint => "Time To First Paint",
-            ProfilerCategory::TimeToFirstContentfulPaint => "Time To First Contentful Paint",
-            ProfilerCategory::TimeToInteractive => "Time to Interactive",
-            ProfilerCategory::IpcReceiver => "Blocked at IPC Receive",
-            ProfilerCategory::IpcBytesReceiver => "Blocked at IPC Bytes Receive",
-            ProfilerCategory::ApplicationHeartbeat => "Application Heartbeat",
-        };
-        format!("{}{}", padding, name)
+        format!("{}{}", padding, category_name(*self))
+    }
+}
+
+/// The name of a category, as shown in the profiler output.
+pub fn category_name(category: ProfilerCategory) -> &'static str {
+    match category {
+        ProfilerCategory::Compositing => "Compositing",
+        ProfilerCategory::LayoutPerform => "Layout",
+        ProfilerCategory::LayoutStyleRecalc => "Style Recalc",
+        ProfilerCategory::LayoutTextShaping => "Text Shaping",
+        ProfilerCategory::LayoutRestyleDamagePropagation => "Restyle Damage Propagation",
+        ProfilerCategory::LayoutNonIncrementalReset => "Non-incremental reset (temporary)",
+        ProfilerCategory::LayoutSelectorMatch => "Selector Matching",
+        ProfilerCategory::LayoutTreeBuilder => "Tree Building",
+        ProfilerCategory::LayoutDamagePropagate => "Damage Propagation",
+        ProfilerCategory::LayoutDisplayListSorting => "Sorting Display List",
+        ProfilerCategory::LayoutGeneratedContent => "Generated Content Resolution",
+        ProfilerCategory::LayoutFloatPlacementSpeculation => "Float Placement Speculation",
+        ProfilerCategory::LayoutMain => "Primary Layout Pass",
+        ProfilerCategory::LayoutStoreOverflow => "Store Overflow",
+        ProfilerCategory::LayoutParallelWarmup => "Parallel Warmup",
+        ProfilerCategory::LayoutDispListBuild => "Display List Construction",
+        ProfilerCategory::NetHTTPRequestResponse => "Network HTTP Request/Response",
+        ProfilerCategory::PaintingPerTile => "Painting Per Tile",
+        ProfilerCategory::PaintingPrepBuff => "Buffer Prep",
+        ProfilerCategory::Painting => "Painting",
+        ProfilerCategory::ImageDecoding => "Image Decoding",
+        ProfilerCategory::ImageSaving => "Image Saving",
+        ProfilerCategory::ScriptAttachLayout => "Script Attach Layout",
+        ProfilerCategory::ScriptConstellationMsg => "Script Constellation Msg",
+        ProfilerCategory::ScriptDevtoolsMsg => "Script Devtools Msg",
+        ProfilerCategory::ScriptDocumentEvent => "Script Document Event",
+        ProfilerCategory::ScriptDomEvent => "Script Dom Event",
+        ProfilerCategory::ScriptEvaluate => "Script JS Evaluate",
+        ProfilerCategory::ScriptFileRead => "Script File Read",
+        ProfilerCategory::ScriptHistoryEvent => "Script History Event",
+        ProfilerCategory::ScriptImageCacheMsg => "Script Image Cache Msg",
+        ProfilerCategory::ScriptInputEvent => "Script Input Event",
+        ProfilerCategory::ScriptNetworkEvent => "Script Network Event",
+        ProfilerCategory::ScriptParseHTML => "Script Parse HTML",
+        ProfilerCategory::ScriptParseXML => "Script Parse XML",
+        ProfilerCategory::ScriptPlannedNavigation => "Script Planned Navigation",
+        ProfilerCategory::ScriptPortMessage => "Script Port Message",
+        ProfilerCategory::ScriptResize => "Script Resize",
+        ProfilerCategory::ScriptEvent => "Script Event",
+        ProfilerCategory::ScriptUpdateReplacedElement => "Script Update Replaced Element",
+        ProfilerCategory::ScriptSetScrollState => "Script Set Scroll State",
+        ProfilerCategory::ScriptSetViewport => "Script Set Viewport",
+        ProfilerCategory::ScriptTimerEvent => "Script Timer Event",
+        ProfilerCategory::ScriptStylesheetLoad => "Script Stylesheet Load",
+        ProfilerCategory::ScriptWebSocketEvent => "Script Web Socket Event",
+        ProfilerCategory::ScriptWorkerEvent => "Script Worker Event",
+        ProfilerCategory::ScriptServiceWorkerEvent => "Script Service Worker Event",
+        ProfilerCategory::ScriptEnterFullscreen => "Script Enter Fullscreen",
+        ProfilerCategory::ScriptExitFullscreen => "Script Exit Fullscreen",
+        ProfilerCategory::ScriptWebVREvent => "Script WebVR Event",
+        ProfilerCategory::ScriptWorkletEvent => "Script Worklet Event",
+        ProfilerCategory::ScriptPerformanceEvent => "Script Performance Event",
+        ProfilerCategory::ScriptWebGPUMsg => "Script WebGPU Message",
+        ProfilerCategory::TimeToFirstPaint => "Time To First Paint",
+        ProfilerCategory::TimeToFirstContentfulPaint => "Time To First Contentful Paint",
+        ProfilerCategory::TimeToInteractive => "Time to Interactive",
+        ProfilerCategory::IpcReceiver => "Blocked at IPC Receive",
+        ProfilerCategory::IpcBytesReceiver => "Blocked at IPC Bytes Receive",
+        ProfilerCategory::ApplicationHeartbeat => "Application Heartbeat",
     }
 }
 
@@ -168,6 +173,7 @@ pub struct Profiler {
     output: Option<OutputOptions>,
     pub last_msg: Option<ProfilerMsg>,
     trace: Option<TraceDump>,
+    trace_events: Option<TraceEvents>,
     blocked_layout_queries: HashMap<String, u32>,
     profile_heartbeats: bool,
 }
@@ -194,7 +200,9 @@ impl Profiler {
                     .expect("Thread spawning failed");
                 // decide if we need to spawn the timer thread
                 match option {
-                    &OutputOptions::FileName(_) => { /* no timer thread needed */ },
+                    &OutputOptions::FileName(_) | &OutputOptions::TraceEvents(_) => {
+                        /* no timer thread needed */
+                    },
                     &OutputOptions::Stdout(period) => {
                         // Spawn a timer thread
                         let chan = chan.clone();
@@ -287,6 +295,7 @@ impl Profiler {
                             (ProfilerCategory::ApplicationHeartbeat, None),
                             (start_time, end_time),
                             (start_energy, end_energy),
+                            ProfilerThread::current(),
                         )) {
                             return;
                         }
@@ -306,12 +315,20 @@ impl Profiler {
         output: Option<OutputOptions>,
         profile_heartbeats: bool,
     ) -> Profiler {
+        let trace_events = match output {
+            Some(OutputOptions::TraceEvents(ref filename)) => match TraceEvents::new(filename) {
+                Err(e) => panic!("Couldn't create {}: {}", filename, e),
+                Ok(trace_events) => Some(trace_events),
+            },
+            _ => None,
+        };
         Profiler {
             port: port,
             buckets: BTreeMap::new(),
             output: output,
             last_msg: None,
             trace: trace,
+            trace_events: trace_events,
             blocked_layout_queries: HashMap::new(),
             profile_heartbeats,
         }
@@ -331,11 +348,14 @@ impl Profiler {
 
     fn handle_msg(&mut self, msg: ProfilerMsg) -> bool {
         match msg.clone() {
-            ProfilerMsg::Time(k, t, e) => {
+            ProfilerMsg::Time(k, t, e, thread) => {
                 heartbeats::maybe_heartbeat(&k.0, t.0, t.1, e.0, e.1, self.profile_heartbeats);
                 if let Some(ref mut trace) = self.trace {
                     trace.write_one(&k, t, e);
                 }
+                if let Some(ref mut trace_events) = self.trace_events {
+                    trace_events.record(&k, t, thread).unwrap();
+                }
                 let ms = (t.1 - t.0) as f64 / 1000000f64;
                 self.find_or_insert(k, ms);
             },
@@ -425,6 +445,11 @@ impl Profiler {
                     write!(file, "{}\t{}\n", url, count).unwrap();
                 }
             },
+            Some(OutputOptions::TraceEvents(_)) => {
+                if let Some(trace_events) = self.trace_events.take() {
+                    trace_events.finish().unwrap();
+                }
+            },
             Some(OutputOptions::Stdout(_)) => {
                 let stdout = io::stdout();
                 let mut lock = stdout.lock();
@@ -0,0 +1,284 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A module for writing time profiler events out in the [Chrome trace-event format]
+//! (https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU),
+//! which Perfetto and chrome://tracing can load.
+
+use crate::time::category_name;
+use profile_traits::time::{ProfilerCategory, ProfilerThread, TimerMetadata};
+use profile_traits::time::{TimerMetadataFrameType, TimerMetadataReflowType};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::mem;
+use std::path::Path;
+
+/// How long, in nanoseconds, an event is kept after it ended, waiting for the events it
+/// flows from or into to arrive. Flows between events further apart than this are not found.
+const FLOW_WINDOW: u64 = 1_000_000_000;
+
+/// A writer of profiled events to a trace file. Events are written out once they are older
+/// than the `FLOW_WINDOW`, since the flows between them can only be found once the events of
+/// all the threads involved have arrived.
+#[derive(Debug)]
+pub struct TraceEvents {
+    writer: BufWriter<File>,
+    /// Whether any trace event was written yet, and a separator is needed before the next one.
+    wrote_event: bool,
+    /// The profiled events that aren't written out yet.
+    events: Vec<ProfiledEvent>,
+    /// The latest time an event ended.
+    latest_time: u64,
+    /// The id of the next flow.
+    next_flow_id: usize,
+    /// The names of the threads the events happened on, keyed by process and thread ids.
+    threads: BTreeMap<(u32, u64), Option<String>>,
+}
+
+#[derive(Debug)]
+struct ProfiledEvent {
+    category: ProfilerCategory,
+    metadata: Option<TimerMetadata>,
+    /// Nanoseconds, from `precise_time_ns`.
+    start_time: u64,
+    end_time: u64,
+    pid: u32,
+    tid: u64,
+}
+
+#[derive(Serialize)]
+struct TraceEvent {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cat: Option<&'static str>,
+    ph: &'static str,
+    /// Microseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ts: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<f64>,
+    pid: u32,
+    tid: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<usize>,
+    /// How a flow end binds to a slice: "e" for the enclosing one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bp: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args: Option<Value>,
+}
+
+/// The group of a category, from the high bits of its value.
+fn category_group(category: ProfilerCategory) -> &'static str {
+    match category as u32 >> 4 {
+        0x0 => "compositing",
+        0x1 => "layout",
+        0x3 => "net",
+        0x4 => "painting",
+        0x5 => "image",
+        0x6 | 0x7 => "script",
+        _ => "misc",
+    }
+}
+
+impl TraceEvents {
+    /// Create the trace file, and write the start of its event list.
+    pub fn new<P>(trace_file_path: P) -> io::Result<TraceEvents>
+    where
+        P: AsRef<Path>,
+    {
+        let mut writer = BufWriter::new(File::create(trace_file_path)?);
+        write!(writer, "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[")?;
+        Ok(TraceEvents {
+            writer: writer,
+            wrote_event: false,
+            events: vec![],
+            latest_time: 0,
+            next_flow_id: 0,
+            threads: BTreeMap::new(),
+        })
+    }
+
+    /// Record one profiled event, and write out the events that are too old to be part of
+    /// a flow with the events yet to arrive.
+    pub fn record(
+        &mut self,
+        category: &(ProfilerCategory, Option<TimerMetadata>),
+        time: (u64, u64),
+        thread: ProfilerThread,
+    ) -> io::Result<()> {
+        self.threads.insert((thread.pid, thread.tid), thread.name);
+        let end_time = time.1.max(time.0);
+        self.events.push(ProfiledEvent {
+            category: category.0,
+            metadata: category.1.clone(),
+            start_time: time.0,
+            end_time: end_time,
+            pid: thread.pid,
+            tid: thread.tid,
+        });
+        self.latest_time = self.latest_time.max(end_time);
+        let window_start = self.latest_time.saturating_sub(FLOW_WINDOW);
+        self.write_events(|event| event.end_time < window_start)
+    }
+
+    /// Write the remaining events, a track per thread, and the end of the trace file.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.write_events(|_| true)?;
+        let threads = mem::replace(&mut self.threads, BTreeMap::new());
+        for ((pid, tid), name) in threads {
+            let name = name.unwrap_or_else(|| format!("Thread {}", tid));
+            self.write_event(&TraceEvent::metadata("thread_name", pid, tid, name))?;
+        }
+        write!(self.writer, "]}}")?;
+        self.writer.flush()
+    }
+
+    /// Write out, and forget, the events matching the predicate, along with the flows
+    /// of the layouts among them.
+    fn write_events<F>(&mut self, predicate: F) -> io::Result<()>
+    where
+        F: Fn(&ProfiledEvent) -> bool,
+    {
+        let mut flows = vec![];
+        let mut next_flow_id = self.next_flow_id;
+        for event in self.events.iter().filter(|event| predicate(*event)) {
+            let (script, composite) = self.flows(event);
+            if let Some(script) = script {
+                flows.push(TraceEvent::flow_pair(next_flow_id, script, event));
+                next_flow_id += 1;
+            }
+            if let Some(composite) = composite {
+                flows.push(TraceEvent::flow_pair(next_flow_id, event, composite));
+                next_flow_id += 1;
+            }
+        }
+        self.next_flow_id = next_flow_id;
+
+        let (written, kept) = mem::replace(&mut self.events, vec![])
+            .into_iter()
+            .partition::<Vec<_>, _>(|event| predicate(event));
+        self.events = kept;
+        for event in &written {
+            self.write_event(&TraceEvent {
+                name: category_name(event.category).to_owned(),
+                cat: Some(category_group(event.category)),
+                ph: "X",
+                ts: Some(micros(event.start_time)),
+                dur: Some(micros(event.end_time) - micros(event.start_time)),
+                pid: event.pid,
+                tid: event.tid,
+                id: None,
+                bp: None,
+                args: event.metadata.as_ref().map(metadata_args),
+            })?;
+        }
+        for (start, end) in flows {
+            // Both ends of a flow bind to the slices enclosing their timestamps.
+            self.write_event(&start)?;
+            self.write_event(&end)?;
+        }
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: &TraceEvent) -> io::Result<()> {
+        if self.wrote_event {
+            write!(self.writer, ",")?;
+        }
+        self.wrote_event = true;
+        serde_json::to_writer(&mut self.writer, event).map_err(io::Error::from)
+    }
+
+    /// Find the flows of a reflow: from the script event that caused a layout to the layout,
+    /// and from the layout to the next composite. Those aren't reported by the profiled
+    /// threads, so they are inferred from the URLs and times of the events.
+    fn flows(&self, event: &ProfiledEvent) -> (Option<&ProfiledEvent>, Option<&ProfiledEvent>) {
+        if event.category != ProfilerCategory::LayoutPerform {
+            return (None, None);
+        }
+
+        // The innermost script event of the same document running when layout started.
+        let script = event.metadata.as_ref().and_then(|metadata| {
+            self.events
+                .iter()
+                .filter(|script| {
+                    category_group(script.category) == "script" &&
+                        script.metadata.as_ref().map(|m| &m.url) == Some(&metadata.url) &&
+                        script.start_time <= event.start_time &&
+                        script.end_time >= event.start_time &&
+                        (script.pid, script.tid) != (event.pid, event.tid)
+                })
+                .max_by_key(|script| script.start_time)
+        });
+
+        // The first composite after the layout.
+        let composite = self
+            .events
+            .iter()
+            .filter(|composite| {
+                category_group(composite.category) == "compositing" &&
+                    composite.start_time >= event.end_time
+            })
+            .min_by_key(|composite| composite.start_time);
+
+        (script, composite)
+    }
+}
+
+impl TraceEvent {
+    fn metadata(name: &str, pid: u32, tid: u64, value: String) -> TraceEvent {
+        let mut args = serde_json::Map::new();
+        args.insert("name".to_owned(), Value::String(value));
+        TraceEvent {
+            name: name.to_owned(),
+            cat: None,
+            ph: "M",
+            ts: None,
+            dur: None,
+            pid: pid,
+            tid: tid,
+            id: None,
+            bp: None,
+            args: Some(Value::Object(args)),
+        }
+    }
+
+    fn flow_pair(id: usize, from: &ProfiledEvent, to: &ProfiledEvent) -> (TraceEvent, TraceEvent) {
+        (
+            TraceEvent::flow("s", id, from),
+            TraceEvent::flow("f", id, to),
+        )
+    }
+
+    fn flow(ph: &'static str, id: usize, event: &ProfiledEvent) -> TraceEvent {
+        TraceEvent {
+            name: "Reflow".to_owned(),
+            cat: Some("flow"),
+            ph: ph,
+            ts: Some(micros(event.start_time)),
+            dur: None,
+            pid: event.pid,
+            tid: event.tid,
+            id: Some(id),
+            bp: if ph == "f" { Some("e") } else { None },
+            args: None,
+        }
+    }
+}
+
+fn metadata_args(metadata: &TimerMetadata) -> Value {
+    let mut args = serde_json::Map::new();
+    args.insert("url".to_owned(), Value::String(metadata.url.clone()));
+    let iframe = metadata.iframe == TimerMetadataFrameType::IFrame;
+    args.insert("iframe".to_owned(), Value::Bool(iframe));
+    let incremental = metadata.incremental == TimerMetadataReflowType::Incremental;
+    args.insert("incremental".to_owned(), Value::Bool(incremental));
+    Value::Object(args)
+}
+
+fn micros(nanos: u64) -> f64 {
+    nanos as f64 / 1000.
+}
@@ -203,6 +203,11 @@ pub enum ProfilerMsg {
     /// Triggers printing of the memory profiling metrics.
     Print,
 
+    /// Collects the reports of all the registered reporters and sends them on the given
+    /// channel. The paths of explicit allocations start with "explicit", which includes the
+    /// unclassified parts of the heaps.
+    Report(IpcSender<Vec<Report>>),
+
     /// Tells the memory profiler to shut down.
     Exit,
 }
@@ -5,6 +5,9 @@
 use crate::energy::read_energy_uj;
 use ipc_channel::ipc::IpcSender;
 use servo_config::opts;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
 use time::precise_time_ns;
 
 #[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
@@ -14,6 +17,30 @@ pub struct TimerMetadata {
     pub incremental: TimerMetadataReflowType,
 }
 
+/// The thread that reported a time profiling event.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProfilerThread {
+    /// The id of the process of the thread.
+    pub pid: u32,
+    /// A number identifying the thread within its process.
+    pub tid: u64,
+    pub name: Option<String>,
+}
+
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local!(static CURRENT_THREAD: ProfilerThread = ProfilerThread {
+    pid: process::id(),
+    tid: NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed),
+    name: thread::current().name().map(ToOwned::to_owned),
+});
+
+impl ProfilerThread {
+    pub fn current() -> ProfilerThread {
+        CURRENT_THREAD.with(|thread| thread.clone())
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ProfilerChan(pub IpcSender<ProfilerMsg>);
 
@@ -38,6 +65,7 @@ pub enum ProfilerMsg {
         (ProfilerCategory, Option<TimerMetadata>),
         (u64, u64),
         (u64, u64),
+        ProfilerThread,
     ),
     /// Message used to get time spend entries for a particular ProfilerBuckets (in nanoseconds)
     Get(
@@ -178,5 +206,6 @@ pub fn send_profile_data(
         (category, meta),
         (start_time, end_time),
         (start_energy, end_energy),
+        ProfilerThread::current(),
     ));
 }
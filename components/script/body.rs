@@ -4,18 +4,23 @@
 
 use crate::dom::bindings::cell::Ref;
 use crate::dom::bindings::codegen::Bindings::FormDataBinding::FormDataMethods;
+use crate::dom::bindings::codegen::UnionTypes::BodyInitOrReadableStream;
 use crate::dom::bindings::error::{Error, Fallible};
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::DomRoot;
-use crate::dom::bindings::str::USVString;
+use crate::dom::bindings::str::{DOMString, USVString};
 use crate::dom::bindings::trace::RootedTraceableBox;
 use crate::dom::blob::{normalize_type_string, Blob};
 use crate::dom::formdata::FormData;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestream::ReadableStream;
+use crate::dom::xmlhttprequest::Extractable;
 use crate::realms::{AlreadyInRealm, InRealm};
 use crate::script_runtime::JSContext;
 use js::jsapi::Heap;
+use js::jsapi::JSContext as RawJSContext;
 use js::jsapi::JSObject;
 use js::jsapi::JS_ClearPendingException;
 use js::jsapi::Value as JSValue;
@@ -23,7 +28,8 @@ use js::jsval::JSVal;
 use js::jsval::UndefinedValue;
 use js::rust::wrappers::JS_GetPendingException;
 use js::rust::wrappers::JS_ParseJSON;
-use js::typedarray::{ArrayBuffer, CreateWith};
+use js::rust::HandleValue;
+use js::typedarray::{ArrayBuffer, CreateWith, Uint8Array};
 use mime::{self, Mime};
 use script_traits::serializable::BlobImpl;
 use std::ptr;
@@ -49,12 +55,42 @@ pub enum FetchedData {
     JSException(RootedTraceableBox<Heap<JSVal>>),
 }
 
+/// The body produced by <https://fetch.spec.whatwg.org/#concept-bodyinit-extract>,
+/// byte sequences are kept as such until a stream is needed for them.
+pub enum ExtractedBody {
+    Bytes(Vec<u8>),
+    Stream(DomRoot<ReadableStream>),
+}
+
+// https://fetch.spec.whatwg.org/#concept-bodyinit-extract
+pub fn extract_body(
+    body: &BodyInitOrReadableStream,
+) -> Fallible<(ExtractedBody, Option<DOMString>)> {
+    let (bytes, content_type) = match *body {
+        BodyInitOrReadableStream::ReadableStream(ref stream) => {
+            if stream.is_disturbed() || stream.is_locked() {
+                return Err(Error::Type(
+                    "The body's stream is disturbed or locked".to_string(),
+                ));
+            }
+            return Ok((ExtractedBody::Stream(stream.clone()), None));
+        },
+        BodyInitOrReadableStream::String(ref s) => s.extract(),
+        BodyInitOrReadableStream::URLSearchParams(ref usp) => usp.extract(),
+        BodyInitOrReadableStream::Blob(ref b) => b.extract(),
+        BodyInitOrReadableStream::FormData(ref formdata) => formdata.extract(),
+        BodyInitOrReadableStream::ArrayBuffer(ref typedarray) => (typedarray.to_vec(), None),
+        BodyInitOrReadableStream::ArrayBufferView(ref typedarray) => (typedarray.to_vec(), None),
+    };
+    Ok((ExtractedBody::Bytes(bytes), content_type))
+}
+
 // https://fetch.spec.whatwg.org/#concept-body-consume-body
 #[allow(unrooted_must_root)]
 pub fn consume_body<T: BodyOperations + DomObject>(object: &T, body_type: BodyType) -> Rc<Promise> {
-    let in_realm_proof = AlreadyInRealm::assert(&object.global());
-    let promise =
-        Promise::new_in_current_realm(&object.global(), InRealm::Already(&in_realm_proof));
+    let global = object.global();
+    let in_realm_proof = AlreadyInRealm::assert(&global);
+    let promise = Promise::new_in_current_realm(&global, InRealm::Already(&in_realm_proof));
 
     // Step 1
     if object.get_body_used() || object.is_locked() {
@@ -64,33 +100,84 @@ pub fn consume_body<T: BodyOperations + DomObject>(object: &T, body_type: BodyTy
         return promise;
     }
 
-    object.set_body_promise(&promise, body_type);
-
     // Steps 2-4
-    // TODO: Body does not yet have a stream.
+    let mime_type = object.get_mime_type().clone();
+    let stream = match object.body_stream() {
+        Some(stream) => stream,
+        None => {
+            resolve_with_package_data(&global, &promise, vec![], body_type, &mime_type);
+            return promise;
+        },
+    };
 
-    consume_body_with_promise(object, body_type, &promise);
+    // Step 5
+    let reader = match stream.acquire_default_reader() {
+        Ok(reader) => reader,
+        Err(error) => {
+            promise.reject_error(error);
+            return promise;
+        },
+    };
+    let handler = PromiseNativeHandler::new(
+        &global,
+        Some(Box::new(ConsumeBodyHandler {
+            promise: promise.clone(),
+            body_type,
+            mime_type: mime_type.clone(),
+            fulfilled: true,
+        })),
+        Some(Box::new(ConsumeBodyHandler {
+            promise: promise.clone(),
+            body_type,
+            mime_type,
+            fulfilled: false,
+        })),
+    );
+    reader.read_all_bytes().append_native_handler(&handler);
 
     promise
 }
 
-// https://fetch.spec.whatwg.org/#concept-body-consume-body
-#[allow(unrooted_must_root)]
-pub fn consume_body_with_promise<T: BodyOperations + DomObject>(
-    object: &T,
+/// Packages the bytes fully read from a body's stream, or rejects with the
+/// error of the stream.
+#[derive(JSTraceable, MallocSizeOf)]
+struct ConsumeBodyHandler {
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
     body_type: BodyType,
-    promise: &Promise,
-) {
-    // Step 5
-    let body = match object.take_body() {
-        Some(body) => body,
-        None => return,
-    };
+    mime_type: Vec<u8>,
+    fulfilled: bool,
+}
+
+impl Callback for ConsumeBodyHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut RawJSContext, v: HandleValue, _realm: InRealm) {
+        let cx = unsafe { JSContext::from_ptr(cx) };
+        if !self.fulfilled {
+            return self.promise.reject(cx, v);
+        }
 
-    let pkg_data_results =
-        run_package_data_algorithm(object, body, body_type, object.get_mime_type());
+        // `read_all_bytes` always resolves with a Uint8Array.
+        typedarray!(in(*cx) let array: Uint8Array = v.to_object());
+        let bytes = unsafe { array.expect("The body is not a Uint8Array").to_vec() };
+        resolve_with_package_data(
+            &self.promise.global(),
+            &self.promise,
+            bytes,
+            self.body_type,
+            &self.mime_type,
+        );
+    }
+}
 
-    match pkg_data_results {
+fn resolve_with_package_data(
+    global: &GlobalScope,
+    promise: &Promise,
+    bytes: Vec<u8>,
+    body_type: BodyType,
+    mime_type: &[u8],
+) {
+    match run_package_data_algorithm(global, bytes, body_type, mime_type) {
         Ok(results) => {
             match results {
                 FetchedData::Text(s) => promise.resolve_native(&USVString(s)),
@@ -106,21 +193,18 @@ pub fn consume_body_with_promise<T: BodyOperations + DomObject>(
 }
 
 // https://fetch.spec.whatwg.org/#concept-body-package-data
-#[allow(unsafe_code)]
-fn run_package_data_algorithm<T: BodyOperations + DomObject>(
-    object: &T,
+fn run_package_data_algorithm(
+    global: &GlobalScope,
     bytes: Vec<u8>,
     body_type: BodyType,
-    mime_type: Ref<Vec<u8>>,
+    mime: &[u8],
 ) -> Fallible<FetchedData> {
-    let global = object.global();
     let cx = global.get_cx();
-    let mime = &*mime_type;
     match body_type {
         BodyType::Text => run_text_data_algorithm(bytes),
         BodyType::Json => run_json_data_algorithm(cx, bytes),
-        BodyType::Blob => run_blob_data_algorithm(&global, bytes, mime),
-        BodyType::FormData => run_form_data_algorithm(&global, bytes, mime),
+        BodyType::Blob => run_blob_data_algorithm(global, bytes, mime),
+        BodyType::FormData => run_form_data_algorithm(global, bytes, mime),
         BodyType::ArrayBuffer => run_array_buffer_data_algorithm(cx, bytes),
     }
 }
@@ -220,10 +304,8 @@ pub fn run_array_buffer_data_algorithm(cx: JSContext, bytes: Vec<u8>) -> Fallibl
 
 pub trait BodyOperations {
     fn get_body_used(&self) -> bool;
-    fn set_body_promise(&self, p: &Rc<Promise>, body_type: BodyType);
-    /// Returns `Some(_)` if the body is complete, `None` if there is more to
-    /// come.
-    fn take_body(&self) -> Option<Vec<u8>>;
     fn is_locked(&self) -> bool;
+    /// The stream of the body, `None` for a null body.
+    fn body_stream(&self) -> Option<DomRoot<ReadableStream>>;
     fn get_mime_type(&self) -> Ref<Vec<u8>>;
 }
@@ -6,19 +6,22 @@ use crate::dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding::CSSStyl
 use crate::dom::bindings::codegen::Bindings::DOMRectBinding::DOMRectMethods;
 use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use crate::dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
+use crate::dom::bindings::codegen::Bindings::StyleSheetBinding::StyleSheetMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::conversions::{jsstring_to_str, ConversionResult, FromJSValConvertible};
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
+use crate::dom::cssstylesheet::CSSStyleSheet;
 use crate::dom::document::AnimationFrameCallback;
 use crate::dom::element::Element;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::node::{window_from_node, Node, ShadowIncluding};
+use crate::dom::stylesheet::StyleSheet;
 use crate::realms::enter_realm;
 use crate::script_thread::Documents;
 use devtools_traits::{AutoMargins, ComputedNodeLayout, TimelineMarkerType};
-use devtools_traits::{EvaluateJSReply, Modification, NodeInfo, TimelineMarker};
+use devtools_traits::{EvaluateJSReply, Modification, NodeInfo, StyleSheetInfo, TimelineMarker};
 use ipc_channel::ipc::IpcSender;
 use js::jsval::UndefinedValue;
 use js::rust::wrappers::ObjectClassName;
@@ -249,3 +252,58 @@ pub fn handle_reload(documents: &Documents, id: PipelineId) {
         win.Location().reload_without_origin_check();
     }
 }
+
+/// The stylesheets of the document of the given pipeline, in tree order.
+fn document_stylesheets(
+    documents: &Documents,
+    pipeline: PipelineId,
+) -> Vec<DomRoot<CSSStyleSheet>> {
+    documents
+        .find_document(pipeline)
+        .map_or(vec![], |document| {
+            (0..document.stylesheet_count())
+                .filter_map(|index| document.stylesheet_at(index))
+                .collect()
+        })
+}
+
+pub fn handle_get_stylesheets(
+    documents: &Documents,
+    pipeline: PipelineId,
+    reply: IpcSender<Vec<StyleSheetInfo>>,
+) {
+    let stylesheets = document_stylesheets(documents, pipeline)
+        .iter()
+        .map(|sheet| {
+            let rule_count = {
+                let guard = sheet.shared_lock().read();
+                let rules = sheet.style_stylesheet().contents.rules.read_with(&guard);
+                rules.0.len() as u32
+            };
+            let stylesheet = sheet.upcast::<StyleSheet>();
+            StyleSheetInfo {
+                href: stylesheet.GetHref().map(String::from),
+                title: stylesheet.GetTitle().map(String::from),
+                disabled: sheet.disabled(),
+                rule_count: rule_count,
+                text: sheet.text(),
+            }
+        })
+        .collect();
+    reply.send(stylesheets).unwrap();
+}
+
+pub fn handle_update_stylesheet(
+    documents: &Documents,
+    pipeline: PipelineId,
+    index: usize,
+    text: String,
+) {
+    match document_stylesheets(documents, pipeline).get(index) {
+        Some(sheet) => sheet.replace_contents(&text),
+        None => warn!(
+            "stylesheet {} for pipeline id {} is not found",
+            index, pipeline
+        ),
+    }
+}
@@ -0,0 +1,245 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::body::BodyOperations;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::AbortSignalBinding::AbortSignalMethods;
+use crate::dom::bindings::codegen::Bindings::EventListenerBinding::EventListener;
+use crate::dom::bindings::codegen::Bindings::EventTargetBinding::EventListenerOptions;
+use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::readablestream::ReadableStreamState;
+use crate::dom::response::Response;
+use crate::fetch::FetchCanceller;
+use crate::realms::enter_realm;
+use crate::script_runtime::JSContext;
+use crate::streams::{error_to_jsval, is_pending, rethrow};
+use dom_struct::dom_struct;
+use js::jsapi::Heap;
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://dom.spec.whatwg.org/#abortsignal-abort-algorithms>
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub enum AbortAlgorithm {
+    /// Removes an event listener that was added with the signal.
+    RemoveEventListener {
+        target: Dom<EventTarget>,
+        ty: DOMString,
+        #[ignore_malloc_size_of = "Rc"]
+        listener: Rc<EventListener>,
+        capture: bool,
+    },
+    /// Aborts a signal that follows this one.
+    Follow(Dom<AbortSignal>),
+    /// <https://fetch.spec.whatwg.org/#abort-fetch>
+    Fetch {
+        #[ignore_malloc_size_of = "Rc"]
+        promise: Rc<Promise>,
+        response: Dom<Response>,
+        canceller: DomRefCell<FetchCanceller>,
+    },
+}
+
+impl AbortAlgorithm {
+    fn run(&self, signal: &AbortSignal) {
+        let global = signal.global();
+        rooted!(in(*global.get_cx()) let mut reason = UndefinedValue());
+        signal.reason(reason.handle_mut());
+        match *self {
+            AbortAlgorithm::RemoveEventListener {
+                ref target,
+                ref ty,
+                ref listener,
+                capture,
+            } => {
+                target.remove_event_listener(
+                    ty.clone(),
+                    Some(listener.clone()),
+                    EventListenerOptions { capture },
+                );
+            },
+            AbortAlgorithm::Follow(ref follower) => follower.signal_abort(reason.handle()),
+            AbortAlgorithm::Fetch {
+                ref promise,
+                ref response,
+                ref canceller,
+            } => {
+                // Step 1.
+                if is_pending(promise) {
+                    let _ac = enter_realm(&**promise);
+                    promise.reject(global.get_cx(), reason.handle());
+                }
+
+                // Step 4.
+                if let Some(stream) = response.body_stream() {
+                    if stream.state() == ReadableStreamState::Readable {
+                        stream.error(reason.handle());
+                    }
+                }
+
+                // Terminate the ongoing fetch.
+                canceller.borrow_mut().cancel();
+            },
+        }
+    }
+}
+
+#[dom_struct]
+pub struct AbortSignal {
+    eventtarget: EventTarget,
+    /// <https://dom.spec.whatwg.org/#abortsignal-abort-reason>
+    #[ignore_malloc_size_of = "mozjs"]
+    reason: Heap<JSVal>,
+    abort_algorithms: DomRefCell<Vec<AbortAlgorithm>>,
+}
+
+impl AbortSignal {
+    fn new_inherited() -> AbortSignal {
+        AbortSignal {
+            eventtarget: EventTarget::new_inherited(),
+            reason: Heap::default(),
+            abort_algorithms: DomRefCell::new(vec![]),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<AbortSignal> {
+        reflect_dom_object(Box::new(AbortSignal::new_inherited()), global)
+    }
+
+    /// <https://dom.spec.whatwg.org/#dom-abortsignal-abort>
+    #[allow(non_snake_case)]
+    pub fn Abort(
+        _cx: JSContext,
+        global: &GlobalScope,
+        reason: HandleValue,
+    ) -> DomRoot<AbortSignal> {
+        let signal = AbortSignal::new(global);
+        signal.signal_abort(reason);
+        signal
+    }
+
+    /// <https://dom.spec.whatwg.org/#abortsignal-aborted>
+    pub fn aborted(&self) -> bool {
+        !self.reason.get().is_undefined()
+    }
+
+    pub fn reason(&self, mut rval: MutableHandleValue) {
+        rval.set(self.reason.get());
+    }
+
+    /// <https://dom.spec.whatwg.org/#abortsignal-add>
+    #[allow(unrooted_must_root)]
+    pub fn add_abort_algorithm(&self, algorithm: AbortAlgorithm) {
+        if !self.aborted() {
+            self.abort_algorithms.borrow_mut().push(algorithm);
+        }
+    }
+
+    /// Removes the algorithm aborting the fetch of `response`, once that fetch is over.
+    #[allow(unrooted_must_root)]
+    pub fn remove_fetch_algorithm(&self, response: &Response) {
+        self.abort_algorithms
+            .borrow_mut()
+            .retain(|algorithm| match *algorithm {
+                AbortAlgorithm::Fetch {
+                    response: ref fetched,
+                    ref canceller,
+                    ..
+                } if ptr::eq(&**fetched, response) => {
+                    // There is nothing left to cancel.
+                    canceller.borrow_mut().ignore();
+                    false
+                },
+                _ => true,
+            });
+    }
+
+    /// Makes this signal abort whenever `parent` does.
+    ///
+    /// <https://dom.spec.whatwg.org/#abortsignal-follow>
+    #[allow(unrooted_must_root)]
+    pub fn follow(&self, parent: &AbortSignal) {
+        // Step 1.
+        if self.aborted() {
+            return;
+        }
+
+        // Step 2.
+        if parent.aborted() {
+            let cx = self.global().get_cx();
+            rooted!(in(*cx) let mut reason = UndefinedValue());
+            parent.reason(reason.handle_mut());
+            return self.signal_abort(reason.handle());
+        }
+
+        // Step 3.
+        parent.add_abort_algorithm(AbortAlgorithm::Follow(Dom::from_ref(self)));
+    }
+
+    /// <https://dom.spec.whatwg.org/#abortsignal-signal-abort>
+    #[allow(unrooted_must_root)]
+    pub fn signal_abort(&self, reason: HandleValue) {
+        // Step 1.
+        if self.aborted() {
+            return;
+        }
+
+        // Step 2.
+        let global = self.global();
+        let _ac = enter_realm(self);
+        if reason.is_undefined() {
+            rooted!(in(*global.get_cx()) let mut error = UndefinedValue());
+            error_to_jsval(&global, Error::Abort, error.handle_mut());
+            self.reason.set(error.get());
+        } else {
+            self.reason.set(reason.get());
+        }
+
+        // Steps 3-4.
+        let algorithms = mem::take(&mut *self.abort_algorithms.borrow_mut());
+        for algorithm in algorithms.iter() {
+            algorithm.run(self);
+        }
+
+        // Step 5.
+        self.upcast::<EventTarget>().fire_event(atom!("abort"));
+    }
+}
+
+impl AbortSignalMethods for AbortSignal {
+    // https://dom.spec.whatwg.org/#dom-abortsignal-aborted
+    fn Aborted(&self) -> bool {
+        self.aborted()
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortsignal-reason
+    fn Reason(&self, _cx: JSContext) -> JSVal {
+        self.reason.get()
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortsignal-throwifaborted
+    fn ThrowIfAborted(&self) -> ErrorResult {
+        if !self.aborted() {
+            return Ok(());
+        }
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let mut reason = UndefinedValue());
+        self.reason(reason.handle_mut());
+        Err(rethrow(cx, reason.handle()))
+    }
+
+    // https://dom.spec.whatwg.org/#dom-abortsignal-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+}
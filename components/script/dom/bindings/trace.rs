@@ -90,6 +90,9 @@ use msg::constellation_msg::{ServiceWorkerId, ServiceWorkerRegistrationId};
 use net_traits::filemanager_thread::RelativePos;
 use net_traits::image::base::{Image, ImageMetadata};
 use net_traits::image_cache::{ImageCache, PendingImageId};
+use net_traits::indexeddb_thread::ObjectStoreInfo;
+use net_traits::indexeddb_thread::{DatabaseInfo, IndexedDBCursorDirection, IndexedDBKeyRange};
+use net_traits::indexeddb_thread::{IndexInfo, IndexedDBKeyType, IndexedDBTxnMode, KeyPath};
 use net_traits::request::{Referrer, Request, RequestBuilder};
 use net_traits::response::HttpsState;
 use net_traits::response::{Response, ResponseBody};
@@ -496,6 +499,9 @@ unsafe_no_jsmanaged_fields!(UntrustedNodeAddress, OpaqueNode);
 unsafe_no_jsmanaged_fields!(LengthOrPercentageOrAuto);
 unsafe_no_jsmanaged_fields!(RGBA);
 unsafe_no_jsmanaged_fields!(StorageType);
+unsafe_no_jsmanaged_fields!(DatabaseInfo, IndexedDBCursorDirection, IndexedDBKeyRange);
+unsafe_no_jsmanaged_fields!(IndexInfo, IndexedDBKeyType, IndexedDBTxnMode, KeyPath);
+unsafe_no_jsmanaged_fields!(ObjectStoreInfo);
 unsafe_no_jsmanaged_fields!(CanvasGradientStop, LinearGradientStyle, RadialGradientStyle);
 unsafe_no_jsmanaged_fields!(LineCapStyle, LineJoinStyle, CompositionOrBlending);
 unsafe_no_jsmanaged_fields!(RepetitionStyle);
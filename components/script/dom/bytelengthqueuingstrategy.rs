@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::ByteLengthQueuingStrategyBinding::ByteLengthQueuingStrategyMethods;
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategyInit;
+use crate::dom::bindings::conversions::get_property_jsval;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::globalscope::GlobalScope;
+use dom_struct::dom_struct;
+use js::error::throw_type_error;
+use js::jsapi::{CallArgs, JSContext, JS_GetFunctionObject, JS_NewFunction};
+use js::jsval::{JSVal, UndefinedValue};
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct ByteLengthQueuingStrategy {
+    reflector_: Reflector,
+    high_water_mark: f64,
+}
+
+impl ByteLengthQueuingStrategy {
+    fn new_inherited(high_water_mark: f64) -> ByteLengthQueuingStrategy {
+        ByteLengthQueuingStrategy {
+            reflector_: Reflector::new(),
+            high_water_mark,
+        }
+    }
+
+    pub fn new(global: &GlobalScope, high_water_mark: f64) -> DomRoot<ByteLengthQueuingStrategy> {
+        reflect_dom_object(
+            Box::new(ByteLengthQueuingStrategy::new_inherited(high_water_mark)),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#blqs-constructor>
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        init: &QueuingStrategyInit,
+    ) -> DomRoot<ByteLengthQueuingStrategy> {
+        ByteLengthQueuingStrategy::new(global, init.highWaterMark)
+    }
+}
+
+impl ByteLengthQueuingStrategyMethods for ByteLengthQueuingStrategy {
+    // https://streams.spec.whatwg.org/#blqs-high-water-mark
+    fn HighWaterMark(&self) -> f64 {
+        self.high_water_mark
+    }
+
+    // https://streams.spec.whatwg.org/#blqs-size
+    #[allow(unsafe_code)]
+    fn Size(&self) -> Rc<Function> {
+        let global = self.global();
+        if let Some(function) = global.get_byte_length_queuing_strategy_size() {
+            return function;
+        }
+
+        // https://streams.spec.whatwg.org/#byte-length-queuing-strategy-size-function
+        let cx = global.get_cx();
+        let function = unsafe {
+            let size = JS_NewFunction(
+                *cx,
+                Some(byte_length_queuing_strategy_size),
+                /* nargs = */ 1,
+                /* flags = */ 0,
+                b"size\0".as_ptr() as *const _,
+            );
+            assert!(!size.is_null());
+            rooted!(in(*cx) let size = JS_GetFunctionObject(size));
+            Function::new(cx, size.get())
+        };
+        global.set_byte_length_queuing_strategy_size(function.clone());
+        function
+    }
+}
+
+/// Returns the `byteLength` property of the chunk.
+#[allow(unsafe_code)]
+unsafe extern "C" fn byte_length_queuing_strategy_size(
+    cx: *mut JSContext,
+    argc: u32,
+    vp: *mut JSVal,
+) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let chunk = args.get(0);
+    if chunk.is_null_or_undefined() {
+        throw_type_error(cx, "The chunk has no byteLength");
+        return false;
+    }
+    rooted!(in(cx) let mut byte_length = UndefinedValue());
+    if chunk.is_object() {
+        rooted!(in(cx) let chunk = chunk.to_object());
+        if get_property_jsval(cx, chunk.handle(), "byteLength", byte_length.handle_mut()).is_err() {
+            return false;
+        }
+    }
+    *args.rval() = byte_length.get();
+    true
+}
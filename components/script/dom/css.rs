@@ -2,8 +2,9 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::codegen::Bindings::CSSBinding::PropertyDefinition;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
-use crate::dom::bindings::error::Fallible;
+use crate::dom::bindings::error::{Error, Fallible};
 use crate::dom::bindings::reflector::Reflector;
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::DOMString;
@@ -11,8 +12,12 @@ use crate::dom::window::Window;
 use crate::dom::worklet::Worklet;
 use cssparser::{serialize_identifier, Parser, ParserInput};
 use dom_struct::dom_struct;
+use servo_atoms::Atom;
 use style::context::QuirksMode;
+use style::custom_properties::{self, VariableValue};
 use style::parser::ParserContext;
+use style::properties_and_values::registry::PropertyRegistration;
+use style::properties_and_values::syntax::Syntax;
 use style::stylesheets::supports_rule::{parse_condition_or_declaration, Declaration};
 use style::stylesheets::CssRuleType;
 use style_traits::ParsingMode;
@@ -71,6 +76,43 @@ impl CSS {
         cond.eval(&context, &Default::default())
     }
 
+    /// <https://drafts.css-houdini.org/css-properties-values-api-1/#the-registerproperty-function>
+    pub fn RegisterProperty(win: &Window, definition: &PropertyDefinition) -> Fallible<()> {
+        let name = custom_properties::parse_name(&definition.name).map_err(|()| Error::Syntax)?;
+        let syntax = definition
+            .syntax
+            .parse::<Syntax>()
+            .map_err(|()| Error::Syntax)?;
+        let initial_value = match definition.initialValue {
+            Some(ref initial_value) => {
+                let mut input = ParserInput::new(initial_value);
+                let mut input = Parser::new(&mut input);
+                let value = input
+                    .parse_entirely(|input| VariableValue::parse(input))
+                    .map_err(|_| Error::Syntax)?;
+                Some(value)
+            },
+            None => None,
+        };
+
+        let document = win.Document();
+        let registration = PropertyRegistration::new(
+            Atom::from(name),
+            syntax,
+            definition.inherits,
+            initial_value,
+            document.url(),
+            &document.device(),
+            document.quirks_mode(),
+        )
+        .map_err(|_| Error::Syntax)?;
+
+        if !document.register_custom_property(registration) {
+            return Err(Error::InvalidModification);
+        }
+        Ok(())
+    }
+
     /// <https://drafts.css-houdini.org/css-paint-api-1/#paint-worklet>
     pub fn PaintWorklet(win: &Window) -> DomRoot<Worklet> {
         win.paint_worklet()
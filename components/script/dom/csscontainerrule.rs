@@ -0,0 +1,102 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowBinding::WindowMethods;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::cssconditionrule::CSSConditionRule;
+use crate::dom::cssrule::SpecificCSSRule;
+use crate::dom::cssstylesheet::CSSStyleSheet;
+use crate::dom::window::Window;
+use cssparser::{Parser, ParserInput};
+use dom_struct::dom_struct;
+use servo_arc::Arc;
+use style::parser::ParserContext;
+use style::shared_lock::{Locked, ToCssWithGuard};
+use style::stylesheets::container_rule::ContainerCondition;
+use style::stylesheets::{ContainerRule, CssRuleType};
+use style_traits::{ParsingMode, ToCss};
+
+#[dom_struct]
+pub struct CSSContainerRule {
+    cssconditionrule: CSSConditionRule,
+    #[ignore_malloc_size_of = "Arc"]
+    containerrule: Arc<Locked<ContainerRule>>,
+}
+
+impl CSSContainerRule {
+    fn new_inherited(
+        parent_stylesheet: &CSSStyleSheet,
+        containerrule: Arc<Locked<ContainerRule>>,
+    ) -> CSSContainerRule {
+        let guard = parent_stylesheet.shared_lock().read();
+        let list = containerrule.read_with(&guard).rules.clone();
+        CSSContainerRule {
+            cssconditionrule: CSSConditionRule::new_inherited(parent_stylesheet, list),
+            containerrule: containerrule,
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        window: &Window,
+        parent_stylesheet: &CSSStyleSheet,
+        containerrule: Arc<Locked<ContainerRule>>,
+    ) -> DomRoot<CSSContainerRule> {
+        reflect_dom_object(
+            Box::new(CSSContainerRule::new_inherited(
+                parent_stylesheet,
+                containerrule,
+            )),
+            window,
+        )
+    }
+
+    /// <https://drafts.csswg.org/css-contain-3/#the-csscontainerrule-interface>
+    pub fn get_condition_text(&self) -> DOMString {
+        let guard = self.cssconditionrule.shared_lock().read();
+        let rule = self.containerrule.read_with(&guard);
+        rule.condition.to_css_string().into()
+    }
+
+    /// <https://drafts.csswg.org/css-contain-3/#the-csscontainerrule-interface>
+    pub fn set_condition_text(&self, text: DOMString) {
+        let mut input = ParserInput::new(&text);
+        let mut input = Parser::new(&mut input);
+        let global = self.global();
+        let win = global.as_window();
+        let url = win.Document().url();
+        let quirks_mode = win.Document().quirks_mode();
+        let context = ParserContext::new_for_cssom(
+            &url,
+            Some(CssRuleType::Container),
+            ParsingMode::DEFAULT,
+            quirks_mode,
+            None,
+            None,
+        );
+        let cond = input.parse_entirely(|input| ContainerCondition::parse(&context, input));
+        if let Ok(cond) = cond {
+            let mut guard = self.cssconditionrule.shared_lock().write();
+            let rule = self.containerrule.write_with(&mut guard);
+            rule.condition = Arc::new(cond);
+        }
+    }
+}
+
+impl SpecificCSSRule for CSSContainerRule {
+    fn ty(&self) -> u16 {
+        // Rules newer than the CSSOM ones don't get a type constant.
+        0
+    }
+
+    fn get_css(&self) -> DOMString {
+        let guard = self.cssconditionrule.shared_lock().read();
+        self.containerrule
+            .read_with(&guard)
+            .to_css_string(&guard)
+            .into()
+    }
+}
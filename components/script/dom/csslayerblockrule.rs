@@ -0,0 +1,85 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CSSLayerBlockRuleBinding::CSSLayerBlockRuleMethods;
+use crate::dom::bindings::reflector::reflect_dom_object;
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::cssgroupingrule::CSSGroupingRule;
+use crate::dom::cssrule::SpecificCSSRule;
+use crate::dom::cssstylesheet::CSSStyleSheet;
+use crate::dom::window::Window;
+use dom_struct::dom_struct;
+use servo_arc::Arc;
+use style::shared_lock::{Locked, ToCssWithGuard};
+use style::stylesheets::{LayerRule, LayerRuleKind};
+use style_traits::ToCss;
+
+#[dom_struct]
+pub struct CSSLayerBlockRule {
+    cssgroupingrule: CSSGroupingRule,
+    #[ignore_malloc_size_of = "Arc"]
+    layerrule: Arc<Locked<LayerRule>>,
+}
+
+impl CSSLayerBlockRule {
+    fn new_inherited(
+        parent_stylesheet: &CSSStyleSheet,
+        layerrule: Arc<Locked<LayerRule>>,
+    ) -> CSSLayerBlockRule {
+        let guard = parent_stylesheet.shared_lock().read();
+        let list = match layerrule.read_with(&guard).kind {
+            LayerRuleKind::Block { ref rules, .. } => rules.clone(),
+            LayerRuleKind::Statement { .. } => unreachable!("Not a block layer rule"),
+        };
+        CSSLayerBlockRule {
+            cssgroupingrule: CSSGroupingRule::new_inherited(parent_stylesheet, list),
+            layerrule: layerrule,
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        window: &Window,
+        parent_stylesheet: &CSSStyleSheet,
+        layerrule: Arc<Locked<LayerRule>>,
+    ) -> DomRoot<CSSLayerBlockRule> {
+        reflect_dom_object(
+            Box::new(CSSLayerBlockRule::new_inherited(
+                parent_stylesheet,
+                layerrule,
+            )),
+            window,
+        )
+    }
+}
+
+impl SpecificCSSRule for CSSLayerBlockRule {
+    fn ty(&self) -> u16 {
+        // Rules newer than the CSSOM ones don't get a type constant.
+        0
+    }
+
+    fn get_css(&self) -> DOMString {
+        let guard = self.cssgroupingrule.shared_lock().read();
+        self.layerrule
+            .read_with(&guard)
+            .to_css_string(&guard)
+            .into()
+    }
+}
+
+impl CSSLayerBlockRuleMethods for CSSLayerBlockRule {
+    // https://drafts.csswg.org/css-cascade-5/#dom-csslayerblockrule-name
+    fn Name(&self) -> DOMString {
+        let guard = self.cssgroupingrule.shared_lock().read();
+        match self.layerrule.read_with(&guard).kind {
+            LayerRuleKind::Block {
+                name: Some(ref name),
+                ..
+            } => name.to_css_string().into(),
+            _ => DOMString::new(),
+        }
+    }
+}
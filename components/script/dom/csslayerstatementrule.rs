@@ -0,0 +1,83 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::CSSLayerStatementRuleBinding::CSSLayerStatementRuleMethods;
+use crate::dom::bindings::reflector::reflect_dom_object;
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::utils::to_frozen_array;
+use crate::dom::cssrule::{CSSRule, SpecificCSSRule};
+use crate::dom::cssstylesheet::CSSStyleSheet;
+use crate::dom::window::Window;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsval::JSVal;
+use servo_arc::Arc;
+use style::shared_lock::{Locked, ToCssWithGuard};
+use style::stylesheets::{LayerRule, LayerRuleKind};
+use style_traits::ToCss;
+
+#[dom_struct]
+pub struct CSSLayerStatementRule {
+    cssrule: CSSRule,
+    #[ignore_malloc_size_of = "Arc"]
+    layerrule: Arc<Locked<LayerRule>>,
+}
+
+impl CSSLayerStatementRule {
+    fn new_inherited(
+        parent_stylesheet: &CSSStyleSheet,
+        layerrule: Arc<Locked<LayerRule>>,
+    ) -> CSSLayerStatementRule {
+        CSSLayerStatementRule {
+            cssrule: CSSRule::new_inherited(parent_stylesheet),
+            layerrule: layerrule,
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        window: &Window,
+        parent_stylesheet: &CSSStyleSheet,
+        layerrule: Arc<Locked<LayerRule>>,
+    ) -> DomRoot<CSSLayerStatementRule> {
+        reflect_dom_object(
+            Box::new(CSSLayerStatementRule::new_inherited(
+                parent_stylesheet,
+                layerrule,
+            )),
+            window,
+        )
+    }
+}
+
+impl SpecificCSSRule for CSSLayerStatementRule {
+    fn ty(&self) -> u16 {
+        // Rules newer than the CSSOM ones don't get a type constant.
+        0
+    }
+
+    fn get_css(&self) -> DOMString {
+        let guard = self.cssrule.shared_lock().read();
+        self.layerrule
+            .read_with(&guard)
+            .to_css_string(&guard)
+            .into()
+    }
+}
+
+impl CSSLayerStatementRuleMethods for CSSLayerStatementRule {
+    // https://drafts.csswg.org/css-cascade-5/#dom-csslayerstatementrule-namelist
+    fn NameList(&self, cx: JSContext) -> JSVal {
+        let guard = self.cssrule.shared_lock().read();
+        let names: Vec<DOMString> = match self.layerrule.read_with(&guard).kind {
+            LayerRuleKind::Statement { ref names } => names
+                .iter()
+                .map(|name| name.to_css_string().into())
+                .collect(),
+            LayerRuleKind::Block { .. } => vec![],
+        };
+        to_frozen_array(names.as_slice(), cx)
+    }
+}
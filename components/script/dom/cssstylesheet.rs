@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::CSSStyleSheetBinding::CSSStyleSheetMethods;
 use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
 use crate::dom::bindings::inheritance::Castable;
@@ -10,14 +11,17 @@ use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::cssrulelist::{CSSRuleList, RulesSource};
 use crate::dom::element::Element;
-use crate::dom::node::{stylesheets_owner_from_node, Node};
+use crate::dom::htmlelement::HTMLElement;
+use crate::dom::node::{stylesheets_owner_from_node, window_from_node, Node};
 use crate::dom::stylesheet::StyleSheet;
 use crate::dom::window::Window;
+use crate::stylesheet_loader::StylesheetLoader;
 use dom_struct::dom_struct;
 use servo_arc::Arc;
 use std::cell::Cell;
-use style::shared_lock::SharedRwLock;
-use style::stylesheets::Stylesheet as StyleStyleSheet;
+use style::shared_lock::{SharedRwLock, ToCssWithGuard};
+use style::stylesheets::StylesheetLoader as StyleStylesheetLoader;
+use style::stylesheets::{AllowImportRules, Stylesheet as StyleStyleSheet};
 
 #[dom_struct]
 pub struct CSSStyleSheet {
@@ -26,6 +30,8 @@ pub struct CSSStyleSheet {
     rulelist: MutNullableDom<CSSRuleList>,
     #[ignore_malloc_size_of = "Arc"]
     style_stylesheet: Arc<StyleStyleSheet>,
+    /// The text the rules were parsed from, forgotten once rules are inserted or deleted.
+    source: DomRefCell<Option<String>>,
     origin_clean: Cell<bool>,
 }
 
@@ -36,12 +42,14 @@ impl CSSStyleSheet {
         href: Option<DOMString>,
         title: Option<DOMString>,
         stylesheet: Arc<StyleStyleSheet>,
+        source: Option<String>,
     ) -> CSSStyleSheet {
         CSSStyleSheet {
             stylesheet: StyleSheet::new_inherited(type_, href, title),
             owner: Dom::from_ref(owner),
             rulelist: MutNullableDom::new(None),
             style_stylesheet: stylesheet,
+            source: DomRefCell::new(source),
             origin_clean: Cell::new(true),
         }
     }
@@ -54,10 +62,11 @@ impl CSSStyleSheet {
         href: Option<DOMString>,
         title: Option<DOMString>,
         stylesheet: Arc<StyleStyleSheet>,
+        source: Option<String>,
     ) -> DomRoot<CSSStyleSheet> {
         reflect_dom_object(
             Box::new(CSSStyleSheet::new_inherited(
-                owner, type_, href, title, stylesheet,
+                owner, type_, href, title, stylesheet, source,
             )),
             window,
         )
@@ -99,6 +108,52 @@ impl CSSStyleSheet {
     pub fn set_origin_clean(&self, origin_clean: bool) {
         self.origin_clean.set(origin_clean);
     }
+
+    /// The source of this stylesheet, or its rules serialized one per line if it is
+    /// unknown or out of date.
+    pub fn text(&self) -> String {
+        if let Some(ref source) = *self.source.borrow() {
+            return source.clone();
+        }
+        let guard = self.shared_lock().read();
+        let rules = self.style_stylesheet.contents.rules.read_with(&guard);
+        rules
+            .0
+            .iter()
+            .map(|rule| rule.to_css_string(&guard))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Replace the rules of this stylesheet by those parsed from `css`, and restyle
+    /// the document or shadow root it applies to.
+    pub fn replace_contents(&self, css: &str) {
+        let window = window_from_node(self.get_owner());
+        let loader = self
+            .owner
+            .downcast::<HTMLElement>()
+            .map(StylesheetLoader::for_element);
+        let url_data = self.style_stylesheet.contents.url_data.read().clone();
+        StyleStyleSheet::update_from_str(
+            &self.style_stylesheet,
+            css,
+            url_data,
+            loader
+                .as_ref()
+                .map(|loader| loader as &dyn StyleStylesheetLoader),
+            window.css_error_reporter(),
+            0,
+            AllowImportRules::Yes,
+        );
+
+        // The rules of the previous contents are gone for good.
+        if let Some(rulelist) = self.rulelist.get() {
+            rulelist.deparent_all();
+        }
+        self.rulelist.set(None);
+        *self.source.borrow_mut() = Some(css.to_owned());
+        stylesheets_owner_from_node(self.owner().upcast::<Node>()).invalidate_stylesheets();
+    }
 }
 
 impl CSSStyleSheetMethods for CSSStyleSheet {
@@ -115,8 +170,11 @@ impl CSSStyleSheetMethods for CSSStyleSheet {
         if !self.origin_clean.get() {
             return Err(Error::Security);
         }
-        self.rulelist()
-            .insert_rule(&rule, index, /* nested */ false)
+        let index = self
+            .rulelist()
+            .insert_rule(&rule, index, /* nested */ false)?;
+        *self.source.borrow_mut() = None;
+        Ok(index)
     }
 
     // https://drafts.csswg.org/cssom/#dom-cssstylesheet-deleterule
@@ -124,6 +182,8 @@ impl CSSStyleSheetMethods for CSSStyleSheet {
         if !self.origin_clean.get() {
             return Err(Error::Security);
         }
-        self.rulelist().remove_rule(index)
+        self.rulelist().remove_rule(index)?;
+        *self.source.borrow_mut() = None;
+        Ok(())
     }
 }
@@ -0,0 +1,406 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::DebuggerGlobalScopeBinding;
+use crate::dom::bindings::codegen::Bindings::DebuggerGlobalScopeBinding::DebuggerFrame;
+use crate::dom::bindings::codegen::Bindings::DebuggerGlobalScopeBinding::DebuggerGlobalScopeMethods;
+use crate::dom::bindings::codegen::Bindings::DebuggerGlobalScopeBinding::DebuggerPause;
+use crate::dom::bindings::codegen::Bindings::DebuggerGlobalScopeBinding::DebuggerSample;
+use crate::dom::bindings::codegen::Bindings::DebuggerGlobalScopeBinding::DebuggerSource;
+use crate::dom::bindings::codegen::Bindings::DebuggerGlobalScopeBinding::DebuggerValue;
+use crate::dom::bindings::conversions::{ConversionResult, ToJSValConvertible};
+use crate::dom::bindings::error::report_pending_exception;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::workerglobalscope::WorkerGlobalScope;
+use crate::realms::{enter_realm, AlreadyInRealm, InRealm};
+use crate::script_thread::ScriptThread;
+use crossbeam_channel::Receiver;
+use devtools_traits::{DebuggerCommand, DevtoolScriptControlMsg, EnvironmentInfo};
+use devtools_traits::{EvaluateJSReply, FrameInfo, JSSample, PauseInfo, PauseReason};
+use devtools_traits::{ResumeLimit, SampledFrameInfo, ScriptToDevtoolsControlMsg, SourceInfo};
+use dom_struct::dom_struct;
+use embedder_traits::resources::{self, Resource};
+use ipc_channel::ipc::IpcSender;
+use js::jsapi::{HandleValueArray, JS_DefineDebuggerObject};
+use js::jsval::{JSVal, UInt32Value, UndefinedValue};
+use js::rust::wrappers::JS_CallFunctionName;
+use js::rust::MutableHandleValue;
+use msg::constellation_msg::PipelineId;
+use servo_url::{ImmutableOrigin, MutableOrigin};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ffi::CString;
+use uuid::Uuid;
+
+/// A global hosting the JS debugger of a script thread. It is never exposed to
+/// content: the globals of the thread are added to it as debuggees, and it runs
+/// `resources/debugger.js` to drive SpiderMonkey's Debugger API on behalf of the
+/// devtools server.
+#[dom_struct]
+pub struct DebuggerGlobalScope {
+    global_scope: GlobalScope,
+    /// The globals being debugged, keyed by the id the debugger script knows them by.
+    debuggees: DomRefCell<HashMap<u32, Dom<GlobalScope>>>,
+    next_debuggee_id: Cell<u32>,
+    /// The devtools messages of this thread, which are read from directly while
+    /// a debuggee is paused.
+    #[ignore_malloc_size_of = "Defined in std"]
+    devtools_receiver: Receiver<DevtoolScriptControlMsg>,
+    /// Where to send the JS stacks sampled from debuggees, keyed by debuggee id.
+    #[ignore_malloc_size_of = "channels are hard"]
+    sample_chans: DomRefCell<HashMap<u32, IpcSender<JSSample>>>,
+}
+
+impl DebuggerGlobalScope {
+    /// Create the debugger global of the thread running `global`, sharing its
+    /// runtime and channels.
+    #[allow(unsafe_code)]
+    pub fn new(
+        global: &GlobalScope,
+        devtools_receiver: Receiver<DevtoolScriptControlMsg>,
+    ) -> DomRoot<DebuggerGlobalScope> {
+        debug!(
+            "Creating debugger global scope for pipeline {}.",
+            global.pipeline_id()
+        );
+        let scope = Box::new(DebuggerGlobalScope {
+            global_scope: GlobalScope::new_inherited(
+                global.pipeline_id(),
+                global.devtools_chan().cloned(),
+                global.mem_profiler_chan().clone(),
+                global.time_profiler_chan().clone(),
+                global.script_to_constellation_chan().clone(),
+                global.scheduler_chan().clone(),
+                global.resource_threads().clone(),
+                MutableOrigin::new(ImmutableOrigin::new_opaque()),
+                global.microtask_queue().clone(),
+                global.is_headless(),
+                global.get_user_agent(),
+                global.wgpu_id_hub(),
+            ),
+            debuggees: Default::default(),
+            next_debuggee_id: Cell::new(0),
+            devtools_receiver,
+            sample_chans: Default::default(),
+        });
+        let scope = unsafe { DebuggerGlobalScopeBinding::Wrap(global.get_cx(), scope) };
+
+        let cx = global.get_cx();
+        {
+            let _ac = enter_realm(&*scope);
+            rooted!(in(*cx) let object = scope.reflector().get_jsobject().get());
+            assert!(unsafe { JS_DefineDebuggerObject(*cx, object.handle().into()) });
+        }
+        rooted!(in(*cx) let mut rval = UndefinedValue());
+        scope
+            .upcast::<GlobalScope>()
+            .evaluate_script_on_global_with_result(
+                &resources::read_string(Resource::DebuggerJS),
+                "resource:///debugger.js",
+                rval.handle_mut(),
+                1,
+            );
+        scope
+    }
+
+    #[allow(unsafe_code)]
+    pub fn add_debuggee(&self, debuggee: &GlobalScope) {
+        let id = self.next_debuggee_id.get();
+        self.next_debuggee_id.set(id + 1);
+        self.debuggees
+            .borrow_mut()
+            .insert(id, Dom::from_ref(debuggee));
+
+        let cx = self.upcast::<GlobalScope>().get_cx();
+        let _ac = enter_realm(self);
+        rooted!(in(*cx) let mut global = UndefinedValue());
+        unsafe { debuggee.reflector().to_jsval(*cx, global.handle_mut()) };
+        rooted!(in(*cx) let mut rval = UndefinedValue());
+        self.call(
+            "addDebuggee",
+            &[UInt32Value(id), global.get()],
+            rval.handle_mut(),
+        );
+    }
+
+    pub fn remove_debuggee(&self, pipeline: PipelineId) {
+        let id = match self.debuggee_id(pipeline) {
+            Some(id) => id,
+            None => return,
+        };
+        let cx = self.upcast::<GlobalScope>().get_cx();
+        let _ac = enter_realm(self);
+        rooted!(in(*cx) let mut rval = UndefinedValue());
+        self.call("removeDebuggee", &[UInt32Value(id)], rval.handle_mut());
+        self.debuggees.borrow_mut().remove(&id);
+        self.sample_chans.borrow_mut().remove(&id);
+    }
+
+    /// Handle a command for the debuggee of the given pipeline.
+    #[allow(unsafe_code)]
+    pub fn handle_command(&self, pipeline: PipelineId, command: DebuggerCommand) {
+        let id = match self.debuggee_id(pipeline) {
+            Some(id) => id,
+            None => return warn!("Debugger command sent to unknown pipeline {}.", pipeline),
+        };
+        let cx = self.upcast::<GlobalScope>().get_cx();
+        let _ac = enter_realm(self);
+        rooted!(in(*cx) let mut url = UndefinedValue());
+        rooted!(in(*cx) let mut rval = UndefinedValue());
+        let (name, line, column) = match command {
+            DebuggerCommand::SetBreakpoint(url_string, line, column) => {
+                unsafe { DOMString::from(url_string).to_jsval(*cx, url.handle_mut()) };
+                ("setBreakpoint", line, column)
+            },
+            DebuggerCommand::RemoveBreakpoint(url_string, line, column) => {
+                unsafe { DOMString::from(url_string).to_jsval(*cx, url.handle_mut()) };
+                ("removeBreakpoint", line, column)
+            },
+            DebuggerCommand::Interrupt => {
+                self.call("interrupt", &[UInt32Value(id)], rval.handle_mut());
+                return;
+            },
+            DebuggerCommand::StartSampling(interval, chan) => {
+                self.sample_chans.borrow_mut().insert(id, chan);
+                self.call(
+                    "startSampling",
+                    &[UInt32Value(id), UInt32Value(interval)],
+                    rval.handle_mut(),
+                );
+                return;
+            },
+            DebuggerCommand::StopSampling => {
+                self.sample_chans.borrow_mut().remove(&id);
+                self.call("stopSampling", &[UInt32Value(id)], rval.handle_mut());
+                return;
+            },
+            DebuggerCommand::Resume(_) => {
+                return debug!(
+                    "Ignoring resume of pipeline {}, which isn't paused.",
+                    pipeline
+                );
+            },
+        };
+        self.call(
+            name,
+            &[
+                UInt32Value(id),
+                url.get(),
+                UInt32Value(line),
+                UInt32Value(column),
+            ],
+            rval.handle_mut(),
+        );
+    }
+
+    fn debuggee_id(&self, pipeline: PipelineId) -> Option<u32> {
+        self.debuggees
+            .borrow()
+            .iter()
+            .find(|&(_, debuggee)| debuggee.pipeline_id() == pipeline)
+            .map(|(id, _)| *id)
+    }
+
+    fn debuggee(&self, id: u32) -> Option<DomRoot<GlobalScope>> {
+        self.debuggees
+            .borrow()
+            .get(&id)
+            .map(|debuggee| DomRoot::from_ref(&**debuggee))
+    }
+
+    /// Evaluate JS in the paused frame of the debuggee of the given pipeline, or
+    /// in its global if it isn't paused.
+    #[allow(unsafe_code)]
+    fn evaluate(&self, pipeline: PipelineId, text: String) -> EvaluateJSReply {
+        let id = match self.debuggee_id(pipeline) {
+            Some(id) => id,
+            None => return EvaluateJSReply::VoidValue,
+        };
+        let cx = self.upcast::<GlobalScope>().get_cx();
+        let _ac = enter_realm(self);
+        rooted!(in(*cx) let mut text_value = UndefinedValue());
+        unsafe { DOMString::from(text).to_jsval(*cx, text_value.handle_mut()) };
+        rooted!(in(*cx) let mut rval = UndefinedValue());
+        if !self.call(
+            "evaluate",
+            &[UInt32Value(id), text_value.get()],
+            rval.handle_mut(),
+        ) {
+            return EvaluateJSReply::VoidValue;
+        }
+        match DebuggerValue::new(cx, rval.handle()) {
+            Ok(ConversionResult::Success(value)) => debuggee_value(&value),
+            _ => EvaluateJSReply::VoidValue,
+        }
+    }
+
+    /// Call one of the functions declared by the debugger script. The realm of this
+    /// global must already be entered, and the arguments rooted.
+    #[allow(unsafe_code)]
+    fn call(&self, name: &str, args: &[JSVal], rval: MutableHandleValue) -> bool {
+        let cx = self.upcast::<GlobalScope>().get_cx();
+        let name = CString::new(name).unwrap();
+        rooted!(in(*cx) let object = self.reflector().get_jsobject().get());
+        unsafe {
+            let mut args = HandleValueArray::from_rooted_slice(args);
+            if JS_CallFunctionName(*cx, object.handle(), name.as_ptr(), &mut args, rval) {
+                return true;
+            }
+            let in_realm_proof = AlreadyInRealm::assert(self.upcast());
+            report_pending_exception(*cx, true, InRealm::Already(&in_realm_proof));
+        }
+        false
+    }
+
+    /// Handle a message for this thread that isn't directed at the debugger while a
+    /// debuggee is paused, the same way its event loop would.
+    fn handle_message_while_paused(debuggee: &GlobalScope, msg: DevtoolScriptControlMsg) {
+        match debuggee.downcast::<DedicatedWorkerGlobalScope>() {
+            Some(worker) => worker.handle_devtools_message(msg),
+            None => ScriptThread::handle_devtools_message(msg),
+        }
+    }
+}
+
+fn debuggee_value(value: &DebuggerValue) -> EvaluateJSReply {
+    match &*value.type_ {
+        "null" => EvaluateJSReply::NullValue,
+        "boolean" => EvaluateJSReply::BooleanValue(value.booleanValue),
+        "number" => EvaluateJSReply::NumberValue(value.numberValue),
+        "string" => EvaluateJSReply::StringValue(value.stringValue.to_string()),
+        "object" => EvaluateJSReply::ActorValue {
+            class: value.stringValue.to_string(),
+            uuid: Uuid::new_v4().to_string(),
+        },
+        _ => EvaluateJSReply::VoidValue,
+    }
+}
+
+fn frame_info(frame: &DebuggerFrame) -> FrameInfo {
+    FrameInfo {
+        display_name: frame.displayName.as_ref().map(|name| name.to_string()),
+        type_: frame.type_.to_string(),
+        source_id: frame.sourceId,
+        line: frame.line,
+        column: frame.column,
+        environments: frame
+            .environments
+            .iter()
+            .map(|environment| EnvironmentInfo {
+                type_: environment.type_.to_string(),
+                function_name: environment
+                    .functionName
+                    .as_ref()
+                    .map(|name| name.to_string()),
+                bindings: environment
+                    .bindings
+                    .iter()
+                    .map(|binding| (binding.name.to_string(), debuggee_value(&binding.value)))
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+impl DebuggerGlobalScopeMethods for DebuggerGlobalScope {
+    fn NotifyNewSource(&self, debuggee_id: u32, source: &DebuggerSource) {
+        let debuggee = match self.debuggee(debuggee_id) {
+            Some(debuggee) => debuggee,
+            None => return,
+        };
+        if let Some(chan) = debuggee.devtools_chan() {
+            let source = SourceInfo {
+                id: source.id,
+                url: source.url.to_string(),
+                introduction_type: source.introductionType.as_ref().map(|ty| ty.to_string()),
+                text: source.text.as_ref().map(|text| text.to_string()),
+            };
+            let worker_id = debuggee
+                .downcast::<WorkerGlobalScope>()
+                .map(|worker| worker.get_worker_id());
+            let _ = chan.send(ScriptToDevtoolsControlMsg::NewSource(
+                debuggee.pipeline_id(),
+                worker_id,
+                source,
+            ));
+        }
+    }
+
+    fn Pause(&self, debuggee_id: u32, pause: &DebuggerPause) -> Option<DOMString> {
+        let debuggee = self.debuggee(debuggee_id)?;
+        let chan = debuggee.devtools_chan()?;
+        let pause = PauseInfo {
+            reason: match &*pause.reason {
+                "breakpoint" => PauseReason::Breakpoint,
+                "debuggerStatement" => PauseReason::DebuggerStatement,
+                "interrupted" => PauseReason::Interrupted,
+                _ => PauseReason::ResumeLimit,
+            },
+            frames: pause.frames.iter().map(frame_info).collect(),
+        };
+        let worker_id = debuggee
+            .downcast::<WorkerGlobalScope>()
+            .map(|worker| worker.get_worker_id());
+        let _ = chan.send(ScriptToDevtoolsControlMsg::DebuggerPaused(
+            debuggee.pipeline_id(),
+            worker_id,
+            pause,
+        ));
+
+        // Run a nested loop over the devtools messages of this thread until the
+        // client lets the debuggee run again.
+        while let Ok(msg) = self.devtools_receiver.recv() {
+            match msg {
+                DevtoolScriptControlMsg::Debugger(_, DebuggerCommand::Resume(limit)) => {
+                    return limit.map(|limit| {
+                        DOMString::from(match limit {
+                            ResumeLimit::Next => "next",
+                            ResumeLimit::Step => "step",
+                            ResumeLimit::Finish => "finish",
+                        })
+                    });
+                },
+                DevtoolScriptControlMsg::Debugger(pipeline, command) => {
+                    self.handle_command(pipeline, command)
+                },
+                DevtoolScriptControlMsg::EvaluateJS(pipeline, text, reply) => {
+                    let _ = reply.send(self.evaluate(pipeline, text));
+                },
+                msg => DebuggerGlobalScope::handle_message_while_paused(&debuggee, msg),
+            }
+        }
+        None
+    }
+
+    fn NotifySample(&self, debuggee_id: u32, sample: &DebuggerSample) -> bool {
+        let sample = JSSample {
+            time: *sample.time,
+            frames: sample
+                .frames
+                .iter()
+                .map(|frame| SampledFrameInfo {
+                    function_name: frame.functionName.as_ref().map(|name| name.to_string()),
+                    url: frame.url.to_string(),
+                    line: frame.line,
+                    column: frame.column,
+                })
+                .collect(),
+        };
+        let mut sample_chans = self.sample_chans.borrow_mut();
+        let sent = match sample_chans.get(&debuggee_id) {
+            Some(chan) => chan.send(sample).is_ok(),
+            None => false,
+        };
+        if !sent {
+            sample_chans.remove(&debuggee_id);
+        }
+        sent
+    }
+}
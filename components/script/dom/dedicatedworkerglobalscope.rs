@@ -14,10 +14,11 @@ use crate::dom::bindings::codegen::Bindings::WorkerBinding::WorkerType;
 use crate::dom::bindings::error::{ErrorInfo, ErrorResult};
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::reflector::DomObject;
-use crate::dom::bindings::root::{DomRoot, RootCollection, ThreadLocalStackRoots};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom, RootCollection, ThreadLocalStackRoots};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::bindings::structuredclone;
 use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::debuggerglobalscope::DebuggerGlobalScope;
 use crate::dom::errorevent::ErrorEvent;
 use crate::dom::event::{Event, EventBubbles, EventCancelable, EventStatus};
 use crate::dom::eventtarget::EventTarget;
@@ -183,6 +184,8 @@ pub struct DedicatedWorkerGlobalScope {
     #[ignore_malloc_size_of = "Arc"]
     image_cache: Arc<dyn ImageCache>,
     browsing_context: Option<BrowsingContextId>,
+    /// The global hosting the JS debugger of this worker, if a devtools server exists.
+    debugger_global: MutNullableDom<DebuggerGlobalScope>,
 }
 
 impl WorkerEventLoopMethods for DedicatedWorkerGlobalScope {
@@ -244,6 +247,7 @@ impl DedicatedWorkerGlobalScope {
             worker: DomRefCell::new(None),
             image_cache: image_cache,
             browsing_context,
+            debugger_global: Default::default(),
         }
     }
 
@@ -337,7 +341,8 @@ impl DedicatedWorkerGlobalScope {
                     .pipeline_id(Some(pipeline_id))
                     .referrer(referrer)
                     .referrer_policy(referrer_policy)
-                    .origin(origin);
+                    .origin(origin)
+                    .top_level_origin(init.top_level_origin.clone());
 
                 let runtime = unsafe {
                     let task_source = NetworkingTaskSource(
@@ -378,6 +383,15 @@ impl DedicatedWorkerGlobalScope {
 
                 global_scope.set_https_state(current_global_https_state);
 
+                if global_scope.devtools_chan().is_some() {
+                    let debugger = DebuggerGlobalScope::new(
+                        global_scope,
+                        scope.from_devtools_receiver().clone(),
+                    );
+                    debugger.add_debuggee(global_scope);
+                    global.debugger_global.set(Some(&debugger));
+                }
+
                 let (metadata, bytes) = match load_whole_resource(
                     request,
                     &global_scope.resource_threads().sender(),
@@ -434,6 +448,9 @@ impl DedicatedWorkerGlobalScope {
                         parent_sender,
                         CommonScriptMsg::CollectReports,
                     );
+                scope
+                    .upcast::<GlobalScope>()
+                    .close_indexeddb_connections();
             })
             .expect("Thread spawning failed");
     }
@@ -485,18 +502,27 @@ impl DedicatedWorkerGlobalScope {
         }
     }
 
+    pub fn handle_devtools_message(&self, msg: DevtoolScriptControlMsg) {
+        match msg {
+            DevtoolScriptControlMsg::EvaluateJS(_pipe_id, string, sender) => {
+                devtools::handle_evaluate_js(self.upcast(), string, sender)
+            },
+            DevtoolScriptControlMsg::WantsLiveNotifications(_pipe_id, bool_val) => {
+                devtools::handle_wants_live_notifications(self.upcast(), bool_val)
+            },
+            DevtoolScriptControlMsg::Debugger(pipe_id, command) => {
+                if let Some(debugger) = self.debugger_global.get() {
+                    debugger.handle_command(pipe_id, command);
+                }
+            },
+            _ => debug!("got an unusable devtools control message inside the worker!"),
+        }
+    }
+
     fn handle_mixed_message(&self, msg: MixedMessage) {
         // FIXME(#26324): `self.worker` is None in devtools messages.
         match msg {
-            MixedMessage::FromDevtools(msg) => match msg {
-                DevtoolScriptControlMsg::EvaluateJS(_pipe_id, string, sender) => {
-                    devtools::handle_evaluate_js(self.upcast(), string, sender)
-                },
-                DevtoolScriptControlMsg::WantsLiveNotifications(_pipe_id, bool_val) => {
-                    devtools::handle_wants_live_notifications(self.upcast(), bool_val)
-                },
-                _ => debug!("got an unusable devtools control message inside the worker!"),
-            },
+            MixedMessage::FromDevtools(msg) => self.handle_devtools_message(msg),
             MixedMessage::FromWorker(DedicatedWorkerScriptMsg::CommonWorker(
                 linked_worker,
                 msg,
@@ -107,14 +107,12 @@ use crate::task_source::{TaskSource, TaskSourceName};
 use crate::timers::OneshotTimerCallback;
 use canvas_traits::webgl::{self, SwapChainId, WebGLContextId, WebGLMsg};
 use content_security_policy::{self as csp, CspList};
-use cookie::Cookie;
 use devtools_traits::ScriptToDevtoolsControlMsg;
 use dom_struct::dom_struct;
 use embedder_traits::EmbedderMsg;
 use encoding_rs::{Encoding, UTF_8};
 use euclid::default::Point2D;
 use html5ever::{LocalName, Namespace, QualName};
-use hyper_serde::Serde;
 use ipc_channel::ipc::{self, IpcSender};
 use js::jsapi::{JSObject, JSRuntime};
 use keyboard_types::{Code, Key, KeyState};
@@ -128,7 +126,7 @@ use net_traits::pub_domains::is_pub_domain;
 use net_traits::request::RequestBuilder;
 use net_traits::response::HttpsState;
 use net_traits::CookieSource::NonHTTP;
-use net_traits::CoreResourceMsg::{GetCookiesForUrl, SetCookiesForUrl};
+use net_traits::CoreResourceMsg::{GetCookiesForUrl, SetCookieStringForUrl};
 use net_traits::{FetchResponseMsg, IpcSend, ReferrerPolicy};
 use num_traits::ToPrimitive;
 use percent_encoding::percent_decode;
@@ -160,6 +158,7 @@ use style::attr::AttrValue;
 use style::context::QuirksMode;
 use style::invalidation::element::restyle_hints::RestyleHint;
 use style::media_queries::{Device, MediaType};
+use style::properties_and_values::registry::PropertyRegistration;
 use style::selector_parser::Snapshot;
 use style::shared_lock::SharedRwLock as StyleSharedRwLock;
 use style::str::{split_html_space_chars, str_join};
@@ -389,6 +388,9 @@ pub struct Document {
     animations: DomRefCell<Animations>,
     /// The nearest inclusive ancestors to all the nodes that require a restyle.
     dirty_root: MutNullableDom<Element>,
+    /// The names of the custom properties registered with `CSS.registerProperty()`.
+    /// https://drafts.css-houdini.org/css-properties-values-api-1/#dom-window-registeredpropertyset-slot
+    registered_custom_properties: DomRefCell<HashSet<Atom>>,
 }
 
 #[derive(JSTraceable, MallocSizeOf)]
@@ -1760,6 +1762,24 @@ impl Document {
         }
     }
 
+    /// Registers a custom property from `CSS.registerProperty()`, and restyles the
+    /// document. Returns false if a property with that name was already registered.
+    pub fn register_custom_property(&self, registration: PropertyRegistration) -> bool {
+        if !self
+            .registered_custom_properties
+            .borrow_mut()
+            .insert(registration.name.clone())
+        {
+            return false;
+        }
+        self.window
+            .layout_chan()
+            .send(Msg::RegisterProperty(registration))
+            .unwrap();
+        self.invalidate_stylesheets();
+        true
+    }
+
     /// <https://html.spec.whatwg.org/multipage/#dom-window-requestanimationframe>
     pub fn request_animation_frame(&self, callback: AnimationFrameCallback) -> u32 {
         let ident = self.animation_frame_ident.get() + 1;
@@ -1888,6 +1908,7 @@ impl Document {
     ) {
         request.csp_list = self.get_csp_list().map(|x| x.clone());
         request.https_state = self.https_state.get();
+        request.top_level_origin = self.window.upcast::<GlobalScope>().top_level_origin();
         let mut loader = self.loader.borrow_mut();
         loader.fetch_async(load, request, fetch_target);
     }
@@ -3048,6 +3069,7 @@ impl Document {
             },
             animations: DomRefCell::new(Animations::new()),
             dirty_root: Default::default(),
+            registered_custom_properties: Default::default(),
         }
     }
 
@@ -4595,12 +4617,14 @@ impl DocumentMethods for Document {
         }
 
         let url = self.url();
-        let (tx, rx) = profile_ipc::channel(self.global().time_profiler_chan().clone()).unwrap();
-        let _ = self
-            .window
-            .upcast::<GlobalScope>()
-            .resource_threads()
-            .send(GetCookiesForUrl(url, tx, NonHTTP));
+        let global = self.window.upcast::<GlobalScope>();
+        let (tx, rx) = profile_ipc::channel(global.time_profiler_chan().clone()).unwrap();
+        let _ = global.resource_threads().send(GetCookiesForUrl(
+            url,
+            global.top_level_origin(),
+            tx,
+            NonHTTP,
+        ));
         let cookies = rx.recv().unwrap();
         Ok(cookies.map_or(DOMString::new(), DOMString::from))
     }
@@ -4615,17 +4639,13 @@ impl DocumentMethods for Document {
             return Err(Error::Security);
         }
 
-        let cookies = if let Some(cookie) = Cookie::parse(cookie.to_string()).ok().map(Serde) {
-            vec![cookie]
-        } else {
-            vec![]
-        };
-
-        let _ = self
-            .window
-            .upcast::<GlobalScope>()
-            .resource_threads()
-            .send(SetCookiesForUrl(self.url(), cookies, NonHTTP));
+        let global = self.window.upcast::<GlobalScope>();
+        let _ = global.resource_threads().send(SetCookieStringForUrl(
+            self.url(),
+            global.top_level_origin(),
+            cookie.to_string(),
+            NonHTTP,
+        ));
         Ok(())
     }
 
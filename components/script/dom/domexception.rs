@@ -38,6 +38,12 @@ pub enum DOMErrorName {
     DataCloneError = DOMExceptionConstants::DATA_CLONE_ERR,
     NotReadableError,
     OperationError,
+    ConstraintError,
+    DataError,
+    TransactionInactiveError,
+    ReadOnlyError,
+    VersionError,
+    UnknownError,
 }
 
 impl DOMErrorName {
@@ -67,6 +73,12 @@ impl DOMErrorName {
             "DataCloneError" => Some(DOMErrorName::DataCloneError),
             "NotReadableError" => Some(DOMErrorName::NotReadableError),
             "OperationError" => Some(DOMErrorName::OperationError),
+            "ConstraintError" => Some(DOMErrorName::ConstraintError),
+            "DataError" => Some(DOMErrorName::DataError),
+            "TransactionInactiveError" => Some(DOMErrorName::TransactionInactiveError),
+            "ReadOnlyError" => Some(DOMErrorName::ReadOnlyError),
+            "VersionError" => Some(DOMErrorName::VersionError),
+            "UnknownError" => Some(DOMErrorName::UnknownError),
             _ => None,
         }
     }
@@ -114,6 +126,22 @@ impl DOMException {
             DOMErrorName::OperationError => {
                 "The operation failed for an operation-specific reason."
             },
+            DOMErrorName::ConstraintError => {
+                "A mutation operation in a transaction failed because a constraint was not satisfied."
+            },
+            DOMErrorName::DataError => "The data provided does not meet requirements.",
+            DOMErrorName::TransactionInactiveError => {
+                "A request was placed against a transaction which is not active."
+            },
+            DOMErrorName::ReadOnlyError => {
+                "The mutating operation was attempted in a readonly transaction."
+            },
+            DOMErrorName::VersionError => {
+                "An attempt was made to open a database using a lower version than the existing version."
+            },
+            DOMErrorName::UnknownError => {
+                "The operation failed for an unknown transient reason (e.g. out of memory)."
+            },
         };
 
         (
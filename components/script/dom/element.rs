@@ -314,6 +314,84 @@ impl Element {
         }
     }
 
+    /// Restyles this element and its descendants, since it anchors :has()
+    /// selectors that may match differently after a change to its subtree or
+    /// to its later siblings.
+    fn restyle_relative_selector_anchor(&self) {
+        self.restyle(NodeDamage::OtherNodeDamage);
+        let doc = self.node.owner_doc();
+        doc.ensure_pending_restyle(self)
+            .hint
+            .insert(RestyleHint::RESTYLE_DESCENDANTS);
+
+        let later_siblings_affected = self.node.GetParentElement().map_or(false, |parent| {
+            parent
+                .selector_flags
+                .get()
+                .intersects(ElementSelectorFlags::HAS_SLOW_SELECTOR_LATER_SIBLINGS)
+        });
+        if later_siblings_affected {
+            for sibling in self.node.following_siblings() {
+                if sibling.is::<Element>() {
+                    sibling.dirty(NodeDamage::OtherNodeDamage);
+                }
+            }
+        }
+    }
+
+    /// Restyles the anchors of :has() selectors that may match differently
+    /// after the children of this element before `next_child`, or all of them
+    /// if there is none, changed: this element and its ancestors, and the
+    /// earlier siblings of all of them.
+    fn restyle_relative_selector_anchors(&self, next_child: Option<&Node>) {
+        let is_anchor = |element: &Element| {
+            element
+                .selector_flags
+                .get()
+                .intersects(ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR)
+        };
+        let has_sibling_anchors = |element: &Element| {
+            element
+                .selector_flags
+                .get()
+                .intersects(ElementSelectorFlags::HAS_SIBLING_RELATIVE_SELECTOR_ANCHORS)
+        };
+
+        if has_sibling_anchors(self) {
+            for child in self.node.children() {
+                if next_child.map_or(false, |next_child| &*child == next_child) {
+                    break;
+                }
+                if let Some(child) = child.downcast::<Element>() {
+                    if is_anchor(child) {
+                        child.restyle_relative_selector_anchor();
+                    }
+                }
+            }
+        }
+
+        let mut current = DomRoot::from_ref(self);
+        loop {
+            if is_anchor(&current) {
+                current.restyle_relative_selector_anchor();
+            }
+            let parent = match current.node.GetParentElement() {
+                Some(parent) => parent,
+                None => break,
+            };
+            if has_sibling_anchors(&parent) {
+                for sibling in current.node.preceding_siblings() {
+                    if let Some(sibling) = sibling.downcast::<Element>() {
+                        if is_anchor(sibling) {
+                            sibling.restyle_relative_selector_anchor();
+                        }
+                    }
+                }
+            }
+            current = parent;
+        }
+    }
+
     pub fn set_is(&self, is: LocalName) {
         *self.is.borrow_mut() = Some(is);
     }
@@ -2996,6 +3074,8 @@ impl VirtualMethods for Element {
                 }
             }
         }
+
+        self.restyle_relative_selector_anchors(mutation.next_child());
     }
 
     fn adopting_steps(&self, old_doc: &Document) {
@@ -3060,6 +3140,10 @@ impl<'a> SelectorsElement for DomRoot<Element> {
             .next()
     }
 
+    fn first_element_child(&self) -> Option<DomRoot<Element>> {
+        self.node.children().filter_map(DomRoot::downcast).next()
+    }
+
     fn attr_matches(
         &self,
         ns: &NamespaceConstraint<&Namespace>,
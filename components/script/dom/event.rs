@@ -20,6 +20,8 @@ use crate::dom::element::Element;
 use crate::dom::eventtarget::{CompiledEventListener, EventTarget, ListenerPhase};
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::htmlinputelement::InputActivationState;
+use crate::dom::idbrequest::IDBRequest;
+use crate::dom::idbtransaction::IDBTransaction;
 use crate::dom::mouseevent::MouseEvent;
 use crate::dom::node::{Node, ShadowIncluding};
 use crate::dom::performance::reduce_timing_resolution;
@@ -153,6 +155,18 @@ impl Event {
                     event_path.push(DomRoot::from_ref(document.window().upcast()));
                 }
             }
+        } else if let Some(request) = target.downcast::<IDBRequest>() {
+            // https://w3c.github.io/IndexedDB/#ref-for-get-the-parent
+            // A request's parent is its transaction,
+            // and a transaction's parent is its connection.
+            event_path.push(DomRoot::from_ref(target));
+            if let Some(transaction) = request.transaction() {
+                event_path.push(DomRoot::from_ref(transaction.upcast()));
+                event_path.push(DomRoot::from_ref(transaction.db().upcast()));
+            }
+        } else if let Some(transaction) = target.downcast::<IDBTransaction>() {
+            event_path.push(DomRoot::from_ref(target));
+            event_path.push(DomRoot::from_ref(transaction.db().upcast()));
         } else {
             // a non-node EventTarget, likely a global.
             // No parent to propagate up to, but we still
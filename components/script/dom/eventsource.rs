@@ -534,7 +534,8 @@ impl EventSource {
             Some(true),
         )
         .origin(global.origin().immutable().clone())
-        .pipeline_id(Some(global.pipeline_id()));
+        .pipeline_id(Some(global.pipeline_id()))
+        .top_level_origin(global.top_level_origin());
 
         // Step 10
         // TODO(eijebong): Replace once typed headers allow it
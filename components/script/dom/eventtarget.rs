@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::abortsignal::AbortAlgorithm;
 use crate::dom::beforeunloadevent::BeforeUnloadEvent;
 use crate::dom::bindings::callback::{CallbackContainer, CallbackFunction, ExceptionHandling};
 use crate::dom::bindings::cell::DomRefCell;
@@ -22,7 +23,7 @@ use crate::dom::bindings::codegen::UnionTypes::EventOrString;
 use crate::dom::bindings::error::{report_pending_exception, Error, Fallible};
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
-use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::element::Element;
 use crate::dom::errorevent::ErrorEvent;
@@ -697,6 +698,7 @@ impl EventTarget {
         event
     }
     // https://dom.spec.whatwg.org/#dom-eventtarget-addeventlistener
+    #[allow(unrooted_must_root)]
     pub fn add_event_listener(
         &self,
         ty: DOMString,
@@ -707,8 +709,15 @@ impl EventTarget {
             Some(l) => l,
             None => return,
         };
+        if options
+            .signal
+            .as_ref()
+            .map_or(false, |signal| signal.aborted())
+        {
+            return;
+        }
         let mut handlers = self.handlers.borrow_mut();
-        let entry = match handlers.entry(Atom::from(ty)) {
+        let entry = match handlers.entry(Atom::from(ty.clone())) {
             Occupied(entry) => entry.into_mut(),
             Vacant(entry) => entry.insert(EventListeners(vec![])),
         };
@@ -720,11 +729,21 @@ impl EventTarget {
         };
         let new_entry = EventListenerEntry {
             phase: phase,
-            listener: EventListenerType::Additive(listener),
+            listener: EventListenerType::Additive(listener.clone()),
             once: options.once,
         };
-        if !entry.contains(&new_entry) {
-            entry.push(new_entry);
+        if entry.contains(&new_entry) {
+            return;
+        }
+        entry.push(new_entry);
+
+        if let Some(ref signal) = options.signal {
+            signal.add_abort_algorithm(AbortAlgorithm::RemoveEventListener {
+                target: Dom::from_ref(self),
+                ty,
+                listener,
+                capture: options.parent.capture,
+            });
         }
     }
 
@@ -806,6 +825,7 @@ impl From<AddEventListenerOptionsOrBoolean> for AddEventListenerOptions {
             AddEventListenerOptionsOrBoolean::Boolean(capture) => Self {
                 parent: EventListenerOptions { capture },
                 once: false,
+                signal: None,
             },
         }
     }
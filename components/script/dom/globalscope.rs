@@ -5,6 +5,7 @@
 use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::BroadcastChannelBinding::BroadcastChannelMethods;
 use crate::dom::bindings::codegen::Bindings::EventSourceBinding::EventSourceBinding::EventSourceMethods;
+use crate::dom::bindings::codegen::Bindings::FunctionBinding::Function;
 use crate::dom::bindings::codegen::Bindings::ImageBitmapBinding::{
     ImageBitmapOptions, ImageBitmapSource,
 };
@@ -26,6 +27,7 @@ use crate::dom::bindings::weakref::{DOMTracker, WeakRef};
 use crate::dom::blob::Blob;
 use crate::dom::broadcastchannel::BroadcastChannel;
 use crate::dom::crypto::Crypto;
+use crate::dom::debuggerglobalscope::DebuggerGlobalScope;
 use crate::dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
 use crate::dom::errorevent::ErrorEvent;
 use crate::dom::event::{Event, EventBubbles, EventCancelable, EventStatus};
@@ -33,6 +35,7 @@ use crate::dom::eventsource::EventSource;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::file::File;
 use crate::dom::htmlscriptelement::ScriptId;
+use crate::dom::idbfactory::IDBFactory;
 use crate::dom::identityhub::Identities;
 use crate::dom::imagebitmap::ImageBitmap;
 use crate::dom::messageevent::MessageEvent;
@@ -52,6 +55,7 @@ use crate::script_module::ModuleTree;
 use crate::script_runtime::{CommonScriptMsg, JSContext as SafeJSContext, ScriptChan, ScriptPort};
 use crate::script_thread::{MainThreadScriptChan, ScriptThread};
 use crate::task::TaskCanceller;
+use crate::task_source::database_access::DatabaseAccessTaskSource;
 use crate::task_source::dom_manipulation::DOMManipulationTaskSource;
 use crate::task_source::file_reading::FileReadingTaskSource;
 use crate::task_source::networking::NetworkingTaskSource;
@@ -101,7 +105,7 @@ use script_traits::{
     ScriptToConstellationChan, TimerEvent,
 };
 use script_traits::{TimerEventId, TimerSchedulerMsg, TimerSource};
-use servo_url::{MutableOrigin, ServoUrl};
+use servo_url::{ImmutableOrigin, MutableOrigin, ServoUrl};
 use std::borrow::Cow;
 use std::cell::Cell;
 use std::collections::hash_map::Entry;
@@ -129,6 +133,9 @@ pub struct GlobalScope {
     eventtarget: EventTarget,
     crypto: MutNullableDom<Crypto>,
 
+    /// https://w3c.github.io/IndexedDB/#dom-windoworworkerglobalscope-indexeddb
+    indexeddb: MutNullableDom<IDBFactory>,
+
     /// The message-port router id for this global, if it is managing ports.
     message_port_state: DomRefCell<MessagePortState>,
 
@@ -252,6 +259,18 @@ pub struct GlobalScope {
 
     /// currect https state (from previous request)
     https_state: Cell<HttpsState>,
+
+    /// The origin of the top-level document this global is loaded under,
+    /// used by the network stack to partition and block third-party cookies.
+    top_level_origin: DomRefCell<Option<ImmutableOrigin>>,
+
+    /// https://streams.spec.whatwg.org/#byte-length-queuing-strategy-size-function
+    #[ignore_malloc_size_of = "Rc"]
+    byte_length_queuing_strategy_size_function: DomRefCell<Option<Rc<Function>>>,
+
+    /// https://streams.spec.whatwg.org/#count-queuing-strategy-size-function
+    #[ignore_malloc_size_of = "Rc"]
+    count_queuing_strategy_size_function: DomRefCell<Option<Rc<Function>>>,
 }
 
 /// A wrapper for glue-code between the ipc router and the event-loop.
@@ -577,6 +596,7 @@ impl GlobalScope {
             blob_state: DomRefCell::new(BlobState::UnManaged),
             eventtarget: EventTarget::new_inherited(),
             crypto: Default::default(),
+            indexeddb: Default::default(),
             registration_map: DomRefCell::new(HashMap::new()),
             worker_map: DomRefCell::new(HashMap::new()),
             pipeline_id,
@@ -605,6 +625,9 @@ impl GlobalScope {
             gpu_id_hub,
             frozen_supported_performance_entry_types: DomRefCell::new(Default::default()),
             https_state: Cell::new(HttpsState::None),
+            top_level_origin: DomRefCell::new(None),
+            byte_length_queuing_strategy_size_function: DomRefCell::new(None),
+            count_queuing_strategy_size_function: DomRefCell::new(None),
         }
     }
 
@@ -1925,6 +1948,18 @@ impl GlobalScope {
         self.crypto.or_init(|| Crypto::new(self))
     }
 
+    pub fn indexeddb(&self) -> DomRoot<IDBFactory> {
+        self.indexeddb.or_init(|| IDBFactory::new(self))
+    }
+
+    /// Close the IndexedDB connections opened by this global,
+    /// so that the storage thread doesn't wait on them.
+    pub fn close_indexeddb_connections(&self) {
+        if let Some(factory) = self.indexeddb.get() {
+            factory.close_all_connections();
+        }
+    }
+
     pub fn live_devtools_updates(&self) -> bool {
         self.devtools_wants_updates.get()
     }
@@ -2064,6 +2099,9 @@ impl GlobalScope {
             // TODO: is this the right URL to return?
             return worklet.base_url();
         }
+        if self.is::<DebuggerGlobalScope>() {
+            return ServoUrl::parse("about:blank").unwrap();
+        }
         unreachable!();
     }
 
@@ -2201,6 +2239,18 @@ impl GlobalScope {
         unreachable!();
     }
 
+    /// `TaskSource` to send messages to the database access task source of
+    /// this global scope.
+    pub fn database_access_task_source(&self) -> DatabaseAccessTaskSource {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.task_manager().database_access_task_source();
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            return worker.database_access_task_source();
+        }
+        unreachable!();
+    }
+
     /// `TaskSource` to send messages to the websocket task source of
     /// this global scope.
     pub fn websocket_task_source(&self) -> WebsocketTaskSource {
@@ -2578,6 +2628,24 @@ impl GlobalScope {
         frozen_types
     }
 
+    pub fn get_byte_length_queuing_strategy_size(&self) -> Option<Rc<Function>> {
+        self.byte_length_queuing_strategy_size_function
+            .borrow()
+            .clone()
+    }
+
+    pub fn set_byte_length_queuing_strategy_size(&self, function: Rc<Function>) {
+        *self.byte_length_queuing_strategy_size_function.borrow_mut() = Some(function);
+    }
+
+    pub fn get_count_queuing_strategy_size(&self) -> Option<Rc<Function>> {
+        self.count_queuing_strategy_size_function.borrow().clone()
+    }
+
+    pub fn set_count_queuing_strategy_size(&self, function: Rc<Function>) {
+        *self.count_queuing_strategy_size_function.borrow_mut() = Some(function);
+    }
+
     pub fn is_headless(&self) -> bool {
         self.is_headless
     }
@@ -2594,6 +2662,14 @@ impl GlobalScope {
         self.https_state.set(https_state);
     }
 
+    pub fn top_level_origin(&self) -> Option<ImmutableOrigin> {
+        self.top_level_origin.borrow().clone()
+    }
+
+    pub fn set_top_level_origin(&self, top_level_origin: Option<ImmutableOrigin>) {
+        *self.top_level_origin.borrow_mut() = top_level_origin;
+    }
+
     /// https://www.w3.org/TR/CSP/#get-csp-of-object
     pub fn get_csp_list(&self) -> Option<CspList> {
         if let Some(window) = self.downcast::<Window>() {
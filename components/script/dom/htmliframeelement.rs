@@ -164,6 +164,9 @@ impl HTMLIFrameElement {
         self.pending_pipeline_id.set(Some(new_pipeline_id));
 
         let global_scope = window.upcast::<GlobalScope>();
+        // The constellation also sets this for the pipelines it creates,
+        // but the initial about:blank document is created from here.
+        load_data.top_level_origin = global_scope.top_level_origin();
         let load_info = IFrameLoadInfo {
             parent_pipeline_id: global_scope.pipeline_id(),
             browsing_context_id: browsing_context_id,
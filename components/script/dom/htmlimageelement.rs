@@ -391,7 +391,8 @@ impl HTMLImageElement {
             } else {
                 FromPictureOrSrcSet::No
             },
-        );
+        )
+        .top_level_origin(document.global().top_level_origin());
 
         // This is a background load because the load blocker already fulfills the
         // purpose of delaying the document's load event.
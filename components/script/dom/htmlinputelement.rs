@@ -1830,6 +1830,35 @@ impl HTMLInputElement {
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
     }
 
+    /// The clear algorithm of input elements, used by WebDriver's Element Clear.
+    ///
+    /// <https://w3c.github.io/webdriver/#dfn-clear-algorithm>
+    pub fn clear(&self) {
+        // Step 1.
+        self.value_dirty.set(false);
+
+        // Step 2.
+        let mut value = DOMString::new();
+        self.sanitize_value(&mut value);
+        self.textinput.borrow_mut().set_content(value);
+
+        // Step 3.
+        if let InputType::Radio | InputType::Checkbox = self.input_type() {
+            self.update_checked_state(self.DefaultChecked(), false);
+            self.checked_changed.set(false);
+        }
+
+        // Step 4.
+        if self.filelist.get().is_some() {
+            let window = window_from_node(self);
+            let filelist = FileList::new(&window, vec![]);
+            self.filelist.set(Some(&filelist));
+        }
+
+        self.update_placeholder_shown_state();
+        self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
+    }
+
     fn update_placeholder_shown_state(&self) {
         if !self.input_type().is_textual_or_password() {
             return;
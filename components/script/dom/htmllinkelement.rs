@@ -55,6 +55,8 @@ pub struct HTMLLinkElement {
     rel_list: MutNullableDom<DOMTokenList>,
     #[ignore_malloc_size_of = "Arc"]
     stylesheet: DomRefCell<Option<Arc<Stylesheet>>>,
+    /// The text the stylesheet was parsed from.
+    stylesheet_source: DomRefCell<String>,
     cssom_stylesheet: MutNullableDom<CSSStyleSheet>,
 
     /// <https://html.spec.whatwg.org/multipage/#a-style-sheet-that-is-blocking-scripts>
@@ -80,6 +82,7 @@ impl HTMLLinkElement {
             rel_list: Default::default(),
             parser_inserted: Cell::new(creator.is_parser_created()),
             stylesheet: DomRefCell::new(None),
+            stylesheet_source: DomRefCell::new(String::new()),
             cssom_stylesheet: MutNullableDom::new(None),
             pending_loads: Cell::new(0),
             any_failed_load: Cell::new(false),
@@ -109,12 +112,13 @@ impl HTMLLinkElement {
     // FIXME(emilio): These methods are duplicated with
     // HTMLStyleElement::set_stylesheet.
     #[allow(unrooted_must_root)]
-    pub fn set_stylesheet(&self, s: Arc<Stylesheet>) {
+    pub fn set_stylesheet(&self, s: Arc<Stylesheet>, source: String) {
         let stylesheets_owner = stylesheets_owner_from_node(self);
         if let Some(ref s) = *self.stylesheet.borrow() {
             stylesheets_owner.remove_stylesheet(self.upcast(), s)
         }
         *self.stylesheet.borrow_mut() = Some(s.clone());
+        *self.stylesheet_source.borrow_mut() = source;
         self.cssom_stylesheet.set(None);
         stylesheets_owner.add_stylesheet(self.upcast(), s);
     }
@@ -133,6 +137,7 @@ impl HTMLLinkElement {
                     None, // todo handle location
                     None, // todo handle title
                     sheet,
+                    Some(self.stylesheet_source.borrow().clone()),
                 )
             })
         })
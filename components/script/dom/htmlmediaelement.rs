@@ -845,7 +845,8 @@ impl HTMLMediaElement {
             .origin(document.origin().immutable().clone())
             .pipeline_id(Some(self.global().pipeline_id()))
             .referrer(Some(Referrer::ReferrerUrl(document.url())))
-            .referrer_policy(document.get_referrer_policy());
+            .referrer_policy(document.get_referrer_policy())
+            .top_level_origin(document.global().top_level_origin());
 
         let mut current_fetch_context = self.current_fetch_context.borrow_mut();
         if let Some(ref mut current_fetch_context) = *current_fetch_context {
@@ -76,6 +76,7 @@ impl HTMLMetaElement {
                     None, // todo handle location
                     None, // todo handle title
                     sheet,
+                    None,
                 )
             })
         })
@@ -34,6 +34,8 @@ pub struct HTMLStyleElement {
     htmlelement: HTMLElement,
     #[ignore_malloc_size_of = "Arc"]
     stylesheet: DomRefCell<Option<Arc<Stylesheet>>>,
+    /// The text the stylesheet was parsed from.
+    stylesheet_source: DomRefCell<String>,
     cssom_stylesheet: MutNullableDom<CSSStyleSheet>,
     /// <https://html.spec.whatwg.org/multipage/#a-style-sheet-that-is-blocking-scripts>
     parser_inserted: Cell<bool>,
@@ -53,6 +55,7 @@ impl HTMLStyleElement {
         HTMLStyleElement {
             htmlelement: HTMLElement::new_inherited(local_name, prefix, document),
             stylesheet: DomRefCell::new(None),
+            stylesheet_source: DomRefCell::new(String::new()),
             cssom_stylesheet: MutNullableDom::new(None),
             parser_inserted: Cell::new(creator.is_parser_created()),
             in_stack_of_open_elements: Cell::new(creator.is_parser_created()),
@@ -133,17 +136,18 @@ impl HTMLStyleElement {
                 .queue_simple_event(self.upcast(), atom!("load"), &window);
         }
 
-        self.set_stylesheet(sheet);
+        self.set_stylesheet(sheet, data.into());
     }
 
     // FIXME(emilio): This is duplicated with HTMLLinkElement::set_stylesheet.
     #[allow(unrooted_must_root)]
-    pub fn set_stylesheet(&self, s: Arc<Stylesheet>) {
+    pub fn set_stylesheet(&self, s: Arc<Stylesheet>, source: String) {
         let stylesheets_owner = stylesheets_owner_from_node(self);
         if let Some(ref s) = *self.stylesheet.borrow() {
             stylesheets_owner.remove_stylesheet(self.upcast(), s)
         }
         *self.stylesheet.borrow_mut() = Some(s.clone());
+        *self.stylesheet_source.borrow_mut() = source;
         self.cssom_stylesheet.set(None);
         stylesheets_owner.add_stylesheet(self.upcast(), s);
     }
@@ -162,6 +166,7 @@ impl HTMLStyleElement {
                     None, // todo handle location
                     None, // todo handle title
                     sheet,
+                    Some(self.stylesheet_source.borrow().clone()),
                 )
             })
         })
@@ -445,6 +445,16 @@ impl HTMLTextAreaElement {
         self.value_dirty.set(false);
     }
 
+    /// The clear algorithm of textarea elements, used by WebDriver's Element Clear.
+    ///
+    /// <https://w3c.github.io/webdriver/#dfn-clear-algorithm>
+    pub fn clear(&self) {
+        self.textinput.borrow_mut().set_content(DOMString::new());
+        self.value_dirty.set(false);
+        self.update_placeholder_shown_state();
+        self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
+    }
+
     #[allow(unrooted_must_root)]
     fn selection(&self) -> TextControlSelection<Self> {
         TextControlSelection::new(&self, &self.textinput)
@@ -194,7 +194,8 @@ impl HTMLVideoElement {
             .credentials_mode(CredentialsMode::Include)
             .use_url_credentials(true)
             .origin(document.origin().immutable().clone())
-            .pipeline_id(Some(document.global().pipeline_id()));
+            .pipeline_id(Some(document.global().pipeline_id()))
+            .top_level_origin(document.global().top_level_origin());
 
         // Step 5.
         // This delay must be independent from the ones created by HTMLMediaElement during
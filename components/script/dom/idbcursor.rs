@@ -0,0 +1,426 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::IDBCursorBinding::{
+    IDBCursorDirection, IDBCursorMethods,
+};
+use crate::dom::bindings::codegen::UnionTypes::IDBObjectStoreOrIDBIndex;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbindex::IDBIndex;
+use crate::dom::idbobjectstore::IDBObjectStore;
+use crate::dom::idbrequest::{IDBRequest, RequestSource, ResultConversion};
+use crate::dom::idbtransaction::IDBTransaction;
+use crate::indexed_db::{convert_value_to_key, extract_key, record_value_to_jsval};
+use crate::indexed_db::{key_type_to_jsval, ExtractionResult};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsapi::Heap;
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::HandleValue;
+use net_traits::indexeddb_thread::IndexedDBTxnMode;
+use net_traits::indexeddb_thread::{AsyncOperation, CursorIteration, IndexedDBCursorDirection};
+use net_traits::indexeddb_thread::{IndexedDBKeyRange, IndexedDBKeyType, IndexedDBRecord};
+use std::cell::Cell;
+
+#[dom_struct]
+pub struct IDBCursor {
+    reflector_: Reflector,
+    object_store: Dom<IDBObjectStore>,
+    /// The index iterated by this cursor, if its source is an index.
+    index: Option<Dom<IDBIndex>>,
+    transaction: Dom<IDBTransaction>,
+    direction: IndexedDBCursorDirection,
+    range: IndexedDBKeyRange,
+    /// https://w3c.github.io/IndexedDB/#cursor-key-only-flag
+    key_only: bool,
+    request: MutNullableDom<IDBRequest>,
+    /// https://w3c.github.io/IndexedDB/#cursor-position
+    position: DomRefCell<Option<IndexedDBKeyType>>,
+    /// https://w3c.github.io/IndexedDB/#cursor-object-store-position
+    object_store_position: DomRefCell<Option<IndexedDBKeyType>>,
+    key: DomRefCell<Option<IndexedDBKeyType>>,
+    primary_key: DomRefCell<Option<IndexedDBKeyType>>,
+    #[ignore_malloc_size_of = "mozjs"]
+    value: Heap<JSVal>,
+    /// https://w3c.github.io/IndexedDB/#cursor-got-value-flag
+    got_value: Cell<bool>,
+}
+
+impl IDBCursor {
+    pub fn new_inherited(
+        object_store: &IDBObjectStore,
+        index: Option<&IDBIndex>,
+        direction: IndexedDBCursorDirection,
+        range: IndexedDBKeyRange,
+        key_only: bool,
+    ) -> IDBCursor {
+        IDBCursor {
+            reflector_: Reflector::new(),
+            object_store: Dom::from_ref(object_store),
+            index: index.map(Dom::from_ref),
+            transaction: Dom::from_ref(&*object_store.transaction()),
+            direction,
+            range,
+            key_only,
+            request: Default::default(),
+            position: DomRefCell::new(None),
+            object_store_position: DomRefCell::new(None),
+            key: DomRefCell::new(None),
+            primary_key: DomRefCell::new(None),
+            value: Heap::default(),
+            got_value: Cell::new(false),
+        }
+    }
+
+    /// Creates a cursor for `openKeyCursor`.
+    pub fn new(
+        global: &GlobalScope,
+        object_store: &IDBObjectStore,
+        index: Option<&IDBIndex>,
+        direction: IndexedDBCursorDirection,
+        range: IndexedDBKeyRange,
+    ) -> DomRoot<IDBCursor> {
+        reflect_dom_object(
+            Box::new(IDBCursor::new_inherited(
+                object_store,
+                index,
+                direction,
+                range,
+                true,
+            )),
+            global,
+        )
+    }
+
+    pub fn object_store(&self) -> DomRoot<IDBObjectStore> {
+        DomRoot::from_ref(&*self.object_store)
+    }
+
+    pub fn set_request(&self, request: &IDBRequest) {
+        self.request.set(Some(request));
+    }
+
+    pub fn value(&self) -> JSVal {
+        self.value.get()
+    }
+
+    fn source_deleted(&self) -> bool {
+        match self.index {
+            Some(ref index) => index.is_deleted(),
+            None => self.object_store.is_deleted(),
+        }
+    }
+
+    fn is_forward(&self) -> bool {
+        match self.direction {
+            IndexedDBCursorDirection::Next | IndexedDBCursorDirection::NextUnique => true,
+            IndexedDBCursorDirection::Prev | IndexedDBCursorDirection::PrevUnique => false,
+        }
+    }
+
+    /// Moves the cursor to the next record, once its request is processed.
+    /// https://w3c.github.io/IndexedDB/#iterate-a-cursor
+    pub fn iterate(
+        &self,
+        key: Option<IndexedDBKeyType>,
+        primary_key: Option<IndexedDBKeyType>,
+        count: u32,
+    ) {
+        let iteration = CursorIteration {
+            store: self.object_store.name(),
+            index: self.index.as_ref().map(|index| index.name()),
+            range: Some(self.range.clone()),
+            direction: self.direction,
+            position: self.position.borrow().clone(),
+            object_store_position: self.object_store_position.borrow().clone(),
+            key,
+            primary_key,
+            count,
+            key_only: self.key_only,
+        };
+        let request = self.request.get().expect("Cursors have a request");
+        request.set_pending();
+        request.execute_async(AsyncOperation::Iterate(iteration), ResultConversion::Cursor);
+    }
+
+    /// Updates the cursor with the record it was moved to,
+    /// returns whether there was one.
+    pub fn iterated(
+        &self,
+        record: Option<IndexedDBRecord>,
+        injected_key_path: Option<&str>,
+    ) -> Fallible<bool> {
+        let record = match record {
+            Some(record) => record,
+            None => {
+                *self.position.borrow_mut() = None;
+                *self.object_store_position.borrow_mut() = None;
+                *self.key.borrow_mut() = None;
+                *self.primary_key.borrow_mut() = None;
+                self.value.set(UndefinedValue());
+                return Ok(false);
+            },
+        };
+
+        *self.position.borrow_mut() = Some(record.key.clone());
+        if self.index.is_some() {
+            *self.object_store_position.borrow_mut() = Some(record.primary_key.clone());
+        }
+        *self.key.borrow_mut() = Some(record.key.clone());
+        *self.primary_key.borrow_mut() = Some(record.primary_key.clone());
+        if !self.key_only {
+            let global = self.global();
+            let cx = global.get_cx();
+            rooted!(in(*cx) let mut value = UndefinedValue());
+            record_value_to_jsval(&global, record, injected_key_path, value.handle_mut())?;
+            self.value.set(value.get());
+        }
+        self.got_value.set(true);
+        Ok(true)
+    }
+
+    /// The checks shared by `update()` and `delete()`.
+    fn check_writable(&self) -> ErrorResult {
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+        if self.transaction.mode() == IndexedDBTxnMode::Readonly {
+            return Err(Error::ReadOnly);
+        }
+        if self.source_deleted() {
+            return Err(Error::InvalidState);
+        }
+        if !self.got_value.get() || self.key_only {
+            return Err(Error::InvalidState);
+        }
+        Ok(())
+    }
+
+    fn key_to_jsval(cx: JSContext, key: &Option<IndexedDBKeyType>) -> JSVal {
+        rooted!(in(*cx) let mut result = UndefinedValue());
+        if let Some(ref key) = *key {
+            key_type_to_jsval(cx, key, result.handle_mut());
+        }
+        result.get()
+    }
+}
+
+impl IDBCursorMethods for IDBCursor {
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-source
+    fn Source(&self) -> IDBObjectStoreOrIDBIndex {
+        match self.index {
+            Some(ref index) => IDBObjectStoreOrIDBIndex::IDBIndex(DomRoot::from_ref(&**index)),
+            None => IDBObjectStoreOrIDBIndex::IDBObjectStore(self.object_store()),
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-direction
+    fn Direction(&self) -> IDBCursorDirection {
+        match self.direction {
+            IndexedDBCursorDirection::Next => IDBCursorDirection::Next,
+            IndexedDBCursorDirection::NextUnique => IDBCursorDirection::Nextunique,
+            IndexedDBCursorDirection::Prev => IDBCursorDirection::Prev,
+            IndexedDBCursorDirection::PrevUnique => IDBCursorDirection::Prevunique,
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-key
+    fn Key(&self, cx: JSContext) -> JSVal {
+        IDBCursor::key_to_jsval(cx, &self.key.borrow())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-primarykey
+    fn PrimaryKey(&self, cx: JSContext) -> JSVal {
+        IDBCursor::key_to_jsval(cx, &self.primary_key.borrow())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-request
+    fn Request(&self) -> DomRoot<IDBRequest> {
+        self.request.get().expect("Cursors have a request")
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-advance
+    fn Advance(&self, count: u32) -> ErrorResult {
+        // Step 1.
+        if count == 0 {
+            return Err(Error::Type("The count must not be 0".to_owned()));
+        }
+
+        // Step 3.
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+
+        // Step 4.
+        if self.source_deleted() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 5.
+        if !self.got_value.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 6-9.
+        self.got_value.set(false);
+        self.iterate(None, None, count);
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-continue
+    fn Continue(&self, cx: JSContext, key: HandleValue) -> ErrorResult {
+        // Step 2.
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+
+        // Step 3.
+        if self.source_deleted() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 4.
+        if !self.got_value.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 5.
+        let key = if key.is_undefined() {
+            None
+        } else {
+            let key = convert_value_to_key(cx, key)?;
+            if let Some(ref position) = *self.position.borrow() {
+                let beyond_position = if self.is_forward() {
+                    key > *position
+                } else {
+                    key < *position
+                };
+                if !beyond_position {
+                    return Err(Error::Data);
+                }
+            }
+            Some(key)
+        };
+
+        // Steps 6-9.
+        self.got_value.set(false);
+        self.iterate(key, None, 1);
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-continueprimarykey
+    fn ContinuePrimaryKey(
+        &self,
+        cx: JSContext,
+        key: HandleValue,
+        primary_key: HandleValue,
+    ) -> ErrorResult {
+        // Step 2.
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+
+        // Step 3.
+        if self.source_deleted() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 4-5.
+        if self.index.is_none() {
+            return Err(Error::InvalidAccess);
+        }
+        match self.direction {
+            IndexedDBCursorDirection::Next | IndexedDBCursorDirection::Prev => {},
+            IndexedDBCursorDirection::NextUnique | IndexedDBCursorDirection::PrevUnique => {
+                return Err(Error::InvalidAccess);
+            },
+        }
+
+        // Step 6.
+        if !self.got_value.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 7-10.
+        let key = convert_value_to_key(cx, key)?;
+        let primary_key = convert_value_to_key(cx, primary_key)?;
+
+        // Steps 11-12.
+        let position = self.position.borrow().clone();
+        let object_store_position = self.object_store_position.borrow().clone();
+        if let (Some(position), Some(object_store_position)) = (position, object_store_position) {
+            let forward = self.is_forward();
+            let (before, after) = if forward {
+                (key < position, primary_key <= object_store_position)
+            } else {
+                (key > position, primary_key >= object_store_position)
+            };
+            if before || (key == position && after) {
+                return Err(Error::Data);
+            }
+        }
+
+        // Steps 13-16.
+        self.got_value.set(false);
+        self.iterate(Some(key), Some(primary_key), 1);
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-update
+    fn Update(&self, cx: JSContext, value: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 2-6.
+        self.check_writable()?;
+
+        // Steps 7-8.
+        rooted!(in(*cx) let mut clone = UndefinedValue());
+        let serialized = self
+            .object_store
+            .clone_value(cx, value, clone.handle_mut())?;
+
+        // Step 9.
+        let primary_key = self.primary_key.borrow().clone();
+        if let Some(key_path) = self.object_store.key_path() {
+            match extract_key(cx, clone.handle(), &key_path)? {
+                ExtractionResult::Key(ref key) if Some(key) == primary_key.as_ref() => {},
+                _ => return Err(Error::Data),
+            }
+        }
+
+        // Step 10.
+        self.object_store.put_record(
+            RequestSource::Cursor(self),
+            cx,
+            clone.handle(),
+            serialized,
+            primary_key,
+            true,
+        )
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-delete
+    fn Delete(&self) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 2-6.
+        self.check_writable()?;
+
+        // Steps 7-8.
+        let primary_key = self
+            .primary_key
+            .borrow()
+            .clone()
+            .expect("Cursors with a value have a primary key");
+        let operation = AsyncOperation::Delete {
+            store: self.object_store.name(),
+            range: IndexedDBKeyRange::only(primary_key),
+        };
+        Ok(self.object_store.execute(
+            RequestSource::Cursor(self),
+            operation,
+            ResultConversion::Undefined,
+        ))
+    }
+}
@@ -0,0 +1,58 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::IDBCursorWithValueBinding::IDBCursorWithValueMethods;
+use crate::dom::bindings::reflector::reflect_dom_object;
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbcursor::IDBCursor;
+use crate::dom::idbindex::IDBIndex;
+use crate::dom::idbobjectstore::IDBObjectStore;
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsval::JSVal;
+use net_traits::indexeddb_thread::{IndexedDBCursorDirection, IndexedDBKeyRange};
+
+#[dom_struct]
+pub struct IDBCursorWithValue {
+    cursor: IDBCursor,
+}
+
+impl IDBCursorWithValue {
+    fn new_inherited(
+        object_store: &IDBObjectStore,
+        index: Option<&IDBIndex>,
+        direction: IndexedDBCursorDirection,
+        range: IndexedDBKeyRange,
+    ) -> IDBCursorWithValue {
+        IDBCursorWithValue {
+            cursor: IDBCursor::new_inherited(object_store, index, direction, range, false),
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        object_store: &IDBObjectStore,
+        index: Option<&IDBIndex>,
+        direction: IndexedDBCursorDirection,
+        range: IndexedDBKeyRange,
+    ) -> DomRoot<IDBCursorWithValue> {
+        reflect_dom_object(
+            Box::new(IDBCursorWithValue::new_inherited(
+                object_store,
+                index,
+                direction,
+                range,
+            )),
+            global,
+        )
+    }
+}
+
+impl IDBCursorWithValueMethods for IDBCursorWithValue {
+    // https://w3c.github.io/IndexedDB/#dom-idbcursorwithvalue-value
+    fn Value(&self, _cx: JSContext) -> JSVal {
+        self.cursor.value()
+    }
+}
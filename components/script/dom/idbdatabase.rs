@@ -0,0 +1,365 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::IDBDatabaseBinding::{
+    IDBDatabaseMethods, IDBObjectStoreParameters,
+};
+use crate::dom::bindings::codegen::Bindings::IDBTransactionBinding::IDBTransactionMode;
+use crate::dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::domstringlist::DOMStringList;
+use crate::dom::event::{Event, EventBubbles, EventCancelable};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbobjectstore::IDBObjectStore;
+use crate::dom::idbtransaction::IDBTransaction;
+use crate::dom::idbversionchangeevent::IDBVersionChangeEvent;
+use crate::indexed_db::{backend_error, is_valid_key_path, key_path_from_union};
+use dom_struct::dom_struct;
+use net_traits::indexeddb_thread::{AsyncOperation, ConnectionEvent, DatabaseInfo};
+use net_traits::indexeddb_thread::{
+    IndexedDBThreadMsg, IndexedDBTxnMode, KeyPath, ObjectStoreInfo,
+};
+use net_traits::IpcSend;
+use std::cell::Cell;
+use std::collections::HashMap;
+
+#[dom_struct]
+pub struct IDBDatabase {
+    eventtarget: EventTarget,
+    /// The id of this connection in the IndexedDB thread.
+    connection: u64,
+    /// The metadata of the database, as seen by this connection.
+    info: DomRefCell<DatabaseInfo>,
+    /// The metadata from before the running upgrade transaction,
+    /// restored if it is aborted.
+    previous_info: DomRefCell<Option<DatabaseInfo>>,
+    /// https://w3c.github.io/IndexedDB/#connection-close-pending-flag
+    close_pending: Cell<bool>,
+    upgrade_transaction: MutNullableDom<IDBTransaction>,
+    /// The transactions of this connection that haven't finished, by serial number.
+    transactions: DomRefCell<HashMap<u64, Dom<IDBTransaction>>>,
+    next_serial: Cell<u64>,
+}
+
+impl IDBDatabase {
+    fn new_inherited(connection: u64, info: DatabaseInfo) -> IDBDatabase {
+        IDBDatabase {
+            eventtarget: EventTarget::new_inherited(),
+            connection,
+            info: DomRefCell::new(info),
+            previous_info: DomRefCell::new(None),
+            close_pending: Cell::new(false),
+            upgrade_transaction: Default::default(),
+            transactions: Default::default(),
+            next_serial: Cell::new(1),
+        }
+    }
+
+    pub fn new(global: &GlobalScope, connection: u64, info: DatabaseInfo) -> DomRoot<IDBDatabase> {
+        reflect_dom_object(
+            Box::new(IDBDatabase::new_inherited(connection, info)),
+            global,
+        )
+    }
+
+    pub fn connection(&self) -> u64 {
+        self.connection
+    }
+
+    pub fn is_close_pending(&self) -> bool {
+        self.close_pending.get()
+    }
+
+    pub fn object_store_info(&self, name: &str) -> Option<ObjectStoreInfo> {
+        self.info
+            .borrow()
+            .object_stores
+            .iter()
+            .find(|store| store.name == name)
+            .cloned()
+    }
+
+    /// Updates the metadata of an object store, after a change made by the upgrade transaction.
+    pub fn update_object_store_info<F: FnOnce(&mut ObjectStoreInfo)>(&self, name: &str, f: F) {
+        let mut info = self.info.borrow_mut();
+        if let Some(store) = info
+            .object_stores
+            .iter_mut()
+            .find(|store| store.name == name)
+        {
+            f(store);
+        }
+    }
+
+    /// Starts the upgrade transaction of this connection,
+    /// whose metadata is already that of the new version.
+    /// https://w3c.github.io/IndexedDB/#run-an-upgrade-transaction
+    pub fn start_upgrade(&self, old_version: u64) -> DomRoot<IDBTransaction> {
+        let mut previous_info = self.info.borrow().clone();
+        previous_info.version = old_version;
+        *self.previous_info.borrow_mut() = Some(previous_info);
+
+        let transaction = IDBTransaction::new(
+            &self.global(),
+            self,
+            0,
+            IndexedDBTxnMode::Versionchange,
+            vec![],
+        );
+        self.upgrade_transaction.set(Some(&transaction));
+        self.transactions
+            .borrow_mut()
+            .insert(0, Dom::from_ref(&*transaction));
+        transaction
+    }
+
+    pub fn finish_upgrade(&self) {
+        self.upgrade_transaction.set(None);
+        *self.previous_info.borrow_mut() = None;
+    }
+
+    /// https://w3c.github.io/IndexedDB/#abort-an-upgrade-transaction
+    pub fn abort_upgrade(&self) {
+        self.upgrade_transaction.set(None);
+        // Steps 2-3.
+        if let Some(info) = self.previous_info.borrow_mut().take() {
+            *self.info.borrow_mut() = info;
+        }
+        // Step 4, the IndexedDB thread closes the connection.
+        self.close_pending.set(true);
+        self.global().indexeddb().remove_connection(self);
+    }
+
+    /// https://w3c.github.io/IndexedDB/#close-a-database-connection
+    pub fn close(&self) {
+        if self.close_pending.get() {
+            return;
+        }
+        self.close_pending.set(true);
+        let _ = self
+            .global()
+            .resource_threads()
+            .send(IndexedDBThreadMsg::Close(self.connection));
+        self.global().indexeddb().remove_connection(self);
+    }
+
+    pub fn handle_connection_event(&self, event: ConnectionEvent) {
+        match event {
+            ConnectionEvent::VersionChange {
+                old_version,
+                new_version,
+            } => {
+                // https://w3c.github.io/IndexedDB/#open-a-database step 10.3
+                if self.close_pending.get() {
+                    return;
+                }
+                let event = IDBVersionChangeEvent::new(
+                    &self.global(),
+                    atom!("versionchange"),
+                    EventBubbles::DoesNotBubble,
+                    EventCancelable::NotCancelable,
+                    old_version,
+                    new_version,
+                );
+                event.upcast::<Event>().fire(self.upcast());
+            },
+            ConnectionEvent::TransactionFinished { serial, result } => {
+                let transaction = self
+                    .transactions
+                    .borrow_mut()
+                    .remove(&serial)
+                    .map(|transaction| DomRoot::from_ref(&*transaction));
+                if let Some(transaction) = transaction {
+                    transaction.finished(result);
+                }
+            },
+        }
+    }
+}
+
+impl IDBDatabaseMethods for IDBDatabase {
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-name
+    fn Name(&self) -> DOMString {
+        DOMString::from(self.info.borrow().name.clone())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-version
+    fn Version(&self) -> u64 {
+        self.info.borrow().version
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-objectstorenames
+    fn ObjectStoreNames(&self) -> DomRoot<DOMStringList> {
+        let mut names: Vec<DOMString> = self
+            .info
+            .borrow()
+            .object_stores
+            .iter()
+            .map(|store| DOMString::from(store.name.clone()))
+            .collect();
+        names.sort();
+        DOMStringList::new(&self.global(), names)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-transaction
+    fn Transaction(
+        &self,
+        store_names: StringOrStringSequence,
+        mode: IDBTransactionMode,
+    ) -> Fallible<DomRoot<IDBTransaction>> {
+        // Step 1.
+        if self.upgrade_transaction.get().is_some() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 2.
+        if self.close_pending.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 3-4.
+        let mut scope = match store_names {
+            StringOrStringSequence::String(name) => vec![name],
+            StringOrStringSequence::StringSequence(names) => names,
+        };
+        scope.sort();
+        scope.dedup();
+        if scope
+            .iter()
+            .any(|name| self.object_store_info(name).is_none())
+        {
+            return Err(Error::NotFound);
+        }
+
+        // Step 5.
+        if scope.is_empty() {
+            return Err(Error::InvalidAccess);
+        }
+
+        // Step 6.
+        let mode = match mode {
+            IDBTransactionMode::Readonly => IndexedDBTxnMode::Readonly,
+            IDBTransactionMode::Readwrite => IndexedDBTxnMode::Readwrite,
+            IDBTransactionMode::Versionchange => {
+                return Err(Error::Type("Invalid transaction mode".to_owned()));
+            },
+        };
+
+        // Steps 7-8.
+        let serial = self.next_serial.get();
+        self.next_serial.set(serial + 1);
+        let transaction = IDBTransaction::new(&self.global(), self, serial, mode, scope);
+        self.transactions
+            .borrow_mut()
+            .insert(serial, Dom::from_ref(&*transaction));
+        transaction.register();
+
+        // Step 9.
+        Ok(transaction)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-close
+    fn Close(&self) {
+        self.close();
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-createobjectstore
+    fn CreateObjectStore(
+        &self,
+        name: DOMString,
+        options: &IDBObjectStoreParameters,
+    ) -> Fallible<DomRoot<IDBObjectStore>> {
+        // Steps 2-4.
+        let transaction = self.upgrade_transaction.get().ok_or(Error::InvalidState)?;
+
+        // Step 5.
+        if !transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+
+        // Steps 6-7.
+        let key_path = options.keyPath.as_ref().map(key_path_from_union);
+        if let Some(ref key_path) = key_path {
+            if !is_valid_key_path(key_path) {
+                return Err(Error::Syntax);
+            }
+        }
+
+        // Step 8.
+        if self.object_store_info(&name).is_some() {
+            return Err(Error::Constraint);
+        }
+
+        // Step 9.
+        if options.autoIncrement {
+            match key_path {
+                Some(KeyPath::String(ref path)) if path.is_empty() => {
+                    return Err(Error::InvalidAccess);
+                },
+                Some(KeyPath::Sequence(_)) => return Err(Error::InvalidAccess),
+                _ => {},
+            }
+        }
+
+        // Step 10.
+        let info = ObjectStoreInfo {
+            name: name.to_string(),
+            key_path,
+            auto_increment: options.autoIncrement,
+            indexes: vec![],
+        };
+        transaction
+            .execute_sync(AsyncOperation::CreateObjectStore(info.clone()))
+            .map_err(backend_error)?;
+        self.info.borrow_mut().object_stores.push(info);
+
+        // Step 11.
+        Ok(transaction.object_store_handle(name))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-deleteobjectstore
+    fn DeleteObjectStore(&self, name: DOMString) -> ErrorResult {
+        // Steps 2-4.
+        let transaction = self.upgrade_transaction.get().ok_or(Error::InvalidState)?;
+
+        // Step 5.
+        if !transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+
+        // Step 6.
+        if self.object_store_info(&name).is_none() {
+            return Err(Error::NotFound);
+        }
+
+        // Steps 7-8.
+        transaction
+            .execute_sync(AsyncOperation::DeleteObjectStore(name.to_string()))
+            .map_err(backend_error)?;
+        self.info
+            .borrow_mut()
+            .object_stores
+            .retain(|store| *store.name != *name);
+        transaction.object_store_deleted(&name);
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onclose
+    event_handler!(close, GetOnclose, SetOnclose);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onversionchange
+    event_handler!(versionchange, GetOnversionchange, SetOnversionchange);
+}
@@ -0,0 +1,170 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::IDBFactoryBinding::{
+    IDBDatabaseInfo, IDBFactoryMethods,
+};
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::refcounted::TrustedPromise;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbdatabase::IDBDatabase;
+use crate::dom::idbopendbrequest::IDBOpenDBRequest;
+use crate::dom::promise::Promise;
+use crate::indexed_db::convert_value_to_key;
+use crate::realms::InRealm;
+use crate::script_runtime::JSContext;
+use crate::task_source::{TaskSource, TaskSourceName};
+use dom_struct::dom_struct;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use js::rust::HandleValue;
+use net_traits::indexeddb_thread::IndexedDBThreadMsg;
+use net_traits::IpcSend;
+use std::cmp::Ordering;
+use std::ptr;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct IDBFactory {
+    reflector_: Reflector,
+    /// The connections opened through this factory that haven't been closed.
+    connections: DomRefCell<Vec<Dom<IDBDatabase>>>,
+}
+
+impl IDBFactory {
+    fn new_inherited() -> IDBFactory {
+        IDBFactory {
+            reflector_: Reflector::new(),
+            connections: Default::default(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<IDBFactory> {
+        reflect_dom_object(Box::new(IDBFactory::new_inherited()), global)
+    }
+
+    pub fn add_connection(&self, connection: &IDBDatabase) {
+        self.connections
+            .borrow_mut()
+            .push(Dom::from_ref(connection));
+    }
+
+    pub fn remove_connection(&self, connection: &IDBDatabase) {
+        self.connections
+            .borrow_mut()
+            .retain(|open| !ptr::eq(&**open, connection));
+    }
+
+    /// Closes the open connections, when the global is torn down.
+    pub fn close_all_connections(&self) {
+        let connections: Vec<DomRoot<IDBDatabase>> = self
+            .connections
+            .borrow()
+            .iter()
+            .map(|connection| DomRoot::from_ref(&**connection))
+            .collect();
+        for connection in connections {
+            connection.close();
+        }
+    }
+}
+
+impl IDBFactoryMethods for IDBFactory {
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-open
+    fn Open(&self, name: DOMString, version: Option<u64>) -> Fallible<DomRoot<IDBOpenDBRequest>> {
+        // Step 1.
+        if version == Some(0) {
+            return Err(Error::Type("The version must not be 0".to_owned()));
+        }
+
+        // Steps 2-3.
+        let global = self.global();
+        if !global.origin().is_tuple() {
+            return Err(Error::Security);
+        }
+
+        // Steps 4-6.
+        let request = IDBOpenDBRequest::new(&global);
+        request.open(name.to_string(), version);
+        Ok(request)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-deletedatabase
+    fn DeleteDatabase(&self, name: DOMString) -> Fallible<DomRoot<IDBOpenDBRequest>> {
+        // Steps 1-2.
+        let global = self.global();
+        if !global.origin().is_tuple() {
+            return Err(Error::Security);
+        }
+
+        // Steps 3-5.
+        let request = IDBOpenDBRequest::new(&global);
+        request.delete_database(name.to_string());
+        Ok(request)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-databases
+    fn Databases(&self, comp: InRealm) -> Rc<Promise> {
+        let global = self.global();
+        let promise = Promise::new_in_current_realm(&global, comp);
+
+        // Steps 2-3.
+        if !global.origin().is_tuple() {
+            promise.reject_error(Error::Security);
+            return promise;
+        }
+
+        // Steps 5-6.
+        let (sender, receiver) = ipc::channel().unwrap();
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DatabaseAccess);
+        let mut trusted = Some(TrustedPromise::new(promise.clone()));
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let trusted = match trusted.take() {
+                    Some(trusted) => trusted,
+                    None => return,
+                };
+                let databases: Vec<(String, u64)> = message.to().unwrap_or_default();
+                let _ = task_source.queue_with_canceller(
+                    task!(resolve_indexeddb_databases: move || {
+                        let infos: Vec<IDBDatabaseInfo> = databases
+                            .into_iter()
+                            .map(|(name, version)| IDBDatabaseInfo {
+                                name: Some(DOMString::from(name)),
+                                version: Some(version),
+                            })
+                            .collect();
+                        trusted.root().resolve_native(&infos);
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+        let msg = IndexedDBThreadMsg::Databases(sender, global.origin().immutable().clone());
+        let _ = global.resource_threads().send(msg);
+
+        // Step 7.
+        promise
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-cmp
+    fn Cmp(&self, cx: JSContext, first: HandleValue, second: HandleValue) -> Fallible<i16> {
+        // Steps 1-4.
+        let first = convert_value_to_key(cx, first)?;
+        let second = convert_value_to_key(cx, second)?;
+
+        // Step 5.
+        Ok(match first.cmp(&second) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        })
+    }
+}
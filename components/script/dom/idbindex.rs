@@ -0,0 +1,261 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorDirection;
+use crate::dom::bindings::codegen::Bindings::IDBIndexBinding::IDBIndexMethods;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbobjectstore::IDBObjectStore;
+use crate::dom::idbrequest::{IDBRequest, RequestSource, ResultConversion};
+use crate::indexed_db::{convert_value_to_key_range, key_path_to_jsval};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::HandleValue;
+use net_traits::indexeddb_thread::{AsyncOperation, IndexInfo};
+use std::cell::Cell;
+
+#[dom_struct]
+pub struct IDBIndex {
+    reflector_: Reflector,
+    object_store: Dom<IDBObjectStore>,
+    info: IndexInfo,
+    /// Whether the index was deleted by the upgrade transaction.
+    deleted: Cell<bool>,
+}
+
+impl IDBIndex {
+    fn new_inherited(object_store: &IDBObjectStore, info: IndexInfo) -> IDBIndex {
+        IDBIndex {
+            reflector_: Reflector::new(),
+            object_store: Dom::from_ref(object_store),
+            info,
+            deleted: Cell::new(false),
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        object_store: &IDBObjectStore,
+        info: IndexInfo,
+    ) -> DomRoot<IDBIndex> {
+        reflect_dom_object(
+            Box::new(IDBIndex::new_inherited(object_store, info)),
+            global,
+        )
+    }
+
+    pub fn name(&self) -> String {
+        self.info.name.clone()
+    }
+
+    pub fn object_store(&self) -> DomRoot<IDBObjectStore> {
+        DomRoot::from_ref(&*self.object_store)
+    }
+
+    /// Whether the index, or its object store, was deleted by the upgrade transaction.
+    pub fn is_deleted(&self) -> bool {
+        self.deleted.get() || self.object_store.is_deleted()
+    }
+
+    pub fn set_deleted(&self) {
+        self.deleted.set(true);
+    }
+
+    /// The checks shared by the operations of the index.
+    fn check_readable(&self) -> ErrorResult {
+        if self.deleted.get() {
+            return Err(Error::InvalidState);
+        }
+        self.object_store.check_readable()
+    }
+
+    fn execute(
+        &self,
+        operation: AsyncOperation,
+        conversion: ResultConversion,
+    ) -> DomRoot<IDBRequest> {
+        self.object_store
+            .execute(RequestSource::Index(self), operation, conversion)
+    }
+}
+
+impl IDBIndexMethods for IDBIndex {
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-name
+    fn Name(&self) -> DOMString {
+        DOMString::from(self.name())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-objectstore
+    fn ObjectStore(&self) -> DomRoot<IDBObjectStore> {
+        self.object_store()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-keypath
+    fn KeyPath(&self, cx: JSContext) -> JSVal {
+        rooted!(in(*cx) let mut result = UndefinedValue());
+        key_path_to_jsval(cx, &self.info.key_path, result.handle_mut());
+        result.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-multientry
+    fn MultiEntry(&self) -> bool {
+        self.info.multi_entry
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-unique
+    fn Unique(&self) -> bool {
+        self.info.unique
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-get
+    fn Get(&self, cx: JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_readable()?;
+
+        // Step 6.
+        let range = convert_value_to_key_range(cx, query, true)?;
+
+        // Step 7.
+        let operation = AsyncOperation::Get {
+            store: self.object_store.name(),
+            index: Some(self.name()),
+            range,
+            key_only: false,
+        };
+        Ok(self.execute(operation, ResultConversion::Value))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getkey
+    fn GetKey(&self, cx: JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_readable()?;
+
+        // Step 6.
+        let range = convert_value_to_key_range(cx, query, true)?;
+
+        // Step 7.
+        let operation = AsyncOperation::Get {
+            store: self.object_store.name(),
+            index: Some(self.name()),
+            range,
+            key_only: true,
+        };
+        Ok(self.execute(operation, ResultConversion::PrimaryKey))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getall
+    fn GetAll(
+        &self,
+        cx: JSContext,
+        query: HandleValue,
+        count: Option<u32>,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_readable()?;
+
+        // Step 6.
+        let range = convert_value_to_key_range(cx, query, false)?;
+
+        // Step 7.
+        let operation = AsyncOperation::GetAll {
+            store: self.object_store.name(),
+            index: Some(self.name()),
+            range: Some(range),
+            count: count.filter(|count| *count > 0),
+            key_only: false,
+        };
+        Ok(self.execute(operation, ResultConversion::Values))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getallkeys
+    fn GetAllKeys(
+        &self,
+        cx: JSContext,
+        query: HandleValue,
+        count: Option<u32>,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_readable()?;
+
+        // Step 6.
+        let range = convert_value_to_key_range(cx, query, false)?;
+
+        // Step 7.
+        let operation = AsyncOperation::GetAll {
+            store: self.object_store.name(),
+            index: Some(self.name()),
+            range: Some(range),
+            count: count.filter(|count| *count > 0),
+            key_only: true,
+        };
+        Ok(self.execute(operation, ResultConversion::PrimaryKeys))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-count
+    fn Count(&self, cx: JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_readable()?;
+
+        // Step 6.
+        let range = convert_value_to_key_range(cx, query, false)?;
+
+        // Step 7.
+        let operation = AsyncOperation::Count {
+            store: self.object_store.name(),
+            index: Some(self.name()),
+            range: Some(range),
+        };
+        Ok(self.execute(operation, ResultConversion::Count))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-opencursor
+    fn OpenCursor(
+        &self,
+        cx: JSContext,
+        query: HandleValue,
+        direction: IDBCursorDirection,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_readable()?;
+
+        // Step 6.
+        let range = convert_value_to_key_range(cx, query, false)?;
+
+        // Steps 7-11.
+        Ok(self.object_store.open_cursor(
+            RequestSource::Index(self),
+            Some(self),
+            range,
+            direction,
+            false,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-openkeycursor
+    fn OpenKeyCursor(
+        &self,
+        cx: JSContext,
+        query: HandleValue,
+        direction: IDBCursorDirection,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_readable()?;
+
+        // Step 6.
+        let range = convert_value_to_key_range(cx, query, false)?;
+
+        // Steps 7-11.
+        Ok(self.object_store.open_cursor(
+            RequestSource::Index(self),
+            Some(self),
+            range,
+            direction,
+            true,
+        ))
+    }
+}
@@ -0,0 +1,728 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorDirection;
+use crate::dom::bindings::codegen::Bindings::IDBObjectStoreBinding::{
+    IDBIndexParameters, IDBObjectStoreMethods,
+};
+use crate::dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::domexception::DOMErrorName;
+use crate::dom::domstringlist::DOMStringList;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbcursor::IDBCursor;
+use crate::dom::idbcursorwithvalue::IDBCursorWithValue;
+use crate::dom::idbindex::IDBIndex;
+use crate::dom::idbrequest::{IDBRequest, RequestSource, ResultConversion};
+use crate::dom::idbtransaction::IDBTransaction;
+use crate::indexed_db::{backend_error, can_inject_key, convert_value_to_key};
+use crate::indexed_db::{convert_value_to_key_range, deserialize_value, extract_index_keys};
+use crate::indexed_db::{extract_key, is_valid_key_path, key_path_from_union, key_path_to_jsval};
+use crate::indexed_db::{record_value_to_jsval, serialize_value, ExtractionResult};
+use crate::script_runtime::JSContext;
+use dom_struct::dom_struct;
+use js::jsval::{JSVal, NullValue, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use net_traits::indexeddb_thread::{AsyncOperation, AsyncResponse, BackendError};
+use net_traits::indexeddb_thread::{IndexInfo, IndexedDBCursorDirection, IndexedDBKeyRange};
+use net_traits::indexeddb_thread::{IndexedDBKeyType, IndexedDBTxnMode, KeyPath, ObjectStoreInfo};
+use std::cell::Cell;
+use std::collections::HashMap;
+
+#[dom_struct]
+pub struct IDBObjectStore {
+    reflector_: Reflector,
+    /// The metadata of the object store, as seen by the transaction of this handle.
+    info: DomRefCell<ObjectStoreInfo>,
+    transaction: Dom<IDBTransaction>,
+    /// The index handles of this object store handle, by name.
+    indexes: DomRefCell<HashMap<DOMString, Dom<IDBIndex>>>,
+    /// Whether the object store was deleted by the upgrade transaction.
+    deleted: Cell<bool>,
+}
+
+impl IDBObjectStore {
+    fn new_inherited(info: ObjectStoreInfo, transaction: &IDBTransaction) -> IDBObjectStore {
+        IDBObjectStore {
+            reflector_: Reflector::new(),
+            info: DomRefCell::new(info),
+            transaction: Dom::from_ref(transaction),
+            indexes: Default::default(),
+            deleted: Cell::new(false),
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        name: DOMString,
+        transaction: &IDBTransaction,
+    ) -> DomRoot<IDBObjectStore> {
+        let info = transaction
+            .db()
+            .object_store_info(&name)
+            .expect("Object store handles are only created for existing object stores");
+        reflect_dom_object(
+            Box::new(IDBObjectStore::new_inherited(info, transaction)),
+            global,
+        )
+    }
+
+    pub fn name(&self) -> String {
+        self.info.borrow().name.clone()
+    }
+
+    pub fn key_path(&self) -> Option<KeyPath> {
+        self.info.borrow().key_path.clone()
+    }
+
+    pub fn transaction(&self) -> DomRoot<IDBTransaction> {
+        DomRoot::from_ref(&*self.transaction)
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted.get()
+    }
+
+    pub fn set_deleted(&self) {
+        self.deleted.set(true);
+    }
+
+    /// The key path the generated keys of this object store are injected at.
+    pub fn injected_key_path(&self) -> Option<String> {
+        let info = self.info.borrow();
+        match info.key_path {
+            Some(KeyPath::String(ref path)) if info.auto_increment => Some(path.clone()),
+            _ => None,
+        }
+    }
+
+    /// The checks shared by the operations that read from the object store.
+    pub fn check_readable(&self) -> ErrorResult {
+        if self.deleted.get() {
+            return Err(Error::InvalidState);
+        }
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+        Ok(())
+    }
+
+    /// The checks shared by the operations that write to the object store.
+    pub fn check_writable(&self) -> ErrorResult {
+        self.check_readable()?;
+        if self.transaction.mode() == IndexedDBTxnMode::Readonly {
+            return Err(Error::ReadOnly);
+        }
+        Ok(())
+    }
+
+    /// Creates a request against the transaction of this object store, and executes it.
+    pub fn execute(
+        &self,
+        source: RequestSource,
+        operation: AsyncOperation,
+        conversion: ResultConversion,
+    ) -> DomRoot<IDBRequest> {
+        let request = IDBRequest::new(&self.global(), source, &self.transaction);
+        request.execute_async(operation, conversion);
+        request
+    }
+
+    /// https://w3c.github.io/IndexedDB/#dom-idbobjectstore-opencursor
+    pub fn open_cursor(
+        &self,
+        source: RequestSource,
+        index: Option<&IDBIndex>,
+        range: IndexedDBKeyRange,
+        direction: IDBCursorDirection,
+        key_only: bool,
+    ) -> DomRoot<IDBRequest> {
+        let global = self.global();
+        let direction = match direction {
+            IDBCursorDirection::Next => IndexedDBCursorDirection::Next,
+            IDBCursorDirection::Nextunique => IndexedDBCursorDirection::NextUnique,
+            IDBCursorDirection::Prev => IndexedDBCursorDirection::Prev,
+            IDBCursorDirection::Prevunique => IndexedDBCursorDirection::PrevUnique,
+        };
+        let cursor = if key_only {
+            IDBCursor::new(&global, self, index, direction, range)
+        } else {
+            DomRoot::upcast(IDBCursorWithValue::new(
+                &global, self, index, direction, range,
+            ))
+        };
+        let request = IDBRequest::new(&global, source, &self.transaction);
+        request.set_cursor(&cursor);
+        cursor.set_request(&request);
+        cursor.iterate(None, None, 1);
+        request
+    }
+
+    /// Clones a value to be stored in this object store, with the transaction inactive
+    /// so that it can not be used by the getters run by the serialization.
+    /// Returns the serialized value, and sets `clone` to its deserialized copy.
+    pub fn clone_value(
+        &self,
+        cx: JSContext,
+        value: HandleValue,
+        clone: MutableHandleValue,
+    ) -> Fallible<Vec<u8>> {
+        self.transaction.deactivate();
+        let serialized = serialize_value(cx, value).and_then(|serialized| {
+            deserialize_value(&self.global(), serialized.clone(), clone)?;
+            Ok(serialized)
+        });
+        self.transaction.activate();
+        serialized
+    }
+
+    /// Stores a cloned value, updating the indexes of the object store.
+    /// https://w3c.github.io/IndexedDB/#store-a-record-into-an-object-store
+    pub fn put_record(
+        &self,
+        source: RequestSource,
+        cx: JSContext,
+        clone: HandleValue,
+        serialized: Vec<u8>,
+        key: Option<IndexedDBKeyType>,
+        overwrite: bool,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        let indexes = self.info.borrow().indexes.clone();
+        let mut index_keys = Vec::with_capacity(indexes.len());
+        for index in indexes {
+            let keys = extract_index_keys(cx, clone, &index.key_path, index.multi_entry)?;
+            index_keys.push((index.name, keys));
+        }
+        let operation = AsyncOperation::Put {
+            store: self.name(),
+            key,
+            value: serialized,
+            index_keys,
+            overwrite,
+        };
+        Ok(self.execute(source, operation, ResultConversion::Key))
+    }
+
+    /// https://w3c.github.io/IndexedDB/#add-or-put
+    fn put_or_add(
+        &self,
+        cx: JSContext,
+        value: HandleValue,
+        key: HandleValue,
+        overwrite: bool,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_writable()?;
+
+        let (key_path, auto_increment) = {
+            let info = self.info.borrow();
+            (info.key_path.clone(), info.auto_increment)
+        };
+        let key_given = !key.is_undefined();
+
+        // Step 6.
+        if key_path.is_some() && key_given {
+            return Err(Error::Data);
+        }
+
+        // Step 7.
+        if key_path.is_none() && !auto_increment && !key_given {
+            return Err(Error::Data);
+        }
+
+        // Step 8.
+        let mut key = if key_given {
+            Some(convert_value_to_key(cx, key)?)
+        } else {
+            None
+        };
+
+        // Steps 9-10.
+        rooted!(in(*cx) let mut clone = UndefinedValue());
+        let serialized = self.clone_value(cx, value, clone.handle_mut())?;
+
+        // Step 11.
+        if let Some(ref key_path) = key_path {
+            match extract_key(cx, clone.handle(), key_path)? {
+                ExtractionResult::Key(extracted) => key = Some(extracted),
+                ExtractionResult::Invalid => return Err(Error::Data),
+                ExtractionResult::Failure => {
+                    let injectable = match *key_path {
+                        KeyPath::String(ref path) => {
+                            auto_increment && can_inject_key(cx, clone.handle(), path)?
+                        },
+                        KeyPath::Sequence(_) => false,
+                    };
+                    if !injectable {
+                        return Err(Error::Data);
+                    }
+                },
+            }
+        }
+
+        // Step 12.
+        self.put_record(
+            RequestSource::ObjectStore(self),
+            cx,
+            clone.handle(),
+            serialized,
+            key,
+            overwrite,
+        )
+    }
+
+    fn index_handle(&self, info: IndexInfo) -> DomRoot<IDBIndex> {
+        let mut indexes = self.indexes.borrow_mut();
+        let index = indexes
+            .entry(DOMString::from(info.name.clone()))
+            .or_insert_with(|| Dom::from_ref(&*IDBIndex::new(&self.global(), self, info)));
+        DomRoot::from_ref(&**index)
+    }
+
+    /// The checks shared by `createIndex` and `deleteIndex`.
+    fn check_upgrade(&self) -> ErrorResult {
+        if self.transaction.mode() != IndexedDBTxnMode::Versionchange {
+            return Err(Error::InvalidState);
+        }
+        if self.deleted.get() {
+            return Err(Error::InvalidState);
+        }
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+        Ok(())
+    }
+
+    /// The primary keys of the records of this object store,
+    /// with the keys they contribute to an index with the given key path.
+    fn compute_index_entries(
+        &self,
+        cx: JSContext,
+        key_path: &KeyPath,
+        multi_entry: bool,
+    ) -> Fallible<Vec<(IndexedDBKeyType, Vec<IndexedDBKeyType>)>> {
+        let operation = AsyncOperation::GetAll {
+            store: self.name(),
+            index: None,
+            range: None,
+            count: None,
+            key_only: false,
+        };
+        let records = match self.transaction.execute_sync(operation) {
+            Ok(AsyncResponse::Records(records)) => records,
+            Ok(response) => unreachable!("Unexpected IndexedDB response {:?}", response),
+            Err(error) => return Err(backend_error(error)),
+        };
+
+        let global = self.global();
+        let injected_key_path = self.injected_key_path();
+        let mut entries = Vec::with_capacity(records.len());
+        for record in records {
+            let primary_key = record.primary_key.clone();
+            rooted!(in(*cx) let mut value = UndefinedValue());
+            record_value_to_jsval(
+                &global,
+                record,
+                injected_key_path.as_ref().map(|path| &**path),
+                value.handle_mut(),
+            )?;
+            let keys = extract_index_keys(cx, value.handle(), key_path, multi_entry)?;
+            entries.push((primary_key, keys));
+        }
+        Ok(entries)
+    }
+}
+
+impl IDBObjectStoreMethods for IDBObjectStore {
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-name
+    fn Name(&self) -> DOMString {
+        DOMString::from(self.name())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-keypath
+    fn KeyPath(&self, cx: JSContext) -> JSVal {
+        rooted!(in(*cx) let mut result = NullValue());
+        if let Some(ref key_path) = self.info.borrow().key_path {
+            key_path_to_jsval(cx, key_path, result.handle_mut());
+        }
+        result.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-indexnames
+    fn IndexNames(&self) -> DomRoot<DOMStringList> {
+        let mut names: Vec<DOMString> = self
+            .info
+            .borrow()
+            .indexes
+            .iter()
+            .map(|index| DOMString::from(index.name.clone()))
+            .collect();
+        names.sort();
+        DOMStringList::new(&self.global(), names)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-transaction
+    fn Transaction(&self) -> DomRoot<IDBTransaction> {
+        self.transaction()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-autoincrement
+    fn AutoIncrement(&self) -> bool {
+        self.info.borrow().auto_increment
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-put
+    fn Put(
+        &self,
+        cx: JSContext,
+        value: HandleValue,
+        key: HandleValue,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.put_or_add(cx, value, key, true)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-add
+    fn Add(
+        &self,
+        cx: JSContext,
+        value: HandleValue,
+        key: HandleValue,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.put_or_add(cx, value, key, false)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-delete
+    fn Delete(&self, cx: JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_writable()?;
+
+        // Step 6.
+        let range = convert_value_to_key_range(cx, query, true)?;
+
+        // Step 7.
+        let operation = AsyncOperation::Delete {
+            store: self.name(),
+            range,
+        };
+        Ok(self.execute(
+            RequestSource::ObjectStore(self),
+            operation,
+            ResultConversion::Undefined,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-clear
+    fn Clear(&self) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_writable()?;
+
+        // Step 6.
+        Ok(self.execute(
+            RequestSource::ObjectStore(self),
+            AsyncOperation::Clear(self.name()),
+            ResultConversion::Undefined,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-get
+    fn Get(&self, cx: JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-4.
+        self.check_readable()?;
+
+        // Step 5.
+        let range = convert_value_to_key_range(cx, query, true)?;
+
+        // Step 6.
+        let operation = AsyncOperation::Get {
+            store: self.name(),
+            index: None,
+            range,
+            key_only: false,
+        };
+        Ok(self.execute(
+            RequestSource::ObjectStore(self),
+            operation,
+            ResultConversion::Value,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getkey
+    fn GetKey(&self, cx: JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-4.
+        self.check_readable()?;
+
+        // Step 5.
+        let range = convert_value_to_key_range(cx, query, true)?;
+
+        // Step 6.
+        let operation = AsyncOperation::Get {
+            store: self.name(),
+            index: None,
+            range,
+            key_only: true,
+        };
+        Ok(self.execute(
+            RequestSource::ObjectStore(self),
+            operation,
+            ResultConversion::PrimaryKey,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getall
+    fn GetAll(
+        &self,
+        cx: JSContext,
+        query: HandleValue,
+        count: Option<u32>,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-4.
+        self.check_readable()?;
+
+        // Step 5.
+        let range = convert_value_to_key_range(cx, query, false)?;
+
+        // Step 6.
+        let operation = AsyncOperation::GetAll {
+            store: self.name(),
+            index: None,
+            range: Some(range),
+            count: count.filter(|count| *count > 0),
+            key_only: false,
+        };
+        Ok(self.execute(
+            RequestSource::ObjectStore(self),
+            operation,
+            ResultConversion::Values,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getallkeys
+    fn GetAllKeys(
+        &self,
+        cx: JSContext,
+        query: HandleValue,
+        count: Option<u32>,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-4.
+        self.check_readable()?;
+
+        // Step 5.
+        let range = convert_value_to_key_range(cx, query, false)?;
+
+        // Step 6.
+        let operation = AsyncOperation::GetAll {
+            store: self.name(),
+            index: None,
+            range: Some(range),
+            count: count.filter(|count| *count > 0),
+            key_only: true,
+        };
+        Ok(self.execute(
+            RequestSource::ObjectStore(self),
+            operation,
+            ResultConversion::PrimaryKeys,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-count
+    fn Count(&self, cx: JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-4.
+        self.check_readable()?;
+
+        // Step 5.
+        let range = convert_value_to_key_range(cx, query, false)?;
+
+        // Step 6.
+        let operation = AsyncOperation::Count {
+            store: self.name(),
+            index: None,
+            range: Some(range),
+        };
+        Ok(self.execute(
+            RequestSource::ObjectStore(self),
+            operation,
+            ResultConversion::Count,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-opencursor
+    fn OpenCursor(
+        &self,
+        cx: JSContext,
+        query: HandleValue,
+        direction: IDBCursorDirection,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-4.
+        self.check_readable()?;
+
+        // Step 5.
+        let range = convert_value_to_key_range(cx, query, false)?;
+
+        // Steps 6-10.
+        Ok(self.open_cursor(
+            RequestSource::ObjectStore(self),
+            None,
+            range,
+            direction,
+            false,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-openkeycursor
+    fn OpenKeyCursor(
+        &self,
+        cx: JSContext,
+        query: HandleValue,
+        direction: IDBCursorDirection,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-4.
+        self.check_readable()?;
+
+        // Step 5.
+        let range = convert_value_to_key_range(cx, query, false)?;
+
+        // Steps 6-10.
+        Ok(self.open_cursor(
+            RequestSource::ObjectStore(self),
+            None,
+            range,
+            direction,
+            true,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-index
+    fn Index(&self, name: DOMString) -> Fallible<DomRoot<IDBIndex>> {
+        // Steps 1-4.
+        if self.deleted.get() || self.transaction.is_finished() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 5-6.
+        let info = self
+            .info
+            .borrow()
+            .indexes
+            .iter()
+            .find(|index| *index.name == *name)
+            .cloned()
+            .ok_or(Error::NotFound)?;
+        Ok(self.index_handle(info))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-createindex
+    fn CreateIndex(
+        &self,
+        name: DOMString,
+        key_path: StringOrStringSequence,
+        options: &IDBIndexParameters,
+    ) -> Fallible<DomRoot<IDBIndex>> {
+        // Steps 1-5.
+        self.check_upgrade()?;
+
+        // Step 6.
+        let exists = self
+            .info
+            .borrow()
+            .indexes
+            .iter()
+            .any(|index| *index.name == *name);
+        if exists {
+            return Err(Error::Constraint);
+        }
+
+        // Step 7.
+        let key_path = key_path_from_union(&key_path);
+        if !is_valid_key_path(&key_path) {
+            return Err(Error::Syntax);
+        }
+
+        // Step 10.
+        if let KeyPath::Sequence(_) = key_path {
+            if options.multiEntry {
+                return Err(Error::InvalidAccess);
+            }
+        }
+
+        // Step 11.
+        let info = IndexInfo {
+            name: name.to_string(),
+            key_path,
+            unique: options.unique,
+            multi_entry: options.multiEntry,
+        };
+        let cx = self.global().get_cx();
+        let entries = self.compute_index_entries(cx, &info.key_path, info.multi_entry)?;
+        let operation = AsyncOperation::CreateIndex {
+            store: self.name(),
+            index: info.clone(),
+            entries,
+        };
+        match self.transaction.execute_sync(operation) {
+            Ok(_) => {},
+            // The index is still returned, the upgrade transaction is aborted
+            // once the creation of the index is known to have failed.
+            Err(BackendError::Constraint) => {
+                self.transaction.abort(Some(DOMErrorName::ConstraintError));
+            },
+            Err(error) => return Err(backend_error(error)),
+        }
+        self.info.borrow_mut().indexes.push(info.clone());
+        let index_info = info.clone();
+        self.transaction
+            .db()
+            .update_object_store_info(&self.name(), move |store| store.indexes.push(index_info));
+
+        // Step 12.
+        Ok(self.index_handle(info))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-deleteindex
+    fn DeleteIndex(&self, name: DOMString) -> ErrorResult {
+        // Steps 1-5.
+        self.check_upgrade()?;
+
+        // Step 6.
+        let exists = self
+            .info
+            .borrow()
+            .indexes
+            .iter()
+            .any(|index| *index.name == *name);
+        if !exists {
+            return Err(Error::NotFound);
+        }
+
+        // Steps 7-8.
+        let operation = AsyncOperation::DeleteIndex {
+            store: self.name(),
+            name: name.to_string(),
+        };
+        self.transaction
+            .execute_sync(operation)
+            .map_err(backend_error)?;
+        self.info
+            .borrow_mut()
+            .indexes
+            .retain(|index| *index.name != *name);
+        self.transaction
+            .db()
+            .update_object_store_info(&self.name(), |store| {
+                store.indexes.retain(|index| *index.name != *name)
+            });
+        let index = self
+            .indexes
+            .borrow_mut()
+            .remove(&name)
+            .map(|index| DomRoot::from_ref(&*index));
+        if let Some(index) = index {
+            index.set_deleted();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::IDBOpenDBRequestBinding::IDBOpenDBRequestMethods;
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::domexception::DOMErrorName;
+use crate::dom::event::{Event, EventBubbles, EventCancelable};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbdatabase::IDBDatabase;
+use crate::dom::idbrequest::IDBRequest;
+use crate::dom::idbversionchangeevent::IDBVersionChangeEvent;
+use crate::indexed_db::backend_error_name;
+use crate::task_source::{TaskSource, TaskSourceName};
+use dom_struct::dom_struct;
+use ipc_channel::ipc::{self, IpcReceiver};
+use ipc_channel::router::ROUTER;
+use js::jsval::{ObjectValue, UndefinedValue};
+use net_traits::indexeddb_thread::OpenResponse;
+use net_traits::indexeddb_thread::{BackendResult, ConnectionEvent, IndexedDBThreadMsg};
+use net_traits::IpcSend;
+use servo_atoms::Atom;
+
+#[dom_struct]
+pub struct IDBOpenDBRequest {
+    idbrequest: IDBRequest,
+    /// The connection opened by this request.
+    connection: MutNullableDom<IDBDatabase>,
+}
+
+impl IDBOpenDBRequest {
+    fn new_inherited() -> IDBOpenDBRequest {
+        IDBOpenDBRequest {
+            idbrequest: IDBRequest::new_inherited(),
+            connection: Default::default(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<IDBOpenDBRequest> {
+        reflect_dom_object(Box::new(IDBOpenDBRequest::new_inherited()), global)
+    }
+
+    /// https://w3c.github.io/IndexedDB/#open-a-database
+    pub fn open(&self, name: String, version: Option<u64>) {
+        let global = self.global();
+        let (sender, receiver) = ipc::channel().unwrap();
+        let (events_sender, events_receiver) = ipc::channel().unwrap();
+        self.route_open_responses(receiver);
+        self.route_connection_events(events_receiver);
+        let msg = IndexedDBThreadMsg::Open {
+            sender,
+            events: events_sender,
+            origin: global.origin().immutable().clone(),
+            name,
+            version,
+        };
+        let _ = global.resource_threads().send(msg);
+    }
+
+    /// https://w3c.github.io/IndexedDB/#delete-a-database
+    pub fn delete_database(&self, name: String) {
+        let global = self.global();
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.route_open_responses(receiver);
+        let msg =
+            IndexedDBThreadMsg::DeleteDatabase(sender, global.origin().immutable().clone(), name);
+        let _ = global.resource_threads().send(msg);
+    }
+
+    fn route_open_responses(&self, receiver: IpcReceiver<OpenResponse>) {
+        let global = self.global();
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DatabaseAccess);
+        let this = Trusted::new(self);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let response: OpenResponse = match message.to() {
+                    Ok(response) => response,
+                    Err(err) => {
+                        warn!("Error receiving an IndexedDB open response: {:?}", err);
+                        return;
+                    },
+                };
+                let this = this.clone();
+                let _ = task_source.queue_with_canceller(
+                    task!(handle_indexeddb_open_response: move || {
+                        this.root().handle_open_response(response);
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+    }
+
+    fn route_connection_events(&self, receiver: IpcReceiver<ConnectionEvent>) {
+        let global = self.global();
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DatabaseAccess);
+        let this = Trusted::new(self);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let event: ConnectionEvent = match message.to() {
+                    Ok(event) => event,
+                    Err(err) => {
+                        warn!("Error receiving an IndexedDB connection event: {:?}", err);
+                        return;
+                    },
+                };
+                let this = this.clone();
+                let _ = task_source.queue_with_canceller(
+                    task!(handle_indexeddb_connection_event: move || {
+                        if let Some(connection) = this.root().connection.get() {
+                            connection.handle_connection_event(event);
+                        }
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+    }
+
+    fn fire_version_change_event(&self, type_: Atom, old_version: u64, new_version: Option<u64>) {
+        let event = IDBVersionChangeEvent::new(
+            &self.global(),
+            type_,
+            EventBubbles::DoesNotBubble,
+            EventCancelable::NotCancelable,
+            old_version,
+            new_version,
+        );
+        event.upcast::<Event>().fire(self.upcast());
+    }
+
+    fn set_connection(&self, connection: &IDBDatabase) {
+        self.connection.set(Some(connection));
+        self.global().indexeddb().add_connection(connection);
+        self.idbrequest
+            .set_result(ObjectValue(connection.reflector().get_jsobject().get()));
+    }
+
+    fn handle_open_response(&self, response: OpenResponse) {
+        let global = self.global();
+        match response {
+            OpenResponse::Blocked {
+                old_version,
+                new_version,
+            } => {
+                self.fire_version_change_event(atom!("blocked"), old_version, new_version);
+            },
+            // https://w3c.github.io/IndexedDB/#run-an-upgrade-transaction
+            OpenResponse::Upgrade {
+                connection,
+                old_version,
+                new_version,
+                info,
+            } => {
+                let connection = IDBDatabase::new(&global, connection, info);
+                let transaction = connection.start_upgrade(old_version);
+                transaction.set_open_request(self);
+                self.set_connection(&connection);
+                self.idbrequest.set_transaction(Some(&transaction));
+
+                self.fire_version_change_event(
+                    atom!("upgradeneeded"),
+                    old_version,
+                    Some(new_version),
+                );
+                transaction.deactivate();
+                transaction.maybe_commit();
+            },
+            OpenResponse::Success { connection, info } => {
+                let connection = IDBDatabase::new(&global, connection, info);
+                self.set_connection(&connection);
+                self.idbrequest.fire_success_event();
+            },
+            // https://w3c.github.io/IndexedDB/#dom-idbfactory-deletedatabase
+            OpenResponse::Deleted { old_version } => {
+                self.idbrequest.set_result(UndefinedValue());
+                self.fire_version_change_event(atom!("success"), old_version, None);
+            },
+            OpenResponse::Error(error) => {
+                self.idbrequest.set_error(backend_error_name(error));
+                self.idbrequest.fire_error_event();
+            },
+        }
+    }
+
+    /// Steps 10.7-10.9 of https://w3c.github.io/IndexedDB/#open-a-database
+    pub fn upgrade_finished(&self, result: BackendResult<()>) {
+        self.idbrequest.set_transaction(None);
+        let connection = self.connection.get();
+        match connection {
+            Some(ref connection) if result.is_ok() && !connection.is_close_pending() => {
+                self.idbrequest
+                    .set_result(ObjectValue(connection.reflector().get_jsobject().get()));
+                self.idbrequest.fire_success_event();
+            },
+            _ => {
+                self.idbrequest.set_error(DOMErrorName::AbortError);
+                self.idbrequest.fire_error_event();
+            },
+        }
+    }
+}
+
+impl IDBOpenDBRequestMethods for IDBOpenDBRequest {
+    // https://w3c.github.io/IndexedDB/#dom-idbopendbrequest-onblocked
+    event_handler!(blocked, GetOnblocked, SetOnblocked);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbopendbrequest-onupgradeneeded
+    event_handler!(upgradeneeded, GetOnupgradeneeded, SetOnupgradeneeded);
+}
@@ -0,0 +1,394 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::DOMExceptionBinding::DOMExceptionMethods;
+use crate::dom::bindings::codegen::Bindings::IDBRequestBinding::{
+    IDBRequestMethods, IDBRequestReadyState,
+};
+use crate::dom::bindings::codegen::UnionTypes::IDBObjectStoreOrIDBIndexOrIDBCursor;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::domexception::{DOMErrorName, DOMException};
+use crate::dom::event::{Event, EventBubbles, EventCancelable, EventStatus};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbcursor::IDBCursor;
+use crate::dom::idbindex::IDBIndex;
+use crate::dom::idbobjectstore::IDBObjectStore;
+use crate::dom::idbtransaction::IDBTransaction;
+use crate::indexed_db::{backend_error_name, key_type_to_jsval};
+use crate::indexed_db::{record_value_to_jsval, record_values_to_jsval};
+use crate::realms::enter_realm;
+use crate::script_runtime::JSContext;
+use crate::task_source::{TaskSource, TaskSourceName};
+use dom_struct::dom_struct;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use js::jsapi::Heap;
+use js::jsval::{DoubleValue, JSVal, NullValue, ObjectValue, UndefinedValue};
+use js::rust::MutableHandleValue;
+use net_traits::indexeddb_thread::IndexedDBKeyType;
+use net_traits::indexeddb_thread::{AsyncOperation, AsyncResponse, BackendResult};
+use std::cell::Cell;
+
+/// The object a request was placed against.
+pub enum RequestSource<'a> {
+    ObjectStore(&'a IDBObjectStore),
+    Index(&'a IDBIndex),
+    Cursor(&'a IDBCursor),
+}
+
+/// How the response to an operation becomes the result of a request.
+#[derive(Clone, Copy)]
+pub enum ResultConversion {
+    /// The value of the record, or undefined if there is none.
+    Value,
+    /// The primary key of the record, or undefined if there is none.
+    PrimaryKey,
+    /// The key the record was stored with.
+    Key,
+    /// The values of the records.
+    Values,
+    /// The primary keys of the records.
+    PrimaryKeys,
+    /// The number of records.
+    Count,
+    Undefined,
+    /// The cursor of the request, moved to the record, or null if there is none.
+    Cursor,
+}
+
+#[dom_struct]
+pub struct IDBRequest {
+    eventtarget: EventTarget,
+    #[ignore_malloc_size_of = "mozjs"]
+    result: Heap<JSVal>,
+    error: MutNullableDom<DOMException>,
+    source_object_store: MutNullableDom<IDBObjectStore>,
+    source_index: MutNullableDom<IDBIndex>,
+    source_cursor: MutNullableDom<IDBCursor>,
+    transaction: MutNullableDom<IDBTransaction>,
+    /// https://w3c.github.io/IndexedDB/#request-done-flag
+    done: Cell<bool>,
+    /// The cursor iterated by this request, for requests made by `openCursor` and `openKeyCursor`.
+    cursor: MutNullableDom<IDBCursor>,
+}
+
+impl IDBRequest {
+    pub fn new_inherited() -> IDBRequest {
+        IDBRequest {
+            eventtarget: EventTarget::new_inherited(),
+            result: Heap::default(),
+            error: Default::default(),
+            source_object_store: Default::default(),
+            source_index: Default::default(),
+            source_cursor: Default::default(),
+            transaction: Default::default(),
+            done: Cell::new(false),
+            cursor: Default::default(),
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        source: RequestSource,
+        transaction: &IDBTransaction,
+    ) -> DomRoot<IDBRequest> {
+        let request = reflect_dom_object(Box::new(IDBRequest::new_inherited()), global);
+        match source {
+            RequestSource::ObjectStore(store) => request.source_object_store.set(Some(store)),
+            RequestSource::Index(index) => request.source_index.set(Some(index)),
+            RequestSource::Cursor(cursor) => request.source_cursor.set(Some(cursor)),
+        }
+        request.transaction.set(Some(transaction));
+        request
+    }
+
+    pub fn transaction(&self) -> Option<DomRoot<IDBTransaction>> {
+        self.transaction.get()
+    }
+
+    pub fn set_transaction(&self, transaction: Option<&IDBTransaction>) {
+        self.transaction.set(transaction);
+    }
+
+    pub fn set_cursor(&self, cursor: &IDBCursor) {
+        self.cursor.set(Some(cursor));
+    }
+
+    /// Resets a cursor request before its cursor iterates again.
+    pub fn set_pending(&self) {
+        self.done.set(false);
+    }
+
+    /// The object store the source of this request belongs to.
+    fn object_store(&self) -> Option<DomRoot<IDBObjectStore>> {
+        if let Some(store) = self.source_object_store.get() {
+            return Some(store);
+        }
+        if let Some(index) = self.source_index.get() {
+            return Some(index.object_store());
+        }
+        self.source_cursor.get().map(|cursor| cursor.object_store())
+    }
+
+    pub fn set_result(&self, value: JSVal) {
+        self.done.set(true);
+        self.error.set(None);
+        self.result.set(value);
+    }
+
+    pub fn set_error(&self, error: DOMErrorName) {
+        self.done.set(true);
+        self.result.set(UndefinedValue());
+        self.error
+            .set(Some(&DOMException::new(&self.global(), error)));
+    }
+
+    /// https://w3c.github.io/IndexedDB/#asynchronously-execute-a-request
+    pub fn execute_async(&self, operation: AsyncOperation, conversion: ResultConversion) {
+        let transaction = self
+            .transaction
+            .get()
+            .expect("Only requests with a transaction execute operations");
+        // Step 4.
+        transaction.add_request(self);
+
+        let global = self.global();
+        let (sender, receiver) = ipc::channel().unwrap();
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(TaskSourceName::DatabaseAccess);
+        let this = Trusted::new(self);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let response: BackendResult<AsyncResponse> = match message.to() {
+                    Ok(response) => response,
+                    Err(err) => {
+                        warn!("Error receiving an IndexedDB response: {:?}", err);
+                        return;
+                    },
+                };
+                let this = this.clone();
+                let _ = task_source.queue_with_canceller(
+                    task!(handle_indexeddb_response: move || {
+                        this.root().handle_response(response, conversion);
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+        transaction.execute(operation, sender);
+    }
+
+    /// Steps 5.4-5.5 of https://w3c.github.io/IndexedDB/#asynchronously-execute-a-request
+    fn handle_response(
+        &self,
+        response: BackendResult<AsyncResponse>,
+        conversion: ResultConversion,
+    ) {
+        let transaction = self.transaction.get().unwrap();
+        // A request that was aborted along with its transaction
+        // has already been given its error.
+        if !transaction.remove_request(self) {
+            return;
+        }
+
+        let global = self.global();
+        let _ac = enter_realm(&*global);
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut result = UndefinedValue());
+        let outcome = response.map_err(backend_error_name).and_then(|response| {
+            self.convert_response(cx, response, conversion, result.handle_mut())
+        });
+        match outcome {
+            Ok(()) => {
+                self.set_result(result.get());
+                self.fire_success_event();
+            },
+            Err(error) => {
+                self.set_error(error);
+                self.fire_error_event();
+            },
+        }
+    }
+
+    fn convert_response(
+        &self,
+        cx: JSContext,
+        response: AsyncResponse,
+        conversion: ResultConversion,
+        mut rval: MutableHandleValue,
+    ) -> Result<(), DOMErrorName> {
+        let global = self.global();
+        let injected_key_path = self
+            .object_store()
+            .and_then(|store| store.injected_key_path());
+        let injected_key_path = injected_key_path.as_ref().map(|path| &**path);
+        let to_error_name = |_: Error| DOMErrorName::DataCloneError;
+        match (conversion, response) {
+            (ResultConversion::Undefined, _) => rval.set(UndefinedValue()),
+            (ResultConversion::Key, AsyncResponse::Key(key)) => key_type_to_jsval(cx, &key, rval),
+            (ResultConversion::Count, AsyncResponse::Count(count)) => {
+                rval.set(DoubleValue(count as f64))
+            },
+            (ResultConversion::Value, AsyncResponse::Record(record)) => match record {
+                Some(record) => record_value_to_jsval(&global, record, injected_key_path, rval)
+                    .map_err(to_error_name)?,
+                None => rval.set(UndefinedValue()),
+            },
+            (ResultConversion::PrimaryKey, AsyncResponse::Record(record)) => match record {
+                Some(record) => key_type_to_jsval(cx, &record.primary_key, rval),
+                None => rval.set(UndefinedValue()),
+            },
+            (ResultConversion::Values, AsyncResponse::Records(records)) => {
+                record_values_to_jsval(&global, records, injected_key_path, rval)
+                    .map_err(to_error_name)?
+            },
+            (ResultConversion::PrimaryKeys, AsyncResponse::Records(records)) => {
+                let keys = records
+                    .into_iter()
+                    .map(|record| record.primary_key)
+                    .collect();
+                key_type_to_jsval(cx, &IndexedDBKeyType::Array(keys), rval)
+            },
+            (ResultConversion::Cursor, AsyncResponse::Record(record)) => {
+                let cursor = self.cursor.get().expect("Cursor requests have a cursor");
+                if cursor
+                    .iterated(record, injected_key_path)
+                    .map_err(to_error_name)?
+                {
+                    rval.set(ObjectValue(cursor.reflector().get_jsobject().get()));
+                } else {
+                    rval.set(NullValue());
+                }
+            },
+            (_, response) => unreachable!("Unexpected IndexedDB response {:?}", response),
+        }
+        Ok(())
+    }
+
+    /// https://w3c.github.io/IndexedDB/#fire-a-success-event
+    pub fn fire_success_event(&self) {
+        let global = self.global();
+        let event = Event::new(
+            &global,
+            atom!("success"),
+            EventBubbles::DoesNotBubble,
+            EventCancelable::NotCancelable,
+        );
+        let transaction = self.transaction.get();
+        if let Some(ref transaction) = transaction {
+            transaction.activate();
+        }
+        event.fire(self.upcast());
+        if let Some(ref transaction) = transaction {
+            transaction.deactivate();
+            transaction.maybe_commit();
+        }
+    }
+
+    /// https://w3c.github.io/IndexedDB/#fire-an-error-event
+    pub fn fire_error_event(&self) {
+        let global = self.global();
+        let event = Event::new(
+            &global,
+            atom!("error"),
+            EventBubbles::Bubbles,
+            EventCancelable::Cancelable,
+        );
+        let transaction = self.transaction.get();
+        if let Some(ref transaction) = transaction {
+            transaction.activate();
+        }
+        let status = event.fire(self.upcast());
+        if let Some(ref transaction) = transaction {
+            transaction.deactivate();
+            // An error that isn't handled aborts the transaction.
+            if status == EventStatus::NotCanceled {
+                let error = self
+                    .error
+                    .get()
+                    .and_then(|error| DOMErrorName::from(&error.Name()));
+                transaction.abort(error);
+            } else {
+                transaction.maybe_commit();
+            }
+        }
+    }
+
+    /// Step 5 of https://w3c.github.io/IndexedDB/#abort-a-transaction
+    pub fn abort(&self) {
+        self.set_error(DOMErrorName::AbortError);
+        let global = self.global();
+        let this = Trusted::new(self);
+        let _ = global.database_access_task_source().queue(
+            task!(fire_indexeddb_request_abort: move || {
+                let this = this.root();
+                let event = Event::new(
+                    &this.global(),
+                    atom!("error"),
+                    EventBubbles::Bubbles,
+                    EventCancelable::Cancelable,
+                );
+                event.fire(this.upcast());
+            }),
+            &global,
+        );
+    }
+}
+
+impl IDBRequestMethods for IDBRequest {
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-result
+    fn Result(&self, _cx: JSContext) -> Fallible<JSVal> {
+        if !self.done.get() {
+            return Err(Error::InvalidState);
+        }
+        Ok(self.result.get())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-error
+    fn GetError(&self) -> Fallible<Option<DomRoot<DOMException>>> {
+        if !self.done.get() {
+            return Err(Error::InvalidState);
+        }
+        Ok(self.error.get())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-source
+    fn GetSource(&self) -> Option<IDBObjectStoreOrIDBIndexOrIDBCursor> {
+        if let Some(store) = self.source_object_store.get() {
+            return Some(IDBObjectStoreOrIDBIndexOrIDBCursor::IDBObjectStore(store));
+        }
+        if let Some(index) = self.source_index.get() {
+            return Some(IDBObjectStoreOrIDBIndexOrIDBCursor::IDBIndex(index));
+        }
+        self.source_cursor
+            .get()
+            .map(IDBObjectStoreOrIDBIndexOrIDBCursor::IDBCursor)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-transaction
+    fn GetTransaction(&self) -> Option<DomRoot<IDBTransaction>> {
+        self.transaction.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-readystate
+    fn ReadyState(&self) -> IDBRequestReadyState {
+        if self.done.get() {
+            IDBRequestReadyState::Done
+        } else {
+            IDBRequestReadyState::Pending
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-onsuccess
+    event_handler!(success, GetOnsuccess, SetOnsuccess);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+}
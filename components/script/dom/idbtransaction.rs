@@ -0,0 +1,404 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBDatabaseMethods;
+use crate::dom::bindings::codegen::Bindings::IDBTransactionBinding::{
+    IDBTransactionMethods, IDBTransactionMode,
+};
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::domexception::{DOMErrorName, DOMException};
+use crate::dom::domstringlist::DOMStringList;
+use crate::dom::event::{Event, EventBubbles, EventCancelable};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbdatabase::IDBDatabase;
+use crate::dom::idbobjectstore::IDBObjectStore;
+use crate::dom::idbopendbrequest::IDBOpenDBRequest;
+use crate::dom::idbrequest::IDBRequest;
+use crate::indexed_db::backend_error_name;
+use crate::task_source::TaskSource;
+use dom_struct::dom_struct;
+use ipc_channel::ipc::IpcSender;
+use net_traits::indexeddb_thread::{AsyncOperation, AsyncResponse, BackendError, BackendResult};
+use net_traits::indexeddb_thread::{IndexedDBThreadMsg, IndexedDBTxnId, IndexedDBTxnMode};
+use net_traits::IpcSend;
+use profile_traits::ipc;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ptr;
+
+/// https://w3c.github.io/IndexedDB/#transaction-lifetime
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+enum TransactionState {
+    Active,
+    Inactive,
+    Committing,
+    Finished,
+}
+
+#[dom_struct]
+pub struct IDBTransaction {
+    eventtarget: EventTarget,
+    db: Dom<IDBDatabase>,
+    /// The serial number of this transaction in its connection, 0 for the upgrade transaction.
+    serial: u64,
+    mode: IndexedDBTxnMode,
+    /// The names of the object stores in the scope of this transaction,
+    /// unused by the upgrade transaction whose scope is the whole database.
+    scope: Vec<DOMString>,
+    state: Cell<TransactionState>,
+    error: MutNullableDom<DOMException>,
+    /// The requests placed against this transaction that haven't been processed yet.
+    requests: DomRefCell<Vec<Dom<IDBRequest>>>,
+    /// The object store handles of this transaction, by name.
+    object_stores: DomRefCell<HashMap<DOMString, Dom<IDBObjectStore>>>,
+    /// The request that opened the connection, for the upgrade transaction.
+    open_request: MutNullableDom<IDBOpenDBRequest>,
+}
+
+impl IDBTransaction {
+    fn new_inherited(
+        db: &IDBDatabase,
+        serial: u64,
+        mode: IndexedDBTxnMode,
+        scope: Vec<DOMString>,
+    ) -> IDBTransaction {
+        IDBTransaction {
+            eventtarget: EventTarget::new_inherited(),
+            db: Dom::from_ref(db),
+            serial,
+            mode,
+            scope,
+            state: Cell::new(TransactionState::Active),
+            error: Default::default(),
+            requests: Default::default(),
+            object_stores: Default::default(),
+            open_request: Default::default(),
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        db: &IDBDatabase,
+        serial: u64,
+        mode: IndexedDBTxnMode,
+        scope: Vec<DOMString>,
+    ) -> DomRoot<IDBTransaction> {
+        reflect_dom_object(
+            Box::new(IDBTransaction::new_inherited(db, serial, mode, scope)),
+            global,
+        )
+    }
+
+    pub fn db(&self) -> DomRoot<IDBDatabase> {
+        DomRoot::from_ref(&*self.db)
+    }
+
+    pub fn id(&self) -> IndexedDBTxnId {
+        IndexedDBTxnId {
+            connection: self.db.connection(),
+            serial: self.serial,
+        }
+    }
+
+    pub fn mode(&self) -> IndexedDBTxnMode {
+        self.mode
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state.get() == TransactionState::Active
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.get() == TransactionState::Finished
+    }
+
+    pub fn set_open_request(&self, request: &IDBOpenDBRequest) {
+        self.open_request.set(Some(request));
+    }
+
+    /// Registers a transaction created by `IDBDatabase.transaction()` with the IndexedDB thread.
+    pub fn register(&self) {
+        let global = self.global();
+        let msg = IndexedDBThreadMsg::CreateTransaction {
+            id: self.id(),
+            scope: self.scope.iter().map(|name| name.to_string()).collect(),
+            mode: self.mode,
+        };
+        let _ = global.resource_threads().send(msg);
+
+        // https://w3c.github.io/IndexedDB/#cleanup-indexed-database-transactions
+        // The transaction stays active until the task that created it is done.
+        let this = Trusted::new(self);
+        let _ = global.database_access_task_source().queue(
+            task!(deactivate_indexeddb_transaction: move || {
+                let this = this.root();
+                this.deactivate();
+                this.maybe_commit();
+            }),
+            &global,
+        );
+    }
+
+    /// Places an operation against this transaction,
+    /// the IndexedDB thread runs it once the transaction has started.
+    pub fn execute(
+        &self,
+        operation: AsyncOperation,
+        sender: IpcSender<BackendResult<AsyncResponse>>,
+    ) {
+        let msg = IndexedDBThreadMsg::Operation(self.id(), sender, operation);
+        let _ = self.global().resource_threads().send(msg);
+    }
+
+    /// Runs an operation against the upgrade transaction, waiting for its result.
+    /// Only used for the changes to the schema of the database,
+    /// as the upgrade transaction is always running.
+    pub fn execute_sync(&self, operation: AsyncOperation) -> BackendResult<AsyncResponse> {
+        assert_eq!(self.mode, IndexedDBTxnMode::Versionchange);
+        let global = self.global();
+        let (sender, receiver) = ipc::channel(global.time_profiler_chan().clone()).unwrap();
+        self.execute(operation, sender);
+        receiver.recv().unwrap_or(Err(BackendError::Abort))
+    }
+
+    pub fn add_request(&self, request: &IDBRequest) {
+        self.requests.borrow_mut().push(Dom::from_ref(request));
+    }
+
+    /// Removes a processed request, returns whether it was still pending.
+    pub fn remove_request(&self, request: &IDBRequest) -> bool {
+        let mut requests = self.requests.borrow_mut();
+        match requests
+            .iter()
+            .position(|pending| ptr::eq(&**pending, request))
+        {
+            Some(index) => {
+                requests.remove(index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Makes an inactive transaction active while an event is dispatched for one of its requests.
+    pub fn activate(&self) {
+        if self.state.get() == TransactionState::Inactive {
+            self.state.set(TransactionState::Active);
+        }
+    }
+
+    pub fn deactivate(&self) {
+        if self.state.get() == TransactionState::Active {
+            self.state.set(TransactionState::Inactive);
+        }
+    }
+
+    /// Commits the transaction once it is inactive and all its requests have been processed.
+    /// https://w3c.github.io/IndexedDB/#transaction-commit
+    pub fn maybe_commit(&self) {
+        if self.state.get() != TransactionState::Inactive || !self.requests.borrow().is_empty() {
+            return;
+        }
+        self.commit();
+    }
+
+    /// https://w3c.github.io/IndexedDB/#commit-a-transaction
+    fn commit(&self) {
+        self.state.set(TransactionState::Committing);
+        let _ = self
+            .global()
+            .resource_threads()
+            .send(IndexedDBThreadMsg::Commit(self.id()));
+    }
+
+    /// https://w3c.github.io/IndexedDB/#abort-a-transaction
+    /// The abort event is fired once the IndexedDB thread has reverted the changes.
+    pub fn abort(&self, error: Option<DOMErrorName>) {
+        if self.is_finished() {
+            return;
+        }
+        let global = self.global();
+        let _ = global
+            .resource_threads()
+            .send(IndexedDBThreadMsg::Abort(self.id()));
+        self.abort_locally(error);
+    }
+
+    fn abort_locally(&self, error: Option<DOMErrorName>) {
+        let global = self.global();
+        // Steps 3-4.
+        if let Some(error) = error {
+            self.error.set(Some(&DOMException::new(&global, error)));
+        }
+        self.state.set(TransactionState::Finished);
+
+        // Step 5.
+        let requests: Vec<_> = self
+            .requests
+            .borrow_mut()
+            .drain(..)
+            .map(|request| DomRoot::from_ref(&*request))
+            .collect();
+        for request in requests {
+            request.abort();
+        }
+
+        // Step 6.
+        if self.mode == IndexedDBTxnMode::Versionchange {
+            self.db.abort_upgrade();
+        }
+    }
+
+    /// Called once the IndexedDB thread has committed or aborted this transaction.
+    pub fn finished(&self, result: BackendResult<()>) {
+        let global = self.global();
+        let event = match result {
+            Ok(()) => {
+                self.state.set(TransactionState::Finished);
+                if self.mode == IndexedDBTxnMode::Versionchange {
+                    self.db.finish_upgrade();
+                }
+                // https://w3c.github.io/IndexedDB/#commit-a-transaction
+                Event::new(
+                    &global,
+                    atom!("complete"),
+                    EventBubbles::DoesNotBubble,
+                    EventCancelable::NotCancelable,
+                )
+            },
+            Err(error) => {
+                // The transaction may have been aborted without a request from this connection,
+                // such as when the connection is dropped.
+                if !self.is_finished() {
+                    self.abort_locally(Some(backend_error_name(error)));
+                }
+                // Step 7 of https://w3c.github.io/IndexedDB/#abort-a-transaction
+                Event::new(
+                    &global,
+                    atom!("abort"),
+                    EventBubbles::Bubbles,
+                    EventCancelable::NotCancelable,
+                )
+            },
+        };
+        event.fire(self.upcast());
+
+        // https://w3c.github.io/IndexedDB/#open-a-database
+        // The open request completes once the upgrade transaction is finished.
+        if let Some(request) = self.open_request.get() {
+            self.open_request.set(None);
+            request.upgrade_finished(result);
+        }
+    }
+
+    /// Marks the handle of an object store deleted in the upgrade transaction.
+    pub fn object_store_deleted(&self, name: &DOMString) {
+        if let Some(store) = self.object_stores.borrow_mut().remove(name) {
+            store.set_deleted();
+        }
+    }
+
+    /// Returns the handle of an object store in this transaction, creating it if needed.
+    pub fn object_store_handle(&self, name: DOMString) -> DomRoot<IDBObjectStore> {
+        let mut object_stores = self.object_stores.borrow_mut();
+        let store = object_stores
+            .entry(name.clone())
+            .or_insert_with(|| Dom::from_ref(&*IDBObjectStore::new(&self.global(), name, self)));
+        DomRoot::from_ref(&**store)
+    }
+}
+
+impl IDBTransactionMethods for IDBTransaction {
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-objectstorenames
+    fn ObjectStoreNames(&self) -> DomRoot<DOMStringList> {
+        if self.mode == IndexedDBTxnMode::Versionchange {
+            return self.db.ObjectStoreNames();
+        }
+        DOMStringList::new(&self.global(), self.scope.clone())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-mode
+    fn Mode(&self) -> IDBTransactionMode {
+        match self.mode {
+            IndexedDBTxnMode::Readonly => IDBTransactionMode::Readonly,
+            IndexedDBTxnMode::Readwrite => IDBTransactionMode::Readwrite,
+            IndexedDBTxnMode::Versionchange => IDBTransactionMode::Versionchange,
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-db
+    fn Db(&self) -> DomRoot<IDBDatabase> {
+        self.db()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-error
+    fn GetError(&self) -> Option<DomRoot<DOMException>> {
+        self.error.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-objectstore
+    fn ObjectStore(&self, name: DOMString) -> Fallible<DomRoot<IDBObjectStore>> {
+        // Step 1.
+        if self.is_finished() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 2.
+        let in_scope = if self.mode == IndexedDBTxnMode::Versionchange {
+            self.db.object_store_info(&name).is_some()
+        } else {
+            self.scope.contains(&name)
+        };
+        if !in_scope {
+            return Err(Error::NotFound);
+        }
+
+        // Step 3.
+        Ok(self.object_store_handle(name))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-commit
+    fn Commit(&self) -> ErrorResult {
+        // Step 1.
+        if !self.is_active() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 2.
+        self.commit();
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-abort
+    fn Abort(&self) -> ErrorResult {
+        // Step 1.
+        match self.state.get() {
+            TransactionState::Committing | TransactionState::Finished => {
+                return Err(Error::InvalidState);
+            },
+            TransactionState::Active | TransactionState::Inactive => {},
+        }
+
+        // Steps 2-3.
+        self.state.set(TransactionState::Inactive);
+        self.abort(None);
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-oncomplete
+    event_handler!(complete, GetOncomplete, SetOncomplete);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+}
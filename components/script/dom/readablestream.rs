@@ -0,0 +1,960 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::{
+    QueuingStrategy, QueuingStrategySize,
+};
+use crate::dom::bindings::codegen::Bindings::ReadableStreamBinding::{
+    ReadableStreamGetReaderOptions, ReadableStreamMethods, ReadableWritablePair, StreamPipeOptions,
+};
+use crate::dom::bindings::conversions::get_property_jsval;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestreamdefaultcontroller::{
+    ReadableStreamDefaultController, SourceAlgorithms,
+};
+use crate::dom::readablestreamdefaultreader::ReadableStreamDefaultReader;
+use crate::dom::writablestream::{WritableStream, WritableStreamState};
+use crate::dom::writablestreamdefaultwriter::WritableStreamDefaultWriter;
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::streams::{
+    call_method, error_to_jsval, extract_high_water_mark, get_method, mark_as_handled,
+    new_rejected_promise, new_rejected_promise_with_error, new_resolved_promise,
+    transform_to_undefined, unpack_read_result,
+};
+use dom_struct::dom_struct;
+use js::jsapi::{HandleValueArray, Heap, JSContext, JSObject, JS_NewArrayObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use js::typedarray::{CreateWith, Uint8Array};
+use std::cell::Cell;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#readablestream-state>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum ReadableStreamState {
+    Readable,
+    Closed,
+    Errored,
+}
+
+#[dom_struct]
+pub struct ReadableStream {
+    reflector_: Reflector,
+    state: Cell<ReadableStreamState>,
+    disturbed: Cell<bool>,
+    #[ignore_malloc_size_of = "mozjs"]
+    stored_error: Heap<JSVal>,
+    controller: MutNullableDom<ReadableStreamDefaultController>,
+    reader: MutNullableDom<ReadableStreamDefaultReader>,
+}
+
+impl ReadableStream {
+    fn new_inherited() -> ReadableStream {
+        ReadableStream {
+            reflector_: Reflector::new(),
+            state: Cell::new(ReadableStreamState::Readable),
+            disturbed: Cell::new(false),
+            stored_error: Heap::default(),
+            controller: Default::default(),
+            reader: Default::default(),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<ReadableStream> {
+        reflect_dom_object(Box::new(ReadableStream::new_inherited()), global)
+    }
+
+    /// <https://streams.spec.whatwg.org/#create-readable-stream>
+    #[allow(unrooted_must_root)]
+    pub fn create(
+        global: &GlobalScope,
+        algorithms: SourceAlgorithms,
+        high_water_mark: f64,
+        size: Option<Rc<QueuingStrategySize>>,
+        start_result: HandleValue,
+    ) -> DomRoot<ReadableStream> {
+        let stream = ReadableStream::new(global);
+        let controller = ReadableStreamDefaultController::new(
+            global,
+            &stream,
+            algorithms,
+            high_water_mark,
+            size,
+        );
+        stream.controller.set(Some(&controller));
+        controller.start(start_result);
+        stream
+    }
+
+    /// Creates a stream whose chunks are enqueued by Rust code, as the body of
+    /// a response is by the fetch listener.
+    pub fn new_native(global: &GlobalScope) -> DomRoot<ReadableStream> {
+        ReadableStream::create(
+            global,
+            SourceAlgorithms::None,
+            1.,
+            None,
+            HandleValue::undefined(),
+        )
+    }
+
+    /// Creates a closed stream holding `bytes` as a single chunk, as for the
+    /// body extracted from a byte sequence.
+    pub fn new_from_bytes(global: &GlobalScope, bytes: Vec<u8>) -> DomRoot<ReadableStream> {
+        let stream = ReadableStream::new_native(global);
+        if !bytes.is_empty() {
+            stream.enqueue_native(bytes);
+        }
+        stream.close_native();
+        stream
+    }
+
+    /// Enqueues `bytes` as a `Uint8Array` chunk.
+    #[allow(unsafe_code)]
+    pub fn enqueue_native(&self, bytes: Vec<u8>) {
+        let controller = self.controller();
+        if !controller.can_close_or_enqueue() {
+            return;
+        }
+        let cx = self.global().get_cx();
+        let _ac = enter_realm(self);
+        rooted!(in(*cx) let mut chunk = ptr::null_mut::<JSObject>());
+        unsafe {
+            Uint8Array::create(*cx, CreateWith::Slice(&bytes), chunk.handle_mut())
+                .expect("Failed to create a Uint8Array");
+        }
+        rooted!(in(*cx) let chunk = ObjectValue(chunk.get()));
+        // Chunks without a size function have a size of 1, so this can't fail.
+        let _ = controller.enqueue(cx, chunk.handle());
+    }
+
+    /// Closes the stream once the chunks enqueued so far are read.
+    pub fn close_native(&self) {
+        let controller = self.controller();
+        if controller.can_close_or_enqueue() {
+            controller.close();
+        }
+    }
+
+    /// Errors the stream with `error`.
+    pub fn error_native(&self, error: Error) {
+        let global = self.global();
+        let cx = global.get_cx();
+        let _ac = enter_realm(self);
+        rooted!(in(*cx) let mut value = UndefinedValue());
+        error_to_jsval(&global, error, value.handle_mut());
+        self.controller().error(value.handle());
+    }
+
+    pub fn state(&self) -> ReadableStreamState {
+        self.state.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-readable-stream-locked>
+    pub fn is_locked(&self) -> bool {
+        self.reader.get().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-readable-stream-disturbed>
+    pub fn is_disturbed(&self) -> bool {
+        self.disturbed.get()
+    }
+
+    pub fn set_disturbed(&self) {
+        self.disturbed.set(true);
+    }
+
+    pub fn stored_error(&self, mut rval: MutableHandleValue) {
+        rval.set(self.stored_error.get());
+    }
+
+    pub fn controller(&self) -> DomRoot<ReadableStreamDefaultController> {
+        self.controller
+            .get()
+            .expect("A readable stream has no controller")
+    }
+
+    pub fn reader(&self) -> Option<DomRoot<ReadableStreamDefaultReader>> {
+        self.reader.get()
+    }
+
+    pub fn set_reader(&self, reader: Option<&ReadableStreamDefaultReader>) {
+        self.reader.set(reader);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-get-num-read-requests>
+    pub fn num_read_requests(&self) -> usize {
+        self.reader
+            .get()
+            .map_or(0, |reader| reader.num_read_requests())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-add-read-request>
+    pub fn add_read_request(&self, request: &Rc<Promise>) {
+        self.reader
+            .get()
+            .expect("Adding a read request to a stream without a reader")
+            .add_read_request(request);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-fulfill-read-request>
+    pub fn fulfill_read_request(&self, chunk: HandleValue, done: bool) {
+        self.reader
+            .get()
+            .expect("Fulfilling a read request of a stream without a reader")
+            .fulfill_read_request(chunk, done);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-close>
+    pub fn close(&self) {
+        assert_eq!(self.state.get(), ReadableStreamState::Readable);
+        self.state.set(ReadableStreamState::Closed);
+        if let Some(reader) = self.reader.get() {
+            reader.close();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-error>
+    pub fn error(&self, e: HandleValue) {
+        assert_eq!(self.state.get(), ReadableStreamState::Readable);
+        self.state.set(ReadableStreamState::Errored);
+        self.stored_error.set(e.get());
+        if let Some(reader) = self.reader.get() {
+            reader.error(e);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-cancel>
+    pub fn cancel(&self, reason: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+
+        // Step 1.
+        self.disturbed.set(true);
+
+        match self.state.get() {
+            // Step 2.
+            ReadableStreamState::Closed => new_resolved_promise(&global, HandleValue::undefined()),
+            // Step 3.
+            ReadableStreamState::Errored => {
+                rooted!(in(*global.get_cx()) let error = self.stored_error.get());
+                new_rejected_promise(&global, error.handle())
+            },
+            ReadableStreamState::Readable => {
+                // Step 4.
+                self.close();
+
+                // Steps 5-8.
+                let source_cancel_promise = self.controller().cancel_steps(reason);
+                transform_to_undefined(&global, &source_cancel_promise)
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#acquire-readable-stream-reader>
+    pub fn acquire_default_reader(&self) -> Fallible<DomRoot<ReadableStreamDefaultReader>> {
+        ReadableStreamDefaultReader::acquire(self)
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-tee>
+    #[allow(unrooted_must_root)]
+    pub fn tee(&self) -> Fallible<(DomRoot<ReadableStream>, DomRoot<ReadableStream>)> {
+        let global = self.global();
+
+        // Step 3.
+        let reader = self.acquire_default_reader()?;
+
+        // Steps 4-10, 16.
+        let source = Rc::new(TeeSource {
+            stream: Dom::from_ref(self),
+            reader: Dom::from_ref(&*reader),
+            reading: Cell::new(false),
+            read_again: Cell::new(false),
+            canceled1: Cell::new(false),
+            canceled2: Cell::new(false),
+            reason1: Heap::default(),
+            reason2: Heap::default(),
+            branch1: Default::default(),
+            branch2: Default::default(),
+            cancel_promise: Promise::new(&global),
+        });
+
+        // Steps 12-17.
+        let branch1 = ReadableStream::create(
+            &global,
+            SourceAlgorithms::Tee(source.clone(), TeeBranch::First),
+            1.,
+            None,
+            HandleValue::undefined(),
+        );
+        let branch2 = ReadableStream::create(
+            &global,
+            SourceAlgorithms::Tee(source.clone(), TeeBranch::Second),
+            1.,
+            None,
+            HandleValue::undefined(),
+        );
+        source.branch1.set(Some(&branch1));
+        source.branch2.set(Some(&branch2));
+
+        // Step 18.
+        let handler = PromiseNativeHandler::new(
+            &global,
+            None,
+            Some(Box::new(TeeHandler {
+                source,
+                reaction: TeeReaction::ReaderClosedRejected,
+            })),
+        );
+        reader.closed_promise().append_native_handler(&handler);
+
+        // Step 19.
+        Ok((branch1, branch2))
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-pipe-to>
+    #[allow(unrooted_must_root)]
+    fn pipe_to(
+        &self,
+        dest: &WritableStream,
+        prevent_close: bool,
+        prevent_abort: bool,
+        prevent_cancel: bool,
+    ) -> Rc<Promise> {
+        let global = self.global();
+
+        // Steps 6-7.
+        let reader = match self.acquire_default_reader() {
+            Ok(reader) => reader,
+            Err(error) => return new_rejected_promise_with_error(&global, error),
+        };
+        let writer = match WritableStreamDefaultWriter::acquire(dest) {
+            Ok(writer) => writer,
+            Err(error) => {
+                reader.release();
+                return new_rejected_promise_with_error(&global, error);
+            },
+        };
+
+        // Steps 8-9, 13.
+        let state = Rc::new(PipeState {
+            source: Dom::from_ref(self),
+            dest: Dom::from_ref(dest),
+            reader: Dom::from_ref(&*reader),
+            writer: Dom::from_ref(&*writer),
+            prevent_close,
+            prevent_abort,
+            prevent_cancel,
+            shutting_down: Cell::new(false),
+            pending_write: DomRefCell::new(None),
+            has_error: Cell::new(false),
+            error: Heap::default(),
+            promise: Promise::new(&global),
+        });
+
+        // Step 14.
+        state.upon(
+            &reader.closed_promise(),
+            PipeStep::SourceClosed,
+            PipeStep::SourceErrored,
+        );
+        state.upon(
+            &writer.closed_promise(),
+            PipeStep::DestClosed,
+            PipeStep::DestErrored,
+        );
+        state.pump();
+
+        // Step 15.
+        state.promise.clone()
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-constructor>
+    #[allow(non_snake_case, unsafe_code, unrooted_must_root)]
+    pub fn Constructor(
+        cx: SafeJSContext,
+        global: &GlobalScope,
+        underlying_source: Option<*mut JSObject>,
+        strategy: &QueuingStrategy,
+    ) -> Fallible<DomRoot<ReadableStream>> {
+        // Step 1.
+        rooted!(in(*cx) let source = underlying_source.unwrap_or(ptr::null_mut()));
+
+        // Step 5.3.
+        let high_water_mark = extract_high_water_mark(strategy, 1.)?;
+
+        // Step 3.
+        let stream = ReadableStream::new(global);
+        let controller = ReadableStreamDefaultController::new(
+            global,
+            &stream,
+            SourceAlgorithms::Object,
+            high_water_mark,
+            strategy.size.clone(),
+        );
+
+        // Step 2.
+        controller.init_underlying_source(cx, source.handle())?;
+        rooted!(in(*cx) let mut start = UndefinedValue());
+        get_method(cx, source.handle(), "start", start.handle_mut())?;
+
+        // Step 4.
+        if !source.is_null() {
+            rooted!(in(*cx) let mut type_ = UndefinedValue());
+            unsafe { get_property_jsval(*cx, source.handle(), "type", type_.handle_mut())? };
+            if !type_.is_undefined() {
+                return Err(Error::Type(
+                    "Readable byte streams are not supported".to_owned(),
+                ));
+            }
+        }
+
+        // Step 5.4.
+        stream.controller.set(Some(&controller));
+        rooted!(in(*cx) let controller_value = ObjectValue(controller.reflector().get_jsobject().get()));
+        rooted!(in(*cx) let mut start_result = UndefinedValue());
+        call_method(
+            cx,
+            source.handle(),
+            start.handle(),
+            &[controller_value.get()],
+            start_result.handle_mut(),
+        )?;
+        controller.start(start_result.handle());
+        Ok(stream)
+    }
+}
+
+impl ReadableStreamMethods for ReadableStream {
+    // https://streams.spec.whatwg.org/#rs-locked
+    fn Locked(&self) -> bool {
+        self.is_locked()
+    }
+
+    // https://streams.spec.whatwg.org/#rs-cancel
+    fn Cancel(&self, _cx: SafeJSContext, reason: HandleValue) -> Rc<Promise> {
+        // Step 1.
+        if self.is_locked() {
+            return new_rejected_promise_with_error(
+                &self.global(),
+                Error::Type("The stream is locked".to_owned()),
+            );
+        }
+
+        // Step 2.
+        self.cancel(reason)
+    }
+
+    // https://streams.spec.whatwg.org/#rs-get-reader
+    fn GetReader(
+        &self,
+        options: &ReadableStreamGetReaderOptions,
+    ) -> Fallible<DomRoot<ReadableStreamDefaultReader>> {
+        // Step 2, which fails as byte streams are not supported.
+        if options.mode.is_some() {
+            return Err(Error::Type(
+                "BYOB readers need a readable byte stream".to_owned(),
+            ));
+        }
+
+        // Step 1.
+        self.acquire_default_reader()
+    }
+
+    // https://streams.spec.whatwg.org/#rs-pipe-through
+    fn PipeThrough(
+        &self,
+        transform: &ReadableWritablePair,
+        options: &StreamPipeOptions,
+    ) -> Fallible<DomRoot<ReadableStream>> {
+        // Step 1.
+        if self.is_locked() {
+            return Err(Error::Type("The stream is locked".to_owned()));
+        }
+
+        // Step 2.
+        if transform.writable.is_locked() {
+            return Err(Error::Type("The writable stream is locked".to_owned()));
+        }
+
+        // Steps 3-5.
+        let promise = self.pipe_to(
+            &transform.writable,
+            options.preventClose,
+            options.preventAbort,
+            options.preventCancel,
+        );
+
+        // Step 6.
+        mark_as_handled(&self.global(), &promise);
+
+        // Step 7.
+        Ok(transform.readable.clone())
+    }
+
+    // https://streams.spec.whatwg.org/#rs-pipe-to
+    fn PipeTo(&self, destination: &WritableStream, options: &StreamPipeOptions) -> Rc<Promise> {
+        let global = self.global();
+
+        // Step 1.
+        if self.is_locked() {
+            return new_rejected_promise_with_error(
+                &global,
+                Error::Type("The stream is locked".to_owned()),
+            );
+        }
+
+        // Step 2.
+        if destination.is_locked() {
+            return new_rejected_promise_with_error(
+                &global,
+                Error::Type("The destination is locked".to_owned()),
+            );
+        }
+
+        // Steps 3-5.
+        self.pipe_to(
+            destination,
+            options.preventClose,
+            options.preventAbort,
+            options.preventCancel,
+        )
+    }
+
+    // https://streams.spec.whatwg.org/#rs-tee
+    fn Tee(&self) -> Fallible<Vec<DomRoot<ReadableStream>>> {
+        let (branch1, branch2) = self.tee()?;
+        Ok(vec![branch1, branch2])
+    }
+}
+
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum TeeBranch {
+    First,
+    Second,
+}
+
+/// The state shared by the two branches of a tee, which read from the
+/// same reader of the teed stream.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub struct TeeSource {
+    stream: Dom<ReadableStream>,
+    reader: Dom<ReadableStreamDefaultReader>,
+    reading: Cell<bool>,
+    read_again: Cell<bool>,
+    canceled1: Cell<bool>,
+    canceled2: Cell<bool>,
+    #[ignore_malloc_size_of = "mozjs"]
+    reason1: Heap<JSVal>,
+    #[ignore_malloc_size_of = "mozjs"]
+    reason2: Heap<JSVal>,
+    branch1: MutNullableDom<ReadableStream>,
+    branch2: MutNullableDom<ReadableStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    cancel_promise: Rc<Promise>,
+}
+
+impl TeeSource {
+    /// The branches that were not canceled.
+    fn live_branches(&self) -> Vec<DomRoot<ReadableStream>> {
+        let mut branches = vec![];
+        if !self.canceled1.get() {
+            branches.extend(self.branch1.get());
+        }
+        if !self.canceled2.get() {
+            branches.extend(self.branch2.get());
+        }
+        branches
+    }
+
+    fn resolve_cancel_promise(&self) {
+        if !self.canceled1.get() || !self.canceled2.get() {
+            self.cancel_promise.resolve_native(&());
+        }
+    }
+
+    /// The pull algorithm of both branches, step 13 of
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-tee>.
+    #[allow(unrooted_must_root)]
+    pub fn pull(self: &Rc<Self>) -> Rc<Promise> {
+        let global = self.stream.global();
+
+        // Step 13.1.
+        if self.reading.get() {
+            self.read_again.set(true);
+            return new_resolved_promise(&global, HandleValue::undefined());
+        }
+
+        // Step 13.2.
+        self.reading.set(true);
+
+        // Steps 13.3-4.
+        let read = self.reader.read();
+        let handler = PromiseNativeHandler::new(
+            &global,
+            Some(Box::new(TeeHandler {
+                source: self.clone(),
+                reaction: TeeReaction::ReadFulfilled,
+            })),
+            Some(Box::new(TeeHandler {
+                source: self.clone(),
+                reaction: TeeReaction::ReadRejected,
+            })),
+        );
+        read.append_native_handler(&handler);
+
+        // Step 13.5.
+        new_resolved_promise(&global, HandleValue::undefined())
+    }
+
+    /// The cancel algorithms of the branches, steps 14 and 15 of
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-tee>.
+    #[allow(unsafe_code)]
+    pub fn cancel(&self, branch: TeeBranch, reason: HandleValue) -> Rc<Promise> {
+        let (canceled, other_canceled) = match branch {
+            TeeBranch::First => {
+                self.reason1.set(reason.get());
+                (&self.canceled1, &self.canceled2)
+            },
+            TeeBranch::Second => {
+                self.reason2.set(reason.get());
+                (&self.canceled2, &self.canceled1)
+            },
+        };
+        canceled.set(true);
+
+        if other_canceled.get() {
+            let global = self.stream.global();
+            let cx = global.get_cx();
+            let _ac = enter_realm(&*global);
+            let reasons = [self.reason1.get(), self.reason2.get()];
+            rooted!(in(*cx) let composite_reason = unsafe {
+                let reasons = HandleValueArray::from_rooted_slice(&reasons);
+                JS_NewArrayObject(*cx, &reasons)
+            });
+            rooted!(in(*cx) let composite_reason = ObjectValue(composite_reason.get()));
+            let cancel_result = self.stream.cancel(composite_reason.handle());
+            rooted!(in(*cx) let cancel_result =
+                ObjectValue(cancel_result.reflector().get_jsobject().get()));
+            self.cancel_promise.resolve(cx, cancel_result.handle());
+        }
+        self.cancel_promise.clone()
+    }
+}
+
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum TeeReaction {
+    ReadFulfilled,
+    ReadRejected,
+    ReaderClosedRejected,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct TeeHandler {
+    #[ignore_malloc_size_of = "Rc"]
+    source: Rc<TeeSource>,
+    reaction: TeeReaction,
+}
+
+impl Callback for TeeHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue, _realm: InRealm) {
+        let cx = unsafe { SafeJSContext::from_ptr(cx) };
+        let source = &self.source;
+        match self.reaction {
+            TeeReaction::ReadFulfilled => {
+                rooted!(in(*cx) let mut chunk = UndefinedValue());
+                let done = unpack_read_result(cx, v, chunk.handle_mut()).unwrap_or(true);
+                if done {
+                    // Close steps.
+                    source.reading.set(false);
+                    for branch in source.live_branches() {
+                        branch.close_native();
+                    }
+                    source.resolve_cancel_promise();
+                    return;
+                }
+
+                // Chunk steps.
+                source.read_again.set(false);
+                for branch in source.live_branches() {
+                    let controller = branch.controller();
+                    if controller.can_close_or_enqueue() {
+                        let _ = controller.enqueue(cx, chunk.handle());
+                    }
+                }
+                source.reading.set(false);
+                if source.read_again.get() {
+                    source.pull();
+                }
+            },
+            TeeReaction::ReadRejected => {
+                // Error steps.
+                source.reading.set(false);
+            },
+            TeeReaction::ReaderClosedRejected => {
+                for branch in [source.branch1.get(), source.branch2.get()]
+                    .iter()
+                    .flatten()
+                {
+                    branch.controller().error(v);
+                }
+                source.resolve_cancel_promise();
+            },
+        }
+    }
+}
+
+/// The state of a pipe, which is settled by the `PipeHandler`s reacting to
+/// the promises of the reader and writer it holds.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct PipeState {
+    source: Dom<ReadableStream>,
+    dest: Dom<WritableStream>,
+    reader: Dom<ReadableStreamDefaultReader>,
+    writer: Dom<WritableStreamDefaultWriter>,
+    prevent_close: bool,
+    prevent_abort: bool,
+    prevent_cancel: bool,
+    shutting_down: Cell<bool>,
+    /// The promise of the last chunk written, which the pipe waits for
+    /// before shutting down.
+    #[ignore_malloc_size_of = "Rc"]
+    pending_write: DomRefCell<Option<Rc<Promise>>>,
+    has_error: Cell<bool>,
+    /// The error the pipe is shut down with.
+    #[ignore_malloc_size_of = "mozjs"]
+    error: Heap<JSVal>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+}
+
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum PipeStep {
+    /// The writer is ready to accept a chunk.
+    WriterReady,
+    /// A chunk was read from the source.
+    Read,
+    /// Nothing to do, the promise is settled because of an error handled
+    /// by another step.
+    Ignore,
+    SourceClosed,
+    SourceErrored,
+    DestClosed,
+    DestErrored,
+    /// The pending writes are done, the action can start.
+    PerformAction(PipeAction),
+    /// The action is done, successfully or not.
+    ActionFulfilled,
+    ActionRejected,
+}
+
+/// The action taken on shutdown, before releasing the reader and the writer.
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum PipeAction {
+    AbortDest,
+    CancelSource,
+    CloseDest,
+    None,
+}
+
+impl PipeState {
+    #[allow(unrooted_must_root)]
+    fn upon(self: &Rc<Self>, promise: &Promise, on_fulfilled: PipeStep, on_rejected: PipeStep) {
+        let handler = PromiseNativeHandler::new(
+            &self.source.global(),
+            Some(Box::new(PipeHandler {
+                state: self.clone(),
+                step: on_fulfilled,
+            })),
+            Some(Box::new(PipeHandler {
+                state: self.clone(),
+                step: on_rejected,
+            })),
+        );
+        promise.append_native_handler(&handler);
+    }
+
+    /// Waits for the writer to be ready before reading the next chunk.
+    fn pump(self: &Rc<Self>) {
+        if self.shutting_down.get() {
+            return;
+        }
+
+        // Closing must be propagated backward.
+        if self.dest.close_queued_or_in_flight() || self.dest.state() == WritableStreamState::Closed
+        {
+            self.dest_closed();
+            return;
+        }
+
+        self.upon(
+            &self.writer.ready_promise(),
+            PipeStep::WriterReady,
+            PipeStep::DestErrored,
+        );
+    }
+
+    fn dest_closed(self: &Rc<Self>) {
+        let global = self.source.global();
+        let cx = global.get_cx();
+        let _ac = enter_realm(&*global);
+        rooted!(in(*cx) let mut error = UndefinedValue());
+        error_to_jsval(
+            &global,
+            Error::Type("The destination stream is closed".to_owned()),
+            error.handle_mut(),
+        );
+        if self.prevent_cancel {
+            self.shutdown(PipeAction::None, Some(error.handle()));
+        } else {
+            self.shutdown(PipeAction::CancelSource, Some(error.handle()));
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-pipeTo-shutdown-with-action>,
+    /// and <https://streams.spec.whatwg.org/#rs-pipeTo-shutdown> when
+    /// `action` is `PipeAction::None`.
+    fn shutdown(self: &Rc<Self>, action: PipeAction, error: Option<HandleValue>) {
+        // Step 1.
+        if self.shutting_down.get() {
+            return;
+        }
+
+        // Step 2.
+        self.shutting_down.set(true);
+        if let Some(error) = error {
+            self.has_error.set(true);
+            self.error.set(error.get());
+        }
+
+        // Step 3.
+        if self.dest.state() == WritableStreamState::Writable &&
+            !self.dest.close_queued_or_in_flight()
+        {
+            let pending_write = self.pending_write.borrow_mut().take();
+            if let Some(pending_write) = pending_write {
+                self.upon(
+                    &pending_write,
+                    PipeStep::PerformAction(action),
+                    PipeStep::PerformAction(action),
+                );
+                return;
+            }
+        }
+
+        // Step 4.
+        self.perform_action(action);
+    }
+
+    fn perform_action(self: &Rc<Self>, action: PipeAction) {
+        let global = self.source.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let error = self.error.get());
+        let promise = match action {
+            PipeAction::AbortDest => self.dest.abort(error.handle()),
+            PipeAction::CancelSource => self.source.cancel(error.handle()),
+            PipeAction::CloseDest => self.writer.close_with_error_propagation(),
+            PipeAction::None => return self.finalize(),
+        };
+        self.upon(
+            &promise,
+            PipeStep::ActionFulfilled,
+            PipeStep::ActionRejected,
+        );
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-pipeTo-finalize>
+    fn finalize(&self) {
+        self.writer.release();
+        self.reader.release();
+        if self.has_error.get() {
+            let cx = self.source.global().get_cx();
+            let _ac = enter_realm(&*self.promise);
+            rooted!(in(*cx) let error = self.error.get());
+            self.promise.reject(cx, error.handle());
+        } else {
+            self.promise.resolve_native(&());
+        }
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct PipeHandler {
+    #[ignore_malloc_size_of = "Rc"]
+    state: Rc<PipeState>,
+    step: PipeStep,
+}
+
+impl Callback for PipeHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue, _realm: InRealm) {
+        let cx = unsafe { SafeJSContext::from_ptr(cx) };
+        let state = &self.state;
+        match self.step {
+            PipeStep::WriterReady => {
+                if state.shutting_down.get() {
+                    return;
+                }
+                let read = state.reader.read();
+                state.upon(&read, PipeStep::Read, PipeStep::Ignore);
+            },
+            PipeStep::Read => {
+                rooted!(in(*cx) let mut chunk = UndefinedValue());
+                // The end of the source is handled when the reader is closed.
+                if unpack_read_result(cx, v, chunk.handle_mut()).unwrap_or(true) {
+                    return;
+                }
+                let write = state.writer.write(cx, chunk.handle());
+                mark_as_handled(&state.source.global(), &write);
+                *state.pending_write.borrow_mut() = Some(write);
+                state.pump();
+            },
+            PipeStep::Ignore => {},
+            // Errors must be propagated forward.
+            PipeStep::SourceErrored => {
+                if state.prevent_abort {
+                    state.shutdown(PipeAction::None, Some(v));
+                } else {
+                    state.shutdown(PipeAction::AbortDest, Some(v));
+                }
+            },
+            // Errors must be propagated backward.
+            PipeStep::DestErrored => {
+                if state.prevent_cancel {
+                    state.shutdown(PipeAction::None, Some(v));
+                } else {
+                    state.shutdown(PipeAction::CancelSource, Some(v));
+                }
+            },
+            // Closing must be propagated forward.
+            PipeStep::SourceClosed => {
+                if state.prevent_close {
+                    state.shutdown(PipeAction::None, None);
+                } else {
+                    state.shutdown(PipeAction::CloseDest, None);
+                }
+            },
+            PipeStep::DestClosed => state.dest_closed(),
+            PipeStep::PerformAction(action) => state.perform_action(action),
+            PipeStep::ActionFulfilled => state.finalize(),
+            PipeStep::ActionRejected => {
+                state.has_error.set(true);
+                state.error.set(v.get());
+                state.finalize();
+            },
+        }
+    }
+}
@@ -0,0 +1,426 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategySize;
+use crate::dom::bindings::codegen::Bindings::ReadableStreamDefaultControllerBinding::ReadableStreamDefaultControllerMethods;
+use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::trace::RootedTraceableBox;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestream::{ReadableStream, ReadableStreamState, TeeBranch, TeeSource};
+use crate::dom::transformstream::TransformStream;
+use crate::realms::InRealm;
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::streams::{
+    chunk_size, error_to_jsval, new_resolved_promise, read_result, rethrow, QueueWithSizes,
+    UnderlyingMethod,
+};
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::{HandleObject, HandleValue};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Where the pull and cancel algorithms of a controller come from.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub enum SourceAlgorithms {
+    /// The methods of the underlying source given to the `ReadableStream`
+    /// constructor.
+    Object,
+    /// A branch of a teed stream.
+    Tee(#[ignore_malloc_size_of = "Rc"] Rc<TeeSource>, TeeBranch),
+    /// The readable side of a transform stream.
+    Transform(Dom<TransformStream>),
+    /// Chunks are enqueued by Rust code, pulling and canceling do nothing.
+    None,
+}
+
+/// A copy of the `SourceAlgorithms` of a controller.
+#[unrooted_must_root_lint::must_root]
+enum Algorithms {
+    Object,
+    Tee(Rc<TeeSource>, TeeBranch),
+    Transform(DomRoot<TransformStream>),
+    None,
+}
+
+#[dom_struct]
+pub struct ReadableStreamDefaultController {
+    reflector_: Reflector,
+    stream: Dom<ReadableStream>,
+    queue: DomRefCell<QueueWithSizes>,
+    started: Cell<bool>,
+    pulling: Cell<bool>,
+    pull_again: Cell<bool>,
+    close_requested: Cell<bool>,
+    strategy_hwm: f64,
+    #[ignore_malloc_size_of = "Rc"]
+    strategy_size: DomRefCell<Option<Rc<QueuingStrategySize>>>,
+    algorithms: DomRefCell<SourceAlgorithms>,
+    pull: UnderlyingMethod,
+    cancel: UnderlyingMethod,
+}
+
+impl ReadableStreamDefaultController {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(
+        stream: &ReadableStream,
+        algorithms: SourceAlgorithms,
+        high_water_mark: f64,
+        size: Option<Rc<QueuingStrategySize>>,
+    ) -> ReadableStreamDefaultController {
+        ReadableStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: Dom::from_ref(stream),
+            queue: Default::default(),
+            started: Cell::new(false),
+            pulling: Cell::new(false),
+            pull_again: Cell::new(false),
+            close_requested: Cell::new(false),
+            strategy_hwm: high_water_mark,
+            strategy_size: DomRefCell::new(size),
+            algorithms: DomRefCell::new(algorithms),
+            pull: Default::default(),
+            cancel: Default::default(),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+        algorithms: SourceAlgorithms,
+        high_water_mark: f64,
+        size: Option<Rc<QueuingStrategySize>>,
+    ) -> DomRoot<ReadableStreamDefaultController> {
+        reflect_dom_object(
+            Box::new(ReadableStreamDefaultController::new_inherited(
+                stream,
+                algorithms,
+                high_water_mark,
+                size,
+            )),
+            global,
+        )
+    }
+
+    /// Steps 1-6 of
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-default-controller-from-underlying-source>,
+    /// the start method is looked up and called by the constructor.
+    pub fn init_underlying_source(&self, cx: SafeJSContext, source: HandleObject) -> ErrorResult {
+        self.cancel.init(cx, source, "cancel")?;
+        self.pull.init(cx, source, "pull")?;
+        Ok(())
+    }
+
+    /// Steps 9-12 of
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-default-controller>,
+    /// given the result of the start algorithm.
+    #[allow(unrooted_must_root)]
+    pub fn start(&self, start_result: HandleValue) {
+        let global = self.global();
+        let start_promise = new_resolved_promise(&global, start_result);
+        self.upon_settlement(
+            &start_promise,
+            Reaction::StartFulfilled,
+            Reaction::StartRejected,
+        );
+    }
+
+    #[allow(unrooted_must_root)]
+    fn upon_settlement(&self, promise: &Promise, on_fulfilled: Reaction, on_rejected: Reaction) {
+        let handler = PromiseNativeHandler::new(
+            &self.global(),
+            Some(Box::new(ControllerHandler {
+                controller: Dom::from_ref(self),
+                reaction: on_fulfilled,
+            })),
+            Some(Box::new(ControllerHandler {
+                controller: Dom::from_ref(self),
+                reaction: on_rejected,
+            })),
+        );
+        promise.append_native_handler(&handler);
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-call-pull-if-needed>
+    fn call_pull_if_needed(&self) {
+        // Steps 1-2.
+        if !self.should_call_pull() {
+            return;
+        }
+
+        // Step 3.
+        if self.pulling.get() {
+            self.pull_again.set(true);
+            return;
+        }
+
+        // Steps 4-7.
+        self.pulling.set(true);
+        let pull_promise = self.pull_algorithm();
+        self.upon_settlement(
+            &pull_promise,
+            Reaction::PullFulfilled,
+            Reaction::PullRejected,
+        );
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-should-call-pull>
+    fn should_call_pull(&self) -> bool {
+        // Steps 2-4.
+        if !self.can_close_or_enqueue() || !self.started.get() {
+            return false;
+        }
+
+        // Step 5.
+        if self.stream.is_locked() && self.stream.num_read_requests() > 0 {
+            return true;
+        }
+
+        // Steps 6-9.
+        self.desired_size().map_or(false, |size| size > 0.)
+    }
+
+    /// Returns the algorithms to run, taken out of the cell as running them
+    /// may clear it.
+    #[allow(unrooted_must_root)]
+    fn algorithms(&self) -> Algorithms {
+        match *self.algorithms.borrow() {
+            SourceAlgorithms::Object => Algorithms::Object,
+            SourceAlgorithms::Tee(ref source, branch) => Algorithms::Tee(source.clone(), branch),
+            SourceAlgorithms::Transform(ref stream) => {
+                Algorithms::Transform(DomRoot::from_ref(&**stream))
+            },
+            SourceAlgorithms::None => Algorithms::None,
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    fn pull_algorithm(&self) -> Rc<Promise> {
+        let global = self.global();
+        match self.algorithms() {
+            Algorithms::Object => {
+                let controller = ObjectValue(self.reflector().get_jsobject().get());
+                self.pull.promise_call(&global, &[controller])
+            },
+            Algorithms::Tee(source, _) => source.pull(),
+            Algorithms::Transform(stream) => stream.source_pull(),
+            Algorithms::None => new_resolved_promise(&global, HandleValue::undefined()),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    fn cancel_algorithm(&self, reason: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        match self.algorithms() {
+            Algorithms::Object => self.cancel.promise_call(&global, &[reason.get()]),
+            Algorithms::Tee(source, branch) => source.cancel(branch, reason),
+            Algorithms::Transform(stream) => stream.source_cancel(reason),
+            Algorithms::None => new_resolved_promise(&global, HandleValue::undefined()),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-clear-algorithms>
+    fn clear_algorithms(&self) {
+        *self.algorithms.borrow_mut() = SourceAlgorithms::None;
+        *self.strategy_size.borrow_mut() = None;
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-can-close-or-enqueue>
+    pub fn can_close_or_enqueue(&self) -> bool {
+        !self.close_requested.get() && self.stream.state() == ReadableStreamState::Readable
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-has-backpressure>
+    pub fn has_backpressure(&self) -> bool {
+        !self.should_call_pull()
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-get-desired-size>
+    pub fn desired_size(&self) -> Option<f64> {
+        match self.stream.state() {
+            ReadableStreamState::Errored => None,
+            ReadableStreamState::Closed => Some(0.),
+            ReadableStreamState::Readable => {
+                Some(self.strategy_hwm - self.queue.borrow().total_size())
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-close>
+    pub fn close(&self) {
+        // Step 1.
+        if !self.can_close_or_enqueue() {
+            return;
+        }
+
+        // Step 3.
+        self.close_requested.set(true);
+
+        // Step 4.
+        if self.queue.borrow().is_empty() {
+            self.clear_algorithms();
+            self.stream.close();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-enqueue>,
+    /// when the size algorithm throws or returns an invalid size, the stream
+    /// is errored and the thrown value is returned.
+    pub fn enqueue(
+        &self,
+        cx: SafeJSContext,
+        chunk: HandleValue,
+    ) -> Result<(), RootedTraceableBox<Heap<JSVal>>> {
+        // Step 1.
+        if !self.can_close_or_enqueue() {
+            return Ok(());
+        }
+
+        // Step 3.
+        if self.stream.is_locked() && self.stream.num_read_requests() > 0 {
+            self.stream.fulfill_read_request(chunk, false);
+        } else {
+            // Step 4.
+            let size = self.strategy_size.borrow().clone();
+            let result = chunk_size(size.as_ref(), chunk)
+                .and_then(|size| self.queue.borrow_mut().enqueue(chunk, size));
+            if let Err(error) = result {
+                rooted!(in(*cx) let mut value = UndefinedValue());
+                error_to_jsval(&self.global(), error, value.handle_mut());
+                self.error(value.handle());
+                return Err(RootedTraceableBox::from_box(Heap::boxed(value.get())));
+            }
+        }
+
+        // Step 5.
+        self.call_pull_if_needed();
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-controller-error>
+    pub fn error(&self, e: HandleValue) {
+        // Step 2.
+        if self.stream.state() != ReadableStreamState::Readable {
+            return;
+        }
+
+        // Steps 3-5.
+        self.queue.borrow_mut().reset();
+        self.clear_algorithms();
+        self.stream.error(e);
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-private-cancel>
+    pub fn cancel_steps(&self, reason: HandleValue) -> Rc<Promise> {
+        // Step 1.
+        self.queue.borrow_mut().reset();
+
+        // Steps 2-4.
+        let result = self.cancel_algorithm(reason);
+        self.clear_algorithms();
+        result
+    }
+
+    /// <https://streams.spec.whatwg.org/#rs-default-controller-private-pull>
+    pub fn pull_steps(&self, cx: SafeJSContext, read_request: &Rc<Promise>) {
+        // Step 2.
+        if !self.queue.borrow().is_empty() {
+            rooted!(in(*cx) let mut chunk = UndefinedValue());
+            self.queue.borrow_mut().dequeue(chunk.handle_mut());
+            if self.close_requested.get() && self.queue.borrow().is_empty() {
+                self.clear_algorithms();
+                self.stream.close();
+            } else {
+                self.call_pull_if_needed();
+            }
+            rooted!(in(*cx) let mut result = UndefinedValue());
+            read_result(cx, chunk.handle(), false, result.handle_mut());
+            read_request.resolve(cx, result.handle());
+            return;
+        }
+
+        // Step 3.
+        self.stream.add_read_request(read_request);
+        self.call_pull_if_needed();
+    }
+}
+
+impl ReadableStreamDefaultControllerMethods for ReadableStreamDefaultController {
+    // https://streams.spec.whatwg.org/#rs-default-controller-desired-size
+    fn GetDesiredSize(&self) -> Option<f64> {
+        self.desired_size()
+    }
+
+    // https://streams.spec.whatwg.org/#rs-default-controller-close
+    fn Close(&self) -> ErrorResult {
+        // Step 1.
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("The stream can't be closed".to_owned()));
+        }
+
+        // Step 2.
+        self.close();
+        Ok(())
+    }
+
+    // https://streams.spec.whatwg.org/#rs-default-controller-enqueue
+    fn Enqueue(&self, cx: SafeJSContext, chunk: HandleValue) -> ErrorResult {
+        // Step 1.
+        if !self.can_close_or_enqueue() {
+            return Err(Error::Type("The stream can't be enqueued to".to_owned()));
+        }
+
+        // Step 2.
+        self.enqueue(cx, chunk)
+            .map_err(|error| rethrow(cx, error.handle()))
+    }
+
+    // https://streams.spec.whatwg.org/#rs-default-controller-error
+    fn Error(&self, _cx: SafeJSContext, e: HandleValue) {
+        self.error(e);
+    }
+}
+
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum Reaction {
+    StartFulfilled,
+    StartRejected,
+    PullFulfilled,
+    PullRejected,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct ControllerHandler {
+    controller: Dom<ReadableStreamDefaultController>,
+    reaction: Reaction,
+}
+
+impl Callback for ControllerHandler {
+    fn callback(&self, _cx: *mut JSContext, v: HandleValue, _realm: InRealm) {
+        let controller = &self.controller;
+        match self.reaction {
+            Reaction::StartFulfilled => {
+                controller.started.set(true);
+                controller.call_pull_if_needed();
+            },
+            Reaction::PullFulfilled => {
+                controller.pulling.set(false);
+                if controller.pull_again.get() {
+                    controller.pull_again.set(false);
+                    controller.call_pull_if_needed();
+                }
+            },
+            Reaction::StartRejected | Reaction::PullRejected => controller.error(v),
+        }
+    }
+}
@@ -0,0 +1,332 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::ReadableStreamDefaultReaderBinding::ReadableStreamDefaultReaderMethods;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestream::{ReadableStream, ReadableStreamState};
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::streams::{
+    mark_as_handled, new_rejected_promise, new_rejected_promise_with_error, read_result,
+    unpack_read_result,
+};
+use dom_struct::dom_struct;
+use js::jsapi::{JSContext, JSObject};
+use js::jsval::{ObjectValue, UndefinedValue};
+use js::rust::HandleValue;
+use js::typedarray::{CreateWith, Uint8Array};
+use std::collections::VecDeque;
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct ReadableStreamDefaultReader {
+    reflector_: Reflector,
+    stream: MutNullableDom<ReadableStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    closed_promise: DomRefCell<Rc<Promise>>,
+    #[ignore_malloc_size_of = "Rc"]
+    read_requests: DomRefCell<VecDeque<Rc<Promise>>>,
+}
+
+impl ReadableStreamDefaultReader {
+    fn new_inherited(global: &GlobalScope) -> ReadableStreamDefaultReader {
+        ReadableStreamDefaultReader {
+            reflector_: Reflector::new(),
+            stream: Default::default(),
+            closed_promise: DomRefCell::new(Promise::new(global)),
+            read_requests: DomRefCell::new(VecDeque::new()),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<ReadableStreamDefaultReader> {
+        reflect_dom_object(
+            Box::new(ReadableStreamDefaultReader::new_inherited(global)),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#acquire-readable-stream-reader>
+    pub fn acquire(stream: &ReadableStream) -> Fallible<DomRoot<ReadableStreamDefaultReader>> {
+        let reader = ReadableStreamDefaultReader::new(&stream.global());
+        reader.set_up(stream)?;
+        Ok(reader)
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-readable-stream-default-reader>
+    fn set_up(&self, stream: &ReadableStream) -> Fallible<()> {
+        // Step 1.
+        if stream.is_locked() {
+            return Err(Error::Type("The stream is locked".to_owned()));
+        }
+
+        // Step 2, <https://streams.spec.whatwg.org/#readable-stream-reader-generic-initialize>.
+        self.stream.set(Some(stream));
+        stream.set_reader(Some(self));
+        let global = self.global();
+        match stream.state() {
+            // The closed promise is created pending.
+            ReadableStreamState::Readable => {},
+            ReadableStreamState::Closed => self.closed_promise.borrow().resolve_native(&()),
+            ReadableStreamState::Errored => {
+                let cx = global.get_cx();
+                rooted!(in(*cx) let mut error = UndefinedValue());
+                stream.stored_error(error.handle_mut());
+                let promise = new_rejected_promise(&global, error.handle());
+                mark_as_handled(&global, &promise);
+                *self.closed_promise.borrow_mut() = promise;
+            },
+        }
+        Ok(())
+    }
+
+    pub fn closed_promise(&self) -> Rc<Promise> {
+        self.closed_promise.borrow().clone()
+    }
+
+    pub fn num_read_requests(&self) -> usize {
+        self.read_requests.borrow().len()
+    }
+
+    pub fn add_read_request(&self, request: &Rc<Promise>) {
+        self.read_requests.borrow_mut().push_back(request.clone());
+    }
+
+    /// Runs the chunk or close steps of the oldest read request.
+    pub fn fulfill_read_request(&self, chunk: HandleValue, done: bool) {
+        let request = self
+            .read_requests
+            .borrow_mut()
+            .pop_front()
+            .expect("No read request to fulfill");
+        resolve_read_request(&request, chunk, done);
+    }
+
+    /// The steps of <https://streams.spec.whatwg.org/#readable-stream-close>
+    /// concerning the reader.
+    pub fn close(&self) {
+        let requests = mem::take(&mut *self.read_requests.borrow_mut());
+        for request in requests {
+            resolve_read_request(&request, HandleValue::undefined(), true);
+        }
+        self.closed_promise.borrow().resolve_native(&());
+    }
+
+    /// The steps of <https://streams.spec.whatwg.org/#readable-stream-error>
+    /// concerning the reader.
+    pub fn error(&self, e: HandleValue) {
+        let global = self.global();
+        let cx = global.get_cx();
+        let _ac = enter_realm(self);
+        let closed_promise = self.closed_promise();
+        closed_promise.reject(cx, e);
+        mark_as_handled(&global, &closed_promise);
+        let requests = mem::take(&mut *self.read_requests.borrow_mut());
+        for request in requests {
+            request.reject(cx, e);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readable-stream-default-reader-read>,
+    /// returning the promise the read request settles.
+    pub fn read(&self) -> Rc<Promise> {
+        let global = self.global();
+        let cx = global.get_cx();
+        let _ac = enter_realm(self);
+        let promise = Promise::new(&global);
+
+        // Steps 1-2.
+        let stream = self.stream.get().expect("Reading from a released reader");
+        stream.set_disturbed();
+
+        match stream.state() {
+            // Step 4.
+            ReadableStreamState::Closed => {
+                resolve_read_request(&promise, HandleValue::undefined(), true)
+            },
+            // Step 5.
+            ReadableStreamState::Errored => {
+                rooted!(in(*cx) let mut error = UndefinedValue());
+                stream.stored_error(error.handle_mut());
+                promise.reject(cx, error.handle());
+            },
+            // Step 6.
+            ReadableStreamState::Readable => stream.controller().pull_steps(cx, &promise),
+        }
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#abstract-opdef-readablestreamdefaultreaderrelease>
+    pub fn release(&self) {
+        let global = self.global();
+        let stream = self.stream.get().expect("Releasing a released reader");
+
+        // <https://streams.spec.whatwg.org/#readable-stream-reader-generic-release>
+        let error = || Error::Type("The reader's lock was released".to_owned());
+        if stream.state() == ReadableStreamState::Readable {
+            self.closed_promise.borrow().reject_error(error());
+        } else {
+            *self.closed_promise.borrow_mut() = new_rejected_promise_with_error(&global, error());
+        }
+        mark_as_handled(&global, &self.closed_promise());
+        stream.set_reader(None);
+        self.stream.set(None);
+
+        // <https://streams.spec.whatwg.org/#abstract-opdef-readablestreamdefaultreadererrorreadrequests>
+        let requests = mem::take(&mut *self.read_requests.borrow_mut());
+        for request in requests {
+            request.reject_error(error());
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#readablestreamdefaultreader-read-all-bytes>,
+    /// returning a promise resolved with a `Uint8Array` of the bytes read.
+    pub fn read_all_bytes(&self) -> Rc<Promise> {
+        let promise = Promise::new(&self.global());
+        self.read_loop(Vec::new(), promise.clone());
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#read-loop>
+    #[allow(unrooted_must_root)]
+    fn read_loop(&self, bytes: Vec<u8>, promise: Rc<Promise>) {
+        let handler = PromiseNativeHandler::new(
+            &self.global(),
+            Some(Box::new(ReadAllBytesHandler {
+                reader: Dom::from_ref(self),
+                bytes: DomRefCell::new(bytes),
+                promise: promise.clone(),
+                fulfilled: true,
+            })),
+            Some(Box::new(ReadAllBytesHandler {
+                reader: Dom::from_ref(self),
+                bytes: DomRefCell::new(vec![]),
+                promise,
+                fulfilled: false,
+            })),
+        );
+        self.read().append_native_handler(&handler);
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-reader-constructor>
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        stream: &ReadableStream,
+    ) -> Fallible<DomRoot<ReadableStreamDefaultReader>> {
+        let reader = ReadableStreamDefaultReader::new(global);
+        reader.set_up(stream)?;
+        Ok(reader)
+    }
+}
+
+/// Resolves the promise of a read request with a `{ value, done }` object.
+fn resolve_read_request(request: &Promise, chunk: HandleValue, done: bool) {
+    let cx = request.global().get_cx();
+    let _ac = enter_realm(request);
+    rooted!(in(*cx) let mut result = UndefinedValue());
+    read_result(cx, chunk, done, result.handle_mut());
+    request.resolve(cx, result.handle());
+}
+
+impl ReadableStreamDefaultReaderMethods for ReadableStreamDefaultReader {
+    // https://streams.spec.whatwg.org/#default-reader-read
+    fn Read(&self) -> Rc<Promise> {
+        // Step 1.
+        if self.stream.get().is_none() {
+            return new_rejected_promise_with_error(
+                &self.global(),
+                Error::Type("The reader's lock was released".to_owned()),
+            );
+        }
+
+        // Steps 2-4.
+        self.read()
+    }
+
+    // https://streams.spec.whatwg.org/#default-reader-release-lock
+    fn ReleaseLock(&self) {
+        if self.stream.get().is_some() {
+            self.release();
+        }
+    }
+
+    // https://streams.spec.whatwg.org/#generic-reader-closed
+    fn Closed(&self) -> Rc<Promise> {
+        self.closed_promise()
+    }
+
+    // https://streams.spec.whatwg.org/#generic-reader-cancel
+    fn Cancel(&self, _cx: SafeJSContext, reason: HandleValue) -> Rc<Promise> {
+        match self.stream.get() {
+            Some(stream) => stream.cancel(reason),
+            None => new_rejected_promise_with_error(
+                &self.global(),
+                Error::Type("The reader's lock was released".to_owned()),
+            ),
+        }
+    }
+}
+
+/// Accumulates the chunks read by `read_all_bytes`, each of which must be
+/// a `Uint8Array`.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct ReadAllBytesHandler {
+    reader: Dom<ReadableStreamDefaultReader>,
+    bytes: DomRefCell<Vec<u8>>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl Callback for ReadAllBytesHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue, _realm: InRealm) {
+        let cx = unsafe { SafeJSContext::from_ptr(cx) };
+        if !self.fulfilled {
+            return self.promise.reject(cx, v);
+        }
+
+        rooted!(in(*cx) let mut chunk = UndefinedValue());
+        let done = match unpack_read_result(cx, v, chunk.handle_mut()) {
+            Ok(done) => done,
+            Err(error) => return self.promise.reject_error(error),
+        };
+        let mut bytes = mem::take(&mut *self.bytes.borrow_mut());
+
+        if done {
+            rooted!(in(*cx) let mut array = ptr::null_mut::<JSObject>());
+            unsafe {
+                Uint8Array::create(*cx, CreateWith::Slice(&bytes), array.handle_mut())
+                    .expect("Failed to create a Uint8Array");
+            }
+            rooted!(in(*cx) let array = ObjectValue(array.get()));
+            return self.promise.resolve(cx, array.handle());
+        }
+
+        if !chunk.is_object() {
+            return self
+                .promise
+                .reject_error(Error::Type("The chunk is not a Uint8Array".to_owned()));
+        }
+        typedarray!(in(*cx) let array: Uint8Array = chunk.to_object());
+        match array {
+            Ok(array) => bytes.extend_from_slice(unsafe { array.as_slice() }),
+            Err(()) => {
+                return self
+                    .promise
+                    .reject_error(Error::Type("The chunk is not a Uint8Array".to_owned()));
+            },
+        }
+        self.reader.read_loop(bytes, self.promise.clone());
+    }
+}
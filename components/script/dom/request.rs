@@ -2,7 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::body::{consume_body, BodyOperations, BodyType};
+use crate::body::{consume_body, extract_body, BodyOperations, BodyType, ExtractedBody};
+use crate::dom::abortsignal::AbortSignal;
 use crate::dom::bindings::cell::{DomRefCell, Ref};
 use crate::dom::bindings::codegen::Bindings::HeadersBinding::{HeadersInit, HeadersMethods};
 use crate::dom::bindings::codegen::Bindings::RequestBinding::ReferrerPolicy;
@@ -16,13 +17,13 @@ use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestMode;
 use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestRedirect;
 use crate::dom::bindings::error::{Error, Fallible};
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
-use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::str::{ByteString, DOMString, USVString};
 use crate::dom::bindings::trace::RootedTraceableBox;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::headers::{Guard, Headers};
 use crate::dom::promise::Promise;
-use crate::dom::xmlhttprequest::Extractable;
+use crate::dom::readablestream::ReadableStream;
 use dom_struct::dom_struct;
 use http::header::{HeaderName, HeaderValue};
 use http::method::InvalidMethod;
@@ -37,7 +38,6 @@ use net_traits::request::RequestMode as NetTraitsRequestMode;
 use net_traits::request::{Origin, Window};
 use net_traits::ReferrerPolicy as MsgReferrerPolicy;
 use servo_url::ServoUrl;
-use std::cell::Cell;
 use std::rc::Rc;
 use std::str::FromStr;
 
@@ -45,27 +45,32 @@ use std::str::FromStr;
 pub struct Request {
     reflector_: Reflector,
     request: DomRefCell<NetTraitsRequest>,
-    body_used: Cell<bool>,
+    /// The stream of the body, `None` for a null body. The bytes of the
+    /// body are also kept in `request` unless it was given as a stream.
+    body_stream: MutNullableDom<ReadableStream>,
     headers: MutNullableDom<Headers>,
+    signal: Dom<AbortSignal>,
     mime_type: DomRefCell<Vec<u8>>,
-    #[ignore_malloc_size_of = "Rc"]
-    body_promise: DomRefCell<Option<(Rc<Promise>, BodyType)>>,
 }
 
 impl Request {
-    fn new_inherited(global: &GlobalScope, url: ServoUrl) -> Request {
+    fn new_inherited(global: &GlobalScope, url: ServoUrl, signal: &AbortSignal) -> Request {
         Request {
             reflector_: Reflector::new(),
             request: DomRefCell::new(net_request_from_global(global, url)),
-            body_used: Cell::new(false),
+            body_stream: Default::default(),
             headers: Default::default(),
+            signal: Dom::from_ref(signal),
             mime_type: DomRefCell::new("".to_string().into_bytes()),
-            body_promise: DomRefCell::new(None),
         }
     }
 
     pub fn new(global: &GlobalScope, url: ServoUrl) -> DomRoot<Request> {
-        reflect_dom_object(Box::new(Request::new_inherited(global, url)), global)
+        let signal = AbortSignal::new(global);
+        reflect_dom_object(
+            Box::new(Request::new_inherited(global, url, &signal)),
+            global,
+        )
     }
 
     // https://fetch.spec.whatwg.org/#dom-request
@@ -87,7 +92,8 @@ impl Request {
         // Step 4
         let base_url = global.api_base_url();
 
-        // Step 5 TODO: "Let signal be null."
+        // Step 5
+        let mut signal: Option<DomRoot<AbortSignal>> = None;
 
         match input {
             // Step 6
@@ -119,7 +125,8 @@ impl Request {
                 }
                 // Step 7.1
                 temporary_request = input_request.request.borrow().clone();
-                // Step 7.2 TODO: "Set signal to input's signal."
+                // Step 7.2
+                signal = Some(input_request.Signal());
             },
         }
 
@@ -298,12 +305,18 @@ impl Request {
             request.method = method;
         }
 
-        // Step 28 TODO: "If init["signal"] exists..."
+        // Step 28
+        if let Some(ref init_signal) = init.signal {
+            signal = init_signal.clone();
+        }
 
         // Step 29
         let r = Request::from_net_request(global, request);
 
-        // Step 30 TODO: "If signal is not null..."
+        // Step 30
+        if let Some(signal) = signal {
+            r.signal.follow(&signal);
+        }
 
         // Step 31
         // "or_init" looks unclear here, but it always enters the block since r
@@ -385,10 +398,15 @@ impl Request {
         } else {
             None
         };
+        let mut input_stream = if let RequestInfo::Request(ref input_request) = input {
+            input_request.body_stream.get()
+        } else {
+            None
+        };
 
         // Step 34
         if let Some(init_body_option) = init.body.as_ref() {
-            if init_body_option.is_some() || input_body.is_some() {
+            if init_body_option.is_some() || input_stream.is_some() {
                 let req = r.request.borrow();
                 let req_method = &req.method;
                 match *req_method {
@@ -412,9 +430,17 @@ impl Request {
             // Step 36.2 TODO "If init["keepalive"] exists and is true..."
 
             // Step 36.3
-            let extracted_body_tmp = init_body.extract();
-            input_body = Some(extracted_body_tmp.0);
-            let content_type = extracted_body_tmp.1;
+            let (extracted_body, content_type) = extract_body(init_body)?;
+            match extracted_body {
+                ExtractedBody::Bytes(bytes) => {
+                    input_stream = Some(ReadableStream::new_from_bytes(global, bytes.clone()));
+                    input_body = Some(bytes);
+                },
+                ExtractedBody::Stream(stream) => {
+                    input_stream = Some(stream);
+                    input_body = None;
+                },
+            }
 
             // Step 36.4
             if let Some(contents) = content_type {
@@ -450,10 +476,12 @@ impl Request {
         // Step 38 is done earlier
 
         // Step 39
-        // TODO: `ReadableStream` object is not implemented in Servo yet.
+        // The stream of the input request is shared rather than proxied,
+        // reading from either request disturbs both of them.
 
         // Step 40
         r.request.borrow_mut().body = input_body;
+        r.body_stream.set(input_stream.as_deref());
 
         // Step 41
         let extracted_mime_type = r.Headers().extract_mime_type();
@@ -465,9 +493,7 @@ impl Request {
 
     // https://fetch.spec.whatwg.org/#concept-body-locked
     fn locked(&self) -> bool {
-        // TODO: ReadableStream is unimplemented. Just return false
-        // for now.
-        false
+        request_is_locked(self)
     }
 }
 
@@ -481,7 +507,6 @@ impl Request {
     fn clone_from(r: &Request) -> Fallible<DomRoot<Request>> {
         let req = r.request.borrow();
         let url = req.url();
-        let body_used = r.body_used.get();
         let mime_type = r.mime_type.borrow().clone();
         let headers_guard = r.Headers().get_guard();
         let r_clone = Request::new(&r.global(), url);
@@ -491,8 +516,13 @@ impl Request {
             borrowed_r_request.origin = req.origin.clone();
         }
         *r_clone.request.borrow_mut() = req.clone();
-        r_clone.body_used.set(body_used);
+        if let Some(stream) = r.body_stream.get() {
+            let (branch1, branch2) = stream.tee()?;
+            r.body_stream.set(Some(&branch1));
+            r_clone.body_stream.set(Some(&branch2));
+        }
         *r_clone.mime_type.borrow_mut() = mime_type;
+        r_clone.signal.follow(&r.signal);
         r_clone
             .Headers()
             .fill(Some(HeadersInit::Headers(r.Headers())))?;
@@ -552,16 +582,20 @@ fn includes_credentials(input: &ServoUrl) -> bool {
     !input.username().is_empty() || input.password().is_some()
 }
 
-// TODO: `Readable Stream` object is not implemented in Servo yet.
 // https://fetch.spec.whatwg.org/#concept-body-disturbed
-fn request_is_disturbed(_input: &Request) -> bool {
-    false
+fn request_is_disturbed(input: &Request) -> bool {
+    input
+        .body_stream
+        .get()
+        .map_or(false, |stream| stream.is_disturbed())
 }
 
-// TODO: `Readable Stream` object is not implemented in Servo yet.
 // https://fetch.spec.whatwg.org/#concept-body-locked
-fn request_is_locked(_input: &Request) -> bool {
-    false
+fn request_is_locked(input: &Request) -> bool {
+    input
+        .body_stream
+        .get()
+        .map_or(false, |stream| stream.is_locked())
 }
 
 impl RequestMethods for Request {
@@ -638,9 +672,19 @@ impl RequestMethods for Request {
         DOMString::from_string(r.integrity_metadata.clone())
     }
 
+    // https://fetch.spec.whatwg.org/#dom-body-body
+    fn GetBody(&self) -> Option<DomRoot<ReadableStream>> {
+        self.body_stream.get()
+    }
+
     // https://fetch.spec.whatwg.org/#dom-body-bodyused
     fn BodyUsed(&self) -> bool {
-        self.body_used.get()
+        request_is_disturbed(self)
+    }
+
+    // https://fetch.spec.whatwg.org/#dom-request-signal
+    fn Signal(&self) -> DomRoot<AbortSignal> {
+        DomRoot::from_ref(&*self.signal)
     }
 
     // https://fetch.spec.whatwg.org/#dom-request-clone
@@ -688,20 +732,12 @@ impl BodyOperations for Request {
         self.BodyUsed()
     }
 
-    fn set_body_promise(&self, p: &Rc<Promise>, body_type: BodyType) {
-        assert!(self.body_promise.borrow().is_none());
-        self.body_used.set(true);
-        *self.body_promise.borrow_mut() = Some((p.clone(), body_type));
-    }
-
     fn is_locked(&self) -> bool {
         self.locked()
     }
 
-    fn take_body(&self) -> Option<Vec<u8>> {
-        let mut request = self.request.borrow_mut();
-        let body = request.body.take();
-        Some(body.unwrap_or(vec![]))
+    fn body_stream(&self) -> Option<DomRoot<ReadableStream>> {
+        self.body_stream.get()
     }
 
     fn get_mime_type(&self) -> Ref<Vec<u8>> {
@@ -2,14 +2,13 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::body::{consume_body, consume_body_with_promise, BodyOperations, BodyType};
+use crate::body::{consume_body, extract_body, BodyOperations, BodyType, ExtractedBody};
 use crate::dom::bindings::cell::{DomRefCell, Ref};
 use crate::dom::bindings::codegen::Bindings::HeadersBinding::{HeadersInit, HeadersMethods};
 use crate::dom::bindings::codegen::Bindings::ResponseBinding;
 use crate::dom::bindings::codegen::Bindings::ResponseBinding::{
-    ResponseMethods, ResponseType as DOMResponseType,
+    ResponseBodyInit, ResponseMethods, ResponseType as DOMResponseType,
 };
-use crate::dom::bindings::codegen::Bindings::XMLHttpRequestBinding::BodyInit;
 use crate::dom::bindings::error::{Error, Fallible};
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
 use crate::dom::bindings::root::{DomRoot, MutNullableDom};
@@ -18,16 +17,13 @@ use crate::dom::globalscope::GlobalScope;
 use crate::dom::headers::{is_obs_text, is_vchar};
 use crate::dom::headers::{Guard, Headers};
 use crate::dom::promise::Promise;
-use crate::dom::xmlhttprequest::Extractable;
+use crate::dom::readablestream::ReadableStream;
 use crate::script_runtime::StreamConsumer;
 use dom_struct::dom_struct;
 use http::header::HeaderMap as HyperHeaders;
 use hyper::StatusCode;
 use hyper_serde::Serde;
-use net_traits::response::ResponseBody as NetTraitsResponseBody;
 use servo_url::ServoUrl;
-use std::cell::Cell;
-use std::mem;
 use std::rc::Rc;
 use std::str::FromStr;
 use url::Position;
@@ -37,7 +33,6 @@ pub struct Response {
     reflector_: Reflector,
     headers_reflector: MutNullableDom<Headers>,
     mime_type: DomRefCell<Vec<u8>>,
-    body_used: Cell<bool>,
     /// `None` can be considered a StatusCode of `0`.
     #[ignore_malloc_size_of = "Defined in hyper"]
     status: DomRefCell<Option<StatusCode>>,
@@ -45,10 +40,8 @@ pub struct Response {
     response_type: DomRefCell<DOMResponseType>,
     url: DomRefCell<Option<ServoUrl>>,
     url_list: DomRefCell<Vec<ServoUrl>>,
-    // For now use the existing NetTraitsResponseBody enum
-    body: DomRefCell<NetTraitsResponseBody>,
-    #[ignore_malloc_size_of = "Rc"]
-    body_promise: DomRefCell<Option<(Rc<Promise>, BodyType)>>,
+    /// The stream of the body, `None` for a null body.
+    body_stream: MutNullableDom<ReadableStream>,
     #[ignore_malloc_size_of = "StreamConsumer"]
     stream_consumer: DomRefCell<Option<StreamConsumer>>,
     redirected: DomRefCell<bool>,
@@ -61,14 +54,12 @@ impl Response {
             reflector_: Reflector::new(),
             headers_reflector: Default::default(),
             mime_type: DomRefCell::new("".to_string().into_bytes()),
-            body_used: Cell::new(false),
             status: DomRefCell::new(Some(StatusCode::OK)),
             raw_status: DomRefCell::new(Some((200, b"".to_vec()))),
             response_type: DomRefCell::new(DOMResponseType::Default),
             url: DomRefCell::new(None),
             url_list: DomRefCell::new(vec![]),
-            body: DomRefCell::new(NetTraitsResponseBody::Empty),
-            body_promise: DomRefCell::new(None),
+            body_stream: Default::default(),
             stream_consumer: DomRefCell::new(None),
             redirected: DomRefCell::new(false),
         }
@@ -81,7 +72,7 @@ impl Response {
 
     pub fn Constructor(
         global: &GlobalScope,
-        body: Option<BodyInit>,
+        body: Option<ResponseBodyInit>,
         init: &ResponseBinding::ResponseInit,
     ) -> Fallible<DomRoot<Response>> {
         // Step 1
@@ -128,8 +119,12 @@ impl Response {
             };
 
             // Step 7.3
-            let (extracted_body, content_type) = body.extract();
-            *r.body.borrow_mut() = NetTraitsResponseBody::Done(extracted_body);
+            let (extracted_body, content_type) = extract_body(body)?;
+            let stream = match extracted_body {
+                ExtractedBody::Bytes(bytes) => ReadableStream::new_from_bytes(global, bytes),
+                ExtractedBody::Stream(stream) => stream,
+            };
+            r.body_stream.set(Some(&stream));
 
             // Step 7.4
             if let Some(content_type_contents) = content_type {
@@ -213,9 +208,9 @@ impl Response {
 
     // https://fetch.spec.whatwg.org/#concept-body-locked
     fn locked(&self) -> bool {
-        // TODO: ReadableStream is unimplemented. Just return false
-        // for now.
-        false
+        self.body_stream
+            .get()
+            .map_or(false, |stream| stream.is_locked())
     }
 }
 
@@ -224,25 +219,12 @@ impl BodyOperations for Response {
         self.BodyUsed()
     }
 
-    fn set_body_promise(&self, p: &Rc<Promise>, body_type: BodyType) {
-        assert!(self.body_promise.borrow().is_none());
-        self.body_used.set(true);
-        *self.body_promise.borrow_mut() = Some((p.clone(), body_type));
-    }
-
     fn is_locked(&self) -> bool {
         self.locked()
     }
 
-    fn take_body(&self) -> Option<Vec<u8>> {
-        let body = mem::replace(&mut *self.body.borrow_mut(), NetTraitsResponseBody::Empty);
-        match body {
-            NetTraitsResponseBody::Done(bytes) => Some(bytes),
-            body => {
-                let _ = mem::replace(&mut *self.body.borrow_mut(), body);
-                None
-            },
-        }
+    fn body_stream(&self) -> Option<DomRoot<ReadableStream>> {
+        self.body_stream.get()
     }
 
     fn get_mime_type(&self) -> Ref<Vec<u8>> {
@@ -328,7 +310,7 @@ impl ResponseMethods for Response {
     // https://fetch.spec.whatwg.org/#dom-response-clone
     fn Clone(&self) -> Fallible<DomRoot<Response>> {
         // Step 1
-        if self.is_locked() || self.body_used.get() {
+        if self.is_locked() || self.BodyUsed() {
             return Err(Error::Type("cannot clone a disturbed response".to_string()));
         }
 
@@ -348,20 +330,29 @@ impl ResponseMethods for Response {
         *new_response.url.borrow_mut() = self.url.borrow().clone();
         *new_response.url_list.borrow_mut() = self.url_list.borrow().clone();
 
-        if *self.body.borrow() != NetTraitsResponseBody::Empty {
-            *new_response.body.borrow_mut() = self.body.borrow().clone();
-        }
+        *new_response.mime_type.borrow_mut() = self.mime_type.borrow().clone();
 
         // Step 3
-        // TODO: This step relies on promises, which are still unimplemented.
+        if let Some(stream) = self.body_stream.get() {
+            let (branch1, branch2) = stream.tee()?;
+            self.body_stream.set(Some(&branch1));
+            new_response.body_stream.set(Some(&branch2));
+        }
 
         // Step 4
         Ok(new_response)
     }
 
+    // https://fetch.spec.whatwg.org/#dom-body-body
+    fn GetBody(&self) -> Option<DomRoot<ReadableStream>> {
+        self.body_stream.get()
+    }
+
     // https://fetch.spec.whatwg.org/#dom-body-bodyused
     fn BodyUsed(&self) -> bool {
-        self.body_used.get()
+        self.body_stream
+            .get()
+            .map_or(false, |stream| stream.is_disturbed())
     }
 
     // https://fetch.spec.whatwg.org/#dom-body-text
@@ -426,20 +417,20 @@ impl Response {
                 *self.status.borrow_mut() = None;
                 self.set_raw_status(None);
                 self.set_headers(None);
-                *self.body.borrow_mut() = NetTraitsResponseBody::Done(vec![]);
+                self.body_stream.set(None);
             },
             DOMResponseType::Opaque => {
                 *self.url_list.borrow_mut() = vec![];
                 *self.status.borrow_mut() = None;
                 self.set_raw_status(None);
                 self.set_headers(None);
-                *self.body.borrow_mut() = NetTraitsResponseBody::Done(vec![]);
+                self.body_stream.set(None);
             },
             DOMResponseType::Opaqueredirect => {
                 *self.status.borrow_mut() = None;
                 self.set_raw_status(None);
                 self.set_headers(None);
-                *self.body.borrow_mut() = NetTraitsResponseBody::Done(vec![]);
+                self.body_stream.set(None);
             },
             DOMResponseType::Default => {},
             DOMResponseType::Basic => {},
@@ -457,14 +448,24 @@ impl Response {
         }
     }
 
-    #[allow(unrooted_must_root)]
-    pub fn finish(&self, body: Vec<u8>) {
-        *self.body.borrow_mut() = NetTraitsResponseBody::Done(body);
-        if let Some((p, body_type)) = self.body_promise.borrow_mut().take() {
-            consume_body_with_promise(self, body_type, &p);
-        }
+    pub fn finish(&self) {
         if let Some(stream_consumer) = self.stream_consumer.borrow_mut().take() {
             stream_consumer.stream_end();
         }
     }
+
+    /// Gives the response a stream for the body that is about to be received
+    /// from the network, `None` if the body of this type of response is
+    /// null.
+    pub fn new_body_stream(&self) -> Option<DomRoot<ReadableStream>> {
+        match *self.response_type.borrow() {
+            DOMResponseType::Error | DOMResponseType::Opaque | DOMResponseType::Opaqueredirect => {
+                return None
+            },
+            DOMResponseType::Default | DOMResponseType::Basic | DOMResponseType::Cors => {},
+        }
+        let stream = ReadableStream::new_native(&self.global());
+        self.body_stream.set(Some(&stream));
+        Some(stream)
+    }
 }
@@ -293,7 +293,8 @@ impl ServiceWorkerGlobalScope {
                     .pipeline_id(Some(pipeline_id))
                     .referrer(referrer)
                     .referrer_policy(referrer_policy)
-                    .origin(origin);
+                    .origin(origin)
+                    .top_level_origin(init.top_level_origin.clone());
 
                 // Service workers are time limited
                 // https://w3c.github.io/ServiceWorker/#service-worker-lifetime
@@ -0,0 +1,354 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategy;
+use crate::dom::bindings::codegen::Bindings::TransformStreamBinding::TransformStreamMethods;
+use crate::dom::bindings::conversions::get_property_jsval;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestream::{ReadableStream, ReadableStreamState};
+use crate::dom::readablestreamdefaultcontroller::SourceAlgorithms;
+use crate::dom::transformstreamdefaultcontroller::TransformStreamDefaultController;
+use crate::dom::writablestream::{WritableStream, WritableStreamState};
+use crate::dom::writablestreamdefaultcontroller::SinkAlgorithms;
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::streams::{call_method, extract_high_water_mark, get_method, new_resolved_promise};
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext, JSObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::HandleValue;
+use std::cell::Cell;
+use std::ptr;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct TransformStream {
+    reflector_: Reflector,
+    readable: MutNullableDom<ReadableStream>,
+    writable: MutNullableDom<WritableStream>,
+    backpressure: Cell<bool>,
+    #[ignore_malloc_size_of = "Rc"]
+    backpressure_change_promise: DomRefCell<Option<Rc<Promise>>>,
+    controller: MutNullableDom<TransformStreamDefaultController>,
+}
+
+impl TransformStream {
+    fn new_inherited() -> TransformStream {
+        TransformStream {
+            reflector_: Reflector::new(),
+            readable: Default::default(),
+            writable: Default::default(),
+            backpressure: Cell::new(false),
+            backpressure_change_promise: DomRefCell::new(None),
+            controller: Default::default(),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<TransformStream> {
+        reflect_dom_object(Box::new(TransformStream::new_inherited()), global)
+    }
+
+    pub fn readable(&self) -> DomRoot<ReadableStream> {
+        self.readable
+            .get()
+            .expect("A transform stream has no readable side")
+    }
+
+    pub fn writable(&self) -> DomRoot<WritableStream> {
+        self.writable
+            .get()
+            .expect("A transform stream has no writable side")
+    }
+
+    pub fn controller(&self) -> DomRoot<TransformStreamDefaultController> {
+        self.controller
+            .get()
+            .expect("A transform stream has no controller")
+    }
+
+    pub fn has_backpressure(&self) -> bool {
+        self.backpressure.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-error>
+    pub fn error(&self, e: HandleValue) {
+        self.readable().controller().error(e);
+        self.error_writable_and_unblock_write(e);
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-error-writable-and-unblock-write>
+    pub fn error_writable_and_unblock_write(&self, e: HandleValue) {
+        self.controller().clear_algorithms();
+        let writable = self.writable();
+        if writable.state() == WritableStreamState::Writable {
+            writable.controller().error(e);
+        }
+        if self.backpressure.get() {
+            self.set_backpressure(false);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-set-backpressure>
+    pub fn set_backpressure(&self, backpressure: bool) {
+        if let Some(promise) = self.backpressure_change_promise.borrow_mut().take() {
+            promise.resolve_native(&());
+        }
+        *self.backpressure_change_promise.borrow_mut() = Some(Promise::new(&self.global()));
+        self.backpressure.set(backpressure);
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-write-algorithm>
+    #[allow(unrooted_must_root)]
+    pub fn sink_write(&self, chunk: HandleValue) -> Rc<Promise> {
+        // Step 3.
+        if !self.backpressure.get() {
+            return self.controller().perform_transform(chunk);
+        }
+
+        // Steps 3.1-3.2.
+        let global = self.global();
+        let backpressure_change_promise = self
+            .backpressure_change_promise
+            .borrow()
+            .clone()
+            .expect("No backpressure change promise");
+        let result = Promise::new(&global);
+        self.upon_settlement(
+            &backpressure_change_promise,
+            &result,
+            chunk,
+            Reaction::BackpressureChanged,
+            Reaction::Reject,
+        );
+        result
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-abort-algorithm>
+    pub fn sink_abort(&self, reason: HandleValue) -> Rc<Promise> {
+        self.error(reason);
+        new_resolved_promise(&self.global(), HandleValue::undefined())
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-sink-close-algorithm>
+    pub fn sink_close(&self) -> Rc<Promise> {
+        let controller = self.controller();
+        let flush_promise = controller.flush();
+        controller.clear_algorithms();
+        let result = Promise::new(&self.global());
+        self.upon_settlement(
+            &flush_promise,
+            &result,
+            HandleValue::undefined(),
+            Reaction::FlushFulfilled,
+            Reaction::FlushRejected,
+        );
+        result
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-source-pull>
+    pub fn source_pull(&self) -> Rc<Promise> {
+        self.set_backpressure(false);
+        self.backpressure_change_promise
+            .borrow()
+            .clone()
+            .expect("No backpressure change promise")
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-source-cancel>
+    pub fn source_cancel(&self, reason: HandleValue) -> Rc<Promise> {
+        self.error_writable_and_unblock_write(reason);
+        new_resolved_promise(&self.global(), HandleValue::undefined())
+    }
+
+    /// Settles `result` after `promise` settles, depending on `on_fulfilled`
+    /// or `on_rejected`.
+    #[allow(unrooted_must_root)]
+    pub fn upon_settlement(
+        &self,
+        promise: &Promise,
+        result: &Rc<Promise>,
+        chunk: HandleValue,
+        on_fulfilled: Reaction,
+        on_rejected: Reaction,
+    ) {
+        let handler = PromiseNativeHandler::new(
+            &self.global(),
+            Some(Box::new(TransformHandler {
+                stream: Dom::from_ref(self),
+                result: result.clone(),
+                chunk: Heap::boxed(chunk.get()),
+                reaction: on_fulfilled,
+            })),
+            Some(Box::new(TransformHandler {
+                stream: Dom::from_ref(self),
+                result: result.clone(),
+                chunk: Heap::boxed(chunk.get()),
+                reaction: on_rejected,
+            })),
+        );
+        promise.append_native_handler(&handler);
+    }
+
+    /// <https://streams.spec.whatwg.org/#ts-constructor>
+    #[allow(non_snake_case, unsafe_code, unrooted_must_root)]
+    pub fn Constructor(
+        cx: SafeJSContext,
+        global: &GlobalScope,
+        transformer: Option<*mut JSObject>,
+        writable_strategy: &QueuingStrategy,
+        readable_strategy: &QueuingStrategy,
+    ) -> Fallible<DomRoot<TransformStream>> {
+        // Step 1.
+        rooted!(in(*cx) let transformer = transformer.unwrap_or(ptr::null_mut()));
+
+        // Step 2.
+        let stream = TransformStream::new(global);
+        let controller = TransformStreamDefaultController::new(global, &stream);
+        controller.init_transformer(cx, transformer.handle())?;
+        rooted!(in(*cx) let mut start = UndefinedValue());
+        get_method(cx, transformer.handle(), "start", start.handle_mut())?;
+
+        // Steps 3-4.
+        if !transformer.is_null() {
+            for name in &["readableType", "writableType"] {
+                rooted!(in(*cx) let mut type_ = UndefinedValue());
+                unsafe { get_property_jsval(*cx, transformer.handle(), name, type_.handle_mut())? };
+                if !type_.is_undefined() {
+                    return Err(Error::Range(format!("Invalid {}", name)));
+                }
+            }
+        }
+
+        // Steps 5-8.
+        let readable_high_water_mark = extract_high_water_mark(readable_strategy, 0.)?;
+        let writable_high_water_mark = extract_high_water_mark(writable_strategy, 1.)?;
+
+        // Step 9.
+        let start_promise = Promise::new(global);
+        rooted!(in(*cx) let start_promise_value =
+            ObjectValue(start_promise.reflector().get_jsobject().get()));
+
+        // Step 10, <https://streams.spec.whatwg.org/#initialize-transform-stream>.
+        let writable = WritableStream::create(
+            global,
+            SinkAlgorithms::Transform(Dom::from_ref(&*stream)),
+            writable_high_water_mark,
+            writable_strategy.size.clone(),
+            start_promise_value.handle(),
+        );
+        let readable = ReadableStream::create(
+            global,
+            SourceAlgorithms::Transform(Dom::from_ref(&*stream)),
+            readable_high_water_mark,
+            readable_strategy.size.clone(),
+            start_promise_value.handle(),
+        );
+        stream.writable.set(Some(&writable));
+        stream.readable.set(Some(&readable));
+        stream.set_backpressure(true);
+
+        // Step 11.
+        stream.controller.set(Some(&controller));
+
+        // Step 12.
+        rooted!(in(*cx) let controller_value = ObjectValue(controller.reflector().get_jsobject().get()));
+        rooted!(in(*cx) let mut start_result = UndefinedValue());
+        call_method(
+            cx,
+            transformer.handle(),
+            start.handle(),
+            &[controller_value.get()],
+            start_result.handle_mut(),
+        )?;
+        start_promise.resolve(cx, start_result.handle());
+        Ok(stream)
+    }
+}
+
+impl TransformStreamMethods for TransformStream {
+    // https://streams.spec.whatwg.org/#ts-readable
+    fn Readable(&self) -> DomRoot<ReadableStream> {
+        self.readable()
+    }
+
+    // https://streams.spec.whatwg.org/#ts-writable
+    fn Writable(&self) -> DomRoot<WritableStream> {
+        self.writable()
+    }
+}
+
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+pub enum Reaction {
+    /// The backpressure was relieved, the chunk can be transformed.
+    BackpressureChanged,
+    TransformFulfilled,
+    TransformRejected,
+    FlushFulfilled,
+    FlushRejected,
+    /// Reject the result with the same reason.
+    Reject,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct TransformHandler {
+    stream: Dom<TransformStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    result: Rc<Promise>,
+    #[ignore_malloc_size_of = "mozjs"]
+    chunk: Box<Heap<JSVal>>,
+    reaction: Reaction,
+}
+
+impl Callback for TransformHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue, _realm: InRealm) {
+        let cx = unsafe { SafeJSContext::from_ptr(cx) };
+        let stream = &self.stream;
+        let _ac = enter_realm(&**stream);
+        match self.reaction {
+            Reaction::BackpressureChanged => {
+                let writable = stream.writable();
+                if writable.state() == WritableStreamState::Erroring {
+                    rooted!(in(*cx) let mut error = UndefinedValue());
+                    writable.stored_error(error.handle_mut());
+                    return self.result.reject(cx, error.handle());
+                }
+                rooted!(in(*cx) let chunk = self.chunk.get());
+                let transform_promise = stream.controller().perform_transform(chunk.handle());
+                rooted!(in(*cx) let transform_promise =
+                    ObjectValue(transform_promise.reflector().get_jsobject().get()));
+                self.result.resolve(cx, transform_promise.handle());
+            },
+            Reaction::TransformFulfilled => self.result.resolve_native(&()),
+            Reaction::TransformRejected => {
+                stream.error(v);
+                self.result.reject(cx, v);
+            },
+            Reaction::FlushFulfilled => {
+                let readable = stream.readable();
+                if readable.state() == ReadableStreamState::Errored {
+                    rooted!(in(*cx) let mut error = UndefinedValue());
+                    readable.stored_error(error.handle_mut());
+                    return self.result.reject(cx, error.handle());
+                }
+                readable.close_native();
+                self.result.resolve_native(&());
+            },
+            Reaction::FlushRejected => {
+                stream.error(v);
+                rooted!(in(*cx) let mut error = UndefinedValue());
+                stream.readable().stored_error(error.handle_mut());
+                self.result.reject(cx, error.handle());
+            },
+            Reaction::Reject => self.result.reject(cx, v),
+        }
+    }
+}
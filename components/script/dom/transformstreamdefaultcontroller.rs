@@ -0,0 +1,163 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::TransformStreamDefaultControllerBinding::TransformStreamDefaultControllerMethods;
+use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::transformstream::{Reaction, TransformStream};
+use crate::script_runtime::JSContext;
+use crate::streams::{
+    error_to_jsval, new_rejected_promise_with_error, new_resolved_promise, rethrow,
+    UnderlyingMethod,
+};
+use dom_struct::dom_struct;
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::{HandleObject, HandleValue};
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct TransformStreamDefaultController {
+    reflector_: Reflector,
+    stream: Dom<TransformStream>,
+    /// The transform method of the transformer, chunks are enqueued
+    /// untouched when it is undefined.
+    transform: UnderlyingMethod,
+    flush: UnderlyingMethod,
+}
+
+impl TransformStreamDefaultController {
+    fn new_inherited(stream: &TransformStream) -> TransformStreamDefaultController {
+        TransformStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: Dom::from_ref(stream),
+            transform: Default::default(),
+            flush: Default::default(),
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        stream: &TransformStream,
+    ) -> DomRoot<TransformStreamDefaultController> {
+        reflect_dom_object(
+            Box::new(TransformStreamDefaultController::new_inherited(stream)),
+            global,
+        )
+    }
+
+    /// Steps 1-5 of
+    /// <https://streams.spec.whatwg.org/#set-up-transform-stream-default-controller-from-transformer>.
+    pub fn init_transformer(&self, cx: JSContext, transformer: HandleObject) -> ErrorResult {
+        self.flush.init(cx, transformer, "flush")?;
+        self.transform.init(cx, transformer, "transform")?;
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-clear-algorithms>
+    pub fn clear_algorithms(&self) {
+        self.transform.clear();
+        self.flush.clear();
+    }
+
+    fn as_jsval(&self) -> JSVal {
+        ObjectValue(self.reflector().get_jsobject().get())
+    }
+
+    /// The flush algorithm.
+    pub fn flush(&self) -> Rc<Promise> {
+        self.flush.promise_call(&self.global(), &[self.as_jsval()])
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-perform-transform>
+    pub fn perform_transform(&self, chunk: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+
+        // Step 1.
+        let transform_promise = if self.transform.is_undefined() {
+            match self.enqueue(global.get_cx(), chunk) {
+                Ok(()) => new_resolved_promise(&global, HandleValue::undefined()),
+                Err(error) => new_rejected_promise_with_error(&global, error),
+            }
+        } else {
+            self.transform
+                .promise_call(&global, &[chunk.get(), self.as_jsval()])
+        };
+
+        // Step 2.
+        let result = Promise::new(&global);
+        self.stream.upon_settlement(
+            &transform_promise,
+            &result,
+            HandleValue::undefined(),
+            Reaction::TransformFulfilled,
+            Reaction::TransformRejected,
+        );
+        result
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-enqueue>
+    pub fn enqueue(&self, cx: JSContext, chunk: HandleValue) -> ErrorResult {
+        // Steps 2-3.
+        let readable = self.stream.readable();
+        let readable_controller = readable.controller();
+        if !readable_controller.can_close_or_enqueue() {
+            return Err(Error::Type(
+                "The readable side can't be enqueued to".to_owned(),
+            ));
+        }
+
+        // Steps 4-5.
+        if let Err(error) = readable_controller.enqueue(cx, chunk) {
+            self.stream.error_writable_and_unblock_write(error.handle());
+            rooted!(in(*cx) let mut stored_error = UndefinedValue());
+            readable.stored_error(stored_error.handle_mut());
+            return Err(rethrow(cx, stored_error.handle()));
+        }
+
+        // Steps 6-7.
+        let backpressure = readable_controller.has_backpressure();
+        if backpressure != self.stream.has_backpressure() {
+            self.stream.set_backpressure(true);
+        }
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#transform-stream-default-controller-terminate>
+    pub fn terminate(&self) {
+        let global = self.global();
+        self.stream.readable().close_native();
+        rooted!(in(*global.get_cx()) let mut error = UndefinedValue());
+        error_to_jsval(
+            &global,
+            Error::Type("The transform stream was terminated".to_owned()),
+            error.handle_mut(),
+        );
+        self.stream.error_writable_and_unblock_write(error.handle());
+    }
+}
+
+impl TransformStreamDefaultControllerMethods for TransformStreamDefaultController {
+    // https://streams.spec.whatwg.org/#ts-default-controller-desired-size
+    fn GetDesiredSize(&self) -> Option<f64> {
+        self.stream.readable().controller().desired_size()
+    }
+
+    // https://streams.spec.whatwg.org/#ts-default-controller-enqueue
+    fn Enqueue(&self, cx: JSContext, chunk: HandleValue) -> ErrorResult {
+        self.enqueue(cx, chunk)
+    }
+
+    // https://streams.spec.whatwg.org/#ts-default-controller-error
+    fn Error(&self, _cx: JSContext, reason: HandleValue) {
+        self.stream.error(reason);
+    }
+
+    // https://streams.spec.whatwg.org/#ts-default-controller-terminate
+    fn Terminate(&self) {
+        self.terminate();
+    }
+}
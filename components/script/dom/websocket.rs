@@ -201,7 +201,8 @@ impl WebSocket {
         let request = RequestBuilder::new(url_record)
             .origin(global.origin().immutable().clone())
             .mode(RequestMode::WebSocket { protocols })
-            .referrer(Some(Referrer::NoReferrer));
+            .referrer(Some(Referrer::NoReferrer))
+            .top_level_origin(global.top_level_origin());
 
         let channels = FetchChannels::WebSocket {
             event_sender: resource_event_sender,
@@ -40,6 +40,7 @@ use crate::dom::eventtarget::EventTarget;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::hashchangeevent::HashChangeEvent;
 use crate::dom::history::History;
+use crate::dom::idbfactory::IDBFactory;
 use crate::dom::identityhub::Identities;
 use crate::dom::location::Location;
 use crate::dom::mediaquerylist::{MediaQueryList, MediaQueryListMatchState};
@@ -177,6 +178,7 @@ pub enum ReflowReason {
     MissingExplicitReflow,
     ElementStateChanged,
     PendingReflow,
+    Print,
 }
 
 #[dom_struct]
@@ -251,6 +253,10 @@ pub struct Window {
     /// A counter of the number of pending reflows for this window.
     pending_reflow_count: Cell<u32>,
 
+    /// The number of layout boxes that the last reflow which built a box tree
+    /// did not reuse from the previous one.
+    rebuilt_layout_box_count: Cell<u32>,
+
     /// A channel for communicating results of async scripts back to the webdriver server
     #[ignore_malloc_size_of = "channels are hard"]
     webdriver_script_chan: DomRefCell<Option<IpcSender<WebDriverJSResult>>>,
@@ -813,6 +819,11 @@ impl WindowMethods for Window {
         self.upcast::<GlobalScope>().crypto()
     }
 
+    // https://w3c.github.io/IndexedDB/#dom-windoworworkerglobalscope-indexeddb
+    fn IndexedDB(&self) -> DomRoot<IDBFactory> {
+        self.upcast::<GlobalScope>().indexeddb()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-frameelement
     fn GetFrameElement(&self) -> Option<DomRoot<Element>> {
         // Steps 1-3.
@@ -1316,6 +1327,10 @@ impl WindowMethods for Window {
             .map_or(0, |d| d.animations().running_animation_count() as u32)
     }
 
+    fn RebuiltLayoutBoxCount(&self) -> u32 {
+        self.rebuilt_layout_box_count.get()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-name
     fn SetName(&self, name: DOMString) {
         if let Some(proxy) = self.undiscarded_window_proxy() {
@@ -1440,6 +1455,9 @@ impl Window {
         // Remove the infra for managing messageports and broadcast channels.
         self.upcast::<GlobalScope>().remove_web_messaging_infra();
 
+        // Close the IndexedDB connections, so they don't block versionchanges.
+        self.upcast::<GlobalScope>().close_indexeddb_connections();
+
         // Clean up any active promises
         // https://github.com/servo/servo/issues/15318
         if let Some(custom_elements) = self.custom_element_registry.get() {
@@ -1720,6 +1738,10 @@ impl Window {
             self.emit_timeline_marker(marker.end());
         }
 
+        if let Some(count) = complete.rebuilt_box_count {
+            self.rebuilt_layout_box_count.set(count as u32);
+        }
+
         for image in complete.pending_images {
             let id = image.id;
             let js_runtime = self.js_runtime.borrow();
@@ -2399,6 +2421,7 @@ impl Window {
             current_viewport: Cell::new(Rect::zero()),
             suppress_reflow: Cell::new(true),
             pending_reflow_count: Default::default(),
+            rebuilt_layout_box_count: Default::default(),
             current_state: Cell::new(WindowState::Alive),
             devtools_marker_sender: Default::default(),
             devtools_markers: Default::default(),
@@ -21,6 +21,7 @@ use crate::dom::bindings::trace::RootedTraceableBox;
 use crate::dom::crypto::Crypto;
 use crate::dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbfactory::IDBFactory;
 use crate::dom::identityhub::Identities;
 use crate::dom::performance::Performance;
 use crate::dom::promise::Promise;
@@ -33,6 +34,7 @@ use crate::realms::{enter_realm, InRealm};
 use crate::script_runtime::JSContext;
 use crate::script_runtime::{get_reports, CommonScriptMsg, Runtime, ScriptChan, ScriptPort};
 use crate::task::TaskCanceller;
+use crate::task_source::database_access::DatabaseAccessTaskSource;
 use crate::task_source::dom_manipulation::DOMManipulationTaskSource;
 use crate::task_source::file_reading::FileReadingTaskSource;
 use crate::task_source::networking::NetworkingTaskSource;
@@ -80,6 +82,7 @@ pub fn prepare_workerscope_init(
         worker_id: worker_id.unwrap_or_else(|| WorkerId(Uuid::new_v4())),
         pipeline_id: global.pipeline_id(),
         origin: global.origin().immutable().clone(),
+        top_level_origin: global.top_level_origin(),
         is_headless: global.is_headless(),
         user_agent: global.get_user_agent(),
     };
@@ -131,21 +134,23 @@ impl WorkerGlobalScope {
     ) -> Self {
         // Install a pipeline-namespace in the current thread.
         PipelineNamespace::auto_install();
+        let globalscope = GlobalScope::new_inherited(
+            init.pipeline_id,
+            init.to_devtools_sender,
+            init.mem_profiler_chan,
+            init.time_profiler_chan,
+            init.script_to_constellation_chan,
+            init.scheduler_chan,
+            init.resource_threads,
+            MutableOrigin::new(init.origin),
+            runtime.microtask_queue.clone(),
+            init.is_headless,
+            init.user_agent,
+            gpu_id_hub,
+        );
+        globalscope.set_top_level_origin(init.top_level_origin);
         Self {
-            globalscope: GlobalScope::new_inherited(
-                init.pipeline_id,
-                init.to_devtools_sender,
-                init.mem_profiler_chan,
-                init.time_profiler_chan,
-                init.script_to_constellation_chan,
-                init.scheduler_chan,
-                init.resource_threads,
-                MutableOrigin::new(init.origin),
-                runtime.microtask_queue.clone(),
-                init.is_headless,
-                init.user_agent,
-                gpu_id_hub,
-            ),
+            globalscope,
             worker_id: init.worker_id,
             worker_name,
             worker_type,
@@ -297,6 +302,11 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
         self.upcast::<GlobalScope>().crypto()
     }
 
+    // https://w3c.github.io/IndexedDB/#dom-windoworworkerglobalscope-indexeddb
+    fn IndexedDB(&self) -> DomRoot<IDBFactory> {
+        self.upcast::<GlobalScope>().indexeddb()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-windowbase64-btoa
     fn Btoa(&self, btoa: DOMString) -> Fallible<DOMString> {
         base64_btoa(btoa)
@@ -448,6 +458,10 @@ impl WorkerGlobalScope {
         }
     }
 
+    pub fn database_access_task_source(&self) -> DatabaseAccessTaskSource {
+        DatabaseAccessTaskSource(self.script_chan(), self.pipeline_id())
+    }
+
     pub fn dom_manipulation_task_source(&self) -> DOMManipulationTaskSource {
         DOMManipulationTaskSource(self.script_chan(), self.pipeline_id())
     }
@@ -0,0 +1,569 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::{
+    QueuingStrategy, QueuingStrategySize,
+};
+use crate::dom::bindings::codegen::Bindings::WritableStreamBinding::WritableStreamMethods;
+use crate::dom::bindings::conversions::get_property_jsval;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::writablestreamdefaultcontroller::{
+    SinkAlgorithms, WritableStreamDefaultController,
+};
+use crate::dom::writablestreamdefaultwriter::WritableStreamDefaultWriter;
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::streams::{
+    call_method, extract_high_water_mark, get_method, new_rejected_promise_with_error,
+    new_resolved_promise,
+};
+use dom_struct::dom_struct;
+use js::jsapi::{Heap, JSContext, JSObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+
+/// <https://streams.spec.whatwg.org/#writablestream-state>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum WritableStreamState {
+    Writable,
+    Erroring,
+    Errored,
+    Closed,
+}
+
+/// <https://streams.spec.whatwg.org/#pending-abort-request>
+#[derive(JSTraceable, MallocSizeOf)]
+struct PendingAbortRequest {
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    #[ignore_malloc_size_of = "mozjs"]
+    reason: Box<Heap<JSVal>>,
+    was_already_erroring: bool,
+}
+
+#[dom_struct]
+pub struct WritableStream {
+    reflector_: Reflector,
+    state: Cell<WritableStreamState>,
+    #[ignore_malloc_size_of = "mozjs"]
+    stored_error: Heap<JSVal>,
+    writer: MutNullableDom<WritableStreamDefaultWriter>,
+    controller: MutNullableDom<WritableStreamDefaultController>,
+    backpressure: Cell<bool>,
+    #[ignore_malloc_size_of = "Rc"]
+    write_requests: DomRefCell<VecDeque<Rc<Promise>>>,
+    #[ignore_malloc_size_of = "Rc"]
+    in_flight_write_request: DomRefCell<Option<Rc<Promise>>>,
+    #[ignore_malloc_size_of = "Rc"]
+    close_request: DomRefCell<Option<Rc<Promise>>>,
+    #[ignore_malloc_size_of = "Rc"]
+    in_flight_close_request: DomRefCell<Option<Rc<Promise>>>,
+    pending_abort_request: DomRefCell<Option<PendingAbortRequest>>,
+}
+
+impl WritableStream {
+    fn new_inherited() -> WritableStream {
+        WritableStream {
+            reflector_: Reflector::new(),
+            state: Cell::new(WritableStreamState::Writable),
+            stored_error: Heap::default(),
+            writer: Default::default(),
+            controller: Default::default(),
+            backpressure: Cell::new(false),
+            write_requests: DomRefCell::new(VecDeque::new()),
+            in_flight_write_request: DomRefCell::new(None),
+            close_request: DomRefCell::new(None),
+            in_flight_close_request: DomRefCell::new(None),
+            pending_abort_request: DomRefCell::new(None),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<WritableStream> {
+        reflect_dom_object(Box::new(WritableStream::new_inherited()), global)
+    }
+
+    /// <https://streams.spec.whatwg.org/#create-writable-stream>
+    #[allow(unrooted_must_root)]
+    pub fn create(
+        global: &GlobalScope,
+        algorithms: SinkAlgorithms,
+        high_water_mark: f64,
+        size: Option<Rc<QueuingStrategySize>>,
+        start_result: HandleValue,
+    ) -> DomRoot<WritableStream> {
+        let stream = WritableStream::new(global);
+        let controller = WritableStreamDefaultController::new(
+            global,
+            &stream,
+            algorithms,
+            high_water_mark,
+            size,
+        );
+        stream.set_controller(&controller);
+        controller.start(start_result);
+        stream
+    }
+
+    /// Steps 1-15 of
+    /// <https://streams.spec.whatwg.org/#set-up-writable-stream-default-controller>,
+    /// the start algorithm is run by the caller.
+    fn set_controller(&self, controller: &WritableStreamDefaultController) {
+        self.controller.set(Some(controller));
+        self.update_backpressure(controller.get_backpressure());
+    }
+
+    pub fn state(&self) -> WritableStreamState {
+        self.state.get()
+    }
+
+    pub fn stored_error(&self, mut rval: MutableHandleValue) {
+        rval.set(self.stored_error.get());
+    }
+
+    pub fn controller(&self) -> DomRoot<WritableStreamDefaultController> {
+        self.controller
+            .get()
+            .expect("A writable stream has no controller")
+    }
+
+    pub fn writer(&self) -> Option<DomRoot<WritableStreamDefaultWriter>> {
+        self.writer.get()
+    }
+
+    pub fn set_writer(&self, writer: Option<&WritableStreamDefaultWriter>) {
+        self.writer.set(writer);
+    }
+
+    pub fn has_backpressure(&self) -> bool {
+        self.backpressure.get()
+    }
+
+    /// <https://streams.spec.whatwg.org/#is-writable-stream-locked>
+    pub fn is_locked(&self) -> bool {
+        self.writer.get().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-abort>
+    pub fn abort(&self, reason: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+
+        // Steps 1-3.
+        let state = self.state.get();
+        if state == WritableStreamState::Closed || state == WritableStreamState::Errored {
+            return new_resolved_promise(&global, HandleValue::undefined());
+        }
+
+        // Step 4.
+        if let Some(ref request) = *self.pending_abort_request.borrow() {
+            return request.promise.clone();
+        }
+
+        // Steps 6-7.
+        let was_already_erroring = state == WritableStreamState::Erroring;
+        let reason = if was_already_erroring {
+            HandleValue::undefined()
+        } else {
+            reason
+        };
+
+        // Steps 8-9.
+        let promise = Promise::new(&global);
+        *self.pending_abort_request.borrow_mut() = Some(PendingAbortRequest {
+            promise: promise.clone(),
+            reason: Heap::boxed(reason.get()),
+            was_already_erroring,
+        });
+
+        // Step 10.
+        if !was_already_erroring {
+            self.start_erroring(reason);
+        }
+
+        // Step 11.
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-close>
+    pub fn close(&self) -> Rc<Promise> {
+        let global = self.global();
+
+        // Step 2.
+        let state = self.state.get();
+        if state == WritableStreamState::Closed || state == WritableStreamState::Errored {
+            return new_rejected_promise_with_error(
+                &global,
+                Error::Type("The stream is closed or errored".to_owned()),
+            );
+        }
+
+        // Steps 3-5.
+        assert!(!self.close_queued_or_in_flight());
+        let promise = Promise::new(&global);
+        *self.close_request.borrow_mut() = Some(promise.clone());
+
+        // Step 6.
+        if let Some(writer) = self.writer.get() {
+            if self.backpressure.get() && state == WritableStreamState::Writable {
+                writer.ready_promise().resolve_native(&());
+            }
+        }
+
+        // Step 7.
+        self.controller().close();
+
+        // Step 8.
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-add-write-request>
+    pub fn add_write_request(&self) -> Rc<Promise> {
+        assert!(self.is_locked());
+        assert_eq!(self.state.get(), WritableStreamState::Writable);
+        let promise = Promise::new(&self.global());
+        self.write_requests.borrow_mut().push_back(promise.clone());
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-deal-with-rejection>
+    pub fn deal_with_rejection(&self, error: HandleValue) {
+        if self.state.get() == WritableStreamState::Writable {
+            return self.start_erroring(error);
+        }
+        assert_eq!(self.state.get(), WritableStreamState::Erroring);
+        self.finish_erroring();
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-start-erroring>
+    pub fn start_erroring(&self, reason: HandleValue) {
+        assert!(self.stored_error.get().is_undefined());
+        assert_eq!(self.state.get(), WritableStreamState::Writable);
+
+        // Steps 3-5.
+        let controller = self.controller();
+        self.state.set(WritableStreamState::Erroring);
+        self.stored_error.set(reason.get());
+
+        // Steps 6-7.
+        if let Some(writer) = self.writer.get() {
+            writer.ensure_ready_promise_rejected(reason);
+        }
+
+        // Step 8.
+        if !self.has_operation_marked_in_flight() && controller.started() {
+            self.finish_erroring();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-erroring>
+    #[allow(unrooted_must_root)]
+    pub fn finish_erroring(&self) {
+        let global = self.global();
+        let cx = global.get_cx();
+        let _ac = enter_realm(self);
+
+        // Steps 1-4.
+        assert_eq!(self.state.get(), WritableStreamState::Erroring);
+        assert!(!self.has_operation_marked_in_flight());
+        self.state.set(WritableStreamState::Errored);
+        let controller = self.controller();
+        controller.error_steps();
+
+        // Steps 5-7.
+        rooted!(in(*cx) let stored_error = self.stored_error.get());
+        let write_requests = mem::take(&mut *self.write_requests.borrow_mut());
+        for request in write_requests {
+            request.reject(cx, stored_error.handle());
+        }
+
+        // Step 8.
+        let abort_request = self.pending_abort_request.borrow_mut().take();
+        let abort_request = match abort_request {
+            Some(abort_request) => abort_request,
+            None => return self.reject_close_and_closed_promise_if_needed(),
+        };
+
+        // Step 11.
+        if abort_request.was_already_erroring {
+            abort_request.promise.reject(cx, stored_error.handle());
+            return self.reject_close_and_closed_promise_if_needed();
+        }
+
+        // Steps 12-13.
+        rooted!(in(*cx) let reason = abort_request.reason.get());
+        let promise = controller.abort_steps(reason.handle());
+        let handler = PromiseNativeHandler::new(
+            &global,
+            Some(Box::new(AbortHandler {
+                stream: Dom::from_ref(self),
+                promise: abort_request.promise.clone(),
+                fulfilled: true,
+            })),
+            Some(Box::new(AbortHandler {
+                stream: Dom::from_ref(self),
+                promise: abort_request.promise,
+                fulfilled: false,
+            })),
+        );
+        promise.append_native_handler(&handler);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-write>
+    pub fn finish_in_flight_write(&self) {
+        let request = self
+            .in_flight_write_request
+            .borrow_mut()
+            .take()
+            .expect("No write in flight");
+        request.resolve_native(&());
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-write-with-error>
+    pub fn finish_in_flight_write_with_error(&self, error: HandleValue) {
+        let cx = self.global().get_cx();
+        let _ac = enter_realm(self);
+        let request = self
+            .in_flight_write_request
+            .borrow_mut()
+            .take()
+            .expect("No write in flight");
+        request.reject(cx, error);
+        self.deal_with_rejection(error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-close>
+    pub fn finish_in_flight_close(&self) {
+        // Steps 1-3.
+        let request = self
+            .in_flight_close_request
+            .borrow_mut()
+            .take()
+            .expect("No close in flight");
+        request.resolve_native(&());
+
+        // Step 5.
+        if self.state.get() == WritableStreamState::Erroring {
+            self.stored_error.set(UndefinedValue());
+            if let Some(abort_request) = self.pending_abort_request.borrow_mut().take() {
+                abort_request.promise.resolve_native(&());
+            }
+        }
+
+        // Steps 6-8.
+        self.state.set(WritableStreamState::Closed);
+        if let Some(writer) = self.writer.get() {
+            writer.closed_promise().resolve_native(&());
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-finish-in-flight-close-with-error>
+    pub fn finish_in_flight_close_with_error(&self, error: HandleValue) {
+        let cx = self.global().get_cx();
+        let _ac = enter_realm(self);
+
+        // Steps 1-3.
+        let request = self
+            .in_flight_close_request
+            .borrow_mut()
+            .take()
+            .expect("No close in flight");
+        request.reject(cx, error);
+
+        // Step 5.
+        if let Some(abort_request) = self.pending_abort_request.borrow_mut().take() {
+            abort_request.promise.reject(cx, error);
+        }
+
+        // Step 6.
+        self.deal_with_rejection(error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-close-queued-or-in-flight>
+    pub fn close_queued_or_in_flight(&self) -> bool {
+        self.close_request.borrow().is_some() || self.in_flight_close_request.borrow().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-has-operation-marked-in-flight>
+    fn has_operation_marked_in_flight(&self) -> bool {
+        self.in_flight_write_request.borrow().is_some() ||
+            self.in_flight_close_request.borrow().is_some()
+    }
+
+    pub fn has_write_in_flight(&self) -> bool {
+        self.in_flight_write_request.borrow().is_some()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-mark-close-request-in-flight>
+    pub fn mark_close_request_in_flight(&self) {
+        let request = self.close_request.borrow_mut().take();
+        assert!(request.is_some());
+        *self.in_flight_close_request.borrow_mut() = request;
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-mark-first-write-request-in-flight>
+    pub fn mark_first_write_request_in_flight(&self) {
+        let request = self.write_requests.borrow_mut().pop_front();
+        assert!(request.is_some());
+        *self.in_flight_write_request.borrow_mut() = request;
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-reject-close-and-closed-promise-if-needed>
+    fn reject_close_and_closed_promise_if_needed(&self) {
+        let cx = self.global().get_cx();
+        let _ac = enter_realm(self);
+        rooted!(in(*cx) let stored_error = self.stored_error.get());
+
+        // Step 2.
+        if let Some(request) = self.close_request.borrow_mut().take() {
+            request.reject(cx, stored_error.handle());
+        }
+
+        // Steps 3-4.
+        if let Some(writer) = self.writer.get() {
+            writer.ensure_closed_promise_rejected(stored_error.handle());
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-update-backpressure>
+    pub fn update_backpressure(&self, backpressure: bool) {
+        if let Some(writer) = self.writer.get() {
+            if backpressure != self.backpressure.get() {
+                if backpressure {
+                    writer.reset_ready_promise();
+                } else {
+                    writer.ready_promise().resolve_native(&());
+                }
+            }
+        }
+        self.backpressure.set(backpressure);
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-constructor>
+    #[allow(non_snake_case, unsafe_code, unrooted_must_root)]
+    pub fn Constructor(
+        cx: SafeJSContext,
+        global: &GlobalScope,
+        underlying_sink: Option<*mut JSObject>,
+        strategy: &QueuingStrategy,
+    ) -> Fallible<DomRoot<WritableStream>> {
+        // Step 1.
+        rooted!(in(*cx) let sink = underlying_sink.unwrap_or(ptr::null_mut()));
+
+        // Step 6.
+        let high_water_mark = extract_high_water_mark(strategy, 1.)?;
+
+        // Step 4.
+        let stream = WritableStream::new(global);
+        let controller = WritableStreamDefaultController::new(
+            global,
+            &stream,
+            SinkAlgorithms::Object,
+            high_water_mark,
+            strategy.size.clone(),
+        );
+
+        // Step 2.
+        controller.init_underlying_sink(cx, sink.handle())?;
+        rooted!(in(*cx) let mut start = UndefinedValue());
+        get_method(cx, sink.handle(), "start", start.handle_mut())?;
+
+        // Step 3.
+        if !sink.is_null() {
+            rooted!(in(*cx) let mut type_ = UndefinedValue());
+            unsafe { get_property_jsval(*cx, sink.handle(), "type", type_.handle_mut())? };
+            if !type_.is_undefined() {
+                return Err(Error::Range(
+                    "Invalid type for a writable stream".to_owned(),
+                ));
+            }
+        }
+
+        // Step 7.
+        stream.set_controller(&controller);
+        rooted!(in(*cx) let controller_value = ObjectValue(controller.reflector().get_jsobject().get()));
+        rooted!(in(*cx) let mut start_result = UndefinedValue());
+        call_method(
+            cx,
+            sink.handle(),
+            start.handle(),
+            &[controller_value.get()],
+            start_result.handle_mut(),
+        )?;
+        controller.start(start_result.handle());
+        Ok(stream)
+    }
+}
+
+impl WritableStreamMethods for WritableStream {
+    // https://streams.spec.whatwg.org/#ws-locked
+    fn Locked(&self) -> bool {
+        self.is_locked()
+    }
+
+    // https://streams.spec.whatwg.org/#ws-abort
+    fn Abort(&self, _cx: SafeJSContext, reason: HandleValue) -> Rc<Promise> {
+        // Step 1.
+        if self.is_locked() {
+            return new_rejected_promise_with_error(
+                &self.global(),
+                Error::Type("The stream is locked".to_owned()),
+            );
+        }
+
+        // Step 2.
+        self.abort(reason)
+    }
+
+    // https://streams.spec.whatwg.org/#ws-close
+    fn Close(&self) -> Rc<Promise> {
+        // Steps 1-2.
+        if self.is_locked() || self.close_queued_or_in_flight() {
+            return new_rejected_promise_with_error(
+                &self.global(),
+                Error::Type("The stream is locked or closing".to_owned()),
+            );
+        }
+
+        // Step 3.
+        self.close()
+    }
+
+    // https://streams.spec.whatwg.org/#ws-get-writer
+    fn GetWriter(&self) -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        WritableStreamDefaultWriter::acquire(self)
+    }
+}
+
+/// Settles the promise of an abort request once the sink is aborted.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct AbortHandler {
+    stream: Dom<WritableStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl Callback for AbortHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue, _realm: InRealm) {
+        let cx = unsafe { SafeJSContext::from_ptr(cx) };
+        if self.fulfilled {
+            self.promise.resolve_native(&());
+        } else {
+            self.promise.reject(cx, v);
+        }
+        self.stream.reject_close_and_closed_promise_if_needed();
+    }
+}
@@ -0,0 +1,397 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::QueuingStrategySize;
+use crate::dom::bindings::codegen::Bindings::WritableStreamDefaultControllerBinding::WritableStreamDefaultControllerMethods;
+use crate::dom::bindings::error::ErrorResult;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::transformstream::TransformStream;
+use crate::dom::writablestream::{WritableStream, WritableStreamState};
+use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::streams::{
+    chunk_size, error_to_jsval, new_resolved_promise, QueueWithSizes, UnderlyingMethod,
+};
+use dom_struct::dom_struct;
+use js::jsapi::JSContext;
+use js::jsval::{ObjectValue, UndefinedValue};
+use js::rust::{HandleObject, HandleValue};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Where the write, close and abort algorithms of a controller come from.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+pub enum SinkAlgorithms {
+    /// The methods of the underlying sink given to the `WritableStream`
+    /// constructor.
+    Object,
+    /// The writable side of a transform stream.
+    Transform(Dom<TransformStream>),
+    /// Writing, closing and aborting do nothing.
+    None,
+}
+
+/// A copy of the `SinkAlgorithms` of a controller.
+#[unrooted_must_root_lint::must_root]
+enum Algorithms {
+    Object,
+    Transform(DomRoot<TransformStream>),
+    None,
+}
+
+#[dom_struct]
+pub struct WritableStreamDefaultController {
+    reflector_: Reflector,
+    stream: Dom<WritableStream>,
+    queue: DomRefCell<QueueWithSizes>,
+    /// Whether the close sentinel follows the chunks of `queue`.
+    close_queued: Cell<bool>,
+    started: Cell<bool>,
+    strategy_hwm: f64,
+    #[ignore_malloc_size_of = "Rc"]
+    strategy_size: DomRefCell<Option<Rc<QueuingStrategySize>>>,
+    algorithms: DomRefCell<SinkAlgorithms>,
+    write: UnderlyingMethod,
+    close: UnderlyingMethod,
+    abort: UnderlyingMethod,
+}
+
+impl WritableStreamDefaultController {
+    #[allow(unrooted_must_root)]
+    fn new_inherited(
+        stream: &WritableStream,
+        algorithms: SinkAlgorithms,
+        high_water_mark: f64,
+        size: Option<Rc<QueuingStrategySize>>,
+    ) -> WritableStreamDefaultController {
+        WritableStreamDefaultController {
+            reflector_: Reflector::new(),
+            stream: Dom::from_ref(stream),
+            queue: Default::default(),
+            close_queued: Cell::new(false),
+            started: Cell::new(false),
+            strategy_hwm: high_water_mark,
+            strategy_size: DomRefCell::new(size),
+            algorithms: DomRefCell::new(algorithms),
+            write: Default::default(),
+            close: Default::default(),
+            abort: Default::default(),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        global: &GlobalScope,
+        stream: &WritableStream,
+        algorithms: SinkAlgorithms,
+        high_water_mark: f64,
+        size: Option<Rc<QueuingStrategySize>>,
+    ) -> DomRoot<WritableStreamDefaultController> {
+        reflect_dom_object(
+            Box::new(WritableStreamDefaultController::new_inherited(
+                stream,
+                algorithms,
+                high_water_mark,
+                size,
+            )),
+            global,
+        )
+    }
+
+    /// Steps 1-8 of
+    /// <https://streams.spec.whatwg.org/#set-up-writable-stream-default-controller-from-underlying-sink>,
+    /// the start method is looked up and called by the constructor.
+    pub fn init_underlying_sink(&self, cx: SafeJSContext, sink: HandleObject) -> ErrorResult {
+        self.abort.init(cx, sink, "abort")?;
+        self.close.init(cx, sink, "close")?;
+        self.write.init(cx, sink, "write")?;
+        Ok(())
+    }
+
+    /// Steps 16-18 of
+    /// <https://streams.spec.whatwg.org/#set-up-writable-stream-default-controller>,
+    /// given the result of the start algorithm.
+    pub fn start(&self, start_result: HandleValue) {
+        let start_promise = new_resolved_promise(&self.global(), start_result);
+        self.upon_settlement(
+            &start_promise,
+            Reaction::StartFulfilled,
+            Reaction::StartRejected,
+        );
+    }
+
+    pub fn started(&self) -> bool {
+        self.started.get()
+    }
+
+    #[allow(unrooted_must_root)]
+    fn upon_settlement(&self, promise: &Promise, on_fulfilled: Reaction, on_rejected: Reaction) {
+        let handler = PromiseNativeHandler::new(
+            &self.global(),
+            Some(Box::new(ControllerHandler {
+                controller: Dom::from_ref(self),
+                reaction: on_fulfilled,
+            })),
+            Some(Box::new(ControllerHandler {
+                controller: Dom::from_ref(self),
+                reaction: on_rejected,
+            })),
+        );
+        promise.append_native_handler(&handler);
+    }
+
+    /// Returns the algorithms to run, taken out of the cell as running them
+    /// may clear it.
+    #[allow(unrooted_must_root)]
+    fn algorithms(&self) -> Algorithms {
+        match *self.algorithms.borrow() {
+            SinkAlgorithms::Object => Algorithms::Object,
+            SinkAlgorithms::Transform(ref stream) => {
+                Algorithms::Transform(DomRoot::from_ref(&**stream))
+            },
+            SinkAlgorithms::None => Algorithms::None,
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    fn write_algorithm(&self, chunk: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        match self.algorithms() {
+            Algorithms::Object => {
+                let controller = ObjectValue(self.reflector().get_jsobject().get());
+                self.write.promise_call(&global, &[chunk.get(), controller])
+            },
+            Algorithms::Transform(stream) => stream.sink_write(chunk),
+            Algorithms::None => new_resolved_promise(&global, HandleValue::undefined()),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    fn close_algorithm(&self) -> Rc<Promise> {
+        let global = self.global();
+        match self.algorithms() {
+            Algorithms::Object => self.close.promise_call(&global, &[]),
+            Algorithms::Transform(stream) => stream.sink_close(),
+            Algorithms::None => new_resolved_promise(&global, HandleValue::undefined()),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    fn abort_algorithm(&self, reason: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        match self.algorithms() {
+            Algorithms::Object => self.abort.promise_call(&global, &[reason.get()]),
+            Algorithms::Transform(stream) => stream.sink_abort(reason),
+            Algorithms::None => new_resolved_promise(&global, HandleValue::undefined()),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-clear-algorithms>
+    fn clear_algorithms(&self) {
+        *self.algorithms.borrow_mut() = SinkAlgorithms::None;
+        *self.strategy_size.borrow_mut() = None;
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-close>
+    pub fn close(&self) {
+        self.close_queued.set(true);
+        self.advance_queue_if_needed();
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-get-chunk-size>
+    pub fn get_chunk_size(&self, cx: SafeJSContext, chunk: HandleValue) -> f64 {
+        let size = self.strategy_size.borrow().clone();
+        match chunk_size(size.as_ref(), chunk) {
+            Ok(size) => size,
+            Err(error) => {
+                rooted!(in(*cx) let mut value = UndefinedValue());
+                error_to_jsval(&self.global(), error, value.handle_mut());
+                self.error_if_needed(value.handle());
+                1.
+            },
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-get-desired-size>
+    pub fn desired_size(&self) -> f64 {
+        self.strategy_hwm - self.queue.borrow().total_size()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-get-backpressure>
+    pub fn get_backpressure(&self) -> bool {
+        self.desired_size() <= 0.
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-write>
+    pub fn write(&self, cx: SafeJSContext, chunk: HandleValue, chunk_size: f64) {
+        // Steps 1-2.
+        let result = self.queue.borrow_mut().enqueue(chunk, chunk_size);
+        if let Err(error) = result {
+            rooted!(in(*cx) let mut value = UndefinedValue());
+            error_to_jsval(&self.global(), error, value.handle_mut());
+            return self.error_if_needed(value.handle());
+        }
+
+        // Steps 3-4.
+        if !self.stream.close_queued_or_in_flight() &&
+            self.stream.state() == WritableStreamState::Writable
+        {
+            self.stream.update_backpressure(self.get_backpressure());
+        }
+
+        // Step 5.
+        self.advance_queue_if_needed();
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-advance-queue-if-needed>
+    fn advance_queue_if_needed(&self) {
+        // Steps 2-3.
+        if !self.started.get() || self.stream.has_write_in_flight() {
+            return;
+        }
+
+        // Steps 4-6.
+        let state = self.stream.state();
+        assert!(state != WritableStreamState::Closed && state != WritableStreamState::Errored);
+        if state == WritableStreamState::Erroring {
+            return self.stream.finish_erroring();
+        }
+
+        // Steps 7-9.
+        if !self.queue.borrow().is_empty() {
+            let cx = self.global().get_cx();
+            rooted!(in(*cx) let mut chunk = UndefinedValue());
+            self.queue.borrow().peek(chunk.handle_mut());
+            self.process_write(chunk.handle());
+        } else if self.close_queued.get() {
+            self.process_close();
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-error-if-needed>
+    fn error_if_needed(&self, error: HandleValue) {
+        if self.stream.state() == WritableStreamState::Writable {
+            self.error(error);
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-process-close>
+    fn process_close(&self) {
+        self.stream.mark_close_request_in_flight();
+        self.close_queued.set(false);
+        assert!(self.queue.borrow().is_empty());
+        let sink_close_promise = self.close_algorithm();
+        self.clear_algorithms();
+        self.upon_settlement(
+            &sink_close_promise,
+            Reaction::CloseFulfilled,
+            Reaction::CloseRejected,
+        );
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-process-write>
+    fn process_write(&self, chunk: HandleValue) {
+        self.stream.mark_first_write_request_in_flight();
+        let sink_write_promise = self.write_algorithm(chunk);
+        self.upon_settlement(
+            &sink_write_promise,
+            Reaction::WriteFulfilled,
+            Reaction::WriteRejected,
+        );
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-controller-error>
+    pub fn error(&self, error: HandleValue) {
+        assert_eq!(self.stream.state(), WritableStreamState::Writable);
+        self.clear_algorithms();
+        self.stream.start_erroring(error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-default-controller-private-abort>
+    pub fn abort_steps(&self, reason: HandleValue) -> Rc<Promise> {
+        let result = self.abort_algorithm(reason);
+        self.clear_algorithms();
+        result
+    }
+
+    /// <https://streams.spec.whatwg.org/#ws-default-controller-private-error>
+    pub fn error_steps(&self) {
+        self.queue.borrow_mut().reset();
+        self.close_queued.set(false);
+    }
+}
+
+impl WritableStreamDefaultControllerMethods for WritableStreamDefaultController {
+    // https://streams.spec.whatwg.org/#ws-default-controller-error
+    fn Error(&self, _cx: SafeJSContext, e: HandleValue) {
+        // Steps 1-2.
+        if self.stream.state() != WritableStreamState::Writable {
+            return;
+        }
+
+        // Step 3.
+        self.error(e);
+    }
+}
+
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf)]
+enum Reaction {
+    StartFulfilled,
+    StartRejected,
+    WriteFulfilled,
+    WriteRejected,
+    CloseFulfilled,
+    CloseRejected,
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct ControllerHandler {
+    controller: Dom<WritableStreamDefaultController>,
+    reaction: Reaction,
+}
+
+impl Callback for ControllerHandler {
+    fn callback(&self, _cx: *mut JSContext, v: HandleValue, _realm: InRealm) {
+        let controller = &self.controller;
+        let stream = &controller.stream;
+        let _ac = enter_realm(&**controller);
+        match self.reaction {
+            Reaction::StartFulfilled => {
+                controller.started.set(true);
+                controller.advance_queue_if_needed();
+            },
+            Reaction::StartRejected => {
+                controller.started.set(true);
+                stream.deal_with_rejection(v);
+            },
+            Reaction::WriteFulfilled => {
+                stream.finish_in_flight_write();
+                let cx = controller.global().get_cx();
+                rooted!(in(*cx) let mut chunk = UndefinedValue());
+                controller.queue.borrow_mut().dequeue(chunk.handle_mut());
+                if !stream.close_queued_or_in_flight() &&
+                    stream.state() == WritableStreamState::Writable
+                {
+                    stream.update_backpressure(controller.get_backpressure());
+                }
+                controller.advance_queue_if_needed();
+            },
+            Reaction::WriteRejected => {
+                if stream.state() == WritableStreamState::Writable {
+                    controller.clear_algorithms();
+                }
+                stream.finish_in_flight_write_with_error(v);
+            },
+            Reaction::CloseFulfilled => stream.finish_in_flight_close(),
+            Reaction::CloseRejected => stream.finish_in_flight_close_with_error(v),
+        }
+    }
+}
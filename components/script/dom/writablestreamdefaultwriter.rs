@@ -0,0 +1,319 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::WritableStreamDefaultWriterBinding::WritableStreamDefaultWriterMethods;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::writablestream::{WritableStream, WritableStreamState};
+use crate::realms::enter_realm;
+use crate::script_runtime::JSContext;
+use crate::streams::{
+    error_to_jsval, is_pending, mark_as_handled, new_rejected_promise,
+    new_rejected_promise_with_error, new_resolved_promise,
+};
+use dom_struct::dom_struct;
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use std::rc::Rc;
+
+#[dom_struct]
+pub struct WritableStreamDefaultWriter {
+    reflector_: Reflector,
+    stream: MutNullableDom<WritableStream>,
+    #[ignore_malloc_size_of = "Rc"]
+    closed_promise: DomRefCell<Rc<Promise>>,
+    #[ignore_malloc_size_of = "Rc"]
+    ready_promise: DomRefCell<Rc<Promise>>,
+}
+
+impl WritableStreamDefaultWriter {
+    fn new_inherited(global: &GlobalScope) -> WritableStreamDefaultWriter {
+        WritableStreamDefaultWriter {
+            reflector_: Reflector::new(),
+            stream: Default::default(),
+            closed_promise: DomRefCell::new(Promise::new(global)),
+            ready_promise: DomRefCell::new(Promise::new(global)),
+        }
+    }
+
+    fn new(global: &GlobalScope) -> DomRoot<WritableStreamDefaultWriter> {
+        reflect_dom_object(
+            Box::new(WritableStreamDefaultWriter::new_inherited(global)),
+            global,
+        )
+    }
+
+    /// <https://streams.spec.whatwg.org/#acquire-writable-stream-default-writer>
+    pub fn acquire(stream: &WritableStream) -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        let writer = WritableStreamDefaultWriter::new(&stream.global());
+        writer.set_up(stream)?;
+        Ok(writer)
+    }
+
+    /// <https://streams.spec.whatwg.org/#set-up-writable-stream-default-writer>
+    fn set_up(&self, stream: &WritableStream) -> Fallible<()> {
+        // Step 1.
+        if stream.is_locked() {
+            return Err(Error::Type("The stream is locked".to_owned()));
+        }
+
+        // Steps 2-3.
+        self.stream.set(Some(stream));
+        stream.set_writer(Some(self));
+
+        // Steps 4-9, the promises are created pending.
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut stored_error = UndefinedValue());
+        stream.stored_error(stored_error.handle_mut());
+        match stream.state() {
+            WritableStreamState::Writable => {
+                if stream.close_queued_or_in_flight() || !stream.has_backpressure() {
+                    self.ready_promise.borrow().resolve_native(&());
+                }
+            },
+            WritableStreamState::Erroring => {
+                let ready_promise = new_rejected_promise(&global, stored_error.handle());
+                mark_as_handled(&global, &ready_promise);
+                *self.ready_promise.borrow_mut() = ready_promise;
+            },
+            WritableStreamState::Closed => {
+                self.ready_promise.borrow().resolve_native(&());
+                self.closed_promise.borrow().resolve_native(&());
+            },
+            WritableStreamState::Errored => {
+                let ready_promise = new_rejected_promise(&global, stored_error.handle());
+                mark_as_handled(&global, &ready_promise);
+                *self.ready_promise.borrow_mut() = ready_promise;
+                let closed_promise = new_rejected_promise(&global, stored_error.handle());
+                mark_as_handled(&global, &closed_promise);
+                *self.closed_promise.borrow_mut() = closed_promise;
+            },
+        }
+        Ok(())
+    }
+
+    pub fn closed_promise(&self) -> Rc<Promise> {
+        self.closed_promise.borrow().clone()
+    }
+
+    pub fn ready_promise(&self) -> Rc<Promise> {
+        self.ready_promise.borrow().clone()
+    }
+
+    /// Replaces the ready promise by a pending one, when backpressure is applied.
+    pub fn reset_ready_promise(&self) {
+        *self.ready_promise.borrow_mut() = Promise::new(&self.global());
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-ensure-closed-promise-rejected>
+    pub fn ensure_closed_promise_rejected(&self, error: HandleValue) {
+        ensure_promise_rejected(&self.global(), &self.closed_promise, error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-ensure-ready-promise-rejected>
+    pub fn ensure_ready_promise_rejected(&self, error: HandleValue) {
+        ensure_promise_rejected(&self.global(), &self.ready_promise, error);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-get-desired-size>
+    fn desired_size(&self, stream: &WritableStream) -> Option<f64> {
+        match stream.state() {
+            WritableStreamState::Errored | WritableStreamState::Erroring => None,
+            WritableStreamState::Closed => Some(0.),
+            WritableStreamState::Writable => Some(stream.controller().desired_size()),
+        }
+    }
+
+    fn stream(&self) -> DomRoot<WritableStream> {
+        self.stream.get().expect("The writer's lock was released")
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-close>
+    pub fn close(&self) -> Rc<Promise> {
+        self.stream().close()
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-close-with-error-propagation>
+    pub fn close_with_error_propagation(&self) -> Rc<Promise> {
+        let global = self.global();
+        let stream = self.stream();
+        match stream.state() {
+            // Step 4.
+            _ if stream.close_queued_or_in_flight() => {
+                new_resolved_promise(&global, HandleValue::undefined())
+            },
+            WritableStreamState::Closed => new_resolved_promise(&global, HandleValue::undefined()),
+            // Step 5.
+            WritableStreamState::Errored => {
+                rooted!(in(*global.get_cx()) let mut error = UndefinedValue());
+                stream.stored_error(error.handle_mut());
+                new_rejected_promise(&global, error.handle())
+            },
+            // Step 7.
+            WritableStreamState::Writable | WritableStreamState::Erroring => self.close(),
+        }
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-release>
+    pub fn release(&self) {
+        let global = self.global();
+        let cx = global.get_cx();
+        let _ac = enter_realm(self);
+        let stream = self.stream();
+
+        // Steps 4-6.
+        rooted!(in(*cx) let mut released_error = UndefinedValue());
+        error_to_jsval(
+            &global,
+            Error::Type("The writer's lock was released".to_owned()),
+            released_error.handle_mut(),
+        );
+        self.ensure_ready_promise_rejected(released_error.handle());
+        self.ensure_closed_promise_rejected(released_error.handle());
+
+        // Steps 7-8.
+        stream.set_writer(None);
+        self.stream.set(None);
+    }
+
+    /// <https://streams.spec.whatwg.org/#writable-stream-default-writer-write>
+    pub fn write(&self, cx: JSContext, chunk: HandleValue) -> Rc<Promise> {
+        let global = self.global();
+        let stream = self.stream();
+
+        // Steps 3-4.
+        let controller = stream.controller();
+        let chunk_size = controller.get_chunk_size(cx, chunk);
+
+        // Step 5.
+        if self.stream.get().map_or(true, |current| current != stream) {
+            return new_rejected_promise_with_error(
+                &global,
+                Error::Type("The writer's lock was released".to_owned()),
+            );
+        }
+
+        // Steps 6-9.
+        let state = stream.state();
+        if state == WritableStreamState::Errored || state == WritableStreamState::Erroring {
+            rooted!(in(*cx) let mut error = UndefinedValue());
+            stream.stored_error(error.handle_mut());
+            return new_rejected_promise(&global, error.handle());
+        }
+        if stream.close_queued_or_in_flight() || state == WritableStreamState::Closed {
+            return new_rejected_promise_with_error(
+                &global,
+                Error::Type("The stream is closing or closed".to_owned()),
+            );
+        }
+
+        // Steps 11-13.
+        let promise = stream.add_write_request();
+        controller.write(cx, chunk, chunk_size);
+        promise
+    }
+
+    /// <https://streams.spec.whatwg.org/#default-writer-constructor>
+    #[allow(non_snake_case)]
+    pub fn Constructor(
+        global: &GlobalScope,
+        stream: &WritableStream,
+    ) -> Fallible<DomRoot<WritableStreamDefaultWriter>> {
+        let writer = WritableStreamDefaultWriter::new(global);
+        writer.set_up(stream)?;
+        Ok(writer)
+    }
+}
+
+/// Rejects `promise` with `error` if it is pending, and replaces it by a
+/// promise rejected with `error` otherwise, marking the result as handled.
+fn ensure_promise_rejected(
+    global: &GlobalScope,
+    promise: &DomRefCell<Rc<Promise>>,
+    error: HandleValue,
+) {
+    let _ac = enter_realm(global);
+    let current = promise.borrow().clone();
+    let rejected = if is_pending(&current) {
+        current.reject(global.get_cx(), error);
+        current
+    } else {
+        let rejected = new_rejected_promise(global, error);
+        *promise.borrow_mut() = rejected.clone();
+        rejected
+    };
+    mark_as_handled(global, &rejected);
+}
+
+impl WritableStreamDefaultWriterMethods for WritableStreamDefaultWriter {
+    // https://streams.spec.whatwg.org/#default-writer-closed
+    fn Closed(&self) -> Rc<Promise> {
+        self.closed_promise()
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-desired-size
+    fn GetDesiredSize(&self) -> Fallible<Option<f64>> {
+        match self.stream.get() {
+            Some(stream) => Ok(self.desired_size(&stream)),
+            None => Err(Error::Type("The writer's lock was released".to_owned())),
+        }
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-ready
+    fn Ready(&self) -> Rc<Promise> {
+        self.ready_promise()
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-abort
+    fn Abort(&self, _cx: JSContext, reason: HandleValue) -> Rc<Promise> {
+        match self.stream.get() {
+            Some(stream) => stream.abort(reason),
+            None => new_rejected_promise_with_error(
+                &self.global(),
+                Error::Type("The writer's lock was released".to_owned()),
+            ),
+        }
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-close
+    fn Close(&self) -> Rc<Promise> {
+        let global = self.global();
+        match self.stream.get() {
+            None => new_rejected_promise_with_error(
+                &global,
+                Error::Type("The writer's lock was released".to_owned()),
+            ),
+            Some(ref stream) if stream.close_queued_or_in_flight() => {
+                new_rejected_promise_with_error(
+                    &global,
+                    Error::Type("The stream is already closing".to_owned()),
+                )
+            },
+            Some(_) => self.close(),
+        }
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-release-lock
+    fn ReleaseLock(&self) {
+        if self.stream.get().is_some() {
+            self.release();
+        }
+    }
+
+    // https://streams.spec.whatwg.org/#default-writer-write
+    fn Write(&self, cx: JSContext, chunk: HandleValue) -> Rc<Promise> {
+        if self.stream.get().is_none() {
+            return new_rejected_promise_with_error(
+                &self.global(),
+                Error::Type("The writer's lock was released".to_owned()),
+            );
+        }
+        self.write(cx, chunk)
+    }
+}
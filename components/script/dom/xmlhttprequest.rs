@@ -655,7 +655,8 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
                     .map(|referrer_url| Referrer::ReferrerUrl(referrer_url)),
             )
             .referrer_policy(self.referrer_policy.clone())
-            .pipeline_id(Some(self.global().pipeline_id()));
+            .pipeline_id(Some(self.global().pipeline_id()))
+            .top_level_origin(self.global().top_level_origin());
 
         // step 4 (second half)
         match extracted_or_serialized {
@@ -2,20 +2,27 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::body::BodyOperations;
+use crate::dom::abortsignal::{AbortAlgorithm, AbortSignal};
+use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestInfo;
 use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestInit;
+use crate::dom::bindings::codegen::Bindings::RequestBinding::RequestMethods;
 use crate::dom::bindings::codegen::Bindings::ResponseBinding::ResponseBinding::ResponseMethods;
 use crate::dom::bindings::codegen::Bindings::ResponseBinding::ResponseType as DOMResponseType;
 use crate::dom::bindings::error::Error;
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::refcounted::{Trusted, TrustedPromise};
 use crate::dom::bindings::reflector::DomObject;
-use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::trace::RootedTraceableBox;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::headers::Guard;
 use crate::dom::performanceresourcetiming::InitiatorType;
 use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::readablestream::ReadableStream;
+use crate::dom::readablestreamdefaultreader::ReadableStreamDefaultReader;
 use crate::dom::request::Request;
 use crate::dom::response::Response;
 use crate::dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
@@ -23,9 +30,16 @@ use crate::network_listener::{
     self, submit_timing_data, NetworkListener, PreInvoke, ResourceTimingListener,
 };
 use crate::realms::{enter_realm, InRealm};
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::streams::unpack_read_result;
 use crate::task_source::TaskSourceName;
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
+use js::jsapi::JSContext;
+use js::jsval::UndefinedValue;
+use js::rust::HandleValue;
+use js::typedarray::Uint8Array;
+use net_traits::request::{BodyChunkRequest, BodyChunkResponse};
 use net_traits::request::{
     CorsSettings, CredentialsMode, Destination, RequestBuilder, RequestMode,
 };
@@ -36,14 +50,16 @@ use net_traits::{FetchChannels, FetchResponseListener, NetworkError};
 use net_traits::{FetchMetadata, FilteredMetadata, Metadata};
 use net_traits::{ResourceFetchTiming, ResourceTimingType};
 use servo_url::ServoUrl;
-use std::mem;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 struct FetchContext {
     fetch_promise: Option<TrustedPromise>,
     response_object: Trusted<Response>,
-    body: Vec<u8>,
+    body_stream: Option<Trusted<ReadableStream>>,
+    /// The signal of the request, once it is aborted the messages of the
+    /// fetch are ignored.
+    signal: Trusted<AbortSignal>,
     resource_timing: ResourceFetchTiming,
 }
 
@@ -106,6 +122,7 @@ fn request_init_from_request(request: NetTraitsRequest) -> RequestBuilder {
         headers: request.headers.clone(),
         unsafe_request: request.unsafe_request,
         body: request.body.clone(),
+        body_chunks: request.body_chunks.clone(),
         service_workers_mode: ServiceWorkersMode::All,
         destination: request.destination,
         synchronous: request.synchronous,
@@ -129,6 +146,7 @@ fn request_init_from_request(request: NetTraitsRequest) -> RequestBuilder {
         initiator: request.initiator,
         csp_list: None,
         https_state: request.https_state,
+        top_level_origin: request.top_level_origin.clone(),
     }
 }
 
@@ -140,8 +158,6 @@ pub fn Fetch(
     init: RootedTraceableBox<RequestInit>,
     comp: InRealm,
 ) -> Rc<Promise> {
-    let core_resource_thread = global.core_resource_thread();
-
     // Step 1
     let promise = Promise::new_in_current_realm(global, comp);
     let response = Response::new(global);
@@ -152,12 +168,23 @@ pub fn Fetch(
             promise.reject_error(e);
             return promise;
         },
-        Ok(r) => r.get_request(),
+        Ok(r) => r,
     };
+    let signal = request.Signal();
+    if signal.aborted() {
+        rooted!(in(*global.get_cx()) let mut reason = UndefinedValue());
+        signal.reason(reason.handle_mut());
+        promise.reject_native(&reason.handle());
+        return promise;
+    }
+    let body_stream = request.body_stream();
+    let request = request.get_request();
     let timing_type = request.timing_type();
+    let has_body_bytes = request.body.is_some();
 
     let mut request_init = request_init_from_request(request);
     request_init.csp_list = global.get_csp_list().clone();
+    request_init.top_level_origin = global.top_level_origin();
 
     // Step 3
     if global.downcast::<ServiceWorkerGlobalScope>().is_some() {
@@ -167,12 +194,42 @@ pub fn Fetch(
     // Step 4
     response.Headers().set_guard(Guard::Immutable);
 
+    // Aborting the signal of the request rejects the promise and cancels
+    // the fetch in the net layer.
+    let mut canceller = FetchCanceller::new();
+    let cancel_chan = canceller.initialize();
+    signal.add_abort_algorithm(AbortAlgorithm::Fetch {
+        promise: promise.clone(),
+        response: Dom::from_ref(&*response),
+        canceller: DomRefCell::new(canceller),
+    });
+
+    // The body of the request is consumed by the fetch, a body given as a
+    // stream is sent to the network a chunk at a time, as it is read.
+    match body_stream {
+        Some(ref stream) if !has_body_bytes => {
+            let reader = match stream.acquire_default_reader() {
+                Ok(reader) => reader,
+                Err(e) => {
+                    signal.remove_fetch_algorithm(&response);
+                    promise.reject_error(e);
+                    return promise;
+                },
+            };
+            request_init.body_chunks = Some(transmit_request_body(global, &reader, &signal));
+        },
+        Some(ref stream) => stream.set_disturbed(),
+        None => {},
+    }
+
     // Step 5
+    let core_resource_thread = global.core_resource_thread();
     let (action_sender, action_receiver) = ipc::channel().unwrap();
     let fetch_context = Arc::new(Mutex::new(FetchContext {
         fetch_promise: Some(TrustedPromise::new(promise.clone())),
         response_object: Trusted::new(&*response),
-        body: vec![],
+        body_stream: None,
+        signal: Trusted::new(&*signal),
         resource_timing: ResourceFetchTiming::new(timing_type),
     }));
     let listener = NetworkListener {
@@ -190,13 +247,114 @@ pub fn Fetch(
     core_resource_thread
         .send(NetTraitsFetch(
             request_init,
-            FetchChannels::ResponseMsg(action_sender, None),
+            FetchChannels::ResponseMsg(action_sender, Some(cancel_chan)),
         ))
         .unwrap();
 
     promise
 }
 
+/// Sends a request body given as a stream to the network a chunk at a time, as it is
+/// read, once the network connects to it. Only the first connection can read the body.
+fn transmit_request_body(
+    global: &GlobalScope,
+    reader: &ReadableStreamDefaultReader,
+    signal: &AbortSignal,
+) -> ipc::IpcSender<BodyChunkRequest> {
+    let (body_sender, body_receiver) = ipc::channel().unwrap();
+    let mut body = Some((Trusted::new(reader), Trusted::new(signal)));
+    let task_source = global.networking_task_source();
+    let canceller = global.task_canceller(TaskSourceName::Networking);
+    ROUTER.add_route(
+        body_receiver.to_opaque(),
+        Box::new(move |message| {
+            let BodyChunkRequest::Connect(chunk_sender) = message.to().unwrap();
+            let (reader, signal) = match body.take() {
+                Some(body) => body,
+                None => {
+                    let _ = chunk_sender.send(BodyChunkResponse::Error);
+                    return;
+                },
+            };
+            let _ = task_source.queue_with_canceller(
+                task!(transmit_request_body: move || {
+                    transmit_body_chunk(&reader.root(), &signal.root(), chunk_sender);
+                }),
+                &canceller,
+            );
+        }),
+    );
+    body_sender
+}
+
+/// Reads the next chunk of a request body, to send it to the network.
+#[allow(unrooted_must_root)]
+fn transmit_body_chunk(
+    reader: &ReadableStreamDefaultReader,
+    signal: &AbortSignal,
+    chunk_sender: ipc::IpcSender<BodyChunkResponse>,
+) {
+    let handler = PromiseNativeHandler::new(
+        &reader.global(),
+        Some(Box::new(TransmitBodyHandler {
+            reader: Dom::from_ref(reader),
+            signal: Dom::from_ref(signal),
+            chunk_sender: chunk_sender.clone(),
+            fulfilled: true,
+        })),
+        Some(Box::new(TransmitBodyHandler {
+            reader: Dom::from_ref(reader),
+            signal: Dom::from_ref(signal),
+            chunk_sender,
+            fulfilled: false,
+        })),
+    );
+    reader.read().append_native_handler(&handler);
+}
+
+/// Sends a chunk read from a request body to the network, and reads the next one.
+/// Each chunk must be a `Uint8Array`.
+#[derive(JSTraceable, MallocSizeOf)]
+#[unrooted_must_root_lint::must_root]
+struct TransmitBodyHandler {
+    reader: Dom<ReadableStreamDefaultReader>,
+    signal: Dom<AbortSignal>,
+    #[ignore_malloc_size_of = "Channels are hard"]
+    chunk_sender: ipc::IpcSender<BodyChunkResponse>,
+    fulfilled: bool,
+}
+
+impl Callback for TransmitBodyHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue, _realm: InRealm) {
+        // Aborting the fetch also stops the transmission of its body.
+        if !self.fulfilled || self.signal.aborted() {
+            let _ = self.chunk_sender.send(BodyChunkResponse::Error);
+            return;
+        }
+        let cx = unsafe { SafeJSContext::from_ptr(cx) };
+        rooted!(in(*cx) let mut value = UndefinedValue());
+        let chunk = match unpack_read_result(cx, v, value.handle_mut()) {
+            Ok(true) => BodyChunkResponse::Done,
+            Ok(false) if value.is_object() => {
+                typedarray!(in(*cx) let array: Uint8Array = value.to_object());
+                match array {
+                    Ok(array) => BodyChunkResponse::Chunk(unsafe { array.to_vec() }),
+                    Err(()) => BodyChunkResponse::Error,
+                }
+            },
+            Ok(false) | Err(_) => BodyChunkResponse::Error,
+        };
+        let read_next = match chunk {
+            BodyChunkResponse::Chunk(_) => true,
+            BodyChunkResponse::Done | BodyChunkResponse::Error => false,
+        };
+        if self.chunk_sender.send(chunk).is_ok() && read_next {
+            transmit_body_chunk(&self.reader, &self.signal, self.chunk_sender.clone());
+        }
+    }
+}
+
 impl PreInvoke for FetchContext {}
 
 impl FetchResponseListener for FetchContext {
@@ -210,6 +368,9 @@ impl FetchResponseListener for FetchContext {
 
     #[allow(unrooted_must_root)]
     fn process_response(&mut self, fetch_metadata: Result<FetchMetadata, NetworkError>) {
+        if self.signal.root().aborted() {
+            return;
+        }
         let promise = self
             .fetch_promise
             .take()
@@ -256,19 +417,42 @@ impl FetchResponseListener for FetchContext {
             },
         }
         // Step 4.3
-        promise.resolve_native(&self.response_object.root());
+        let response = self.response_object.root();
+        self.body_stream = response
+            .new_body_stream()
+            .map(|stream| Trusted::new(&*stream));
+        promise.resolve_native(&response);
         self.fetch_promise = Some(TrustedPromise::new(promise));
     }
 
-    fn process_response_chunk(&mut self, mut chunk: Vec<u8>) {
+    fn process_response_chunk(&mut self, chunk: Vec<u8>) {
+        if self.signal.root().aborted() {
+            return;
+        }
         self.response_object.root().stream_chunk(chunk.as_slice());
-        self.body.append(&mut chunk);
+        if let Some(ref stream) = self.body_stream {
+            stream.root().enqueue_native(chunk);
+        }
     }
 
-    fn process_response_eof(&mut self, _response: Result<ResourceFetchTiming, NetworkError>) {
-        let response = self.response_object.root();
-        let _ac = enter_realm(&*response);
-        response.finish(mem::replace(&mut self.body, vec![]));
+    fn process_response_eof(&mut self, response: Result<ResourceFetchTiming, NetworkError>) {
+        let response_object = self.response_object.root();
+        let _ac = enter_realm(&*response_object);
+        let signal = self.signal.root();
+        if signal.aborted() {
+            return response_object.finish();
+        }
+        // The fetch is over, aborting the signal must no longer affect the response.
+        signal.remove_fetch_algorithm(&response_object);
+        if let Some(stream) = self.body_stream.take() {
+            match response {
+                Ok(_) => stream.root().close_native(),
+                Err(_) => stream
+                    .root()
+                    .error_native(Error::Type("Network error occurred".to_string())),
+            }
+        }
+        response_object.finish();
         // TODO
         // ... trailerObject is not supported in Servo yet.
     }
@@ -316,7 +500,9 @@ pub fn load_whole_resource(
     core_resource_thread: &CoreResourceThread,
     global: &GlobalScope,
 ) -> Result<(Metadata, Vec<u8>), NetworkError> {
-    let request = request.https_state(global.get_https_state());
+    let request = request
+        .https_state(global.get_https_state())
+        .top_level_origin(global.top_level_origin());
     let (action_sender, action_receiver) = ipc::channel().unwrap();
     let url = request.url.clone();
     core_resource_thread
@@ -0,0 +1,572 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Conversions between JS values and IndexedDB keys, and the evaluation of key paths.
+//!
+//! Values are stored by the IndexedDB thread as structured clones,
+//! the algorithms here run against the deserialized copy of a value.
+
+use crate::dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use crate::dom::bindings::conversions::{jsstring_to_str, root_from_handlevalue};
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::structuredclone;
+use crate::dom::domexception::DOMErrorName;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbkeyrange::IDBKeyRange;
+use crate::script_runtime::JSContext as SafeJSContext;
+use js::conversions::ToJSValConvertible;
+use js::jsapi::{ClippedTime, DateGetMsecSinceEpoch, Handle, HandleValueArray, JSContext};
+use js::jsapi::{JSObject, JS_GetStringLength, JS_NewArrayObject, JS_NewPlainObject};
+use js::jsapi::{JS_ClearPendingException, JSPROP_ENUMERATE};
+use js::jsapi::{NewDateObject, ObjectIsDate};
+use js::jsval::{DoubleValue, ObjectValue, UndefinedValue};
+use js::rust::jsapi_wrapped::{JS_GetArrayLength, JS_GetElement};
+use js::rust::wrappers::{JS_DefineProperty, JS_GetProperty, JS_HasOwnProperty, JS_IsArrayObject};
+use js::rust::{HandleValue, MutableHandleValue};
+use js::typedarray::{ArrayBuffer, ArrayBufferView, CreateWith};
+use net_traits::indexeddb_thread::{BackendError, IndexedDBKeyRange, IndexedDBKeyType};
+use net_traits::indexeddb_thread::{IndexedDBRecord, KeyPath};
+use script_traits::StructuredSerializedData;
+use std::ffi::CString;
+use std::ptr;
+
+/// The outcome of https://w3c.github.io/IndexedDB/#extract-a-key-from-a-value-using-a-key-path
+pub enum ExtractionResult {
+    Key(IndexedDBKeyType),
+    /// The key path resolved to a value that is not a valid key.
+    Invalid,
+    /// The key path did not resolve to a value.
+    Failure,
+}
+
+pub fn backend_error_name(error: BackendError) -> DOMErrorName {
+    match error {
+        BackendError::Constraint => DOMErrorName::ConstraintError,
+        BackendError::NotFound => DOMErrorName::NotFoundError,
+        BackendError::Version => DOMErrorName::VersionError,
+        BackendError::Abort => DOMErrorName::AbortError,
+        BackendError::Unknown => DOMErrorName::UnknownError,
+    }
+}
+
+pub fn backend_error(error: BackendError) -> Error {
+    match error {
+        BackendError::Constraint => Error::Constraint,
+        BackendError::NotFound => Error::NotFound,
+        BackendError::Version => Error::Version,
+        BackendError::Abort => Error::Abort,
+        BackendError::Unknown => Error::Unknown,
+    }
+}
+
+/// Wraps a key so that it can be converted to a JS value.
+struct JSKey<'a>(&'a IndexedDBKeyType);
+
+impl<'a> ToJSValConvertible for JSKey<'a> {
+    #[allow(unsafe_code)]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, mut rval: MutableHandleValue) {
+        match *self.0 {
+            IndexedDBKeyType::Number(number) => number.to_jsval(cx, rval),
+            IndexedDBKeyType::Date(time) => {
+                let date = NewDateObject(cx, ClippedTime { t: time });
+                rval.set(ObjectValue(date));
+            },
+            IndexedDBKeyType::String(ref string) => string.to_jsval(cx, rval),
+            IndexedDBKeyType::Binary(ref bytes) => {
+                rooted!(in(cx) let mut buffer = ptr::null_mut::<JSObject>());
+                assert!(
+                    ArrayBuffer::create(cx, CreateWith::Slice(bytes), buffer.handle_mut()).is_ok()
+                );
+                rval.set(ObjectValue(buffer.get()));
+            },
+            IndexedDBKeyType::Array(ref keys) => keys
+                .iter()
+                .map(JSKey)
+                .collect::<Vec<_>>()
+                .to_jsval(cx, rval),
+        }
+    }
+}
+
+/// https://w3c.github.io/IndexedDB/#convert-a-key-to-a-value
+#[allow(unsafe_code)]
+pub fn key_type_to_jsval(cx: SafeJSContext, key: &IndexedDBKeyType, rval: MutableHandleValue) {
+    unsafe { JSKey(key).to_jsval(*cx, rval) }
+}
+
+/// https://w3c.github.io/IndexedDB/#convert-value-to-key
+/// Returns a `DataError` if the value is not a valid key.
+pub fn convert_value_to_key(cx: SafeJSContext, input: HandleValue) -> Fallible<IndexedDBKeyType> {
+    convert_value_to_key_with_seen(cx, input, &mut vec![])
+}
+
+#[allow(unsafe_code)]
+fn convert_value_to_key_with_seen(
+    cx: SafeJSContext,
+    input: HandleValue,
+    seen: &mut Vec<*mut JSObject>,
+) -> Fallible<IndexedDBKeyType> {
+    if input.is_number() {
+        let number = input.to_number();
+        if number.is_nan() {
+            return Err(Error::Data);
+        }
+        return Ok(IndexedDBKeyType::Number(number));
+    }
+
+    if input.is_string() {
+        let string = unsafe { jsstring_to_str(*cx, input.to_string()) };
+        return Ok(IndexedDBKeyType::String(string.into()));
+    }
+
+    if !input.is_object() {
+        return Err(Error::Data);
+    }
+
+    unsafe {
+        rooted!(in(*cx) let object = input.to_object());
+
+        let mut is_date = false;
+        if !ObjectIsDate(*cx, Handle::from(object.handle()), &mut is_date) {
+            return Err(Error::JSFailed);
+        }
+        if is_date {
+            let mut time = 0.;
+            if !DateGetMsecSinceEpoch(*cx, Handle::from(object.handle()), &mut time) {
+                return Err(Error::JSFailed);
+            }
+            if time.is_nan() {
+                return Err(Error::Data);
+            }
+            return Ok(IndexedDBKeyType::Date(time));
+        }
+
+        typedarray!(in(*cx) let buffer: ArrayBuffer = object.get());
+        if let Ok(buffer) = buffer {
+            return Ok(IndexedDBKeyType::Binary(buffer.as_slice().to_vec()));
+        }
+        typedarray!(in(*cx) let view: ArrayBufferView = object.get());
+        if let Ok(view) = view {
+            return Ok(IndexedDBKeyType::Binary(view.as_slice().to_vec()));
+        }
+
+        let mut is_array = false;
+        if !JS_IsArrayObject(*cx, input, &mut is_array) {
+            return Err(Error::JSFailed);
+        }
+        if !is_array || seen.contains(&object.get()) {
+            return Err(Error::Data);
+        }
+        seen.push(object.get());
+
+        let mut length = 0;
+        if !JS_GetArrayLength(*cx, object.handle(), &mut length) {
+            return Err(Error::JSFailed);
+        }
+        let mut keys = Vec::with_capacity(length as usize);
+        for index in 0..length {
+            rooted!(in(*cx) let mut entry = UndefinedValue());
+            if !JS_GetElement(*cx, object.handle(), index, &mut entry.handle_mut()) {
+                return Err(Error::JSFailed);
+            }
+            keys.push(convert_value_to_key_with_seen(cx, entry.handle(), seen)?);
+        }
+        seen.pop();
+        Ok(IndexedDBKeyType::Array(keys))
+    }
+}
+
+/// https://w3c.github.io/IndexedDB/#convert-a-value-to-a-multientry-key
+#[allow(unsafe_code)]
+fn convert_value_to_multi_entry_keys(
+    cx: SafeJSContext,
+    input: HandleValue,
+) -> Fallible<Vec<IndexedDBKeyType>> {
+    let mut is_array = false;
+    if input.is_object() && unsafe { !JS_IsArrayObject(*cx, input, &mut is_array) } {
+        return Err(Error::JSFailed);
+    }
+    if !is_array {
+        return convert_value_to_key(cx, input).map(|key| vec![key]);
+    }
+
+    rooted!(in(*cx) let object = input.to_object());
+    let mut seen = vec![object.get()];
+    let mut length = 0;
+    if unsafe { !JS_GetArrayLength(*cx, object.handle(), &mut length) } {
+        return Err(Error::JSFailed);
+    }
+    let mut keys = vec![];
+    for index in 0..length {
+        rooted!(in(*cx) let mut entry = UndefinedValue());
+        if unsafe { !JS_GetElement(*cx, object.handle(), index, &mut entry.handle_mut()) } {
+            return Err(Error::JSFailed);
+        }
+        // Entries that are not valid keys are skipped.
+        match convert_value_to_key_with_seen(cx, entry.handle(), &mut seen) {
+            Ok(key) => keys.push(key),
+            Err(Error::Data) => {},
+            Err(error) => return Err(error),
+        }
+    }
+    keys.sort();
+    keys.dedup();
+    Ok(keys)
+}
+
+/// https://w3c.github.io/IndexedDB/#convert-a-value-to-a-key-range
+/// An undefined or null value is the unbounded range, unless `null_disallowed` is set.
+pub fn convert_value_to_key_range(
+    cx: SafeJSContext,
+    input: HandleValue,
+    null_disallowed: bool,
+) -> Fallible<IndexedDBKeyRange> {
+    if let Ok(range) = root_from_handlevalue::<IDBKeyRange>(input, *cx) {
+        return Ok(range.inner().clone());
+    }
+    if input.is_undefined() || input.is_null() {
+        if null_disallowed {
+            return Err(Error::Data);
+        }
+        return Ok(IndexedDBKeyRange::unbounded());
+    }
+    convert_value_to_key(cx, input).map(IndexedDBKeyRange::only)
+}
+
+/// https://w3c.github.io/IndexedDB/#valid-key-path
+pub fn is_valid_key_path(key_path: &KeyPath) -> bool {
+    fn is_identifier(name: &str) -> bool {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(c) if c.is_alphabetic() || c == '$' || c == '_' => {},
+            _ => return false,
+        }
+        chars.all(|c| c.is_alphanumeric() || c == '$' || c == '_')
+    }
+
+    fn is_valid_string(key_path: &str) -> bool {
+        key_path.is_empty() || key_path.split('.').all(is_identifier)
+    }
+
+    match *key_path {
+        KeyPath::String(ref path) => is_valid_string(path),
+        KeyPath::Sequence(ref paths) => {
+            !paths.is_empty() && paths.iter().all(|path| is_valid_string(path))
+        },
+    }
+}
+
+pub fn key_path_from_union(key_path: &StringOrStringSequence) -> KeyPath {
+    match *key_path {
+        StringOrStringSequence::String(ref path) => KeyPath::String(path.to_string()),
+        StringOrStringSequence::StringSequence(ref paths) => {
+            KeyPath::Sequence(paths.iter().map(|path| path.to_string()).collect())
+        },
+    }
+}
+
+#[allow(unsafe_code)]
+pub fn key_path_to_jsval(cx: SafeJSContext, key_path: &KeyPath, rval: MutableHandleValue) {
+    unsafe {
+        match *key_path {
+            KeyPath::String(ref path) => DOMString::from(path.clone()).to_jsval(*cx, rval),
+            KeyPath::Sequence(ref paths) => paths.to_jsval(*cx, rval),
+        }
+    }
+}
+
+/// https://w3c.github.io/IndexedDB/#evaluate-a-key-path-on-a-value
+/// for a key path that is a string.
+/// Returns whether the key path resolved to a value.
+#[allow(unsafe_code)]
+fn evaluate_key_path(
+    cx: SafeJSContext,
+    value: HandleValue,
+    key_path: &str,
+    mut rval: MutableHandleValue,
+) -> Fallible<bool> {
+    rval.set(value.get());
+    if key_path.is_empty() {
+        return Ok(true);
+    }
+
+    for identifier in key_path.split('.') {
+        unsafe {
+            if identifier == "length" {
+                if rval.is_string() {
+                    let length = JS_GetStringLength(rval.to_string());
+                    rval.set(DoubleValue(length as f64));
+                    continue;
+                }
+                let mut is_array = false;
+                if rval.is_object() && !JS_IsArrayObject(*cx, rval.handle(), &mut is_array) {
+                    return Err(Error::JSFailed);
+                }
+                if is_array {
+                    rooted!(in(*cx) let array = rval.to_object());
+                    let mut length = 0;
+                    if !JS_GetArrayLength(*cx, array.handle(), &mut length) {
+                        return Err(Error::JSFailed);
+                    }
+                    rval.set(DoubleValue(length as f64));
+                    continue;
+                }
+            }
+
+            if !rval.is_object() {
+                return Ok(false);
+            }
+            rooted!(in(*cx) let object = rval.to_object());
+            let name = CString::new(identifier).unwrap();
+            let mut found = false;
+            if !JS_HasOwnProperty(*cx, object.handle(), name.as_ptr(), &mut found) {
+                return Err(Error::JSFailed);
+            }
+            if !found {
+                return Ok(false);
+            }
+            if !JS_GetProperty(*cx, object.handle(), name.as_ptr(), rval) {
+                return Err(Error::JSFailed);
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// https://w3c.github.io/IndexedDB/#extract-a-key-from-a-value-using-a-key-path
+pub fn extract_key(
+    cx: SafeJSContext,
+    value: HandleValue,
+    key_path: &KeyPath,
+) -> Fallible<ExtractionResult> {
+    let paths = match *key_path {
+        KeyPath::String(ref path) => {
+            rooted!(in(*cx) let mut result = UndefinedValue());
+            if !evaluate_key_path(cx, value, path, result.handle_mut())? {
+                return Ok(ExtractionResult::Failure);
+            }
+            return Ok(match convert_value_to_key(cx, result.handle()) {
+                Ok(key) => ExtractionResult::Key(key),
+                Err(Error::Data) => ExtractionResult::Invalid,
+                Err(error) => return Err(error),
+            });
+        },
+        KeyPath::Sequence(ref paths) => paths,
+    };
+
+    let mut keys = Vec::with_capacity(paths.len());
+    for path in paths {
+        match extract_key(cx, value, &KeyPath::String(path.clone()))? {
+            ExtractionResult::Key(key) => keys.push(key),
+            result => return Ok(result),
+        }
+    }
+    Ok(ExtractionResult::Key(IndexedDBKeyType::Array(keys)))
+}
+
+/// The keys a value contributes to an index, following
+/// https://w3c.github.io/IndexedDB/#store-a-record-into-an-object-store
+/// A value whose key path doesn't resolve to a valid key has no index keys.
+pub fn extract_index_keys(
+    cx: SafeJSContext,
+    value: HandleValue,
+    key_path: &KeyPath,
+    multi_entry: bool,
+) -> Fallible<Vec<IndexedDBKeyType>> {
+    let path = match *key_path {
+        KeyPath::String(ref path) if multi_entry => path,
+        _ => {
+            return Ok(match extract_key(cx, value, key_path)? {
+                ExtractionResult::Key(key) => vec![key],
+                _ => vec![],
+            });
+        },
+    };
+
+    rooted!(in(*cx) let mut result = UndefinedValue());
+    if !evaluate_key_path(cx, value, path, result.handle_mut())? {
+        return Ok(vec![]);
+    }
+    match convert_value_to_multi_entry_keys(cx, result.handle()) {
+        Ok(keys) => Ok(keys),
+        Err(Error::Data) => Ok(vec![]),
+        Err(error) => Err(error),
+    }
+}
+
+/// https://w3c.github.io/IndexedDB/#check-that-a-key-could-be-injected-into-a-value
+#[allow(unsafe_code)]
+pub fn can_inject_key(cx: SafeJSContext, value: HandleValue, key_path: &str) -> Fallible<bool> {
+    let mut identifiers: Vec<&str> = key_path.split('.').collect();
+    identifiers.pop();
+
+    rooted!(in(*cx) let mut current = value.get());
+    for identifier in identifiers {
+        if !current.is_object() {
+            return Ok(false);
+        }
+        rooted!(in(*cx) let object = current.to_object());
+        let name = CString::new(identifier).unwrap();
+        let mut found = false;
+        unsafe {
+            if !JS_HasOwnProperty(*cx, object.handle(), name.as_ptr(), &mut found) {
+                return Err(Error::JSFailed);
+            }
+            if !found {
+                return Ok(true);
+            }
+            if !JS_GetProperty(*cx, object.handle(), name.as_ptr(), current.handle_mut()) {
+                return Err(Error::JSFailed);
+            }
+        }
+    }
+    Ok(current.is_object())
+}
+
+/// https://w3c.github.io/IndexedDB/#inject-a-key-into-a-value-using-a-key-path
+/// The key path must have been checked with `can_inject_key`.
+#[allow(unsafe_code)]
+pub fn inject_key(
+    cx: SafeJSContext,
+    value: HandleValue,
+    key: &IndexedDBKeyType,
+    key_path: &str,
+) -> Fallible<()> {
+    let mut identifiers: Vec<&str> = key_path.split('.').collect();
+    let last = identifiers.pop().unwrap();
+
+    rooted!(in(*cx) let mut current = value.get());
+    for identifier in identifiers {
+        rooted!(in(*cx) let object = current.to_object());
+        let name = CString::new(identifier).unwrap();
+        let mut found = false;
+        unsafe {
+            if !JS_HasOwnProperty(*cx, object.handle(), name.as_ptr(), &mut found) {
+                return Err(Error::JSFailed);
+            }
+            if found {
+                if !JS_GetProperty(*cx, object.handle(), name.as_ptr(), current.handle_mut()) {
+                    return Err(Error::JSFailed);
+                }
+                continue;
+            }
+            rooted!(in(*cx) let child = JS_NewPlainObject(*cx));
+            current.set(ObjectValue(child.get()));
+            if !JS_DefineProperty(
+                *cx,
+                object.handle(),
+                name.as_ptr(),
+                current.handle(),
+                JSPROP_ENUMERATE as u32,
+            ) {
+                return Err(Error::JSFailed);
+            }
+        }
+    }
+
+    rooted!(in(*cx) let object = current.to_object());
+    rooted!(in(*cx) let mut key_value = UndefinedValue());
+    key_type_to_jsval(cx, key, key_value.handle_mut());
+    let name = CString::new(last).unwrap();
+    unsafe {
+        if !JS_DefineProperty(
+            *cx,
+            object.handle(),
+            name.as_ptr(),
+            key_value.handle(),
+            JSPROP_ENUMERATE as u32,
+        ) {
+            return Err(Error::JSFailed);
+        }
+    }
+    Ok(())
+}
+
+/// Serializes a value to be stored in an object store.
+/// Blobs can not be stored yet, they would need to outlive the global that created them.
+pub fn serialize_value(cx: SafeJSContext, value: HandleValue) -> Fallible<Vec<u8>> {
+    let data = structuredclone::write(cx, value, None)?;
+    if data.blobs.is_some() {
+        return Err(Error::DataClone);
+    }
+    Ok(data.serialized)
+}
+
+/// Deserializes a stored value into the realm of `global`.
+#[allow(unsafe_code)]
+pub fn deserialize_value(
+    global: &GlobalScope,
+    serialized: Vec<u8>,
+    rval: MutableHandleValue,
+) -> Fallible<()> {
+    let data = StructuredSerializedData {
+        serialized,
+        ports: None,
+        blobs: None,
+    };
+    structuredclone::read(global, data, rval).map_err(|_| {
+        unsafe { JS_ClearPendingException(*global.get_cx()) };
+        Error::DataClone
+    })?;
+    Ok(())
+}
+
+/// Deserializes the value of a record.
+/// Records of a store with a key generator and an inline key path are stored
+/// without their generated key, which `injected_key_path` puts back.
+pub fn record_value_to_jsval(
+    global: &GlobalScope,
+    record: IndexedDBRecord,
+    injected_key_path: Option<&str>,
+    rval: MutableHandleValue,
+) -> Fallible<()> {
+    let cx = global.get_cx();
+    let serialized = record
+        .value
+        .expect("The value of the record was not requested");
+    deserialize_value(global, serialized, rval)?;
+    let key_path = match injected_key_path {
+        Some(key_path) => key_path,
+        None => return Ok(()),
+    };
+    let key_path_resolves = {
+        rooted!(in(*cx) let mut key = UndefinedValue());
+        evaluate_key_path(cx, rval.handle(), key_path, key.handle_mut())?
+    };
+    if !key_path_resolves && can_inject_key(cx, rval.handle(), key_path)? {
+        inject_key(cx, rval.handle(), &record.primary_key, key_path)?;
+    }
+    Ok(())
+}
+
+/// Deserializes the values of records into an array.
+#[allow(unsafe_code)]
+pub fn record_values_to_jsval(
+    global: &GlobalScope,
+    records: Vec<IndexedDBRecord>,
+    injected_key_path: Option<&str>,
+    mut rval: MutableHandleValue,
+) -> Fallible<()> {
+    let cx = global.get_cx();
+    rooted!(in(*cx) let array = unsafe { JS_NewArrayObject(*cx, &HandleValueArray::new()) });
+    for (index, record) in records.into_iter().enumerate() {
+        rooted!(in(*cx) let mut value = UndefinedValue());
+        record_value_to_jsval(global, record, injected_key_path, value.handle_mut())?;
+        let name = CString::new(index.to_string()).unwrap();
+        unsafe {
+            if !JS_DefineProperty(
+                *cx,
+                array.handle(),
+                name.as_ptr(),
+                value.handle(),
+                JSPROP_ENUMERATE as u32,
+            ) {
+                return Err(Error::JSFailed);
+            }
+        }
+    }
+    rval.set(ObjectValue(array.get()));
+    Ok(())
+}
@@ -113,7 +113,8 @@ pub fn fetch_image_for_layout(
     let request = FetchRequestInit::new(url)
         .origin(document.origin().immutable().clone())
         .destination(Destination::Image)
-        .pipeline_id(Some(document.global().pipeline_id()));
+        .pipeline_id(Some(document.global().pipeline_id()))
+        .top_level_origin(document.global().top_level_origin());
 
     // Layout image loads do not delay the document load event.
     document
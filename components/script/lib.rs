@@ -71,6 +71,8 @@ pub mod fetch;
 #[warn(deprecated)]
 mod image_listener;
 #[warn(deprecated)]
+mod indexed_db;
+#[warn(deprecated)]
 mod init;
 #[warn(deprecated)]
 mod layout_image;
@@ -92,6 +94,8 @@ pub mod script_thread;
 #[warn(deprecated)]
 pub mod serviceworker_manager;
 #[warn(deprecated)]
+mod streams;
+#[warn(deprecated)]
 mod stylesheet_loader;
 #[warn(deprecated)]
 mod stylesheet_set;
@@ -47,6 +47,7 @@ use crate::dom::bindings::utils::WRAP_CALLBACKS;
 use crate::dom::customelementregistry::{
     CallbackReaction, CustomElementDefinition, CustomElementReactionStack,
 };
+use crate::dom::debuggerglobalscope::DebuggerGlobalScope;
 use crate::dom::document::{
     Document, DocumentSource, FocusType, HasBrowsingContext, IsHTMLDocument, TouchEventResult,
 };
@@ -78,6 +79,7 @@ use crate::script_runtime::{get_reports, new_rt_and_cx, JSContext, Runtime, Scri
 use crate::script_runtime::{CommonScriptMsg, ScriptChan, ScriptThreadEventCategory};
 use crate::task_manager::TaskManager;
 use crate::task_queue::{QueuedTask, QueuedTaskConversion, TaskQueue};
+use crate::task_source::database_access::DatabaseAccessTaskSource;
 use crate::task_source::dom_manipulation::DOMManipulationTaskSource;
 use crate::task_source::file_reading::FileReadingTaskSource;
 use crate::task_source::history_traversal::HistoryTraversalTaskSource;
@@ -213,6 +215,8 @@ struct InProgressLoad {
     url: ServoUrl,
     /// The origin for the document
     origin: MutableOrigin,
+    /// The origin of the top-level document, if this is a load in a nested browsing context.
+    top_level_origin: Option<ImmutableOrigin>,
     /// Timestamp reporting the time when the browser started this load.
     navigation_start: u64,
     /// High res timestamp reporting the time when the browser started this load.
@@ -235,6 +239,7 @@ impl InProgressLoad {
         window_size: WindowSizeData,
         url: ServoUrl,
         origin: MutableOrigin,
+        top_level_origin: Option<ImmutableOrigin>,
         layout_is_busy: Arc<AtomicBool>,
     ) -> InProgressLoad {
         let current_time = get_time();
@@ -254,6 +259,7 @@ impl InProgressLoad {
             is_visible: true,
             url: url,
             origin: origin,
+            top_level_origin: top_level_origin,
             navigation_start: (current_time.sec * 1000 + current_time.nsec as i64 / 1000000) as u64,
             navigation_start_precise: navigation_start_precise,
             canceller: Default::default(),
@@ -600,6 +606,9 @@ pub struct ScriptThread {
     /// no such server exists.
     devtools_port: Receiver<DevtoolScriptControlMsg>,
     devtools_sender: IpcSender<DevtoolScriptControlMsg>,
+    /// The global hosting the JS debugger of this thread, created along with the
+    /// first window when a devtools server exists.
+    debugger_global: MutNullableDom<DebuggerGlobalScope>,
 
     /// The JavaScript runtime.
     js_runtime: Rc<Runtime>,
@@ -799,6 +808,7 @@ impl ScriptThreadFactory for ScriptThread {
                     window_size,
                     load_data.url.clone(),
                     origin,
+                    load_data.top_level_origin.clone(),
                     layout_is_busy,
                 );
                 script_thread.pre_page_load(new_load, load_data);
@@ -1339,6 +1349,7 @@ impl ScriptThread {
             devtools_chan: state.devtools_chan,
             devtools_port: devtools_port,
             devtools_sender: ipc_devtools_sender,
+            debugger_global: Default::default(),
 
             microtask_queue: runtime.microtask_queue.clone(),
 
@@ -2078,7 +2089,47 @@ impl ScriptThread {
                 devtools::handle_request_animation_frame(&*documents, id, name)
             },
             DevtoolScriptControlMsg::Reload(id) => devtools::handle_reload(&*documents, id),
+            DevtoolScriptControlMsg::Debugger(id, command) => match self.debugger_global.get() {
+                Some(debugger) => debugger.handle_command(id, command),
+                None => warn!(
+                    "Debugger command sent to pipeline {} without a debugger.",
+                    id
+                ),
+            },
+            DevtoolScriptControlMsg::GetStyleSheets(id, reply) => {
+                devtools::handle_get_stylesheets(&*documents, id, reply)
+            },
+            DevtoolScriptControlMsg::UpdateStyleSheet(id, index, text) => {
+                devtools::handle_update_stylesheet(&*documents, id, index, text)
+            },
+        }
+    }
+
+    /// Handle a devtools message outside of the event loop, while the JS debugger
+    /// has paused a global of this thread.
+    pub fn handle_devtools_message(msg: DevtoolScriptControlMsg) {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            if let Some(script_thread) = root.get() {
+                let script_thread = unsafe { &*script_thread };
+                script_thread.handle_msg_from_devtools(msg);
+            }
+        });
+    }
+
+    /// Make a new global of this thread visible to the JS debugger.
+    fn add_debuggee(&self, global: &GlobalScope) {
+        if self.devtools_chan.is_none() {
+            return;
         }
+        let debugger = match self.debugger_global.get() {
+            Some(debugger) => debugger,
+            None => {
+                let debugger = DebuggerGlobalScope::new(global, self.devtools_port.clone());
+                self.debugger_global.set(Some(&debugger));
+                debugger
+            },
+        };
+        debugger.add_debuggee(global);
     }
 
     fn handle_msg_from_image_cache(&self, (id, response): (PipelineId, PendingImageResponse)) {
@@ -2110,6 +2161,9 @@ impl ScriptThread {
             WebDriverScriptCommand::AddCookie(params, reply) => {
                 webdriver_handlers::handle_add_cookie(&*documents, pipeline_id, params, reply)
             },
+            WebDriverScriptCommand::DeleteCookie(name, reply) => {
+                webdriver_handlers::handle_delete_cookie(&*documents, pipeline_id, name, reply)
+            },
             WebDriverScriptCommand::DeleteCookies(reply) => {
                 webdriver_handlers::handle_delete_cookies(&*documents, pipeline_id, reply)
             },
@@ -2241,6 +2295,14 @@ impl ScriptThread {
                     reply,
                 )
             },
+            WebDriverScriptCommand::ElementClear(element_id, reply) => {
+                webdriver_handlers::handle_element_clear(
+                    &*documents,
+                    pipeline_id,
+                    element_id,
+                    reply,
+                )
+            },
             WebDriverScriptCommand::GetActiveElement(reply) => {
                 webdriver_handlers::handle_get_active_element(&*documents, pipeline_id, reply)
             },
@@ -2319,6 +2381,9 @@ impl ScriptThread {
             WebDriverScriptCommand::GetTitle(reply) => {
                 webdriver_handlers::handle_get_title(&*documents, pipeline_id, reply)
             },
+            WebDriverScriptCommand::Print(settings, reply) => {
+                webdriver_handlers::handle_print(&*documents, pipeline_id, settings, reply)
+            },
             _ => (),
         }
     }
@@ -2457,6 +2522,7 @@ impl ScriptThread {
             window_size,
             load_data.url.clone(),
             origin,
+            load_data.top_level_origin.clone(),
             layout_is_busy.clone(),
         );
         if load_data.url.as_str() == "about:blank" {
@@ -2800,6 +2866,13 @@ impl ScriptThread {
         WebsocketTaskSource(self.remote_event_task_sender.clone(), pipeline_id)
     }
 
+    pub fn database_access_task_source(
+        &self,
+        pipeline_id: PipelineId,
+    ) -> DatabaseAccessTaskSource {
+        DatabaseAccessTaskSource(self.remote_event_task_sender.clone(), pipeline_id)
+    }
+
     /// Handles a request for the window title.
     fn handle_get_title_msg(&self, pipeline_id: PipelineId) {
         let document = match self.documents.borrow().find_document(pipeline_id) {
@@ -2877,6 +2950,9 @@ impl ScriptThread {
             if discard_bc == DiscardBrowsingContext::Yes {
                 window.discard_browsing_context();
             }
+            if let Some(debugger) = self.debugger_global.get() {
+                debugger.remove_debuggee(id);
+            }
             window.clear_js_runtime();
         }
 
@@ -3262,6 +3338,7 @@ impl ScriptThread {
         };
 
         let task_manager = TaskManager::new(
+            self.database_access_task_source(incomplete.pipeline_id),
             self.dom_manipulation_task_source(incomplete.pipeline_id),
             self.file_reading_task_source(incomplete.pipeline_id),
             self.history_traversal_task_source(incomplete.pipeline_id),
@@ -3332,6 +3409,15 @@ impl ScriptThread {
         }
         window.init_window_proxy(&window_proxy);
 
+        // A top-level document is its own top-level origin.
+        let top_level_origin = incomplete
+            .top_level_origin
+            .clone()
+            .unwrap_or_else(|| origin.immutable().clone());
+        window
+            .upcast::<GlobalScope>()
+            .set_top_level_origin(Some(top_level_origin));
+
         let last_modified = metadata.headers.as_ref().and_then(|headers| {
             headers
                 .typed_get::<LastModified>()
@@ -3423,6 +3509,7 @@ impl ScriptThread {
             final_url.clone(),
             (incomplete.browsing_context_id, incomplete.pipeline_id, None),
         );
+        self.add_debuggee(window.upcast());
 
         let parse_input = DOMString::new();
 
@@ -3819,7 +3906,8 @@ impl ScriptThread {
             .headers(load_data.headers)
             .body(load_data.data)
             .redirect_mode(RedirectMode::Manual)
-            .origin(incomplete.origin.immutable().clone());
+            .origin(incomplete.origin.immutable().clone())
+            .top_level_origin(load_data.top_level_origin.clone());
 
         let context = ParserContext::new(id, load_data.url);
         self.incomplete_parser_contexts
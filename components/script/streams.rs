@@ -0,0 +1,320 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Machinery shared by readable, writable and transform streams.
+//!
+//! The underlying source, sink or transformer given to a stream constructor
+//! is a plain JS object, its methods are looked up once when the stream is
+//! set up and later called with the object as `this`.
+
+use crate::dom::bindings::callback::ExceptionHandling;
+use crate::dom::bindings::codegen::Bindings::QueuingStrategyBinding::{
+    QueuingStrategy, QueuingStrategySize,
+};
+use crate::dom::bindings::codegen::Bindings::ReadableStreamDefaultReaderBinding::ReadableStreamReadResult;
+use crate::dom::bindings::conversions::{get_property, get_property_jsval};
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::realms::InRealm;
+use crate::script_runtime::JSContext as SafeJSContext;
+use js::conversions::ToJSValConvertible;
+use js::jsapi::{ExceptionStackBehavior, HandleValueArray, Heap, IsCallable, JSContext, JSObject};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::wrappers::{Call, JS_SetPendingException};
+use js::rust::{HandleObject, HandleValue, MutableHandleValue};
+use std::collections::VecDeque;
+use std::ptr;
+use std::rc::Rc;
+
+/// A method of the underlying source, sink or transformer of a stream.
+#[derive(Default, JSTraceable, MallocSizeOf)]
+pub struct UnderlyingMethod {
+    #[ignore_malloc_size_of = "mozjs"]
+    object: Heap<*mut JSObject>,
+    #[ignore_malloc_size_of = "mozjs"]
+    method: Heap<JSVal>,
+}
+
+impl UnderlyingMethod {
+    /// Looks up the method `name` of `object`, an absent method does nothing
+    /// when called.
+    pub fn init(&self, cx: SafeJSContext, object: HandleObject, name: &str) -> Fallible<()> {
+        rooted!(in(*cx) let mut method = UndefinedValue());
+        get_method(cx, object, name, method.handle_mut())?;
+        self.object.set(object.get());
+        self.method.set(method.get());
+        Ok(())
+    }
+
+    pub fn is_undefined(&self) -> bool {
+        self.method.get().is_undefined()
+    }
+
+    /// Forgets the method and its object, once the stream no longer needs them.
+    pub fn clear(&self) {
+        self.object.set(ptr::null_mut());
+        self.method.set(UndefinedValue());
+    }
+
+    /// Calls the method, an absent method returns undefined.
+    pub fn call(
+        &self,
+        cx: SafeJSContext,
+        args: &[JSVal],
+        rval: MutableHandleValue,
+    ) -> Fallible<()> {
+        rooted!(in(*cx) let object = self.object.get());
+        rooted!(in(*cx) let method = self.method.get());
+        call_method(cx, object.handle(), method.handle(), args, rval)
+    }
+
+    /// Calls the method, converting an exception to a rejected promise and
+    /// any other result to a promise resolved with it.
+    pub fn promise_call(&self, global: &GlobalScope, args: &[JSVal]) -> Rc<Promise> {
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut result = UndefinedValue());
+        match self.call(cx, args, result.handle_mut()) {
+            Ok(()) => new_resolved_promise(global, result.handle()),
+            Err(error) => new_rejected_promise_with_error(global, error),
+        }
+    }
+}
+
+/// <https://tc39.es/ecma262/#sec-getmethod>, returning undefined when
+/// `object` is null.
+#[allow(unsafe_code)]
+pub fn get_method(
+    cx: SafeJSContext,
+    object: HandleObject,
+    name: &str,
+    mut rval: MutableHandleValue,
+) -> Fallible<()> {
+    rval.set(UndefinedValue());
+    if object.is_null() {
+        return Ok(());
+    }
+    unsafe { get_property_jsval(*cx, object, name, rval.reborrow())? };
+    if rval.is_null() {
+        rval.set(UndefinedValue());
+    }
+    if !rval.is_undefined() && (!rval.is_object() || unsafe { !IsCallable(rval.to_object()) }) {
+        return Err(Error::Type(format!("{} is not a function", name)));
+    }
+    Ok(())
+}
+
+/// Calls `method` with `object` as `this`, an undefined method returns undefined.
+/// The values of `args` must be rooted by the caller.
+#[allow(unsafe_code)]
+pub fn call_method(
+    cx: SafeJSContext,
+    object: HandleObject,
+    method: HandleValue,
+    args: &[JSVal],
+    mut rval: MutableHandleValue,
+) -> Fallible<()> {
+    rval.set(UndefinedValue());
+    if method.is_undefined() {
+        return Ok(());
+    }
+    rooted!(in(*cx) let this = ObjectValue(object.get()));
+    let args = unsafe { HandleValueArray::from_rooted_slice(args) };
+    if unsafe { !Call(*cx, this.handle(), method, &args, rval) } {
+        return Err(Error::JSFailed);
+    }
+    Ok(())
+}
+
+/// A value queued by a stream controller, along with its size.
+#[derive(JSTraceable, MallocSizeOf)]
+struct ValueWithSize {
+    #[ignore_malloc_size_of = "mozjs"]
+    value: Box<Heap<JSVal>>,
+    size: f64,
+}
+
+/// <https://streams.spec.whatwg.org/#queue-with-sizes>
+#[derive(Default, JSTraceable, MallocSizeOf)]
+pub struct QueueWithSizes {
+    queue: VecDeque<ValueWithSize>,
+    total_size: f64,
+}
+
+impl QueueWithSizes {
+    /// <https://streams.spec.whatwg.org/#enqueue-value-with-size>
+    pub fn enqueue(&mut self, value: HandleValue, size: f64) -> Fallible<()> {
+        if !size.is_finite() || size < 0. {
+            return Err(Error::Range(
+                "The size of a chunk must be a finite, non-negative number".to_owned(),
+            ));
+        }
+        self.queue.push_back(ValueWithSize {
+            value: Heap::boxed(value.get()),
+            size,
+        });
+        self.total_size += size;
+        Ok(())
+    }
+
+    /// <https://streams.spec.whatwg.org/#dequeue-value>
+    pub fn dequeue(&mut self, mut rval: MutableHandleValue) {
+        let pair = self
+            .queue
+            .pop_front()
+            .expect("Dequeuing from an empty queue");
+        // Rounding errors can make the total size slightly negative.
+        self.total_size = (self.total_size - pair.size).max(0.);
+        rval.set(pair.value.get());
+    }
+
+    /// <https://streams.spec.whatwg.org/#peek-queue-value>
+    pub fn peek(&self, mut rval: MutableHandleValue) {
+        let pair = self.queue.front().expect("Peeking into an empty queue");
+        rval.set(pair.value.get());
+    }
+
+    /// <https://streams.spec.whatwg.org/#reset-queue>
+    pub fn reset(&mut self) {
+        self.queue.clear();
+        self.total_size = 0.;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn total_size(&self) -> f64 {
+        self.total_size
+    }
+}
+
+/// <https://streams.spec.whatwg.org/#validate-and-normalize-high-water-mark>
+pub fn extract_high_water_mark(strategy: &QueuingStrategy, default: f64) -> Fallible<f64> {
+    match strategy.highWaterMark {
+        None => Ok(default),
+        Some(high_water_mark) if high_water_mark.is_nan() || high_water_mark < 0. => Err(
+            Error::Range("The high water mark must be a non-negative number".to_owned()),
+        ),
+        Some(high_water_mark) => Ok(high_water_mark),
+    }
+}
+
+/// <https://streams.spec.whatwg.org/#make-size-algorithm-from-size-function>
+pub fn chunk_size(size: Option<&Rc<QueuingStrategySize>>, chunk: HandleValue) -> Fallible<f64> {
+    match size {
+        Some(size) => size.Call__(chunk, ExceptionHandling::Rethrow),
+        None => Ok(1.),
+    }
+}
+
+/// Returns a promise resolved with `value`, adopting its state if it is a
+/// promise or a thenable.
+pub fn new_resolved_promise(global: &GlobalScope, value: HandleValue) -> Rc<Promise> {
+    Promise::new_resolved(global, global.get_cx(), value).expect("Failed to resolve a promise")
+}
+
+/// Returns a promise rejected with `reason`.
+pub fn new_rejected_promise(global: &GlobalScope, reason: HandleValue) -> Rc<Promise> {
+    Promise::new_rejected(global, global.get_cx(), reason).expect("Failed to reject a promise")
+}
+
+/// Returns a promise rejected with `error`.
+pub fn new_rejected_promise_with_error(global: &GlobalScope, error: Error) -> Rc<Promise> {
+    let promise = Promise::new(global);
+    promise.reject_error(error);
+    promise
+}
+
+/// Converts `error` to the JS value streams are errored with, taking the
+/// pending exception in the case of `Error::JSFailed`.
+#[allow(unsafe_code)]
+pub fn error_to_jsval(global: &GlobalScope, error: Error, rval: MutableHandleValue) {
+    unsafe { error.to_jsval(*global.get_cx(), global, rval) };
+}
+
+/// Throws `value` back to the caller of a stream method.
+#[allow(unsafe_code)]
+pub fn rethrow(cx: SafeJSContext, value: HandleValue) -> Error {
+    unsafe { JS_SetPendingException(*cx, value, ExceptionStackBehavior::Capture) };
+    Error::JSFailed
+}
+
+/// <https://webidl.spec.whatwg.org/#mark-a-promise-as-handled>
+pub fn mark_as_handled(global: &GlobalScope, promise: &Promise) {
+    promise.append_native_handler(&PromiseNativeHandler::new(global, None, None));
+}
+
+/// Returns whether `promise` is neither fulfilled nor rejected.
+pub fn is_pending(promise: &Promise) -> bool {
+    !promise.is_fulfilled()
+}
+
+/// Settles a promise once the promise it waits upon settles: resolving it
+/// with undefined, or rejecting it with the same reason.
+#[derive(JSTraceable, MallocSizeOf)]
+struct SettleHandler {
+    #[ignore_malloc_size_of = "Rc"]
+    promise: Rc<Promise>,
+    fulfilled: bool,
+}
+
+impl Callback for SettleHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue, _realm: InRealm) {
+        let cx = unsafe { SafeJSContext::from_ptr(cx) };
+        if self.fulfilled {
+            rooted!(in(*cx) let undefined = UndefinedValue());
+            self.promise.resolve(cx, undefined.handle());
+        } else {
+            self.promise.reject(cx, v);
+        }
+    }
+}
+
+/// Returns the result of transforming `promise` with a fulfillment handler
+/// that returns undefined.
+pub fn transform_to_undefined(global: &GlobalScope, promise: &Promise) -> Rc<Promise> {
+    let result = Promise::new(global);
+    let handler = PromiseNativeHandler::new(
+        global,
+        Some(Box::new(SettleHandler {
+            promise: result.clone(),
+            fulfilled: true,
+        })),
+        Some(Box::new(SettleHandler {
+            promise: result.clone(),
+            fulfilled: false,
+        })),
+    );
+    promise.append_native_handler(&handler);
+    result
+}
+
+/// Creates the `{ value, done }` object reads are fulfilled with.
+#[allow(unsafe_code)]
+pub fn read_result(cx: SafeJSContext, value: HandleValue, done: bool, rval: MutableHandleValue) {
+    let mut result = ReadableStreamReadResult::empty();
+    result.value.set(value.get());
+    result.done = Some(done);
+    unsafe { result.to_jsval(*cx, rval) };
+}
+
+/// Takes a `{ value, done }` object apart, returning `done`.
+#[allow(unsafe_code)]
+pub fn unpack_read_result(
+    cx: SafeJSContext,
+    result: HandleValue,
+    value: MutableHandleValue,
+) -> Fallible<bool> {
+    if !result.is_object() {
+        return Err(Error::Type("The read result is not an object".to_owned()));
+    }
+    rooted!(in(*cx) let object = result.to_object());
+    unsafe {
+        get_property_jsval(*cx, object.handle(), "value", value)?;
+        Ok(get_property::<bool>(*cx, object.handle(), "done", ())?.unwrap_or(false))
+    }
+}
@@ -39,10 +39,10 @@ use std::sync::Mutex;
 use style::media_queries::MediaList;
 use style::parser::ParserContext;
 use style::shared_lock::{Locked, SharedRwLock};
-use style::stylesheets::import_rule::ImportSheet;
+use style::stylesheets::import_rule::{ImportLayer, ImportSheet};
 use style::stylesheets::StylesheetLoader as StyleStylesheetLoader;
 use style::stylesheets::{
-    CssRules, ImportRule, Namespaces, Origin, Stylesheet, StylesheetContents,
+    AllowImportRules, CssRules, ImportRule, Namespaces, Origin, Stylesheet, StylesheetContents,
 };
 use style::values::CssUrl;
 
@@ -157,24 +157,31 @@ impl FetchResponseListener for StylesheetContext {
                         .map_or(true, |gen| gen == link.get_request_generation_id());
                     if is_stylesheet_load_applicable {
                         let shared_lock = document.style_shared_lock().clone();
-                        let sheet = Arc::new(Stylesheet::from_bytes(
+                        let media = Arc::new(shared_lock.wrap(media.take().unwrap()));
+                        // The source is kept for the devtools, which show it as written.
+                        let css = Stylesheet::decode_bytes(
                             &data,
-                            final_url,
                             protocol_encoding_label,
                             Some(environment_encoding),
+                        );
+                        let sheet = Arc::new(Stylesheet::from_str(
+                            &css,
+                            final_url,
                             Origin::Author,
-                            media.take().unwrap(),
+                            media,
                             shared_lock,
                             Some(&loader),
                             win.css_error_reporter(),
                             document.quirks_mode(),
+                            0,
+                            AllowImportRules::Yes,
                         ));
 
                         if link.is_alternate() {
                             sheet.set_disabled(true);
                         }
 
-                        link.set_stylesheet(sheet);
+                        link.set_stylesheet(sheet, css.into_owned());
                     }
                 },
                 StylesheetContextSource::Import(ref stylesheet) => {
@@ -362,6 +369,7 @@ impl<'a> StyleStylesheetLoader for StylesheetLoader<'a> {
         context: &ParserContext,
         lock: &SharedRwLock,
         media: Arc<Locked<MediaList>>,
+        layer: Option<ImportLayer>,
     ) -> Arc<Locked<ImportRule>> {
         let sheet = Arc::new(Stylesheet {
             contents: StylesheetContents {
@@ -383,6 +391,7 @@ impl<'a> StyleStylesheetLoader for StylesheetLoader<'a> {
             url,
             source_location,
             stylesheet,
+            layer,
         };
 
         let url = match import.url.url().cloned() {
@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+pub mod database_access;
 pub mod dom_manipulation;
 pub mod file_reading;
 pub mod history_traversal;
@@ -25,6 +26,7 @@ use std::result::Result;
 // because it doesn't implement TaskSource.
 #[derive(Clone, Eq, Hash, IntoEnumIterator, JSTraceable, PartialEq)]
 pub enum TaskSourceName {
+    DatabaseAccess,
     DOMManipulation,
     FileReading,
     HistoryTraversal,
@@ -6,10 +6,12 @@ use crate::dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding::CSSStyl
 use crate::dom::bindings::codegen::Bindings::DOMRectBinding::DOMRectMethods;
 use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use crate::dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
+use crate::dom::bindings::codegen::Bindings::FileListBinding::FileListMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLElementBinding::HTMLElementMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLInputElementBinding::HTMLInputElementMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLOptionElementBinding::HTMLOptionElementMethods;
 use crate::dom::bindings::codegen::Bindings::HTMLSelectElementBinding::HTMLSelectElementMethods;
+use crate::dom::bindings::codegen::Bindings::HTMLTextAreaElementBinding::HTMLTextAreaElementMethods;
 use crate::dom::bindings::codegen::Bindings::NodeBinding::{GetRootNodeOptions, NodeMethods};
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::codegen::Bindings::XMLSerializerBinding::XMLSerializerMethods;
@@ -33,9 +35,10 @@ use crate::dom::htmliframeelement::HTMLIFrameElement;
 use crate::dom::htmlinputelement::{HTMLInputElement, InputType};
 use crate::dom::htmloptionelement::HTMLOptionElement;
 use crate::dom::htmlselectelement::HTMLSelectElement;
+use crate::dom::htmltextareaelement::HTMLTextAreaElement;
 use crate::dom::node::{window_from_node, Node, ShadowIncluding};
 use crate::dom::nodelist::NodeList;
-use crate::dom::window::Window;
+use crate::dom::window::{ReflowReason, Window};
 use crate::dom::xmlserializer::XMLSerializer;
 use crate::realms::enter_realm;
 use crate::script_runtime::JSContext as SafeJSContext;
@@ -51,12 +54,16 @@ use js::rust::{Handle, HandleObject, HandleValue};
 use msg::constellation_msg::BrowsingContextId;
 use msg::constellation_msg::PipelineId;
 use net_traits::CookieSource::{NonHTTP, HTTP};
-use net_traits::CoreResourceMsg::{DeleteCookies, GetCookiesDataForUrl, SetCookieForUrl};
+use net_traits::CoreResourceMsg::{
+    DeleteCookie, DeleteCookies, GetCookiesDataForUrl, SetCookieForUrl,
+};
 use net_traits::IpcSend;
+use script_layout_interface::message::{Msg, ReflowGoal};
 use script_traits::webdriver_msg::WebDriverCookieError;
 use script_traits::webdriver_msg::{
     WebDriverFrameId, WebDriverJSError, WebDriverJSResult, WebDriverJSValue,
 };
+use script_traits::{PrintError, PrintSettings};
 use servo_url::ServoUrl;
 use std::cmp;
 use std::collections::HashMap;
@@ -744,12 +751,14 @@ pub fn handle_get_cookies(
             match documents.find_document(pipeline) {
                 Some(document) => {
                     let url = document.url();
+                    let global = document.window().upcast::<GlobalScope>();
                     let (sender, receiver) = ipc::channel().unwrap();
-                    let _ = document
-                        .window()
-                        .upcast::<GlobalScope>()
-                        .resource_threads()
-                        .send(GetCookiesDataForUrl(url, sender, NonHTTP));
+                    let _ = global.resource_threads().send(GetCookiesDataForUrl(
+                        url,
+                        global.top_level_origin(),
+                        sender,
+                        NonHTTP,
+                    ));
                     receiver.recv().unwrap()
                 },
                 None => Vec::new(),
@@ -771,12 +780,14 @@ pub fn handle_get_cookie(
             match documents.find_document(pipeline) {
                 Some(document) => {
                     let url = document.url();
+                    let global = document.window().upcast::<GlobalScope>();
                     let (sender, receiver) = ipc::channel().unwrap();
-                    let _ = document
-                        .window()
-                        .upcast::<GlobalScope>()
-                        .resource_threads()
-                        .send(GetCookiesDataForUrl(url, sender, NonHTTP));
+                    let _ = global.resource_threads().send(GetCookiesDataForUrl(
+                        url,
+                        global.top_level_origin(),
+                        sender,
+                        NonHTTP,
+                    ));
                     let cookies = receiver.recv().unwrap();
                     cookies
                         .into_iter()
@@ -817,19 +828,23 @@ pub fn handle_add_cookie(
         .send(match (document.is_cookie_averse(), domain) {
             (true, _) => Err(WebDriverCookieError::InvalidDomain),
             (false, Some(ref domain)) if url.host_str().map(|x| x == domain).unwrap_or(false) => {
-                let _ = document
-                    .window()
-                    .upcast::<GlobalScope>()
-                    .resource_threads()
-                    .send(SetCookieForUrl(url, Serde(cookie), method));
+                let global = document.window().upcast::<GlobalScope>();
+                let _ = global.resource_threads().send(SetCookieForUrl(
+                    url,
+                    global.top_level_origin(),
+                    Serde(cookie),
+                    method,
+                ));
                 Ok(())
             },
             (false, None) => {
-                let _ = document
-                    .window()
-                    .upcast::<GlobalScope>()
-                    .resource_threads()
-                    .send(SetCookieForUrl(url, Serde(cookie), method));
+                let global = document.window().upcast::<GlobalScope>();
+                let _ = global.resource_threads().send(SetCookieForUrl(
+                    url,
+                    global.top_level_origin(),
+                    Serde(cookie),
+                    method,
+                ));
                 Ok(())
             },
             (_, _) => Err(WebDriverCookieError::UnableToSetCookie),
@@ -858,6 +873,29 @@ pub fn handle_delete_cookies(
     reply.send(Ok(())).unwrap();
 }
 
+// https://w3c.github.io/webdriver/#delete-cookie
+pub fn handle_delete_cookie(
+    documents: &Documents,
+    pipeline: PipelineId,
+    name: String,
+    reply: IpcSender<Result<(), ErrorStatus>>,
+) {
+    let document = match documents.find_document(pipeline) {
+        Some(document) => document,
+        None => {
+            return reply.send(Err(ErrorStatus::UnknownError)).unwrap();
+        },
+    };
+    let url = document.url();
+    document
+        .window()
+        .upcast::<GlobalScope>()
+        .resource_threads()
+        .send(DeleteCookie(url, name))
+        .unwrap();
+    reply.send(Ok(())).unwrap();
+}
+
 pub fn handle_get_title(documents: &Documents, pipeline: PipelineId, reply: IpcSender<String>) {
     reply
         .send(
@@ -870,6 +908,34 @@ pub fn handle_get_title(documents: &Documents, pipeline: PipelineId, reply: IpcS
         .unwrap();
 }
 
+pub fn handle_print(
+    documents: &Documents,
+    pipeline: PipelineId,
+    settings: PrintSettings,
+    reply: IpcSender<Result<Vec<u8>, ErrorStatus>>,
+) {
+    reply
+        .send(match documents.find_window(pipeline) {
+            Some(window) => {
+                // Make sure that layout has an up-to-date display list to print.
+                window.reflow(ReflowGoal::Full, ReflowReason::Print);
+                let (sender, receiver) = ipc::channel().unwrap();
+                window
+                    .layout_chan()
+                    .send(Msg::Print(settings, sender))
+                    .unwrap();
+                match receiver.recv() {
+                    Ok(Ok(pdf)) => Ok(pdf),
+                    Ok(Err(PrintError::NoPagesInRange)) => Err(ErrorStatus::InvalidArgument),
+                    Ok(Err(PrintError::Unsupported)) => Err(ErrorStatus::UnsupportedOperation),
+                    Err(_) => Err(ErrorStatus::UnknownError),
+                }
+            },
+            None => Err(ErrorStatus::NoSuchWindow),
+        })
+        .unwrap();
+}
+
 pub fn handle_get_rect(
     documents: &Documents,
     pipeline: PipelineId,
@@ -1198,3 +1264,87 @@ pub fn handle_is_selected(
         )
         .unwrap();
 }
+
+// https://w3c.github.io/webdriver/#element-clear
+pub fn handle_element_clear(
+    documents: &Documents,
+    pipeline: PipelineId,
+    element_id: String,
+    reply: IpcSender<Result<(), ErrorStatus>>,
+) {
+    reply
+        .send(
+            // Step 3
+            find_node_by_unique_id(documents, pipeline, element_id).and_then(|node| {
+                // Step 4
+                // TODO: clear content editable elements once they are supported.
+                if !is_mutable_form_control(&node) {
+                    return Err(ErrorStatus::InvalidElementState);
+                }
+
+                // Steps 5 - 8
+                // TODO: scroll into view and wait for the element to be interactable
+
+                // Step 9.1
+                let element = node.downcast::<Element>().unwrap();
+                let read_only = match node.downcast::<HTMLInputElement>() {
+                    Some(input_element) => input_element.ReadOnly(),
+                    None => node.downcast::<HTMLTextAreaElement>().unwrap().ReadOnly(),
+                };
+                if element.disabled_state() || read_only {
+                    return Err(ErrorStatus::InvalidElementState);
+                }
+
+                // Step 9.2
+                // https://w3c.github.io/webdriver/#dfn-clear-a-resettable-element
+                let html_element = node.downcast::<HTMLElement>().unwrap();
+                if let Some(input_element) = node.downcast::<HTMLInputElement>() {
+                    if input_element.Value().is_empty() &&
+                        input_element
+                            .GetFiles()
+                            .map_or(true, |files| files.Length() == 0)
+                    {
+                        return Ok(());
+                    }
+                    html_element.Focus();
+                    input_element.clear();
+                } else {
+                    let textarea_element = node.downcast::<HTMLTextAreaElement>().unwrap();
+                    if textarea_element.Value().is_empty() {
+                        return Ok(());
+                    }
+                    html_element.Focus();
+                    textarea_element.clear();
+                }
+                html_element.Blur();
+                Ok(())
+            }),
+        )
+        .unwrap();
+}
+
+// https://w3c.github.io/webdriver/#dfn-mutable-form-control-element
+fn is_mutable_form_control(node: &Node) -> bool {
+    if let Some(input_element) = node.downcast::<HTMLInputElement>() {
+        match input_element.input_type() {
+            InputType::Text |
+            InputType::Search |
+            InputType::Url |
+            InputType::Tel |
+            InputType::Email |
+            InputType::Password |
+            InputType::Date |
+            InputType::Month |
+            InputType::Week |
+            InputType::Time |
+            InputType::DatetimeLocal |
+            InputType::Number |
+            InputType::Range |
+            InputType::Color |
+            InputType::File => true,
+            _ => false,
+        }
+    } else {
+        node.is::<HTMLTextAreaElement>()
+    }
+}
@@ -15,11 +15,11 @@ use msg::constellation_msg::{BackgroundHangMonitorRegister, BrowsingContextId, P
 use net_traits::image_cache::ImageCache;
 use parking_lot::RwLock;
 use profile_traits::mem::ReportsChan;
-use script_traits::Painter;
 use script_traits::{
     ConstellationControlMsg, LayoutControlMsg, LayoutMsg as ConstellationMsg, ScrollState,
     WindowSizeData,
 };
+use script_traits::{Painter, PrintError, PrintSettings};
 use servo_arc::Arc as ServoArc;
 use servo_atoms::Atom;
 use servo_url::{ImmutableOrigin, ServoUrl};
@@ -30,6 +30,7 @@ use style::context::QuirksMode;
 use style::dom::OpaqueNode;
 use style::invalidation::element::restyle_hints::RestyleHint;
 use style::properties::PropertyId;
+use style::properties_and_values::registry::PropertyRegistration;
 use style::selector_parser::{PseudoElement, RestyleDamage, Snapshot};
 use style::stylesheets::Stylesheet;
 
@@ -90,8 +91,15 @@ pub enum Msg {
     /// Tells layout that script has added some paint worklet modules.
     RegisterPaint(Atom, Vec<Atom>, Box<dyn Painter>),
 
+    /// Tells layout that script has registered a custom property with
+    /// `CSS.registerProperty()`.
+    RegisterProperty(PropertyRegistration),
+
     /// Send to layout the precise time when the navigation started.
     SetNavigationStart(u64),
+
+    /// Renders the current display list to a PDF document, paginated with the given settings.
+    Print(PrintSettings, IpcSender<Result<Vec<u8>, PrintError>>),
 }
 
 #[derive(Debug, PartialEq)]
@@ -185,6 +193,8 @@ pub struct Reflow {
 pub struct ReflowComplete {
     /// The list of images that were encountered that are in progress.
     pub pending_images: Vec<PendingImage>,
+    /// The number of layout boxes that were built rather than reused, if a box tree was built.
+    pub rebuilt_box_count: Option<usize>,
 }
 
 /// Information needed for a script-initiated reflow.
@@ -459,6 +459,17 @@ pub trait ThreadSafeLayoutElement<'dom>:
         }
     }
 
+    /// Returns the style of the `::marker` pseudo-element of this element,
+    /// if it is a list item.
+    #[inline]
+    fn marker_style(&self) -> Option<Arc<ComputedValues>> {
+        self.style_data()
+            .styles
+            .pseudos
+            .get(&PseudoElement::Marker)
+            .cloned()
+    }
+
     #[inline]
     fn selected_style(&self) -> Arc<ComputedValues> {
         let data = self.style_data();
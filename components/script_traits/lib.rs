@@ -25,13 +25,15 @@ pub mod webdriver_msg;
 
 use crate::serializable::{BlobData, BlobImpl};
 use crate::transferable::MessagePortImpl;
+use crate::webdriver_msg::WebDriverWindowState;
 use crate::webdriver_msg::{LoadStatus, WebDriverScriptCommand};
+use crate::webdriver_msg::{WebDriverUserPromptAction, WebDriverUserPromptError};
 use bluetooth_traits::BluetoothRequest;
 use canvas_traits::webgl::WebGLPipeline;
 use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
 use devtools_traits::{DevtoolScriptControlMsg, ScriptToDevtoolsControlMsg, WorkerId};
 use embedder_traits::EventLoopWaker;
-use euclid::{default::Point2D, Length, Rect, Scale, Size2D, UnknownUnit, Vector2D};
+use euclid::{default::Point2D, Length, Rect, Scale, SideOffsets2D, Size2D, UnknownUnit, Vector2D};
 use gfx_traits::Epoch;
 use http::HeaderMap;
 use hyper::Method;
@@ -182,6 +184,8 @@ pub struct LoadData {
 
     /// The source to use instead of a network response for a srcdoc document.
     pub srcdoc: String,
+    /// The origin of the top-level document, if this is a load in a nested browsing context.
+    pub top_level_origin: Option<ImmutableOrigin>,
 }
 
 /// The result of evaluating a javascript scheme url.
@@ -214,6 +218,7 @@ impl LoadData {
             referrer: referrer,
             referrer_policy: referrer_policy,
             srcdoc: "".to_string(),
+            top_level_origin: None,
         }
     }
 }
@@ -808,6 +813,33 @@ pub enum WindowSizeType {
     Resize,
 }
 
+/// The page layout to use when printing a document to paged media.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PrintSettings {
+    /// The size of each page, including its margins.
+    pub page_size: Size2D<f32, CSSPixel>,
+    /// The margins of each page.
+    pub margins: SideOffsets2D<f32, CSSPixel>,
+    /// The factor by which the document is scaled onto the pages.
+    pub scale: f32,
+    /// Whether the document is scaled down further when it is wider than a page.
+    pub shrink_to_fit: bool,
+    /// Whether backgrounds are printed.
+    pub background: bool,
+    /// The inclusive, one-based ranges of pages to print. All pages are
+    /// printed if this is empty.
+    pub page_ranges: Vec<(u32, u32)>,
+}
+
+/// The reasons why a document could not be printed.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum PrintError {
+    /// None of the requested page ranges contain a page of the document.
+    NoPagesInRange,
+    /// The layout engine does not support paged output.
+    Unsupported,
+}
+
 /// Messages to the constellation originating from the WebDriver server.
 #[derive(Debug, Deserialize, Serialize)]
 pub enum WebDriverCommandMsg {
@@ -840,6 +872,28 @@ pub enum WebDriverCommandMsg {
         Option<Rect<f32, CSSPixel>>,
         IpcSender<Option<Image>>,
     ),
+    /// Maximize, minimize or make fullscreen the window.
+    SetWindowState(
+        TopLevelBrowsingContextId,
+        WebDriverWindowState,
+        IpcSender<WindowSizeData>,
+    ),
+    /// Open a new top-level browsing context, and return its ID.
+    NewWindow(IpcSender<TopLevelBrowsingContextId>),
+    /// Close the top-level browsing context with the given ID.
+    CloseWindow(TopLevelBrowsingContextId),
+    /// Get the IDs of all the open top-level browsing contexts.
+    GetTopLevelBrowsingContexts(IpcSender<Vec<TopLevelBrowsingContextId>>),
+    /// Whether user prompts opened by content are kept for WebDriver to handle,
+    /// rather than being shown by the embedder.
+    InterceptUserPrompts(bool),
+    /// Act on the user prompt currently shown in the top-level browsing context
+    /// with the given ID. Replies with the text of the prompt for `GetText`.
+    HandleUserPrompt(
+        TopLevelBrowsingContextId,
+        WebDriverUserPromptAction,
+        IpcSender<Result<Option<String>, WebDriverUserPromptError>>,
+    ),
 }
 
 /// Resources required by workerglobalscopes
@@ -865,6 +919,8 @@ pub struct WorkerGlobalScopeInit {
     pub pipeline_id: PipelineId,
     /// The origin
     pub origin: ImmutableOrigin,
+    /// The origin of the top-level document of the worker's owner
+    pub top_level_origin: Option<ImmutableOrigin>,
     /// True if headless mode
     pub is_headless: bool,
     /// An optional string allowing the user agnet to be set for testing.
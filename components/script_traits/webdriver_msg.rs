@@ -4,6 +4,7 @@
 
 #![allow(missing_docs)]
 
+use crate::PrintSettings;
 use cookie::Cookie;
 use euclid::default::Rect;
 use hyper_serde::Serde;
@@ -24,6 +25,7 @@ pub enum WebDriverScriptCommand {
         Cookie<'static>,
         IpcSender<Result<(), WebDriverCookieError>>,
     ),
+    DeleteCookie(String, IpcSender<Result<(), ErrorStatus>>),
     DeleteCookies(IpcSender<Result<(), ErrorStatus>>),
     ExecuteScript(String, IpcSender<WebDriverJSResult>),
     ExecuteAsyncScript(String, IpcSender<WebDriverJSResult>),
@@ -59,6 +61,7 @@ pub enum WebDriverScriptCommand {
     FindElementElementsTagName(String, String, IpcSender<Result<Vec<String>, ErrorStatus>>),
     FocusElement(String, IpcSender<Result<(), ErrorStatus>>),
     ElementClick(String, IpcSender<Result<Option<String>, ErrorStatus>>),
+    ElementClear(String, IpcSender<Result<(), ErrorStatus>>),
     GetActiveElement(IpcSender<Option<String>>),
     GetCookie(String, IpcSender<Vec<Serde<Cookie<'static>>>>),
     GetCookies(IpcSender<Vec<Serde<Cookie<'static>>>>),
@@ -87,6 +90,7 @@ pub enum WebDriverScriptCommand {
     IsEnabled(String, IpcSender<Result<bool, ErrorStatus>>),
     IsSelected(String, IpcSender<Result<bool, ErrorStatus>>),
     GetTitle(IpcSender<String>),
+    Print(PrintSettings, IpcSender<Result<Vec<u8>, ErrorStatus>>),
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -129,6 +133,28 @@ pub enum WebDriverFrameId {
     Parent,
 }
 
+/// What to do with the user prompt of a top-level browsing context.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum WebDriverUserPromptAction {
+    Accept,
+    Dismiss,
+    GetText,
+    SendText(String),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum WebDriverUserPromptError {
+    NoSuchAlert,
+    ElementNotInteractable,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum WebDriverWindowState {
+    Maximized,
+    Minimized,
+    Fullscreen,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub enum LoadStatus {
     LoadComplete,
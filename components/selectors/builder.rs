@@ -342,7 +342,20 @@ where
                 }
                 *specificity += Specificity::from(max);
             },
+            Component::Has(ref list) => {
+                // https://drafts.csswg.org/selectors/#specificity-rules:
+                //
+                //     The specificity of a :has() pseudo-class is replaced by
+                //     the specificity of the most specific complex selector in
+                //     its selector list argument.
+                let mut max = 0;
+                for relative in &**list {
+                    max = std::cmp::max(relative.selector.specificity(), max);
+                }
+                *specificity += Specificity::from(max);
+            },
             Component::Where(..) |
+            Component::RelativeSelectorAnchor |
             Component::ExplicitUniversalType |
             Component::ExplicitAnyNamespace |
             Component::ExplicitNoNamespace |
@@ -132,6 +132,10 @@ where
     /// Whether we're inside a negation or not.
     in_negation: bool,
 
+    /// The element the relative selectors of the innermost :has() we're
+    /// matching are anchored at, if any.
+    relative_selector_anchor: Option<OpaqueElement>,
+
     /// An optional hook function for checking whether a pseudo-element
     /// should match when matching_mode is ForStatelessPseudoElement.
     pub pseudo_element_matching_fn: Option<&'a dyn Fn(&Impl::PseudoElement) -> bool>,
@@ -183,6 +187,7 @@ where
             current_host: None,
             nesting_level: 0,
             in_negation: false,
+            relative_selector_anchor: None,
             pseudo_element_matching_fn: None,
             extra_data: Default::default(),
             _impl: ::std::marker::PhantomData,
@@ -245,6 +250,25 @@ where
         result
     }
 
+    /// Runs F with a deeper nesting level, and with `anchor` as the anchor of
+    /// relative selectors, for the arguments of a :has(..) selector.
+    #[inline]
+    pub fn nest_for_relative_selector<F, R>(&mut self, anchor: OpaqueElement, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let original_anchor = self.relative_selector_anchor.replace(anchor);
+        let result = self.nest(f);
+        self.relative_selector_anchor = original_anchor;
+        result
+    }
+
+    /// The anchor of the relative selectors we're matching, if any.
+    #[inline]
+    pub fn relative_selector_anchor(&self) -> Option<OpaqueElement> {
+        self.relative_selector_anchor
+    }
+
     #[inline]
     pub fn visited_handling(&self) -> VisitedHandlingMode {
         self.visited_handling
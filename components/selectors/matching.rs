@@ -6,7 +6,8 @@ use crate::attr::{AttrSelectorOperation, NamespaceConstraint, ParsedAttrSelector
 use crate::bloom::{BloomFilter, BLOOM_HASH_MASK};
 use crate::nth_index_cache::NthIndexCacheInner;
 use crate::parser::{AncestorHashes, Combinator, Component, LocalName};
-use crate::parser::{NonTSPseudoClass, Selector, SelectorImpl, SelectorIter, SelectorList};
+use crate::parser::{NonTSPseudoClass, RelativeSelector, RelativeSelectorMatchHint};
+use crate::parser::{Selector, SelectorImpl, SelectorIter, SelectorList};
 use crate::tree::Element;
 use smallvec::SmallVec;
 use std::borrow::Borrow;
@@ -41,20 +42,33 @@ bitflags! {
         /// The element has an empty selector, so when a child is appended we
         /// might need to restyle the parent completely.
         const HAS_EMPTY_SELECTOR = 1 << 3;
+
+        /// The element is the anchor of a relative selector (that is, it was
+        /// matched against :has()), so when its descendants or later siblings
+        /// change, it may need to be restyled.
+        const ANCHORS_RELATIVE_SELECTOR = 1 << 4;
+
+        /// When a child is added or removed from the parent, or changes, the
+        /// earlier children that are anchors of relative selectors must be
+        /// restyled, because they may match :has() with a sibling combinator,
+        /// as in :has(+ a) or :has(~ a).
+        const HAS_SIBLING_RELATIVE_SELECTOR_ANCHORS = 1 << 5;
     }
 }
 
 impl ElementSelectorFlags {
     /// Returns the subset of flags that apply to the element.
     pub fn for_self(self) -> ElementSelectorFlags {
-        self & (ElementSelectorFlags::HAS_EMPTY_SELECTOR)
+        self & (ElementSelectorFlags::HAS_EMPTY_SELECTOR |
+            ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR)
     }
 
     /// Returns the subset of flags that apply to the parent.
     pub fn for_parent(self) -> ElementSelectorFlags {
         self & (ElementSelectorFlags::HAS_SLOW_SELECTOR |
             ElementSelectorFlags::HAS_SLOW_SELECTOR_LATER_SIBLINGS |
-            ElementSelectorFlags::HAS_EDGE_CHILD_SELECTOR)
+            ElementSelectorFlags::HAS_EDGE_CHILD_SELECTOR |
+            ElementSelectorFlags::HAS_SIBLING_RELATIVE_SELECTOR_ANCHORS)
     }
 }
 
@@ -377,6 +391,7 @@ fn matches_hover_and_active_quirk<Impl: SelectorImpl>(
         Component::Class(_) |
         Component::PseudoElement(_) |
         Component::Negation(_) |
+        Component::Has(_) |
         Component::FirstChild |
         Component::LastChild |
         Component::OnlyChild |
@@ -859,6 +874,23 @@ where
             }
             false
         }),
+        Component::Has(ref list) => {
+            let mut flags = ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR;
+            if list.iter().any(|relative| relative.match_hint.is_sibling()) {
+                flags |= ElementSelectorFlags::HAS_SIBLING_RELATIVE_SELECTOR_ANCHORS;
+            }
+            flags_setter(element, flags);
+            context
+                .shared
+                .nest_for_relative_selector(element.opaque(), |context| {
+                    list.iter().any(|relative| {
+                        matches_relative_selector(relative, element, context, flags_setter)
+                    })
+                })
+        },
+        Component::RelativeSelectorAnchor => {
+            context.shared.relative_selector_anchor() == Some(element.opaque())
+        },
         Component::Negation(ref negated) => context.shared.nest_for_negation(|context| {
             let mut local_context = LocalMatchingContext {
                 matches_hover_and_active_quirk: MatchesHoverAndActiveQuirk::No,
@@ -871,6 +903,68 @@ where
     }
 }
 
+/// Whether the given relative selector, anchored at `anchor`, matches any of
+/// the elements its match hint says it may match.
+fn matches_relative_selector<E, F>(
+    relative: &RelativeSelector<E::Impl>,
+    anchor: &E,
+    context: &mut MatchingContext<E::Impl>,
+    flags_setter: &mut F,
+) -> bool
+where
+    E: Element,
+    F: FnMut(&E, ElementSelectorFlags),
+{
+    let (mut candidate, in_subtree) = match relative.match_hint {
+        RelativeSelectorMatchHint::InChild => (anchor.first_element_child(), false),
+        RelativeSelectorMatchHint::InSubtree => (anchor.first_element_child(), true),
+        RelativeSelectorMatchHint::InNextSibling | RelativeSelectorMatchHint::InSibling => {
+            (anchor.next_sibling_element(), false)
+        },
+        RelativeSelectorMatchHint::InSiblingSubtree => (anchor.next_sibling_element(), true),
+    };
+
+    while let Some(element) = candidate {
+        if matches_complex_selector(relative.selector.iter(), &element, context, flags_setter) {
+            return true;
+        }
+        if in_subtree &&
+            matches_relative_selector_in_subtree(relative, &element, context, flags_setter)
+        {
+            return true;
+        }
+        if relative.match_hint == RelativeSelectorMatchHint::InNextSibling {
+            break;
+        }
+        candidate = element.next_sibling_element();
+    }
+
+    false
+}
+
+/// Whether the given relative selector matches any descendant of `element`.
+fn matches_relative_selector_in_subtree<E, F>(
+    relative: &RelativeSelector<E::Impl>,
+    element: &E,
+    context: &mut MatchingContext<E::Impl>,
+    flags_setter: &mut F,
+) -> bool
+where
+    E: Element,
+    F: FnMut(&E, ElementSelectorFlags),
+{
+    let mut child = element.first_element_child();
+    while let Some(element) = child {
+        if matches_complex_selector(relative.selector.iter(), &element, context, flags_setter) ||
+            matches_relative_selector_in_subtree(relative, &element, context, flags_setter)
+        {
+            return true;
+        }
+        child = element.next_sibling_element();
+    }
+    false
+}
+
 #[inline(always)]
 fn select_name<'a, T>(is_html: bool, local_name: &'a T, local_name_lower: &'a T) -> &'a T {
     if is_html {
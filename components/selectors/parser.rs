@@ -104,12 +104,24 @@ bitflags! {
         /// disallowed. If this flag is set, `AFTER_PSEUDO_ELEMENT` must be set
         /// as well.
         const AFTER_NON_STATEFUL_PSEUDO_ELEMENT = 1 << 4;
+        /// Whether we're inside the relative selectors of a :has(), where
+        /// another :has() isn't allowed.
+        ///
+        /// https://drafts.csswg.org/selectors/#relational
+        const DISALLOW_RELATIVE_SELECTOR = 1 << 5;
         /// Whether we are after any of the pseudo-like things.
         const AFTER_PSEUDO = Self::AFTER_PART.bits | Self::AFTER_SLOTTED.bits | Self::AFTER_PSEUDO_ELEMENT.bits;
     }
 }
 
 impl SelectorParsingState {
+    /// The flags that also apply to the selectors nested in a pseudo-class or
+    /// pseudo-element.
+    #[inline]
+    fn inherited(self) -> Self {
+        self & SelectorParsingState::DISALLOW_RELATIVE_SELECTOR
+    }
+
     #[inline]
     fn allows_functional_pseudo_classes(self) -> bool {
         !self.intersects(SelectorParsingState::AFTER_PSEUDO)
@@ -155,6 +167,7 @@ pub enum SelectorParseErrorKind<'i> {
     NonPseudoElementAfterSlotted,
     InvalidPseudoElementAfterSlotted,
     InvalidPseudoElementInsideWhere,
+    InvalidPseudoElementInsideHas,
     InvalidState,
     UnexpectedTokenInAttributeSelector(Token<'i>),
     PseudoElementExpectedColon(Token<'i>),
@@ -244,6 +257,11 @@ pub trait Parser<'i> {
         false
     }
 
+    /// Whether to parse the `:has` pseudo-class.
+    fn parse_has(&self) -> bool {
+        false
+    }
+
     /// This function can return an "Err" pseudo-element in order to support CSS2.1
     /// pseudo-elements.
     fn parse_non_ts_pseudo_class(
@@ -321,15 +339,25 @@ impl<Impl: SelectorImpl> SelectorList<Impl> {
         parser: &P,
         input: &mut CssParser<'i, 't>,
     ) -> Result<Self, ParseError<'i, P::Error>>
+    where
+        P: Parser<'i, Impl = Impl>,
+    {
+        Self::parse_with_state(parser, input, SelectorParsingState::empty())
+    }
+
+    fn parse_with_state<'i, 't, P>(
+        parser: &P,
+        input: &mut CssParser<'i, 't>,
+        state: SelectorParsingState,
+    ) -> Result<Self, ParseError<'i, P::Error>>
     where
         P: Parser<'i, Impl = Impl>,
     {
         let mut values = SmallVec::new();
         loop {
-            values.push(
-                input
-                    .parse_until_before(Delimiter::Comma, |input| parse_selector(parser, input))?,
-            );
+            values.push(input.parse_until_before(Delimiter::Comma, |input| {
+                parse_selector(parser, input, state)
+            })?);
             match input.next() {
                 Err(_) => return Ok(SelectorList(values)),
                 Ok(&Token::Comma) => continue,
@@ -348,13 +376,14 @@ impl<Impl: SelectorImpl> SelectorList<Impl> {
 fn parse_inner_compound_selector<'i, 't, P, Impl>(
     parser: &P,
     input: &mut CssParser<'i, 't>,
+    state: SelectorParsingState,
 ) -> Result<Selector<Impl>, ParseError<'i, P::Error>>
 where
     P: Parser<'i, Impl = Impl>,
     Impl: SelectorImpl,
 {
     let location = input.current_source_location();
-    let selector = parse_selector(parser, input)?;
+    let selector = parse_selector(parser, input, state)?;
 
     // Ensure they're actually all compound selectors without pseudo-elements.
     if selector.has_pseudo_element() {
@@ -380,7 +409,9 @@ where
     Impl: SelectorImpl,
 {
     input
-        .parse_comma_separated(|input| parse_inner_compound_selector(parser, input))
+        .parse_comma_separated(|input| {
+            parse_inner_compound_selector(parser, input, SelectorParsingState::empty())
+        })
         .map(|selectors| selectors.into_boxed_slice())
 }
 
@@ -975,6 +1006,16 @@ pub enum Component<Impl: SelectorImpl> {
     ///
     /// Same comment as above re. the argument.
     Is(Box<[Selector<Impl>]>),
+    /// The `:has` pseudo-class.
+    ///
+    /// https://drafts.csswg.org/selectors/#relational
+    Has(Box<[RelativeSelector<Impl>]>),
+    /// The element a relative selector (an argument of `:has`) is anchored
+    /// at, that is, the element `:has` is being matched against.
+    ///
+    /// This is always the leftmost compound selector of a relative selector,
+    /// and is never serialized.
+    RelativeSelectorAnchor,
     /// An implementation-dependent pseudo-element selector.
     PseudoElement(#[shmem(field_bound)] Impl::PseudoElement),
 }
@@ -1112,6 +1153,11 @@ impl<Impl: SelectorImpl> Component<Impl> {
                     return false;
                 }
             },
+            Has(ref list) => {
+                if !visitor.visit_relative_selector_list(&list) {
+                    return false;
+                }
+            },
             _ => {},
         }
 
@@ -1119,6 +1165,66 @@ impl<Impl: SelectorImpl> Component<Impl> {
     }
 }
 
+/// Where the elements that may match a relative selector are, relative to its
+/// anchor, as determined by its combinators.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ToShmem)]
+pub enum RelativeSelectorMatchHint {
+    /// The children of the anchor, as in `:has(> a)`.
+    InChild,
+    /// The descendants of the anchor, as in `:has(a)` or `:has(> a b)`.
+    InSubtree,
+    /// The next sibling of the anchor, as in `:has(+ a)`.
+    InNextSibling,
+    /// The later siblings of the anchor, as in `:has(~ a)` or `:has(+ a ~ b)`.
+    InSibling,
+    /// The later siblings of the anchor and their descendants, as in
+    /// `:has(+ a b)`.
+    InSiblingSubtree,
+}
+
+impl RelativeSelectorMatchHint {
+    /// Whether the elements that may match are later siblings of the anchor,
+    /// or their descendants.
+    #[inline]
+    pub fn is_sibling(self) -> bool {
+        !matches!(
+            self,
+            RelativeSelectorMatchHint::InChild | RelativeSelectorMatchHint::InSubtree
+        )
+    }
+
+    fn new(
+        leading_combinator: Combinator,
+        has_ancestor_combinators: bool,
+        has_sibling_combinators: bool,
+    ) -> Self {
+        match leading_combinator {
+            Combinator::Descendant => RelativeSelectorMatchHint::InSubtree,
+            Combinator::Child if has_ancestor_combinators => RelativeSelectorMatchHint::InSubtree,
+            Combinator::Child => RelativeSelectorMatchHint::InChild,
+            _ if has_ancestor_combinators => RelativeSelectorMatchHint::InSiblingSubtree,
+            Combinator::NextSibling if !has_sibling_combinators => {
+                RelativeSelectorMatchHint::InNextSibling
+            },
+            _ => RelativeSelectorMatchHint::InSibling,
+        }
+    }
+}
+
+/// A relative selector, that is, an argument of `:has()`.
+///
+/// https://drafts.csswg.org/selectors/#relative
+#[derive(Clone, Eq, PartialEq, ToShmem)]
+#[shmem(no_bounds)]
+pub struct RelativeSelector<Impl: SelectorImpl> {
+    /// Where the elements that may match this relative selector are.
+    pub match_hint: RelativeSelectorMatchHint,
+    /// The selector, whose leftmost compound selector is a
+    /// `Component::RelativeSelectorAnchor`.
+    #[shmem(field_bound)]
+    pub selector: Selector<Impl>,
+}
+
 #[derive(Clone, Eq, PartialEq, ToShmem)]
 #[shmem(no_bounds)]
 pub struct LocalName<Impl: SelectorImpl> {
@@ -1145,6 +1251,11 @@ impl<Impl: SelectorImpl> Debug for AttrSelectorWithOptionalNamespace<Impl> {
         self.to_css(f)
     }
 }
+impl<Impl: SelectorImpl> Debug for RelativeSelector<Impl> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.to_css(f)
+    }
+}
 impl<Impl: SelectorImpl> Debug for LocalName<Impl> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.to_css(f)
@@ -1301,6 +1412,20 @@ impl<Impl: SelectorImpl> ToCss for Selector<Impl> {
     }
 }
 
+impl<Impl: SelectorImpl> ToCss for RelativeSelector<Impl> {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        // The anchor isn't serialized, so the selector serializes as its
+        // leading combinator followed by the rest of it, except that the
+        // descendant combinator is implied.
+        let mut selector = String::new();
+        self.selector.to_css(&mut selector)?;
+        dest.write_str(selector.trim_start())
+    }
+}
+
 impl ToCss for Combinator {
     fn to_css<W>(&self, dest: &mut W) -> fmt::Result
     where
@@ -1455,6 +1580,17 @@ impl<Impl: SelectorImpl> ToCss for Component<Impl> {
                 serialize_selector_list(list.iter(), dest)?;
                 dest.write_str(")")
             },
+            Has(ref list) => {
+                dest.write_str(":has(")?;
+                for (i, selector) in list.iter().enumerate() {
+                    if i != 0 {
+                        dest.write_str(", ")?;
+                    }
+                    selector.to_css(dest)?;
+                }
+                dest.write_str(")")
+            },
+            RelativeSelectorAnchor => Ok(()),
             NonTSPseudoClass(ref pseudo) => pseudo.to_css(dest),
         }
     }
@@ -1533,19 +1669,34 @@ fn display_to_css_identifier<T: Display, W: fmt::Write>(x: &T, dest: &mut W) ->
 fn parse_selector<'i, 't, P, Impl>(
     parser: &P,
     input: &mut CssParser<'i, 't>,
+    state: SelectorParsingState,
 ) -> Result<Selector<Impl>, ParseError<'i, P::Error>>
 where
     P: Parser<'i, Impl = Impl>,
     Impl: SelectorImpl,
 {
-    let mut builder = SelectorBuilder::default();
+    parse_complex_selector(parser, input, SelectorBuilder::default(), state)
+}
 
+/// Parses the rest of a selector into `builder`, which may already contain
+/// the leftmost compound selector and a combinator, as in relative selectors.
+/// `state` holds the flags inherited from the selectors it is nested in.
+fn parse_complex_selector<'i, 't, P, Impl>(
+    parser: &P,
+    input: &mut CssParser<'i, 't>,
+    mut builder: SelectorBuilder<Impl>,
+    inherited_state: SelectorParsingState,
+) -> Result<Selector<Impl>, ParseError<'i, P::Error>>
+where
+    P: Parser<'i, Impl = Impl>,
+    Impl: SelectorImpl,
+{
     let mut has_pseudo_element = false;
     let mut slotted = false;
     let mut part = false;
     'outer_loop: loop {
         // Parse a sequence of simple selectors.
-        let state = match parse_compound_selector(parser, input, &mut builder)? {
+        let state = match parse_compound_selector(parser, input, &mut builder, inherited_state)? {
             Some(state) => state,
             None => {
                 return Err(input.new_custom_error(if builder.has_combinators() {
@@ -1611,7 +1762,7 @@ impl<Impl: SelectorImpl> Selector<Impl> {
     where
         P: Parser<'i, Impl = Impl>,
     {
-        parse_selector(parser, input)
+        parse_selector(parser, input, SelectorParsingState::empty())
     }
 }
 
@@ -2006,6 +2157,7 @@ fn parse_attribute_flags<'i, 't>(
 fn parse_negation<'i, 't, P, Impl>(
     parser: &P,
     input: &mut CssParser<'i, 't>,
+    state: SelectorParsingState,
 ) -> Result<Component<Impl>, ParseError<'i, P::Error>>
 where
     P: Parser<'i, Impl = Impl>,
@@ -2027,7 +2179,8 @@ where
         Err(e) => return Err(e.into()),
     };
     if !is_type_sel {
-        match parse_one_simple_selector(parser, input, SelectorParsingState::INSIDE_NEGATION)? {
+        let state = state | SelectorParsingState::INSIDE_NEGATION;
+        match parse_one_simple_selector(parser, input, state)? {
             Some(SimpleSelectorParseResult::SimpleSelector(s)) => {
                 sequence.push(s);
             },
@@ -2059,6 +2212,7 @@ fn parse_compound_selector<'i, 't, P, Impl>(
     parser: &P,
     input: &mut CssParser<'i, 't>,
     builder: &mut SelectorBuilder<Impl>,
+    inherited_state: SelectorParsingState,
 ) -> Result<Option<SelectorParsingState>, ParseError<'i, P::Error>>
 where
     P: Parser<'i, Impl = Impl>,
@@ -2071,7 +2225,7 @@ where
         empty = false;
     }
 
-    let mut state = SelectorParsingState::empty();
+    let mut state = inherited_state;
     loop {
         let result = match parse_one_simple_selector(parser, input, state)? {
             None => break,
@@ -2143,6 +2297,7 @@ where
 fn parse_is_or_where<'i, 't, P, Impl>(
     parser: &P,
     input: &mut CssParser<'i, 't>,
+    state: SelectorParsingState,
     component: impl FnOnce(Box<[Selector<Impl>]>) -> Component<Impl>,
 ) -> Result<Component<Impl>, ParseError<'i, P::Error>>
 where
@@ -2150,7 +2305,7 @@ where
     Impl: SelectorImpl,
 {
     debug_assert!(parser.parse_is_and_where());
-    let inner = SelectorList::parse(parser, input)?;
+    let inner = SelectorList::parse_with_state(parser, input, state.inherited())?;
     // https://drafts.csswg.org/selectors/#matches-pseudo:
     //
     //     Pseudo-elements cannot be represented by the matches-any
@@ -2162,6 +2317,85 @@ where
     Ok(component(inner.0.into_vec().into_boxed_slice()))
 }
 
+/// Parses a relative selector, that is, a selector with an optional leading
+/// combinator, defaulting to the descendant combinator.
+///
+/// <https://drafts.csswg.org/selectors/#relative>
+fn parse_relative_selector<'i, 't, P, Impl>(
+    parser: &P,
+    input: &mut CssParser<'i, 't>,
+    state: SelectorParsingState,
+) -> Result<RelativeSelector<Impl>, ParseError<'i, P::Error>>
+where
+    P: Parser<'i, Impl = Impl>,
+    Impl: SelectorImpl,
+{
+    let location = input.current_source_location();
+    let before_combinator = input.state();
+    let leading_combinator = match input.next() {
+        Ok(&Token::Delim('>')) => Combinator::Child,
+        Ok(&Token::Delim('+')) => Combinator::NextSibling,
+        Ok(&Token::Delim('~')) => Combinator::LaterSibling,
+        _ => {
+            input.reset(&before_combinator);
+            Combinator::Descendant
+        },
+    };
+
+    let mut builder = SelectorBuilder::default();
+    builder.push_simple_selector(Component::RelativeSelectorAnchor);
+    builder.push_combinator(leading_combinator);
+    let selector = parse_complex_selector(parser, input, builder, state)?;
+
+    // https://drafts.csswg.org/selectors/#relational:
+    //
+    //     Pseudo-elements are not valid within :has().
+    //
+    if selector.has_pseudo_element() || selector.is_slotted() || selector.is_part() {
+        return Err(
+            location.new_custom_error(SelectorParseErrorKind::InvalidPseudoElementInsideHas)
+        );
+    }
+
+    let mut has_ancestor_combinators = false;
+    let mut has_sibling_combinators = false;
+    // Skip the leading combinator, which is the leftmost one.
+    for combinator in selector
+        .iter_raw_match_order()
+        .rev()
+        .filter_map(|c| c.as_combinator())
+        .skip(1)
+    {
+        has_ancestor_combinators |= combinator.is_ancestor();
+        has_sibling_combinators |= combinator.is_sibling();
+    }
+    let match_hint = RelativeSelectorMatchHint::new(
+        leading_combinator,
+        has_ancestor_combinators,
+        has_sibling_combinators,
+    );
+    Ok(RelativeSelector {
+        match_hint,
+        selector,
+    })
+}
+
+fn parse_has<'i, 't, P, Impl>(
+    parser: &P,
+    input: &mut CssParser<'i, 't>,
+    state: SelectorParsingState,
+) -> Result<Component<Impl>, ParseError<'i, P::Error>>
+where
+    P: Parser<'i, Impl = Impl>,
+    Impl: SelectorImpl,
+{
+    debug_assert!(parser.parse_has());
+    let state = state.inherited() | SelectorParsingState::DISALLOW_RELATIVE_SELECTOR;
+    let selectors =
+        input.parse_comma_separated(|input| parse_relative_selector(parser, input, state))?;
+    Ok(Component::Has(selectors.into_boxed_slice()))
+}
+
 fn parse_functional_pseudo_class<'i, 't, P, Impl>(
     parser: &P,
     input: &mut CssParser<'i, 't>,
@@ -2181,17 +2415,30 @@ where
         "nth-of-type" => return Ok(parse_nth_pseudo_class(input, Component::NthOfType)?),
         "nth-last-child" => return Ok(parse_nth_pseudo_class(input, Component::NthLastChild)?),
         "nth-last-of-type" => return Ok(parse_nth_pseudo_class(input, Component::NthLastOfType)?),
-        "is" if parser.parse_is_and_where() => return parse_is_or_where(parser, input, Component::Is),
-        "where" if parser.parse_is_and_where() => return parse_is_or_where(parser, input, Component::Where),
-        "host" => return Ok(Component::Host(Some(parse_inner_compound_selector(parser, input)?))),
+        "is" if parser.parse_is_and_where() => return parse_is_or_where(parser, input, state, Component::Is),
+        "where" if parser.parse_is_and_where() => return parse_is_or_where(parser, input, state, Component::Where),
+        "host" => return Ok(Component::Host(Some(parse_inner_compound_selector(parser, input, state.inherited())?))),
+        "has" if parser.parse_has() => {
+            // https://drafts.csswg.org/selectors/#relational:
+            //
+            //     The :has() pseudo-class cannot be nested; :has() is not
+            //     valid within :has().
+            //
+            if state.intersects(SelectorParsingState::DISALLOW_RELATIVE_SELECTOR) {
+                return Err(input.new_custom_error(
+                    SelectorParseErrorKind::UnexpectedIdent("has".into())
+                ));
+            }
+            return parse_has(parser, input, state)
+        },
         "not" => {
             if state.intersects(SelectorParsingState::INSIDE_NEGATION) {
                 return Err(input.new_custom_error(
                     SelectorParseErrorKind::UnexpectedIdent("not".into())
                 ));
             }
-            debug_assert!(state.is_empty());
-            return parse_negation(parser, input)
+            debug_assert_eq!(state, state.inherited());
+            return parse_negation(parser, input, state)
         },
         _ => {}
     }
@@ -2317,7 +2564,7 @@ where
                             );
                         }
                         let selector = input.parse_nested_block(|input| {
-                            parse_inner_compound_selector(parser, input)
+                            parse_inner_compound_selector(parser, input, state.inherited())
                         })?;
                         return Ok(Some(SimpleSelectorParseResult::SlottedPseudo(selector)));
                     }
@@ -2544,6 +2791,10 @@ pub mod tests {
             true
         }
 
+        fn parse_has(&self) -> bool {
+            true
+        }
+
         fn parse_non_ts_pseudo_class(
             &self,
             location: SourceLocation,
@@ -3177,6 +3428,65 @@ pub mod tests {
         assert!(parse("foo:where(::before)").is_err());
     }
 
+    #[test]
+    fn test_has() {
+        assert!(parse("foo:has()").is_err());
+        assert!(parse("foo:has(>)").is_err());
+        assert!(parse("foo:has(::before)").is_err());
+        assert!(parse("foo:has(.bar::before)").is_err());
+        assert!(parse("foo:has(::slotted(div))").is_err());
+        assert!(parse(":not(:has(.bar))").is_ok());
+        assert!(parse("foo:has(.bar)").is_ok());
+        assert!(parse("foo:has(> .bar, + baz)").is_ok());
+        assert!(parse("foo:has(~ .bar:has(.baz)) + qux").is_err());
+        assert!(parse("foo:has(:is(.bar:has(.baz)))").is_err());
+        assert!(parse("foo:has(:not(:has(.bar)))").is_err());
+        assert!(parse(":is(:has(.bar)):has(.baz)").is_ok());
+        assert!(parse_expected("foo:has( ~  .bar)", Some("foo:has(~ .bar)")).is_ok());
+
+        let selector = &parse(":has(> .foo .bar, + .baz)").unwrap().0[0];
+        assert_eq!(selector.specificity(), specificity(0, 2, 0));
+
+        fn match_hint(input: &str) -> RelativeSelectorMatchHint {
+            match *parse(input).unwrap().0[0].iter().next().unwrap() {
+                Component::Has(ref list) => list[0].match_hint,
+                ref other => panic!("Expected :has(), got {:?}", other),
+            }
+        }
+        assert_eq!(
+            match_hint(":has(.foo)"),
+            RelativeSelectorMatchHint::InSubtree
+        );
+        assert_eq!(
+            match_hint(":has(> .foo)"),
+            RelativeSelectorMatchHint::InChild
+        );
+        assert_eq!(
+            match_hint(":has(> .foo + .bar)"),
+            RelativeSelectorMatchHint::InChild
+        );
+        assert_eq!(
+            match_hint(":has(> .foo > .bar)"),
+            RelativeSelectorMatchHint::InSubtree
+        );
+        assert_eq!(
+            match_hint(":has(+ .foo)"),
+            RelativeSelectorMatchHint::InNextSibling
+        );
+        assert_eq!(
+            match_hint(":has(+ .foo ~ .bar)"),
+            RelativeSelectorMatchHint::InSibling
+        );
+        assert_eq!(
+            match_hint(":has(~ .foo)"),
+            RelativeSelectorMatchHint::InSibling
+        );
+        assert_eq!(
+            match_hint(":has(~ .foo .bar)"),
+            RelativeSelectorMatchHint::InSiblingSubtree
+        );
+    }
+
     #[test]
     fn test_pseudo_iter() {
         let selector = &parse("q::before").unwrap().0[0];
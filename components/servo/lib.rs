@@ -62,6 +62,48 @@ fn webdriver(port: u16, constellation: Sender<ConstellationMsg>) {
 #[cfg(not(feature = "webdriver"))]
 fn webdriver(_port: u16, _constellation: Sender<ConstellationMsg>) {}
 
+#[cfg(feature = "webdriver")]
+type WebDriverBiDiEvents = crossbeam_channel::Receiver<webdriver_server::BiDiEvent>;
+
+#[cfg(not(feature = "webdriver"))]
+type WebDriverBiDiEvents = ();
+
+/// Route the messages sent to devtools through the WebDriver BiDi server,
+/// which reports some of them to its clients as events.
+#[cfg(feature = "webdriver")]
+fn tap_devtools_for_webdriver_bidi(
+    devtools_chan: Option<Sender<devtools_traits::DevtoolsControlMsg>>,
+) -> (
+    Option<Sender<devtools_traits::DevtoolsControlMsg>>,
+    Option<WebDriverBiDiEvents>,
+) {
+    let (devtools_chan, events) = webdriver_server::tap_devtools_messages(devtools_chan);
+    (Some(devtools_chan), Some(events))
+}
+
+#[cfg(not(feature = "webdriver"))]
+fn tap_devtools_for_webdriver_bidi(
+    devtools_chan: Option<Sender<devtools_traits::DevtoolsControlMsg>>,
+) -> (
+    Option<Sender<devtools_traits::DevtoolsControlMsg>>,
+    Option<WebDriverBiDiEvents>,
+) {
+    (devtools_chan, None)
+}
+
+#[cfg(feature = "webdriver")]
+fn webdriver_bidi(port: u16, constellation: Sender<ConstellationMsg>, events: WebDriverBiDiEvents) {
+    webdriver_server::start_bidi_server(port, constellation, events);
+}
+
+#[cfg(not(feature = "webdriver"))]
+fn webdriver_bidi(
+    _port: u16,
+    _constellation: Sender<ConstellationMsg>,
+    _events: WebDriverBiDiEvents,
+) {
+}
+
 use bluetooth::BluetoothThreadFactory;
 use bluetooth_traits::BluetoothRequest;
 use canvas::{SurfaceProviders, WebGLComm, WebGlExecutor};
@@ -390,10 +432,21 @@ where
         );
         let mem_profiler_chan = profile_mem::Profiler::create(opts.mem_profiler_period);
 
-        let debugger_chan = opts.debugger_port.map(|port| debugger::start_server(port));
-        let devtools_chan = opts
-            .devtools_port
-            .map(|port| devtools::start_server(port, embedder_proxy.clone()));
+        let devtools_chan = opts.devtools_port.map(|port| {
+            devtools::start_server(port, embedder_proxy.clone(), mem_profiler_chan.clone())
+        });
+        let (devtools_chan, webdriver_bidi_events) = match opts.webdriver_bidi_port {
+            Some(_) => tap_devtools_for_webdriver_bidi(devtools_chan),
+            None => (devtools_chan, None),
+        };
+        let (devtools_chan, debugger_chan) = match opts.debugger_port {
+            Some(port) => {
+                let (devtools_chan, events) = debugger::tap_devtools_messages(devtools_chan);
+                let debugger_chan = debugger::start_server(port, user_agent.to_string(), events);
+                (Some(devtools_chan), Some(debugger_chan))
+            },
+            None => (devtools_chan, None),
+        };
 
         let coordinates = window.get_coordinates();
         let device_pixel_ratio = coordinates.hidpi_factor.get();
@@ -530,6 +583,9 @@ where
             if let Some(port) = opts.webdriver_port {
                 webdriver(port, constellation_chan.clone());
             }
+            if let (Some(port), Some(events)) = (opts.webdriver_bidi_port, webdriver_bidi_events) {
+                webdriver_bidi(port, constellation_chan.clone(), events);
+            }
         }
 
         // The compositor coordinates with the client window to create the final
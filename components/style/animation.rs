@@ -9,19 +9,22 @@
 
 use crate::bezier::Bezier;
 use crate::context::SharedStyleContext;
+use crate::custom_properties::Name;
 use crate::dom::{OpaqueNode, TElement, TNode};
 use crate::font_metrics::FontMetricsProvider;
 use crate::properties::animated_properties::AnimationValue;
 use crate::properties::longhands::animation_direction::computed_value::single_value::T as AnimationDirection;
 use crate::properties::longhands::animation_fill_mode::computed_value::single_value::T as AnimationFillMode;
 use crate::properties::longhands::animation_play_state::computed_value::single_value::T as AnimationPlayState;
-use crate::properties::LonghandIdSet;
-use crate::properties::{self, CascadeMode, ComputedValues, LonghandId};
+use crate::properties::{self, CascadeMode, ComputedValues, LonghandIdSet};
+use crate::properties::{PropertyDeclarationId, ShorthandId};
+use crate::selector_map::PrecomputedHashSet;
 use crate::stylesheets::keyframes_rule::{KeyframesAnimation, KeyframesStep, KeyframesStepValue};
 use crate::stylesheets::Origin;
 use crate::values::animated::{Animate, Procedure};
 use crate::values::computed::Time;
 use crate::values::computed::TimingFunction;
+use crate::values::computed::TransitionProperty;
 use crate::values::generics::box_::AnimationIterationCount;
 use crate::values::generics::easing::{StepPosition, TimingFunction as GenericTimingFunction};
 use crate::Atom;
@@ -45,23 +48,34 @@ pub struct PropertyAnimation {
 }
 
 impl PropertyAnimation {
-    /// Returns the given property longhand id.
-    pub fn property_id(&self) -> LonghandId {
+    /// Returns the id of the animated property.
+    pub fn property_id(&self) -> PropertyDeclarationId {
         debug_assert_eq!(self.from.id(), self.to.id());
         self.from.id()
     }
 
-    fn from_longhand(
-        longhand: LonghandId,
+    fn from_property(
+        property: PropertyDeclarationId,
         timing_function: TimingFunction,
         duration: Time,
         old_style: &ComputedValues,
         new_style: &ComputedValues,
+        context: &SharedStyleContext,
     ) -> Option<PropertyAnimation> {
-        // FIXME(emilio): Handle the case where old_style and new_style's writing mode differ.
-        let longhand = longhand.to_physical(new_style.writing_mode);
-        let from = AnimationValue::from_computed_values(longhand, old_style)?;
-        let to = AnimationValue::from_computed_values(longhand, new_style)?;
+        let (from, to) = match property {
+            PropertyDeclarationId::Longhand(longhand) => {
+                // FIXME(emilio): Handle the case where old_style and new_style's writing mode differ.
+                let longhand = longhand.to_physical(new_style.writing_mode);
+                (
+                    AnimationValue::from_computed_values(longhand, old_style)?,
+                    AnimationValue::from_computed_values(longhand, new_style)?,
+                )
+            },
+            PropertyDeclarationId::Custom(name) => (
+                AnimationValue::from_custom_property(name, old_style, context.stylist)?,
+                AnimationValue::from_custom_property(name, new_style, context.stylist)?,
+            ),
+        };
         let duration = duration.seconds() as f64;
 
         if from == to || duration == 0.0 {
@@ -452,6 +466,16 @@ impl Animation {
             {
                 property.set_in_style_for_servo(mutable_style);
             }
+            for property in self
+                .keyframes_animation
+                .custom_properties_changed
+                .iter()
+                .filter_map(|name| {
+                    AnimationValue::from_custom_property(name, &**computed_style, context.stylist)
+                })
+            {
+                property.set_in_style_for_servo(mutable_style);
+            }
         };
 
         // TODO: How could we optimise it? Is it such a big deal?
@@ -516,9 +540,7 @@ impl Animation {
         };
 
         let mut new_style = (**style).clone();
-        let mut update_style_for_longhand = |longhand| {
-            let from = AnimationValue::from_computed_values(longhand, &prev_keyframe_style)?;
-            let to = AnimationValue::from_computed_values(longhand, &next_keyframe_style)?;
+        let mut update_style_for_values = |from, to| {
             PropertyAnimation {
                 from,
                 to,
@@ -526,11 +548,24 @@ impl Animation {
                 duration: relative_duration as f64,
             }
             .update(&mut new_style, relative_progress);
-            None::<()>
         };
 
-        for property in self.keyframes_animation.properties_changed.iter() {
-            update_style_for_longhand(property);
+        for longhand in self.keyframes_animation.properties_changed.iter() {
+            let from = AnimationValue::from_computed_values(longhand, &prev_keyframe_style);
+            let to = AnimationValue::from_computed_values(longhand, &next_keyframe_style);
+            if let (Some(from), Some(to)) = (from, to) {
+                update_style_for_values(from, to);
+            }
+        }
+
+        for name in self.keyframes_animation.custom_properties_changed.iter() {
+            let from =
+                AnimationValue::from_custom_property(name, &prev_keyframe_style, context.stylist);
+            let to =
+                AnimationValue::from_custom_property(name, &next_keyframe_style, context.stylist);
+            if let (Some(from), Some(to)) = (from, to) {
+                update_style_for_values(from, to);
+            }
         }
 
         *Arc::make_mut(style) = new_style;
@@ -812,20 +847,27 @@ impl ElementAnimationSet {
             self.apply_active_animations::<E>(context, &mut before_change_style, font_metrics);
         }
 
-        let transitioning_properties = start_transitions_if_applicable(
-            context,
-            opaque_node,
-            &before_change_style,
-            after_change_style,
-            self,
-        );
+        let (transitioning_properties, transitioning_custom_properties) =
+            start_transitions_if_applicable(
+                context,
+                opaque_node,
+                &before_change_style,
+                after_change_style,
+                self,
+            );
 
         // Cancel any non-finished transitions that have properties which no longer transition.
         for transition in self.transitions.iter_mut() {
             if transition.state == AnimationState::Finished {
                 continue;
             }
-            if transitioning_properties.contains(transition.property_animation.property_id()) {
+            let still_transitioning = match transition.property_animation.property_id() {
+                PropertyDeclarationId::Longhand(id) => transitioning_properties.contains(id),
+                PropertyDeclarationId::Custom(name) => {
+                    transitioning_custom_properties.contains(name)
+                },
+            };
+            if still_transitioning {
                 continue;
             }
             transition.state = AnimationState::Canceled;
@@ -836,7 +878,7 @@ impl ElementAnimationSet {
         &mut self,
         context: &SharedStyleContext,
         opaque_node: OpaqueNode,
-        longhand_id: LonghandId,
+        property: PropertyDeclarationId,
         index: usize,
         old_style: &ComputedValues,
         new_style: &Arc<ComputedValues>,
@@ -849,12 +891,13 @@ impl ElementAnimationSet {
 
         // Only start a new transition if the style actually changes between
         // the old style and the new style.
-        let property_animation = match PropertyAnimation::from_longhand(
-            longhand_id,
+        let property_animation = match PropertyAnimation::from_property(
+            property,
             timing_function,
             duration,
             old_style,
             new_style,
+            context,
         ) {
             Some(property_animation) => property_animation,
             None => return,
@@ -890,7 +933,7 @@ impl ElementAnimationSet {
             .transitions
             .iter_mut()
             .filter(|transition| transition.state == AnimationState::Running)
-            .find(|transition| transition.property_animation.property_id() == longhand_id)
+            .find(|transition| transition.property_animation.property_id() == property)
         {
             // We always cancel any running transitions for the same property.
             old_transition.state = AnimationState::Canceled;
@@ -902,19 +945,20 @@ impl ElementAnimationSet {
 }
 
 /// Kick off any new transitions for this node and return all of the properties that are
-/// transitioning. This is at the end of calculating style for a single node.
+/// transitioning, both longhands and registered custom properties. This is at the end of
+/// calculating style for a single node.
 pub fn start_transitions_if_applicable(
     context: &SharedStyleContext,
     opaque_node: OpaqueNode,
     old_style: &ComputedValues,
     new_style: &Arc<ComputedValues>,
     animation_state: &mut ElementAnimationSet,
-) -> LonghandIdSet {
+) -> (LonghandIdSet, PrecomputedHashSet<Name>) {
     // If the style of this element is display:none, then we don't start any transitions
-    // and we cancel any currently running transitions by returning an empty LonghandIdSet.
+    // and we cancel any currently running transitions by returning empty sets.
     let box_style = new_style.get_box();
     if box_style.clone_display().is_none() {
-        return LonghandIdSet::new();
+        return (LonghandIdSet::new(), PrecomputedHashSet::default());
     }
 
     let mut properties_that_transition = LonghandIdSet::new();
@@ -928,14 +972,45 @@ pub fn start_transitions_if_applicable(
         animation_state.start_transition_if_applicable(
             context,
             opaque_node,
-            physical_property,
+            PropertyDeclarationId::Longhand(physical_property),
             transition.index,
             old_style,
             new_style,
         );
     }
 
-    properties_that_transition
+    let mut custom_properties_that_transition = PrecomputedHashSet::default();
+    let mut maybe_start_custom_transition = |name: &Name, index| {
+        if !custom_properties_that_transition.insert(name.clone()) {
+            return;
+        }
+        animation_state.start_transition_if_applicable(
+            context,
+            opaque_node,
+            PropertyDeclarationId::Custom(name),
+            index,
+            old_style,
+            new_style,
+        );
+    };
+    for index in 0..box_style.transition_property_count() {
+        match box_style.transition_property_at(index) {
+            TransitionProperty::Custom(ref name) => maybe_start_custom_transition(name, index),
+            TransitionProperty::Shorthand(ShorthandId::All) => {
+                for name in context.stylist.custom_property_registrations().keys() {
+                    maybe_start_custom_transition(name, index);
+                }
+            },
+            TransitionProperty::Shorthand(..) |
+            TransitionProperty::Longhand(..) |
+            TransitionProperty::Unsupported(..) => {},
+        }
+    }
+
+    (
+        properties_that_transition,
+        custom_properties_that_transition,
+    )
 }
 
 fn compute_style_for_animation_step<E>(
@@ -969,7 +1044,7 @@ where
             // This currently ignores visited styles, which seems acceptable,
             // as existing browsers don't appear to animate visited styles.
             let computed = properties::apply_declarations::<E, _, _>(
-                context.stylist.device(),
+                context.stylist,
                 /* pseudo = */ None,
                 previous_style.rules(),
                 &context.guards,
@@ -7,6 +7,7 @@
 use crate::properties::PropertyDeclarationBlock;
 use crate::rule_tree::{CascadeLevel, StyleSource};
 use crate::shared_lock::Locked;
+use crate::stylesheets::layer_rule::LayerOrder;
 use servo_arc::Arc;
 use smallvec::SmallVec;
 
@@ -71,6 +72,8 @@ pub struct ApplicableDeclarationBlock {
     bits: ApplicableDeclarationBits,
     /// The specificity of the selector this block is represented by.
     pub specificity: u32,
+    /// The order of the cascade layer this block belongs to.
+    layer_order: LayerOrder,
 }
 
 impl ApplicableDeclarationBlock {
@@ -85,16 +88,24 @@ impl ApplicableDeclarationBlock {
             source: StyleSource::from_declarations(declarations),
             bits: ApplicableDeclarationBits::new(0, level),
             specificity: 0,
+            layer_order: LayerOrder::root(),
         }
     }
 
     /// Constructs an applicable declaration block from the given components
     #[inline]
-    pub fn new(source: StyleSource, order: u32, level: CascadeLevel, specificity: u32) -> Self {
+    pub fn new(
+        source: StyleSource,
+        order: u32,
+        level: CascadeLevel,
+        specificity: u32,
+        layer_order: LayerOrder,
+    ) -> Self {
         ApplicableDeclarationBlock {
             source,
             bits: ApplicableDeclarationBits::new(order, level),
             specificity,
+            layer_order,
         }
     }
 
@@ -110,11 +121,17 @@ impl ApplicableDeclarationBlock {
         self.bits.level()
     }
 
+    /// Returns the cascade layer order of the block.
+    #[inline]
+    pub fn layer_order(&self) -> LayerOrder {
+        self.layer_order
+    }
+
     /// Convenience method to consume self and return the right thing for the
     /// rule tree to iterate over.
     #[inline]
-    pub fn for_rule_tree(self) -> (StyleSource, CascadeLevel) {
+    pub fn for_rule_tree(self) -> (StyleSource, CascadeLevel, LayerOrder) {
         let level = self.level();
-        (self.source, level)
+        (self.source, level, self.layer_order)
     }
 }
@@ -0,0 +1,393 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Conversions between color spaces, going through CIE XYZ with a D65 white
+//! point.
+//!
+//! The matrices and transfer functions are the ones of the sample code in
+//! <https://drafts.csswg.org/css-color-4/#color-conversion-code>.
+
+use super::ColorSpace;
+use std::f32::consts::PI;
+
+type Transform = [[f32; 3]; 3];
+
+fn transform(matrix: &Transform, [x, y, z]: [f32; 3]) -> [f32; 3] {
+    [
+        matrix[0][0] * x + matrix[0][1] * y + matrix[0][2] * z,
+        matrix[1][0] * x + matrix[1][1] * y + matrix[1][2] * z,
+        matrix[2][0] * x + matrix[2][1] * y + matrix[2][2] * z,
+    ]
+}
+
+fn map(components: [f32; 3], f: impl Fn(f32) -> f32) -> [f32; 3] {
+    [f(components[0]), f(components[1]), f(components[2])]
+}
+
+/// Converts the components of a color in the given color space to XYZ D65.
+pub fn to_xyz_d65(color_space: ColorSpace, components: [f32; 3]) -> [f32; 3] {
+    match color_space {
+        ColorSpace::Srgb => transform(&SRGB_TO_XYZ, map(components, srgb_to_linear)),
+        ColorSpace::SrgbLinear => transform(&SRGB_TO_XYZ, components),
+        ColorSpace::DisplayP3 => transform(&DISPLAY_P3_TO_XYZ, map(components, srgb_to_linear)),
+        ColorSpace::A98Rgb => transform(&A98_RGB_TO_XYZ, map(components, a98_rgb_to_linear)),
+        ColorSpace::ProphotoRgb => transform(
+            &D50_TO_D65,
+            transform(
+                &PROPHOTO_RGB_TO_XYZ_D50,
+                map(components, prophoto_rgb_to_linear),
+            ),
+        ),
+        ColorSpace::Rec2020 => transform(&REC2020_TO_XYZ, map(components, rec2020_to_linear)),
+        ColorSpace::XyzD50 => transform(&D50_TO_D65, components),
+        ColorSpace::XyzD65 => components,
+        ColorSpace::Lab => transform(&D50_TO_D65, lab_to_xyz_d50(components)),
+        ColorSpace::Lch => to_xyz_d65(ColorSpace::Lab, polar_to_rectangular(components)),
+        ColorSpace::Oklab => oklab_to_xyz(components),
+        ColorSpace::Oklch => oklab_to_xyz(polar_to_rectangular(components)),
+        ColorSpace::Hsl => to_xyz_d65(ColorSpace::Srgb, hsl_to_srgb(components)),
+        ColorSpace::Hwb => to_xyz_d65(ColorSpace::Srgb, hwb_to_srgb(components)),
+    }
+}
+
+/// Converts the components of a color in XYZ D65 to the given color space.
+pub fn from_xyz_d65(color_space: ColorSpace, xyz: [f32; 3]) -> [f32; 3] {
+    match color_space {
+        ColorSpace::Srgb => map(transform(&XYZ_TO_SRGB, xyz), srgb_from_linear),
+        ColorSpace::SrgbLinear => transform(&XYZ_TO_SRGB, xyz),
+        ColorSpace::DisplayP3 => map(transform(&XYZ_TO_DISPLAY_P3, xyz), srgb_from_linear),
+        ColorSpace::A98Rgb => map(transform(&XYZ_TO_A98_RGB, xyz), a98_rgb_from_linear),
+        ColorSpace::ProphotoRgb => map(
+            transform(&XYZ_D50_TO_PROPHOTO_RGB, transform(&D65_TO_D50, xyz)),
+            prophoto_rgb_from_linear,
+        ),
+        ColorSpace::Rec2020 => map(transform(&XYZ_TO_REC2020, xyz), rec2020_from_linear),
+        ColorSpace::XyzD50 => transform(&D65_TO_D50, xyz),
+        ColorSpace::XyzD65 => xyz,
+        ColorSpace::Lab => lab_from_xyz_d50(transform(&D65_TO_D50, xyz)),
+        ColorSpace::Lch => rectangular_to_polar(from_xyz_d65(ColorSpace::Lab, xyz), LCH_ACHROMATIC),
+        ColorSpace::Oklab => oklab_from_xyz(xyz),
+        ColorSpace::Oklch => rectangular_to_polar(oklab_from_xyz(xyz), OKLCH_ACHROMATIC),
+        ColorSpace::Hsl => srgb_to_hsl(from_xyz_d65(ColorSpace::Srgb, xyz)),
+        ColorSpace::Hwb => srgb_to_hwb(from_xyz_d65(ColorSpace::Srgb, xyz)),
+    }
+}
+
+/// The chroma under which the hue of a lch() color is powerless.
+const LCH_ACHROMATIC: f32 = 0.0015;
+
+/// The chroma under which the hue of an oklch() color is powerless.
+const OKLCH_ACHROMATIC: f32 = 0.000_004;
+
+/// Normalizes a hue, in degrees, to the range [0, 360).
+pub fn normalize_hue(hue: f32) -> f32 {
+    let hue = hue % 360.;
+    if hue < 0. {
+        hue + 360.
+    } else {
+        hue
+    }
+}
+
+fn polar_to_rectangular([lightness, chroma, hue]: [f32; 3]) -> [f32; 3] {
+    let hue = hue * PI / 180.;
+    [lightness, chroma * hue.cos(), chroma * hue.sin()]
+}
+
+fn rectangular_to_polar([lightness, a, b]: [f32; 3], achromatic: f32) -> [f32; 3] {
+    let chroma = (a * a + b * b).sqrt();
+    let hue = if chroma <= achromatic {
+        0.
+    } else {
+        normalize_hue(b.atan2(a) * 180. / PI)
+    };
+    [lightness, chroma, hue]
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    let abs = value.abs();
+    if abs <= 0.04045 {
+        value / 12.92
+    } else {
+        value.signum() * ((abs + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn srgb_from_linear(value: f32) -> f32 {
+    let abs = value.abs();
+    if abs > 0.0031308 {
+        value.signum() * (1.055 * abs.powf(1. / 2.4) - 0.055)
+    } else {
+        12.92 * value
+    }
+}
+
+fn a98_rgb_to_linear(value: f32) -> f32 {
+    value.signum() * value.abs().powf(563. / 256.)
+}
+
+fn a98_rgb_from_linear(value: f32) -> f32 {
+    value.signum() * value.abs().powf(256. / 563.)
+}
+
+fn prophoto_rgb_to_linear(value: f32) -> f32 {
+    let abs = value.abs();
+    if abs <= 16. / 512. {
+        value / 16.
+    } else {
+        value.signum() * abs.powf(1.8)
+    }
+}
+
+fn prophoto_rgb_from_linear(value: f32) -> f32 {
+    let abs = value.abs();
+    if abs >= 1. / 512. {
+        value.signum() * abs.powf(1. / 1.8)
+    } else {
+        16. * value
+    }
+}
+
+const REC2020_ALPHA: f32 = 1.09929682680944;
+const REC2020_BETA: f32 = 0.018053968510807;
+
+fn rec2020_to_linear(value: f32) -> f32 {
+    let abs = value.abs();
+    if abs < REC2020_BETA * 4.5 {
+        value / 4.5
+    } else {
+        value.signum() * ((abs + REC2020_ALPHA - 1.) / REC2020_ALPHA).powf(1. / 0.45)
+    }
+}
+
+fn rec2020_from_linear(value: f32) -> f32 {
+    let abs = value.abs();
+    if abs > REC2020_BETA {
+        value.signum() * (REC2020_ALPHA * abs.powf(0.45) - (REC2020_ALPHA - 1.))
+    } else {
+        4.5 * value
+    }
+}
+
+const D50_WHITE: [f32; 3] = [0.3457 / 0.3585, 1., (1. - 0.3457 - 0.3585) / 0.3585];
+const LAB_KAPPA: f32 = 24389. / 27.;
+const LAB_EPSILON: f32 = 216. / 24389.;
+
+fn lab_to_xyz_d50([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    let f1 = (lightness + 16.) / 116.;
+    let f0 = a / 500. + f1;
+    let f2 = f1 - b / 200.;
+    let x = if f0.powi(3) > LAB_EPSILON {
+        f0.powi(3)
+    } else {
+        (116. * f0 - 16.) / LAB_KAPPA
+    };
+    let y = if lightness > LAB_KAPPA * LAB_EPSILON {
+        f1.powi(3)
+    } else {
+        lightness / LAB_KAPPA
+    };
+    let z = if f2.powi(3) > LAB_EPSILON {
+        f2.powi(3)
+    } else {
+        (116. * f2 - 16.) / LAB_KAPPA
+    };
+    [x * D50_WHITE[0], y * D50_WHITE[1], z * D50_WHITE[2]]
+}
+
+fn lab_from_xyz_d50(xyz: [f32; 3]) -> [f32; 3] {
+    let f = |value: f32, white: f32| {
+        let value = value / white;
+        if value > LAB_EPSILON {
+            value.cbrt()
+        } else {
+            (LAB_KAPPA * value + 16.) / 116.
+        }
+    };
+    let f0 = f(xyz[0], D50_WHITE[0]);
+    let f1 = f(xyz[1], D50_WHITE[1]);
+    let f2 = f(xyz[2], D50_WHITE[2]);
+    [116. * f1 - 16., 500. * (f0 - f1), 200. * (f1 - f2)]
+}
+
+fn oklab_to_xyz(oklab: [f32; 3]) -> [f32; 3] {
+    let lms = map(transform(&OKLAB_TO_LMS, oklab), |value| value.powi(3));
+    transform(&LMS_TO_XYZ, lms)
+}
+
+fn oklab_from_xyz(xyz: [f32; 3]) -> [f32; 3] {
+    let lms = map(transform(&XYZ_TO_LMS, xyz), f32::cbrt);
+    transform(&LMS_TO_OKLAB, lms)
+}
+
+/// Converts hsl() components, with the saturation and lightness as
+/// percentages, to sRGB.
+fn hsl_to_srgb([hue, saturation, lightness]: [f32; 3]) -> [f32; 3] {
+    let hue = normalize_hue(hue);
+    let saturation = saturation / 100.;
+    let lightness = lightness / 100.;
+    let f = |n: f32| {
+        let k = (n + hue / 30.) % 12.;
+        let a = saturation * lightness.min(1. - lightness);
+        lightness - a * (k - 3.).min(9. - k).min(1.).max(-1.)
+    };
+    [f(0.), f(8.), f(4.)]
+}
+
+fn srgb_to_hsl([red, green, blue]: [f32; 3]) -> [f32; 3] {
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let lightness = (min + max) / 2.;
+    let delta = max - min;
+    if delta == 0. {
+        return [0., 0., lightness * 100.];
+    }
+    let saturation = if lightness == 0. || lightness == 1. {
+        0.
+    } else {
+        (max - lightness) / lightness.min(1. - lightness)
+    };
+    let hue = if max == red {
+        (green - blue) / delta + if green < blue { 6. } else { 0. }
+    } else if max == green {
+        (blue - red) / delta + 2.
+    } else {
+        (red - green) / delta + 4.
+    };
+    [hue * 60., saturation * 100., lightness * 100.]
+}
+
+fn hwb_to_srgb([hue, whiteness, blackness]: [f32; 3]) -> [f32; 3] {
+    let whiteness = whiteness / 100.;
+    let blackness = blackness / 100.;
+    if whiteness + blackness >= 1. {
+        let gray = whiteness / (whiteness + blackness);
+        return [gray, gray, gray];
+    }
+    map(hsl_to_srgb([hue, 100., 50.]), |value| {
+        value * (1. - whiteness - blackness) + whiteness
+    })
+}
+
+fn srgb_to_hwb(srgb: [f32; 3]) -> [f32; 3] {
+    let [red, green, blue] = srgb;
+    let hue = srgb_to_hsl(srgb)[0];
+    let whiteness = red.min(green).min(blue);
+    let blackness = 1. - red.max(green).max(blue);
+    [hue, whiteness * 100., blackness * 100.]
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const SRGB_TO_XYZ: Transform = [
+    [506752. / 1228815., 87881. / 245763., 12673. / 70218.],
+    [87098. / 409605., 175762. / 245763., 12673. / 175545.],
+    [7918. / 409605., 87881. / 737289., 1001167. / 1053270.],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const XYZ_TO_SRGB: Transform = [
+    [12831. / 3959., -329. / 214., -1974. / 3959.],
+    [-851781. / 878810., 1648619. / 878810., 36519. / 878810.],
+    [705. / 12673., -2585. / 12673., 705. / 667.],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const DISPLAY_P3_TO_XYZ: Transform = [
+    [608311. / 1250200., 189793. / 714400., 198249. / 1000160.],
+    [35783. / 156275., 247089. / 357200., 198249. / 2500400.],
+    [0., 32229. / 714400., 5220557. / 5000800.],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const XYZ_TO_DISPLAY_P3: Transform = [
+    [446124. / 178915., -333277. / 357830., -72051. / 178915.],
+    [-14852. / 17905., 63121. / 35810., 423. / 17905.],
+    [11844. / 330415., -50337. / 660830., 316169. / 330415.],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const A98_RGB_TO_XYZ: Transform = [
+    [573536. / 994567., 263643. / 1420810., 187206. / 994567.],
+    [591459. / 1989134., 6239551. / 9945670., 374412. / 4972835.],
+    [53769. / 1989134., 351524. / 4972835., 4929758. / 4972835.],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const XYZ_TO_A98_RGB: Transform = [
+    [1829569. / 896150., -506331. / 896150., -308931. / 896150.],
+    [-851781. / 878810., 1648619. / 878810., 36519. / 878810.],
+    [16779. / 1248040., -147721. / 1248040., 1266979. / 1248040.],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const PROPHOTO_RGB_TO_XYZ_D50: Transform = [
+    [0.7977604896723027, 0.13518583717574031, 0.0313493495815248],
+    [0.2880711282292934, 0.7118432178101014, 0.00008565396060525902],
+    [0., 0., 0.8251046025104601],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const XYZ_D50_TO_PROPHOTO_RGB: Transform = [
+    [1.3457989731028281, -0.25558010007997534, -0.05110628506753401],
+    [-0.5446224939028347, 1.5082327413132781, 0.02053603239147973],
+    [0., 0., 1.2119675456389454],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const REC2020_TO_XYZ: Transform = [
+    [63426534. / 99577255., 20160776. / 139408157., 47086771. / 278816314.],
+    [26158966. / 99577255., 472592308. / 697040785., 8267143. / 139408157.],
+    [0., 19567812. / 697040785., 295819943. / 278816314.],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const XYZ_TO_REC2020: Transform = [
+    [30757411. / 17917100., -6372589. / 17917100., -4539589. / 17917100.],
+    [-19765991. / 29648200., 47925759. / 29648200., 467509. / 29648200.],
+    [792561. / 44930125., -1921689. / 44930125., 42328811. / 44930125.],
+];
+
+/// Bradford chromatic adaptation from D50 to D65.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const D50_TO_D65: Transform = [
+    [0.955473421488075, -0.02309845494876471, 0.06325924320057072],
+    [-0.0283697093338637, 1.0099953980813041, 0.021041441191917323],
+    [0.012314014864481998, -0.020507649298898964, 1.330365926242124],
+];
+
+/// Bradford chromatic adaptation from D65 to D50.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const D65_TO_D50: Transform = [
+    [1.0479297925449969, 0.022946870601609652, -0.05019226628920524],
+    [0.02962780877005599, 0.9904344267538799, -0.017073799063418826],
+    [-0.009243040646204504, 0.015055191490298152, 0.7518742814281371],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const XYZ_TO_LMS: Transform = [
+    [0.8190224379967030, 0.3619062600528904, -0.1288737815209879],
+    [0.0329836539323885, 0.9292868615863434, 0.0361446663506424],
+    [0.0481771893596242, 0.2642395317527308, 0.6335478284694309],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const LMS_TO_OKLAB: Transform = [
+    [0.2104542683093140, 0.7936177747023054, -0.0040720430116193],
+    [1.9779985324311684, -2.4285922420485799, 0.4505937096174110],
+    [0.0259040424655478, 0.7827717124575296, -0.8086757549230774],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const OKLAB_TO_LMS: Transform = [
+    [1., 0.3963377773761749, 0.2158037573099136],
+    [1., -0.1055613458156586, -0.0638541728258133],
+    [1., -0.0894841775298119, -1.2914855480194092],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const LMS_TO_XYZ: Transform = [
+    [1.2268798758459243, -0.5578149944602171, 0.2813910456659647],
+    [-0.0405757452148008, 1.1122868032803170, -0.0717110580655164],
+    [-0.0763729366746601, -0.4214933324022432, 1.5869240198367816],
+];
@@ -0,0 +1,226 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Color interpolation, as used by color-mix(), animations and gradients.
+//!
+//! <https://drafts.csswg.org/css-color-4/#interpolation>
+
+use super::convert::normalize_hue;
+use super::{AbsoluteColor, ColorFlags, ColorSpace};
+use crate::parser::{Parse, ParserContext};
+use cssparser::Parser;
+use std::fmt::{self, Write};
+use style_traits::{CssWriter, ParseError, ToCss};
+
+/// How hues are interpolated in cylindrical color spaces.
+///
+/// <https://drafts.csswg.org/css-color-4/#hue-interpolation>
+#[derive(Clone, Copy, Debug, Eq, MallocSizeOf, Parse, PartialEq, ToCss, ToShmem)]
+#[repr(u8)]
+pub enum HueInterpolationMethod {
+    /// Interpolate along the shorter arc between the hues.
+    Shorter,
+    /// Interpolate along the longer arc between the hues.
+    Longer,
+    /// Interpolate with increasing hues.
+    Increasing,
+    /// Interpolate with decreasing hues.
+    Decreasing,
+}
+
+/// <https://drafts.csswg.org/css-color-4/#color-interpolation-method>
+#[derive(Clone, Copy, Debug, Eq, MallocSizeOf, PartialEq, ToShmem)]
+pub struct ColorInterpolationMethod {
+    /// The color space to interpolate in.
+    pub space: ColorSpace,
+    /// How to interpolate hues, if `space` is cylindrical.
+    pub hue: HueInterpolationMethod,
+}
+
+impl ColorInterpolationMethod {
+    /// Interpolation in sRGB.
+    pub const SRGB: Self = ColorInterpolationMethod {
+        space: ColorSpace::Srgb,
+        hue: HueInterpolationMethod::Shorter,
+    };
+
+    /// Interpolation in Oklab.
+    pub const OKLAB: Self = ColorInterpolationMethod {
+        space: ColorSpace::Oklab,
+        hue: HueInterpolationMethod::Shorter,
+    };
+
+    /// The interpolation method to use when none is specified, as for
+    /// animations and gradients: sRGB if all the colors have a legacy sRGB
+    /// syntax, and Oklab otherwise.
+    ///
+    /// <https://drafts.csswg.org/css-color-4/#interpolation-space>
+    pub fn default_for<'a>(colors: impl IntoIterator<Item = &'a AbsoluteColor>) -> Self {
+        if colors.into_iter().all(AbsoluteColor::is_legacy_srgb) {
+            Self::SRGB
+        } else {
+            Self::OKLAB
+        }
+    }
+}
+
+impl Parse for ColorInterpolationMethod {
+    fn parse<'i, 't>(
+        _context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i>> {
+        input.expect_ident_matching("in")?;
+        let space = ColorSpace::parse(input)?;
+        let mut hue = HueInterpolationMethod::Shorter;
+        if space.hue_index().is_some() {
+            if let Ok(method) = input.try(|i| -> Result<_, ParseError<'i>> {
+                let method = HueInterpolationMethod::parse(i)?;
+                i.expect_ident_matching("hue")?;
+                Ok(method)
+            }) {
+                hue = method;
+            }
+        }
+        Ok(ColorInterpolationMethod { space, hue })
+    }
+}
+
+impl ToCss for ColorInterpolationMethod {
+    fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
+    where
+        W: Write,
+    {
+        dest.write_str("in ")?;
+        self.space.to_css(dest)?;
+        if self.hue != HueInterpolationMethod::Shorter {
+            dest.write_char(' ')?;
+            self.hue.to_css(dest)?;
+            dest.write_str(" hue")?;
+        }
+        Ok(())
+    }
+}
+
+/// Mixes two colors with the given method, `left_weight` and `right_weight`
+/// being their contributions to the result. The weights usually add up to
+/// one, but not when adding or accumulating animations.
+///
+/// <https://drafts.csswg.org/css-color-5/#color-mix-result>
+pub fn mix(
+    method: &ColorInterpolationMethod,
+    left: &AbsoluteColor,
+    left_weight: f32,
+    right: &AbsoluteColor,
+    right_weight: f32,
+) -> AbsoluteColor {
+    let space = method.space;
+    let left_color = left.to_color_space(space);
+    let right_color = right.to_color_space(space);
+    let mut left_components = left_color.components.to_array();
+    let mut right_components = right_color.components.to_array();
+    let mut left_alpha = left_color.alpha;
+    let mut right_alpha = right_color.alpha;
+    let mut flags = ColorFlags::empty();
+
+    // Missing components take the value of the other color.
+    // https://drafts.csswg.org/css-color-4/#interpolation-missing
+    for index in 0..3 {
+        let flag = ColorFlags::component_is_none(index);
+        match (
+            left_color.flags.contains(flag),
+            right_color.flags.contains(flag),
+        ) {
+            (true, true) => flags.insert(flag),
+            (true, false) => left_components[index] = right_components[index],
+            (false, true) => right_components[index] = left_components[index],
+            (false, false) => {},
+        }
+    }
+    match (
+        left_color.flags.contains(ColorFlags::ALPHA_IS_NONE),
+        right_color.flags.contains(ColorFlags::ALPHA_IS_NONE),
+    ) {
+        (true, true) => flags.insert(ColorFlags::ALPHA_IS_NONE),
+        (true, false) => left_alpha = right_alpha,
+        (false, true) => right_alpha = left_alpha,
+        (false, false) => {},
+    }
+
+    let hue_index = space.hue_index();
+    if let Some(index) = hue_index {
+        let (left_hue, right_hue) =
+            fixup_hues(method.hue, left_components[index], right_components[index]);
+        left_components[index] = left_hue;
+        right_components[index] = right_hue;
+    }
+
+    // Interpolate with premultiplied alpha, except for the hue.
+    // https://drafts.csswg.org/css-color-4/#interpolation-alpha
+    let alpha = left_alpha * left_weight + right_alpha * right_weight;
+    let mut components = [0.; 3];
+    for index in 0..3 {
+        if hue_index == Some(index) {
+            components[index] = normalize_hue(
+                left_components[index] * left_weight + right_components[index] * right_weight,
+            );
+            continue;
+        }
+        if alpha <= 0. {
+            continue;
+        }
+        let premultiplied = left_components[index] * left_alpha * left_weight +
+            right_components[index] * right_alpha * right_weight;
+        components[index] = premultiplied / alpha;
+    }
+
+    let mut result = AbsoluteColor {
+        components: components.into(),
+        alpha: alpha.max(0.).min(1.),
+        color_space: space,
+        flags,
+    };
+    // Colors in these spaces serialize as rgb().
+    if let ColorSpace::Hsl | ColorSpace::Hwb = space {
+        result = result.to_color_space(ColorSpace::Srgb);
+        result.flags.insert(ColorFlags::IS_LEGACY_SRGB);
+    }
+    result
+}
+
+/// Adjusts two hues so that interpolating linearly between them follows the
+/// given method.
+///
+/// <https://drafts.csswg.org/css-color-4/#hue-interpolation>
+fn fixup_hues(method: HueInterpolationMethod, left: f32, right: f32) -> (f32, f32) {
+    let mut left = normalize_hue(left);
+    let mut right = normalize_hue(right);
+    let delta = right - left;
+    match method {
+        HueInterpolationMethod::Shorter => {
+            if delta > 180. {
+                left += 360.;
+            } else if delta < -180. {
+                right += 360.;
+            }
+        },
+        HueInterpolationMethod::Longer => {
+            if 0. < delta && delta < 180. {
+                left += 360.;
+            } else if -180. < delta && delta <= 0. {
+                right += 360.;
+            }
+        },
+        HueInterpolationMethod::Increasing => {
+            if right < left {
+                right += 360.;
+            }
+        },
+        HueInterpolationMethod::Decreasing => {
+            if left < right {
+                left += 360.;
+            }
+        },
+    }
+    (left, right)
+}
@@ -0,0 +1,336 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Colors in the color spaces of CSS Color 4, and their conversion to sRGB
+//! for rendering.
+//!
+//! <https://drafts.csswg.org/css-color-4/>
+
+pub mod convert;
+pub mod mix;
+
+use cssparser::RGBA;
+use std::fmt::{self, Write};
+use style_traits::{CssWriter, ToCss};
+
+/// A color space, in which the components of an `AbsoluteColor` are
+/// expressed.
+///
+/// <https://drafts.csswg.org/css-color-4/#color-type>
+#[derive(Clone, Copy, Debug, Eq, MallocSizeOf, Parse, PartialEq, ToCss, ToShmem)]
+#[repr(u8)]
+pub enum ColorSpace {
+    /// <https://drafts.csswg.org/css-color-4/#predefined-sRGB>
+    Srgb,
+    /// <https://drafts.csswg.org/css-color-4/#predefined-sRGB-linear>
+    SrgbLinear,
+    /// <https://drafts.csswg.org/css-color-4/#predefined-display-p3>
+    DisplayP3,
+    /// <https://drafts.csswg.org/css-color-4/#predefined-a98-rgb>
+    A98Rgb,
+    /// <https://drafts.csswg.org/css-color-4/#predefined-prophoto-rgb>
+    ProphotoRgb,
+    /// <https://drafts.csswg.org/css-color-4/#predefined-rec2020>
+    Rec2020,
+    /// <https://drafts.csswg.org/css-color-4/#predefined-xyz>
+    XyzD50,
+    /// <https://drafts.csswg.org/css-color-4/#predefined-xyz>
+    #[parse(aliases = "xyz")]
+    XyzD65,
+    /// <https://drafts.csswg.org/css-color-4/#specifying-lab-lch>
+    Lab,
+    /// <https://drafts.csswg.org/css-color-4/#specifying-lab-lch>
+    Lch,
+    /// <https://drafts.csswg.org/css-color-4/#specifying-oklab-oklch>
+    Oklab,
+    /// <https://drafts.csswg.org/css-color-4/#specifying-oklab-oklch>
+    Oklch,
+    /// Only used as an interpolation space; hsl() colors are stored in sRGB.
+    Hsl,
+    /// Only used as an interpolation space; hwb() colors are stored in sRGB.
+    Hwb,
+}
+
+impl ColorSpace {
+    /// Whether this is one of the color spaces that can be given to the
+    /// color() function.
+    pub fn is_predefined(self) -> bool {
+        !matches!(
+            self,
+            ColorSpace::Lab |
+                ColorSpace::Lch |
+                ColorSpace::Oklab |
+                ColorSpace::Oklch |
+                ColorSpace::Hsl |
+                ColorSpace::Hwb
+        )
+    }
+
+    /// The index of the hue component, for cylindrical color spaces.
+    pub fn hue_index(self) -> Option<usize> {
+        match self {
+            ColorSpace::Hsl | ColorSpace::Hwb => Some(0),
+            ColorSpace::Lch | ColorSpace::Oklch => Some(2),
+            _ => None,
+        }
+    }
+}
+
+bitflags! {
+    /// Flags of an `AbsoluteColor`.
+    #[derive(Default, MallocSizeOf, ToShmem)]
+    #[repr(C)]
+    pub struct ColorFlags: u8 {
+        /// Whether the first component is missing, that is, `none`.
+        const C0_IS_NONE = 1 << 0;
+        /// Whether the second component is missing.
+        const C1_IS_NONE = 1 << 1;
+        /// Whether the third component is missing.
+        const C2_IS_NONE = 1 << 2;
+        /// Whether the alpha component is missing.
+        const ALPHA_IS_NONE = 1 << 3;
+        /// Whether the color was specified with one of the legacy sRGB
+        /// syntaxes, like a hex color or rgb(), so that it serializes as
+        /// rgb() and interpolates in sRGB.
+        const IS_LEGACY_SRGB = 1 << 4;
+    }
+}
+
+impl ColorFlags {
+    /// The flag telling whether the component at the given index is missing.
+    pub fn component_is_none(index: usize) -> Self {
+        match index {
+            0 => ColorFlags::C0_IS_NONE,
+            1 => ColorFlags::C1_IS_NONE,
+            _ => ColorFlags::C2_IS_NONE,
+        }
+    }
+}
+
+/// The three components of a color, whose meaning depends on its color
+/// space.
+#[derive(Clone, Copy, Debug, MallocSizeOf, PartialEq, ToShmem)]
+#[repr(C)]
+pub struct ColorComponents(pub f32, pub f32, pub f32);
+
+impl ColorComponents {
+    /// Returns the components as an array.
+    #[inline]
+    pub fn to_array(self) -> [f32; 3] {
+        [self.0, self.1, self.2]
+    }
+}
+
+impl From<[f32; 3]> for ColorComponents {
+    #[inline]
+    fn from(components: [f32; 3]) -> Self {
+        ColorComponents(components[0], components[1], components[2])
+    }
+}
+
+/// A color in a given color space, which may be outside of the sRGB gamut.
+#[derive(Clone, Copy, Debug, MallocSizeOf, PartialEq, ToShmem)]
+#[repr(C)]
+pub struct AbsoluteColor {
+    /// The components, in `color_space`.
+    pub components: ColorComponents,
+    /// The alpha component, between 0 and 1.
+    pub alpha: f32,
+    /// The color space the components are expressed in.
+    pub color_space: ColorSpace,
+    /// Which components are missing, and whether the color has a legacy
+    /// syntax.
+    pub flags: ColorFlags,
+}
+
+/// The just noticeable difference between two colors in Oklab, used for gamut
+/// mapping.
+const JND: f32 = 0.02;
+
+/// The precision of the chroma reduction when gamut mapping.
+const GAMUT_MAPPING_EPSILON: f32 = 0.0001;
+
+impl AbsoluteColor {
+    /// Returns a color with the given components, in the given color space.
+    pub fn new(color_space: ColorSpace, components: ColorComponents, alpha: f32) -> Self {
+        AbsoluteColor {
+            components,
+            alpha,
+            color_space,
+            flags: ColorFlags::empty(),
+        }
+    }
+
+    /// Returns a transparent color.
+    pub fn transparent() -> Self {
+        RGBA::transparent().into()
+    }
+
+    /// Whether this color was specified with one of the legacy sRGB syntaxes.
+    #[inline]
+    pub fn is_legacy_srgb(&self) -> bool {
+        self.flags.contains(ColorFlags::IS_LEGACY_SRGB)
+    }
+
+    /// Returns this color converted to the given color space. Missing
+    /// components are treated as zero, and are not missing anymore after a
+    /// conversion.
+    pub fn to_color_space(&self, color_space: ColorSpace) -> Self {
+        if self.color_space == color_space {
+            return *self;
+        }
+        let xyz = convert::to_xyz_d65(self.color_space, self.components.to_array());
+        AbsoluteColor {
+            components: convert::from_xyz_d65(color_space, xyz).into(),
+            alpha: self.alpha,
+            color_space,
+            flags: self.flags & ColorFlags::ALPHA_IS_NONE,
+        }
+    }
+
+    /// Returns this color in sRGB, mapped into the sRGB gamut as described in
+    /// <https://drafts.csswg.org/css-color-4/#binsearch>, for rendering.
+    pub fn to_rgba(&self) -> RGBA {
+        let [red, green, blue] = self.to_srgb_gamut_mapped();
+        RGBA::from_floats(red, green, blue, self.alpha)
+    }
+
+    fn to_srgb_gamut_mapped(&self) -> [f32; 3] {
+        let srgb = self.to_color_space(ColorSpace::Srgb).components.to_array();
+        if in_srgb_gamut(srgb) {
+            return srgb;
+        }
+
+        let mut current = self.to_color_space(ColorSpace::Oklch);
+        let lightness = current.components.0;
+        if lightness >= 1. {
+            return [1., 1., 1.];
+        }
+        if lightness <= 0. {
+            return [0., 0., 0.];
+        }
+
+        let clipped = clip(srgb);
+        if delta_eok(clipped, &current) < JND {
+            return clipped;
+        }
+
+        let mut min = 0.;
+        let mut max = current.components.1;
+        let mut min_in_gamut = true;
+        while max - min > GAMUT_MAPPING_EPSILON {
+            let chroma = (min + max) / 2.;
+            current.components.1 = chroma;
+            let srgb = current
+                .to_color_space(ColorSpace::Srgb)
+                .components
+                .to_array();
+            if min_in_gamut && in_srgb_gamut(srgb) {
+                min = chroma;
+                continue;
+            }
+            let clipped = clip(srgb);
+            let error = delta_eok(clipped, &current);
+            if error < JND {
+                if JND - error < GAMUT_MAPPING_EPSILON {
+                    return clipped;
+                }
+                min_in_gamut = false;
+                min = chroma;
+            } else {
+                max = chroma;
+            }
+        }
+        clip(
+            current
+                .to_color_space(ColorSpace::Srgb)
+                .components
+                .to_array(),
+        )
+    }
+}
+
+fn in_srgb_gamut(srgb: [f32; 3]) -> bool {
+    // Allow for some imprecision in the conversions.
+    const EPSILON: f32 = 0.000_1;
+    srgb.iter()
+        .all(|component| *component >= -EPSILON && *component <= 1. + EPSILON)
+}
+
+fn clip(srgb: [f32; 3]) -> [f32; 3] {
+    [
+        srgb[0].max(0.).min(1.),
+        srgb[1].max(0.).min(1.),
+        srgb[2].max(0.).min(1.),
+    ]
+}
+
+/// The distance between a color in sRGB and another one, in Oklab.
+///
+/// <https://drafts.csswg.org/css-color-4/#color-difference-OK>
+fn delta_eok(srgb: [f32; 3], other: &AbsoluteColor) -> f32 {
+    let [l1, a1, b1] = AbsoluteColor::new(ColorSpace::Srgb, srgb.into(), 1.)
+        .to_color_space(ColorSpace::Oklab)
+        .components
+        .to_array();
+    let [l2, a2, b2] = other
+        .to_color_space(ColorSpace::Oklab)
+        .components
+        .to_array();
+    ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+}
+
+impl From<RGBA> for AbsoluteColor {
+    fn from(rgba: RGBA) -> Self {
+        AbsoluteColor {
+            components: ColorComponents(rgba.red_f32(), rgba.green_f32(), rgba.blue_f32()),
+            alpha: rgba.alpha_f32(),
+            color_space: ColorSpace::Srgb,
+            flags: ColorFlags::IS_LEGACY_SRGB,
+        }
+    }
+}
+
+impl ToCss for AbsoluteColor {
+    fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
+    where
+        W: Write,
+    {
+        let function = match self.color_space {
+            _ if self.is_legacy_srgb() => return self.to_rgba().to_css(dest),
+            // These are never stored, see `ColorSpace`.
+            ColorSpace::Hsl | ColorSpace::Hwb => return self.to_rgba().to_css(dest),
+            ColorSpace::Lab => "lab(",
+            ColorSpace::Lch => "lch(",
+            ColorSpace::Oklab => "oklab(",
+            ColorSpace::Oklch => "oklch(",
+            _ => "color(",
+        };
+        dest.write_str(function)?;
+        if self.color_space.is_predefined() {
+            self.color_space.to_css(dest)?;
+            dest.write_char(' ')?;
+        }
+
+        let components = self.components.to_array();
+        for (index, component) in components.iter().enumerate() {
+            if index != 0 {
+                dest.write_char(' ')?;
+            }
+            if self.flags.contains(ColorFlags::component_is_none(index)) {
+                dest.write_str("none")?;
+            } else {
+                component.to_css(dest)?;
+            }
+        }
+
+        if self.flags.contains(ColorFlags::ALPHA_IS_NONE) {
+            dest.write_str(" / none")?;
+        } else if self.alpha != 1. {
+            dest.write_str(" / ")?;
+            self.alpha.to_css(dest)?;
+        }
+        dest.write_char(')')
+    }
+}
@@ -11,6 +11,8 @@ use crate::media_queries::Device;
 use crate::properties::{CSSWideKeyword, CustomDeclaration, CustomDeclarationValue};
 use crate::selector_map::{PrecomputedHashMap, PrecomputedHashSet, PrecomputedHasher};
 use crate::stylesheets::{Origin, PerOrigin};
+use crate::stylist::Stylist;
+use crate::values::computed;
 use crate::Atom;
 use cssparser::{
     CowRcStr, Delimiter, Parser, ParserInput, SourcePosition, Token, TokenSerializationType,
@@ -234,6 +236,19 @@ impl VariableValue {
         )
     }
 
+    /// Returns the CSS text of this value.
+    #[inline]
+    pub fn css_text(&self) -> &str {
+        &self.css
+    }
+
+    /// Whether this value references other custom properties or environment
+    /// variables, which need to be substituted before using it.
+    #[inline]
+    pub fn has_references(&self) -> bool {
+        self.references_environment || !self.references.is_empty()
+    }
+
     /// Parse a custom property value.
     pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Arc<Self>, ParseError<'i>> {
         let mut references = VarOrEnvReferences::default();
@@ -522,22 +537,31 @@ pub struct CustomPropertiesBuilder<'a> {
     may_have_cycles: bool,
     custom_properties: Option<CustomPropertiesMap>,
     inherited: Option<&'a Arc<CustomPropertiesMap>>,
-    device: &'a Device,
+    stylist: &'a Stylist,
 }
 
 impl<'a> CustomPropertiesBuilder<'a> {
     /// Create a new builder, inheriting from a given custom properties map.
-    pub fn new(inherited: Option<&'a Arc<CustomPropertiesMap>>, device: &'a Device) -> Self {
+    pub fn new(inherited: Option<&'a Arc<CustomPropertiesMap>>, stylist: &'a Stylist) -> Self {
         Self {
             seen: PrecomputedHashSet::default(),
             reverted: Default::default(),
             may_have_cycles: false,
             custom_properties: None,
             inherited,
-            device,
+            stylist,
         }
     }
 
+    fn custom_properties_mut(&mut self) -> &mut CustomPropertiesMap {
+        let inherited = self.inherited;
+        self.custom_properties
+            .get_or_insert_with(|| match inherited {
+                Some(inherited) => (**inherited).clone(),
+                None => CustomPropertiesMap::default(),
+            })
+    }
+
     /// Cascade a given custom property declaration.
     pub fn cascade(&mut self, declaration: &'a CustomDeclaration, origin: Origin) {
         let CustomDeclaration {
@@ -558,14 +582,10 @@ impl<'a> CustomPropertiesBuilder<'a> {
             return;
         }
 
-        if self.custom_properties.is_none() {
-            self.custom_properties = Some(match self.inherited {
-                Some(inherited) => (**inherited).clone(),
-                None => CustomPropertiesMap::default(),
-            });
-        }
-
-        let map = self.custom_properties.as_mut().unwrap();
+        let stylist = self.stylist;
+        let registration = stylist.get_custom_property_registration(name);
+        let inherited = self.inherited;
+        let map = self.custom_properties_mut();
         match *value {
             CustomDeclarationValue::Value(ref unparsed_value) => {
                 let has_references = !unparsed_value.references.is_empty();
@@ -575,7 +595,8 @@ impl<'a> CustomPropertiesBuilder<'a> {
                 // environment variable here, perform substitution here instead
                 // of forcing a full traversal in `substitute_all` afterwards.
                 let value = if !has_references && unparsed_value.references_environment {
-                    let result = substitute_references_in_value(unparsed_value, &map, &self.device);
+                    let result =
+                        substitute_references_in_value(unparsed_value, &map, stylist.device());
                     match result {
                         Ok(new_value) => Arc::new(new_value),
                         Err(..) => {
@@ -596,24 +617,44 @@ impl<'a> CustomPropertiesBuilder<'a> {
                         self.reverted.borrow_mut_for_origin(&origin).insert(name);
                     }
                 },
-                CSSWideKeyword::Initial => {
-                    map.remove(name);
+                // `unset` only gets here for registered properties that don't
+                // inherit.
+                CSSWideKeyword::Initial | CSSWideKeyword::Unset => {
+                    match registration.and_then(|r| r.initial_value.as_ref()) {
+                        Some(initial_value) => {
+                            map.insert(name.clone(), initial_value.clone());
+                        },
+                        None => {
+                            map.remove(name);
+                        },
+                    }
+                },
+                // `inherit` only gets here for registered properties that
+                // don't inherit.
+                CSSWideKeyword::Inherit => match inherited.and_then(|m| m.get(name)) {
+                    Some(inherited_value) => {
+                        map.insert(name.clone(), inherited_value.clone());
+                    },
+                    None => {
+                        map.remove(name);
+                    },
                 },
-                // handled in value_may_affect_style
-                CSSWideKeyword::Unset | CSSWideKeyword::Inherit => unreachable!(),
             },
         }
     }
 
     fn value_may_affect_style(&self, name: &Name, value: &CustomDeclarationValue) -> bool {
+        let registration = self.stylist.get_custom_property_registration(name);
         match *value {
             CustomDeclarationValue::CSSWideKeyword(CSSWideKeyword::Unset) |
-            CustomDeclarationValue::CSSWideKeyword(CSSWideKeyword::Inherit) => {
+            CustomDeclarationValue::CSSWideKeyword(CSSWideKeyword::Inherit)
+                if registration.map_or(true, |r| r.inherits) =>
+            {
                 // Custom properties are inherited by default. So
                 // explicit 'inherit' or 'unset' means we can just use
                 // any existing value in the inherited CustomPropertiesMap.
                 return false;
-            },
+            }
             _ => {},
         }
 
@@ -624,11 +665,13 @@ impl<'a> CustomPropertiesBuilder<'a> {
             .or_else(|| self.inherited.and_then(|m| m.get(name)));
 
         match (existing_value, value) {
-            (None, &CustomDeclarationValue::CSSWideKeyword(CSSWideKeyword::Initial)) => {
-                // The initial value of a custom property is the same as it
-                // not existing in the map.
+            (None, &CustomDeclarationValue::CSSWideKeyword(CSSWideKeyword::Initial))
+                if registration.map_or(true, |r| r.initial_value.is_none()) =>
+            {
+                // The initial value of an unregistered custom property is the
+                // same as it not existing in the map.
                 return false;
-            },
+            }
             (Some(existing_value), &CustomDeclarationValue::Value(ref value)) => {
                 // Don't bother overwriting an existing inherited value with
                 // the same specified value.
@@ -649,16 +692,123 @@ impl<'a> CustomPropertiesBuilder<'a> {
     ///
     /// Otherwise, just use the inherited custom properties map.
     pub fn build(mut self) -> Option<Arc<CustomPropertiesMap>> {
+        self.apply_registrations();
         let mut map = match self.custom_properties.take() {
             Some(m) => m,
             None => return self.inherited.cloned(),
         };
         if self.may_have_cycles {
             let inherited = self.inherited.as_ref().map(|m| &***m);
-            substitute_all(&mut map, inherited, self.device);
+            substitute_all(&mut map, inherited, self.stylist);
         }
         Some(Arc::new(map))
     }
+
+    /// Gives the registered custom properties that weren't specified their
+    /// initial value, if they don't inherit, or if there's nothing to inherit.
+    fn apply_registrations(&mut self) {
+        let stylist = self.stylist;
+        for (name, registration) in stylist.custom_property_registrations().iter() {
+            if self.seen.contains(name) {
+                continue;
+            }
+            let initial_value = registration.initial_value.as_ref();
+            let needs_initial_value = {
+                let current_value = match self.custom_properties {
+                    Some(ref map) => map.get(name),
+                    None => self.inherited.and_then(|m| m.get(name)),
+                };
+                match (current_value, initial_value) {
+                    (Some(current_value), Some(initial_value)) => {
+                        !registration.inherits && !Arc::ptr_eq(current_value, initial_value)
+                    },
+                    (Some(_), None) => !registration.inherits,
+                    (None, Some(_)) => true,
+                    (None, None) => false,
+                }
+            };
+            if !needs_initial_value {
+                continue;
+            }
+            let map = self.custom_properties_mut();
+            match initial_value {
+                Some(initial_value) => {
+                    map.insert(name.clone(), initial_value.clone());
+                },
+                None => {
+                    map.remove(name);
+                },
+            }
+        }
+    }
+}
+
+/// Returns the value a custom property takes when it's invalid at
+/// computed-value time, which is the same as if it was `unset`.
+fn unset_value(
+    name: &Name,
+    inherited: Option<&CustomPropertiesMap>,
+    stylist: &Stylist,
+) -> Option<Arc<VariableValue>> {
+    match stylist.get_custom_property_registration(name) {
+        Some(registration) if !registration.inherits => registration.initial_value.clone(),
+        _ => inherited.and_then(|m| m.get(name)).cloned(),
+    }
+}
+
+/// Computes the values of the registered custom properties in the given
+/// map, after `var()` substitution, so that they're inherited and
+/// substituted as computed values.
+///
+/// Returns the new map if any value changed.
+///
+/// FIXME: Early properties, like `font-size`, still see the specified values
+/// of registered custom properties in `var()` functions, since these values
+/// depend on them.
+pub fn compute_registered_custom_properties(
+    map: &Arc<CustomPropertiesMap>,
+    inherited: Option<&Arc<CustomPropertiesMap>>,
+    stylist: &Stylist,
+    context: &computed::Context,
+) -> Option<Arc<CustomPropertiesMap>> {
+    let mut computed_map = None;
+    for (name, registration) in stylist.custom_property_registrations().iter() {
+        if registration.syntax.is_universal() {
+            continue;
+        }
+        let initial_value = registration.initial_value.as_ref();
+        let inherited_value = if registration.inherits {
+            inherited.and_then(|m| m.get(name))
+        } else {
+            None
+        };
+        let computed_value = match map.get(name) {
+            // These are already computed.
+            Some(value)
+                if initial_value.map_or(false, |v| Arc::ptr_eq(v, value)) ||
+                    inherited_value.map_or(false, |v| Arc::ptr_eq(v, value)) =>
+            {
+                continue;
+            }
+            // Values that don't match the syntax are invalid at computed-value
+            // time.
+            Some(value) => match registration.compute(value, context) {
+                Ok(computed_value) => computed_value,
+                Err(()) => match inherited_value.or(initial_value) {
+                    Some(value) => value.clone(),
+                    None => continue,
+                },
+            },
+            None => match initial_value {
+                Some(initial_value) => initial_value.clone(),
+                None => continue,
+            },
+        };
+        computed_map
+            .get_or_insert_with(|| (**map).clone())
+            .insert(name.clone(), computed_value);
+    }
+    computed_map.map(Arc::new)
 }
 
 /// Resolve all custom properties to either substituted, invalid, or unset
@@ -668,7 +818,7 @@ impl<'a> CustomPropertiesBuilder<'a> {
 fn substitute_all(
     custom_properties_map: &mut CustomPropertiesMap,
     inherited: Option<&CustomPropertiesMap>,
-    device: &Device,
+    stylist: &Stylist,
 ) {
     // The cycle dependencies removal in this function is a variant
     // of Tarjan's algorithm. It is mostly based on the pseudo-code
@@ -708,8 +858,9 @@ fn substitute_all(
         /// The inherited variables. We may need to restore some if we fail
         /// substitution.
         inherited: Option<&'a CustomPropertiesMap>,
-        /// to resolve the environment to substitute `env()` variables.
-        device: &'a Device,
+        /// The stylist, to resolve the environment to substitute `env()`
+        /// variables, and to look up registered custom properties.
+        stylist: &'a Stylist,
     }
 
     /// This function combines the traversal for cycle removal and value
@@ -844,15 +995,14 @@ fn substitute_all(
         // Now we have shown that this variable is not in a loop, and all of its
         // dependencies should have been resolved. We can start substitution
         // now.
-        let result = substitute_references_in_value(&value, &context.map, &context.device);
+        let result = substitute_references_in_value(&value, &context.map, context.stylist.device());
         match result {
             Ok(computed_value) => {
                 context.map.insert(name, Arc::new(computed_value));
             },
             Err(..) => {
-                // This is invalid, reset it to the unset (inherited) value.
-                let inherited = context.inherited.and_then(|m| m.get(&name)).cloned();
-                match inherited {
+                // This is invalid, reset it to the unset value.
+                match unset_value(&name, context.inherited, context.stylist) {
                     Some(computed_value) => {
                         context.map.insert(name, computed_value);
                     },
@@ -878,7 +1028,7 @@ fn substitute_all(
             var_info: SmallVec::new(),
             map: custom_properties_map,
             inherited,
-            device,
+            stylist,
         };
         traverse(name, &mut context);
     }
@@ -19,6 +19,7 @@ use crate::media_queries::Device;
 use crate::properties::{AnimationRules, ComputedValues, PropertyDeclarationBlock};
 use crate::selector_parser::{AttrValue, Lang, PseudoElement, SelectorImpl};
 use crate::shared_lock::Locked;
+use crate::stylesheets::container_rule::ContainerSize;
 use crate::stylist::CascadeData;
 use crate::traversal_flags::TraversalFlags;
 use crate::{Atom, LocalName, Namespace, WeakAtom};
@@ -536,7 +537,7 @@ pub trait TElement:
     /// `::-first-line` or `::-first-letter`, when we know it won't affect us.
     ///
     /// TODO(emilio, bz): actually implement the logic for it.
-    fn may_generate_pseudo(&self, pseudo: &PseudoElement, _primary_style: &ComputedValues) -> bool {
+    fn may_generate_pseudo(&self, pseudo: &PseudoElement, primary_style: &ComputedValues) -> bool {
         // ::before/::after are always supported for now, though we could try to
         // optimize out leaf elements.
 
@@ -549,6 +550,12 @@ pub trait TElement:
             pseudo.is_eager(),
             "Someone called may_generate_pseudo with a non-eager pseudo."
         );
+
+        // In Servo, ::marker is only generated for list items, so don't bother
+        // matching it for other elements.
+        if cfg!(feature = "servo") && pseudo.is_marker() {
+            return primary_style.get_box().clone_display().is_list_item();
+        }
         true
     }
 
@@ -906,6 +913,17 @@ pub trait TElement:
     /// document it is on.
     fn is_html_document_body_element(&self) -> bool;
 
+    /// Returns the size, as of the last layout, of the nearest ancestor of
+    /// this element that is a query container named `name`, or of any query
+    /// container if `name` is `None`.
+    ///
+    /// Query containers are sized by layout, so after a layout changes their
+    /// size, the layout engine is responsible for restyling their
+    /// descendants.
+    fn query_container_size(&self, _name: Option<&Atom>) -> Option<ContainerSize> {
+        None
+    }
+
     /// Generate the proper applicable declarations due to presentational hints,
     /// and insert them into `hints`.
     fn synthesize_presentational_hints_for_legacy_attributes<V>(
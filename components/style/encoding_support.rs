@@ -50,6 +50,16 @@ fn decode_stylesheet_bytes<'a>(
 }
 
 impl Stylesheet {
+    /// Decode the bytes of a stylesheet received over the network, the way `from_bytes`
+    /// does before parsing them.
+    pub fn decode_bytes<'a>(
+        bytes: &'a [u8],
+        protocol_encoding_label: Option<&str>,
+        environment_encoding: Option<&'static encoding_rs::Encoding>,
+    ) -> Cow<'a, str> {
+        decode_stylesheet_bytes(bytes, protocol_encoding_label, environment_encoding)
+    }
+
     /// Parse a stylesheet from a set of bytes, potentially received over the
     /// network.
     ///
@@ -6,6 +6,7 @@
 
 #![deny(missing_docs)]
 
+use crate::properties_and_values::registry::PropertyRegistrationError;
 use crate::selector_parser::SelectorImpl;
 use crate::stylesheets::UrlExtraData;
 use cssparser::{BasicParseErrorKind, ParseErrorKind, SourceLocation, Token};
@@ -52,6 +53,14 @@ pub enum ContextualParseError<'a> {
     InvalidCounterStyleExtendsWithAdditiveSymbols,
     /// A media rule was invalid for some reason.
     InvalidMediaRule(&'a str, ParseError<'a>),
+    /// A property descriptor declaration was not recognized.
+    UnsupportedPropertyDescriptor(&'a str, ParseError<'a>),
+    /// A property rule had no syntax.
+    InvalidPropertyRuleWithoutSyntax,
+    /// A property rule had no inherits.
+    InvalidPropertyRuleWithoutInherits,
+    /// A property rule had an invalid initial value.
+    InvalidPropertyRuleInitialValue(PropertyRegistrationError),
     /// A value was not recognized.
     UnsupportedValue(&'a str, ParseError<'a>),
 }
@@ -209,6 +218,32 @@ impl<'a> fmt::Display for ContextualParseError<'a> {
                 write!(f, "Invalid media rule: {}, ", media_rule)?;
                 parse_error_to_str(err, f)
             },
+            ContextualParseError::UnsupportedPropertyDescriptor(decl, ref err) => {
+                write!(
+                    f,
+                    "Unsupported @property descriptor declaration: '{}', ",
+                    decl
+                )?;
+                parse_error_to_str(err, f)
+            },
+            ContextualParseError::InvalidPropertyRuleWithoutSyntax => {
+                write!(f, "Invalid @property rule: missing 'syntax'")
+            },
+            ContextualParseError::InvalidPropertyRuleWithoutInherits => {
+                write!(f, "Invalid @property rule: missing 'inherits'")
+            },
+            ContextualParseError::InvalidPropertyRuleInitialValue(error) => {
+                let reason = match error {
+                    PropertyRegistrationError::NoInitialValue => "missing 'initial-value'",
+                    PropertyRegistrationError::InvalidInitialValue => {
+                        "'initial-value' doesn't match 'syntax'"
+                    },
+                    PropertyRegistrationError::InitialValueNotComputationallyIndependent => {
+                        "'initial-value' is not computationally independent"
+                    },
+                };
+                write!(f, "Invalid @property rule: {}", reason)
+            },
             ContextualParseError::UnsupportedValue(_value, ref err) => parse_error_to_str(err, f),
         }
     }
@@ -808,7 +808,10 @@ impl<'le> GeckoElement<'le> {
             let end_value = AnimationValue::arc_from_borrowed(&raw_end_value)
                 .expect("AnimationValue not found in ElementTransitions");
 
-            let property = end_value.id();
+            let property = end_value
+                .id()
+                .as_longhand()
+                .expect("Only longhands are transitioned in Gecko");
             debug_assert!(!property.is_logical());
             map.insert(property, end_value.clone_arc());
         }
@@ -1893,6 +1896,18 @@ impl<'le> ::selectors::Element for GeckoElement<'le> {
         None
     }
 
+    #[inline]
+    fn first_element_child(&self) -> Option<Self> {
+        let mut child = self.as_node().first_child();
+        while let Some(child_node) = child {
+            if let Some(el) = child_node.as_element() {
+                return Some(el);
+            }
+            child = child_node.next_sibling();
+        }
+        None
+    }
+
     fn attr_matches(
         &self,
         ns: &NamespaceConstraint<&Namespace>,
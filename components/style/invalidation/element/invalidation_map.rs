@@ -6,13 +6,14 @@
 
 use crate::context::QuirksMode;
 use crate::element_state::{DocumentState, ElementState};
+use crate::invalidation::element::relative_selector::RelativeSelectorDependencies;
 use crate::selector_map::{MaybeCaseInsensitiveHashMap, SelectorMap, SelectorMapEntry};
 use crate::selector_parser::SelectorImpl;
 use crate::{Atom, LocalName, Namespace};
 use fallible::FallibleVec;
 use hashglobe::FailedAllocationError;
 use selectors::attr::NamespaceConstraint;
-use selectors::parser::{Combinator, Component};
+use selectors::parser::{Combinator, Component, RelativeSelector};
 use selectors::parser::{Selector, SelectorIter};
 use selectors::visitor::SelectorVisitor;
 use smallvec::SmallVec;
@@ -180,6 +181,9 @@ pub struct InvalidationMap {
     /// A set of flags that contain whether various special attributes are used
     /// in this invalidation map.
     pub flags: InvalidationMapFlags,
+    /// What the arguments of the :has() pseudo-classes in the rules we
+    /// represent depend on.
+    pub relative_selector_dependencies: RelativeSelectorDependencies,
 }
 
 impl InvalidationMap {
@@ -192,6 +196,7 @@ impl InvalidationMap {
             document_state_selectors: Vec::new(),
             other_attribute_affecting_selectors: SelectorMap::new(),
             flags: InvalidationMapFlags::empty(),
+            relative_selector_dependencies: RelativeSelectorDependencies::new(),
         }
     }
 
@@ -216,6 +221,7 @@ impl InvalidationMap {
         self.document_state_selectors.clear();
         self.other_attribute_affecting_selectors.clear();
         self.flags = InvalidationMapFlags::empty();
+        self.relative_selector_dependencies.clear();
     }
 
     /// Adds a selector to this `InvalidationMap`.  Returns Err(..) to
@@ -227,6 +233,9 @@ impl InvalidationMap {
     ) -> Result<(), FailedAllocationError> {
         debug!("InvalidationMap::note_selector({:?})", selector);
 
+        self.relative_selector_dependencies
+            .note_selector(selector, quirks_mode)?;
+
         let mut iter = selector.iter();
         let mut combinator;
         let mut index = 0;
@@ -367,6 +376,12 @@ struct CompoundSelectorDependencyCollector<'a> {
 impl<'a> SelectorVisitor for CompoundSelectorDependencyCollector<'a> {
     type Impl = SelectorImpl;
 
+    fn visit_relative_selector_list(&mut self, _list: &[RelativeSelector<SelectorImpl>]) -> bool {
+        // Changes to the elements the arguments of :has() match invalidate
+        // the anchor instead, see relative_selector.rs.
+        true
+    }
+
     fn visit_simple_selector(&mut self, s: &Component<SelectorImpl>) -> bool {
         #[cfg(feature = "gecko")]
         use crate::selector_parser::NonTSPseudoClass;
@@ -0,0 +1,351 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Invalidation of the anchors of relative selectors, that is, of the elements
+//! `:has()` is matched against, whose style depends on their descendants and
+//! later siblings.
+//!
+//! The rest of the invalidation machinery runs as part of the traversal, from
+//! an element to its descendants and later siblings, so it can't reach the
+//! anchors, which are ancestors of the element that changed, or earlier
+//! siblings of them. Instead, the anchors are invalidated before the
+//! traversal, looking at the selector flags matching left on them.
+//!
+//! Changes to the children of an element are handled by the DOM, using the
+//! same flags.
+
+use crate::context::QuirksMode;
+use crate::dom::TElement;
+use crate::element_state::ElementState;
+use crate::invalidation::element::element_wrapper::{ElementSnapshot, ElementWrapper};
+use crate::invalidation::element::restyle_hints::RestyleHint;
+use crate::selector_map::MaybeCaseInsensitiveHashMap;
+use crate::selector_parser::{SelectorImpl, SnapshotMap};
+use crate::stylesheets::origin::OriginSet;
+use crate::stylist::{CascadeData, Stylist};
+use crate::{Atom, LocalName, Namespace, WeakAtom};
+use hashglobe::FailedAllocationError;
+use selectors::attr::{CaseSensitivity, NamespaceConstraint};
+use selectors::matching::ElementSelectorFlags;
+use selectors::parser::{Component, RelativeSelector, Selector};
+use selectors::visitor::SelectorVisitor;
+use smallvec::SmallVec;
+
+/// What the relative selectors in a set of stylesheets depend on, in the
+/// elements they match, that may change without a DOM mutation.
+#[derive(Debug, MallocSizeOf)]
+pub struct RelativeSelectorDependencies {
+    /// Whether there are any relative selectors at all.
+    any: bool,
+    /// The classes relative selectors depend on.
+    classes: MaybeCaseInsensitiveHashMap<Atom, ()>,
+    /// The IDs relative selectors depend on.
+    ids: MaybeCaseInsensitiveHashMap<Atom, ()>,
+    /// The states relative selectors depend on.
+    state: ElementState,
+    /// Whether relative selectors depend on attributes other than the class
+    /// and ID.
+    other_attributes: bool,
+}
+
+impl RelativeSelectorDependencies {
+    /// Creates an empty `RelativeSelectorDependencies`.
+    pub fn new() -> Self {
+        Self {
+            any: false,
+            classes: MaybeCaseInsensitiveHashMap::new(),
+            ids: MaybeCaseInsensitiveHashMap::new(),
+            state: ElementState::empty(),
+            other_attributes: false,
+        }
+    }
+
+    /// Clears these dependencies, leaving them empty.
+    pub fn clear(&mut self) {
+        self.any = false;
+        self.classes.clear();
+        self.ids.clear();
+        self.state = ElementState::empty();
+        self.other_attributes = false;
+    }
+
+    /// Adds the dependencies of the relative selectors in the given selector,
+    /// if any. Returns Err(..) to signify OOM.
+    pub fn note_selector(
+        &mut self,
+        selector: &Selector<SelectorImpl>,
+        quirks_mode: QuirksMode,
+    ) -> Result<(), FailedAllocationError> {
+        let mut collector = RelativeSelectorDependencyCollector {
+            dependencies: self,
+            quirks_mode,
+            in_relative_selector: false,
+            result: Ok(()),
+        };
+        selector.visit(&mut collector);
+        collector.result
+    }
+
+    /// Whether the given changes to the classes, ID, other attributes and
+    /// state of an element may change what relative selectors match.
+    fn may_be_affected_by<E>(
+        &self,
+        element: E,
+        wrapper: &ElementWrapper<E>,
+        quirks_mode: QuirksMode,
+    ) -> bool
+    where
+        E: TElement,
+    {
+        if !self.any {
+            return false;
+        }
+
+        let snapshot = match wrapper.snapshot() {
+            Some(snapshot) => snapshot,
+            // The element wasn't styled, so we don't know what changed.
+            None => return true,
+        };
+
+        if self.state.intersects(wrapper.state_changes()) {
+            return true;
+        }
+
+        if !snapshot.has_attrs() {
+            return false;
+        }
+
+        if snapshot.other_attr_changed() && self.other_attributes {
+            return true;
+        }
+
+        if snapshot.id_changed() {
+            let has_id = |id: Option<&WeakAtom>| {
+                id.map_or(false, |id| self.ids.get(id, quirks_mode).is_some())
+            };
+            if has_id(snapshot.id_attr()) || has_id(element.id()) {
+                return true;
+            }
+        }
+
+        if snapshot.class_changed() {
+            let mut affected = false;
+            snapshot.each_class(|class| {
+                affected |= !element.has_class(class, CaseSensitivity::CaseSensitive) &&
+                    self.classes.get(class, quirks_mode).is_some();
+            });
+            element.each_class(|class| {
+                affected |= !snapshot.has_class(class, CaseSensitivity::CaseSensitive) &&
+                    self.classes.get(class, quirks_mode).is_some();
+            });
+            if affected {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// A visitor that collects the dependencies of the relative selectors in a
+/// selector.
+struct RelativeSelectorDependencyCollector<'a> {
+    dependencies: &'a mut RelativeSelectorDependencies,
+    quirks_mode: QuirksMode,
+    /// Whether we're visiting the arguments of a :has() pseudo-class.
+    in_relative_selector: bool,
+    result: Result<(), FailedAllocationError>,
+}
+
+impl<'a> SelectorVisitor for RelativeSelectorDependencyCollector<'a> {
+    type Impl = SelectorImpl;
+
+    fn visit_relative_selector_list(&mut self, list: &[RelativeSelector<SelectorImpl>]) -> bool {
+        self.dependencies.any = true;
+        let in_relative_selector = self.in_relative_selector;
+        self.in_relative_selector = true;
+        for relative in list {
+            if !relative.selector.visit(self) {
+                return false;
+            }
+        }
+        self.in_relative_selector = in_relative_selector;
+        true
+    }
+
+    fn visit_simple_selector(&mut self, s: &Component<SelectorImpl>) -> bool {
+        if !self.in_relative_selector {
+            return true;
+        }
+
+        self.result = match *s {
+            Component::ID(ref id) => self
+                .dependencies
+                .ids
+                .try_entry(id.clone(), self.quirks_mode)
+                .map(|entry| {
+                    entry.or_insert(());
+                }),
+            Component::Class(ref class) => self
+                .dependencies
+                .classes
+                .try_entry(class.clone(), self.quirks_mode)
+                .map(|entry| {
+                    entry.or_insert(());
+                }),
+            Component::NonTSPseudoClass(ref pc) => {
+                self.dependencies.other_attributes |= pc.is_attr_based();
+                self.dependencies.state |= pc.state_flag();
+                Ok(())
+            },
+            _ => Ok(()),
+        };
+        self.result.is_ok()
+    }
+
+    fn visit_attribute_selector(
+        &mut self,
+        _constraint: &NamespaceConstraint<&Namespace>,
+        _local_name: &LocalName,
+        _local_name_lower: &LocalName,
+    ) -> bool {
+        if self.in_relative_selector {
+            self.dependencies.other_attributes = true;
+        }
+        true
+    }
+}
+
+/// Invalidates the style of the anchors of relative selectors that changes to
+/// the state and attributes of the given elements, recorded in their
+/// snapshots, may have made match differently.
+///
+/// Since anchors are ancestors of the elements, or earlier siblings of
+/// those, they may be outside of the subtree rooted at `dirty_root`. Returns
+/// the element the traversal must start at: `dirty_root`, or the root element
+/// if any anchor was invalidated.
+pub fn invalidate_relative_selector_anchors<E>(
+    elements: &[E],
+    dirty_root: E,
+    stylist: &Stylist,
+    snapshots: &SnapshotMap,
+) -> E
+where
+    E: TElement,
+{
+    let mut invalidated = false;
+    for element in elements {
+        let wrapper = ElementWrapper::new(*element, snapshots);
+        if may_affect_relative_selectors(*element, &wrapper, stylist) {
+            invalidated |= invalidate_anchors_of(*element);
+        }
+    }
+
+    if !invalidated {
+        return dirty_root;
+    }
+
+    let mut root = dirty_root;
+    while let Some(parent) = root.traversal_parent() {
+        unsafe { parent.set_dirty_descendants() };
+        root = parent;
+    }
+    root
+}
+
+/// Whether the changes to the given element may change what the relative
+/// selectors in the rules that apply to it and to its anchors match.
+fn may_affect_relative_selectors<E>(
+    element: E,
+    wrapper: &ElementWrapper<E>,
+    stylist: &Stylist,
+) -> bool
+where
+    E: TElement,
+{
+    let quirks_mode = stylist.quirks_mode();
+    let may_be_affected_by = |data: &CascadeData| {
+        data.invalidation_map()
+            .relative_selector_dependencies
+            .may_be_affected_by(element, wrapper, quirks_mode)
+    };
+
+    let mut shadow_rule_datas = SmallVec::<[_; 3]>::new();
+    let matches_document_author_rules = element
+        .each_applicable_non_document_style_rule_data(|data, _| shadow_rule_datas.push(data));
+    if shadow_rule_datas.into_iter().any(may_be_affected_by) {
+        return true;
+    }
+
+    let document_origins = if !matches_document_author_rules {
+        OriginSet::ORIGIN_USER_AGENT | OriginSet::ORIGIN_USER
+    } else {
+        OriginSet::all()
+    };
+    stylist
+        .iter_origins()
+        .any(|(data, origin)| document_origins.contains(origin.into()) && may_be_affected_by(data))
+}
+
+/// Invalidates the anchors whose relative selectors may match the given
+/// element, that is, its ancestors and the earlier siblings of it and of its
+/// ancestors that were flagged as anchors while matching.
+///
+/// Returns whether any anchor was invalidated.
+fn invalidate_anchors_of<E>(element: E) -> bool
+where
+    E: TElement,
+{
+    let mut invalidated = false;
+    let mut current = element;
+    while let Some(parent) = current.parent_element() {
+        if parent.has_selector_flags(ElementSelectorFlags::HAS_SIBLING_RELATIVE_SELECTOR_ANCHORS) {
+            let mut sibling = current.prev_sibling_element();
+            while let Some(anchor) = sibling {
+                if anchor.has_selector_flags(ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR) {
+                    invalidated |= invalidate_anchor(anchor);
+                }
+                sibling = anchor.prev_sibling_element();
+            }
+        }
+        if parent.has_selector_flags(ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR) {
+            invalidated |= invalidate_anchor(parent);
+        }
+        current = parent;
+    }
+    invalidated
+}
+
+/// Restyles the given anchor and its descendants, and also its later siblings
+/// if selectors with sibling combinators were matched against them, and
+/// propagates the dirty bits up to the root.
+fn invalidate_anchor<E>(anchor: E) -> bool
+where
+    E: TElement,
+{
+    match anchor.mutate_data() {
+        Some(mut data) => data.hint.insert(RestyleHint::restyle_subtree()),
+        None => return false,
+    }
+
+    let later_siblings_affected = anchor.parent_element().map_or(false, |parent| {
+        parent.has_selector_flags(ElementSelectorFlags::HAS_SLOW_SELECTOR_LATER_SIBLINGS)
+    });
+    if later_siblings_affected {
+        let mut sibling = anchor.next_sibling_element();
+        while let Some(element) = sibling {
+            if let Some(mut data) = element.mutate_data() {
+                data.hint.insert(RestyleHint::restyle_subtree());
+            }
+            sibling = element.next_sibling_element();
+        }
+    }
+
+    let mut current = anchor.traversal_parent();
+    while let Some(parent) = current {
+        unsafe { parent.set_dirty_descendants() };
+        current = parent.traversal_parent();
+    }
+    true
+}
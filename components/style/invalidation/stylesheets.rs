@@ -14,7 +14,7 @@ use crate::invalidation::element::restyle_hints::RestyleHint;
 use crate::media_queries::Device;
 use crate::selector_parser::{SelectorImpl, Snapshot, SnapshotMap};
 use crate::shared_lock::SharedRwLockReadGuard;
-use crate::stylesheets::{CssRule, StylesheetInDocument};
+use crate::stylesheets::{CssRule, LayerRuleKind, StylesheetInDocument};
 use crate::Atom;
 use crate::CaseSensitivityExt;
 use crate::LocalName as SelectorLocalName;
@@ -442,10 +442,39 @@ impl StylesheetInvalidationSet {
                     }
                 }
             },
-            Document(..) | Namespace(..) | Import(..) | Media(..) | Supports(..) => {
+            Document(..) | Namespace(..) | Media(..) | Supports(..) | Container(..) => {
                 // Do nothing, relevant nested rules are visited as part of the
                 // iteration.
             },
+            Import(ref lock) => {
+                let import_rule = lock.read_with(guard);
+                if import_rule
+                    .layer
+                    .as_ref()
+                    .map_or(false, |layer| layer.name.is_some())
+                {
+                    debug!(" > Found @import into a named layer, marking the whole tree invalid.");
+                    self.fully_invalid = true;
+                }
+            },
+            Layer(ref lock) => match lock.read_with(guard).kind {
+                LayerRuleKind::Block { name: None, .. } => {
+                    // Do nothing, an anonymous layer can't change the order of
+                    // other layers, and its nested rules are visited as part
+                    // of the iteration.
+                },
+                LayerRuleKind::Block { .. } | LayerRuleKind::Statement { .. } => {
+                    // Named layers may reorder the layers of existing rules.
+                    debug!(" > Found named @layer rule, marking the whole tree invalid.");
+                    self.fully_invalid = true;
+                },
+            },
+            Property(..) => {
+                // Registering a custom property changes how it's computed,
+                // and whether it inherits, anywhere in the tree.
+                debug!(" > Found @property rule, marking the whole tree invalid.");
+                self.fully_invalid = true;
+            },
             FontFace(..) => {
                 // Do nothing, @font-face doesn't affect computed style
                 // information. We'll restyle when the font face loads, if
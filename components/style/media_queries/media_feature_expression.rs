@@ -209,7 +209,7 @@ impl ToCss for MediaFeatureExpression {
 }
 
 /// Consumes an operation or a colon, or returns an error.
-fn consume_operation_or_colon(input: &mut Parser) -> Result<Option<Operator>, ()> {
+pub(crate) fn consume_operation_or_colon(input: &mut Parser) -> Result<Option<Operator>, ()> {
     let first_delim = {
         let next_token = match input.next() {
             Ok(t) => t,
@@ -5,11 +5,10 @@
 //! The main cascading algorithm of the style system.
 
 use crate::context::QuirksMode;
-use crate::custom_properties::CustomPropertiesBuilder;
+use crate::custom_properties::{self, CustomPropertiesBuilder};
 use crate::dom::TElement;
 use crate::font_metrics::FontMetricsProvider;
 use crate::logical_geometry::WritingMode;
-use crate::media_queries::Device;
 use crate::properties::{ComputedValues, StyleBuilder};
 use crate::properties::{LonghandId, LonghandIdSet, CSSWideKeyword};
 use crate::properties::{PropertyDeclaration, PropertyDeclarationId, DeclarationImportanceIterator};
@@ -17,6 +16,7 @@ use crate::properties::{CASCADE_PROPERTY, ComputedValueFlags};
 use crate::rule_cache::{RuleCache, RuleCacheConditions};
 use crate::rule_tree::StrongRuleNode;
 use crate::selector_parser::PseudoElement;
+use crate::stylesheets::container_rule::ContainerSizeQuery;
 use crate::stylesheets::{Origin, PerOrigin};
 use servo_arc::Arc;
 use crate::shared_lock::StylesheetGuards;
@@ -25,6 +25,7 @@ use smallvec::SmallVec;
 use std::borrow::Cow;
 use std::cell::RefCell;
 use crate::style_adjuster::StyleAdjuster;
+use crate::stylist::Stylist;
 use crate::values::{computed, specified};
 
 /// We split the cascade in two phases: 'early' properties, and 'late'
@@ -63,7 +64,8 @@ enum ApplyResetProperties {
 ///
 /// The arguments are:
 ///
-///   * `device`: Used to get the initial viewport and other external state.
+///   * `stylist`: Used to get the device, with the initial viewport and other
+///   external state, and the registered custom properties.
 ///
 ///   * `rule_node`: The rule node in the tree that represent the CSS rules that
 ///   matched.
@@ -74,7 +76,7 @@ enum ApplyResetProperties {
 ///   * `flags`: Various flags.
 ///
 pub fn cascade<E>(
-    device: &Device,
+    stylist: &Stylist,
     pseudo: Option<&PseudoElement>,
     rule_node: &StrongRuleNode,
     guards: &StylesheetGuards,
@@ -92,7 +94,7 @@ where
     E: TElement,
 {
     cascade_rules(
-        device,
+        stylist,
         pseudo,
         rule_node,
         guards,
@@ -109,7 +111,7 @@ where
 }
 
 fn cascade_rules<E>(
-    device: &Device,
+    stylist: &Stylist,
     pseudo: Option<&PseudoElement>,
     rule_node: &StrongRuleNode,
     guards: &StylesheetGuards,
@@ -173,7 +175,7 @@ where
     };
 
     apply_declarations(
-        device,
+        stylist,
         pseudo,
         rule_node,
         guards,
@@ -209,7 +211,7 @@ pub enum CascadeMode<'a> {
 /// NOTE: This function expects the declaration with more priority to appear
 /// first.
 pub fn apply_declarations<'a, E, F, I>(
-    device: &Device,
+    stylist: &Stylist,
     pseudo: Option<&PseudoElement>,
     rules: &StrongRuleNode,
     guards: &StylesheetGuards,
@@ -244,13 +246,14 @@ where
             parent_style.unwrap().is_first_line_style()
     );
 
+    let device = stylist.device();
     let inherited_style = parent_style.unwrap_or(device.default_computed_values());
 
     let mut declarations = SmallVec::<[(&_, Origin); 32]>::new();
     let custom_properties = {
         let mut builder = CustomPropertiesBuilder::new(
             inherited_style.custom_properties(),
-            device,
+            stylist,
         );
 
         for (declaration, origin) in iter_declarations() {
@@ -267,6 +270,8 @@ where
     let is_root_element =
         pseudo.is_none() && element.map_or(false, |e| e.is_root());
 
+    let container_size_query = || element.and_then(|e| e.query_container_size(None));
+
     let mut context = computed::Context {
         // We'd really like to own the rules here to avoid refcount traffic, but
         // animation's usage of `apply_declarations` make this tricky. See bug
@@ -287,6 +292,7 @@ where
         font_metrics_provider,
         quirks_mode,
         rule_cache_conditions: RefCell::new(rule_cache_conditions),
+        container_size_query: ContainerSizeQuery::new(&container_size_query),
     };
 
     let using_cached_reset_properties = {
@@ -295,7 +301,10 @@ where
         cascade
             .apply_properties::<EarlyProperties, _>(ApplyResetProperties::Yes, declarations.iter().cloned());
 
+        cascade.compute_registered_custom_properties(stylist, inherited_style);
+
         cascade.compute_visited_style_if_needed(
+            stylist,
             element,
             parent_style,
             parent_style_ignoring_first_line,
@@ -613,8 +622,31 @@ impl<'a, 'b: 'a> Cascade<'a, 'b> {
         self.context.builder.writing_mode = writing_mode;
     }
 
+    /// Computes the values of registered custom properties, now that the
+    /// properties that other properties depend on, like `font-size`, are
+    /// known.
+    fn compute_registered_custom_properties(
+        &mut self,
+        stylist: &Stylist,
+        inherited_style: &ComputedValues,
+    ) {
+        let computed = match self.context.builder.custom_properties {
+            Some(ref map) => custom_properties::compute_registered_custom_properties(
+                map,
+                inherited_style.custom_properties(),
+                stylist,
+                self.context,
+            ),
+            None => return,
+        };
+        if let Some(computed) = computed {
+            self.context.builder.custom_properties = Some(computed);
+        }
+    }
+
     fn compute_visited_style_if_needed<E>(
         &mut self,
+        stylist: &Stylist,
         element: Option<E>,
         parent_style: Option<&ComputedValues>,
         parent_style_ignoring_first_line: Option<&ComputedValues>,
@@ -650,7 +682,7 @@ impl<'a, 'b: 'a> Cascade<'a, 'b> {
         // We could call apply_declarations directly, but that'd cause
         // another instantiation of this function which is not great.
         let style = cascade_rules(
-            self.context.builder.device,
+            stylist,
             self.context.builder.pseudo,
             visited_rules,
             guards,
@@ -84,6 +84,10 @@ bitflags! {
         /// FIXME(emilio): Try to merge this with BORDER_BACKGROUND, see
         /// https://github.com/w3c/csswg-drafts/issues/4777
         const HAS_AUTHOR_SPECIFIED_PADDING = 1 << 14;
+
+        /// Whether any value on our style is relative to the size of a query
+        /// container, with container-relative length units.
+        const USES_CONTAINER_UNITS = 1 << 15;
     }
 }
 
@@ -259,6 +259,17 @@ impl ComputedValuesInner {
     }
     % endfor
 
+    /// Sets the computed value of a custom property, for animations.
+    pub fn set_custom_property(
+        &mut self,
+        name: crate::custom_properties::Name,
+        value: Arc<crate::custom_properties::VariableValue>,
+    ) {
+        let mut map = self.custom_properties.as_ref().map_or_else(Default::default, |m| (**m).clone());
+        map.insert(name, value);
+        self.custom_properties = Some(Arc::new(map));
+    }
+
     /// Gets the raw visited style. Useful for memory reporting.
     pub fn get_raw_visited_style(&self) -> &Option<RawOffsetArc<ComputedValues>> {
         &self.visited_style
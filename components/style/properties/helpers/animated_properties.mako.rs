@@ -11,7 +11,10 @@
 
 #[cfg(feature = "gecko")] use crate::gecko_bindings::structs::nsCSSPropertyID;
 use itertools::{EitherOrBoth, Itertools};
+use crate::custom_properties::Name;
 use crate::properties::{CSSWideKeyword, PropertyDeclaration, NonCustomPropertyIterator};
+use crate::properties::{CustomDeclaration, CustomDeclarationValue, PropertyDeclarationId};
+use crate::properties_and_values::value::CustomAnimatedValue;
 use crate::properties::longhands;
 use crate::properties::longhands::visibility::computed_value::T as Visibility;
 use crate::properties::LonghandId;
@@ -20,6 +23,7 @@ use smallvec::SmallVec;
 use std::ptr;
 use std::mem;
 use crate::hash::FxHashMap;
+use crate::stylist::Stylist;
 use super::ComputedValues;
 use crate::values::animated::{Animate, Procedure, ToAnimatedValue, ToAnimatedZero};
 use crate::values::animated::effects::AnimatedFilter;
@@ -65,9 +69,6 @@ pub type AnimationValueMap = FxHashMap<LonghandId, AnimationValue>;
 /// An enum to represent a single computed value belonging to an animated
 /// property in order to be interpolated with another one. When interpolating,
 /// both values need to belong to the same property.
-///
-/// FIXME: We need to add a path for custom properties, but that's trivial after
-/// this (is a similar path to that of PropertyDeclaration).
 #[cfg_attr(feature = "servo", derive(MallocSizeOf))]
 #[derive(Debug)]
 #[repr(u16)]
@@ -80,6 +81,8 @@ pub enum AnimationValue {
     ${prop.camel_case}(Void),
     % endif
     % endfor
+    /// A registered custom property.
+    Custom(CustomAnimatedValue),
 }
 
 <%
@@ -151,6 +154,7 @@ impl Clone for AnimationValue {
                 % endif
             }
             % endfor
+            Custom(ref value) => Custom(value.clone()),
             _ => unsafe { debug_unreachable!() }
         }
     }
@@ -176,6 +180,11 @@ impl PartialEq for AnimationValue {
                     *this == other_repr.value
                 }
                 % endfor
+                Custom(ref this) => {
+                    let other_repr =
+                        &*(other as *const _ as *const AnimationValueVariantRepr<CustomAnimatedValue>);
+                    *this == other_repr.value
+                }
                 ${" |\n".join("{}(void)".format(prop.camel_case) for prop in unanimated)} => {
                     void::unreachable(void)
                 }
@@ -185,9 +194,12 @@ impl PartialEq for AnimationValue {
 }
 
 impl AnimationValue {
-    /// Returns the longhand id this animated value corresponds to.
+    /// Returns the property id this animated value corresponds to.
     #[inline]
-    pub fn id(&self) -> LonghandId {
+    pub fn id(&self) -> PropertyDeclarationId {
+        if let AnimationValue::Custom(ref value) = *self {
+            return PropertyDeclarationId::Custom(&value.name);
+        }
         let id = unsafe { *(self as *const _ as *const LonghandId) };
         debug_assert_eq!(id, match *self {
             % for prop in data.longhands:
@@ -197,8 +209,9 @@ impl AnimationValue {
             AnimationValue::${prop.camel_case}(void) => void::unreachable(void),
             % endif
             % endfor
+            AnimationValue::Custom(..) => unreachable!(),
         });
-        id
+        PropertyDeclarationId::Longhand(id)
     }
 
     /// "Uncompute" this animation value in order to be used inside the CSS
@@ -238,6 +251,16 @@ impl AnimationValue {
                 % endif
             }
             % endfor
+            Custom(ref animated) => {
+                let value = match animated.value.to_variable_value() {
+                    Ok(value) => CustomDeclarationValue::Value(value),
+                    Err(()) => CustomDeclarationValue::CSSWideKeyword(CSSWideKeyword::Unset),
+                };
+                PropertyDeclaration::Custom(CustomDeclaration {
+                    name: animated.name.clone(),
+                    value,
+                })
+            }
             ${" |\n".join("{}(void)".format(prop.camel_case) for prop in unanimated)} => {
                 void::unreachable(void)
             }
@@ -416,6 +439,20 @@ impl AnimationValue {
         })
     }
 
+    /// Get an AnimationValue for a registered custom property from a given
+    /// computed values, if its syntax is not the universal one.
+    pub fn from_custom_property(
+        name: &Name,
+        style: &ComputedValues,
+        stylist: &Stylist,
+    ) -> Option<Self> {
+        let registration = stylist.get_custom_property_registration(name)?;
+        let value = style.custom_properties()?.get(name)?;
+        registration
+            .to_animated_value(value, stylist.device(), stylist.quirks_mode())
+            .map(AnimationValue::Custom)
+    }
+
     /// Update `style` with the value of this `AnimationValue`.
     ///
     /// SERVO ONLY: This doesn't properly handle things like updating 'em' units
@@ -437,6 +474,11 @@ impl AnimationValue {
             AnimationValue::${prop.camel_case}(..) => unreachable!(),
             % endif
             % endfor
+            AnimationValue::Custom(ref value) => {
+                if let Ok(computed) = value.value.to_variable_value() {
+                    style.set_custom_property(value.name.clone(), computed);
+                }
+            }
         }
     }
 }
@@ -483,6 +525,16 @@ impl Animate for AnimationValue {
                     out.assume_init()
                 }
                 % endfor
+                Custom(ref this) => {
+                    let other_repr =
+                        &*(other as *const _ as *const AnimationValueVariantRepr<CustomAnimatedValue>);
+                    // Values that can't be interpolated, like identifiers, or
+                    // lists of different lengths, animate discretely.
+                    let value = this
+                        .animate(&other_repr.value, procedure)
+                        .or_else(|()| animate_discrete(this, &other_repr.value, procedure))?;
+                    Custom(value)
+                }
                 ${" |\n".join("{}(void)".format(prop.camel_case) for prop in unanimated)} => {
                     void::unreachable(void)
                 }
@@ -92,7 +92,7 @@ ${helpers.predefined_type(
     "vertical-align",
     "VerticalAlign",
     "computed::VerticalAlign::baseline()",
-    engines="gecko servo-2013",
+    engines="gecko servo-2013 servo-2020",
     animation_value_type="ComputedValue",
     spec="https://www.w3.org/TR/CSS2/visudet.html#propdef-vertical-align",
     servo_restyle_damage = "reflow",
@@ -617,6 +617,24 @@ ${helpers.predefined_type(
     enabled_in="chrome",
 )}
 
+${helpers.single_keyword(
+    "container-type",
+    "normal size inline-size",
+    engines="servo-2020",
+    animation_value_type="none",
+    spec="https://drafts.csswg.org/css-contain-3/#container-type",
+    servo_restyle_damage="reflow",
+)}
+
+${helpers.predefined_type(
+    "container-name",
+    "ContainerName",
+    "computed::ContainerName::none()",
+    engines="servo-2020",
+    animation_value_type="none",
+    spec="https://drafts.csswg.org/css-contain-3/#container-name",
+)}
+
 // Non-standard
 ${helpers.predefined_type(
     "-moz-appearance",
@@ -20,7 +20,7 @@ ${helpers.predefined_type(
 ${helpers.predefined_type(
     "counter-increment",
     "CounterIncrement",
-    engines="gecko servo-2013",
+    engines="gecko servo-2013 servo-2020",
     initial_value="Default::default()",
     animation_value_type="discrete",
     spec="https://drafts.csswg.org/css-lists/#propdef-counter-increment",
@@ -30,7 +30,7 @@ ${helpers.predefined_type(
 ${helpers.predefined_type(
     "counter-reset",
     "CounterSetOrReset",
-    engines="gecko servo-2013",
+    engines="gecko servo-2013 servo-2020",
     initial_value="Default::default()",
     animation_value_type="discrete",
     spec="https://drafts.csswg.org/css-lists-3/#propdef-counter-reset",
@@ -72,7 +72,7 @@ ${helpers.predefined_type(
 ${helpers.single_keyword(
     "flex-direction",
     "row row-reverse column column-reverse",
-    engines="gecko servo-2013",
+    engines="gecko servo-2013 servo-2020",
     spec="https://drafts.csswg.org/css-flexbox/#flex-direction-property",
     extra_prefixes="webkit",
     animation_value_type="discrete",
@@ -83,7 +83,7 @@ ${helpers.single_keyword(
 ${helpers.single_keyword(
     "flex-wrap",
     "nowrap wrap wrap-reverse",
-    engines="gecko servo-2013",
+    engines="gecko servo-2013 servo-2020",
     spec="https://drafts.csswg.org/css-flexbox/#flex-wrap-property",
     extra_prefixes="webkit",
     animation_value_type="discrete",
@@ -91,12 +91,12 @@ ${helpers.single_keyword(
     gecko_enum_prefix = "StyleFlexWrap",
 )}
 
-% if engine == "servo-2013":
+% if engine in ["servo-2013", "servo-2020"]:
     // FIXME: Update Servo to support the same Syntax as Gecko.
     ${helpers.single_keyword(
         "justify-content",
         "flex-start stretch flex-end center space-between space-around",
-        engines="servo-2013",
+        engines="servo-2013 servo-2020",
         extra_prefixes="webkit",
         spec="https://drafts.csswg.org/css-align/#propdef-justify-content",
         animation_value_type="discrete",
@@ -121,7 +121,7 @@ ${helpers.single_keyword(
     ${helpers.single_keyword(
         "align-content",
         "stretch flex-start flex-end center space-between space-around",
-        engines="servo-2013",
+        engines="servo-2013 servo-2020",
         extra_prefixes="webkit",
         spec="https://drafts.csswg.org/css-align/#propdef-align-content",
         animation_value_type="discrete",
@@ -132,12 +132,20 @@ ${helpers.single_keyword(
         "align-items",
         "stretch flex-start flex-end center baseline",
         engines="servo-2013 servo-2020",
-        servo_2020_pref="layout.2020.unimplemented",
         extra_prefixes="webkit",
         spec="https://drafts.csswg.org/css-flexbox/#align-items-property",
         animation_value_type="discrete",
         servo_restyle_damage="reflow",
     )}
+
+    ${helpers.single_keyword(
+        "justify-items",
+        "normal stretch start end center",
+        engines="servo-2020",
+        spec="https://drafts.csswg.org/css-align/#propdef-justify-items",
+        animation_value_type="discrete",
+        servo_restyle_damage="reflow",
+    )}
 % endif
 % if engine == "gecko":
     ${helpers.predefined_type(
@@ -183,7 +191,7 @@ ${helpers.predefined_type(
     "flex-grow",
     "NonNegativeNumber",
     "From::from(0.0)",
-    engines="gecko servo-2013",
+    engines="gecko servo-2013 servo-2020",
     spec="https://drafts.csswg.org/css-flexbox/#flex-grow-property",
     extra_prefixes="webkit",
     animation_value_type="NonNegativeNumber",
@@ -194,7 +202,7 @@ ${helpers.predefined_type(
     "flex-shrink",
     "NonNegativeNumber",
     "From::from(1.0)",
-    engines="gecko servo-2013",
+    engines="gecko servo-2013 servo-2020",
     spec="https://drafts.csswg.org/css-flexbox/#flex-shrink-property",
     extra_prefixes="webkit",
     animation_value_type="NonNegativeNumber",
@@ -208,12 +216,20 @@ ${helpers.predefined_type(
         "align-self",
         "auto stretch flex-start flex-end center baseline",
         engines="servo-2013 servo-2020",
-        servo_2020_pref="layout.2020.unimplemented",
         extra_prefixes="webkit",
         spec="https://drafts.csswg.org/css-flexbox/#propdef-align-self",
         animation_value_type="discrete",
         servo_restyle_damage = "reflow",
     )}
+
+    ${helpers.single_keyword(
+        "justify-self",
+        "auto normal stretch start end center",
+        engines="servo-2020",
+        spec="https://drafts.csswg.org/css-align/#justify-self-property",
+        animation_value_type="discrete",
+        servo_restyle_damage="reflow",
+    )}
 % endif
 % if engine == "gecko":
     ${helpers.predefined_type(
@@ -244,7 +260,7 @@ ${helpers.predefined_type(
     "order",
     "Integer",
     "0",
-    engines="gecko servo-2013",
+    engines="gecko servo-2013 servo-2020",
     extra_prefixes="webkit",
     animation_value_type="ComputedValue",
     spec="https://drafts.csswg.org/css-flexbox/#order-property",
@@ -255,7 +271,7 @@ ${helpers.predefined_type(
     "flex-basis",
     "FlexBasis",
     "computed::FlexBasis::auto()",
-    engines="gecko servo-2013",
+    engines="gecko servo-2013 servo-2020",
     spec="https://drafts.csswg.org/css-flexbox/#flex-basis-property",
     extra_prefixes="webkit",
     animation_value_type="FlexBasis",
@@ -346,7 +362,7 @@ ${helpers.predefined_type(
             "grid-%s-%s" % (kind, range),
             "GridLine",
             "Default::default()",
-            engines="gecko",
+            engines="gecko servo-2020",
             animation_value_type="discrete",
             spec="https://drafts.csswg.org/css-grid/#propdef-grid-%s-%s" % (kind, range),
         )}
@@ -356,7 +372,7 @@ ${helpers.predefined_type(
         "grid-auto-%ss" % kind,
         "ImplicitGridTracks",
         "Default::default()",
-        engines="gecko",
+        engines="gecko servo-2020",
         animation_value_type="discrete",
         spec="https://drafts.csswg.org/css-grid/#propdef-grid-auto-%ss" % kind,
     )}
@@ -365,7 +381,7 @@ ${helpers.predefined_type(
         "grid-template-%ss" % kind,
         "GridTemplateComponent",
         "specified::GenericGridTemplateComponent::None",
-        engines="gecko",
+        engines="gecko servo-2020",
         spec="https://drafts.csswg.org/css-grid/#propdef-grid-template-%ss" % kind,
         animation_value_type="ComputedValue",
     )}
@@ -376,7 +392,7 @@ ${helpers.predefined_type(
     "grid-auto-flow",
     "GridAutoFlow",
     "computed::GridAutoFlow::ROW",
-    engines="gecko",
+    engines="gecko servo-2020",
     animation_value_type="discrete",
     spec="https://drafts.csswg.org/css-grid/#propdef-grid-auto-flow",
 )}
@@ -385,7 +401,7 @@ ${helpers.predefined_type(
     "grid-template-areas",
     "GridTemplateAreas",
     "computed::GridTemplateAreas::none()",
-    engines="gecko",
+    engines="gecko servo-2020",
     animation_value_type="discrete",
     spec="https://drafts.csswg.org/css-grid/#propdef-grid-template-areas",
 )}
@@ -394,8 +410,8 @@ ${helpers.predefined_type(
     "column-gap",
     "length::NonNegativeLengthPercentageOrNormal",
     "computed::length::NonNegativeLengthPercentageOrNormal::normal()",
-    engines="gecko servo-2013",
-    alias="grid-column-gap" if engine == "gecko" else "",
+    engines="gecko servo-2013 servo-2020",
+    alias="grid-column-gap" if engine in ["gecko", "servo-2020"] else "",
     extra_prefixes="moz:layout.css.prefixes.columns",
     servo_2013_pref="layout.columns.enabled",
     spec="https://drafts.csswg.org/css-align-3/#propdef-column-gap",
@@ -408,7 +424,7 @@ ${helpers.predefined_type(
     "row-gap",
     "length::NonNegativeLengthPercentageOrNormal",
     "computed::length::NonNegativeLengthPercentageOrNormal::normal()",
-    engines="gecko",
+    engines="gecko servo-2020",
     alias="grid-row-gap",
     spec="https://drafts.csswg.org/css-align-3/#propdef-row-gap",
     animation_value_type="NonNegativeLengthPercentageOrNormal",
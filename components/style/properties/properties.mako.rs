@@ -22,6 +22,7 @@ use std::mem;
 use cssparser::{Parser, RGBA, TokenSerializationType};
 use cssparser::ParserInput;
 #[cfg(feature = "servo")] use euclid::SideOffsets2D;
+use crate::color::AbsoluteColor;
 use crate::context::QuirksMode;
 #[cfg(feature = "gecko")] use crate::gecko_bindings::structs::{self, nsCSSPropertyID};
 #[cfg(feature = "servo")] use crate::logical_geometry::LogicalMargin;
@@ -2344,7 +2345,9 @@ impl PropertyDeclaration {
     pub fn is_animatable(&self) -> bool {
         match self.id() {
             PropertyDeclarationId::Longhand(id) => id.is_animatable(),
-            PropertyDeclarationId::Custom(..) => false,
+            // Whether custom properties are animatable depends on their
+            // registration, which is looked up when animating.
+            PropertyDeclarationId::Custom(..) => true,
         }
     }
 
@@ -3008,6 +3011,16 @@ impl ComputedValues {
         color.to_rgba(self.get_inherited_text().clone_color())
     }
 
+    /// Resolves the currentColor keyword, like `resolve_color`, but keeps
+    /// colors outside of the sRGB gamut as they are.
+    #[inline]
+    pub fn resolve_absolute_color(&self, color: computed::Color) -> AbsoluteColor {
+        match color {
+            computed::Color::Numeric(color) => color,
+            _ => self.resolve_color(color).into(),
+        }
+    }
+
     /// Returns which longhand properties have different values in the two
     /// ComputedValues.
     #[cfg(feature = "gecko_debug")]
@@ -3125,6 +3138,17 @@ impl ComputedValuesInner {
         }
     % endfor
 
+    /// Sets the computed value of a custom property, for animations.
+    pub fn set_custom_property(
+        &mut self,
+        name: crate::custom_properties::Name,
+        value: Arc<crate::custom_properties::VariableValue>,
+    ) {
+        let mut map = self.custom_properties.as_ref().map_or_else(Default::default, |m| (**m).clone());
+        map.insert(name, value);
+        self.custom_properties = Some(Arc::new(map));
+    }
+
     /// Gets a reference to the rule node. Panic if no rule node exists.
     pub fn rules(&self) -> &StrongRuleNode {
         self.rules.as_ref().unwrap()
@@ -0,0 +1,12 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Registered custom properties, with a syntax, an initial value, and whether
+//! they inherit, from `@property` rules or `CSS.registerProperty()`.
+//!
+//! <https://drafts.css-houdini.org/css-properties-values-api-1/>
+
+pub mod registry;
+pub mod syntax;
+pub mod value;
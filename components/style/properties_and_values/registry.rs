@@ -0,0 +1,164 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Registrations of custom properties.
+//!
+//! <https://drafts.css-houdini.org/css-properties-values-api-1/#registered-custom-property>
+
+use super::syntax::Syntax;
+use super::value::{CustomAnimatedValue, SpecifiedValue};
+use crate::context::QuirksMode;
+use crate::custom_properties::{Name, VariableValue};
+use crate::media_queries::Device;
+use crate::parser::ParserContext;
+use crate::selector_map::PrecomputedHashMap;
+use crate::stylesheets::{CssRuleType, Origin, UrlExtraData};
+use crate::values::computed::{Context, ToComputedValue};
+use servo_arc::Arc;
+use style_traits::ParsingMode;
+
+/// The reasons a custom property registration can be invalid.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PropertyRegistrationError {
+    /// The syntax is not the universal one, and there is no initial value.
+    NoInitialValue,
+    /// The initial value doesn't match the syntax.
+    InvalidInitialValue,
+    /// The initial value depends on the element it applies to, like `1em`.
+    InitialValueNotComputationallyIndependent,
+}
+
+/// Parses the initial value of a registration with the given syntax, and
+/// checks that it is valid.
+///
+/// Returns `None` for the universal syntax, whose initial value is optional
+/// and used as-is.
+///
+/// <https://drafts.css-houdini.org/css-properties-values-api-1/#initial-value-descriptor>
+pub fn parse_initial_value(
+    context: &ParserContext,
+    syntax: &Syntax,
+    initial_value: Option<&VariableValue>,
+) -> Result<Option<SpecifiedValue>, PropertyRegistrationError> {
+    if initial_value.map_or(false, VariableValue::has_references) {
+        return Err(PropertyRegistrationError::InitialValueNotComputationallyIndependent);
+    }
+    if syntax.is_universal() {
+        return Ok(None);
+    }
+    let initial_value = initial_value.ok_or(PropertyRegistrationError::NoInitialValue)?;
+    let value = SpecifiedValue::parse_variable_value(context, initial_value, syntax)
+        .map_err(|()| PropertyRegistrationError::InvalidInitialValue)?;
+    if !value.is_computationally_independent() {
+        return Err(PropertyRegistrationError::InitialValueNotComputationallyIndependent);
+    }
+    Ok(Some(value))
+}
+
+/// A registered custom property.
+#[derive(Clone, Debug, MallocSizeOf)]
+pub struct PropertyRegistration {
+    /// The name of the property, without the leading `--`.
+    pub name: Name,
+    /// The syntax the values of the property must match.
+    pub syntax: Syntax,
+    /// Whether the property inherits.
+    pub inherits: bool,
+    /// The computed initial value of the property. Only the universal syntax
+    /// allows not having one.
+    #[ignore_malloc_size_of = "Arc"]
+    pub initial_value: Option<Arc<VariableValue>>,
+    /// The URL values of the property are resolved against.
+    #[ignore_malloc_size_of = "Arc"]
+    pub url_data: UrlExtraData,
+}
+
+impl PropertyRegistration {
+    /// Validates a registration, and computes its initial value.
+    pub fn new(
+        name: Name,
+        syntax: Syntax,
+        inherits: bool,
+        initial_value: Option<Arc<VariableValue>>,
+        url_data: UrlExtraData,
+        device: &Device,
+        quirks_mode: QuirksMode,
+    ) -> Result<Self, PropertyRegistrationError> {
+        let specified_initial_value = {
+            let context = parser_context(&url_data, quirks_mode);
+            parse_initial_value(&context, &syntax, initial_value.as_ref().map(|v| &**v))?
+        };
+        let initial_value = match specified_initial_value {
+            Some(value) => Some(
+                Context::for_media_query_evaluation(device, quirks_mode, |context| {
+                    value.to_computed_value(context)
+                })
+                .to_variable_value()
+                .map_err(|()| PropertyRegistrationError::InvalidInitialValue)?,
+            ),
+            None => initial_value,
+        };
+        Ok(PropertyRegistration {
+            name,
+            syntax,
+            inherits,
+            initial_value,
+            url_data,
+        })
+    }
+
+    fn parse(&self, value: &VariableValue, quirks_mode: QuirksMode) -> Result<SpecifiedValue, ()> {
+        let context = parser_context(&self.url_data, quirks_mode);
+        SpecifiedValue::parse_variable_value(&context, value, &self.syntax)
+    }
+
+    /// Computes the given value of this property, after `var()` substitution.
+    /// Fails if the value doesn't match the syntax of the property, which
+    /// must not be the universal one.
+    pub fn compute(
+        &self,
+        value: &VariableValue,
+        context: &Context,
+    ) -> Result<Arc<VariableValue>, ()> {
+        self.parse(value, context.quirks_mode)?
+            .to_computed_value(context)
+            .to_variable_value()
+    }
+
+    /// Returns the given computed value of this property, for animations, if
+    /// it has a syntax other than the universal one.
+    pub fn to_animated_value(
+        &self,
+        value: &VariableValue,
+        device: &Device,
+        quirks_mode: QuirksMode,
+    ) -> Option<CustomAnimatedValue> {
+        if self.syntax.is_universal() {
+            return None;
+        }
+        let value = self.parse(value, quirks_mode).ok()?;
+        let value = Context::for_media_query_evaluation(device, quirks_mode, |context| {
+            value.to_computed_value(context)
+        });
+        Some(CustomAnimatedValue {
+            name: self.name.clone(),
+            value,
+        })
+    }
+}
+
+fn parser_context(url_data: &UrlExtraData, quirks_mode: QuirksMode) -> ParserContext {
+    ParserContext::new(
+        Origin::Author,
+        url_data,
+        Some(CssRuleType::Style),
+        ParsingMode::DEFAULT,
+        quirks_mode,
+        None,
+        None,
+    )
+}
+
+/// The custom property registrations in effect, by name.
+pub type PropertyRegistrations = PrecomputedHashMap<Name, PropertyRegistration>;
@@ -0,0 +1,245 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The syntax of registered custom properties, as given by the `syntax`
+//! descriptor of `@property` rules.
+//!
+//! <https://drafts.css-houdini.org/css-properties-values-api-1/#syntax-strings>
+
+use crate::values::CustomIdent;
+use crate::Atom;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+use style_traits::{CssWriter, ToCss};
+
+/// A supported data type name, as in `<length>`.
+///
+/// <https://drafts.css-houdini.org/css-properties-values-api-1/#supported-names>
+#[derive(Clone, Copy, Debug, Eq, MallocSizeOf, PartialEq, ToShmem)]
+pub enum DataType {
+    /// `<length>`
+    Length,
+    /// `<number>`
+    Number,
+    /// `<percentage>`
+    Percentage,
+    /// `<length-percentage>`
+    LengthPercentage,
+    /// `<color>`
+    Color,
+    /// `<integer>`
+    Integer,
+    /// `<angle>`
+    Angle,
+    /// `<time>`
+    Time,
+    /// `<custom-ident>`
+    CustomIdent,
+}
+
+impl DataType {
+    fn from_name(name: &str) -> Option<Self> {
+        // Data type names are case-sensitive.
+        Some(match name {
+            "length" => DataType::Length,
+            "number" => DataType::Number,
+            "percentage" => DataType::Percentage,
+            "length-percentage" => DataType::LengthPercentage,
+            "color" => DataType::Color,
+            "integer" => DataType::Integer,
+            "angle" => DataType::Angle,
+            "time" => DataType::Time,
+            "custom-ident" => DataType::CustomIdent,
+            _ => return None,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            DataType::Length => "length",
+            DataType::Number => "number",
+            DataType::Percentage => "percentage",
+            DataType::LengthPercentage => "length-percentage",
+            DataType::Color => "color",
+            DataType::Integer => "integer",
+            DataType::Angle => "angle",
+            DataType::Time => "time",
+            DataType::CustomIdent => "custom-ident",
+        }
+    }
+}
+
+/// What a syntax component matches: a value of a data type, or a given
+/// identifier.
+#[derive(Clone, Debug, MallocSizeOf, PartialEq, ToShmem)]
+pub enum ComponentName {
+    /// A data type name, like `<length>`.
+    DataType(DataType),
+    /// An identifier, which matches itself, case-sensitively.
+    Ident(CustomIdent),
+}
+
+/// A multiplier, which makes a component match a list of values.
+///
+/// <https://drafts.css-houdini.org/css-properties-values-api-1/#multipliers>
+#[derive(Clone, Copy, Debug, Eq, MallocSizeOf, PartialEq, ToComputedValue, ToShmem)]
+pub enum Multiplier {
+    /// `+`: a space-separated list of one or more values.
+    Space,
+    /// `#`: a comma-separated list of one or more values.
+    Comma,
+}
+
+impl Multiplier {
+    /// The separator of the values of lists with this multiplier.
+    pub fn separator(self) -> &'static str {
+        match self {
+            Multiplier::Space => " ",
+            Multiplier::Comma => ", ",
+        }
+    }
+}
+
+/// A syntax component: a name with an optional multiplier.
+///
+/// <https://drafts.css-houdini.org/css-properties-values-api-1/#syntax-component>
+#[derive(Clone, Debug, MallocSizeOf, PartialEq, ToShmem)]
+pub struct Component {
+    /// What the component matches.
+    pub name: ComponentName,
+    /// Whether the component matches a list of values.
+    pub multiplier: Option<Multiplier>,
+}
+
+/// A parsed syntax string.
+///
+/// <https://drafts.css-houdini.org/css-properties-values-api-1/#syntax-definition>
+#[derive(Clone, Debug, MallocSizeOf, PartialEq, ToShmem)]
+pub enum Syntax {
+    /// `*`, which matches any token stream, like unregistered custom
+    /// properties.
+    Universal,
+    /// A list of alternatives, separated by `|` in the syntax string.
+    Components(crate::OwnedSlice<Component>),
+}
+
+impl Syntax {
+    /// Whether this is the universal syntax, whose values are not parsed.
+    #[inline]
+    pub fn is_universal(&self) -> bool {
+        matches!(*self, Syntax::Universal)
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+fn is_name(c: char) -> bool {
+    is_name_start(c) || c.is_ascii_digit() || c == '-'
+}
+
+/// Whether the given string is a valid identifier, without escapes, which is
+/// all a syntax string allows.
+fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    let first_is_valid = match chars.next() {
+        Some('-') => match chars.next() {
+            Some(c) => c == '-' || is_name_start(c),
+            None => false,
+        },
+        Some(c) => is_name_start(c),
+        None => false,
+    };
+    first_is_valid && chars.all(is_name)
+}
+
+fn parse_component(component: &str) -> Result<Component, ()> {
+    let (name, multiplier) = match component.chars().last() {
+        Some('+') => (&component[..component.len() - 1], Some(Multiplier::Space)),
+        Some('#') => (&component[..component.len() - 1], Some(Multiplier::Comma)),
+        _ => (component, None),
+    };
+
+    let name = if name.starts_with('<') && name.ends_with('>') && name.len() > 2 {
+        ComponentName::DataType(DataType::from_name(&name[1..name.len() - 1]).ok_or(())?)
+    } else {
+        if !is_ident(name) {
+            return Err(());
+        }
+        let reserved = match_ignore_ascii_case! { name,
+            "initial" | "inherit" | "unset" | "revert" | "default" => true,
+            _ => false,
+        };
+        if reserved {
+            return Err(());
+        }
+        ComponentName::Ident(CustomIdent(Atom::from(name)))
+    };
+
+    Ok(Component { name, multiplier })
+}
+
+/// <https://drafts.css-houdini.org/css-properties-values-api-1/#consume-syntax-definition>
+impl FromStr for Syntax {
+    type Err = ();
+
+    fn from_str(syntax: &str) -> Result<Self, ()> {
+        let syntax = syntax.trim_matches(|c: char| c.is_ascii_whitespace());
+        if syntax.is_empty() {
+            return Err(());
+        }
+        if syntax == "*" {
+            return Ok(Syntax::Universal);
+        }
+
+        let components = syntax
+            .split('|')
+            .map(|component| {
+                parse_component(component.trim_matches(|c: char| c.is_ascii_whitespace()))
+            })
+            .collect::<Result<Vec<_>, ()>>()?;
+        Ok(Syntax::Components(components.into()))
+    }
+}
+
+impl ToCss for Component {
+    fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
+    where
+        W: Write,
+    {
+        match self.name {
+            ComponentName::DataType(data_type) => {
+                dest.write_char('<')?;
+                dest.write_str(data_type.name())?;
+                dest.write_char('>')?;
+            },
+            ComponentName::Ident(ref ident) => ident.to_css(dest)?,
+        }
+        match self.multiplier {
+            Some(Multiplier::Space) => dest.write_char('+'),
+            Some(Multiplier::Comma) => dest.write_char('#'),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ToCss for Syntax {
+    fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
+    where
+        W: Write,
+    {
+        let components = match *self {
+            Syntax::Universal => return dest.write_char('*'),
+            Syntax::Components(ref components) => components,
+        };
+        for (index, component) in components.iter().enumerate() {
+            if index != 0 {
+                dest.write_str(" | ")?;
+            }
+            component.to_css(dest)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Parsing, computation and interpolation of the values of registered custom
+//! properties.
+//!
+//! <https://drafts.css-houdini.org/css-properties-values-api-1/#calculation-of-computed-values>
+
+use super::syntax::{Component, ComponentName, DataType, Multiplier, Syntax};
+use crate::custom_properties::{Name, VariableValue};
+use crate::parser::{Parse, ParserContext};
+use crate::values::animated::{Animate, Procedure};
+use crate::values::specified::calc::Leaf;
+use crate::values::specified::NoCalcLength;
+use crate::values::{computed, specified, CustomIdent};
+use cssparser::{Parser, ParserInput, Token};
+use servo_arc::Arc;
+use std::fmt::{self, Write};
+use style_traits::{CssWriter, ParseError, StyleParseErrorKind, ToCss};
+
+/// A value of a data type, or an identifier.
+#[derive(Animate, Clone, Debug, MallocSizeOf, PartialEq, ToComputedValue, ToCss)]
+pub enum GenericValueComponent<
+    Length,
+    Number,
+    Percentage,
+    LengthPercentage,
+    Color,
+    Integer,
+    Angle,
+    Time,
+> {
+    /// A `<length>` value.
+    Length(Length),
+    /// A `<number>` value.
+    Number(Number),
+    /// A `<percentage>` value.
+    Percentage(Percentage),
+    /// A `<length-percentage>` value.
+    LengthPercentage(LengthPercentage),
+    /// A `<color>` value.
+    Color(Color),
+    /// An `<integer>` value.
+    Integer(Integer),
+    /// An `<angle>` value.
+    Angle(Angle),
+    /// A `<time>` value.
+    Time(Time),
+    /// A `<custom-ident>` value, or an identifier of the syntax.
+    #[animation(error)]
+    CustomIdent(CustomIdent),
+}
+
+/// A list of values, for a component with a multiplier.
+#[derive(Clone, Debug, MallocSizeOf, PartialEq, ToComputedValue)]
+pub struct GenericValueList<Component> {
+    /// The values.
+    pub components: crate::OwnedSlice<Component>,
+    /// How the values are separated.
+    pub multiplier: Multiplier,
+}
+
+/// A value of a registered custom property.
+#[derive(Animate, Clone, Debug, MallocSizeOf, PartialEq, ToComputedValue, ToCss)]
+pub enum GenericValue<Component> {
+    /// A single value.
+    Component(Component),
+    /// A list of values.
+    List(GenericValueList<Component>),
+}
+
+/// A specified value component.
+pub type SpecifiedValueComponent = GenericValueComponent<
+    specified::Length,
+    specified::Number,
+    specified::Percentage,
+    specified::LengthPercentage,
+    specified::Color,
+    specified::Integer,
+    specified::Angle,
+    specified::Time,
+>;
+
+/// A computed value component.
+pub type ComputedValueComponent = GenericValueComponent<
+    computed::Length,
+    computed::Number,
+    computed::Percentage,
+    computed::LengthPercentage,
+    computed::Color,
+    computed::Integer,
+    computed::Angle,
+    computed::Time,
+>;
+
+/// The specified value of a registered custom property.
+pub type SpecifiedValue = GenericValue<SpecifiedValueComponent>;
+
+/// The computed value of a registered custom property.
+pub type ComputedValue = GenericValue<ComputedValueComponent>;
+
+impl<Component: ToCss> ToCss for GenericValueList<Component> {
+    fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
+    where
+        W: Write,
+    {
+        for (index, component) in self.components.iter().enumerate() {
+            if index != 0 {
+                dest.write_str(self.multiplier.separator())?;
+            }
+            component.to_css(dest)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists interpolate value by value, if they have the same length.
+impl<Component: Animate> Animate for GenericValueList<Component> {
+    fn animate(&self, other: &Self, procedure: Procedure) -> Result<Self, ()> {
+        if self.multiplier != other.multiplier || self.components.len() != other.components.len() {
+            return Err(());
+        }
+        let components = self
+            .components
+            .iter()
+            .zip(other.components.iter())
+            .map(|(this, other)| this.animate(other, procedure))
+            .collect::<Result<Vec<_>, ()>>()?;
+        Ok(GenericValueList {
+            components: components.into(),
+            multiplier: self.multiplier,
+        })
+    }
+}
+
+fn is_absolute(length: &NoCalcLength) -> bool {
+    matches!(*length, NoCalcLength::Absolute(..))
+}
+
+fn is_calc_absolute(calc: &specified::CalcLengthPercentage) -> bool {
+    !calc
+        .node
+        .any_leaf(|leaf| matches!(*leaf, Leaf::Length(ref length) if !is_absolute(length)))
+}
+
+impl SpecifiedValueComponent {
+    fn parse<'i, 't>(
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+        name: &ComponentName,
+    ) -> Result<Self, ParseError<'i>> {
+        let data_type = match *name {
+            ComponentName::DataType(data_type) => data_type,
+            ComponentName::Ident(ref expected) => {
+                let location = input.current_source_location();
+                let ident = input.expect_ident()?;
+                if **ident != *expected.0 {
+                    return Err(location.new_unexpected_token_error(Token::Ident(ident.clone())));
+                }
+                return Ok(GenericValueComponent::CustomIdent(expected.clone()));
+            },
+        };
+        Ok(match data_type {
+            DataType::Length => {
+                GenericValueComponent::Length(specified::Length::parse(context, input)?)
+            },
+            DataType::Number => {
+                GenericValueComponent::Number(specified::Number::parse(context, input)?)
+            },
+            DataType::Percentage => {
+                GenericValueComponent::Percentage(specified::Percentage::parse(context, input)?)
+            },
+            DataType::LengthPercentage => GenericValueComponent::LengthPercentage(
+                specified::LengthPercentage::parse(context, input)?,
+            ),
+            DataType::Color => {
+                GenericValueComponent::Color(specified::Color::parse(context, input)?)
+            },
+            DataType::Integer => {
+                GenericValueComponent::Integer(specified::Integer::parse(context, input)?)
+            },
+            DataType::Angle => {
+                GenericValueComponent::Angle(specified::Angle::parse(context, input)?)
+            },
+            DataType::Time => GenericValueComponent::Time(specified::Time::parse(context, input)?),
+            DataType::CustomIdent => {
+                let location = input.current_source_location();
+                let ident = input.expect_ident()?;
+                GenericValueComponent::CustomIdent(CustomIdent::from_ident(location, ident, &[])?)
+            },
+        })
+    }
+
+    /// Whether this value can be computed without an element, as required
+    /// for initial values.
+    ///
+    /// <https://drafts.css-houdini.org/css-properties-values-api-1/#computationally-independent>
+    fn is_computationally_independent(&self) -> bool {
+        match *self {
+            GenericValueComponent::Length(specified::Length::NoCalc(ref length)) |
+            GenericValueComponent::LengthPercentage(specified::LengthPercentage::Length(
+                ref length,
+            )) => is_absolute(length),
+            GenericValueComponent::Length(specified::Length::Calc(ref calc)) |
+            GenericValueComponent::LengthPercentage(specified::LengthPercentage::Calc(
+                ref calc,
+            )) => is_calc_absolute(calc),
+            _ => true,
+        }
+    }
+}
+
+impl SpecifiedValue {
+    /// Parses a value matching the given component.
+    fn parse_component<'i, 't>(
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+        component: &Component,
+    ) -> Result<Self, ParseError<'i>> {
+        let parse_one = |input: &mut Parser<'i, '_>| {
+            SpecifiedValueComponent::parse(context, input, &component.name)
+        };
+        let multiplier = match component.multiplier {
+            Some(multiplier) => multiplier,
+            None => return Ok(GenericValue::Component(parse_one(input)?)),
+        };
+        let components = match multiplier {
+            Multiplier::Space => {
+                let mut components = vec![parse_one(input)?];
+                while !input.is_exhausted() {
+                    components.push(parse_one(input)?);
+                }
+                components
+            },
+            Multiplier::Comma => input.parse_comma_separated(parse_one)?,
+        };
+        Ok(GenericValue::List(GenericValueList {
+            components: components.into(),
+            multiplier,
+        }))
+    }
+
+    /// Parses a value matching the given syntax, which must not be the
+    /// universal one. The first alternative of the syntax that matches the
+    /// whole input is used.
+    pub fn parse<'i, 't>(
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+        syntax: &Syntax,
+    ) -> Result<Self, ParseError<'i>> {
+        let components = match *syntax {
+            Syntax::Universal => {
+                debug_assert!(false, "Values of the universal syntax are not parsed");
+                return Err(input.new_custom_error(StyleParseErrorKind::UnspecifiedError));
+            },
+            Syntax::Components(ref components) => components,
+        };
+        for component in components.iter() {
+            let result = input.try(|input| {
+                input.parse_entirely(|input| Self::parse_component(context, input, component))
+            });
+            if let Ok(value) = result {
+                return Ok(value);
+            }
+        }
+        Err(input.new_custom_error(StyleParseErrorKind::UnspecifiedError))
+    }
+
+    /// Parses the given custom property value, after `var()` substitution,
+    /// against the given syntax.
+    pub fn parse_variable_value(
+        context: &ParserContext,
+        value: &VariableValue,
+        syntax: &Syntax,
+    ) -> Result<Self, ()> {
+        let mut input = ParserInput::new(value.css_text());
+        let mut input = Parser::new(&mut input);
+        Self::parse(context, &mut input, syntax).map_err(|_| ())
+    }
+
+    /// Whether this value can be computed without an element, as required
+    /// for initial values.
+    pub fn is_computationally_independent(&self) -> bool {
+        match *self {
+            GenericValue::Component(ref component) => component.is_computationally_independent(),
+            GenericValue::List(ref list) => list
+                .components
+                .iter()
+                .all(SpecifiedValueComponent::is_computationally_independent),
+        }
+    }
+}
+
+impl ComputedValue {
+    /// Returns this value as the value of a custom property, so that it can
+    /// be substituted in `var()` functions.
+    pub fn to_variable_value(&self) -> Result<Arc<VariableValue>, ()> {
+        let css = self.to_css_string();
+        let mut input = ParserInput::new(&css);
+        VariableValue::parse(&mut Parser::new(&mut input)).map_err(|_| ())
+    }
+}
+
+/// The computed value of a registered custom property, for animations.
+#[derive(Clone, Debug, MallocSizeOf, PartialEq)]
+pub struct CustomAnimatedValue {
+    /// The name of the custom property.
+    pub name: Name,
+    /// The computed value.
+    pub value: ComputedValue,
+}
+
+impl Animate for CustomAnimatedValue {
+    fn animate(&self, other: &Self, procedure: Procedure) -> Result<Self, ()> {
+        if self.name != other.name {
+            return Err(());
+        }
+        Ok(CustomAnimatedValue {
+            name: self.name.clone(),
+            value: self.value.animate(&other.value, procedure)?,
+        })
+    }
+}
@@ -7,12 +7,13 @@
 use crate::applicable_declarations::{ApplicableDeclarationBlock, ApplicableDeclarationList};
 use crate::dom::{TElement, TNode, TShadowRoot};
 use crate::properties::{AnimationRules, PropertyDeclarationBlock};
-use crate::rule_tree::{CascadeLevel, ShadowCascadeOrder};
+use crate::rule_tree::{CascadeLevel, ShadowCascadeOrder, StyleSource};
 use crate::selector_map::SelectorMap;
 use crate::selector_parser::PseudoElement;
 use crate::shared_lock::Locked;
+use crate::stylesheets::layer_rule::LayerOrder;
 use crate::stylesheets::Origin;
-use crate::stylist::{AuthorStylesEnabled, Rule, RuleInclusion, Stylist};
+use crate::stylist::{AuthorStylesEnabled, CascadeData, Rule, RuleInclusion, Stylist};
 use crate::Atom;
 use selectors::matching::{ElementSelectorFlags, MatchingContext, MatchingMode};
 use servo_arc::ArcBorrow;
@@ -138,7 +139,8 @@ where
     /// (either the document tree, or a shadow tree).
     ///
     /// All rules in the same tree need to be matched together, and this
-    /// function takes care of sorting them by specificity and source order.
+    /// function takes care of sorting them by cascade layer, specificity and
+    /// source order.
     #[inline]
     fn in_tree(&mut self, host: Option<E>, f: impl FnOnce(&mut Self)) {
         debug_assert!(!self.in_sort_scope, "Nested sorting makes no sense");
@@ -148,8 +150,9 @@ where
         self.context.current_host = host.map(|e| e.opaque());
         f(self);
         if start != self.rules.len() {
-            self.rules[start..]
-                .sort_unstable_by_key(|block| (block.specificity, block.source_order()));
+            self.rules[start..].sort_unstable_by_key(|block| {
+                (block.layer_order(), block.specificity, block.source_order())
+            });
         }
         self.context.current_host = old_host;
         self.in_sort_scope = false;
@@ -174,7 +177,7 @@ where
         };
 
         self.in_tree(None, |collector| {
-            collector.collect_rules_in_map(map, cascade_level);
+            collector.collect_rules_in_map(map, cascade_level, cascade_data);
         });
     }
 
@@ -215,7 +218,12 @@ where
     }
 
     #[inline]
-    fn collect_rules_in_list(&mut self, part_rules: &[Rule], cascade_level: CascadeLevel) {
+    fn collect_rules_in_list(
+        &mut self,
+        part_rules: &[Rule],
+        cascade_level: CascadeLevel,
+        cascade_data: &CascadeData,
+    ) {
         debug_assert!(self.in_sort_scope, "Rules gotta be sorted");
         SelectorMap::get_matching_rules(
             self.element,
@@ -224,11 +232,18 @@ where
             &mut self.context,
             &mut self.flags_setter,
             cascade_level,
+            cascade_data,
+            self.stylist.device(),
         );
     }
 
     #[inline]
-    fn collect_rules_in_map(&mut self, map: &SelectorMap<Rule>, cascade_level: CascadeLevel) {
+    fn collect_rules_in_map(
+        &mut self,
+        map: &SelectorMap<Rule>,
+        cascade_level: CascadeLevel,
+        cascade_data: &CascadeData,
+    ) {
         debug_assert!(self.in_sort_scope, "Rules gotta be sorted");
         map.get_all_matching_rules(
             self.element,
@@ -237,6 +252,8 @@ where
             &mut self.context,
             &mut self.flags_setter,
             cascade_level,
+            cascade_data,
+            self.stylist.device(),
         );
     }
 
@@ -278,7 +295,7 @@ where
                 let cascade_level = CascadeLevel::AuthorNormal {
                     shadow_cascade_order,
                 };
-                collector.collect_rules_in_map(slotted_rules, cascade_level);
+                collector.collect_rules_in_map(slotted_rules, cascade_level, data);
             });
         }
     }
@@ -304,7 +321,7 @@ where
         let cascade_level = CascadeLevel::same_tree_author_normal();
         self.in_shadow_tree(containing_shadow.host(), |collector| {
             if let Some(map) = cascade_data.normal_rules(collector.pseudo_element) {
-                collector.collect_rules_in_map(map, cascade_level);
+                collector.collect_rules_in_map(map, cascade_level, cascade_data);
             }
 
             // Collect rules from :host::part() and such
@@ -320,7 +337,7 @@ where
 
             hash_target.each_part(|part| {
                 if let Some(part_rules) = part_rules.get(part) {
-                    collector.collect_rules_in_list(part_rules, cascade_level);
+                    collector.collect_rules_in_list(part_rules, cascade_level, cascade_data);
                 }
             });
         });
@@ -353,7 +370,7 @@ where
             let cascade_level = CascadeLevel::AuthorNormal {
                 shadow_cascade_order,
             };
-            collector.collect_rules_in_map(host_rules, cascade_level);
+            collector.collect_rules_in_map(host_rules, cascade_level, style_data);
         });
     }
 
@@ -387,18 +404,20 @@ where
 
             let inner_shadow_host = inner_shadow.host();
             let outer_shadow = inner_shadow_host.containing_shadow();
-            let part_rules = match outer_shadow {
-                Some(shadow) => shadow
-                    .style_data()
-                    .and_then(|data| data.part_rules(self.pseudo_element)),
-                None => self
-                    .stylist
-                    .cascade_data()
-                    .borrow_for_origin(Origin::Author)
-                    .part_rules(self.pseudo_element),
+            let cascade_data = match outer_shadow {
+                Some(shadow) => shadow.style_data(),
+                None => Some(
+                    self.stylist
+                        .cascade_data()
+                        .borrow_for_origin(Origin::Author),
+                ),
             };
+            let part_rules = cascade_data.and_then(|data| {
+                data.part_rules(self.pseudo_element)
+                    .map(|rules| (data, rules))
+            });
 
-            if let Some(part_rules) = part_rules {
+            if let Some((cascade_data, part_rules)) = part_rules {
                 let containing_host = outer_shadow.map(|s| s.host());
                 let cascade_level = CascadeLevel::AuthorNormal {
                     shadow_cascade_order,
@@ -406,7 +425,11 @@ where
                 self.in_tree(containing_host, |collector| {
                     for p in &parts {
                         if let Some(part_rules) = part_rules.get(p) {
-                            collector.collect_rules_in_list(part_rules, cascade_level);
+                            collector.collect_rules_in_list(
+                                part_rules,
+                                cascade_level,
+                                cascade_data,
+                            );
                         }
                     }
                 });
@@ -430,11 +453,13 @@ where
 
     fn collect_style_attribute(&mut self) {
         if let Some(sa) = self.style_attribute {
-            self.rules
-                .push(ApplicableDeclarationBlock::from_declarations(
-                    sa.clone_arc(),
-                    CascadeLevel::same_tree_author_normal(),
-                ));
+            self.rules.push(ApplicableDeclarationBlock::new(
+                StyleSource::from_declarations(sa.clone_arc()),
+                0,
+                CascadeLevel::same_tree_author_normal(),
+                0,
+                LayerOrder::style_attribute(),
+            ));
         }
     }
 
@@ -9,8 +9,10 @@
 use crate::applicable_declarations::ApplicableDeclarationList;
 use crate::properties::{LonghandIdSet, PropertyDeclarationBlock};
 use crate::shared_lock::{Locked, StylesheetGuards};
+use crate::stylesheets::layer_rule::LayerOrder;
 use servo_arc::{Arc, ArcBorrow};
 use smallvec::SmallVec;
+use std::cmp::Reverse;
 use std::io::{self, Write};
 
 mod core;
@@ -35,8 +37,9 @@ impl RuleTree {
         self.dump(guards, &mut stdout);
     }
 
-    /// Inserts the given rules, that must be in proper order by specifity, and
-    /// returns the corresponding rule node representing the last inserted one.
+    /// Inserts the given rules, that must be in proper order by cascade layer
+    /// and specifity, and returns the corresponding rule node representing the
+    /// last inserted one.
     ///
     /// !important rules are detected and inserted into the appropriate position
     /// in the rule tree. This allows selector matching to ignore importance,
@@ -47,20 +50,21 @@ impl RuleTree {
         guards: &StylesheetGuards,
     ) -> StrongRuleNode
     where
-        I: Iterator<Item = (StyleSource, CascadeLevel)>,
+        I: Iterator<Item = (StyleSource, CascadeLevel, LayerOrder)>,
     {
         use self::CascadeLevel::*;
         let mut current = self.root().clone();
 
         let mut found_important = false;
 
-        let mut important_author = SmallVec::<[(StyleSource, ShadowCascadeOrder); 4]>::new();
+        let mut important_author =
+            SmallVec::<[(StyleSource, ShadowCascadeOrder, LayerOrder); 4]>::new();
 
-        let mut important_user = SmallVec::<[StyleSource; 4]>::new();
-        let mut important_ua = SmallVec::<[StyleSource; 4]>::new();
+        let mut important_user = SmallVec::<[(StyleSource, LayerOrder); 4]>::new();
+        let mut important_ua = SmallVec::<[(StyleSource, LayerOrder); 4]>::new();
         let mut transition = None;
 
-        for (source, level) in iter {
+        for (source, level, layer_order) in iter {
             debug_assert!(!level.is_important(), "Important levels handled internally");
             let any_important = {
                 let pdb = source.read(level.guard(guards));
@@ -73,10 +77,10 @@ impl RuleTree {
                     AuthorNormal {
                         shadow_cascade_order,
                     } => {
-                        important_author.push((source.clone(), shadow_cascade_order));
+                        important_author.push((source.clone(), shadow_cascade_order, layer_order));
                     },
-                    UANormal => important_ua.push(source.clone()),
-                    UserNormal => important_user.push(source.clone()),
+                    UANormal => important_ua.push((source.clone(), layer_order)),
+                    UserNormal => important_user.push((source.clone(), layer_order)),
                     _ => {},
                 };
             }
@@ -114,11 +118,21 @@ impl RuleTree {
         //
         // We negate the shadow cascade order to preserve the right PartialOrd
         // behavior.
+        //
+        // Within a tree, earlier cascade layers win over later ones, so their
+        // order is reversed too, except for the style attribute, which wins
+        // over every layer.
         if !important_author.is_empty() &&
-            important_author.first().unwrap().1 != important_author.last().unwrap().1
+            (
+                important_author.first().unwrap().1,
+                important_author.first().unwrap().2,
+            ) != (
+                important_author.last().unwrap().1,
+                important_author.last().unwrap().2,
+            )
         {
             // We only need to sort if the important rules come from
-            // different trees, but we need this sort to be stable.
+            // different trees or layers, but we need this sort to be stable.
             //
             // FIXME(emilio): This could maybe be smarter, probably by chunking
             // the important rules while inserting, and iterating the outer
@@ -129,10 +143,16 @@ impl RuleTree {
             // inside the same chunk already sorted. Seems like we could try to
             // keep a SmallVec-of-SmallVecs with the chunks and just iterate the
             // outer in reverse.
-            important_author.sort_by_key(|&(_, order)| -order);
+            important_author.sort_by_key(|&(_, order, layer_order)| {
+                (
+                    -order,
+                    layer_order.is_style_attribute_layer(),
+                    Reverse(layer_order),
+                )
+            });
         }
 
-        for (source, shadow_cascade_order) in important_author.drain(..) {
+        for (source, shadow_cascade_order, _) in important_author.drain(..) {
             current = current.ensure_child(
                 self.root(),
                 source,
@@ -142,11 +162,13 @@ impl RuleTree {
             );
         }
 
-        for source in important_user.drain(..) {
+        important_user.sort_by_key(|&(_, layer_order)| Reverse(layer_order));
+        for (source, _) in important_user.drain(..) {
             current = current.ensure_child(self.root(), source, UserImportant);
         }
 
-        for source in important_ua.drain(..) {
+        important_ua.sort_by_key(|&(_, layer_order)| Reverse(layer_order));
+        for (source, _) in important_ua.drain(..) {
             current = current.ensure_child(self.root(), source, UAImportant);
         }
 
@@ -10,9 +10,10 @@ use crate::context::QuirksMode;
 use crate::dom::TElement;
 use crate::hash::map as hash_map;
 use crate::hash::{HashMap, HashSet};
+use crate::media_queries::Device;
 use crate::rule_tree::CascadeLevel;
 use crate::selector_parser::SelectorImpl;
-use crate::stylist::Rule;
+use crate::stylist::{CascadeData, Rule};
 use crate::{Atom, LocalName, Namespace, WeakAtom};
 use fallible::FallibleVec;
 use hashglobe::FailedAllocationError;
@@ -171,6 +172,8 @@ impl SelectorMap<Rule> {
         context: &mut MatchingContext<E::Impl>,
         flags_setter: &mut F,
         cascade_level: CascadeLevel,
+        cascade_data: &CascadeData,
+        device: &Device,
     ) where
         E: TElement,
         F: FnMut(&E, ElementSelectorFlags),
@@ -189,6 +192,8 @@ impl SelectorMap<Rule> {
                 context,
                 flags_setter,
                 cascade_level,
+                cascade_data,
+                device,
             );
         }
 
@@ -201,6 +206,8 @@ impl SelectorMap<Rule> {
                     context,
                     flags_setter,
                     cascade_level,
+                    cascade_data,
+                    device,
                 )
             }
         }
@@ -214,6 +221,8 @@ impl SelectorMap<Rule> {
                     context,
                     flags_setter,
                     cascade_level,
+                    cascade_data,
+                    device,
                 )
             }
         });
@@ -226,6 +235,8 @@ impl SelectorMap<Rule> {
                 context,
                 flags_setter,
                 cascade_level,
+                cascade_data,
+                device,
             )
         }
 
@@ -237,6 +248,8 @@ impl SelectorMap<Rule> {
                 context,
                 flags_setter,
                 cascade_level,
+                cascade_data,
+                device,
             )
         }
 
@@ -247,6 +260,8 @@ impl SelectorMap<Rule> {
             context,
             flags_setter,
             cascade_level,
+            cascade_data,
+            device,
         );
     }
 
@@ -258,6 +273,8 @@ impl SelectorMap<Rule> {
         context: &mut MatchingContext<E::Impl>,
         flags_setter: &mut F,
         cascade_level: CascadeLevel,
+        cascade_data: &CascadeData,
+        device: &Device,
     ) where
         E: TElement,
         F: FnMut(&E, ElementSelectorFlags),
@@ -270,8 +287,14 @@ impl SelectorMap<Rule> {
                 &element,
                 context,
                 flags_setter,
+            ) && cascade_data.container_condition_matches(
+                rule.container_condition_id,
+                element,
+                device,
+                context.quirks_mode(),
             ) {
-                matching_rules.push(rule.to_applicable_declaration_block(cascade_level));
+                matching_rules
+                    .push(rule.to_applicable_declaration_block(cascade_level, cascade_data));
             }
         }
     }
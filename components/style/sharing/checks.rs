@@ -7,6 +7,7 @@
 //! elements can indeed share the same style.
 
 use crate::bloom::StyleBloom;
+use crate::computed_value_flags::ComputedValueFlags;
 use crate::context::{SelectorFlagsMap, SharedStyleContext};
 use crate::dom::TElement;
 use crate::sharing::{StyleSharingCandidate, StyleSharingTarget};
@@ -55,6 +56,38 @@ where
     true
 }
 
+/// Whether the target and the candidate are subject to the same query
+/// containers, or don't depend on them.
+///
+/// Siblings always have the same query containers, but the parents of cousins
+/// may be query containers of different sizes.
+pub fn container_queries_allow_sharing<E>(
+    shared_context: &SharedStyleContext,
+    target: &StyleSharingTarget<E>,
+    candidate: &StyleSharingCandidate<E>,
+) -> bool
+where
+    E: TElement,
+{
+    if target.inheritance_parent() == candidate.element.inheritance_parent() {
+        return true;
+    }
+
+    let candidate_data = candidate.element.borrow_data().unwrap();
+    if candidate_data
+        .styles
+        .primary()
+        .flags
+        .contains(ComputedValueFlags::USES_CONTAINER_UNITS)
+    {
+        return false;
+    }
+
+    !shared_context
+        .stylist
+        .any_applicable_rule_data(target.element, |data| data.has_container_conditions())
+}
+
 /// Whether two elements have the same same style attribute (by pointer identity).
 pub fn have_same_style_attribute<E>(
     target: &mut StyleSharingTarget<E>,
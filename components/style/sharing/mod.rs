@@ -723,6 +723,11 @@ impl<E: TElement> StyleSharingCache<E> {
             return None;
         }
 
+        if !checks::container_queries_allow_sharing(shared, target, candidate) {
+            trace!("Miss: Container queries");
+            return None;
+        }
+
         if target.local_name() != candidate.element.local_name() {
             trace!("Miss: Local Name");
             return None;
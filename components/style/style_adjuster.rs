@@ -177,7 +177,6 @@ impl<'a, 'b: 'a> StyleAdjuster<'a, 'b> {
     where
         E: TElement,
     {
-        #[cfg(any(feature = "servo-layout-2013", feature = "gecko"))]
         use crate::computed_values::list_style_position::T as ListStylePosition;
 
         let mut blockify = false;
@@ -199,7 +198,6 @@ impl<'a, 'b: 'a> StyleAdjuster<'a, 'b> {
 
         blockify_if!(self.style.is_floating());
         blockify_if!(self.style.is_absolutely_positioned());
-        #[cfg(any(feature = "servo-layout-2013", feature = "gecko"))]
         blockify_if!(
             self.style.pseudo.map_or(false, |p| p.is_marker()) &&
                 self.style.get_parent_list().clone_list_style_position() ==
@@ -395,6 +393,25 @@ impl<'a, 'b: 'a> StyleAdjuster<'a, 'b> {
             };
             self.style.mutate_position().set_align_self(self_align);
         }
+
+        #[cfg(feature = "servo-layout-2020")]
+        {
+            use crate::computed_values::justify_items::T as JustifyItems;
+            use crate::computed_values::justify_self::T as JustifySelf;
+
+            if self.style.get_position().clone_justify_self() == JustifySelf::Auto &&
+                !self.style.is_absolutely_positioned()
+            {
+                let justify_self = match layout_parent_style.get_position().clone_justify_items() {
+                    JustifyItems::Normal => JustifySelf::Normal,
+                    JustifyItems::Stretch => JustifySelf::Stretch,
+                    JustifyItems::Start => JustifySelf::Start,
+                    JustifyItems::End => JustifySelf::End,
+                    JustifyItems::Center => JustifySelf::Center,
+                };
+                self.style.mutate_position().set_justify_self(justify_self);
+            }
+        }
     }
 
     /// The initial value of border-*-width may be changed at computed value
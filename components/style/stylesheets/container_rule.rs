@@ -0,0 +1,631 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A [`@container`][container] rule.
+//!
+//! [container]: https://drafts.csswg.org/css-contain-3/#container-rule
+
+use crate::context::QuirksMode;
+use crate::logical_geometry::WritingMode;
+use crate::media_queries::media_condition::Operator;
+use crate::media_queries::media_feature_expression::{consume_operation_or_colon, AspectRatio};
+use crate::media_queries::media_feature_expression::{Range, RangeOrOperator};
+use crate::media_queries::Device;
+use crate::parser::{Parse, ParserContext};
+use crate::shared_lock::{DeepCloneParams, DeepCloneWithLock, Locked};
+use crate::shared_lock::{SharedRwLock, SharedRwLockReadGuard, ToCssWithGuard};
+use crate::str::{starts_with_ignore_ascii_case, CssStringWriter};
+use crate::stylesheets::CssRules;
+use crate::values::computed::{self, ToComputedValue};
+use crate::values::specified::{Integer, Length};
+use crate::values::{CSSFloat, CustomIdent};
+use app_units::Au;
+use cssparser::{Parser, SourceLocation, Token};
+#[cfg(feature = "gecko")]
+use malloc_size_of::{MallocSizeOfOps, MallocUnconditionalShallowSizeOf};
+use servo_arc::Arc;
+use std::cell::Cell;
+use std::fmt::{self, Write};
+use style_traits::{CssWriter, ParseError, StyleParseErrorKind, ToCss};
+
+/// A [`@container`][container] rule.
+///
+/// [container]: https://drafts.csswg.org/css-contain-3/#container-rule
+#[derive(Debug, ToShmem)]
+pub struct ContainerRule {
+    /// The container query of this rule, and the name of the query container
+    /// it applies to, if any.
+    pub condition: Arc<ContainerCondition>,
+    /// The nested rules to this container rule.
+    pub rules: Arc<Locked<CssRules>>,
+    /// The source position where this container rule was found.
+    pub source_location: SourceLocation,
+}
+
+impl ContainerRule {
+    /// Measure heap usage.
+    #[cfg(feature = "gecko")]
+    pub fn size_of(&self, guard: &SharedRwLockReadGuard, ops: &mut MallocSizeOfOps) -> usize {
+        // Measurement of other fields may be added later.
+        self.rules.unconditional_shallow_size_of(ops) +
+            self.rules.read_with(guard).size_of(guard, ops)
+    }
+}
+
+impl ToCssWithGuard for ContainerRule {
+    fn to_css(&self, guard: &SharedRwLockReadGuard, dest: &mut CssStringWriter) -> fmt::Result {
+        dest.write_str("@container ")?;
+        self.condition.to_css(&mut CssWriter::new(dest))?;
+        self.rules.read_with(guard).to_css_block(guard, dest)
+    }
+}
+
+impl DeepCloneWithLock for ContainerRule {
+    fn deep_clone_with_lock(
+        &self,
+        lock: &SharedRwLock,
+        guard: &SharedRwLockReadGuard,
+        params: &DeepCloneParams,
+    ) -> Self {
+        let rules = self.rules.read_with(guard);
+        ContainerRule {
+            condition: self.condition.clone(),
+            rules: Arc::new(lock.wrap(rules.deep_clone_with_lock(lock, guard, params))),
+            source_location: self.source_location.clone(),
+        }
+    }
+}
+
+/// The size of a query container, as of the last layout.
+///
+/// The size is only known in the axes the container establishes size
+/// containment in, see `container-type`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContainerSize {
+    /// The width of the content box of the container.
+    pub width: Option<Au>,
+    /// The height of the content box of the container.
+    pub height: Option<Au>,
+    /// The writing mode of the container, which maps the logical size
+    /// features to the physical size.
+    pub writing_mode: WritingMode,
+}
+
+impl ContainerSize {
+    /// The inline size of the content box of the container.
+    pub fn inline_size(&self) -> Option<Au> {
+        if self.writing_mode.is_vertical() {
+            self.height
+        } else {
+            self.width
+        }
+    }
+
+    /// The block size of the content box of the container.
+    pub fn block_size(&self) -> Option<Au> {
+        if self.writing_mode.is_vertical() {
+            self.width
+        } else {
+            self.height
+        }
+    }
+}
+
+/// A lookup of the size of the nearest query container of the element being
+/// styled, which is only done when a container-relative length needs it.
+pub struct ContainerSizeQuery<'a> {
+    lookup: Option<&'a dyn Fn() -> Option<ContainerSize>>,
+    cached: Cell<Option<Option<ContainerSize>>>,
+}
+
+impl<'a> ContainerSizeQuery<'a> {
+    /// A query that finds no query container, when there is no element.
+    pub fn none() -> Self {
+        Self {
+            lookup: None,
+            cached: Cell::new(None),
+        }
+    }
+
+    /// A query that looks up the query container with `lookup`, at most once.
+    pub fn new(lookup: &'a dyn Fn() -> Option<ContainerSize>) -> Self {
+        Self {
+            lookup: Some(lookup),
+            cached: Cell::new(None),
+        }
+    }
+
+    /// Returns the size of the nearest query container, if any.
+    pub fn get(&self) -> Option<ContainerSize> {
+        if let Some(size) = self.cached.get() {
+            return size;
+        }
+        let size = self.lookup.and_then(|lookup| lookup());
+        self.cached.set(Some(size));
+        size
+    }
+}
+
+/// The container query of a `@container` rule, along with the name of the
+/// query container it applies to.
+///
+/// <https://drafts.csswg.org/css-contain-3/#typedef-container-condition>
+#[derive(Debug, PartialEq, ToShmem)]
+pub struct ContainerCondition {
+    /// The name of the query container, or `None` for the nearest one.
+    pub name: Option<CustomIdent>,
+    /// The container query.
+    pub query: ContainerQuery,
+}
+
+impl ContainerCondition {
+    /// Parse the prelude of a `@container` rule.
+    pub fn parse<'i, 't>(
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i>> {
+        let name = input
+            .try(|input| {
+                let location = input.current_source_location();
+                let ident = input.expect_ident()?;
+                CustomIdent::from_ident(location, ident, &["none", "and", "or", "not"])
+            })
+            .ok();
+        let query = ContainerQuery::parse(context, input)?;
+        Ok(Self { name, query })
+    }
+
+    /// Returns whether this condition matches the given query container, or
+    /// `false` if there is no such container.
+    pub fn matches(
+        &self,
+        device: &Device,
+        quirks_mode: QuirksMode,
+        size: Option<ContainerSize>,
+    ) -> bool {
+        match size {
+            Some(size) => self.query.matches(device, quirks_mode, &size) == Some(true),
+            None => false,
+        }
+    }
+}
+
+impl ToCss for ContainerCondition {
+    fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
+    where
+        W: Write,
+    {
+        if let Some(ref name) = self.name {
+            name.to_css(dest)?;
+            dest.write_char(' ')?;
+        }
+        self.query.to_css(dest)
+    }
+}
+
+/// A container query, which has the same grammar as a media condition, but
+/// with container features instead of media features.
+///
+/// <https://drafts.csswg.org/css-contain-3/#typedef-container-query>
+#[derive(Debug, PartialEq, ToShmem)]
+pub enum ContainerQuery {
+    /// A simple container feature expression, implicitly parenthesized.
+    Feature(ContainerFeatureExpression),
+    /// A negation of a query.
+    Not(Box<ContainerQuery>),
+    /// A set of joint operations.
+    Operation(Box<[ContainerQuery]>, Operator),
+    /// A query wrapped in parenthesis.
+    InParens(Box<ContainerQuery>),
+}
+
+impl ToCss for ContainerQuery {
+    fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
+    where
+        W: Write,
+    {
+        match *self {
+            // ContainerFeatureExpression already includes the parenthesis.
+            ContainerQuery::Feature(ref f) => f.to_css(dest),
+            ContainerQuery::Not(ref q) => {
+                dest.write_str("not ")?;
+                q.to_css(dest)
+            },
+            ContainerQuery::InParens(ref q) => {
+                dest.write_char('(')?;
+                q.to_css(dest)?;
+                dest.write_char(')')
+            },
+            ContainerQuery::Operation(ref list, op) => {
+                let mut iter = list.iter();
+                iter.next().unwrap().to_css(dest)?;
+                for item in iter {
+                    dest.write_char(' ')?;
+                    op.to_css(dest)?;
+                    dest.write_char(' ')?;
+                    item.to_css(dest)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl ContainerQuery {
+    /// Parse a single container query.
+    pub fn parse<'i, 't>(
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i>> {
+        let location = input.current_source_location();
+
+        let is_negation = match *input.next()? {
+            Token::ParenthesisBlock => false,
+            Token::Ident(ref ident) if ident.eq_ignore_ascii_case("not") => true,
+            ref t => return Err(location.new_unexpected_token_error(t.clone())),
+        };
+
+        if is_negation {
+            let inner_query = Self::parse_in_parens(context, input)?;
+            return Ok(ContainerQuery::Not(Box::new(inner_query)));
+        }
+
+        // ParenthesisBlock.
+        let first_query = Self::parse_paren_block(context, input)?;
+        let operator = match input.try(Operator::parse) {
+            Ok(op) => op,
+            Err(..) => return Ok(first_query),
+        };
+
+        let mut queries = vec![];
+        queries.push(first_query);
+        queries.push(Self::parse_in_parens(context, input)?);
+
+        let delim = match operator {
+            Operator::And => "and",
+            Operator::Or => "or",
+        };
+
+        loop {
+            if input.try(|i| i.expect_ident_matching(delim)).is_err() {
+                return Ok(ContainerQuery::Operation(
+                    queries.into_boxed_slice(),
+                    operator,
+                ));
+            }
+
+            queries.push(Self::parse_in_parens(context, input)?);
+        }
+    }
+
+    fn parse_in_parens<'i, 't>(
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i>> {
+        input.expect_parenthesis_block()?;
+        Self::parse_paren_block(context, input)
+    }
+
+    fn parse_paren_block<'i, 't>(
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i>> {
+        input.parse_nested_block(|input| {
+            // Base case.
+            if let Ok(inner) = input.try(|i| Self::parse(context, i)) {
+                return Ok(ContainerQuery::InParens(Box::new(inner)));
+            }
+            let expr = ContainerFeatureExpression::parse_in_parenthesis_block(context, input)?;
+            Ok(ContainerQuery::Feature(expr))
+        })
+    }
+
+    /// Whether this query matches the given query container, or `None` if
+    /// that is unknown because the container is not sized in the queried axes.
+    ///
+    /// <https://drafts.csswg.org/css-contain-3/#evaluate-a-container>
+    fn matches(
+        &self,
+        device: &Device,
+        quirks_mode: QuirksMode,
+        size: &ContainerSize,
+    ) -> Option<bool> {
+        match *self {
+            ContainerQuery::Feature(ref f) => f.matches(device, quirks_mode, size),
+            ContainerQuery::InParens(ref q) => q.matches(device, quirks_mode, size),
+            ContainerQuery::Not(ref q) => q.matches(device, quirks_mode, size).map(|m| !m),
+            ContainerQuery::Operation(ref queries, op) => {
+                // Unknown results only decide the result if no other query
+                // does.
+                let decisive = op == Operator::Or;
+                let mut result = Some(!decisive);
+                for query in queries.iter() {
+                    match query.matches(device, quirks_mode, size) {
+                        Some(m) if m == decisive => return Some(decisive),
+                        Some(_) => {},
+                        None => result = None,
+                    }
+                }
+                result
+            },
+        }
+    }
+}
+
+/// A size feature that a container query can test.
+///
+/// <https://drafts.csswg.org/css-contain-3/#container-features>
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ToShmem)]
+pub enum ContainerFeature {
+    /// `width`
+    Width,
+    /// `height`
+    Height,
+    /// `inline-size`
+    InlineSize,
+    /// `block-size`
+    BlockSize,
+    /// `aspect-ratio`
+    AspectRatio,
+    /// `orientation`
+    Orientation,
+}
+
+impl ContainerFeature {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match_ignore_ascii_case! { name,
+            "width" => ContainerFeature::Width,
+            "height" => ContainerFeature::Height,
+            "inline-size" => ContainerFeature::InlineSize,
+            "block-size" => ContainerFeature::BlockSize,
+            "aspect-ratio" => ContainerFeature::AspectRatio,
+            "orientation" => ContainerFeature::Orientation,
+            _ => return None,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            ContainerFeature::Width => "width",
+            ContainerFeature::Height => "height",
+            ContainerFeature::InlineSize => "inline-size",
+            ContainerFeature::BlockSize => "block-size",
+            ContainerFeature::AspectRatio => "aspect-ratio",
+            ContainerFeature::Orientation => "orientation",
+        }
+    }
+
+    fn allows_ranges(&self) -> bool {
+        *self != ContainerFeature::Orientation
+    }
+}
+
+/// The value of the `orientation` container feature.
+#[derive(Clone, Copy, Debug, Eq, Parse, PartialEq, ToCss, ToShmem)]
+#[allow(missing_docs)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+/// A value found in a container feature expression.
+#[derive(Clone, Debug, PartialEq, ToShmem)]
+pub enum ContainerFeatureValue {
+    /// A length, for the size features.
+    Length(Length),
+    /// A ratio, for `aspect-ratio`.
+    Ratio(AspectRatio),
+    /// An orientation, for `orientation`.
+    Orientation(Orientation),
+}
+
+impl ToCss for ContainerFeatureValue {
+    fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
+    where
+        W: Write,
+    {
+        match *self {
+            ContainerFeatureValue::Length(ref l) => l.to_css(dest),
+            ContainerFeatureValue::Ratio(ratio) => ratio.to_css(dest),
+            ContainerFeatureValue::Orientation(o) => o.to_css(dest),
+        }
+    }
+}
+
+/// A container feature expression, like `(width >= 400px)`.
+#[derive(Debug, PartialEq, ToShmem)]
+pub struct ContainerFeatureExpression {
+    feature: ContainerFeature,
+    value: Option<ContainerFeatureValue>,
+    range_or_operator: Option<RangeOrOperator>,
+}
+
+impl ToCss for ContainerFeatureExpression {
+    fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
+    where
+        W: Write,
+    {
+        dest.write_char('(')?;
+
+        if let Some(RangeOrOperator::Range(range)) = self.range_or_operator {
+            match range {
+                Range::Min => dest.write_str("min-")?,
+                Range::Max => dest.write_str("max-")?,
+            }
+        }
+
+        dest.write_str(self.feature.name())?;
+
+        if let Some(RangeOrOperator::Operator(op)) = self.range_or_operator {
+            dest.write_char(' ')?;
+            op.to_css(dest)?;
+            dest.write_char(' ')?;
+        } else if self.value.is_some() {
+            dest.write_str(": ")?;
+        }
+
+        if let Some(ref val) = self.value {
+            val.to_css(dest)?;
+        }
+
+        dest.write_char(')')
+    }
+}
+
+impl ContainerFeatureExpression {
+    /// Parse a container feature expression where we've already consumed the
+    /// parenthesis.
+    fn parse_in_parenthesis_block<'i, 't>(
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i>> {
+        let location = input.current_source_location();
+        let ident = input.expect_ident()?;
+
+        let mut feature_name = &**ident;
+        let range = if starts_with_ignore_ascii_case(feature_name, "min-") {
+            feature_name = &feature_name[4..];
+            Some(Range::Min)
+        } else if starts_with_ignore_ascii_case(feature_name, "max-") {
+            feature_name = &feature_name[4..];
+            Some(Range::Max)
+        } else {
+            None
+        };
+
+        let feature = match ContainerFeature::from_name(feature_name) {
+            Some(feature) if range.is_none() || feature.allows_ranges() => feature,
+            _ => {
+                return Err(location.new_custom_error(
+                    StyleParseErrorKind::MediaQueryExpectedFeatureName(ident.clone()),
+                ))
+            },
+        };
+
+        let operator = match input.try(consume_operation_or_colon) {
+            Err(..) => {
+                if range.is_some() {
+                    return Err(
+                        input.new_custom_error(StyleParseErrorKind::RangedExpressionWithNoValue)
+                    );
+                }
+                return Ok(Self {
+                    feature,
+                    value: None,
+                    range_or_operator: None,
+                });
+            },
+            Ok(operator) => operator,
+        };
+
+        let range_or_operator = match (range, operator) {
+            (Some(..), Some(..)) => {
+                return Err(
+                    input.new_custom_error(StyleParseErrorKind::MediaQueryUnexpectedOperator)
+                );
+            },
+            (Some(range), None) => Some(RangeOrOperator::Range(range)),
+            (None, Some(operator)) => {
+                if !feature.allows_ranges() {
+                    return Err(
+                        input.new_custom_error(StyleParseErrorKind::MediaQueryUnexpectedOperator)
+                    );
+                }
+                Some(RangeOrOperator::Operator(operator))
+            },
+            (None, None) => None,
+        };
+
+        let value = Self::parse_value(feature, context, input).map_err(|err| {
+            err.location
+                .new_custom_error(StyleParseErrorKind::MediaQueryExpectedFeatureValue)
+        })?;
+
+        Ok(Self {
+            feature,
+            value: Some(value),
+            range_or_operator,
+        })
+    }
+
+    fn parse_value<'i, 't>(
+        feature: ContainerFeature,
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<ContainerFeatureValue, ParseError<'i>> {
+        Ok(match feature {
+            ContainerFeature::Width |
+            ContainerFeature::Height |
+            ContainerFeature::InlineSize |
+            ContainerFeature::BlockSize => {
+                ContainerFeatureValue::Length(Length::parse_non_negative(context, input)?)
+            },
+            ContainerFeature::AspectRatio => {
+                let a = Integer::parse_positive(context, input)?;
+                input.expect_delim('/')?;
+                let b = Integer::parse_positive(context, input)?;
+                ContainerFeatureValue::Ratio(AspectRatio(
+                    a.value() as CSSFloat,
+                    b.value() as CSSFloat,
+                ))
+            },
+            ContainerFeature::Orientation => {
+                ContainerFeatureValue::Orientation(Orientation::parse(context, input)?)
+            },
+        })
+    }
+
+    fn matches(
+        &self,
+        device: &Device,
+        quirks_mode: QuirksMode,
+        size: &ContainerSize,
+    ) -> Option<bool> {
+        let size_feature = match self.feature {
+            ContainerFeature::Width => size.width,
+            ContainerFeature::Height => size.height,
+            ContainerFeature::InlineSize => size.inline_size(),
+            ContainerFeature::BlockSize => size.block_size(),
+            ContainerFeature::AspectRatio | ContainerFeature::Orientation => {
+                let width = size.width?;
+                let height = size.height?;
+                return Some(match (self.feature, &self.value) {
+                    (ContainerFeature::Orientation, None) => true,
+                    (
+                        ContainerFeature::Orientation,
+                        Some(&ContainerFeatureValue::Orientation(o)),
+                    ) => {
+                        let portrait = height >= width;
+                        portrait == (o == Orientation::Portrait)
+                    },
+                    (_, Some(&ContainerFeatureValue::Ratio(ratio))) => {
+                        RangeOrOperator::evaluate_with_query_value(
+                            self.range_or_operator,
+                            ratio,
+                            AspectRatio(width.0 as CSSFloat, height.0 as CSSFloat),
+                        )
+                    },
+                    (_, None) => width != Au(0) && height != Au(0),
+                    _ => unreachable!("Unexpected ContainerFeatureValue"),
+                });
+            },
+        };
+
+        let value = size_feature?;
+        let query_value = match self.value {
+            Some(ContainerFeatureValue::Length(ref specified)) => {
+                computed::Context::for_media_query_evaluation(device, quirks_mode, |context| {
+                    Au::from(specified.to_computed_value(context))
+                })
+            },
+            Some(..) => unreachable!("Unexpected ContainerFeatureValue"),
+            None => return Some(value != Au(0)),
+        };
+        Some(RangeOrOperator::evaluate_with_query_value(
+            self.range_or_operator,
+            query_value,
+            value,
+        ))
+    }
+}
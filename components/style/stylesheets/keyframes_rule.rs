@@ -4,6 +4,7 @@
 
 //! Keyframes: https://drafts.csswg.org/css-animations/#keyframes
 
+use crate::custom_properties::Name;
 use crate::error_reporting::ContextualParseError;
 use crate::parser::ParserContext;
 use crate::properties::longhands::transition_timing_function::single_value::SpecifiedValue as SpecifiedTimingFunction;
@@ -11,6 +12,7 @@ use crate::properties::LonghandIdSet;
 use crate::properties::{Importance, PropertyDeclaration};
 use crate::properties::{LonghandId, PropertyDeclarationBlock, PropertyId};
 use crate::properties::{PropertyDeclarationId, SourcePropertyDeclaration};
+use crate::selector_map::PrecomputedHashSet;
 use crate::shared_lock::{DeepCloneParams, DeepCloneWithLock, SharedRwLock, SharedRwLockReadGuard};
 use crate::shared_lock::{Locked, ToCssWithGuard};
 use crate::str::CssStringWriter;
@@ -353,6 +355,9 @@ pub struct KeyframesAnimation {
     pub steps: Vec<KeyframesStep>,
     /// The properties that change in this animation.
     pub properties_changed: LonghandIdSet,
+    /// The custom properties that change in this animation. Only registered
+    /// ones are actually animated.
+    pub custom_properties_changed: PrecomputedHashSet<Name>,
     /// Vendor prefix type the @keyframes has.
     pub vendor_prefix: Option<VendorPrefix>,
 }
@@ -361,8 +366,9 @@ pub struct KeyframesAnimation {
 fn get_animated_properties(
     keyframes: &[Arc<Locked<Keyframe>>],
     guard: &SharedRwLockReadGuard,
-) -> LonghandIdSet {
+) -> (LonghandIdSet, PrecomputedHashSet<Name>) {
     let mut ret = LonghandIdSet::new();
+    let mut custom = PrecomputedHashSet::default();
     // NB: declarations are already deduplicated, so we don't have to check for
     // it here.
     for keyframe in keyframes {
@@ -377,7 +383,10 @@ fn get_animated_properties(
         for declaration in block.normal_declaration_iter() {
             let longhand_id = match declaration.id() {
                 PropertyDeclarationId::Longhand(id) => id,
-                _ => continue,
+                PropertyDeclarationId::Custom(name) => {
+                    custom.insert(name.clone());
+                    continue;
+                },
             };
 
             if longhand_id == LonghandId::Display {
@@ -392,7 +401,7 @@ fn get_animated_properties(
         }
     }
 
-    ret
+    (ret, custom)
 }
 
 impl KeyframesAnimation {
@@ -412,6 +421,7 @@ impl KeyframesAnimation {
         let mut result = KeyframesAnimation {
             steps: vec![],
             properties_changed: LonghandIdSet::new(),
+            custom_properties_changed: PrecomputedHashSet::default(),
             vendor_prefix,
         };
 
@@ -419,8 +429,11 @@ impl KeyframesAnimation {
             return result;
         }
 
-        result.properties_changed = get_animated_properties(keyframes, guard);
-        if result.properties_changed.is_empty() {
+        let (properties_changed, custom_properties_changed) =
+            get_animated_properties(keyframes, guard);
+        result.properties_changed = properties_changed;
+        result.custom_properties_changed = custom_properties_changed;
+        if result.properties_changed.is_empty() && result.custom_properties_changed.is_empty() {
             return result;
         }
 
@@ -0,0 +1,231 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A [`@layer`][layer] rule.
+//!
+//! [layer]: https://drafts.csswg.org/css-cascade-5/#layering
+
+use crate::parser::{Parse, ParserContext};
+use crate::shared_lock::{DeepCloneParams, DeepCloneWithLock, Locked};
+use crate::shared_lock::{SharedRwLock, SharedRwLockReadGuard, ToCssWithGuard};
+use crate::str::CssStringWriter;
+use crate::stylesheets::CssRules;
+use crate::values::CustomIdent;
+use cssparser::{CowRcStr, Parser, SourceLocation, Token};
+#[cfg(feature = "gecko")]
+use malloc_size_of::{MallocSizeOfOps, MallocUnconditionalShallowSizeOf};
+use servo_arc::Arc;
+use smallvec::SmallVec;
+use std::fmt::{self, Write};
+use style_traits::{CssWriter, ParseError, ToCss};
+
+/// The order of a given layer among all the layers of a cascade origin (or
+/// shadow tree). Later layers win over earlier ones for normal declarations,
+/// and the other way around for important ones.
+#[derive(Clone, Copy, Debug, Eq, Hash, MallocSizeOf, Ord, PartialEq, PartialOrd)]
+pub struct LayerOrder(u16);
+
+impl LayerOrder {
+    /// The order of the implicit outer layer, which contains the rules that
+    /// are not in any layer. It comes after every explicit layer.
+    #[inline]
+    pub const fn root() -> Self {
+        Self(std::u16::MAX - 1)
+    }
+
+    /// The order of the style attribute, which wins over every layer, both
+    /// for normal and important declarations.
+    #[inline]
+    pub const fn style_attribute() -> Self {
+        Self(std::u16::MAX)
+    }
+
+    /// Returns whether this is the order of the style attribute.
+    #[inline]
+    pub fn is_style_attribute_layer(&self) -> bool {
+        *self == Self::style_attribute()
+    }
+
+    /// The order of the first explicit layer.
+    #[inline]
+    pub const fn first() -> Self {
+        Self(0)
+    }
+
+    /// Moves to the order of the next layer. Layers beyond the last order we
+    /// can represent all share it.
+    #[inline]
+    pub fn inc(&mut self) {
+        if self.0 != Self::root().0 - 1 {
+            self.0 += 1;
+        }
+    }
+}
+
+/// A `<layer-name>`: a dot-separated list of identifiers.
+///
+/// <https://drafts.csswg.org/css-cascade-5/#typedef-layer-name>
+#[derive(Clone, Debug, Eq, Hash, MallocSizeOf, PartialEq, ToShmem)]
+pub struct LayerName(pub SmallVec<[CustomIdent; 1]>);
+
+impl Parse for LayerName {
+    fn parse<'i, 't>(
+        _: &ParserContext,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i>> {
+        // The CSS-wide keywords and `default` are reserved, as for any
+        // <custom-ident>, and so is `revert-layer`.
+        fn parse_ident<'i>(
+            location: SourceLocation,
+            ident: &CowRcStr<'i>,
+        ) -> Result<CustomIdent, ParseError<'i>> {
+            CustomIdent::from_ident(location, ident, &["revert-layer"])
+        }
+
+        let mut result = SmallVec::new();
+        let location = input.current_source_location();
+        let first = input.expect_ident_cloned()?;
+        result.push(parse_ident(location, &first)?);
+        loop {
+            // The identifiers are separated by periods, with no whitespace in
+            // between.
+            let next = input.try(|input| -> Result<_, ParseError<'i>> {
+                let location = input.current_source_location();
+                match *input.next_including_whitespace()? {
+                    Token::Delim('.') => {},
+                    ref t => return Err(location.new_unexpected_token_error(t.clone())),
+                }
+                let location = input.current_source_location();
+                match *input.next_including_whitespace()? {
+                    Token::Ident(ref ident) => Ok((location, ident.clone())),
+                    ref t => Err(location.new_unexpected_token_error(t.clone())),
+                }
+            });
+            match next {
+                Ok((location, ident)) => result.push(parse_ident(location, &ident)?),
+                Err(..) => break,
+            }
+        }
+        Ok(LayerName(result))
+    }
+}
+
+impl ToCss for LayerName {
+    fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
+    where
+        W: Write,
+    {
+        let mut first = true;
+        for name in self.0.iter() {
+            if !first {
+                dest.write_char('.')?;
+            }
+            first = false;
+            name.to_css(dest)?;
+        }
+        Ok(())
+    }
+}
+
+/// The two forms of a `@layer` rule.
+#[derive(Debug, ToShmem)]
+pub enum LayerRuleKind {
+    /// A block `@layer <name>? { ... }`, without a name for anonymous layers.
+    Block {
+        /// The layer name, if any.
+        name: Option<LayerName>,
+        /// The nested rules of this layer.
+        rules: Arc<Locked<CssRules>>,
+    },
+    /// A statement `@layer <name>, <name>, <name>;`, which only establishes
+    /// the order of the given layers.
+    Statement {
+        /// The list of layers this statement declares.
+        names: Vec<LayerName>,
+    },
+}
+
+/// A [`@layer`][layer] rule.
+///
+/// [layer]: https://drafts.csswg.org/css-cascade-5/#layering
+#[derive(Debug, ToShmem)]
+pub struct LayerRule {
+    /// The kind of layer rule this is.
+    pub kind: LayerRuleKind,
+    /// The source position where this layer rule was found.
+    pub source_location: SourceLocation,
+}
+
+impl LayerRule {
+    /// Measure heap usage.
+    #[cfg(feature = "gecko")]
+    pub fn size_of(&self, guard: &SharedRwLockReadGuard, ops: &mut MallocSizeOfOps) -> usize {
+        // Measurement of other fields may be added later.
+        match self.kind {
+            LayerRuleKind::Block { ref rules, .. } => {
+                rules.unconditional_shallow_size_of(ops) +
+                    rules.read_with(guard).size_of(guard, ops)
+            },
+            LayerRuleKind::Statement { .. } => 0,
+        }
+    }
+}
+
+impl ToCssWithGuard for LayerRule {
+    fn to_css(&self, guard: &SharedRwLockReadGuard, dest: &mut CssStringWriter) -> fmt::Result {
+        dest.write_str("@layer")?;
+        match self.kind {
+            LayerRuleKind::Block {
+                ref name,
+                ref rules,
+            } => {
+                if let Some(ref name) = *name {
+                    dest.write_char(' ')?;
+                    name.to_css(&mut CssWriter::new(dest))?;
+                }
+                rules.read_with(guard).to_css_block(guard, dest)
+            },
+            LayerRuleKind::Statement { ref names } => {
+                let mut first = true;
+                for name in names {
+                    dest.write_str(if first { " " } else { ", " })?;
+                    first = false;
+                    name.to_css(&mut CssWriter::new(dest))?;
+                }
+                dest.write_char(';')
+            },
+        }
+    }
+}
+
+impl DeepCloneWithLock for LayerRule {
+    fn deep_clone_with_lock(
+        &self,
+        lock: &SharedRwLock,
+        guard: &SharedRwLockReadGuard,
+        params: &DeepCloneParams,
+    ) -> Self {
+        Self {
+            kind: match self.kind {
+                LayerRuleKind::Block {
+                    ref name,
+                    ref rules,
+                } => LayerRuleKind::Block {
+                    name: name.clone(),
+                    rules: Arc::new(
+                        lock.wrap(
+                            rules
+                                .read_with(guard)
+                                .deep_clone_with_lock(lock, guard, params),
+                        ),
+                    ),
+                },
+                LayerRuleKind::Statement { ref names } => LayerRuleKind::Statement {
+                    names: names.clone(),
+                },
+            },
+            source_location: self.source_location.clone(),
+        }
+    }
+}
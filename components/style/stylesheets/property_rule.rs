@@ -0,0 +1,184 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The [`@property`][property] at-rule.
+//!
+//! [property]: https://drafts.css-houdini.org/css-properties-values-api-1/#at-property-rule
+
+use crate::context::QuirksMode;
+use crate::custom_properties::{Name, VariableValue};
+use crate::error_reporting::ContextualParseError;
+use crate::media_queries::Device;
+use crate::parser::ParserContext;
+use crate::properties_and_values::registry::{
+    self, PropertyRegistration, PropertyRegistrationError,
+};
+use crate::properties_and_values::syntax::Syntax;
+use crate::shared_lock::{SharedRwLockReadGuard, ToCssWithGuard};
+use crate::str::CssStringWriter;
+use crate::stylesheets::UrlExtraData;
+use cssparser::{serialize_name, serialize_string};
+use cssparser::{AtRuleParser, CowRcStr, DeclarationListParser, DeclarationParser};
+use cssparser::{Parser, SourceLocation};
+use selectors::parser::SelectorParseErrorKind;
+use servo_arc::Arc;
+use std::fmt::{self, Write};
+use style_traits::{CssWriter, ParseError, StyleParseErrorKind, ToCss};
+
+/// A valid `@property` rule.
+#[derive(Clone, Debug, ToShmem)]
+pub struct PropertyRule {
+    /// The name of the registered custom property, without the leading `--`.
+    pub name: Name,
+    /// The `syntax` descriptor.
+    pub syntax: Syntax,
+    /// The `inherits` descriptor.
+    pub inherits: bool,
+    /// The `initial-value` descriptor, if any.
+    pub initial_value: Option<Arc<VariableValue>>,
+    /// The URL of the stylesheet, to resolve values against.
+    pub url_data: UrlExtraData,
+    /// The source position where this rule was found.
+    pub source_location: SourceLocation,
+}
+
+impl PropertyRule {
+    /// Returns the registration this rule makes. Rules are validated when
+    /// parsed, so this only fails if the initial value can't be computed.
+    pub fn to_registration(
+        &self,
+        device: &Device,
+        quirks_mode: QuirksMode,
+    ) -> Result<PropertyRegistration, PropertyRegistrationError> {
+        PropertyRegistration::new(
+            self.name.clone(),
+            self.syntax.clone(),
+            self.inherits,
+            self.initial_value.clone(),
+            self.url_data.clone(),
+            device,
+            quirks_mode,
+        )
+    }
+}
+
+#[derive(Default)]
+struct PropertyDescriptors {
+    syntax: Option<Syntax>,
+    inherits: Option<bool>,
+    initial_value: Option<Arc<VariableValue>>,
+}
+
+/// Parses the block of a `@property` rule.
+///
+/// The rule is invalid, and ignored, if it lacks the `syntax` or `inherits`
+/// descriptors, or if its initial value doesn't match its syntax.
+pub fn parse_property_block<'i, 't>(
+    context: &ParserContext,
+    input: &mut Parser<'i, 't>,
+    name: Name,
+    source_location: SourceLocation,
+) -> Result<PropertyRule, ParseError<'i>> {
+    let start = input.current_source_location();
+    let mut descriptors = PropertyDescriptors::default();
+    {
+        let parser = PropertyRuleParser {
+            descriptors: &mut descriptors,
+        };
+        let mut iter = DeclarationListParser::new(input, parser);
+        while let Some(declaration) = iter.next() {
+            if let Err((error, slice)) = declaration {
+                let location = error.location;
+                let error = ContextualParseError::UnsupportedPropertyDescriptor(slice, error);
+                context.log_css_error(location, error)
+            }
+        }
+    }
+
+    let result = match (descriptors.syntax, descriptors.inherits) {
+        (None, _) => Err(ContextualParseError::InvalidPropertyRuleWithoutSyntax),
+        (_, None) => Err(ContextualParseError::InvalidPropertyRuleWithoutInherits),
+        (Some(syntax), Some(inherits)) => {
+            let initial_value = descriptors.initial_value;
+            registry::parse_initial_value(context, &syntax, initial_value.as_ref().map(|v| &**v))
+                .map(|_| PropertyRule {
+                    name,
+                    syntax,
+                    inherits,
+                    initial_value,
+                    url_data: context.url_data.clone(),
+                    source_location,
+                })
+                .map_err(ContextualParseError::InvalidPropertyRuleInitialValue)
+        },
+    };
+    result.map_err(|error| {
+        context.log_css_error(start, error);
+        input.new_custom_error(StyleParseErrorKind::UnspecifiedError)
+    })
+}
+
+struct PropertyRuleParser<'a> {
+    descriptors: &'a mut PropertyDescriptors,
+}
+
+/// Default methods reject all at rules.
+impl<'a, 'i> AtRuleParser<'i> for PropertyRuleParser<'a> {
+    type PreludeNoBlock = ();
+    type PreludeBlock = ();
+    type AtRule = ();
+    type Error = StyleParseErrorKind<'i>;
+}
+
+impl<'a, 'i> DeclarationParser<'i> for PropertyRuleParser<'a> {
+    type Declaration = ();
+    type Error = StyleParseErrorKind<'i>;
+
+    fn parse_value<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<(), ParseError<'i>> {
+        match_ignore_ascii_case! { &*name,
+            "syntax" => {
+                let location = input.current_source_location();
+                let syntax = input.expect_string()?.parse::<Syntax>().map_err(|()| {
+                    location.new_custom_error(StyleParseErrorKind::UnspecifiedError)
+                })?;
+                self.descriptors.syntax = Some(syntax);
+            },
+            "inherits" => {
+                let inherits = try_match_ident_ignore_ascii_case! { input,
+                    "true" => true,
+                    "false" => false,
+                };
+                self.descriptors.inherits = Some(inherits);
+            },
+            "initial-value" => {
+                self.descriptors.initial_value = Some(VariableValue::parse(input)?);
+            },
+            _ => return Err(input.new_custom_error(SelectorParseErrorKind::UnexpectedIdent(name.clone()))),
+        }
+        Ok(())
+    }
+}
+
+impl ToCssWithGuard for PropertyRule {
+    /// <https://drafts.css-houdini.org/css-properties-values-api-1/#serialize-a-csspropertyrule>
+    fn to_css(&self, _guard: &SharedRwLockReadGuard, dest: &mut CssStringWriter) -> fmt::Result {
+        dest.write_str("@property --")?;
+        serialize_name(&self.name, dest)?;
+        dest.write_str(" { syntax: ")?;
+        serialize_string(&self.syntax.to_css_string(), dest)?;
+        dest.write_str("; inherits: ")?;
+        dest.write_str(if self.inherits { "true" } else { "false" })?;
+        dest.write_str("; ")?;
+        if let Some(ref initial_value) = self.initial_value {
+            dest.write_str("initial-value: ")?;
+            initial_value.to_css(&mut CssWriter::new(dest))?;
+            dest.write_str("; ")?;
+        }
+        dest.write_char('}')
+    }
+}
@@ -5,6 +5,7 @@
 //! Parsing of the stylesheet contents.
 
 use crate::counter_style::{parse_counter_style_body, parse_counter_style_name_definition};
+use crate::custom_properties::{self, Name};
 use crate::error_reporting::ContextualParseError;
 use crate::font_face::parse_font_face_block;
 use crate::media_queries::MediaList;
@@ -13,9 +14,13 @@ use crate::properties::parse_property_declaration_list;
 use crate::selector_parser::{SelectorImpl, SelectorParser};
 use crate::shared_lock::{Locked, SharedRwLock};
 use crate::str::starts_with_ignore_ascii_case;
+use crate::stylesheets::container_rule::{ContainerCondition, ContainerRule};
 use crate::stylesheets::document_rule::DocumentCondition;
 use crate::stylesheets::font_feature_values_rule::parse_family_name_list;
+use crate::stylesheets::import_rule::ImportLayer;
 use crate::stylesheets::keyframes_rule::parse_keyframe_list;
+use crate::stylesheets::layer_rule::{LayerName, LayerRule, LayerRuleKind};
+use crate::stylesheets::property_rule::parse_property_block;
 use crate::stylesheets::stylesheet::Namespaces;
 use crate::stylesheets::supports_rule::SupportsCondition;
 use crate::stylesheets::viewport_rule;
@@ -25,9 +30,10 @@ use crate::stylesheets::{CssRule, CssRuleType, CssRules, RulesMutateError, Style
 use crate::stylesheets::{NamespaceRule, PageRule, StyleRule, SupportsRule, ViewportRule};
 use crate::values::computed::font::FamilyName;
 use crate::values::{CssUrl, CustomIdent, KeyframesName};
-use crate::{Namespace, Prefix};
+use crate::{Atom, Namespace, Prefix};
 use cssparser::{AtRuleParser, AtRuleType, Parser, QualifiedRuleParser, RuleListParser};
 use cssparser::{BasicParseError, BasicParseErrorKind, CowRcStr, SourceLocation};
+use selectors::parser::SelectorParseErrorKind;
 use selectors::SelectorList;
 use servo_arc::Arc;
 use style_traits::{ParseError, StyleParseErrorKind};
@@ -65,6 +71,9 @@ pub struct TopLevelRuleParser<'a> {
     pub insert_rule_context: Option<InsertRuleContext<'a>>,
     /// Whether @import rules will be allowed.
     pub allow_import_rules: AllowImportRules,
+    /// The name and location of the last `@layer` rule parsed as a block, in
+    /// case it turns out to be a statement. See `parse_layer_prelude`.
+    pub pending_layer_statement: Option<(LayerName, SourceLocation)>,
 }
 
 impl<'b> TopLevelRuleParser<'b> {
@@ -73,6 +82,25 @@ impl<'b> TopLevelRuleParser<'b> {
             shared_lock: self.shared_lock,
             context: &self.context,
             namespaces: &self.namespaces,
+            pending_layer_statement: None,
+        }
+    }
+
+    /// Returns the `@layer` rule that was last parsed as a block, if it turns
+    /// out to be a statement, because a semicolon followed its name instead of
+    /// a block. See `parse_layer_prelude`.
+    pub fn take_layer_statement(&mut self) -> Option<CssRule> {
+        let (name, location) = self.pending_layer_statement.take()?;
+        Some(self.rule_without_block(AtRuleNonBlockPrelude::Layer(vec![name]), location))
+    }
+
+    /// The state in which a `@layer` statement can be, given the current state:
+    /// unlike other rules, these can come before `@import` rules.
+    fn layer_statement_state(&self) -> State {
+        if self.state <= State::EarlyLayers {
+            State::EarlyLayers
+        } else {
+            State::Body
         }
     }
 
@@ -128,12 +156,14 @@ impl<'b> TopLevelRuleParser<'b> {
 pub enum State {
     /// We haven't started parsing rules.
     Start = 1,
+    /// We're parsing `@layer` statements before any `@import` rule.
+    EarlyLayers = 2,
     /// We're parsing `@import` rules.
-    Imports = 2,
+    Imports = 3,
     /// We're parsing `@namespace` rules.
-    Namespaces = 3,
+    Namespaces = 4,
     /// We're parsing the main body of the stylesheet.
-    Body = 4,
+    Body = 5,
 }
 
 #[derive(Clone, Debug, MallocSizeOf, ToShmem)]
@@ -165,14 +195,49 @@ pub enum AtRuleBlockPrelude {
     Page,
     /// A @document rule, with its conditional.
     Document(DocumentCondition),
+    /// A @layer block, with its layer name, if any.
+    Layer(Option<LayerName>),
+    /// A @container rule, with its container condition.
+    Container(ContainerCondition),
+    /// A @property rule, with the name of its custom property.
+    Property(Name),
 }
 
 /// A rule prelude for at-rule without block.
 pub enum AtRuleNonBlockPrelude {
     /// A @import rule prelude.
-    Import(CssUrl, Arc<Locked<MediaList>>),
+    Import(CssUrl, Arc<Locked<MediaList>>, Option<ImportLayer>),
     /// A @namespace rule prelude.
     Namespace(Option<Prefix>, Namespace),
+    /// A @layer statement, with its layer names.
+    Layer(Vec<LayerName>),
+}
+
+/// Parses the prelude of a `@layer` rule.
+///
+/// The parser needs to know whether an at-rule has a block before seeing what
+/// follows its prelude, so a `@layer` rule with a single name is parsed as a
+/// block, and its name is kept in `pending_statement`, so that it can be
+/// turned into a statement if a semicolon follows it instead.
+fn parse_layer_prelude<'i, 't>(
+    context: &ParserContext,
+    input: &mut Parser<'i, 't>,
+    pending_statement: &mut Option<(LayerName, SourceLocation)>,
+) -> Result<AtRuleType<AtRuleNonBlockPrelude, AtRuleBlockPrelude>, ParseError<'i>> {
+    let location = input.current_source_location();
+    let mut names = if input.is_exhausted() {
+        Vec::new()
+    } else {
+        input.parse_comma_separated(|input| LayerName::parse(context, input))?
+    };
+    Ok(match names.len() {
+        0 => AtRuleType::WithBlock(AtRuleBlockPrelude::Layer(None)),
+        1 => {
+            *pending_statement = Some((names[0].clone(), location));
+            AtRuleType::WithBlock(AtRuleBlockPrelude::Layer(names.pop()))
+        },
+        _ => AtRuleType::WithoutBlock(AtRuleNonBlockPrelude::Layer(names)),
+    })
 }
 
 impl<'a, 'i> AtRuleParser<'i> for TopLevelRuleParser<'a> {
@@ -206,10 +271,20 @@ impl<'a, 'i> AtRuleParser<'i> for TopLevelRuleParser<'a> {
                 let url_string = input.expect_url_or_string()?.as_ref().to_owned();
                 let url = CssUrl::parse_from_string(url_string, &self.context, CorsMode::None);
 
+                let layer = if input.try(|input| input.expect_ident_matching("layer")).is_ok() {
+                    Some(ImportLayer { name: None })
+                } else {
+                    let context = &self.context;
+                    input.try(|input| {
+                        input.expect_function_matching("layer")?;
+                        input.parse_nested_block(|input| LayerName::parse(context, input))
+                    }).ok().map(|name| ImportLayer { name: Some(name) })
+                };
+
                 let media = MediaList::parse(&self.context, input);
                 let media = Arc::new(self.shared_lock.wrap(media));
 
-                let prelude = AtRuleNonBlockPrelude::Import(url, media);
+                let prelude = AtRuleNonBlockPrelude::Import(url, media, layer);
 
                 return Ok(AtRuleType::WithoutBlock(prelude));
             },
@@ -231,6 +306,14 @@ impl<'a, 'i> AtRuleParser<'i> for TopLevelRuleParser<'a> {
                 let prelude = AtRuleNonBlockPrelude::Namespace(prefix, url);
                 return Ok(AtRuleType::WithoutBlock(prelude));
             },
+            "layer" => {
+                let state = self.layer_statement_state();
+                if !self.check_state(state) {
+                    return Err(input.new_custom_error(StyleParseErrorKind::UnspecifiedError))
+                }
+
+                return parse_layer_prelude(&self.context, input, &mut self.pending_layer_statement);
+            },
             // @charset is removed by rust-cssparser if it’s the first rule in the stylesheet
             // anything left is invalid.
             "charset" => {
@@ -254,6 +337,14 @@ impl<'a, 'i> AtRuleParser<'i> for TopLevelRuleParser<'a> {
         location: SourceLocation,
         input: &mut Parser<'i, 't>,
     ) -> Result<CssRule, ParseError<'i>> {
+        self.pending_layer_statement = None;
+        if let AtRuleBlockPrelude::Layer(..) = prelude {
+            // Unlike statements, `@layer` blocks can't come before `@import`
+            // rules.
+            if !self.check_state(State::Body) {
+                return Err(input.new_custom_error(StyleParseErrorKind::UnspecifiedError));
+            }
+        }
         AtRuleParser::parse_block(&mut self.nested(), prelude, location, input).map(|rule| {
             self.state = State::Body;
             rule
@@ -267,7 +358,7 @@ impl<'a, 'i> AtRuleParser<'i> for TopLevelRuleParser<'a> {
         source_location: SourceLocation,
     ) -> CssRule {
         match prelude {
-            AtRuleNonBlockPrelude::Import(url, media) => {
+            AtRuleNonBlockPrelude::Import(url, media, layer) => {
                 let loader = self
                     .loader
                     .expect("Expected a stylesheet loader for @import");
@@ -278,6 +369,7 @@ impl<'a, 'i> AtRuleParser<'i> for TopLevelRuleParser<'a> {
                     &self.context,
                     &self.shared_lock,
                     media,
+                    layer,
                 );
 
                 self.state = State::Imports;
@@ -299,6 +391,10 @@ impl<'a, 'i> AtRuleParser<'i> for TopLevelRuleParser<'a> {
                     source_location,
                 })))
             },
+            AtRuleNonBlockPrelude::Layer(..) => {
+                self.state = self.layer_statement_state();
+                AtRuleParser::rule_without_block(&mut self.nested(), prelude, source_location)
+            },
         }
     }
 }
@@ -341,6 +437,7 @@ struct NestedRuleParser<'a, 'b: 'a> {
     shared_lock: &'a SharedRwLock,
     context: &'a ParserContext<'b>,
     namespaces: &'a Namespaces,
+    pending_layer_statement: Option<(LayerName, SourceLocation)>,
 }
 
 impl<'a, 'b> NestedRuleParser<'a, 'b> {
@@ -355,6 +452,7 @@ impl<'a, 'b> NestedRuleParser<'a, 'b> {
             shared_lock: self.shared_lock,
             context: &context,
             namespaces: self.namespaces,
+            pending_layer_statement: None,
         };
 
         let mut iter = RuleListParser::new_for_nested_rule(input, nested_parser);
@@ -363,6 +461,10 @@ impl<'a, 'b> NestedRuleParser<'a, 'b> {
             match result {
                 Ok(rule) => rules.push(rule),
                 Err((error, slice)) => {
+                    if let Some(rule) = iter.parser.take_layer_statement() {
+                        rules.push(rule);
+                        continue;
+                    }
                     let location = error.location;
                     let error = ContextualParseError::InvalidRule(slice, error);
                     self.context.log_css_error(location, error);
@@ -371,6 +473,11 @@ impl<'a, 'b> NestedRuleParser<'a, 'b> {
         }
         CssRules::new(rules, self.shared_lock)
     }
+
+    fn take_layer_statement(&mut self) -> Option<CssRule> {
+        let (name, location) = self.pending_layer_statement.take()?;
+        Some(self.rule_without_block(AtRuleNonBlockPrelude::Layer(vec![name]), location))
+    }
 }
 
 impl<'a, 'b, 'i> AtRuleParser<'i> for NestedRuleParser<'a, 'b> {
@@ -454,6 +561,25 @@ impl<'a, 'b, 'i> AtRuleParser<'i> for NestedRuleParser<'a, 'b> {
                 let cond = DocumentCondition::parse(self.context, input)?;
                 Ok(AtRuleType::WithBlock(AtRuleBlockPrelude::Document(cond)))
             },
+            "layer" => {
+                parse_layer_prelude(self.context, input, &mut self.pending_layer_statement)
+            },
+            "container" => {
+                if !cfg!(feature = "servo-layout-2020") {
+                    // Query containers are only laid out by Layout 2020.
+                    return Err(input.new_custom_error(StyleParseErrorKind::UnsupportedAtRule(name.clone())))
+                }
+                let condition = ContainerCondition::parse(self.context, input)?;
+                Ok(AtRuleType::WithBlock(AtRuleBlockPrelude::Container(condition)))
+            },
+            "property" => {
+                let location = input.current_source_location();
+                let ident = input.expect_ident_cloned()?;
+                let name = custom_properties::parse_name(&ident).map_err(|()| {
+                    location.new_custom_error(SelectorParseErrorKind::UnexpectedIdent(ident.clone()))
+                })?;
+                Ok(AtRuleType::WithBlock(AtRuleBlockPrelude::Property(Atom::from(name))))
+            },
             _ => Err(input.new_custom_error(StyleParseErrorKind::UnsupportedAtRule(name.clone())))
         }
     }
@@ -464,6 +590,7 @@ impl<'a, 'b, 'i> AtRuleParser<'i> for NestedRuleParser<'a, 'b> {
         source_location: SourceLocation,
         input: &mut Parser<'i, 't>,
     ) -> Result<CssRule, ParseError<'i>> {
+        self.pending_layer_statement = None;
         match prelude {
             AtRuleBlockPrelude::FontFace => {
                 let context = ParserContext::new_with_rule_type(
@@ -574,6 +701,51 @@ impl<'a, 'b, 'i> AtRuleParser<'i> for NestedRuleParser<'a, 'b> {
                     },
                 ))))
             },
+            AtRuleBlockPrelude::Layer(name) => {
+                Ok(CssRule::Layer(Arc::new(self.shared_lock.wrap(LayerRule {
+                    kind: LayerRuleKind::Block {
+                        name,
+                        rules: self.parse_nested_rules(input, CssRuleType::Layer),
+                    },
+                    source_location,
+                }))))
+            },
+            AtRuleBlockPrelude::Container(condition) => Ok(CssRule::Container(Arc::new(
+                self.shared_lock.wrap(ContainerRule {
+                    condition: Arc::new(condition),
+                    rules: self.parse_nested_rules(input, CssRuleType::Container),
+                    source_location,
+                }),
+            ))),
+            AtRuleBlockPrelude::Property(name) => {
+                let context = ParserContext::new_with_rule_type(
+                    self.context,
+                    CssRuleType::Property,
+                    self.namespaces,
+                );
+
+                Ok(CssRule::Property(Arc::new(self.shared_lock.wrap(
+                    parse_property_block(&context, input, name, source_location)?,
+                ))))
+            },
+        }
+    }
+
+    fn rule_without_block(
+        &mut self,
+        prelude: AtRuleNonBlockPrelude,
+        source_location: SourceLocation,
+    ) -> CssRule {
+        match prelude {
+            AtRuleNonBlockPrelude::Layer(names) => {
+                CssRule::Layer(Arc::new(self.shared_lock.wrap(LayerRule {
+                    kind: LayerRuleKind::Statement { names },
+                    source_location,
+                })))
+            },
+            AtRuleNonBlockPrelude::Import(..) | AtRuleNonBlockPrelude::Namespace(..) => {
+                unreachable!("Only @layer statements are parsed in nested rule lists")
+            },
         }
     }
 }
@@ -7,8 +7,8 @@
 use crate::context::QuirksMode;
 use crate::media_queries::Device;
 use crate::shared_lock::SharedRwLockReadGuard;
-use crate::stylesheets::StylesheetInDocument;
 use crate::stylesheets::{CssRule, DocumentRule, ImportRule, MediaRule, SupportsRule};
+use crate::stylesheets::{LayerRuleKind, StylesheetInDocument};
 use smallvec::SmallVec;
 use std::slice;
 
@@ -62,78 +62,30 @@ where
     type Item = &'a CssRule;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut nested_iter_finished = false;
         while !self.stack.is_empty() {
-            if nested_iter_finished {
-                self.stack.pop();
-                nested_iter_finished = false;
+            let rule = match self.stack.last_mut().unwrap().next() {
+                Some(r) => r,
+                None => {
+                    self.stack.pop();
+                    continue;
+                },
+            };
+
+            let mut effective = true;
+            let children = Self::children(
+                rule,
+                self.device,
+                self.quirks_mode,
+                self.guard,
+                &mut effective,
+            );
+            if !effective {
                 continue;
             }
 
-            let rule;
-            let sub_iter = {
-                let nested_iter = self.stack.last_mut().unwrap();
-                rule = match nested_iter.next() {
-                    Some(r) => r,
-                    None => {
-                        nested_iter_finished = true;
-                        continue;
-                    },
-                };
-
-                match *rule {
-                    CssRule::Namespace(_) |
-                    CssRule::Style(_) |
-                    CssRule::FontFace(_) |
-                    CssRule::CounterStyle(_) |
-                    CssRule::Viewport(_) |
-                    CssRule::Keyframes(_) |
-                    CssRule::Page(_) |
-                    CssRule::FontFeatureValues(_) => return Some(rule),
-                    CssRule::Import(ref import_rule) => {
-                        let import_rule = import_rule.read_with(self.guard);
-                        if !C::process_import(
-                            self.guard,
-                            self.device,
-                            self.quirks_mode,
-                            import_rule,
-                        ) {
-                            continue;
-                        }
-                        import_rule.stylesheet.rules(self.guard).iter()
-                    },
-                    CssRule::Document(ref doc_rule) => {
-                        let doc_rule = doc_rule.read_with(self.guard);
-                        if !C::process_document(self.guard, self.device, self.quirks_mode, doc_rule)
-                        {
-                            continue;
-                        }
-                        doc_rule.rules.read_with(self.guard).0.iter()
-                    },
-                    CssRule::Media(ref lock) => {
-                        let media_rule = lock.read_with(self.guard);
-                        if !C::process_media(self.guard, self.device, self.quirks_mode, media_rule)
-                        {
-                            continue;
-                        }
-                        media_rule.rules.read_with(self.guard).0.iter()
-                    },
-                    CssRule::Supports(ref lock) => {
-                        let supports_rule = lock.read_with(self.guard);
-                        if !C::process_supports(
-                            self.guard,
-                            self.device,
-                            self.quirks_mode,
-                            supports_rule,
-                        ) {
-                            continue;
-                        }
-                        supports_rule.rules.read_with(self.guard).0.iter()
-                    },
-                }
-            };
-
-            self.stack.push(sub_iter);
+            if let Some(children) = children {
+                self.stack.push(children);
+            }
             return Some(rule);
         }
 
@@ -141,6 +93,78 @@ where
     }
 }
 
+impl<'a, 'b, C> RulesIterator<'a, 'b, C>
+where
+    'b: 'a,
+    C: NestedRuleIterationCondition + 'static,
+{
+    /// Returns the nested rules of `rule`, if it has any.
+    ///
+    /// `effective` is set to false if, according to `C`, neither the rule nor
+    /// its nested rules should be processed.
+    pub fn children(
+        rule: &'a CssRule,
+        device: &'a Device,
+        quirks_mode: QuirksMode,
+        guard: &'a SharedRwLockReadGuard<'b>,
+        effective: &mut bool,
+    ) -> Option<slice::Iter<'a, CssRule>> {
+        *effective = true;
+        match *rule {
+            CssRule::Namespace(_) |
+            CssRule::Style(_) |
+            CssRule::FontFace(_) |
+            CssRule::CounterStyle(_) |
+            CssRule::Viewport(_) |
+            CssRule::Keyframes(_) |
+            CssRule::Page(_) |
+            CssRule::FontFeatureValues(_) |
+            CssRule::Property(_) => None,
+            CssRule::Import(ref import_rule) => {
+                let import_rule = import_rule.read_with(guard);
+                if !C::process_import(guard, device, quirks_mode, import_rule) {
+                    *effective = false;
+                    return None;
+                }
+                Some(import_rule.stylesheet.rules(guard).iter())
+            },
+            CssRule::Document(ref doc_rule) => {
+                let doc_rule = doc_rule.read_with(guard);
+                if !C::process_document(guard, device, quirks_mode, doc_rule) {
+                    *effective = false;
+                    return None;
+                }
+                Some(doc_rule.rules.read_with(guard).0.iter())
+            },
+            CssRule::Media(ref lock) => {
+                let media_rule = lock.read_with(guard);
+                if !C::process_media(guard, device, quirks_mode, media_rule) {
+                    *effective = false;
+                    return None;
+                }
+                Some(media_rule.rules.read_with(guard).0.iter())
+            },
+            CssRule::Supports(ref lock) => {
+                let supports_rule = lock.read_with(guard);
+                if !C::process_supports(guard, device, quirks_mode, supports_rule) {
+                    *effective = false;
+                    return None;
+                }
+                Some(supports_rule.rules.read_with(guard).0.iter())
+            },
+            CssRule::Layer(ref lock) => match lock.read_with(guard).kind {
+                LayerRuleKind::Block { ref rules, .. } => Some(rules.read_with(guard).0.iter()),
+                LayerRuleKind::Statement { .. } => None,
+            },
+            // Container queries are evaluated against each element when
+            // matching, so their nested rules are always effective.
+            CssRule::Container(ref lock) => {
+                Some(lock.read_with(guard).rules.read_with(guard).0.iter())
+            },
+        }
+    }
+}
+
 /// RulesIterator.
 pub trait NestedRuleIterationCondition {
     /// Whether we should process the nested rules in a given `@import` rule.
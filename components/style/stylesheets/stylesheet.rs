@@ -378,7 +378,9 @@ impl SanitizationKind {
             CssRule::Document(..) |
             CssRule::Media(..) |
             CssRule::Supports(..) |
-            CssRule::Import(..) => false,
+            CssRule::Import(..) |
+            CssRule::Layer(..) |
+            CssRule::Container(..) => false,
 
             CssRule::FontFace(..) | CssRule::Namespace(..) | CssRule::Style(..) => true,
 
@@ -386,7 +388,8 @@ impl SanitizationKind {
             CssRule::Page(..) |
             CssRule::FontFeatureValues(..) |
             CssRule::Viewport(..) |
-            CssRule::CounterStyle(..) => !is_standard,
+            CssRule::CounterStyle(..) |
+            CssRule::Property(..) => !is_standard,
         }
     }
 }
@@ -497,6 +500,7 @@ impl Stylesheet {
             insert_rule_context: None,
             namespaces,
             allow_import_rules,
+            pending_layer_statement: None,
         };
 
         {
@@ -505,6 +509,9 @@ impl Stylesheet {
             loop {
                 let rule_start = iter.input.position().byte_index();
                 let result = match iter.next() {
+                    Some(Err(_)) if iter.parser.pending_layer_statement.is_some() => {
+                        Ok(iter.parser.take_layer_statement().unwrap())
+                    },
                     Some(result) => result,
                     None => break,
                 };
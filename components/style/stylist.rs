@@ -6,16 +6,19 @@
 
 use crate::applicable_declarations::{ApplicableDeclarationBlock, ApplicableDeclarationList};
 use crate::context::{CascadeInputs, QuirksMode};
+use crate::custom_properties::Name;
 use crate::dom::{TElement, TShadowRoot};
 use crate::element_state::{DocumentState, ElementState};
 use crate::font_metrics::FontMetricsProvider;
 #[cfg(feature = "gecko")]
 use crate::gecko_bindings::structs::{ServoStyleSetSizes, StyleRuleInclusion};
+use crate::hash::FxHashMap;
 use crate::invalidation::element::invalidation_map::InvalidationMap;
 use crate::invalidation::media_queries::{EffectiveMediaQueryResults, ToMediaListKey};
 use crate::media_queries::Device;
 use crate::properties::{self, CascadeMode, ComputedValues};
 use crate::properties::{AnimationRules, PropertyDeclarationBlock};
+use crate::properties_and_values::registry::{PropertyRegistration, PropertyRegistrations};
 use crate::rule_cache::{RuleCache, RuleCacheConditions};
 use crate::rule_collector::{containing_shadow_ignoring_svg_use, RuleCollector};
 use crate::rule_tree::{CascadeLevel, RuleTree, StrongRuleNode, StyleSource};
@@ -24,13 +27,16 @@ use crate::selector_parser::{PerPseudoElementMap, PseudoElement, SelectorImpl, S
 use crate::shared_lock::{Locked, SharedRwLockReadGuard, StylesheetGuards};
 use crate::stylesheet_set::{DataValidity, DocumentStylesheetSet, SheetRebuildKind};
 use crate::stylesheet_set::{DocumentStylesheetFlusher, SheetCollectionFlusher};
+use crate::stylesheets::container_rule::ContainerCondition;
 use crate::stylesheets::keyframes_rule::KeyframesAnimation;
+use crate::stylesheets::layer_rule::{LayerName, LayerOrder};
+use crate::stylesheets::rules_iterator::EffectiveRulesIterator;
 use crate::stylesheets::viewport_rule::{self, MaybeNew, ViewportRule};
 use crate::stylesheets::StyleRule;
 use crate::stylesheets::StylesheetInDocument;
 #[cfg(feature = "gecko")]
 use crate::stylesheets::{CounterStyleRule, FontFaceRule, FontFeatureValuesRule, PageRule};
-use crate::stylesheets::{CssRule, Origin, OriginSet, PerOrigin, PerOriginIter};
+use crate::stylesheets::{CssRule, LayerRuleKind, Origin, OriginSet, PerOrigin, PerOriginIter};
 use crate::thread_state::{self, ThreadState};
 use crate::{Atom, LocalName, Namespace, WeakAtom};
 use fallible::FallibleVec;
@@ -42,14 +48,15 @@ use selectors::attr::{CaseSensitivity, NamespaceConstraint};
 use selectors::bloom::BloomFilter;
 use selectors::matching::VisitedHandlingMode;
 use selectors::matching::{matches_selector, ElementSelectorFlags, MatchingContext, MatchingMode};
-use selectors::parser::{AncestorHashes, Combinator, Component, Selector, SelectorIter};
+use selectors::parser::{AncestorHashes, Combinator, Component, RelativeSelector};
+use selectors::parser::{Selector, SelectorIter};
 use selectors::visitor::SelectorVisitor;
 use selectors::NthIndexCache;
 use servo_arc::{Arc, ArcBorrow};
 use smallbitvec::SmallBitVec;
 use smallvec::SmallVec;
 use std::sync::Mutex;
-use std::{mem, ops};
+use std::{mem, ops, slice};
 use style_traits::viewport::ViewportConstraints;
 
 /// The type of the stylesheets that the stylist contains.
@@ -122,6 +129,7 @@ impl UserAgentCascadeDataCache {
                 Some(&mut new_data.precomputed_pseudo_element_decls),
             )?;
         }
+        new_data.cascade_data.compute_layer_order();
 
         let new_data = Arc::new(new_data);
         self.entries.push(new_data.clone());
@@ -388,6 +396,13 @@ pub struct Stylist {
 
     /// The total number of times the stylist has been rebuilt.
     num_rebuilds: usize,
+
+    /// The custom properties registered with `CSS.registerProperty()`.
+    script_custom_property_registrations: PropertyRegistrations,
+
+    /// The custom property registrations in effect, from both script and
+    /// `@property` rules.
+    custom_property_registrations: PropertyRegistrations,
 }
 
 /// What cascade levels to include when styling elements.
@@ -426,6 +441,8 @@ impl Stylist {
             author_styles_enabled: AuthorStylesEnabled::Yes,
             rule_tree: RuleTree::new(),
             num_rebuilds: 0,
+            script_custom_property_registrations: Default::default(),
+            custom_property_registrations: Default::default(),
         }
     }
 
@@ -560,9 +577,46 @@ impl Stylist {
             .rebuild(&self.device, self.quirks_mode, flusher, guards)
             .unwrap_or_else(|_| warn!("OOM in Stylist::flush"));
 
+        self.rebuild_custom_property_registrations();
+
         had_invalidations
     }
 
+    /// Registers a custom property from script. The caller is responsible for
+    /// rejecting duplicate registrations, and for restyling the document.
+    pub fn register_custom_property(&mut self, registration: PropertyRegistration) {
+        self.script_custom_property_registrations
+            .insert(registration.name.clone(), registration);
+        self.rebuild_custom_property_registrations();
+    }
+
+    /// Merges the registrations from script and from `@property` rules.
+    /// Script registrations win over rules, and rules from higher origins win
+    /// over rules from lower ones.
+    fn rebuild_custom_property_registrations(&mut self) {
+        let mut registrations = self.script_custom_property_registrations.clone();
+        for (data, _) in self.cascade_data.iter_origins() {
+            for (name, registration) in data.custom_property_registrations.iter() {
+                registrations
+                    .entry(name.clone())
+                    .or_insert_with(|| registration.clone());
+            }
+        }
+        self.custom_property_registrations = registrations;
+    }
+
+    /// Returns the registration of the given custom property, if any.
+    #[inline]
+    pub fn get_custom_property_registration(&self, name: &Name) -> Option<&PropertyRegistration> {
+        self.custom_property_registrations.get(name)
+    }
+
+    /// Returns all the custom property registrations in effect.
+    #[inline]
+    pub fn custom_property_registrations(&self) -> &PropertyRegistrations {
+        &self.custom_property_registrations
+    }
+
     /// Insert a given stylesheet before another stylesheet in the document.
     pub fn insert_stylesheet_before(
         &mut self,
@@ -890,7 +944,7 @@ impl Stylist {
         //
         // FIXME(emilio): We should assert that it holds if pseudo.is_none()!
         properties::cascade::<E>(
-            &self.device,
+            self,
             pseudo,
             inputs.rules.as_ref().unwrap_or(self.rule_tree.root()),
             guards,
@@ -1333,7 +1387,7 @@ impl Stylist {
         // reversing this as it shouldn't be slow anymore, and should avoid
         // generating two instantiations of apply_declarations.
         properties::apply_declarations::<E, _, _>(
-            &self.device,
+            self,
             /* pseudo = */ None,
             self.rule_tree.root(),
             guards,
@@ -1571,7 +1625,8 @@ fn component_needs_revalidation(
         Component::NthLastOfType(..) |
         Component::FirstOfType |
         Component::LastOfType |
-        Component::OnlyOfType => true,
+        Component::OnlyOfType |
+        Component::Has(..) => true,
         Component::NonTSPseudoClass(ref p) => p.needs_cache_revalidation(),
         _ => false,
     }
@@ -1609,6 +1664,24 @@ impl<'a> SelectorVisitor for StylistSelectorVisitor<'a> {
         true
     }
 
+    fn visit_relative_selector_list(&mut self, list: &[RelativeSelector<Self::Impl>]) -> bool {
+        for relative in list {
+            // The arguments of :has() never match the element itself, so its
+            // ID can't be used to map the rule.
+            let mut nested = StylistSelectorVisitor {
+                passed_rightmost_selector: true,
+                needs_revalidation: &mut *self.needs_revalidation,
+                attribute_dependencies: &mut *self.attribute_dependencies,
+                state_dependencies: &mut *self.state_dependencies,
+                document_state_dependencies: &mut *self.document_state_dependencies,
+                mapped_ids: &mut *self.mapped_ids,
+            };
+            let _ret = relative.selector.visit(&mut nested);
+            debug_assert!(_ret, "We never return false");
+        }
+        true
+    }
+
     fn visit_attribute_selector(
         &mut self,
         _ns: &NamespaceConstraint<&Namespace>,
@@ -1791,12 +1864,30 @@ pub struct CascadeData {
     /// by name.
     animations: PrecomputedHashMap<Atom, KeyframesAnimation>,
 
+    /// A map with the custom properties registered by `@property` rules at
+    /// this `CascadeData`'s origin, indexed by name.
+    custom_property_registrations: PropertyRegistrations,
+
     /// Effective media query results cached from the last rebuild.
     effective_media_query_results: EffectiveMediaQueryResults,
 
     /// Extra data, like different kinds of rules, etc.
     extra_data: ExtraStyleData,
 
+    /// The cascade layers at this `CascadeData`'s origin, indexed by
+    /// `LayerId`. The first one is the implicit outer layer, which contains
+    /// the rules that are not in any layer.
+    layers: SmallVec<[CascadeLayer; 1]>,
+
+    /// The named layers, indexed by their parent layer and the last
+    /// identifier of their name.
+    layer_ids: FxHashMap<(LayerId, Atom), LayerId>,
+
+    /// The `@container` conditions at this `CascadeData`'s origin, indexed
+    /// by `ContainerConditionId`. The first one is the absence of any
+    /// condition, for the rules that are not in any `@container` rule.
+    container_conditions: SmallVec<[ContainerConditionReference; 1]>,
+
     /// A monotonically increasing counter to represent the order on which a
     /// style rule appears in a stylesheet, needed to sort them by source order.
     rules_source_order: u32,
@@ -1823,8 +1914,12 @@ impl CascadeData {
             mapped_ids: PrecomputedHashSet::default(),
             selectors_for_cache_revalidation: SelectorMap::new(),
             animations: Default::default(),
+            custom_property_registrations: Default::default(),
             extra_data: ExtraStyleData::default(),
             effective_media_query_results: EffectiveMediaQueryResults::new(),
+            layers: smallvec::smallvec![CascadeLayer::root()],
+            layer_ids: FxHashMap::default(),
+            container_conditions: smallvec::smallvec![ContainerConditionReference::none()],
             rules_source_order: 0,
             num_selectors: 0,
             num_declarations: 0,
@@ -1865,10 +1960,108 @@ impl CascadeData {
                 /* precomputed_pseudo_element_decls = */ None,
             )?;
         }
+        self.compute_layer_order();
 
         Ok(())
     }
 
+    /// Returns the order of the given cascade layer.
+    #[inline]
+    pub fn layer_order_for(&self, id: LayerId) -> LayerOrder {
+        self.layers[id.0 as usize].order
+    }
+
+    /// Assigns the final order of every cascade layer, once all of them have
+    /// been declared.
+    ///
+    /// Layers are ordered by their first declaration, and the sub-layers of a
+    /// layer come before the rules directly in it.
+    fn compute_layer_order(&mut self) {
+        fn compute_order(layers: &mut [CascadeLayer], id: LayerId, next: &mut LayerOrder) {
+            for i in 0..layers[id.0 as usize].children.len() {
+                let child = layers[id.0 as usize].children[i];
+                compute_order(layers, child, next);
+                layers[child.0 as usize].order = *next;
+                next.inc();
+            }
+        }
+
+        let mut next = LayerOrder::first();
+        compute_order(&mut self.layers, LayerId::root(), &mut next);
+        self.layers[0].order = LayerOrder::root();
+    }
+
+    /// Returns the layer with the given name in `parent`, declaring it and
+    /// the layers it is nested in if needed. Anonymous layers are always new.
+    fn declare_layer(
+        &mut self,
+        parent: LayerId,
+        name: Option<&LayerName>,
+    ) -> Result<LayerId, FailedAllocationError> {
+        let name = match name {
+            Some(name) => name,
+            None => return Ok(self.new_layer(parent)),
+        };
+
+        let mut id = parent;
+        for ident in name.0.iter() {
+            let key = (id, ident.0.clone());
+            id = match self.layer_ids.get(&key) {
+                Some(&child) => child,
+                None => {
+                    let child = self.new_layer(id);
+                    self.layer_ids.try_insert(key, child)?;
+                    child
+                },
+            };
+        }
+        Ok(id)
+    }
+
+    fn new_layer(&mut self, parent: LayerId) -> LayerId {
+        let id = LayerId(self.layers.len() as u32);
+        self.layers.push(CascadeLayer {
+            order: LayerOrder::first(),
+            children: Vec::new(),
+        });
+        self.layers[parent.0 as usize].children.push(id);
+        id
+    }
+
+    /// Returns whether any rule at this `CascadeData`'s origin is in a
+    /// `@container` rule.
+    #[inline]
+    pub fn has_container_conditions(&self) -> bool {
+        self.container_conditions.len() > 1
+    }
+
+    /// Returns whether the given `@container` condition, and the conditions
+    /// it is nested in, match the query containers of `element`.
+    pub fn container_condition_matches<E>(
+        &self,
+        mut id: ContainerConditionId,
+        element: E,
+        device: &Device,
+        quirks_mode: QuirksMode,
+    ) -> bool
+    where
+        E: TElement,
+    {
+        loop {
+            let reference = &self.container_conditions[id.0 as usize];
+            let condition = match reference.condition {
+                Some(ref condition) => condition,
+                None => return true,
+            };
+            let name = condition.name.as_ref().map(|name| &name.0);
+            let size = element.query_container_size(name);
+            if !condition.matches(device, quirks_mode, size) {
+                return false;
+            }
+            id = reference.parent;
+        }
+    }
+
     /// Returns the invalidation map.
     pub fn invalidation_map(&self) -> &InvalidationMap {
         &self.invalidation_map
@@ -1984,13 +2177,54 @@ impl CascadeData {
             return Ok(());
         }
 
-        let origin = stylesheet.origin(guard);
-
         if rebuild_kind.should_rebuild_invalidation() {
             self.effective_media_query_results.saw_effective(stylesheet);
         }
 
-        for rule in stylesheet.effective_rules(device, guard) {
+        self.add_rule_list(
+            stylesheet.rules(guard).iter(),
+            device,
+            quirks_mode,
+            stylesheet,
+            guard,
+            rebuild_kind,
+            LayerId::root(),
+            ContainerConditionId::none(),
+            precomputed_pseudo_element_decls,
+        )
+    }
+
+    // Returns Err(..) to signify OOM
+    fn add_rule_list<S>(
+        &mut self,
+        rules: slice::Iter<CssRule>,
+        device: &Device,
+        quirks_mode: QuirksMode,
+        stylesheet: &S,
+        guard: &SharedRwLockReadGuard,
+        rebuild_kind: SheetRebuildKind,
+        current_layer: LayerId,
+        current_container_condition: ContainerConditionId,
+        mut precomputed_pseudo_element_decls: Option<&mut PrecomputedPseudoElementDeclarations>,
+    ) -> Result<(), FailedAllocationError>
+    where
+        S: StylesheetInDocument + ToMediaListKey + 'static,
+    {
+        let origin = stylesheet.origin(guard);
+
+        for rule in rules {
+            let mut effective = true;
+            let children =
+                EffectiveRulesIterator::children(rule, device, quirks_mode, guard, &mut effective);
+            if !effective {
+                continue;
+            }
+
+            // The layer and container condition the nested rules of this
+            // rule, if any, belong to.
+            let mut children_layer = current_layer;
+            let mut children_container_condition = current_container_condition;
+
             match *rule {
                 CssRule::Style(ref locked) => {
                     let style_rule = locked.read_with(&guard);
@@ -2014,6 +2248,7 @@ impl CascadeData {
                                         self.rules_source_order,
                                         CascadeLevel::UANormal,
                                         selector.specificity(),
+                                        LayerOrder::root(),
                                     ));
                                 continue;
                             }
@@ -2029,6 +2264,8 @@ impl CascadeData {
                             hashes,
                             locked.clone(),
                             self.rules_source_order,
+                            current_layer,
+                            current_container_condition,
                         );
 
                         if rebuild_kind.should_rebuild_invalidation() {
@@ -2093,14 +2330,14 @@ impl CascadeData {
                     self.rules_source_order += 1;
                 },
                 CssRule::Import(ref lock) => {
+                    let import_rule = lock.read_with(guard);
                     if rebuild_kind.should_rebuild_invalidation() {
-                        let import_rule = lock.read_with(guard);
                         self.effective_media_query_results
                             .saw_effective(import_rule);
                     }
-
-                    // NOTE: effective_rules visits the inner stylesheet if
-                    // appropriate.
+                    if let Some(ref layer) = import_rule.layer {
+                        children_layer = self.declare_layer(current_layer, layer.name.as_ref())?;
+                    }
                 },
                 CssRule::Media(ref lock) => {
                     if rebuild_kind.should_rebuild_invalidation() {
@@ -2108,6 +2345,27 @@ impl CascadeData {
                         self.effective_media_query_results.saw_effective(media_rule);
                     }
                 },
+                CssRule::Layer(ref lock) => match lock.read_with(guard).kind {
+                    LayerRuleKind::Block { ref name, .. } => {
+                        children_layer = self.declare_layer(current_layer, name.as_ref())?;
+                    },
+                    LayerRuleKind::Statement { ref names } => {
+                        for name in names {
+                            self.declare_layer(current_layer, Some(name))?;
+                        }
+                    },
+                },
+                CssRule::Container(ref lock) => {
+                    let container_rule = lock.read_with(guard);
+                    children_container_condition =
+                        ContainerConditionId(self.container_conditions.len() as u32);
+                    self.container_conditions.push(ContainerConditionReference {
+                        parent: current_container_condition,
+                        condition: Some(container_rule.condition.clone()),
+                    });
+                },
+                // TODO: Keyframes in different cascade layers should be
+                // ordered by layer too, rather than by source order only.
                 CssRule::Keyframes(ref keyframes_rule) => {
                     let keyframes_rule = keyframes_rule.read_with(guard);
                     debug!("Found valid keyframes rule: {:?}", *keyframes_rule);
@@ -2128,6 +2386,19 @@ impl CascadeData {
                             .try_insert(keyframes_rule.name.as_atom().clone(), animation)?;
                     }
                 },
+                CssRule::Property(ref rule) => {
+                    // Rules that come later win over earlier ones.
+                    let rule = rule.read_with(guard);
+                    match rule.to_registration(device, quirks_mode) {
+                        Ok(registration) => {
+                            self.custom_property_registrations
+                                .try_insert(rule.name.clone(), registration)?;
+                        },
+                        Err(error) => {
+                            debug!("Ignoring @property rule {:?}: {:?}", rule.name, error);
+                        },
+                    }
+                },
                 #[cfg(feature = "gecko")]
                 CssRule::FontFace(ref rule) => {
                     self.extra_data.add_font_face(rule);
@@ -2147,6 +2418,22 @@ impl CascadeData {
                 // We don't care about any other rule.
                 _ => {},
             }
+
+            if let Some(children) = children {
+                self.add_rule_list(
+                    children,
+                    device,
+                    quirks_mode,
+                    stylesheet,
+                    guard,
+                    rebuild_kind,
+                    children_layer,
+                    children_container_condition,
+                    precomputed_pseudo_element_decls
+                        .as_mut()
+                        .map(|decls| &mut **decls),
+                )?;
+            }
         }
 
         Ok(())
@@ -2197,6 +2484,9 @@ impl CascadeData {
                 CssRule::Page(..) |
                 CssRule::Viewport(..) |
                 CssRule::Document(..) |
+                CssRule::Layer(..) |
+                CssRule::Container(..) |
+                CssRule::Property(..) |
                 CssRule::FontFeatureValues(..) => {
                     // Not affected by device changes.
                     continue;
@@ -2261,7 +2551,14 @@ impl CascadeData {
             host_rules.clear();
         }
         self.animations.clear();
+        self.custom_property_registrations.clear();
         self.extra_data.clear();
+        self.layers.clear();
+        self.layers.push(CascadeLayer::root());
+        self.layer_ids.clear();
+        self.container_conditions.clear();
+        self.container_conditions
+            .push(ContainerConditionReference::none());
         self.rules_source_order = 0;
         self.num_selectors = 0;
         self.num_declarations = 0;
@@ -2294,6 +2591,7 @@ impl CascadeData {
         sizes.mInvalidationMap += self.invalidation_map.size_of(ops);
         sizes.mRevalidationSelectors += self.selectors_for_cache_revalidation.size_of(ops);
         sizes.mOther += self.animations.size_of(ops);
+        sizes.mOther += self.custom_property_registrations.size_of(ops);
         sizes.mOther += self.effective_media_query_results.size_of(ops);
         sizes.mOther += self.extra_data.size_of(ops);
     }
@@ -2324,6 +2622,12 @@ pub struct Rule {
     /// we could repurpose that storage here if we needed to.
     pub source_order: u32,
 
+    /// The cascade layer this style rule is in.
+    pub layer_id: LayerId,
+
+    /// The `@container` condition this style rule is in, if any.
+    pub container_condition_id: ContainerConditionId,
+
     /// The actual style rule.
     #[cfg_attr(
         feature = "gecko",
@@ -2346,13 +2650,20 @@ impl Rule {
     }
 
     /// Turns this rule into an `ApplicableDeclarationBlock` for the given
-    /// cascade level.
+    /// cascade level, in the given `CascadeData`.
     pub fn to_applicable_declaration_block(
         &self,
         level: CascadeLevel,
+        cascade_data: &CascadeData,
     ) -> ApplicableDeclarationBlock {
         let source = StyleSource::from_rule(self.style_rule.clone());
-        ApplicableDeclarationBlock::new(source, self.source_order, level, self.specificity())
+        ApplicableDeclarationBlock::new(
+            source,
+            self.source_order,
+            level,
+            self.specificity(),
+            cascade_data.layer_order_for(self.layer_id),
+        )
     }
 
     /// Creates a new Rule.
@@ -2361,16 +2672,112 @@ impl Rule {
         hashes: AncestorHashes,
         style_rule: Arc<Locked<StyleRule>>,
         source_order: u32,
+        layer_id: LayerId,
+        container_condition_id: ContainerConditionId,
     ) -> Self {
         Rule {
             selector: selector,
             hashes: hashes,
             style_rule: style_rule,
             source_order: source_order,
+            layer_id: layer_id,
+            container_condition_id: container_condition_id,
         }
     }
 }
 
+/// The identifier of a cascade layer in a `CascadeData`.
+#[derive(Clone, Copy, Debug, Eq, Hash, MallocSizeOf, PartialEq)]
+pub struct LayerId(u32);
+
+impl LayerId {
+    /// The implicit outer layer, which contains the rules that are not in any
+    /// layer.
+    pub const fn root() -> Self {
+        Self(0)
+    }
+}
+
+/// A cascade layer, in a `CascadeData`.
+#[derive(Clone, Debug, MallocSizeOf)]
+struct CascadeLayer {
+    /// The order of this layer, once all the layers have been declared.
+    order: LayerOrder,
+    /// The sub-layers of this layer, in declaration order.
+    children: Vec<LayerId>,
+}
+
+impl CascadeLayer {
+    fn root() -> Self {
+        Self {
+            order: LayerOrder::root(),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// The identifier of a `@container` condition in a `CascadeData`.
+#[derive(Clone, Copy, Debug, Eq, Hash, MallocSizeOf, PartialEq)]
+pub struct ContainerConditionId(u32);
+
+impl ContainerConditionId {
+    /// The absence of any condition, for the rules that are not in any
+    /// `@container` rule.
+    pub const fn none() -> Self {
+        Self(0)
+    }
+}
+
+/// A `@container` condition, in a `CascadeData`.
+#[derive(Clone, Debug, MallocSizeOf)]
+struct ContainerConditionReference {
+    /// The condition of the `@container` rule this one is nested in, if any.
+    parent: ContainerConditionId,
+    /// The condition, or `None` for `ContainerConditionId::none()`.
+    #[ignore_malloc_size_of = "Arc"]
+    condition: Option<Arc<ContainerCondition>>,
+}
+
+impl ContainerConditionReference {
+    fn none() -> Self {
+        Self {
+            parent: ContainerConditionId::none(),
+            condition: None,
+        }
+    }
+}
+
+/// A function to be able to test the cascade layer order: returns the order
+/// of the layer of each style rule with a universal selector in `stylesheet`,
+/// in source order.
+pub fn layer_orders_for_testing<S>(
+    stylesheet: &S,
+    device: &Device,
+    guard: &SharedRwLockReadGuard,
+) -> Vec<LayerOrder>
+where
+    S: StylesheetInDocument + ToMediaListKey + 'static,
+{
+    let mut data = CascadeData::new();
+    data.add_stylesheet(
+        device,
+        QuirksMode::NoQuirks,
+        stylesheet,
+        guard,
+        SheetRebuildKind::Full,
+        /* precomputed_pseudo_element_decls = */ None,
+    )
+    .unwrap();
+    data.compute_layer_order();
+    data.normal_rules(None).map_or(Vec::new(), |rules| {
+        rules
+            .other
+            .iter()
+            .map(|rule| data.layer_order_for(rule.layer_id))
+            .collect()
+    })
+}
+
 /// A function to be able to test the revalidation stuff.
 pub fn needs_revalidation_for_testing(s: &Selector<SelectorImpl>) -> bool {
     let mut attribute_dependencies = Default::default();
@@ -4,6 +4,8 @@
 
 //! Animated types for CSS colors.
 
+use crate::color::mix::{mix, ColorInterpolationMethod};
+use crate::color::{AbsoluteColor, ColorFlags, ColorSpace};
 use crate::values::animated::{Animate, Procedure, ToAnimatedZero};
 use crate::values::distance::{ComputeSquaredDistance, SquaredDistance};
 use crate::values::generics::color::{Color as GenericColor, ComplexColorRatios};
@@ -93,17 +95,45 @@ impl ComputeSquaredDistance for RGBA {
     }
 }
 
+impl From<AbsoluteColor> for RGBA {
+    /// Converts the color to sRGB, without clamping its components.
+    fn from(color: AbsoluteColor) -> Self {
+        let srgb = color.to_color_space(ColorSpace::Srgb);
+        RGBA::new(
+            srgb.components.0,
+            srgb.components.1,
+            srgb.components.2,
+            srgb.alpha,
+        )
+    }
+}
+
+impl From<RGBA> for AbsoluteColor {
+    fn from(rgba: RGBA) -> Self {
+        let mut color = AbsoluteColor::new(
+            ColorSpace::Srgb,
+            [rgba.red, rgba.green, rgba.blue].into(),
+            rgba.alpha,
+        );
+        color.flags.insert(ColorFlags::IS_LEGACY_SRGB);
+        color
+    }
+}
+
 /// An animated value for `<color>`.
-pub type Color = GenericColor<RGBA>;
+pub type Color = GenericColor<AbsoluteColor>;
 
 impl Color {
     fn effective_intermediate_rgba(&self) -> RGBA {
         match *self {
-            GenericColor::Numeric(color) => color,
+            GenericColor::Numeric(color) => color.into(),
             GenericColor::CurrentColor => RGBA::transparent(),
-            GenericColor::Complex { color, ratios } => RGBA {
-                alpha: color.alpha * ratios.bg,
-                ..color.clone()
+            GenericColor::Complex { color, ratios } => {
+                let color = RGBA::from(color);
+                RGBA {
+                    alpha: color.alpha * ratios.bg,
+                    ..color
+                }
             },
         }
     }
@@ -129,8 +159,16 @@ impl Animate for Color {
         Ok(match (*self, *other, procedure) {
             // Any interpolation of currentcolor with currentcolor returns currentcolor.
             (CurrentColor, CurrentColor, Procedure::Interpolate { .. }) => CurrentColor,
-            // Animating two numeric colors.
-            (Numeric(c1), Numeric(c2), _) => Numeric(c1.animate(&c2, procedure)?),
+            // Animating two numeric colors, in sRGB if both have a legacy
+            // syntax, and in Oklab otherwise.
+            (Numeric(c1), Numeric(c2), _) => {
+                let method = ColorInterpolationMethod::default_for(&[c1, c2]);
+                let mut color = mix(&method, &c1, this_weight as f32, &c2, other_weight as f32);
+                if method == ColorInterpolationMethod::SRGB {
+                    color.flags.insert(ColorFlags::IS_LEGACY_SRGB);
+                }
+                Numeric(color)
+            },
             // Combinations of numeric color and currentcolor
             (CurrentColor, Numeric(color), _) => Self::with_ratios(
                 color,
@@ -149,7 +187,7 @@ impl Animate for Color {
 
             // Any other animation of currentcolor with currentcolor.
             (CurrentColor, CurrentColor, _) => Self::with_ratios(
-                RGBA::transparent(),
+                AbsoluteColor::transparent(),
                 ComplexColorRatios {
                     bg: 0.,
                     fg: (this_weight + other_weight) as f32,
@@ -161,13 +199,16 @@ impl Animate for Color {
                 // Compute the "scaled" contribution for `color`.
                 fn scaled_rgba(color: &Color) -> RGBA {
                     match *color {
-                        GenericColor::Numeric(color) => color,
+                        GenericColor::Numeric(color) => color.into(),
                         GenericColor::CurrentColor => RGBA::transparent(),
-                        GenericColor::Complex { color, ratios } => RGBA {
-                            red: color.red * ratios.bg,
-                            green: color.green * ratios.bg,
-                            blue: color.blue * ratios.bg,
-                            alpha: color.alpha * ratios.bg,
+                        GenericColor::Complex { color, ratios } => {
+                            let color = RGBA::from(color);
+                            RGBA {
+                                red: color.red * ratios.bg,
+                                green: color.green * ratios.bg,
+                                blue: color.blue * ratios.bg,
+                                alpha: color.alpha * ratios.bg,
+                            }
                         },
                     }
                 }
@@ -223,7 +264,7 @@ impl Animate for Color {
                 // Perform fg1 op fg2
                 let fg = fg1.animate(&fg2, procedure)?;
 
-                Self::with_ratios(bg_color, ComplexColorRatios { bg: 1., fg })
+                Self::with_ratios(bg_color.into(), ComplexColorRatios { bg: 1., fg })
             },
         })
     }
@@ -237,10 +278,10 @@ impl ComputeSquaredDistance for Color {
         // All comments from the Animate impl also applies here.
         Ok(match (*self, *other) {
             (CurrentColor, CurrentColor) => SquaredDistance::from_sqrt(0.),
-            (Numeric(c1), Numeric(c2)) => c1.compute_squared_distance(&c2)?,
+            (Numeric(c1), Numeric(c2)) => RGBA::from(c1).compute_squared_distance(&c2.into())?,
             (CurrentColor, Numeric(color)) | (Numeric(color), CurrentColor) => {
                 // `computed_squared_distance` is symmetric.
-                color.compute_squared_distance(&RGBA::transparent())? +
+                RGBA::from(color).compute_squared_distance(&RGBA::transparent())? +
                     SquaredDistance::from_sqrt(1.)
             },
             (_, _) => {
@@ -260,6 +301,6 @@ impl ComputeSquaredDistance for Color {
 impl ToAnimatedZero for Color {
     #[inline]
     fn to_animated_zero(&self) -> Result<Self, ()> {
-        Ok(RGBA::transparent().into())
+        Ok(Color::numeric(AbsoluteColor::transparent()))
     }
 }
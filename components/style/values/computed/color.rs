@@ -4,8 +4,11 @@
 
 //! Computed color values.
 
+use crate::color::mix::ColorInterpolationMethod;
+use crate::color::AbsoluteColor;
 use crate::values::animated::color::RGBA as AnimatedRGBA;
-use crate::values::animated::ToAnimatedValue;
+use crate::values::animated::{Animate, Procedure, ToAnimatedValue};
+use crate::values::generics::color::ComplexColorRatios;
 use crate::values::generics::color::{Color as GenericColor, ColorOrAuto as GenericColorOrAuto};
 use cssparser::{Color as CSSParserColor, RGBA};
 use std::fmt;
@@ -18,7 +21,7 @@ pub type ColorPropertyValue = RGBA;
 pub type MozFontSmoothingBackgroundColor = RGBA;
 
 /// A computed value for `<color>`.
-pub type Color = GenericColor<RGBA>;
+pub type Color = GenericColor<AbsoluteColor>;
 
 impl Color {
     /// Returns a complex color value representing transparent.
@@ -26,15 +29,74 @@ impl Color {
         Color::rgba(RGBA::transparent())
     }
 
+    /// Returns a numeric color representing the given sRGB color.
+    pub fn rgba(rgba: RGBA) -> Self {
+        Color::numeric(rgba.into())
+    }
+
+    /// Mixes two colors, as color-mix() does, with the given weights, which
+    /// add up to one. The alpha of the result is multiplied by
+    /// `alpha_multiplier`.
+    ///
+    /// Colors depending on currentcolor can only be mixed in sRGB, as
+    /// animations do.
+    pub fn mix(
+        method: &ColorInterpolationMethod,
+        left: &Self,
+        left_weight: f32,
+        right: &Self,
+        right_weight: f32,
+        alpha_multiplier: f32,
+    ) -> Self {
+        let mixed = match (*left, *right) {
+            (GenericColor::Numeric(left), GenericColor::Numeric(right)) => GenericColor::Numeric(
+                crate::color::mix::mix(method, &left, left_weight, &right, right_weight),
+            ),
+            _ => left
+                .animate(
+                    right,
+                    Procedure::Interpolate {
+                        progress: right_weight as f64,
+                    },
+                )
+                .unwrap_or(*left),
+        };
+
+        if alpha_multiplier == 1. {
+            return mixed;
+        }
+        match mixed {
+            GenericColor::Numeric(mut color) => {
+                color.alpha *= alpha_multiplier;
+                GenericColor::Numeric(color)
+            },
+            GenericColor::CurrentColor => Self::with_ratios(
+                AbsoluteColor::transparent(),
+                ComplexColorRatios {
+                    bg: 0.,
+                    fg: alpha_multiplier,
+                },
+            ),
+            GenericColor::Complex { color, ratios } => Self::with_ratios(
+                color,
+                ComplexColorRatios {
+                    bg: ratios.bg * alpha_multiplier,
+                    fg: ratios.fg * alpha_multiplier,
+                },
+            ),
+        }
+    }
+
     /// Combine this complex color with the given foreground color into
-    /// a numeric RGBA color. It currently uses linear blending.
+    /// a numeric RGBA color, mapped into the sRGB gamut. It currently uses
+    /// linear blending.
     pub fn to_rgba(&self, fg_color: RGBA) -> RGBA {
         let (color, ratios) = match *self {
             // Common cases that the complex color is either pure numeric
             // color or pure currentcolor.
-            GenericColor::Numeric(color) => return color,
+            GenericColor::Numeric(color) => return color.to_rgba(),
             GenericColor::CurrentColor => return fg_color,
-            GenericColor::Complex { color, ratios } => (color, ratios),
+            GenericColor::Complex { color, ratios } => (color.to_rgba(), ratios),
         };
 
         // For the more complicated case that the alpha value differs,
@@ -102,5 +164,11 @@ impl ToAnimatedValue for RGBA {
     }
 }
 
+impl From<RGBA> for Color {
+    fn from(rgba: RGBA) -> Self {
+        Color::rgba(rgba)
+    }
+}
+
 /// auto | <color>
 pub type ColorOrAuto = GenericColorOrAuto<Color>;
@@ -38,6 +38,9 @@ impl ToComputedValue for specified::NoCalcLength {
             specified::NoCalcLength::ViewportPercentage(length) => {
                 length.to_computed_value(context.viewport_size_for_viewport_unit_resolution())
             },
+            specified::NoCalcLength::ContainerRelative(length) => {
+                length.to_computed_value(context.container_size_for_container_unit_resolution())
+            },
             specified::NoCalcLength::ServoCharacterWidth(length) => {
                 length.to_computed_value(context.style().get_font().clone_font_size().size())
             },
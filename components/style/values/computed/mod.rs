@@ -14,6 +14,7 @@ use super::generics::transform::IsParallelTo;
 use super::generics::{self, GreaterThanOrEqualToOne, NonNegative, ZeroToOne};
 use super::specified;
 use super::{CSSFloat, CSSInteger};
+use crate::computed_value_flags::ComputedValueFlags;
 use crate::context::QuirksMode;
 use crate::font_metrics::{get_metrics_provider_for_product, FontMetricsProvider};
 use crate::media_queries::Device;
@@ -21,6 +22,7 @@ use crate::media_queries::Device;
 use crate::properties;
 use crate::properties::{ComputedValues, LonghandId, StyleBuilder};
 use crate::rule_cache::RuleCacheConditions;
+use crate::stylesheets::container_rule::ContainerSizeQuery;
 use crate::{ArcSlice, Atom};
 use euclid::default::Size2D;
 use servo_arc::Arc;
@@ -39,7 +41,7 @@ pub use self::border::{BorderCornerRadius, BorderRadius, BorderSpacing};
 pub use self::border::{BorderImageRepeat, BorderImageSideWidth};
 pub use self::border::{BorderImageSlice, BorderImageWidth};
 pub use self::box_::{AnimationIterationCount, AnimationName, Contain};
-pub use self::box_::{Appearance, BreakBetween, BreakWithin, Clear, Float};
+pub use self::box_::{Appearance, BreakBetween, BreakWithin, Clear, ContainerName, Float};
 pub use self::box_::{Display, Overflow, OverflowAnchor, TransitionProperty};
 pub use self::box_::{OverflowClipBox, OverscrollBehavior, Perspective, Resize};
 pub use self::box_::{ScrollSnapAlign, ScrollSnapAxis, ScrollSnapStrictness, ScrollSnapType};
@@ -169,6 +171,10 @@ pub struct Context<'a> {
     ///
     /// FIXME(emilio): Drop the refcell.
     pub rule_cache_conditions: RefCell<&'a mut RuleCacheConditions>,
+
+    /// The size of the nearest query container, to resolve
+    /// container-relative lengths.
+    pub container_size_query: ContainerSizeQuery<'a>,
 }
 
 impl<'a> Context<'a> {
@@ -191,6 +197,7 @@ impl<'a> Context<'a> {
             for_smil_animation: false,
             for_non_inherited_property: None,
             rule_cache_conditions: RefCell::new(&mut conditions),
+            container_size_query: ContainerSizeQuery::none(),
         };
 
         f(&context)
@@ -208,6 +215,28 @@ impl<'a> Context<'a> {
             .au_viewport_size_for_viewport_unit_resolution()
     }
 
+    /// The size to resolve container-relative units against: the size of the
+    /// nearest query container in the axes it is sized in, and the size of the
+    /// viewport otherwise.
+    ///
+    /// <https://drafts.csswg.org/css-contain-3/#container-lengths>
+    pub fn container_size_for_container_unit_resolution(&self) -> Size2D<Au> {
+        self.builder
+            .add_flags(ComputedValueFlags::USES_CONTAINER_UNITS);
+        // The size of query containers is not part of the rule node.
+        self.rule_cache_conditions.borrow_mut().set_uncacheable();
+
+        let viewport_size = self.viewport_size_for_viewport_unit_resolution();
+        let container_size = match self.container_size_query.get() {
+            Some(size) => size,
+            None => return viewport_size,
+        };
+        Size2D::new(
+            container_size.width.unwrap_or(viewport_size.width),
+            container_size.height.unwrap_or(viewport_size.height),
+        )
+    }
+
     /// The default computed style we're getting our reset style from.
     pub fn default_style(&self) -> &ComputedValues {
         self.builder.default_style()
@@ -43,6 +43,8 @@ pub enum SortKey {
     Number,
     Percentage,
     Ch,
+    Cqh,
+    Cqw,
     Deg,
     Em,
     Ex,
@@ -185,6 +187,33 @@ impl<L: CalcNodeLeaf> CalcNode<L> {
         }
     }
 
+    /// Returns whether any leaf of this expression matches the given
+    /// predicate.
+    pub fn any_leaf(&self, mut predicate: impl FnMut(&L) -> bool) -> bool {
+        self.any_leaf_internal(&mut predicate)
+    }
+
+    fn any_leaf_internal<F>(&self, predicate: &mut F) -> bool
+    where
+        F: FnMut(&L) -> bool,
+    {
+        match *self {
+            Self::Leaf(ref l) => predicate(l),
+            Self::Sum(ref c) | Self::MinMax(ref c, _) => {
+                c.iter().any(|c| c.any_leaf_internal(predicate))
+            },
+            Self::Clamp {
+                ref min,
+                ref center,
+                ref max,
+            } => {
+                min.any_leaf_internal(predicate) ||
+                    center.any_leaf_internal(predicate) ||
+                    max.any_leaf_internal(predicate)
+            },
+        }
+    }
+
     /// Resolves the expression returning a value of `O`, given a function to
     /// turn a leaf into the relevant value.
     pub fn resolve<O>(
@@ -57,8 +57,8 @@ impl<RGBA> Color<RGBA> {
         }
     }
 
-    /// Returns a numeric color representing the given RGBA value.
-    pub fn rgba(color: RGBA) -> Self {
+    /// Returns a numeric color representing the given value.
+    pub fn numeric(color: RGBA) -> Self {
         Color::Numeric(color)
     }
 
@@ -80,7 +80,7 @@ impl<RGBA> Color<RGBA> {
 
 impl<RGBA> From<RGBA> for Color<RGBA> {
     fn from(color: RGBA) -> Self {
-        Self::rgba(color)
+        Self::numeric(color)
     }
 }
 
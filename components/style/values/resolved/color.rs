@@ -6,16 +6,18 @@
 
 use super::{Context, ToResolvedValue};
 
+use crate::color::AbsoluteColor;
 use crate::values::computed;
 use crate::values::generics::color as generics;
 
 impl ToResolvedValue for computed::Color {
-    // A resolved color value is an rgba color, with currentcolor resolved.
-    type ResolvedValue = cssparser::RGBA;
+    // A resolved color value is an absolute color, with currentcolor
+    // resolved.
+    type ResolvedValue = AbsoluteColor;
 
     #[inline]
     fn to_resolved_value(self, context: &Context) -> Self::ResolvedValue {
-        context.style.resolve_color(self)
+        context.style.resolve_absolute_color(self)
     }
 
     #[inline]
@@ -25,9 +27,9 @@ impl ToResolvedValue for computed::Color {
 }
 
 impl ToResolvedValue for computed::ColorOrAuto {
-    // A resolved caret-color value is an rgba color, with auto resolving to
-    // currentcolor.
-    type ResolvedValue = cssparser::RGBA;
+    // A resolved caret-color value is an absolute color, with auto resolving
+    // to currentcolor.
+    type ResolvedValue = AbsoluteColor;
 
     #[inline]
     fn to_resolved_value(self, context: &Context) -> Self::ResolvedValue {
@@ -9,8 +9,8 @@
 use crate::parser::ParserContext;
 use crate::values::generics::calc as generic;
 use crate::values::generics::calc::{MinMaxOp, SortKey};
-use crate::values::specified::length::ViewportPercentageLength;
 use crate::values::specified::length::{AbsoluteLength, FontRelativeLength, NoCalcLength};
+use crate::values::specified::length::{ContainerRelativeLength, ViewportPercentageLength};
 use crate::values::specified::{self, Angle, Time};
 use crate::values::{CSSFloat, CSSInteger};
 use cssparser::{AngleOrNumber, CowRcStr, NumberOrPercentage, Parser, Token};
@@ -176,6 +176,10 @@ impl generic::CalcNodeLeaf for Leaf {
                     ViewportPercentageLength::Vmax(..) => SortKey::Vmax,
                     ViewportPercentageLength::Vmin(..) => SortKey::Vmin,
                 },
+                NoCalcLength::ContainerRelative(ref cq) => match *cq {
+                    ContainerRelativeLength::Cqh(..) => SortKey::Cqh,
+                    ContainerRelativeLength::Cqw(..) => SortKey::Cqw,
+                },
                 NoCalcLength::ServoCharacterWidth(..) => unreachable!(),
             },
         }
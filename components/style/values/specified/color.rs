@@ -5,12 +5,17 @@
 //! Specified color values.
 
 use super::AllowQuirks;
+use crate::color::convert::normalize_hue;
+use crate::color::mix::ColorInterpolationMethod;
+use crate::color::{AbsoluteColor, ColorFlags, ColorSpace};
 #[cfg(feature = "gecko")]
 use crate::gecko_bindings::structs::nscolor;
 use crate::parser::{Parse, ParserContext};
 use crate::values::computed::{Color as ComputedColor, Context, ToComputedValue};
 use crate::values::generics::color::{Color as GenericColor, ColorOrAuto as GenericColorOrAuto};
 use crate::values::specified::calc::CalcNode;
+use crate::values::specified::Percentage;
+use cssparser::ColorComponentParser as _;
 use cssparser::{AngleOrNumber, Color as CSSParserColor, Parser, Token, RGBA};
 use cssparser::{BasicParseErrorKind, NumberOrPercentage, ParseErrorKind};
 use itoa;
@@ -31,8 +36,13 @@ pub enum Color {
         /// Authored representation
         authored: Option<Box<str>>,
     },
+    /// A color specified with lab(), lch(), oklab(), oklch() or color(),
+    /// which may be outside of the sRGB gamut.
+    Absolute(Box<AbsoluteColor>),
+    /// A color-mix() function.
+    ColorMix(Box<ColorMix>),
     /// A complex color value from computed value
-    Complex(ComputedColor),
+    Complex(Box<ComputedColor>),
     /// A system color
     #[cfg(feature = "gecko")]
     System(SystemColor),
@@ -312,8 +322,6 @@ impl<'a, 'b: 'a, 'i: 'a> ::cssparser::ColorComponentParser<'i> for ColorComponen
     }
 
     fn parse_percentage<'t>(&self, input: &mut Parser<'i, 't>) -> Result<f32, ParseError<'i>> {
-        use crate::values::specified::Percentage;
-
         Ok(Percentage::parse(self.0, input)?.get())
     }
 
@@ -365,6 +373,10 @@ impl Parse for Color {
                 },
             }),
             Err(e) => {
+                if let Ok(color) = input.try(|i| parse_color_function(context, i)) {
+                    return Ok(color);
+                }
+
                 #[cfg(feature = "gecko")]
                 {
                     if let Ok(system) = input.try(|i| SystemColor::parse(context, i)) {
@@ -399,6 +411,8 @@ impl ToCss for Color {
             Color::Numeric {
                 parsed: ref rgba, ..
             } => rgba.to_css(dest),
+            Color::Absolute(ref color) => color.to_css(dest),
+            Color::ColorMix(ref mix) => mix.to_css(dest),
             Color::Complex(_) => Ok(()),
             #[cfg(feature = "gecko")]
             Color::System(system) => system.to_css(dest),
@@ -408,6 +422,228 @@ impl ToCss for Color {
     }
 }
 
+/// Parses the color functions of CSS Color 4 and 5 that cssparser doesn't
+/// know about.
+fn parse_color_function<'i, 't>(
+    context: &ParserContext,
+    input: &mut Parser<'i, 't>,
+) -> Result<Color, ParseError<'i>> {
+    let location = input.current_source_location();
+    let name = input.expect_function()?.clone();
+    let component_parser = ColorComponentParser(context);
+    input.parse_nested_block(|i| {
+        let color = match_ignore_ascii_case! { &name,
+            "lab" => parse_color_components(&component_parser, i, ColorSpace::Lab, [100., 125., 125.])?,
+            "lch" => parse_color_components(&component_parser, i, ColorSpace::Lch, [100., 150., 0.])?,
+            "oklab" => parse_color_components(&component_parser, i, ColorSpace::Oklab, [1., 0.4, 0.4])?,
+            "oklch" => parse_color_components(&component_parser, i, ColorSpace::Oklch, [1., 0.4, 0.])?,
+            "color" => {
+                let location = i.current_source_location();
+                let color_space = ColorSpace::parse(i)?;
+                if !color_space.is_predefined() {
+                    return Err(location.new_custom_error(StyleParseErrorKind::UnspecifiedError));
+                }
+                parse_color_components(&component_parser, i, color_space, [1., 1., 1.])?
+            },
+            "color-mix" => return ColorMix::parse_arguments(context, i).map(|mix| Color::ColorMix(Box::new(mix))),
+            _ => return Err(location.new_unexpected_token_error(Token::Function(name.clone()))),
+        };
+        Ok(Color::Absolute(Box::new(color)))
+    })
+}
+
+/// Parses the components of a color in the given color space, each of them
+/// being a number, a percentage of the given reference, or `none`, or a hue
+/// for cylindrical color spaces, then an optional alpha.
+///
+/// <https://drafts.csswg.org/css-color-4/#specifying-lab-lch>
+fn parse_color_components<'i, 't>(
+    component_parser: &ColorComponentParser,
+    input: &mut Parser<'i, 't>,
+    color_space: ColorSpace,
+    percentage_references: [f32; 3],
+) -> Result<AbsoluteColor, ParseError<'i>> {
+    let mut components = [0.; 3];
+    let mut flags = ColorFlags::empty();
+    for index in 0..3 {
+        if input.try(|i| i.expect_ident_matching("none")).is_ok() {
+            flags.insert(ColorFlags::component_is_none(index));
+            continue;
+        }
+        components[index] = if color_space.hue_index() == Some(index) {
+            match component_parser.parse_angle_or_number(input)? {
+                AngleOrNumber::Number { value } => normalize_hue(value),
+                AngleOrNumber::Angle { degrees } => normalize_hue(degrees),
+            }
+        } else {
+            match component_parser.parse_number_or_percentage(input)? {
+                NumberOrPercentage::Number { value } => value,
+                NumberOrPercentage::Percentage { unit_value } => {
+                    unit_value * percentage_references[index]
+                },
+            }
+        };
+    }
+
+    // Lightness is clamped to its range, and chroma can't be negative.
+    match color_space {
+        ColorSpace::Lab | ColorSpace::Lch => components[0] = components[0].max(0.).min(100.),
+        ColorSpace::Oklab | ColorSpace::Oklch => components[0] = components[0].max(0.).min(1.),
+        _ => {},
+    }
+    if let ColorSpace::Lch | ColorSpace::Oklch = color_space {
+        components[1] = components[1].max(0.);
+    }
+
+    let mut alpha = 1.;
+    if input.try(|i| i.expect_delim('/')).is_ok() {
+        if input.try(|i| i.expect_ident_matching("none")).is_ok() {
+            flags.insert(ColorFlags::ALPHA_IS_NONE);
+            alpha = 0.;
+        } else {
+            alpha = match component_parser.parse_number_or_percentage(input)? {
+                NumberOrPercentage::Number { value } => value,
+                NumberOrPercentage::Percentage { unit_value } => unit_value,
+            }
+            .max(0.)
+            .min(1.);
+        }
+    }
+
+    let mut color = AbsoluteColor::new(color_space, components.into(), alpha);
+    color.flags = flags;
+    Ok(color)
+}
+
+/// A specified color-mix() function.
+///
+/// <https://drafts.csswg.org/css-color-5/#color-mix>
+#[derive(Clone, Debug, MallocSizeOf, PartialEq, ToShmem)]
+pub struct ColorMix {
+    /// The color space and hue interpolation method to mix the colors with.
+    pub interpolation: ColorInterpolationMethod,
+    /// The first color.
+    pub left: Color,
+    /// The proportion of the first color, if specified.
+    pub left_percentage: Option<Percentage>,
+    /// The second color.
+    pub right: Color,
+    /// The proportion of the second color, if specified.
+    pub right_percentage: Option<Percentage>,
+}
+
+impl ColorMix {
+    /// Parses the arguments of a color-mix() function.
+    fn parse_arguments<'i, 't>(
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i>> {
+        let interpolation = ColorInterpolationMethod::parse(context, input)?;
+        input.expect_comma()?;
+        let (left, left_percentage) = Self::parse_color_and_percentage(context, input)?;
+        input.expect_comma()?;
+        let (right, right_percentage) = Self::parse_color_and_percentage(context, input)?;
+
+        // The percentages can't both be zero.
+        if let (Some(ref left), Some(ref right)) = (&left_percentage, &right_percentage) {
+            if left.get() + right.get() <= 0. {
+                return Err(input.new_custom_error(StyleParseErrorKind::UnspecifiedError));
+            }
+        }
+
+        Ok(ColorMix {
+            interpolation,
+            left,
+            left_percentage,
+            right,
+            right_percentage,
+        })
+    }
+
+    /// Parses `<color> && <percentage [0,100]>?`.
+    fn parse_color_and_percentage<'i, 't>(
+        context: &ParserContext,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<(Color, Option<Percentage>), ParseError<'i>> {
+        let parse_percentage = |input: &mut Parser<'i, 't>| {
+            input
+                .try(|i| -> Result<_, ParseError<'i>> {
+                    let location = i.current_source_location();
+                    let percentage = Percentage::parse_non_negative(context, i)?;
+                    if !percentage.is_calc() && percentage.get() > 1. {
+                        return Err(
+                            location.new_custom_error(StyleParseErrorKind::UnspecifiedError)
+                        );
+                    }
+                    Ok(percentage)
+                })
+                .ok()
+        };
+        let percentage = parse_percentage(input);
+        let color = Color::parse(context, input)?;
+        let percentage = percentage.or_else(|| parse_percentage(input));
+        Ok((color, percentage))
+    }
+
+    /// Computes the mixed color, normalizing the percentages.
+    ///
+    /// <https://drafts.csswg.org/css-color-5/#color-mix-percent-norm>
+    fn to_computed_color(&self, context: Option<&Context>) -> Option<ComputedColor> {
+        let left = self.left.to_computed_color(context)?;
+        let right = self.right.to_computed_color(context)?;
+
+        let get = |percentage: &Option<Percentage>| {
+            percentage
+                .as_ref()
+                .map(|percentage| percentage.get().max(0.).min(1.))
+        };
+        let (left_percentage, right_percentage) =
+            match (get(&self.left_percentage), get(&self.right_percentage)) {
+                (None, None) => (0.5, 0.5),
+                (Some(left), None) => (left, 1. - left),
+                (None, Some(right)) => (1. - right, right),
+                (Some(left), Some(right)) => (left, right),
+            };
+        let sum = left_percentage + right_percentage;
+        if sum <= 0. {
+            // Only possible with calc(), which is not checked at parse time.
+            return Some(ComputedColor::transparent());
+        }
+
+        Some(ComputedColor::mix(
+            &self.interpolation,
+            &left,
+            left_percentage / sum,
+            &right,
+            right_percentage / sum,
+            sum.min(1.),
+        ))
+    }
+}
+
+impl ToCss for ColorMix {
+    fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result
+    where
+        W: Write,
+    {
+        dest.write_str("color-mix(")?;
+        self.interpolation.to_css(dest)?;
+        dest.write_str(", ")?;
+        self.left.to_css(dest)?;
+        if let Some(ref percentage) = self.left_percentage {
+            dest.write_char(' ')?;
+            percentage.to_css(dest)?;
+        }
+        dest.write_str(", ")?;
+        self.right.to_css(dest)?;
+        if let Some(ref percentage) = self.right_percentage {
+            dest.write_char(' ')?;
+            percentage.to_css(dest)?;
+        }
+        dest.write_char(')')
+    }
+}
+
 /// A wrapper of cssparser::Color::parse_hash.
 ///
 /// That function should never return CurrentColor, so it makes no sense to
@@ -529,6 +765,7 @@ impl Color {
     pub fn is_transparent(&self) -> bool {
         match *self {
             Color::Numeric { ref parsed, .. } => parsed.alpha == 0,
+            Color::Absolute(ref color) => color.alpha == 0.,
             _ => false,
         }
     }
@@ -545,17 +782,17 @@ impl Color {
     ///
     /// If `context` is `None`, and the specified color requires data from
     /// the context to resolve, then `None` is returned.
-    pub fn to_computed_color(&self, _context: Option<&Context>) -> Option<ComputedColor> {
+    pub fn to_computed_color(&self, context: Option<&Context>) -> Option<ComputedColor> {
         Some(match *self {
             Color::CurrentColor => ComputedColor::currentcolor(),
             Color::Numeric { ref parsed, .. } => ComputedColor::rgba(*parsed),
-            Color::Complex(ref complex) => *complex,
+            Color::Absolute(ref color) => ComputedColor::numeric(**color),
+            Color::ColorMix(ref mix) => mix.to_computed_color(context)?,
+            Color::Complex(ref complex) => **complex,
             #[cfg(feature = "gecko")]
-            Color::System(system) => system.compute(_context?),
+            Color::System(system) => system.compute(context?),
             #[cfg(feature = "gecko")]
-            Color::InheritFromBodyQuirk => {
-                ComputedColor::rgba(_context?.device().body_text_color())
-            },
+            Color::InheritFromBodyQuirk => ComputedColor::rgba(context?.device().body_text_color()),
         })
     }
 }
@@ -569,9 +806,10 @@ impl ToComputedValue for Color {
 
     fn from_computed_value(computed: &ComputedColor) -> Self {
         match *computed {
-            GenericColor::Numeric(color) => Color::rgba(color),
+            GenericColor::Numeric(color) if color.is_legacy_srgb() => Color::rgba(color.to_rgba()),
+            GenericColor::Numeric(color) => Color::Absolute(Box::new(color)),
             GenericColor::CurrentColor => Color::currentcolor(),
-            GenericColor::Complex { .. } => Color::Complex(*computed),
+            GenericColor::Complex { .. } => Color::Complex(Box::new(*computed)),
         }
     }
 }
@@ -617,7 +855,20 @@ impl SpecifiedValueInfo for Color {
         // should probably be handled that way as well.
         // XXX `currentColor` should really be `currentcolor`. But let's
         // keep it consistent with the old system for now.
-        f(&["rgb", "rgba", "hsl", "hsla", "currentColor", "transparent"]);
+        f(&[
+            "rgb",
+            "rgba",
+            "hsl",
+            "hsla",
+            "lab",
+            "lch",
+            "oklab",
+            "oklch",
+            "color",
+            "color-mix",
+            "currentColor",
+            "transparent",
+        ]);
     }
 }
 
@@ -332,6 +332,75 @@ impl ViewportPercentageLength {
     }
 }
 
+/// A container-relative length.
+///
+/// <https://drafts.csswg.org/css-contain-3/#container-lengths>
+#[derive(Clone, Copy, Debug, MallocSizeOf, PartialEq, ToCss, ToShmem)]
+pub enum ContainerRelativeLength {
+    /// <https://drafts.csswg.org/css-contain-3/#valdef-length-cqw>
+    #[css(dimension)]
+    Cqw(CSSFloat),
+    /// <https://drafts.csswg.org/css-contain-3/#valdef-length-cqh>
+    #[css(dimension)]
+    Cqh(CSSFloat),
+}
+
+impl ContainerRelativeLength {
+    fn unitless_value(&self) -> CSSFloat {
+        match *self {
+            ContainerRelativeLength::Cqw(v) | ContainerRelativeLength::Cqh(v) => v,
+        }
+    }
+
+    fn try_sum(&self, other: &Self) -> Result<Self, ()> {
+        use self::ContainerRelativeLength::*;
+
+        Ok(match (self, other) {
+            (&Cqw(one), &Cqw(other)) => Cqw(one + other),
+            (&Cqh(one), &Cqh(other)) => Cqh(one + other),
+            _ => return Err(()),
+        })
+    }
+
+    /// Computes the given container-relative length for the given size of the
+    /// query container (or the viewport, if there's none).
+    pub fn to_computed_value(&self, container_size: Size2D<Au>) -> CSSPixelLength {
+        let (factor, length) = match *self {
+            ContainerRelativeLength::Cqw(length) => (length, container_size.width),
+            ContainerRelativeLength::Cqh(length) => (length, container_size.height),
+        };
+
+        // Truncate the same way viewport units do, so that adding up to 100
+        // units doesn't overflow due to rounding differences.
+        let trunc_scaled = ((length.0 as f64) * factor as f64 / 100.).trunc();
+        Au::from_f64_au(trunc_scaled).into()
+    }
+}
+
+impl Mul<CSSFloat> for ContainerRelativeLength {
+    type Output = ContainerRelativeLength;
+
+    #[inline]
+    fn mul(self, scalar: CSSFloat) -> ContainerRelativeLength {
+        match self {
+            ContainerRelativeLength::Cqw(v) => ContainerRelativeLength::Cqw(v * scalar),
+            ContainerRelativeLength::Cqh(v) => ContainerRelativeLength::Cqh(v * scalar),
+        }
+    }
+}
+
+impl PartialOrd for ContainerRelativeLength {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        use self::ContainerRelativeLength::*;
+
+        match (self, other) {
+            (&Cqw(ref one), &Cqw(ref other)) => one.partial_cmp(other),
+            (&Cqh(ref one), &Cqh(ref other)) => one.partial_cmp(other),
+            _ => None,
+        }
+    }
+}
+
 /// HTML5 "character width", as defined in HTML5 § 14.5.4.
 #[derive(Clone, Copy, Debug, MallocSizeOf, PartialEq, ToCss, ToShmem)]
 pub struct CharacterWidth(pub i32);
@@ -491,6 +560,11 @@ pub enum NoCalcLength {
     /// <https://drafts.csswg.org/css-values/#viewport-relative-lengths>
     ViewportPercentage(ViewportPercentageLength),
 
+    /// A container-relative length.
+    ///
+    /// <https://drafts.csswg.org/css-contain-3/#container-lengths>
+    ContainerRelative(ContainerRelativeLength),
+
     /// HTML5 "character width", as defined in HTML5 § 14.5.4.
     ///
     /// This cannot be specified by the user directly and is only generated by
@@ -508,6 +582,7 @@ impl Mul<CSSFloat> for NoCalcLength {
             NoCalcLength::Absolute(v) => NoCalcLength::Absolute(v * scalar),
             NoCalcLength::FontRelative(v) => NoCalcLength::FontRelative(v * scalar),
             NoCalcLength::ViewportPercentage(v) => NoCalcLength::ViewportPercentage(v * scalar),
+            NoCalcLength::ContainerRelative(v) => NoCalcLength::ContainerRelative(v * scalar),
             NoCalcLength::ServoCharacterWidth(_) => panic!("Can't multiply ServoCharacterWidth!"),
         }
     }
@@ -520,6 +595,7 @@ impl NoCalcLength {
             NoCalcLength::Absolute(v) => v.is_negative(),
             NoCalcLength::FontRelative(v) => v.is_negative(),
             NoCalcLength::ViewportPercentage(v) => v.is_negative(),
+            NoCalcLength::ContainerRelative(v) => v.unitless_value() < 0.,
             NoCalcLength::ServoCharacterWidth(c) => c.0 < 0,
         }
     }
@@ -556,6 +632,13 @@ impl NoCalcLength {
             "vmax" if !context.in_page_rule() => {
                 NoCalcLength::ViewportPercentage(ViewportPercentageLength::Vmax(value))
             },
+            // container-relative lengths
+            "cqw" if cfg!(feature = "servo-layout-2020") => {
+                NoCalcLength::ContainerRelative(ContainerRelativeLength::Cqw(value))
+            },
+            "cqh" if cfg!(feature = "servo-layout-2020") => {
+                NoCalcLength::ContainerRelative(ContainerRelativeLength::Cqh(value))
+            },
             _ => return Err(()),
         })
     }
@@ -574,6 +657,9 @@ impl NoCalcLength {
             (&ViewportPercentage(ref one), &ViewportPercentage(ref other)) => {
                 ViewportPercentage(one.try_sum(other)?)
             },
+            (&ContainerRelative(ref one), &ContainerRelative(ref other)) => {
+                ContainerRelative(one.try_sum(other)?)
+            },
             (&ServoCharacterWidth(ref one), &ServoCharacterWidth(ref other)) => {
                 ServoCharacterWidth(CharacterWidth(one.0 + other.0))
             },
@@ -584,6 +670,7 @@ impl NoCalcLength {
                     Absolute(..) |
                     FontRelative(..) |
                     ViewportPercentage(..) |
+                    ContainerRelative(..) |
                     ServoCharacterWidth(..) => {},
                 }
                 debug_unreachable!("Forgot to handle unit in try_sum()")
@@ -623,6 +710,7 @@ impl PartialOrd for NoCalcLength {
             (&ViewportPercentage(ref one), &ViewportPercentage(ref other)) => {
                 one.partial_cmp(other)
             },
+            (&ContainerRelative(ref one), &ContainerRelative(ref other)) => one.partial_cmp(other),
             (&ServoCharacterWidth(ref one), &ServoCharacterWidth(ref other)) => {
                 one.0.partial_cmp(&other.0)
             },
@@ -633,6 +721,7 @@ impl PartialOrd for NoCalcLength {
                     Absolute(..) |
                     FontRelative(..) |
                     ViewportPercentage(..) |
+                    ContainerRelative(..) |
                     ServoCharacterWidth(..) => {},
                 }
                 debug_unreachable!("Forgot an arm in partial_cmp?")
@@ -651,6 +740,7 @@ impl Zero for NoCalcLength {
             NoCalcLength::Absolute(v) => v.is_zero(),
             NoCalcLength::FontRelative(v) => v.is_zero(),
             NoCalcLength::ViewportPercentage(v) => v.is_zero(),
+            NoCalcLength::ContainerRelative(v) => v.unitless_value() == 0.,
             NoCalcLength::ServoCharacterWidth(v) => v.0 == 0,
         }
     }
@@ -0,0 +1,1097 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A WebDriver BiDi server, speaking <https://w3c.github.io/webdriver-bidi/>
+//! over a WebSocket.
+//!
+//! Commands are carried out with the same constellation messages as the
+//! classic HTTP server. Events are copied from the messages that script and
+//! the resource threads send to devtools, see `tap_devtools_messages`.
+
+use crate::capabilities::ServoCapabilities;
+use compositing::ConstellationMsg;
+use crossbeam_channel::{after, unbounded, Receiver, Sender};
+use devtools_traits::ScriptToDevtoolsControlMsg;
+use devtools_traits::{ChromeToDevtoolsControlMsg, ConsoleMessage, DevtoolsControlMsg};
+use devtools_traits::{LogLevel, NavigationState, NetworkEvent, PageError};
+use ipc_channel::ipc::{self, IpcReceiver};
+use ipc_channel::router::ROUTER;
+use msg::constellation_msg::{BrowsingContextId, PipelineId, TopLevelBrowsingContextId};
+use script_traits::webdriver_msg::WebDriverScriptCommand;
+use script_traits::webdriver_msg::{LoadStatus, WebDriverJSError, WebDriverJSValue};
+use script_traits::{LoadData, LoadOrigin, WebDriverCommandMsg};
+use serde_json::{json, Map, Value};
+use servo_url::ServoUrl;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+use webdriver::error::{ErrorStatus, WebDriverError, WebDriverResult};
+use ws::{self, CloseCode, Handler, Handshake};
+
+/// The events that can be subscribed to, or whole modules of them.
+const EVENTS: &[&str] = &[
+    "browsingContext.contextCreated",
+    "browsingContext.contextDestroyed",
+    "browsingContext.navigationStarted",
+    "browsingContext.load",
+    "log.entryAdded",
+    "network.beforeRequestSent",
+    "network.responseStarted",
+    "network.responseCompleted",
+];
+
+/// How long to wait for a navigation to complete.
+const LOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// An event WebDriver BiDi clients may be interested in, copied out of a
+/// message sent to devtools.
+#[derive(Debug)]
+pub enum BiDiEvent {
+    /// A new document was created in a browsing context.
+    NewGlobal(BrowsingContextId, PipelineId, ServoUrl),
+    /// A browsing context started navigating to a URL.
+    NavigationStarted(BrowsingContextId, ServoUrl),
+    /// A browsing context completed navigating to a document.
+    Load(BrowsingContextId, PipelineId, ServoUrl),
+    /// A page used the console API.
+    ConsoleMessage(PipelineId, ConsoleMessage),
+    /// A page reported an uncaught error.
+    PageError(PipelineId, PageError),
+    /// A page sent an HTTP request.
+    RequestSent {
+        id: String,
+        pipeline_id: PipelineId,
+        url: ServoUrl,
+        method: String,
+        headers: Vec<(String, String)>,
+    },
+    /// A page received the response to an HTTP request.
+    ResponseReceived {
+        id: String,
+        pipeline_id: PipelineId,
+        status: Option<(u16, Vec<u8>)>,
+        headers: Vec<(String, String)>,
+    },
+}
+
+impl BiDiEvent {
+    fn from_devtools_message(message: &DevtoolsControlMsg) -> Option<BiDiEvent> {
+        match *message {
+            DevtoolsControlMsg::FromScript(ref message) => match *message {
+                ScriptToDevtoolsControlMsg::NewGlobal(
+                    (browsing_context, pipeline, None),
+                    _,
+                    ref info,
+                ) => Some(BiDiEvent::NewGlobal(
+                    browsing_context,
+                    pipeline,
+                    info.url.clone(),
+                )),
+                ScriptToDevtoolsControlMsg::Navigate(browsing_context, ref state) => {
+                    Some(match *state {
+                        NavigationState::Start(ref url) => {
+                            BiDiEvent::NavigationStarted(browsing_context, url.clone())
+                        },
+                        NavigationState::Stop(pipeline, ref info) => {
+                            BiDiEvent::Load(browsing_context, pipeline, info.url.clone())
+                        },
+                    })
+                },
+                ScriptToDevtoolsControlMsg::ConsoleAPI(pipeline, ref message, _) => {
+                    Some(BiDiEvent::ConsoleMessage(pipeline, message.clone()))
+                },
+                ScriptToDevtoolsControlMsg::ReportPageError(pipeline, ref error) => {
+                    Some(BiDiEvent::PageError(pipeline, error.clone()))
+                },
+                _ => None,
+            },
+            DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::NetworkEvent(
+                ref id,
+                ref event,
+            )) => Some(match *event {
+                NetworkEvent::HttpRequest(ref request) => BiDiEvent::RequestSent {
+                    id: id.clone(),
+                    pipeline_id: request.pipeline_id,
+                    url: request.url.clone(),
+                    method: request.method.as_str().to_owned(),
+                    headers: headers_to_vec(Some(&request.headers)),
+                },
+                NetworkEvent::HttpResponse(ref response) => BiDiEvent::ResponseReceived {
+                    id: id.clone(),
+                    pipeline_id: response.pipeline_id,
+                    status: response.status.clone(),
+                    headers: headers_to_vec(response.headers.as_ref()),
+                },
+            }),
+            DevtoolsControlMsg::FromChrome(_) => None,
+        }
+    }
+}
+
+fn headers_to_vec(headers: Option<&http::HeaderMap>) -> Vec<(String, String)> {
+    headers
+        .into_iter()
+        .flat_map(|headers| headers.iter())
+        .map(|(name, value)| {
+            (
+                name.as_str().to_owned(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// Returns a channel to send devtools messages on in place of `devtools_chan`,
+/// and a receiver of the WebDriver BiDi events found in those messages.
+///
+/// The messages are still forwarded to `devtools_chan`, if any.
+pub fn tap_devtools_messages(
+    devtools_chan: Option<Sender<DevtoolsControlMsg>>,
+) -> (Sender<DevtoolsControlMsg>, Receiver<BiDiEvent>) {
+    let (tap_sender, tap_receiver) = unbounded();
+    let (event_sender, event_receiver) = unbounded();
+    thread::Builder::new()
+        .name("WebDriverBiDiDevtoolsTap".to_owned())
+        .spawn(move || {
+            while let Ok(message) = tap_receiver.recv() {
+                if let Some(event) = BiDiEvent::from_devtools_message(&message) {
+                    let _ = event_sender.send(event);
+                }
+                let exit = match message {
+                    DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::ServerExitMsg) => {
+                        true
+                    },
+                    _ => false,
+                };
+                if let Some(ref devtools_chan) = devtools_chan {
+                    let _ = devtools_chan.send(message);
+                }
+                if exit {
+                    break;
+                }
+            }
+        })
+        .expect("Thread spawning failed");
+    (tap_sender, event_receiver)
+}
+
+/// Start a WebDriver BiDi server listening on the given port, and return the
+/// address it is bound to. The server stops once `events` is disconnected.
+pub fn start_bidi_server(
+    port: u16,
+    constellation_chan: Sender<ConstellationMsg>,
+    events: Receiver<BiDiEvent>,
+) -> SocketAddr {
+    let (message_sender, message_receiver) = unbounded();
+    let socket = ws::Builder::new()
+        .build(move |sender: ws::Sender| Connection {
+            sender,
+            server: message_sender.clone(),
+        })
+        .and_then(|socket| socket.bind(("127.0.0.1", port)))
+        .unwrap_or_else(|_| panic!("Unable to start WebDriver BiDi server"));
+    let address = socket.local_addr().unwrap();
+    let broadcaster = socket.broadcaster();
+    info!("WebDriver BiDi server listening on {}", address);
+
+    thread::Builder::new()
+        .name("WebDriverBiDiWebSocket".to_owned())
+        .spawn(move || {
+            if let Err(error) = socket.run() {
+                warn!("WebDriver BiDi server failed ({})", error);
+            }
+        })
+        .expect("Thread spawning failed");
+
+    thread::Builder::new()
+        .name("WebDriverBiDiServer".to_owned())
+        .spawn(move || {
+            let mut server = BiDiServer::new(constellation_chan);
+            loop {
+                select! {
+                    recv(message_receiver) -> message => match message {
+                        Ok(message) => server.handle_message(message),
+                        Err(_) => break,
+                    },
+                    recv(events) -> event => match event {
+                        Ok(event) => server.handle_event(event),
+                        Err(_) => break,
+                    },
+                }
+            }
+            let _ = broadcaster.shutdown();
+        })
+        .expect("Thread spawning failed");
+
+    address
+}
+
+/// Messages from the WebSocket connections to the server thread.
+enum Message {
+    Open(ws::Sender),
+    Command(ws::Sender, String),
+    Close(ws::Sender),
+}
+
+struct Connection {
+    sender: ws::Sender,
+    server: Sender<Message>,
+}
+
+impl Handler for Connection {
+    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
+        debug!("WebDriver BiDi connection opened.");
+        let _ = self.server.send(Message::Open(self.sender.clone()));
+        Ok(())
+    }
+
+    fn on_close(&mut self, _: CloseCode, _: &str) {
+        debug!("WebDriver BiDi connection closed.");
+        let _ = self.server.send(Message::Close(self.sender.clone()));
+    }
+
+    fn on_message(&mut self, message: ws::Message) -> ws::Result<()> {
+        let text = message.into_text()?;
+        let _ = self
+            .server
+            .send(Message::Command(self.sender.clone(), text));
+        Ok(())
+    }
+}
+
+/// <https://w3c.github.io/webdriver-bidi/#session>
+struct BiDiSession {
+    id: Uuid,
+    subscriptions: Vec<Subscription>,
+}
+
+/// <https://w3c.github.io/webdriver-bidi/#subscription>
+struct Subscription {
+    /// An event name, or the name of a whole module of events.
+    event: String,
+    /// The browsing contexts to get events for, `None` for all of them.
+    contexts: Option<Vec<String>>,
+}
+
+impl Subscription {
+    fn matches(&self, event: &str, context: Option<&str>) -> bool {
+        let event_matches = self.event == event || event.starts_with(&format!("{}.", self.event));
+        let context_matches = match (&self.contexts, context) {
+            (Some(contexts), Some(context)) => contexts.iter().any(|id| id == context),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        event_matches && context_matches
+    }
+}
+
+struct Client {
+    sender: ws::Sender,
+    session: Option<BiDiSession>,
+}
+
+struct BiDiServer {
+    constellation_chan: Sender<ConstellationMsg>,
+    clients: HashMap<u32, Client>,
+    /// The ids given out for browsing contexts, and back.
+    context_ids: HashMap<BrowsingContextId, String>,
+    browsing_contexts: HashMap<String, BrowsingContextId>,
+    /// The browsing context of every pipeline with a document.
+    pipelines: HashMap<PipelineId, BrowsingContextId>,
+    /// The URL of the active document of every browsing context.
+    urls: HashMap<BrowsingContextId, ServoUrl>,
+    /// The URL and method of the requests waiting for a response.
+    requests: HashMap<String, (ServoUrl, String)>,
+}
+
+impl BiDiServer {
+    fn new(constellation_chan: Sender<ConstellationMsg>) -> BiDiServer {
+        BiDiServer {
+            constellation_chan,
+            clients: HashMap::new(),
+            context_ids: HashMap::new(),
+            browsing_contexts: HashMap::new(),
+            pipelines: HashMap::new(),
+            urls: HashMap::new(),
+            requests: HashMap::new(),
+        }
+    }
+
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Open(sender) => {
+                self.clients.insert(
+                    sender.connection_id(),
+                    Client {
+                        sender,
+                        session: None,
+                    },
+                );
+            },
+            Message::Command(sender, text) => {
+                let response = self.handle_command(sender.connection_id(), &text);
+                if let Err(error) = sender.send(response.to_string()) {
+                    warn!("Failed to send WebDriver BiDi response ({})", error);
+                }
+            },
+            Message::Close(sender) => {
+                self.clients.remove(&sender.connection_id());
+            },
+        }
+    }
+
+    /// <https://w3c.github.io/webdriver-bidi/#handle-an-incoming-message>
+    fn handle_command(&mut self, connection: u32, text: &str) -> Value {
+        let command: Value = match serde_json::from_str(text) {
+            Ok(command) => command,
+            Err(_) => {
+                return error_response(
+                    Value::Null,
+                    WebDriverError::new(ErrorStatus::InvalidArgument, "Invalid JSON"),
+                );
+            },
+        };
+        let id = match command.get("id").and_then(Value::as_u64) {
+            Some(id) => id,
+            None => {
+                return error_response(
+                    Value::Null,
+                    WebDriverError::new(ErrorStatus::InvalidArgument, "Missing command id"),
+                );
+            },
+        };
+        let method = command.get("method").and_then(Value::as_str).unwrap_or("");
+        let empty_params = Map::new();
+        let params = match command.get("params") {
+            Some(Value::Object(params)) => params,
+            Some(_) => {
+                return error_response(
+                    json!(id),
+                    WebDriverError::new(ErrorStatus::InvalidArgument, "Invalid params"),
+                );
+            },
+            None => &empty_params,
+        };
+
+        match self.handle_method(connection, method, params) {
+            Ok(result) => json!({
+                "type": "success",
+                "id": id,
+                "result": result,
+            }),
+            Err(error) => error_response(json!(id), error),
+        }
+    }
+
+    fn handle_method(
+        &mut self,
+        connection: u32,
+        method: &str,
+        params: &Map<String, Value>,
+    ) -> WebDriverResult<Value> {
+        match method {
+            "session.status" => return Ok(self.handle_session_status()),
+            "session.new" => return self.handle_session_new(connection),
+            _ => {},
+        }
+
+        if self
+            .clients
+            .get(&connection)
+            .map_or(true, |client| client.session.is_none())
+        {
+            return Err(WebDriverError::new(
+                ErrorStatus::InvalidSessionId,
+                "No session was started on this connection",
+            ));
+        }
+
+        match method {
+            "session.end" => self.handle_session_end(connection),
+            "session.subscribe" => self.handle_session_subscribe(connection, params),
+            "session.unsubscribe" => self.handle_session_unsubscribe(connection, params),
+            "browsingContext.getTree" => self.handle_get_tree(),
+            "browsingContext.create" => self.handle_create(),
+            "browsingContext.close" => self.handle_close(params),
+            "browsingContext.navigate" => self.handle_navigate(params),
+            "browsingContext.reload" => self.handle_reload(params),
+            "script.evaluate" => self.handle_evaluate(params),
+            _ => Err(WebDriverError::new(
+                ErrorStatus::UnknownCommand,
+                format!("Unknown command {}", method),
+            )),
+        }
+    }
+
+    // https://w3c.github.io/webdriver-bidi/#command-session-status
+    fn handle_session_status(&self) -> Value {
+        let ready = self.clients.values().all(|client| client.session.is_none());
+        let message = if ready {
+            "ready"
+        } else {
+            "A session already exists"
+        };
+        json!({
+            "ready": ready,
+            "message": message,
+        })
+    }
+
+    // https://w3c.github.io/webdriver-bidi/#command-session-new
+    fn handle_session_new(&mut self, connection: u32) -> WebDriverResult<Value> {
+        if self.clients.values().any(|client| client.session.is_some()) {
+            return Err(WebDriverError::new(
+                ErrorStatus::SessionNotCreated,
+                "Maximum number of active sessions",
+            ));
+        }
+        let client = self
+            .clients
+            .get_mut(&connection)
+            .ok_or_else(|| WebDriverError::new(ErrorStatus::UnknownError, "Unknown connection"))?;
+        let session = BiDiSession {
+            id: Uuid::new_v4(),
+            subscriptions: vec![],
+        };
+        let capabilities = ServoCapabilities::new();
+        let result = json!({
+            "sessionId": session.id.to_string(),
+            "capabilities": {
+                "acceptInsecureCerts": capabilities.accept_insecure_certs,
+                "browserName": capabilities.browser_name,
+                "browserVersion": capabilities.browser_version,
+                "platformName": capabilities.platform_name,
+                "setWindowRect": capabilities.set_window_rect,
+            },
+        });
+        client.session = Some(session);
+        Ok(result)
+    }
+
+    // https://w3c.github.io/webdriver-bidi/#command-session-end
+    fn handle_session_end(&mut self, connection: u32) -> WebDriverResult<Value> {
+        if let Some(client) = self.clients.get_mut(&connection) {
+            client.session = None;
+        }
+        Ok(json!({}))
+    }
+
+    /// The event names and the browsing contexts of subscription parameters.
+    fn subscription_params(
+        &self,
+        params: &Map<String, Value>,
+    ) -> WebDriverResult<(Vec<String>, Option<Vec<String>>)> {
+        let events = params
+            .get("events")
+            .and_then(Value::as_array)
+            .ok_or_else(|| WebDriverError::new(ErrorStatus::InvalidArgument, "Missing events"))?
+            .iter()
+            .map(|event| match event.as_str() {
+                Some(event) if is_known_event(event) => Ok(event.to_owned()),
+                _ => Err(WebDriverError::new(
+                    ErrorStatus::InvalidArgument,
+                    format!("Unknown event {}", event),
+                )),
+            })
+            .collect::<WebDriverResult<Vec<_>>>()?;
+        let contexts = match params.get("contexts") {
+            Some(Value::Array(contexts)) => Some(
+                contexts
+                    .iter()
+                    .map(|context| {
+                        let context = context.as_str().unwrap_or("");
+                        self.browsing_context(context).map(|_| context.to_owned())
+                    })
+                    .collect::<WebDriverResult<Vec<_>>>()?,
+            ),
+            Some(_) => {
+                return Err(WebDriverError::new(
+                    ErrorStatus::InvalidArgument,
+                    "Invalid contexts",
+                ));
+            },
+            None => None,
+        };
+        Ok((events, contexts))
+    }
+
+    // https://w3c.github.io/webdriver-bidi/#command-session-subscribe
+    fn handle_session_subscribe(
+        &mut self,
+        connection: u32,
+        params: &Map<String, Value>,
+    ) -> WebDriverResult<Value> {
+        let (events, contexts) = self.subscription_params(params)?;
+        if let Some(session) = self.session_mut(connection) {
+            session
+                .subscriptions
+                .extend(events.into_iter().map(|event| Subscription {
+                    event,
+                    contexts: contexts.clone(),
+                }));
+        }
+        Ok(json!({}))
+    }
+
+    // https://w3c.github.io/webdriver-bidi/#command-session-unsubscribe
+    fn handle_session_unsubscribe(
+        &mut self,
+        connection: u32,
+        params: &Map<String, Value>,
+    ) -> WebDriverResult<Value> {
+        let (events, contexts) = self.subscription_params(params)?;
+        let session = match self.session_mut(connection) {
+            Some(session) => session,
+            None => return Ok(json!({})),
+        };
+        let subscription_count = session.subscriptions.len();
+        session.subscriptions.retain(|subscription| {
+            !events.contains(&subscription.event) || subscription.contexts != contexts
+        });
+        if session.subscriptions.len() == subscription_count {
+            return Err(WebDriverError::new(
+                ErrorStatus::InvalidArgument,
+                "No such subscription",
+            ));
+        }
+        Ok(json!({}))
+    }
+
+    // https://w3c.github.io/webdriver-bidi/#command-browsingContext-getTree
+    fn handle_get_tree(&mut self) -> WebDriverResult<Value> {
+        let contexts = self
+            .top_level_browsing_context_ids()
+            .into_iter()
+            .map(|id| self.context_info(BrowsingContextId::from(id)))
+            .collect::<Vec<_>>();
+        Ok(json!({ "contexts": contexts }))
+    }
+
+    // https://w3c.github.io/webdriver-bidi/#command-browsingContext-create
+    fn handle_create(&mut self) -> WebDriverResult<Value> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.send_command(WebDriverCommandMsg::NewWindow(sender));
+        let top_level_browsing_context_id = receiver.recv().unwrap();
+        let context = self.context_id(BrowsingContextId::from(top_level_browsing_context_id));
+        Ok(json!({ "context": context }))
+    }
+
+    // https://w3c.github.io/webdriver-bidi/#command-browsingContext-close
+    fn handle_close(&mut self, params: &Map<String, Value>) -> WebDriverResult<Value> {
+        let top_level_browsing_context_id = self.top_level_browsing_context_param(params)?;
+        self.send_command(WebDriverCommandMsg::CloseWindow(
+            top_level_browsing_context_id,
+        ));
+
+        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+        let params = self.context_info(browsing_context_id);
+        let context = self.context_id(browsing_context_id);
+        self.emit("browsingContext.contextDestroyed", Some(&context), params);
+        self.urls.remove(&browsing_context_id);
+        Ok(json!({}))
+    }
+
+    // https://w3c.github.io/webdriver-bidi/#command-browsingContext-navigate
+    fn handle_navigate(&mut self, params: &Map<String, Value>) -> WebDriverResult<Value> {
+        let top_level_browsing_context_id = self.top_level_browsing_context_param(params)?;
+        let url = params
+            .get("url")
+            .and_then(Value::as_str)
+            .and_then(|url| ServoUrl::parse(url).ok())
+            .ok_or_else(|| WebDriverError::new(ErrorStatus::InvalidArgument, "Invalid URL"))?;
+
+        let (sender, receiver) = ipc::channel().unwrap();
+        let load_data = LoadData::new(LoadOrigin::WebDriver, url.clone(), None, None, None);
+        self.send_command(WebDriverCommandMsg::LoadUrl(
+            top_level_browsing_context_id,
+            load_data,
+            sender,
+        ));
+        self.wait_for_load(params, receiver)?;
+
+        Ok(json!({
+            "navigation": null,
+            "url": url.as_str(),
+        }))
+    }
+
+    // https://w3c.github.io/webdriver-bidi/#command-browsingContext-reload
+    fn handle_reload(&mut self, params: &Map<String, Value>) -> WebDriverResult<Value> {
+        let top_level_browsing_context_id = self.top_level_browsing_context_param(params)?;
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.send_command(WebDriverCommandMsg::Refresh(
+            top_level_browsing_context_id,
+            sender,
+        ));
+        self.wait_for_load(params, receiver)?;
+        Ok(json!({}))
+    }
+
+    /// Wait for a navigation according to the `wait` parameter. Only the
+    /// completion of the load is reported, so "interactive" waits for it too.
+    fn wait_for_load(
+        &self,
+        params: &Map<String, Value>,
+        receiver: IpcReceiver<LoadStatus>,
+    ) -> WebDriverResult<()> {
+        match params.get("wait").and_then(Value::as_str) {
+            None | Some("none") => return Ok(()),
+            Some("interactive") | Some("complete") => {},
+            Some(_) => {
+                return Err(WebDriverError::new(
+                    ErrorStatus::InvalidArgument,
+                    "Invalid wait",
+                ));
+            },
+        }
+        // IPC does not come with a timeout on receive.
+        let (sender, load_status_receiver) = unbounded();
+        ROUTER.route_ipc_receiver_to_crossbeam_sender(receiver, sender);
+        select! {
+            recv(load_status_receiver) -> _ => Ok(()),
+            recv(after(LOAD_TIMEOUT)) -> _ => Err(
+                WebDriverError::new(ErrorStatus::Timeout, "Load timed out")
+            ),
+        }
+    }
+
+    // https://w3c.github.io/webdriver-bidi/#command-script-evaluate
+    fn handle_evaluate(&mut self, params: &Map<String, Value>) -> WebDriverResult<Value> {
+        let expression = params
+            .get("expression")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                WebDriverError::new(ErrorStatus::InvalidArgument, "Missing expression")
+            })?;
+        let context = params
+            .get("target")
+            .and_then(|target| target.get("context"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                WebDriverError::new(ErrorStatus::InvalidArgument, "Missing target context")
+            })?;
+        let browsing_context_id = self.browsing_context(context)?;
+        let await_promise = params
+            .get("awaitPromise")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+
+        // The expression is evaluated with an eval, and reports whether it
+        // completed normally along with its value or exception.
+        let expression = Value::from(expression).to_string();
+        let (sender, receiver) = ipc::channel().unwrap();
+        let command = if await_promise {
+            let script = format!(
+                "(function(callback) {{ \
+                     new Promise(function(resolve) {{ resolve(eval({})); }}).then( \
+                         function(value) {{ callback([true, value]); }}, \
+                         function(error) {{ callback([false, String(error)]); }}); \
+                 }})(window.webdriverCallback)",
+                expression
+            );
+            WebDriverScriptCommand::ExecuteAsyncScript(script, sender)
+        } else {
+            let script = format!(
+                "(function() {{ \
+                     try {{ return [true, eval({})]; }} \
+                     catch (error) {{ return [false, String(error)]; }} \
+                 }})()",
+                expression
+            );
+            WebDriverScriptCommand::ExecuteScript(script, sender)
+        };
+        self.send_command(WebDriverCommandMsg::ScriptCommand(
+            browsing_context_id,
+            command,
+        ));
+
+        let completion = match receiver.recv().unwrap() {
+            Ok(WebDriverJSValue::ArrayLike(mut completion)) if completion.len() == 2 => {
+                let value = completion.pop().unwrap();
+                match completion.pop().unwrap() {
+                    WebDriverJSValue::Boolean(normal) => Ok((normal, value)),
+                    _ => Err(WebDriverJSError::UnknownType),
+                }
+            },
+            Ok(_) => Err(WebDriverJSError::UnknownType),
+            Err(error) => Err(error),
+        };
+        let realm = context.to_owned();
+        match completion {
+            Ok((true, value)) => Ok(json!({
+                "type": "success",
+                "result": remote_value(value),
+                "realm": realm,
+            })),
+            Ok((false, error)) => {
+                let text = match error {
+                    WebDriverJSValue::String(text) => text,
+                    _ => String::new(),
+                };
+                Ok(json!({
+                    "type": "exception",
+                    "exceptionDetails": {
+                        "columnNumber": 0,
+                        "exception": { "type": "error" },
+                        "lineNumber": 0,
+                        "stackTrace": { "callFrames": [] },
+                        "text": text,
+                    },
+                    "realm": realm,
+                }))
+            },
+            Err(WebDriverJSError::BrowsingContextNotFound) => Err(WebDriverError::new(
+                ErrorStatus::NoSuchFrame,
+                "The browsing context has no document",
+            )),
+            Err(WebDriverJSError::UnknownType) => Err(WebDriverError::new(
+                ErrorStatus::UnsupportedOperation,
+                "Unsupported return type",
+            )),
+            Err(error) => Err(WebDriverError::new(
+                ErrorStatus::UnknownError,
+                format!("Evaluation failed ({:?})", error),
+            )),
+        }
+    }
+
+    fn handle_event(&mut self, event: BiDiEvent) {
+        match event {
+            BiDiEvent::NewGlobal(browsing_context_id, pipeline_id, url) => {
+                self.pipelines.insert(pipeline_id, browsing_context_id);
+                let is_new_context = !self.context_ids.contains_key(&browsing_context_id);
+                self.urls.insert(browsing_context_id, url);
+                if is_new_context {
+                    let params = self.context_info(browsing_context_id);
+                    let context = self.context_id(browsing_context_id);
+                    self.emit("browsingContext.contextCreated", Some(&context), params);
+                }
+            },
+            BiDiEvent::NavigationStarted(browsing_context_id, url) => {
+                let context = self.context_id(browsing_context_id);
+                let params = json!({
+                    "context": context,
+                    "navigation": null,
+                    "timestamp": timestamp(),
+                    "url": url.as_str(),
+                });
+                self.emit("browsingContext.navigationStarted", Some(&context), params);
+            },
+            BiDiEvent::Load(browsing_context_id, pipeline_id, url) => {
+                self.pipelines.insert(pipeline_id, browsing_context_id);
+                self.urls.insert(browsing_context_id, url.clone());
+                let context = self.context_id(browsing_context_id);
+                let params = json!({
+                    "context": context,
+                    "navigation": null,
+                    "timestamp": timestamp(),
+                    "url": url.as_str(),
+                });
+                self.emit("browsingContext.load", Some(&context), params);
+            },
+            BiDiEvent::ConsoleMessage(pipeline_id, message) => {
+                let context = self.pipeline_context_id(pipeline_id);
+                let (level, method) = match message.logLevel {
+                    LogLevel::Log => ("info", "log"),
+                    LogLevel::Info => ("info", "info"),
+                    LogLevel::Debug => ("debug", "debug"),
+                    LogLevel::Warn => ("warn", "warn"),
+                    LogLevel::Error => ("error", "error"),
+                };
+                let params = json!({
+                    "type": "console",
+                    "level": level,
+                    "source": { "realm": context, "context": context },
+                    "text": message.message,
+                    "timestamp": timestamp(),
+                    "stackTrace": {
+                        "callFrames": [{
+                            "columnNumber": message.columnNumber,
+                            "functionName": "",
+                            "lineNumber": message.lineNumber,
+                            "url": message.filename,
+                        }],
+                    },
+                    "method": method,
+                    "args": [{ "type": "string", "value": message.message }],
+                });
+                self.emit("log.entryAdded", context.as_deref(), params);
+            },
+            BiDiEvent::PageError(pipeline_id, error) => {
+                let context = self.pipeline_context_id(pipeline_id);
+                let params = json!({
+                    "type": "javascript",
+                    "level": "error",
+                    "source": { "realm": context, "context": context },
+                    "text": error.errorMessage,
+                    "timestamp": error.timeStamp,
+                    "stackTrace": {
+                        "callFrames": [{
+                            "columnNumber": error.columnNumber,
+                            "functionName": "",
+                            "lineNumber": error.lineNumber,
+                            "url": error.sourceName,
+                        }],
+                    },
+                });
+                self.emit("log.entryAdded", context.as_deref(), params);
+            },
+            BiDiEvent::RequestSent {
+                id,
+                pipeline_id,
+                url,
+                method,
+                headers,
+            } => {
+                let context = self.pipeline_context_id(pipeline_id);
+                let params = json!({
+                    "context": context,
+                    "navigation": null,
+                    "redirectCount": 0,
+                    "request": request_data(&id, &url, &method, &headers),
+                    "timestamp": timestamp(),
+                    "isBlocked": false,
+                    "initiator": { "type": "other" },
+                });
+                self.requests.insert(id, (url, method));
+                self.emit("network.beforeRequestSent", context.as_deref(), params);
+            },
+            BiDiEvent::ResponseReceived {
+                id,
+                pipeline_id,
+                status,
+                headers,
+            } => {
+                let (url, method) = match self.requests.remove(&id) {
+                    Some(request) => request,
+                    None => return,
+                };
+                let context = self.pipeline_context_id(pipeline_id);
+                let (status, status_text) = status
+                    .map(|(code, text)| (code, String::from_utf8_lossy(&text).into_owned()))
+                    .unwrap_or((0, String::new()));
+                let mime_type = headers
+                    .iter()
+                    .find(|&(name, _)| name.eq_ignore_ascii_case("content-type"))
+                    .map_or("", |(_, value)| &**value)
+                    .to_owned();
+                let params = json!({
+                    "context": context,
+                    "navigation": null,
+                    "redirectCount": 0,
+                    "request": request_data(&id, &url, &method, &[]),
+                    "timestamp": timestamp(),
+                    "isBlocked": false,
+                    "response": {
+                        "url": url.as_str(),
+                        "protocol": "http/1.1",
+                        "status": status,
+                        "statusText": status_text,
+                        "fromCache": false,
+                        "headers": headers_data(&headers),
+                        "mimeType": mime_type,
+                        "bytesReceived": 0,
+                        "headersSize": null,
+                        "bodySize": null,
+                        "content": { "size": 0 },
+                    },
+                });
+                let context = context.as_deref();
+                self.emit("network.responseStarted", context, params.clone());
+                self.emit("network.responseCompleted", context, params);
+            },
+        }
+    }
+
+    /// Send an event to the sessions subscribed to it.
+    fn emit(&self, event: &str, context: Option<&str>, params: Value) {
+        let message = json!({
+            "type": "event",
+            "method": event,
+            "params": params,
+        })
+        .to_string();
+        for client in self.clients.values() {
+            let subscribed = client.session.as_ref().map_or(false, |session| {
+                session
+                    .subscriptions
+                    .iter()
+                    .any(|subscription| subscription.matches(event, context))
+            });
+            if subscribed {
+                if let Err(error) = client.sender.send(message.clone()) {
+                    warn!("Failed to send WebDriver BiDi event ({})", error);
+                }
+            }
+        }
+    }
+
+    fn session_mut(&mut self, connection: u32) -> Option<&mut BiDiSession> {
+        self.clients
+            .get_mut(&connection)
+            .and_then(|client| client.session.as_mut())
+    }
+
+    fn send_command(&self, cmd_msg: WebDriverCommandMsg) {
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+    }
+
+    fn top_level_browsing_context_ids(&self) -> Vec<TopLevelBrowsingContextId> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.send_command(WebDriverCommandMsg::GetTopLevelBrowsingContexts(sender));
+        receiver.recv().unwrap()
+    }
+
+    /// The id of a browsing context, given out the first time it is asked for.
+    fn context_id(&mut self, browsing_context_id: BrowsingContextId) -> String {
+        let browsing_contexts = &mut self.browsing_contexts;
+        self.context_ids
+            .entry(browsing_context_id)
+            .or_insert_with(|| {
+                let context = Uuid::new_v4().to_string();
+                browsing_contexts.insert(context.clone(), browsing_context_id);
+                context
+            })
+            .clone()
+    }
+
+    fn pipeline_context_id(&mut self, pipeline_id: PipelineId) -> Option<String> {
+        let browsing_context_id = *self.pipelines.get(&pipeline_id)?;
+        Some(self.context_id(browsing_context_id))
+    }
+
+    fn browsing_context(&self, context: &str) -> WebDriverResult<BrowsingContextId> {
+        self.browsing_contexts.get(context).cloned().ok_or_else(|| {
+            WebDriverError::new(
+                ErrorStatus::NoSuchFrame,
+                format!("No browsing context with id {}", context),
+            )
+        })
+    }
+
+    /// The top-level browsing context named by the `context` parameter.
+    /// Navigating nested browsing contexts is not supported.
+    fn top_level_browsing_context_param(
+        &self,
+        params: &Map<String, Value>,
+    ) -> WebDriverResult<TopLevelBrowsingContextId> {
+        let context = params
+            .get("context")
+            .and_then(Value::as_str)
+            .ok_or_else(|| WebDriverError::new(ErrorStatus::InvalidArgument, "Missing context"))?;
+        let browsing_context_id = self.browsing_context(context)?;
+        self.top_level_browsing_context_ids()
+            .into_iter()
+            .find(|id| *id == browsing_context_id)
+            .ok_or_else(|| {
+                WebDriverError::new(
+                    ErrorStatus::UnsupportedOperation,
+                    "Only top-level browsing contexts are supported",
+                )
+            })
+    }
+
+    /// <https://w3c.github.io/webdriver-bidi/#type-browsingContext-Info>
+    fn context_info(&mut self, browsing_context_id: BrowsingContextId) -> Value {
+        let url = self
+            .urls
+            .get(&browsing_context_id)
+            .map_or("about:blank".to_owned(), |url| url.as_str().to_owned());
+        json!({
+            "context": self.context_id(browsing_context_id),
+            "url": url,
+            "children": null,
+            "parent": null,
+            "userContext": "default",
+        })
+    }
+}
+
+fn is_known_event(event: &str) -> bool {
+    EVENTS
+        .iter()
+        .any(|known| *known == event || known.starts_with(&format!("{}.", event)))
+}
+
+fn error_response(id: Value, error: WebDriverError) -> Value {
+    json!({
+        "type": "error",
+        "id": id,
+        "error": error.error.error_code(),
+        "message": error.message,
+    })
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as u64)
+}
+
+fn headers_data(headers: &[(String, String)]) -> Value {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            json!({
+                "name": name,
+                "value": { "type": "string", "value": value },
+            })
+        })
+        .collect()
+}
+
+/// <https://w3c.github.io/webdriver-bidi/#type-network-RequestData>
+fn request_data(id: &str, url: &ServoUrl, method: &str, headers: &[(String, String)]) -> Value {
+    json!({
+        "request": id,
+        "url": url.as_str(),
+        "method": method,
+        "headers": headers_data(headers),
+        "cookies": [],
+        "headersSize": -1,
+        "bodySize": null,
+        "timings": {},
+    })
+}
+
+/// <https://w3c.github.io/webdriver-bidi/#type-script-RemoteValue>
+fn remote_value(value: WebDriverJSValue) -> Value {
+    match value {
+        WebDriverJSValue::Undefined => json!({ "type": "undefined" }),
+        WebDriverJSValue::Null => json!({ "type": "null" }),
+        WebDriverJSValue::Boolean(value) => json!({ "type": "boolean", "value": value }),
+        WebDriverJSValue::Number(value) => {
+            let value = if value.is_nan() {
+                json!("NaN")
+            } else if value == 0. && value.is_sign_negative() {
+                json!("-0")
+            } else if value.is_infinite() {
+                json!(if value > 0. { "Infinity" } else { "-Infinity" })
+            } else {
+                json!(value)
+            };
+            json!({ "type": "number", "value": value })
+        },
+        WebDriverJSValue::String(value) => json!({ "type": "string", "value": value }),
+        WebDriverJSValue::Element(element) => {
+            json!({ "type": "node", "sharedId": element.to_string() })
+        },
+        WebDriverJSValue::Frame(_) | WebDriverJSValue::Window(_) => json!({ "type": "window" }),
+        WebDriverJSValue::ArrayLike(values) => json!({
+            "type": "array",
+            "value": values.into_iter().map(remote_value).collect::<Vec<_>>(),
+        }),
+        WebDriverJSValue::Object(properties) => json!({
+            "type": "object",
+            "value": properties
+                .into_iter()
+                .map(|(key, value)| json!([key, remote_value(value)]))
+                .collect::<Vec<_>>(),
+        }),
+    }
+}
@@ -14,17 +14,20 @@ extern crate log;
 extern crate serde;
 
 mod actions;
+mod bidi;
 mod capabilities;
 
+pub use crate::bidi::{start_bidi_server, tap_devtools_messages, BiDiEvent};
+
 use crate::actions::{InputSourceState, PointerInputState};
 use base64;
 use capabilities::ServoCapabilities;
 use compositing::ConstellationMsg;
 use crossbeam_channel::{after, unbounded, Receiver, Sender};
-use euclid::{Rect, Size2D};
+use euclid::{Rect, SideOffsets2D, Size2D};
 use hyper::Method;
 use image::{DynamicImage, ImageFormat, RgbImage};
-use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use ipc_channel::router::ROUTER;
 use keyboard_types::webdriver::send_keys;
 use msg::constellation_msg::{BrowsingContextId, TopLevelBrowsingContextId, TraversalDirection};
@@ -33,7 +36,10 @@ use script_traits::webdriver_msg::{LoadStatus, WebDriverCookieError, WebDriverFr
 use script_traits::webdriver_msg::{
     WebDriverJSError, WebDriverJSResult, WebDriverJSValue, WebDriverScriptCommand,
 };
-use script_traits::{LoadData, LoadOrigin, WebDriverCommandMsg};
+use script_traits::webdriver_msg::{
+    WebDriverUserPromptAction, WebDriverUserPromptError, WebDriverWindowState,
+};
+use script_traits::{LoadData, LoadOrigin, PrintSettings, WebDriverCommandMsg, WindowSizeData};
 use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
 use serde::ser::{Serialize, Serializer};
 use serde_json::{json, Value};
@@ -58,7 +64,8 @@ use webdriver::command::{
     AddCookieParameters, GetParameters, JavascriptCommandParameters, LocatorParameters,
 };
 use webdriver::command::{
-    NewSessionParameters, SendKeysParameters, SwitchToFrameParameters, TimeoutsParameters,
+    NewSessionParameters, NewWindowParameters, SendKeysParameters, SwitchToFrameParameters,
+    TimeoutsParameters,
 };
 use webdriver::command::{
     WebDriverCommand, WebDriverExtensionCommand, WebDriverMessage, WindowRectParameters,
@@ -66,8 +73,9 @@ use webdriver::command::{
 use webdriver::common::{Cookie, Date, LocatorStrategy, Parameters, WebElement};
 use webdriver::error::{ErrorStatus, WebDriverError, WebDriverResult};
 use webdriver::httpapi::WebDriverExtensionRoute;
-use webdriver::response::{CookieResponse, CookiesResponse};
-use webdriver::response::{ElementRectResponse, NewSessionResponse, ValueResponse};
+use webdriver::response::ValueResponse;
+use webdriver::response::{CloseWindowResponse, CookieResponse, CookiesResponse};
+use webdriver::response::{ElementRectResponse, NewSessionResponse, NewWindowResponse};
 use webdriver::response::{TimeoutsResponse, WebDriverResponse, WindowRectResponse};
 use webdriver::server::{self, Session, WebDriverHandler};
 
@@ -88,6 +96,11 @@ fn extension_routes() -> Vec<(Method, &'static str, ServoExtensionRoute)> {
             "/session/{sessionId}/servo/prefs/reset",
             ServoExtensionRoute::ResetPrefs,
         ),
+        (
+            Method::POST,
+            "/session/{sessionId}/print",
+            ServoExtensionRoute::Print,
+        ),
     ];
 }
 
@@ -125,6 +138,10 @@ pub struct WebDriverSession {
     browsing_context_id: BrowsingContextId,
     top_level_browsing_context_id: TopLevelBrowsingContextId,
 
+    /// The window handles given out for the top-level browsing contexts.
+    /// https://w3c.github.io/webdriver/#dfn-window-handle
+    window_handles: HashMap<TopLevelBrowsingContextId, String>,
+
     /// Time to wait for injected scripts to run before interrupting them.  A [`None`] value
     /// specifies that the script should run indefinitely.
     script_timeout: Option<u64>,
@@ -159,6 +176,8 @@ impl WebDriverSession {
             browsing_context_id: browsing_context_id,
             top_level_browsing_context_id: top_level_browsing_context_id,
 
+            window_handles: HashMap::new(),
+
             script_timeout: Some(30_000),
             load_timeout: 300_000,
             implicit_wait_timeout: 0,
@@ -194,6 +213,7 @@ enum ServoExtensionRoute {
     GetPrefs,
     SetPrefs,
     ResetPrefs,
+    Print,
 }
 
 impl WebDriverExtensionRoute for ServoExtensionRoute {
@@ -217,6 +237,10 @@ impl WebDriverExtensionRoute for ServoExtensionRoute {
                 let parameters: GetPrefsParameters = serde_json::from_value(body_data.clone())?;
                 ServoExtensionCommand::ResetPrefs(parameters)
             },
+            ServoExtensionRoute::Print => {
+                let parameters: PrintParameters = serde_json::from_value(body_data.clone())?;
+                ServoExtensionCommand::Print(parameters)
+            },
         };
         Ok(WebDriverCommand::Extension(command))
     }
@@ -227,6 +251,7 @@ enum ServoExtensionCommand {
     GetPrefs(GetPrefsParameters),
     SetPrefs(SetPrefsParameters),
     ResetPrefs(GetPrefsParameters),
+    Print(PrintParameters),
 }
 
 impl WebDriverExtensionCommand for ServoExtensionCommand {
@@ -235,6 +260,7 @@ impl WebDriverExtensionCommand for ServoExtensionCommand {
             ServoExtensionCommand::GetPrefs(ref x) => serde_json::to_value(x).ok(),
             ServoExtensionCommand::SetPrefs(ref x) => serde_json::to_value(x).ok(),
             ServoExtensionCommand::ResetPrefs(ref x) => serde_json::to_value(x).ok(),
+            ServoExtensionCommand::Print(ref x) => serde_json::to_value(x).ok(),
         }
     }
 }
@@ -353,6 +379,173 @@ struct SetPrefsParameters {
     prefs: Vec<(String, WebDriverPrefValue)>,
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum PrintOrientation {
+    Portrait,
+    Landscape,
+}
+
+impl Default for PrintOrientation {
+    fn default() -> Self {
+        PrintOrientation::Portrait
+    }
+}
+
+/// The size of the page, in centimeters.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+struct PrintPage {
+    width: f64,
+    height: f64,
+}
+
+impl Default for PrintPage {
+    fn default() -> Self {
+        // US Letter.
+        PrintPage {
+            width: 21.59,
+            height: 27.94,
+        }
+    }
+}
+
+/// The margins of the page, in centimeters.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+struct PrintMargins {
+    top: f64,
+    bottom: f64,
+    left: f64,
+    right: f64,
+}
+
+impl Default for PrintMargins {
+    fn default() -> Self {
+        PrintMargins {
+            top: 1.,
+            bottom: 1.,
+            left: 1.,
+            right: 1.,
+        }
+    }
+}
+
+/// <https://w3c.github.io/webdriver/#print-page>
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+struct PrintParameters {
+    orientation: PrintOrientation,
+    scale: f64,
+    background: bool,
+    page: PrintPage,
+    margin: PrintMargins,
+    shrink_to_fit: bool,
+    page_ranges: Vec<Value>,
+}
+
+impl Default for PrintParameters {
+    fn default() -> Self {
+        PrintParameters {
+            orientation: PrintOrientation::default(),
+            scale: 1.,
+            background: false,
+            page: PrintPage::default(),
+            margin: PrintMargins::default(),
+            shrink_to_fit: true,
+            page_ranges: vec![],
+        }
+    }
+}
+
+impl PrintParameters {
+    /// Validates the parameters, and converts them to the page layout used by script.
+    fn to_print_settings(&self) -> WebDriverResult<PrintSettings> {
+        // The smallest page is a point wide, and lengths are converted to CSS pixels.
+        const MIN_PAGE_SIZE: f64 = 2.54 / 72.;
+        const PX_PER_CM: f64 = 96. / 2.54;
+
+        let invalid_argument =
+            |message| Err(WebDriverError::new(ErrorStatus::InvalidArgument, message));
+        if !(0.1..=2.).contains(&self.scale) {
+            return invalid_argument("The scale must be between 0.1 and 2");
+        }
+        if self.page.width < MIN_PAGE_SIZE || self.page.height < MIN_PAGE_SIZE {
+            return invalid_argument("The page is too small");
+        }
+        let margin = &self.margin;
+        if margin.top < 0. || margin.bottom < 0. || margin.left < 0. || margin.right < 0. {
+            return invalid_argument("The margins must not be negative");
+        }
+
+        let (width, height) = match self.orientation {
+            PrintOrientation::Portrait => (self.page.width, self.page.height),
+            PrintOrientation::Landscape => (self.page.height, self.page.width),
+        };
+        if width - margin.left - margin.right < MIN_PAGE_SIZE ||
+            height - margin.top - margin.bottom < MIN_PAGE_SIZE
+        {
+            return invalid_argument("The margins leave no room for the content");
+        }
+
+        let page_ranges = match self
+            .page_ranges
+            .iter()
+            .map(parse_page_range)
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(page_ranges) => page_ranges,
+            None => return invalid_argument("Invalid page range"),
+        };
+
+        let px = |cm: f64| (cm * PX_PER_CM) as f32;
+        Ok(PrintSettings {
+            page_size: Size2D::new(px(width), px(height)),
+            margins: SideOffsets2D::new(
+                px(margin.top),
+                px(margin.right),
+                px(margin.bottom),
+                px(margin.left),
+            ),
+            scale: self.scale as f32,
+            shrink_to_fit: self.shrink_to_fit,
+            background: self.background,
+            page_ranges,
+        })
+    }
+}
+
+/// Parses a page range, which is either a page number or a string like "1-5", "-5", "1-" or
+/// "3", into inclusive bounds.
+fn parse_page_range(range: &Value) -> Option<(u32, u32)> {
+    let page_number = |page: &str| page.trim().parse::<u32>().ok().filter(|&page| page >= 1);
+    let bound = |page: &str, default: u32| match page.trim() {
+        "" => Some(default),
+        page => page_number(page),
+    };
+    match *range {
+        Value::Number(ref page) => {
+            let page = page
+                .as_u64()
+                .filter(|&page| page >= 1 && page <= u32::MAX as u64)?;
+            Some((page as u32, page as u32))
+        },
+        Value::String(ref range) => {
+            let mut bounds = range.splitn(2, '-');
+            let start = bounds.next()?;
+            let (start, end) = match bounds.next() {
+                Some(end) => (bound(start, 1)?, bound(end, u32::MAX)?),
+                None => (page_number(start)?, page_number(start)?),
+            };
+            if start > end {
+                return None;
+            }
+            Some((start, end))
+        },
+        _ => None,
+    }
+}
+
 fn map_to_vec<'de, D>(de: D) -> Result<Vec<(String, WebDriverPrefValue)>, D::Error>
 where
     D: Deserializer<'de>,
@@ -544,8 +737,10 @@ impl Handler {
 
                     match processed.get("unhandledPromptBehavior") {
                         Some(unhandled_prompt_behavior) => {
-                            session.unhandled_prompt_behavior =
-                                unhandled_prompt_behavior.to_string()
+                            session.unhandled_prompt_behavior = unhandled_prompt_behavior
+                                .as_str()
+                                .unwrap_or("dismiss and notify")
+                                .to_string()
                         },
                         None => {
                             processed.insert(
@@ -578,6 +773,12 @@ impl Handler {
                         NewSessionResponse::new(session.id.to_string(), Value::Object(processed));
                     self.session = Some(session);
 
+                    // Keep the user prompts opened by content for the session to handle.
+                    let cmd_msg = WebDriverCommandMsg::InterceptUserPrompts(true);
+                    self.constellation_chan
+                        .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+                        .unwrap();
+
                     Ok(WebDriverResponse::NewSession(response))
                 },
                 None => Ok(WebDriverResponse::Void),
@@ -591,10 +792,19 @@ impl Handler {
     }
 
     fn handle_delete_session(&mut self) -> WebDriverResult<WebDriverResponse> {
-        self.session = None;
+        self.end_session();
         Ok(WebDriverResponse::DeleteSession)
     }
 
+    fn end_session(&mut self) {
+        if self.session.take().is_some() {
+            let cmd_msg = WebDriverCommandMsg::InterceptUserPrompts(false);
+            self.constellation_chan
+                .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+                .unwrap();
+        }
+    }
+
     // https://w3c.github.io/webdriver/#status
     fn handle_status(&self) -> WebDriverResult<WebDriverResponse> {
         Ok(WebDriverResponse::Generic(ValueResponse(
@@ -610,6 +820,8 @@ impl Handler {
         &self,
         cmd_msg: WebDriverScriptCommand,
     ) -> WebDriverResult<()> {
+        // The script thread can't handle the command while a prompt is shown.
+        self.handle_any_user_prompts()?;
         let browsing_context_id = self.session()?.browsing_context_id;
         let msg = ConstellationMsg::WebDriverCommand(WebDriverCommandMsg::ScriptCommand(
             browsing_context_id,
@@ -620,6 +832,7 @@ impl Handler {
     }
 
     fn top_level_script_command(&self, cmd_msg: WebDriverScriptCommand) -> WebDriverResult<()> {
+        self.handle_any_user_prompts()?;
         let browsing_context_id =
             BrowsingContextId::from(self.session()?.top_level_browsing_context_id);
         let msg = ConstellationMsg::WebDriverCommand(WebDriverCommandMsg::ScriptCommand(
@@ -722,6 +935,17 @@ impl Handler {
             .send(ConstellationMsg::WebDriverCommand(cmd_msg))
             .unwrap();
 
+        self.wait_for_window_rect(top_level_browsing_context_id, sender, receiver)
+    }
+
+    /// Wait for the window to be resized after a command sent with `sender`,
+    /// or for the resize timeout, and reply with the resulting window rect.
+    fn wait_for_window_rect(
+        &self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        sender: IpcSender<WindowSizeData>,
+        receiver: IpcReceiver<WindowSizeData>,
+    ) -> WebDriverResult<WebDriverResponse> {
         let timeout = self.resize_timeout;
         let constellation_chan = self.constellation_chan.clone();
         thread::spawn(move || {
@@ -745,6 +969,28 @@ impl Handler {
         Ok(WebDriverResponse::WindowRect(window_size_response))
     }
 
+    // https://w3c.github.io/webdriver/#maximize-window
+    // https://w3c.github.io/webdriver/#minimize-window
+    // https://w3c.github.io/webdriver/#fullscreen-window
+    fn handle_set_window_state(
+        &self,
+        state: WebDriverWindowState,
+    ) -> WebDriverResult<WebDriverResponse> {
+        self.handle_any_user_prompts()?;
+        let (sender, receiver) = ipc::channel().unwrap();
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let cmd_msg = WebDriverCommandMsg::SetWindowState(
+            top_level_browsing_context_id,
+            state,
+            sender.clone(),
+        );
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+
+        self.wait_for_window_rect(top_level_browsing_context_id, sender, receiver)
+    }
+
     fn handle_is_enabled(&self, element: &WebElement) -> WebDriverResult<WebDriverResponse> {
         let (sender, receiver) = ipc::channel().unwrap();
 
@@ -818,26 +1064,98 @@ impl Handler {
         )))
     }
 
-    fn handle_window_handle(&self) -> WebDriverResult<WebDriverResponse> {
-        // For now we assume there's only one window so just use the session
-        // id as the window id
-        let handle = self.session.as_ref().unwrap().id.to_string();
+    /// The window handle of a top-level browsing context, given out the
+    /// first time it is asked for.
+    fn window_handle(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) -> WebDriverResult<String> {
+        Ok(self
+            .session_mut()?
+            .window_handles
+            .entry(top_level_browsing_context_id)
+            .or_insert_with(|| Uuid::new_v4().to_string())
+            .clone())
+    }
+
+    fn top_level_browsing_context_ids(&self) -> Vec<TopLevelBrowsingContextId> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::GetTopLevelBrowsingContexts(sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+        receiver.recv().unwrap()
+    }
+
+    fn handle_window_handle(&mut self) -> WebDriverResult<WebDriverResponse> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let handle = self.window_handle(top_level_browsing_context_id)?;
         Ok(WebDriverResponse::Generic(ValueResponse(
             serde_json::to_value(handle)?,
         )))
     }
 
-    fn handle_window_handles(&self) -> WebDriverResult<WebDriverResponse> {
-        // For now we assume there's only one window so just use the session
-        // id as the window id
-        let handles = vec![serde_json::to_value(
-            self.session.as_ref().unwrap().id.to_string(),
-        )?];
+    fn handle_window_handles(&mut self) -> WebDriverResult<WebDriverResponse> {
+        let handles = self
+            .top_level_browsing_context_ids()
+            .into_iter()
+            .map(|id| self.window_handle(id))
+            .collect::<WebDriverResult<Vec<_>>>()?;
         Ok(WebDriverResponse::Generic(ValueResponse(
             serde_json::to_value(handles)?,
         )))
     }
 
+    // https://w3c.github.io/webdriver/#new-window
+    fn handle_new_window(
+        &mut self,
+        _parameters: &NewWindowParameters,
+    ) -> WebDriverResult<WebDriverResponse> {
+        self.handle_any_user_prompts()?;
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::NewWindow(sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+
+        let top_level_browsing_context_id = receiver.recv().unwrap();
+        let handle = self.window_handle(top_level_browsing_context_id)?;
+        Ok(WebDriverResponse::NewWindow(NewWindowResponse {
+            handle,
+            typ: "tab".to_string(),
+        }))
+    }
+
+    // https://w3c.github.io/webdriver/#close-window
+    fn handle_close_window(&mut self) -> WebDriverResult<WebDriverResponse> {
+        // Step 3
+        self.handle_any_user_prompts()?;
+
+        // Step 4
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let cmd_msg = WebDriverCommandMsg::CloseWindow(top_level_browsing_context_id);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+        self.session_mut()?
+            .window_handles
+            .remove(&top_level_browsing_context_id);
+
+        // Step 5
+        let top_level_browsing_context_ids = self.top_level_browsing_context_ids();
+        if top_level_browsing_context_ids.is_empty() {
+            self.end_session();
+            return Ok(WebDriverResponse::CloseWindow(CloseWindowResponse(vec![])));
+        }
+
+        // Step 6
+        let handles = top_level_browsing_context_ids
+            .into_iter()
+            .map(|id| self.window_handle(id))
+            .collect::<WebDriverResult<Vec<_>>>()?;
+        Ok(WebDriverResponse::CloseWindow(CloseWindowResponse(handles)))
+    }
+
     fn handle_find_element(
         &self,
         parameters: &LocatorParameters,
@@ -909,15 +1227,26 @@ impl Handler {
         &mut self,
         parameters: &SwitchToWindowParameters,
     ) -> WebDriverResult<WebDriverResponse> {
-        // For now we assume there is only one window which has the current
-        // session's id as window id
-        if parameters.handle == self.session.as_ref().unwrap().id.to_string() {
-            Ok(WebDriverResponse::Void)
-        } else {
-            Err(WebDriverError::new(
+        let top_level_browsing_context_ids = self.top_level_browsing_context_ids();
+        let session = self.session_mut()?;
+        let top_level_browsing_context_id = session
+            .window_handles
+            .iter()
+            .find(|&(id, handle)| {
+                *handle == parameters.handle && top_level_browsing_context_ids.contains(id)
+            })
+            .map(|(id, _)| *id);
+        match top_level_browsing_context_id {
+            Some(top_level_browsing_context_id) => {
+                session.top_level_browsing_context_id = top_level_browsing_context_id;
+                session.browsing_context_id =
+                    BrowsingContextId::from(top_level_browsing_context_id);
+                Ok(WebDriverResponse::Void)
+            },
+            None => Err(WebDriverError::new(
                 ErrorStatus::NoSuchWindow,
                 "No such window",
-            ))
+            )),
         }
     }
 
@@ -1276,6 +1605,17 @@ impl Handler {
         }
     }
 
+    // https://w3c.github.io/webdriver/#delete-cookie
+    fn handle_delete_cookie(&self, name: &str) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::DeleteCookie(name.to_owned(), sender);
+        self.browsing_context_script_command(cmd)?;
+        match receiver.recv().unwrap() {
+            Ok(_) => Ok(WebDriverResponse::Void),
+            Err(error) => Err(WebDriverError::new(error, "")),
+        }
+    }
+
     fn handle_delete_cookies(&self) -> WebDriverResult<WebDriverResponse> {
         let (sender, receiver) = ipc::channel().unwrap();
         let cmd = WebDriverScriptCommand::DeleteCookies(sender);
@@ -1286,9 +1626,75 @@ impl Handler {
         }
     }
 
+    /// Act on the user prompt of the current top-level browsing context.
+    fn user_prompt_command(
+        &self,
+        action: WebDriverUserPromptAction,
+    ) -> WebDriverResult<Option<String>> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let cmd_msg =
+            WebDriverCommandMsg::HandleUserPrompt(top_level_browsing_context_id, action, sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+
+        receiver.recv().unwrap().map_err(|error| match error {
+            WebDriverUserPromptError::NoSuchAlert => {
+                WebDriverError::new(ErrorStatus::NoSuchAlert, "No user prompt is shown")
+            },
+            WebDriverUserPromptError::ElementNotInteractable => WebDriverError::new(
+                ErrorStatus::ElementNotInteractable,
+                "The user prompt does not accept text",
+            ),
+        })
+    }
+
+    // https://w3c.github.io/webdriver/#dfn-handle-any-user-prompts
+    fn handle_any_user_prompts(&self) -> WebDriverResult<()> {
+        let behavior = self.session()?.unhandled_prompt_behavior.as_str();
+        let action = match behavior {
+            "accept" | "accept and notify" => WebDriverUserPromptAction::Accept,
+            "ignore" => WebDriverUserPromptAction::GetText,
+            _ => WebDriverUserPromptAction::Dismiss,
+        };
+        match self.user_prompt_command(action) {
+            Ok(_) if behavior == "ignore" || behavior.ends_with("and notify") => Err(
+                WebDriverError::new(ErrorStatus::UnexpectedAlertOpen, "A user prompt is shown"),
+            ),
+            Ok(_) => Ok(()),
+            Err(ref error) if error.error == ErrorStatus::NoSuchAlert => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
     // https://w3c.github.io/webdriver/#dismiss-alert
     fn handle_dismiss_alert(&mut self) -> WebDriverResult<WebDriverResponse> {
-        // Since user prompts are not yet implement this will always succeed
+        self.user_prompt_command(WebDriverUserPromptAction::Dismiss)?;
+        Ok(WebDriverResponse::Void)
+    }
+
+    // https://w3c.github.io/webdriver/#accept-alert
+    fn handle_accept_alert(&mut self) -> WebDriverResult<WebDriverResponse> {
+        self.user_prompt_command(WebDriverUserPromptAction::Accept)?;
+        Ok(WebDriverResponse::Void)
+    }
+
+    // https://w3c.github.io/webdriver/#get-alert-text
+    fn handle_get_alert_text(&self) -> WebDriverResult<WebDriverResponse> {
+        let text = self.user_prompt_command(WebDriverUserPromptAction::GetText)?;
+        Ok(WebDriverResponse::Generic(ValueResponse(
+            serde_json::to_value(text)?,
+        )))
+    }
+
+    // https://w3c.github.io/webdriver/#send-alert-text
+    fn handle_send_alert_text(
+        &self,
+        parameters: &SendKeysParameters,
+    ) -> WebDriverResult<WebDriverResponse> {
+        let action = WebDriverUserPromptAction::SendText(parameters.text.clone());
+        self.user_prompt_command(action)?;
         Ok(WebDriverResponse::Void)
     }
 
@@ -1476,6 +1882,17 @@ impl Handler {
         Ok(WebDriverResponse::Void)
     }
 
+    // https://w3c.github.io/webdriver/#element-clear
+    fn handle_element_clear(&self, element: &WebElement) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::ElementClear(element.to_string(), sender);
+        self.browsing_context_script_command(cmd)?;
+        match receiver.recv().unwrap() {
+            Ok(_) => Ok(WebDriverResponse::Void),
+            Err(error) => Err(WebDriverError::new(error, "")),
+        }
+    }
+
     // https://w3c.github.io/webdriver/#element-click
     fn handle_element_click(&mut self, element: &WebElement) -> WebDriverResult<WebDriverResponse> {
         let (sender, receiver) = ipc::channel().unwrap();
@@ -1621,6 +2038,18 @@ impl Handler {
         }
     }
 
+    fn handle_print(&self, parameters: &PrintParameters) -> WebDriverResult<WebDriverResponse> {
+        let settings = parameters.to_print_settings()?;
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.top_level_script_command(WebDriverScriptCommand::Print(settings, sender))?;
+        match receiver.recv().unwrap() {
+            Ok(pdf) => Ok(WebDriverResponse::Generic(ValueResponse(
+                serde_json::to_value(base64::encode(&pdf))?,
+            ))),
+            Err(error) => Err(WebDriverError::new(error, "")),
+        }
+    }
+
     fn handle_get_prefs(
         &self,
         parameters: &GetPrefsParameters,
@@ -1715,6 +2144,17 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
             WebDriverCommand::GetTitle => self.handle_title(),
             WebDriverCommand::GetWindowHandle => self.handle_window_handle(),
             WebDriverCommand::GetWindowHandles => self.handle_window_handles(),
+            WebDriverCommand::NewWindow(ref parameters) => self.handle_new_window(parameters),
+            WebDriverCommand::CloseWindow => self.handle_close_window(),
+            WebDriverCommand::MaximizeWindow => {
+                self.handle_set_window_state(WebDriverWindowState::Maximized)
+            },
+            WebDriverCommand::MinimizeWindow => {
+                self.handle_set_window_state(WebDriverWindowState::Minimized)
+            },
+            WebDriverCommand::FullscreenWindow => {
+                self.handle_set_window_state(WebDriverWindowState::Fullscreen)
+            },
             WebDriverCommand::SwitchToFrame(ref parameters) => {
                 self.handle_switch_to_frame(parameters)
             },
@@ -1756,7 +2196,14 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
                 self.handle_element_send_keys(element, keys)
             },
             WebDriverCommand::ElementClick(ref element) => self.handle_element_click(element),
+            WebDriverCommand::ElementClear(ref element) => self.handle_element_clear(element),
             WebDriverCommand::DismissAlert => self.handle_dismiss_alert(),
+            WebDriverCommand::AcceptAlert => self.handle_accept_alert(),
+            WebDriverCommand::GetAlertText => self.handle_get_alert_text(),
+            WebDriverCommand::SendAlertText(ref parameters) => {
+                self.handle_send_alert_text(parameters)
+            },
+            WebDriverCommand::DeleteCookie(ref name) => self.handle_delete_cookie(name),
             WebDriverCommand::DeleteCookies => self.handle_delete_cookies(),
             WebDriverCommand::GetTimeouts => self.handle_get_timeouts(),
             WebDriverCommand::SetTimeouts(ref x) => self.handle_set_timeouts(x),
@@ -1768,6 +2215,7 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
                 ServoExtensionCommand::GetPrefs(ref x) => self.handle_get_prefs(x),
                 ServoExtensionCommand::SetPrefs(ref x) => self.handle_set_prefs(x),
                 ServoExtensionCommand::ResetPrefs(ref x) => self.handle_reset_prefs(x),
+                ServoExtensionCommand::Print(ref x) => self.handle_print(x),
             },
             _ => Err(WebDriverError::new(
                 ErrorStatus::UnsupportedOperation,
@@ -1777,6 +2225,6 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
     }
 
     fn delete_session(&mut self, _session: &Option<Session>) {
-        self.session = None;
+        self.end_session();
     }
 }
@@ -0,0 +1,343 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use compositing::ConstellationMsg;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use devtools_traits::{ConsoleMessage, DevtoolsControlMsg, DevtoolsPageInfo, LogLevel};
+use devtools_traits::{NavigationState, ScriptToDevtoolsControlMsg};
+use msg::constellation_msg::{BrowsingContextId, PipelineId, TopLevelBrowsingContextId};
+use msg::constellation_msg::{PipelineNamespace, PipelineNamespaceId};
+use script_traits::webdriver_msg::{LoadStatus, WebDriverJSValue, WebDriverScriptCommand};
+use script_traits::WebDriverCommandMsg;
+use serde_json::{json, Value};
+use servo_url::ServoUrl;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::thread;
+use std::time::Duration;
+use ws::{CloseCode, Handler, Handshake};
+
+/// A constellation with a single top-level browsing context, answering the
+/// commands of the WebDriver BiDi server.
+fn fake_constellation(
+    top_level_browsing_context_id: TopLevelBrowsingContextId,
+) -> Sender<ConstellationMsg> {
+    let (sender, receiver) = unbounded();
+    thread::spawn(move || {
+        while let Ok(ConstellationMsg::WebDriverCommand(command)) = receiver.recv() {
+            match command {
+                WebDriverCommandMsg::GetTopLevelBrowsingContexts(reply) => {
+                    reply.send(vec![top_level_browsing_context_id]).unwrap();
+                },
+                WebDriverCommandMsg::LoadUrl(_, _, reply) => {
+                    reply.send(LoadStatus::LoadComplete).unwrap();
+                },
+                WebDriverCommandMsg::ScriptCommand(
+                    _,
+                    WebDriverScriptCommand::ExecuteScript(script, reply),
+                ) => {
+                    // Pretend to evaluate the expressions the tests use.
+                    let completion = if script.contains("\"1 + 2\"") {
+                        vec![
+                            WebDriverJSValue::Boolean(true),
+                            WebDriverJSValue::Number(3.),
+                        ]
+                    } else {
+                        vec![
+                            WebDriverJSValue::Boolean(false),
+                            WebDriverJSValue::String("ReferenceError".to_owned()),
+                        ]
+                    };
+                    reply
+                        .send(Ok(WebDriverJSValue::ArrayLike(completion)))
+                        .unwrap();
+                },
+                _ => {},
+            }
+        }
+    });
+    sender
+}
+
+struct Browser {
+    devtools_chan: Sender<DevtoolsControlMsg>,
+    address: SocketAddr,
+    browsing_context_id: BrowsingContextId,
+    pipeline_id: PipelineId,
+}
+
+impl Browser {
+    fn start() -> Browser {
+        PipelineNamespace::install(PipelineNamespaceId(1));
+        let top_level_browsing_context_id = TopLevelBrowsingContextId::new();
+        let (devtools_chan, events) = webdriver_server::tap_devtools_messages(None);
+        let constellation_chan = fake_constellation(top_level_browsing_context_id);
+        let address = webdriver_server::start_bidi_server(0, constellation_chan, events);
+        Browser {
+            devtools_chan,
+            address,
+            browsing_context_id: BrowsingContextId::from(top_level_browsing_context_id),
+            pipeline_id: PipelineId::new(),
+        }
+    }
+
+    fn send_to_devtools(&self, message: ScriptToDevtoolsControlMsg) {
+        self.devtools_chan
+            .send(DevtoolsControlMsg::FromScript(message))
+            .unwrap();
+    }
+
+    fn load_document(&self, url: &str) {
+        let (sender, _receiver) = ipc_channel::ipc::channel().unwrap();
+        let info = DevtoolsPageInfo {
+            title: String::new(),
+            url: ServoUrl::parse(url).unwrap(),
+        };
+        self.send_to_devtools(ScriptToDevtoolsControlMsg::NewGlobal(
+            (self.browsing_context_id, self.pipeline_id, None),
+            sender,
+            info,
+        ));
+    }
+}
+
+struct ClientHandler {
+    out: ws::Sender,
+    opened: Sender<ws::Sender>,
+    messages: Sender<Value>,
+}
+
+impl Handler for ClientHandler {
+    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
+        self.opened.send(self.out.clone()).unwrap();
+        Ok(())
+    }
+
+    fn on_message(&mut self, message: ws::Message) -> ws::Result<()> {
+        let message = serde_json::from_str(&message.into_text()?).unwrap();
+        self.messages.send(message).unwrap();
+        Ok(())
+    }
+
+    fn on_close(&mut self, _: CloseCode, _: &str) {}
+}
+
+struct Client {
+    out: ws::Sender,
+    messages: Receiver<Value>,
+    next_id: RefCell<u64>,
+    events: RefCell<VecDeque<Value>>,
+}
+
+impl Client {
+    fn connect(address: SocketAddr) -> Client {
+        let (opened, opened_receiver) = unbounded();
+        let (messages_sender, messages) = unbounded();
+        thread::spawn(move || {
+            ws::connect(format!("ws://{}", address), move |out| ClientHandler {
+                out,
+                opened: opened.clone(),
+                messages: messages_sender.clone(),
+            })
+            .unwrap();
+        });
+        Client {
+            out: opened_receiver.recv().unwrap(),
+            messages,
+            next_id: RefCell::new(0),
+            events: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    fn next_message(&self) -> Value {
+        self.messages
+            .recv_timeout(Duration::from_secs(5))
+            .expect("No message from the WebDriver BiDi server")
+    }
+
+    /// Send a command and return its response, keeping the events received
+    /// in the meantime.
+    fn command(&self, method: &str, params: Value) -> Value {
+        *self.next_id.borrow_mut() += 1;
+        let id = *self.next_id.borrow();
+        let command = json!({ "id": id, "method": method, "params": params });
+        self.out.send(command.to_string()).unwrap();
+        loop {
+            let message = self.next_message();
+            if message["type"] == "event" {
+                self.events.borrow_mut().push_back(message);
+            } else {
+                assert_eq!(message["id"], id);
+                return message;
+            }
+        }
+    }
+
+    fn result(&self, method: &str, params: Value) -> Value {
+        let response = self.command(method, params);
+        assert_eq!(response["type"], "success", "{}", response);
+        response["result"].clone()
+    }
+
+    fn event(&self) -> Value {
+        let event = match self.events.borrow_mut().pop_front() {
+            Some(event) => event,
+            None => self.next_message(),
+        };
+        assert_eq!(event["type"], "event", "{}", event);
+        event
+    }
+
+    fn start_session(&self) {
+        self.result("session.new", json!({ "capabilities": {} }));
+    }
+}
+
+#[test]
+fn test_commands_require_a_session() {
+    let browser = Browser::start();
+    let client = Client::connect(browser.address);
+
+    let response = client.command("browsingContext.getTree", json!({}));
+    assert_eq!(response["type"], "error");
+    assert_eq!(response["error"], "invalid session id");
+}
+
+#[test]
+fn test_only_one_session_at_a_time() {
+    let browser = Browser::start();
+    let client = Client::connect(browser.address);
+    assert_eq!(client.result("session.status", json!({}))["ready"], true);
+
+    client.start_session();
+    assert_eq!(client.result("session.status", json!({}))["ready"], false);
+
+    let other_client = Client::connect(browser.address);
+    let response = other_client.command("session.new", json!({ "capabilities": {} }));
+    assert_eq!(response["error"], "session not created");
+
+    client.result("session.end", json!({}));
+    other_client.start_session();
+}
+
+#[test]
+fn test_unknown_commands_and_events() {
+    let browser = Browser::start();
+    let client = Client::connect(browser.address);
+    client.start_session();
+
+    let response = client.command("browsingContext.print", json!({}));
+    assert_eq!(response["error"], "unknown command");
+
+    let response = client.command("session.subscribe", json!({ "events": ["log.unknown"] }));
+    assert_eq!(response["error"], "invalid argument");
+}
+
+#[test]
+fn test_browsing_context_events() {
+    let browser = Browser::start();
+    let client = Client::connect(browser.address);
+    client.start_session();
+    client.result(
+        "session.subscribe",
+        json!({ "events": ["browsingContext"] }),
+    );
+
+    browser.load_document("http://example.com/");
+    let event = client.event();
+    assert_eq!(event["method"], "browsingContext.contextCreated");
+    assert_eq!(event["params"]["url"], "http://example.com/");
+    let context = event["params"]["context"].clone();
+
+    let tree = client.result("browsingContext.getTree", json!({}));
+    assert_eq!(tree["contexts"][0]["context"], context);
+    assert_eq!(tree["contexts"][0]["url"], "http://example.com/");
+
+    let result = client.result(
+        "browsingContext.navigate",
+        json!({ "context": context, "url": "http://example.org/", "wait": "complete" }),
+    );
+    assert_eq!(result["url"], "http://example.org/");
+
+    let url = ServoUrl::parse("http://example.org/").unwrap();
+    browser.send_to_devtools(ScriptToDevtoolsControlMsg::Navigate(
+        browser.browsing_context_id,
+        NavigationState::Start(url),
+    ));
+    let event = client.event();
+    assert_eq!(event["method"], "browsingContext.navigationStarted");
+    assert_eq!(event["params"]["context"], context);
+    assert_eq!(event["params"]["url"], "http://example.org/");
+}
+
+#[test]
+fn test_log_entry_added() {
+    let browser = Browser::start();
+    let client = Client::connect(browser.address);
+    client.start_session();
+    browser.load_document("http://example.com/");
+
+    // Only the subscribed events are received.
+    client.result("session.subscribe", json!({ "events": ["log.entryAdded"] }));
+    browser.send_to_devtools(ScriptToDevtoolsControlMsg::ConsoleAPI(
+        browser.pipeline_id,
+        ConsoleMessage {
+            message: "hello".to_owned(),
+            logLevel: LogLevel::Warn,
+            filename: "http://example.com/".to_owned(),
+            lineNumber: 1,
+            columnNumber: 2,
+        },
+        None,
+    ));
+    let event = client.event();
+    assert_eq!(event["method"], "log.entryAdded");
+    assert_eq!(event["params"]["type"], "console");
+    assert_eq!(event["params"]["level"], "warn");
+    assert_eq!(event["params"]["method"], "warn");
+    assert_eq!(event["params"]["text"], "hello");
+    let context =
+        client.result("browsingContext.getTree", json!({}))["contexts"][0]["context"].clone();
+    assert_eq!(event["params"]["source"]["context"], context);
+
+    client.result(
+        "session.unsubscribe",
+        json!({ "events": ["log.entryAdded"] }),
+    );
+    let response = client.command(
+        "session.unsubscribe",
+        json!({ "events": ["log.entryAdded"] }),
+    );
+    assert_eq!(response["error"], "invalid argument");
+}
+
+#[test]
+fn test_script_evaluate() {
+    let browser = Browser::start();
+    let client = Client::connect(browser.address);
+    client.start_session();
+    let context =
+        client.result("browsingContext.getTree", json!({}))["contexts"][0]["context"].clone();
+
+    let result = client.result(
+        "script.evaluate",
+        json!({ "expression": "1 + 2", "target": { "context": context }, "awaitPromise": false }),
+    );
+    assert_eq!(result["type"], "success");
+    assert_eq!(result["result"], json!({ "type": "number", "value": 3. }));
+
+    let result = client.result(
+        "script.evaluate",
+        json!({ "expression": "missing", "target": { "context": context }, "awaitPromise": false }),
+    );
+    assert_eq!(result["type"], "exception");
+    assert_eq!(result["exceptionDetails"]["text"], "ReferenceError");
+
+    let response = client.command(
+        "script.evaluate",
+        json!({ "expression": "1 + 2", "target": { "context": "missing" } }),
+    );
+    assert_eq!(response["error"], "no such frame");
+}
@@ -8,8 +8,8 @@ use euclid::{Point2D, Vector2D};
 use keyboard_types::{Key, KeyboardEvent, Modifiers, ShortcutMatcher};
 use servo::compositing::windowing::{WebRenderDebugOption, WindowEvent};
 use servo::embedder_traits::{
-    ContextMenuResult, EmbedderMsg, FilterPattern, PermissionRequest, PromptDefinition, PromptOrigin, PromptResult,
-    PermissionPrompt,
+    ContextMenuResult, EmbedderMsg, FilterPattern, PermissionRequest, PromptCredentialsInput,
+    PromptDefinition, PromptOrigin, PromptResult, PermissionPrompt,
 };
 use servo::msg::constellation_msg::TopLevelBrowsingContextId as BrowserId;
 use servo::msg::constellation_msg::TraversalDirection;
@@ -317,6 +317,9 @@ where
                             PromptDefinition::Input(_message, default, sender) => {
                                 sender.send(Some(default.to_owned()))
                             }
+                            PromptDefinition::Credentials(sender) => {
+                                sender.send(PromptCredentialsInput::default())
+                            }
                         }
                     } else {
                         thread::Builder::new()
@@ -366,6 +369,20 @@ where
                                         let result = tinyfiledialogs::input_box("", &message, &default);
                                         sender.send(result)
                                     }
+                                    PromptDefinition::Credentials(sender) => {
+                                        let username = tinyfiledialogs::input_box(
+                                            "Authentication required",
+                                            "Username:",
+                                            "",
+                                        );
+                                        let password = username.as_ref().and_then(|_| {
+                                            tinyfiledialogs::password_box(
+                                                "Authentication required",
+                                                "Password:",
+                                            )
+                                        });
+                                        sender.send(PromptCredentialsInput { username, password })
+                                    }
                                 }
                             })
                             .unwrap()
@@ -452,6 +469,14 @@ where
                 EmbedderMsg::SetFullscreenState(state) => {
                     self.window.set_fullscreen(state);
                 },
+                EmbedderMsg::MaximizeWindow => {
+                    self.window.set_fullscreen(false);
+                    self.window.set_maximized(true);
+                },
+                EmbedderMsg::MinimizeWindow => {
+                    self.window.set_fullscreen(false);
+                    self.window.set_minimized(true);
+                },
                 EmbedderMsg::LoadStart => {
                     self.loading_state = Some(LoadingState::Connecting);
                 },
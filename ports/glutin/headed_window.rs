@@ -328,6 +328,10 @@ impl WindowPortsMethods for Window {
         return self.fullscreen.get();
     }
 
+    fn set_maximized(&self, state: bool) {
+        self.winit_window.set_maximized(state);
+    }
+
     fn set_cursor(&self, cursor: Cursor) {
         use winit::MouseCursor;
 
@@ -26,6 +26,8 @@ pub trait WindowPortsMethods: WindowMethods {
     fn set_inner_size(&self, _size: DeviceIntSize) {}
     fn set_position(&self, _point: DeviceIntPoint) {}
     fn set_fullscreen(&self, _state: bool) {}
+    fn set_maximized(&self, _state: bool) {}
+    fn set_minimized(&self, _state: bool) {}
     fn set_cursor(&self, _cursor: Cursor) {}
     fn new_glwindow(&self, events_loop: &EventsLoop) -> Box<dyn webxr::glwindow::GlWindow>;
 }
@@ -36,6 +36,7 @@ fn filename(file: Resource) -> &'static str {
         Resource::RippyPNG => "rippy.png",
         Resource::MediaControlsCSS => "media-controls.css",
         Resource::MediaControlsJS => "media-controls.js",
+        Resource::DebuggerJS => "debugger.js",
     }
 }
 
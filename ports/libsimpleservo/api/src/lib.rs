@@ -21,7 +21,8 @@ use servo::compositing::windowing::{
 };
 use servo::embedder_traits::resources::{self, Resource, ResourceReaderMethods};
 use servo::embedder_traits::{
-    EmbedderMsg, EmbedderProxy, MediaSessionEvent, PromptDefinition, PromptOrigin,
+    EmbedderMsg, EmbedderProxy, MediaSessionEvent, PromptCredentialsInput, PromptDefinition,
+    PromptOrigin,
 };
 use servo::euclid::{Point2D, Rect, Scale, Size2D, Vector2D};
 use servo::keyboard_types::{Key, KeyState, KeyboardEvent};
@@ -618,6 +619,10 @@ impl ServoGlue {
                         PromptDefinition::Input(message, default, sender) => {
                             sender.send(cb.prompt_input(message, default, trusted))
                         },
+                        // TODO: add a host callback to ask for credentials.
+                        PromptDefinition::Credentials(sender) => {
+                            sender.send(PromptCredentialsInput::default())
+                        },
                     };
                     if let Err(e) = res {
                         let reason = format!("Failed to send Prompt response: {}", e);
@@ -724,6 +729,8 @@ impl ServoGlue {
                 EmbedderMsg::NewFavicon(..) |
                 EmbedderMsg::HeadParsed |
                 EmbedderMsg::SetFullscreenState(..) |
+                EmbedderMsg::MaximizeWindow |
+                EmbedderMsg::MinimizeWindow |
                 EmbedderMsg::Panic(..) |
                 EmbedderMsg::ReportProfile(..) => {},
             }
@@ -943,6 +950,7 @@ impl ResourceReaderMethods for ResourceReaderInstance {
             Resource::MediaControlsJS => {
                 &include_bytes!("../../../../resources/media-controls.js")[..]
             },
+            Resource::DebuggerJS => &include_bytes!("../../../../resources/debugger.js")[..],
         })
     }
 
@@ -252,6 +252,8 @@ class MachCommands(CommandBase):
 
         self_contained_tests = [
             "background_hang_monitor",
+            "debugger",
+            "devtools",
             "gfx",
             "layout_2013",
             "msg",
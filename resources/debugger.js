@@ -0,0 +1,362 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// The script of the debugger global of a script thread. It drives SpiderMonkey's
+// Debugger API on behalf of the devtools server: the functions declared at the top
+// level are called from DebuggerGlobalScope, which in turn exposes notifyNewSource(),
+// pause() and notifySample() to report back.
+
+"use strict";
+
+const dbg = new Debugger();
+
+// Debuggee globals, and their ids as known to DebuggerGlobalScope.
+const debuggees = new Map();
+const debuggeeIds = new Map();
+
+// Breakpoints of each debuggee, keyed by location.
+const breakpoints = new Map();
+
+// Debuggees that should pause as soon as they run any script.
+const interrupts = new Set();
+
+const sourceIds = new WeakMap();
+let nextSourceId = 1;
+
+// The youngest frame of the paused debuggee, if any.
+let pausedFrame = null;
+
+// Frames with stepping hooks installed by the last resume.
+let steppingFrames = [];
+
+// The hook run on entering a frame for interrupts and stepping, if any.
+let enterFrameHook;
+
+// Debuggees being sampled, with the minimum time between samples and when the next
+// one is due, in milliseconds.
+const samplers = new Map();
+
+function addDebuggee(id, global) {
+  const debuggee = dbg.addDebuggee(global);
+  debuggees.set(id, debuggee);
+  debuggeeIds.set(debuggee, id);
+  breakpoints.set(id, new Map());
+}
+
+function removeDebuggee(id) {
+  const debuggee = debuggees.get(id);
+  if (!debuggee) {
+    return;
+  }
+  for (const breakpoint of breakpoints.get(id).values()) {
+    dbg.clearBreakpoint(breakpoint);
+  }
+  dbg.removeDebuggee(debuggee);
+  debuggees.delete(id);
+  debuggeeIds.delete(debuggee);
+  breakpoints.delete(id);
+  interrupts.delete(id);
+  stopSampling(id);
+}
+
+function registerSource(debuggeeId, source) {
+  let id = sourceIds.get(source);
+  if (id === undefined) {
+    id = nextSourceId++;
+    sourceIds.set(source, id);
+    notifyNewSource(debuggeeId, {
+      id,
+      url: source.url || "",
+      introductionType: source.introductionType || undefined,
+      text: source.text,
+    });
+  }
+  return id;
+}
+
+function breakpointOffsets(script, breakpoint) {
+  if (breakpoint.column) {
+    const offsets = script.getAllColumnOffsets()
+      .filter(entry => entry.lineNumber === breakpoint.line &&
+                       entry.columnNumber === breakpoint.column)
+      .map(entry => entry.offset);
+    if (offsets.length) {
+      return offsets;
+    }
+  }
+  return script.getLineOffsets(breakpoint.line);
+}
+
+function setBreakpointInScript(script, breakpoint) {
+  for (const offset of breakpointOffsets(script, breakpoint)) {
+    script.setBreakpoint(offset, breakpoint);
+  }
+}
+
+function setBreakpointInScriptTree(script, breakpoint) {
+  setBreakpointInScript(script, breakpoint);
+  for (const child of script.getChildScripts()) {
+    setBreakpointInScriptTree(child, breakpoint);
+  }
+}
+
+function setBreakpoint(id, url, line, column) {
+  const debuggeeBreakpoints = breakpoints.get(id);
+  const key = `${url}:${line}:${column}`;
+  if (!debuggeeBreakpoints || debuggeeBreakpoints.has(key)) {
+    return;
+  }
+  const breakpoint = {
+    url,
+    line,
+    column,
+    hit: frame => pauseAt(frame, "breakpoint"),
+  };
+  debuggeeBreakpoints.set(key, breakpoint);
+  for (const script of dbg.findScripts({ global: debuggees.get(id), url, line })) {
+    setBreakpointInScript(script, breakpoint);
+  }
+}
+
+function removeBreakpoint(id, url, line, column) {
+  const debuggeeBreakpoints = breakpoints.get(id);
+  const key = `${url}:${line}:${column}`;
+  if (!debuggeeBreakpoints || !debuggeeBreakpoints.has(key)) {
+    return;
+  }
+  dbg.clearBreakpoint(debuggeeBreakpoints.get(key));
+  debuggeeBreakpoints.delete(key);
+}
+
+function setEnterFrameHook(hook) {
+  enterFrameHook = hook;
+  dbg.onEnterFrame = (hook || samplers.size) ? onEnterFrame : undefined;
+}
+
+// Samples are only taken when entering frames, which is all the Debugger API allows
+// without slowing every step down.
+function onEnterFrame(frame) {
+  if (frame.script && samplers.size) {
+    sample(frame);
+  }
+  return enterFrameHook ? enterFrameHook(frame) : undefined;
+}
+
+function interrupt(id) {
+  interrupts.add(id);
+  if (!steppingFrames.length) {
+    setEnterFrameHook(onInterrupt);
+  }
+}
+
+function onInterrupt(frame) {
+  if (!frame.script || !interrupts.delete(debuggeeIds.get(frame.script.global))) {
+    return undefined;
+  }
+  return pauseAt(frame, "interrupted");
+}
+
+function describeValue(value) {
+  if (value === undefined) {
+    return { type: "undefined" };
+  }
+  if (value === null) {
+    return { type: "null" };
+  }
+  switch (typeof value) {
+    case "boolean":
+      return { type: "boolean", booleanValue: value };
+    case "number":
+      return { type: "number", numberValue: value };
+    case "string":
+      return { type: "string", stringValue: value };
+    case "object":
+      if (value instanceof Debugger.Object) {
+        return { type: "object", stringValue: value.class };
+      }
+      // One of the sentinels for optimized out or uninitialized bindings.
+      return { type: "undefined" };
+    default:
+      return { type: "string", stringValue: String(value) };
+  }
+}
+
+function describeEnvironments(environment) {
+  const environments = [];
+  // The global scope is left out, since it is the whole debuggee global.
+  for (let env = environment; env && env.parent; env = env.parent) {
+    let type = env.type;
+    if (type === "declarative") {
+      type = env.callee ? "function" : "block";
+    }
+    const bindings = [];
+    for (const name of env.names()) {
+      let value;
+      try {
+        value = env.getVariable(name);
+      } catch (e) {
+        value = undefined;
+      }
+      bindings.push({ name, value: describeValue(value) });
+    }
+    environments.push({
+      type,
+      functionName: env.callee ? (env.callee.displayName || undefined) : undefined,
+      bindings,
+    });
+  }
+  return environments;
+}
+
+function describeFrame(frame) {
+  const script = frame.script;
+  const location = script.getOffsetLocation(frame.offset);
+  return {
+    displayName: frame.callee ? (frame.callee.displayName || undefined) : undefined,
+    type: frame.type,
+    sourceId: registerSource(debuggeeIds.get(script.global), script.source),
+    line: location.lineNumber,
+    column: location.columnNumber,
+    environments: describeEnvironments(frame.environment),
+  };
+}
+
+function clearStepping() {
+  for (const frame of steppingFrames) {
+    if (frame.live) {
+      frame.onStep = undefined;
+      frame.onPop = undefined;
+    }
+  }
+  steppingFrames = [];
+  setEnterFrameHook(interrupts.size ? onInterrupt : undefined);
+}
+
+function startStepping(startFrame, limit) {
+  const startLine = startFrame.script.getOffsetLocation(startFrame.offset).lineNumber;
+
+  // Pause on the first statement that isn't on the line we were paused on.
+  function onStep() {
+    const location = this.script.getOffsetLocation(this.offset);
+    if (!location.isEntryPoint || (this === startFrame && location.lineNumber === startLine)) {
+      return undefined;
+    }
+    return pauseAt(this, "resumeLimit");
+  }
+
+  // Pause in the caller as soon as the frame returns.
+  function onPop() {
+    const caller = this.older;
+    if (caller && caller.script) {
+      watch(caller);
+    }
+    return undefined;
+  }
+
+  function watch(frame) {
+    frame.onStep = onStep;
+    frame.onPop = onPop;
+    steppingFrames.push(frame);
+  }
+
+  if (limit === "finish") {
+    startFrame.onPop = onPop;
+    steppingFrames.push(startFrame);
+  } else {
+    watch(startFrame);
+  }
+  if (limit === "step") {
+    setEnterFrameHook(watch);
+  }
+}
+
+function pauseAt(frame, reason) {
+  clearStepping();
+  const frames = [];
+  for (let older = frame; older; older = older.older) {
+    if (older.script) {
+      frames.push(describeFrame(older));
+    }
+  }
+  pausedFrame = frame;
+  let limit;
+  try {
+    limit = pause(debuggeeIds.get(frame.script.global), { reason, frames });
+  } finally {
+    pausedFrame = null;
+  }
+  if (limit) {
+    startStepping(frame, limit);
+  }
+  return undefined;
+}
+
+// Evaluate code in the paused frame of the given debuggee, or in its global when it
+// isn't paused.
+function evaluate(id, text) {
+  const debuggee = debuggees.get(id);
+  if (!debuggee) {
+    return { type: "undefined" };
+  }
+  const completion = pausedFrame && pausedFrame.script.global === debuggee ?
+    pausedFrame.eval(text) :
+    debuggee.executeInGlobal(text);
+  if (!completion) {
+    return { type: "undefined" };
+  }
+  return describeValue("return" in completion ? completion.return : completion.throw);
+}
+
+function startSampling(id, interval) {
+  if (!debuggees.has(id)) {
+    return;
+  }
+  samplers.set(id, { interval, next: 0 });
+  setEnterFrameHook(enterFrameHook);
+}
+
+function stopSampling(id) {
+  samplers.delete(id);
+  setEnterFrameHook(enterFrameHook);
+}
+
+function sample(youngest) {
+  const id = debuggeeIds.get(youngest.script.global);
+  const sampler = samplers.get(id);
+  const now = Date.now();
+  if (!sampler || now < sampler.next) {
+    return;
+  }
+  sampler.next = now + sampler.interval;
+  const frames = [];
+  for (let frame = youngest; frame; frame = frame.older) {
+    if (frame.script) {
+      const location = frame.script.getOffsetLocation(frame.offset);
+      frames.push({
+        functionName: frame.callee ? (frame.callee.displayName || undefined) : undefined,
+        url: frame.script.url || "",
+        line: location.lineNumber,
+        column: location.columnNumber,
+      });
+    }
+  }
+  if (!notifySample(id, { time: now, frames })) {
+    stopSampling(id);
+  }
+}
+
+dbg.onNewScript = function(script) {
+  const id = debuggeeIds.get(script.global);
+  if (id === undefined) {
+    return;
+  }
+  registerSource(id, script.source);
+  for (const breakpoint of breakpoints.get(id).values()) {
+    if (breakpoint.url === script.url) {
+      setBreakpointInScriptTree(script, breakpoint);
+    }
+  }
+};
+
+dbg.onDebuggerStatement = frame => pauseAt(frame, "debuggerStatement");
@@ -4,4 +4,5 @@
 
 #![cfg(test)]
 
+mod mem;
 mod time;
@@ -0,0 +1,55 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use profile::mem;
+use profile_traits::mem::{ProfilerMsg, Report, ReportKind, Reporter, ReporterRequest};
+
+#[test]
+fn mem_profiler_report_test() {
+    let chan = mem::Profiler::create(None);
+
+    let (reporter_sender, reporter_receiver) = ipc::channel().unwrap();
+    ROUTER.add_route(
+        reporter_receiver.to_opaque(),
+        Box::new(|message| {
+            let request: ReporterRequest = message.to().unwrap();
+            request.reports_channel.send(vec![
+                Report {
+                    path: vec!["test".to_owned(), "heap".to_owned()],
+                    kind: ReportKind::ExplicitJemallocHeapSize,
+                    size: 42,
+                },
+                Report {
+                    path: vec!["test-total".to_owned()],
+                    kind: ReportKind::NonExplicitSize,
+                    size: 7,
+                },
+            ]);
+        }),
+    );
+    chan.send(ProfilerMsg::RegisterReporter(
+        "test".to_owned(),
+        Reporter(reporter_sender),
+    ));
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    chan.send(ProfilerMsg::Report(sender));
+    let reports = receiver.recv().unwrap();
+
+    // Explicit allocations are reported under "explicit".
+    let heap = reports
+        .iter()
+        .find(|report| report.path == ["explicit", "test", "heap"])
+        .expect("The explicit report is missing");
+    assert_eq!(heap.size, 42);
+    let total = reports
+        .iter()
+        .find(|report| report.path == ["test-total"])
+        .expect("The non-explicit report is missing");
+    assert_eq!(total.size, 7);
+
+    chan.send(ProfilerMsg::Exit);
+}
@@ -5,8 +5,11 @@
 use ipc_channel::ipc;
 use profile::time;
 use profile_traits::ipc as ProfiledIpc;
-use profile_traits::time::{ProfilerCategory, ProfilerData, ProfilerMsg};
+use profile_traits::time::{ProfilerCategory, ProfilerData, ProfilerMsg, ProfilerThread};
+use profile_traits::time::{TimerMetadata, TimerMetadataFrameType, TimerMetadataReflowType};
+use serde_json::Value;
 use servo_config::opts::OutputOptions;
+use std::fs::{self, File};
 use std::thread;
 use std::time::Duration;
 
@@ -93,6 +96,162 @@ fn bytes_channel_profiler_test() {
     };
 }
 
+#[test]
+fn trace_events_profiler_test() {
+    let path = std::env::temp_dir().join("servo-trace-events-profiler-test.json");
+    let output = OutputOptions::TraceEvents(path.to_string_lossy().into_owned());
+    let chan = time::Profiler::create(&Some(output), None, false);
+
+    let metadata = TimerMetadata {
+        url: "http://example.com/".to_owned(),
+        iframe: TimerMetadataFrameType::RootWindow,
+        incremental: TimerMetadataReflowType::FirstReflow,
+    };
+    let profiled_thread = |tid: u64, name: &str| ProfilerThread {
+        pid: 1,
+        tid: tid,
+        name: Some(name.to_owned()),
+    };
+    let events = vec![
+        (
+            ProfilerCategory::Compositing,
+            None,
+            (5000, 6000),
+            profiled_thread(3, "Compositor"),
+        ),
+        (
+            ProfilerCategory::LayoutPerform,
+            Some(metadata.clone()),
+            (2000, 3000),
+            profiled_thread(2, "Layout"),
+        ),
+        (
+            ProfilerCategory::ScriptEvent,
+            Some(metadata),
+            (1000, 4000),
+            profiled_thread(1, "Script"),
+        ),
+    ];
+    for (category, metadata, time, thread) in events {
+        chan.send(ProfilerMsg::Time(
+            (category, metadata),
+            time,
+            (0, 0),
+            thread,
+        ));
+    }
+    let (sender, receiver) = ipc::channel().unwrap();
+    chan.send(ProfilerMsg::Exit(sender));
+    receiver.recv().unwrap();
+
+    let trace: Value = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+    let of_phase = |ph: &str| -> Vec<&Value> { events.iter().filter(|e| e["ph"] == ph).collect() };
+
+    let thread_names: Vec<_> = of_phase("M")
+        .iter()
+        .map(|e| {
+            (
+                e["tid"].as_u64().unwrap(),
+                e["args"]["name"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        thread_names,
+        vec![(1, "Script"), (2, "Layout"), (3, "Compositor")]
+    );
+
+    let layout = of_phase("X")
+        .into_iter()
+        .find(|e| e["name"] == "Layout")
+        .unwrap();
+    assert_eq!(layout["cat"], "layout");
+    assert_eq!(layout["ts"], 2.0);
+    assert_eq!(layout["dur"], 1.0);
+    assert_eq!(layout["tid"], 2);
+    assert_eq!(layout["args"]["url"], "http://example.com/");
+
+    // Script flows into layout, which flows into the next composite.
+    let flow_ends = |ph: &str| -> Vec<(u64, u64)> {
+        of_phase(ph)
+            .iter()
+            .map(|e| (e["id"].as_u64().unwrap(), e["tid"].as_u64().unwrap()))
+            .collect()
+    };
+    assert_eq!(flow_ends("s"), vec![(0, 1), (1, 2)]);
+    assert_eq!(flow_ends("f"), vec![(0, 2), (1, 3)]);
+}
+
+#[test]
+fn trace_events_profiler_flushes_old_events_test() {
+    let path = std::env::temp_dir().join("servo-trace-events-flush-test.json");
+    let output = OutputOptions::TraceEvents(path.to_string_lossy().into_owned());
+    let chan = time::Profiler::create(&Some(output), None, false);
+
+    let metadata = TimerMetadata {
+        url: "http://example.com/".to_owned(),
+        iframe: TimerMetadataFrameType::RootWindow,
+        incremental: TimerMetadataReflowType::Incremental,
+    };
+    let profiled_thread = |tid: u64| ProfilerThread {
+        pid: 1,
+        tid: tid,
+        name: None,
+    };
+    // Two reflows, far enough apart for the first one to be written out before the second.
+    let second = 5_000_000_000;
+    let mut events = vec![];
+    for &offset in &[0, second] {
+        events.push((
+            ProfilerCategory::LayoutPerform,
+            Some(metadata.clone()),
+            (offset + 2000, offset + 3000),
+            profiled_thread(2),
+        ));
+        events.push((
+            ProfilerCategory::ScriptEvent,
+            Some(metadata.clone()),
+            (offset + 1000, offset + 4000),
+            profiled_thread(1),
+        ));
+        events.push((
+            ProfilerCategory::Compositing,
+            None,
+            (offset + 5000, offset + 6000),
+            profiled_thread(3),
+        ));
+    }
+    for (category, metadata, time, thread) in events {
+        chan.send(ProfilerMsg::Time(
+            (category, metadata),
+            time,
+            (0, 0),
+            thread,
+        ));
+    }
+    let (sender, receiver) = ipc::channel().unwrap();
+    chan.send(ProfilerMsg::Exit(sender));
+    receiver.recv().unwrap();
+
+    let trace: Value = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+    let of_phase = |ph: &str| -> Vec<&Value> { events.iter().filter(|e| e["ph"] == ph).collect() };
+
+    assert_eq!(of_phase("X").len(), 6);
+    assert_eq!(of_phase("M").len(), 3);
+    let flow_starts: Vec<_> = of_phase("s")
+        .iter()
+        .map(|e| (e["id"].as_u64().unwrap(), e["ts"].as_f64().unwrap()))
+        .collect();
+    assert_eq!(
+        flow_starts,
+        vec![(0, 1.0), (1, 2.0), (2, 5_000_001.0), (3, 5_000_002.0)]
+    );
+}
+
 #[cfg(debug_assertions)]
 #[test]
 #[should_panic]
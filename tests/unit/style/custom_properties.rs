@@ -5,12 +5,14 @@
 use cssparser::{Parser, ParserInput};
 use euclid::{Scale, Size2D};
 use servo_arc::Arc;
+use style::context::QuirksMode;
 use style::custom_properties::{
     CustomPropertiesBuilder, CustomPropertiesMap, Name, SpecifiedValue,
 };
 use style::media_queries::{Device, MediaType};
 use style::properties::{CustomDeclaration, CustomDeclarationValue};
 use style::stylesheets::Origin;
+use style::stylist::Stylist;
 use test::{self, Bencher};
 
 fn cascade(
@@ -33,7 +35,8 @@ fn cascade(
         Size2D::new(800., 600.),
         Scale::new(1.0),
     );
-    let mut builder = CustomPropertiesBuilder::new(inherited, &device);
+    let stylist = Stylist::new(device, QuirksMode::NoQuirks);
+    let mut builder = CustomPropertiesBuilder::new(inherited, &stylist);
 
     for declaration in &declarations {
         builder.cascade(declaration, Origin::Author);
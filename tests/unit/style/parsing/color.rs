@@ -0,0 +1,49 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::parsing::parse;
+use style::parser::Parse;
+use style::values::specified::Color;
+use style_traits::ToCss;
+
+#[test]
+fn test_color_functions() {
+    assert_roundtrip_with_context!(Color::parse, "lab(50% 40 -20)", "lab(50 40 -20)");
+    assert_roundtrip_with_context!(Color::parse, "lch(120 -10 400)", "lch(100 0 40)");
+    assert_roundtrip_with_context!(
+        Color::parse,
+        "oklch(0.7 100% 0.5turn / 50%)",
+        "oklch(0.7 0.4 180 / 0.5)"
+    );
+    assert_roundtrip_with_context!(Color::parse, "oklab(none 0.1 -0.1 / none)");
+    assert_roundtrip_with_context!(Color::parse, "color(display-p3 1 0 none)");
+    assert_roundtrip_with_context!(
+        Color::parse,
+        "color(xyz 0.5 0.5 0.5)",
+        "color(xyz-d65 0.5 0.5 0.5)"
+    );
+
+    assert!(parse(Color::parse, "color(lab 50 40 -20)").is_err());
+    assert!(parse(Color::parse, "lab(50 40)").is_err());
+}
+
+#[test]
+fn test_color_mix() {
+    assert_roundtrip_with_context!(Color::parse, "color-mix(in srgb, red, blue 25%)");
+    assert_roundtrip_with_context!(
+        Color::parse,
+        "color-mix(in lch longer hue, 30% red, lab(50 40 -20))",
+        "color-mix(in lch longer hue, red 30%, lab(50 40 -20))"
+    );
+    assert_roundtrip_with_context!(
+        Color::parse,
+        "color-mix(in hsl shorter hue, red, blue)",
+        "color-mix(in hsl, red, blue)"
+    );
+
+    assert!(parse(Color::parse, "color-mix(red, blue)").is_err());
+    assert!(parse(Color::parse, "color-mix(in srgb, red 0%, blue 0%)").is_err());
+    assert!(parse(Color::parse, "color-mix(in srgb, red 150%, blue)").is_err());
+    assert!(parse(Color::parse, "color-mix(in srgb longer hue, red, blue)").is_err());
+}
@@ -115,11 +115,13 @@ macro_rules! parse_longhand {
 mod background;
 mod border;
 mod box_;
+mod color;
 mod column;
 mod effects;
 mod image;
 mod inherited_text;
 mod outline;
+mod properties_and_values;
 mod selectors;
 mod supports;
 mod text_overflow;
@@ -0,0 +1,117 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::parsing::parse;
+use cssparser::{Parser, ParserInput};
+use euclid::{Scale, Size2D};
+use servo_url::ServoUrl;
+use style::context::QuirksMode;
+use style::custom_properties::VariableValue;
+use style::media_queries::{Device, MediaType};
+use style::properties_and_values::registry::{PropertyRegistration, PropertyRegistrationError};
+use style::properties_and_values::syntax::Syntax;
+use style::properties_and_values::value::SpecifiedValue;
+use style_traits::ToCss;
+
+fn parse_syntax(syntax: &str) -> Result<String, ()> {
+    syntax
+        .parse::<Syntax>()
+        .map(|syntax| syntax.to_css_string())
+}
+
+fn parse_value(syntax: &str, value: &'static str) -> Result<String, ()> {
+    let syntax = syntax.parse::<Syntax>().unwrap();
+    parse(
+        |context, input| SpecifiedValue::parse(context, input, &syntax),
+        value,
+    )
+    .map(|value| value.to_css_string())
+    .map_err(|_| ())
+}
+
+fn register(syntax: &str, initial_value: Option<&str>) -> Result<(), PropertyRegistrationError> {
+    let initial_value = initial_value.map(|initial_value| {
+        let mut input = ParserInput::new(initial_value);
+        VariableValue::parse(&mut Parser::new(&mut input)).unwrap()
+    });
+    let device = Device::new(
+        MediaType::screen(),
+        Size2D::new(800., 600.),
+        Scale::new(1.0),
+    );
+    PropertyRegistration::new(
+        "foo".into(),
+        syntax.parse().unwrap(),
+        false,
+        initial_value,
+        ServoUrl::parse("http://localhost").unwrap(),
+        &device,
+        QuirksMode::NoQuirks,
+    )
+    .map(|_| ())
+}
+
+#[test]
+fn test_syntax() {
+    assert_eq!(parse_syntax("*"), Ok("*".to_owned()));
+    assert_eq!(parse_syntax(" <length> "), Ok("<length>".to_owned()));
+    assert_eq!(
+        parse_syntax("<length>|auto"),
+        Ok("<length> | auto".to_owned())
+    );
+    assert_eq!(
+        parse_syntax("<color># | <angle>+"),
+        Ok("<color># | <angle>+".to_owned())
+    );
+
+    assert!(parse_syntax("").is_err());
+    assert!(parse_syntax("<length").is_err());
+    assert!(parse_syntax("<unknown>").is_err());
+    assert!(parse_syntax("<length> |").is_err());
+    assert!(parse_syntax("<length>++").is_err());
+}
+
+#[test]
+fn test_registered_values() {
+    assert_eq!(parse_value("<length>", "10px"), Ok("10px".to_owned()));
+    assert_eq!(
+        parse_value("<length> | auto", "auto"),
+        Ok("auto".to_owned())
+    );
+    assert_eq!(parse_value("<angle>", "90deg"), Ok("90deg".to_owned()));
+    assert_eq!(
+        parse_value("<color>#", "red, blue"),
+        Ok("red, blue".to_owned())
+    );
+    assert_eq!(
+        parse_value("<length>+", "1px 2px"),
+        Ok("1px 2px".to_owned())
+    );
+
+    assert!(parse_value("<length>", "red").is_err());
+    assert!(parse_value("<number>", "10px").is_err());
+    assert!(parse_value("<length>", "1px 2px").is_err());
+}
+
+#[test]
+fn test_registrations() {
+    assert_eq!(register("*", None), Ok(()));
+    assert_eq!(register("<length>", Some("10px")), Ok(()));
+    assert_eq!(
+        register("<length>", None),
+        Err(PropertyRegistrationError::NoInitialValue)
+    );
+    assert_eq!(
+        register("<length>", Some("red")),
+        Err(PropertyRegistrationError::InvalidInitialValue)
+    );
+    assert_eq!(
+        register("<length>", Some("1em")),
+        Err(PropertyRegistrationError::InitialValueNotComputationallyIndependent)
+    );
+    assert_eq!(
+        register("*", Some("var(--bar)")),
+        Err(PropertyRegistrationError::InitialValueNotComputationallyIndependent)
+    );
+}
@@ -10,8 +10,11 @@ use std::cell::RefCell;
 use style::context::QuirksMode;
 use style::error_reporting::{ContextualParseError, ParseErrorReporter};
 use style::media_queries::MediaList;
-use style::shared_lock::SharedRwLock;
-use style::stylesheets::{AllowImportRules, Origin, Stylesheet};
+use style::parser::ParserContext;
+use style::shared_lock::{Locked, SharedRwLock, ToCssWithGuard};
+use style::stylesheets::import_rule::{ImportLayer, ImportSheet};
+use style::stylesheets::{AllowImportRules, ImportRule, Origin, Stylesheet, StylesheetLoader};
+use style::values::CssUrl;
 
 #[derive(Debug)]
 struct CSSError {
@@ -237,3 +240,89 @@ fn test_source_url() {
         assert_eq!(*url_opt, test.1);
     }
 }
+
+/// A loader that imports empty stylesheets.
+struct EmptyStylesheetLoader;
+
+impl StylesheetLoader for EmptyStylesheetLoader {
+    fn request_stylesheet(
+        &self,
+        url: CssUrl,
+        location: SourceLocation,
+        context: &ParserContext,
+        lock: &SharedRwLock,
+        media: Arc<Locked<MediaList>>,
+        layer: Option<ImportLayer>,
+    ) -> Arc<Locked<ImportRule>> {
+        let stylesheet = Stylesheet::from_str(
+            "",
+            context.url_data.clone(),
+            context.stylesheet_origin,
+            media,
+            lock.clone(),
+            None,
+            None,
+            context.quirks_mode,
+            0,
+            AllowImportRules::Yes,
+        );
+        Arc::new(lock.wrap(ImportRule {
+            url,
+            stylesheet: ImportSheet(Arc::new(stylesheet)),
+            layer,
+            source_location: location,
+        }))
+    }
+}
+
+#[test]
+fn test_layer_rules() {
+    let css = r"
+    @layer base, components;
+    @import url(a.css) layer(base.imports);
+    @import url(b.css) layer screen;
+    @layer components.buttons { div {} }
+    @layer { div {} }
+    @layer theme;
+    @layer default {}
+    @layer revert-layer;
+    @layer base.Inherit;
+    @layer a .b;
+    @layer a, b {}
+    ";
+    let lock = SharedRwLock::new();
+    let media = Arc::new(lock.wrap(MediaList::empty()));
+    let stylesheet = Stylesheet::from_str(
+        css,
+        ServoUrl::parse("about::test").unwrap(),
+        Origin::Author,
+        media,
+        lock,
+        Some(&EmptyStylesheetLoader),
+        None,
+        QuirksMode::NoQuirks,
+        0,
+        AllowImportRules::Yes,
+    );
+
+    let guard = stylesheet.shared_lock.read();
+    let rules: Vec<_> = stylesheet
+        .contents
+        .rules
+        .read_with(&guard)
+        .0
+        .iter()
+        .map(|rule| rule.to_css_string(&guard))
+        .collect();
+    assert_eq!(
+        rules,
+        [
+            "@layer base, components;",
+            "@import url(\"a.css\") layer(base.imports);",
+            "@import url(\"b.css\") layer screen;",
+            "@layer components.buttons {\n  div { }\n}",
+            "@layer {\n  div { }\n}",
+            "@layer theme;",
+        ]
+    );
+}
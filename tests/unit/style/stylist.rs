@@ -8,16 +8,19 @@ use euclid::Size2D;
 use selectors::parser::{AncestorHashes, Selector};
 use servo_arc::Arc;
 use servo_atoms::Atom;
+use servo_url::ServoUrl;
 use style::context::QuirksMode;
-use style::media_queries::{Device, MediaType};
+use style::media_queries::{Device, MediaList, MediaType};
 use style::properties::{longhands, Importance};
 use style::properties::{PropertyDeclaration, PropertyDeclarationBlock};
+use style::rule_tree::{CascadeLevel, ShadowCascadeOrder, StyleSource};
 use style::selector_map::SelectorMap;
 use style::selector_parser::{SelectorImpl, SelectorParser};
-use style::shared_lock::SharedRwLock;
-use style::stylesheets::StyleRule;
-use style::stylist::needs_revalidation_for_testing;
-use style::stylist::{Rule, Stylist};
+use style::shared_lock::{SharedRwLock, StylesheetGuards};
+use style::stylesheets::layer_rule::LayerOrder;
+use style::stylesheets::{AllowImportRules, Origin, StyleRule, Stylesheet};
+use style::stylist::{layer_orders_for_testing, needs_revalidation_for_testing};
+use style::stylist::{ContainerConditionId, LayerId, Rule, Stylist};
 use style::thread_state::{self, ThreadState};
 
 /// Helper method to get some Rules from selector strings.
@@ -52,6 +55,8 @@ fn get_mock_rules(css_selectors: &[&str]) -> (Vec<Vec<Rule>>, SharedRwLock) {
                             AncestorHashes::new(s, QuirksMode::NoQuirks),
                             locked.clone(),
                             i as u32,
+                            LayerId::root(),
+                            ContainerConditionId::none(),
                         )
                     })
                     .collect()
@@ -236,3 +241,118 @@ fn test_stylist_rule_tree_accessors() {
     stylist.rule_tree();
     stylist.rule_tree().root();
 }
+
+fn nth_layer_order(n: usize) -> LayerOrder {
+    let mut order = LayerOrder::first();
+    for _ in 0..n {
+        order.inc();
+    }
+    order
+}
+
+#[test]
+fn test_layer_order() {
+    let css = "
+        @layer base, components;
+        * {}
+        @layer components { * {} }
+        @layer base { * {} }
+        @layer { * {} }
+        @layer base.inner { * {} }
+        @layer components { * {} }
+    ";
+    let lock = SharedRwLock::new();
+    let media = Arc::new(lock.wrap(MediaList::empty()));
+    let stylesheet = Stylesheet::from_str(
+        css,
+        ServoUrl::parse("about::test").unwrap(),
+        Origin::Author,
+        media,
+        lock.clone(),
+        None,
+        None,
+        QuirksMode::NoQuirks,
+        0,
+        AllowImportRules::Yes,
+    );
+    let stylist = mock_stylist();
+    let guard = lock.read();
+
+    // Layers are ordered by their first declaration, sub-layers come before
+    // their parent layer, and unlayered rules come after every layer.
+    let base_inner = nth_layer_order(0);
+    let base = nth_layer_order(1);
+    let components = nth_layer_order(2);
+    let anonymous = nth_layer_order(3);
+    assert_eq!(
+        layer_orders_for_testing(&stylesheet, stylist.device(), &guard),
+        [
+            LayerOrder::root(),
+            components,
+            base,
+            anonymous,
+            base_inner,
+            components
+        ]
+    );
+}
+
+#[test]
+fn test_important_declarations_in_earlier_layers_win() {
+    thread_state::initialize(ThreadState::LAYOUT);
+    let stylist = mock_stylist();
+    let lock = SharedRwLock::new();
+    let important_block = || {
+        StyleSource::from_declarations(Arc::new(lock.wrap(PropertyDeclarationBlock::with_one(
+            PropertyDeclaration::Display(longhands::display::SpecifiedValue::Block),
+            Importance::Important,
+        ))))
+    };
+    let shadow_tree = important_block();
+    let first_layer = important_block();
+    let second_layer = important_block();
+    let unlayered = important_block();
+    let style_attribute = important_block();
+
+    let same_tree = CascadeLevel::same_tree_author_normal();
+    let rules = vec![
+        (
+            shadow_tree.clone(),
+            CascadeLevel::AuthorNormal {
+                shadow_cascade_order: ShadowCascadeOrder::for_outermost_shadow_tree(),
+            },
+            nth_layer_order(0),
+        ),
+        (first_layer.clone(), same_tree, nth_layer_order(0)),
+        (second_layer.clone(), same_tree, nth_layer_order(1)),
+        (unlayered.clone(), same_tree, LayerOrder::root()),
+        (
+            style_attribute.clone(),
+            same_tree,
+            LayerOrder::style_attribute(),
+        ),
+    ];
+    let guard = lock.read();
+    let node = stylist
+        .rule_tree()
+        .insert_ordered_rules_with_important(rules.into_iter(), &StylesheetGuards::same(&guard));
+
+    // From the declarations that win to the ones that lose: the order of the
+    // trees is reversed, then the order of the layers, except for the style
+    // attribute, which still wins over every layer of its tree.
+    let important: Vec<_> = node
+        .self_and_ancestors()
+        .filter(|node| node.cascade_level().is_important())
+        .map(|node| node.style_source().unwrap().clone())
+        .collect();
+    assert_eq!(
+        important,
+        [
+            shadow_tree,
+            style_attribute,
+            first_layer,
+            second_layer,
+            unlayered
+        ]
+    );
+}